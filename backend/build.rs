@@ -3,6 +3,7 @@ use std::path::Path;
 
 fn main() {
     println!("cargo:rerun-if-changed=src/types/");
+    println!("cargo:rerun-if-changed=proto/game.proto");
 
     // Generate OpenAPI spec at build time
     // Note: This is a placeholder - the actual spec is generated at runtime
@@ -14,4 +15,12 @@ fn main() {
     }
 
     println!("Build script executed - OpenAPI spec will be generated at runtime");
+
+    // Rust types for the protobuf protocol, built with a vendored protoc so
+    // no system install is needed
+    if std::env::var_os("PROTOC").is_none() {
+        let protoc = protoc_bin_vendored::protoc_bin_path().expect("no vendored protoc for this platform");
+        std::env::set_var("PROTOC", protoc);
+    }
+    prost_build::compile_protos(&["proto/game.proto"], &["proto"]).expect("failed to compile proto/game.proto");
 }
@@ -0,0 +1,126 @@
+//! Admission control.
+//!
+//! A public server caps how many games and agent environments it hosts, how
+//! many rooms each client opens a minute, how many connections each game
+//! takes and how big a map may be generated. Requests past a limit are
+//! turned away with an `AdmissionError` instead of slowing every game down,
+//! and each decision is counted for the metrics endpoint.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Window the per-client room quota counts over
+const ROOM_QUOTA_WINDOW: Duration = Duration::from_secs(60);
+
+/// Why a room or connection was turned away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdmissionError {
+    /// Every room is in use by a running game
+    RoomsFull { max: usize },
+    /// The map asked for has more territories than the server generates
+    MapTooLarge { territories: usize, max: usize },
+    /// The game has as many connections as it takes
+    GameFull { max: usize },
+    /// Every agent environment is in use
+    GymsFull { max: usize },
+    /// The client opened as many rooms as it may in the last minute
+    RoomQuota { per_minute: usize },
+}
+
+impl fmt::Display for AdmissionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdmissionError::RoomsFull { max } => write!(f, "All {} rooms are in use", max),
+            AdmissionError::MapTooLarge { territories, max } => {
+                write!(f, "Maps can have at most {} territories, not {}", max, territories)
+            }
+            AdmissionError::GameFull { max } => write!(f, "This game already has {} connections", max),
+            AdmissionError::GymsFull { max } => write!(f, "All {} agent environments are in use", max),
+            AdmissionError::RoomQuota { per_minute } => write!(f, "You can open at most {} rooms a minute", per_minute),
+        }
+    }
+}
+
+impl std::error::Error for AdmissionError {}
+
+/// Rooms and connections let in or turned away since the server started
+#[derive(Debug, Default)]
+pub struct AdmissionStats {
+    rooms_admitted: AtomicU64,
+    clients_admitted: AtomicU64,
+    rooms_full: AtomicU64,
+    map_too_large: AtomicU64,
+    game_full: AtomicU64,
+    gyms_full: AtomicU64,
+    room_quota: AtomicU64,
+}
+
+impl AdmissionStats {
+    pub fn room_admitted(&self) {
+        self.rooms_admitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_admitted(&self) {
+        self.clients_admitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a rejection and hand it back
+    pub fn rejected(&self, error: AdmissionError) -> AdmissionError {
+        let counter = match error {
+            AdmissionError::RoomsFull { .. } => &self.rooms_full,
+            AdmissionError::MapTooLarge { .. } => &self.map_too_large,
+            AdmissionError::GameFull { .. } => &self.game_full,
+            AdmissionError::GymsFull { .. } => &self.gyms_full,
+            AdmissionError::RoomQuota { .. } => &self.room_quota,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        error
+    }
+
+    /// Rooms and clients admitted
+    pub fn admitted(&self) -> [(&'static str, u64); 2] {
+        [("room", self.rooms_admitted.load(Ordering::Relaxed)), ("client", self.clients_admitted.load(Ordering::Relaxed))]
+    }
+
+    /// Rejections by reason
+    pub fn rejections(&self) -> [(&'static str, u64); 5] {
+        [
+            ("rooms_full", self.rooms_full.load(Ordering::Relaxed)),
+            ("map_too_large", self.map_too_large.load(Ordering::Relaxed)),
+            ("game_full", self.game_full.load(Ordering::Relaxed)),
+            ("gyms_full", self.gyms_full.load(Ordering::Relaxed)),
+            ("room_quota", self.room_quota.load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+/// When each client opened its rooms of the last minute, by address
+#[derive(Debug, Default)]
+pub struct RoomQuota {
+    opened: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+}
+
+impl RoomQuota {
+    /// Count a room request from `client`; false if it already made
+    /// `per_minute` in the last minute
+    pub fn take(&self, client: IpAddr, per_minute: usize, now: Instant) -> bool {
+        let mut opened = self.opened.lock().unwrap();
+        // Forget requests out of the window, and clients with none left
+        opened.retain(|_, times| {
+            while times.front().is_some_and(|&t| now.saturating_duration_since(t) >= ROOM_QUOTA_WINDOW) {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = opened.entry(client).or_default();
+        if times.len() >= per_minute {
+            return false;
+        }
+        times.push_back(now);
+        true
+    }
+}
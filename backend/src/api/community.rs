@@ -0,0 +1,113 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::community::{chat_text, ChatMessage, Profile};
+use crate::lobby::Lobby;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatQuery {
+    /// Id of the last message the client has; everything if omitted
+    #[serde(default)]
+    after: u64,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ChatPost {
+    /// Session token of a seat in any room; the message is posted under that player's name
+    pub token: String,
+    pub text: String,
+}
+/// A player's totals and recent matches
+#[utoipa::path(
+    get,
+    path = "/profiles/{player}",
+    tag = "strategy-game",
+    params(("player" = String, Path, description = "Ladder identity of the player, as on the leaderboard")),
+    responses(
+        (status = 200, description = "The player's profile", body = Profile),
+        (status = 404, description = "Profiles disabled or no games played by this player")
+    )
+)]
+pub async fn profile_handler(State(lobby): State<Arc<Lobby>>, Path(player): Path<Uuid>) -> Result<Json<Profile>, StatusCode> {
+    let game_session = lobby.main_room().await;
+    let community = game_session.community.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    community.profile(player).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Lobby chat messages, oldest first
+#[utoipa::path(
+    get,
+    path = "/lobby/chat",
+    tag = "strategy-game",
+    params(("after" = Option<u64>, Query, description = "Only messages with a higher id")),
+    responses(
+        (status = 200, description = "Recent messages", body = [ChatMessage]),
+        (status = 404, description = "Lobby chat disabled"),
+        (status = 503, description = "Storage unavailable")
+    )
+)]
+pub async fn lobby_chat_handler(State(lobby): State<Arc<Lobby>>, Query(query): Query<ChatQuery>) -> Response {
+    let Some(storage) = lobby.main_room().await.storage.clone() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match storage.chat_since(query.after).await {
+        Ok(messages) => Json(messages).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to read the lobby chat: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+/// Post to the lobby chat as a seated player
+#[utoipa::path(
+    post,
+    path = "/lobby/chat",
+    tag = "strategy-game",
+    request_body = ChatPost,
+    responses(
+        (status = 201, description = "The posted message", body = ChatMessage),
+        (status = 400, description = "Empty or overlong message"),
+        (status = 401, description = "No seat was issued for this token"),
+        (status = 404, description = "Lobby chat disabled"),
+        (status = 429, description = "Posting too fast"),
+        (status = 503, description = "Storage unavailable")
+    )
+)]
+pub async fn post_lobby_chat_handler(State(lobby): State<Arc<Lobby>>, Json(post): Json<ChatPost>) -> Response {
+    let Some(storage) = lobby.main_room().await.storage.clone() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some((session, player_id)) = lobby.find_seat(&post.token).await else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let text = match chat_text(&post.text) {
+        Ok(text) => text,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    if !lobby.may_chat(player_id.into(), Instant::now()) {
+        return (StatusCode::TOO_MANY_REQUESTS, "You are posting too fast").into_response();
+    }
+
+    let name = match session.engine.read().await.get_player(player_id) {
+        Ok(player) => player.name.clone(),
+        Err(_) => return StatusCode::UNAUTHORIZED.into_response(),
+    };
+    match storage.post_chat(&name, text, Utc::now()).await {
+        Ok(message) => (StatusCode::CREATED, Json(message)).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to post to the lobby chat: {}", e);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use axum::response::Html;
+
+const DASHBOARD: &str = include_str!("../../static/dashboard.html");
+
+/// Status page for operators running the server without the frontend: live
+/// games, connections, tick health and recent errors, drawn from the admin
+/// channel; open it with `?token=` set to the admin token
+#[utoipa::path(
+    get,
+    path = "/dashboard",
+    tag = "strategy-game",
+    responses((status = 200, description = "The dashboard page", body = String, content_type = "text/html"))
+)]
+pub async fn dashboard_handler() -> Html<&'static str> {
+    Html(DASHBOARD)
+}
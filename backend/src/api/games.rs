@@ -0,0 +1,116 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::game::custom_map::MapDefinition;
+use crate::lobby::Lobby;
+use crate::types::*;
+use crate::websocket::AdminQuery;
+
+/// Games hosted by every server instance sharing the backplane
+#[utoipa::path(
+    get,
+    path = "/games",
+    tag = "strategy-game",
+    responses(
+        (status = 200, description = "Live games across instances", body = [GameListing]),
+        (status = 503, description = "Backplane unavailable")
+    )
+)]
+pub async fn list_games_handler(
+    State(lobby): State<Arc<Lobby>>,
+) -> Result<Json<Vec<GameListing>>, StatusCode> {
+    lobby.main_room().await.backplane.list_games().await.map(Json).map_err(|e| {
+        tracing::warn!("Failed to list games: {}", e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })
+}
+
+/// End-of-game summary of a finished game hosted here, or archived by this server's storage
+#[utoipa::path(
+    get,
+    path = "/games/{game_id}/summary",
+    tag = "strategy-game",
+    params(("game_id" = String, Path, description = "Game id")),
+    responses(
+        (status = 200, description = "Summary of the finished game", body = GameSummary),
+        (status = 404, description = "Unknown game, or not finished yet"),
+        (status = 503, description = "Storage unavailable")
+    )
+)]
+pub async fn game_summary_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Path(game_id): Path<Uuid>,
+) -> Result<Json<GameSummary>, StatusCode> {
+    if let Some(game_session) = lobby.room(game_id).await {
+        return game_session.summary().map(Json).ok_or(StatusCode::NOT_FOUND);
+    }
+    let storage = lobby.main_room().await.storage.clone().ok_or(StatusCode::NOT_FOUND)?;
+    match storage.archived_game(game_id).await {
+        Ok(archive) => archive.and_then(|archive| archive.summary).map(Json).ok_or(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::warn!(game_id = %game_id, "Failed to read archived game: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+/// Map of a game hosted here, in the format `POST /maps` imports: the map the
+/// game started on if it is being recorded, its current map otherwise
+#[utoipa::path(
+    get,
+    path = "/games/{game_id}/map",
+    tag = "strategy-game",
+    params(("game_id" = String, Path, description = "Game id")),
+    responses(
+        (status = 200, description = "Territories with their geometry, terrain, neighbors and buildings", body = MapDefinition),
+        (status = 404, description = "Unknown game")
+    )
+)]
+pub async fn game_map_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Path(game_id): Path<Uuid>,
+) -> Result<Json<MapDefinition>, StatusCode> {
+    let game_session = lobby.room(game_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let engine = game_session.engine.read().await;
+    let state = engine.replay.as_ref().map_or(&engine.state, |replay| &replay.initial);
+    Ok(Json(MapDefinition::export(&format!("Game {}", game_id), state)))
+}
+
+/// Recent decisions of an AI player with the options it weighed and their
+/// scores; needs the admin token, and the server running with `GAME_DEBUG`
+#[utoipa::path(
+    get,
+    path = "/games/{game_id}/ai/{player_id}/last_decisions",
+    tag = "strategy-game",
+    params(
+        ("game_id" = String, Path, description = "Game id"),
+        ("player_id" = String, Path, description = "AI player id"),
+        ("token" = String, Query, description = "Admin token")
+    ),
+    responses(
+        (status = 200, description = "Up to 20 decisions, oldest first", body = [AiDecision]),
+        (status = 403, description = "Missing or wrong admin token"),
+        (status = 404, description = "Unknown game or AI player, or debug mode is off")
+    )
+)]
+pub async fn ai_decisions_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Path((game_id, player_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<AdminQuery>,
+) -> Result<Json<Vec<AiDecision>>, StatusCode> {
+    if !lobby.main_room().await.admin.authorize(query.token.as_deref()) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let game_session = lobby.room(game_id).await.ok_or(StatusCode::NOT_FOUND)?;
+    let engine = game_session.engine.read().await;
+    let is_ai = engine.get_player(player_id.into()).is_ok_and(|p| p.is_ai);
+    match &engine.ai_log {
+        Some(log) if is_ai => Ok(Json(log.last(player_id))),
+        _ => Err(StatusCode::NOT_FOUND),
+    }
+}
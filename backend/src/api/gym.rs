@@ -0,0 +1,133 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admission::AdmissionError;
+use crate::game::gym::{GymConfig, Observation, StepRequest, StepResult};
+use crate::lobby::Lobby;
+use crate::websocket::AdminQuery;
+
+async fn authorized(lobby: &Lobby, query: &AdminQuery) -> bool {
+    lobby.main_room().await.admin.authorize(query.token.as_deref())
+}
+
+/// A new environment and where the agent starts
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GymOpened {
+    #[schema(value_type = String, format = "uuid")]
+    pub env_id: Uuid,
+    pub observation: Observation,
+}
+
+/// Deal a headless game for an agent, which advances it with `/gym/{env_id}/step`;
+/// needs the admin token
+#[utoipa::path(
+    post,
+    path = "/gym",
+    tag = "strategy-game",
+    request_body = GymConfig,
+    params(("token" = String, Query, description = "Admin token")),
+    responses(
+        (status = 201, description = "The new environment", body = GymOpened),
+        (status = 400, description = "Invalid map size, or a map bigger than the server allows"),
+        (status = 403, description = "Missing or wrong admin token"),
+        (status = 503, description = "The server hosts as many environments as it may")
+    )
+)]
+pub async fn open_gym_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Query(query): Query<AdminQuery>,
+    Json(config): Json<GymConfig>,
+) -> Response {
+    if !authorized(&lobby, &query).await {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    match lobby.open_gym(&config).await {
+        Ok((env_id, observation)) => (StatusCode::CREATED, Json(GymOpened { env_id, observation })).into_response(),
+        Err(e) => match e.downcast_ref::<AdmissionError>() {
+            Some(AdmissionError::GymsFull { .. }) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+            _ => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        },
+    }
+}
+
+/// Apply the agent's actions and play the environment forward; needs the admin token
+#[utoipa::path(
+    post,
+    path = "/gym/{env_id}/step",
+    tag = "strategy-game",
+    request_body = StepRequest,
+    params(
+        ("env_id" = String, Path, description = "Environment id"),
+        ("token" = String, Query, description = "Admin token")
+    ),
+    responses(
+        (status = 200, description = "Observation, reward and whether the game is over", body = StepResult),
+        (status = 400, description = "Too many ticks in one step"),
+        (status = 403, description = "Missing or wrong admin token"),
+        (status = 404, description = "No such environment, or it sat idle too long")
+    )
+)]
+pub async fn gym_step_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Path(env_id): Path<Uuid>,
+    Query(query): Query<AdminQuery>,
+    Json(request): Json<StepRequest>,
+) -> Response {
+    if !authorized(&lobby, &query).await {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let Some(env) = lobby.gym(env_id).await else {
+        return (StatusCode::NOT_FOUND, "Unknown environment").into_response();
+    };
+    // Steps are CPU bound; keep them off the async workers
+    let stepped = tokio::task::spawn_blocking(move || {
+        env.lock().unwrap().step(&request)
+    })
+    .await;
+    match stepped {
+        Ok(Ok(result)) => Json(result).into_response(),
+        Ok(Err(e)) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) => {
+            tracing::error!("Gym step failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Drop an environment the agent is done with; needs the admin token
+#[utoipa::path(
+    delete,
+    path = "/gym/{env_id}",
+    tag = "strategy-game",
+    params(
+        ("env_id" = String, Path, description = "Environment id"),
+        ("token" = String, Query, description = "Admin token")
+    ),
+    responses(
+        (status = 204, description = "Dropped"),
+        (status = 403, description = "Missing or wrong admin token"),
+        (status = 404, description = "No such environment")
+    )
+)]
+pub async fn close_gym_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Path(env_id): Path<Uuid>,
+    Query(query): Query<AdminQuery>,
+) -> StatusCode {
+    if !authorized(&lobby, &query).await {
+        return StatusCode::FORBIDDEN;
+    }
+    if lobby.close_gym(env_id).await {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
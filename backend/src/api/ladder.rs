@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::ladder::Leaderboard;
+use crate::lobby::Lobby;
+use crate::storage::{MatchQuery, MatchResult};
+
+#[derive(Debug, Deserialize)]
+pub struct LadderQuery {
+    /// Season number; the current season if omitted
+    season: Option<u32>,
+}
+
+/// Ranked ladder standings for a season
+#[utoipa::path(
+    get,
+    path = "/ladder",
+    tag = "strategy-game",
+    params(("season" = Option<u32>, Query, description = "Season number, defaults to the current season")),
+    responses(
+        (status = 200, description = "Placed players, best first", body = Leaderboard),
+        (status = 404, description = "Ladder disabled or unknown season")
+    )
+)]
+pub async fn ladder_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Query(query): Query<LadderQuery>,
+) -> Result<Json<Leaderboard>, StatusCode> {
+    let game_session = lobby.main_room().await;
+    let ladder = game_session.ladder.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    ladder.leaderboard(query.season, Utc::now()).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MatchesQuery {
+    /// Only games this player took part in
+    player: Option<String>,
+    limit: Option<usize>,
+}
+
+/// Matches served per request at most
+const MAX_MATCHES: usize = 100;
+
+/// Finished games recorded in the match database, most recent first
+#[utoipa::path(
+    get,
+    path = "/matches",
+    tag = "strategy-game",
+    params(
+        ("player" = Option<String>, Query, description = "Only games this player took part in"),
+        ("limit" = Option<usize>, Query, description = "Number of matches, 20 by default and 100 at most")
+    ),
+    responses(
+        (status = 200, description = "Finished games", body = [MatchResult]),
+        (status = 404, description = "Match history disabled"),
+        (status = 500, description = "The database couldn't be queried")
+    )
+)]
+pub async fn list_matches_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Query(query): Query<MatchesQuery>,
+) -> Result<Json<Vec<MatchResult>>, StatusCode> {
+    let game_session = lobby.main_room().await;
+    let storage = game_session.storage.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let query = MatchQuery { player: query.player, limit: query.limit.unwrap_or(20).min(MAX_MATCHES) };
+    storage.matches(&query).await.map(Json).map_err(|e| {
+        tracing::warn!("Failed to list matches: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
@@ -0,0 +1,104 @@
+use axum::{extract::{Query, State}, http::StatusCode, response::{IntoResponse, Response}, Json};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::admission::AdmissionError;
+use crate::game::custom_map::MapDefinition;
+use crate::lobby::{ImportedMap, Lobby};
+use crate::game::fairness::{analyze, FairnessReport, DEFAULT_FAIRNESS_HOPS};
+use crate::game::MapGenerator;
+use crate::types::*;
+
+#[derive(Debug, Deserialize)]
+pub struct MapPreviewQuery {
+    #[serde(default = "default_territories")]
+    territories: usize,
+    #[serde(default = "default_players")]
+    players: usize,
+    /// Random if omitted
+    seed: Option<u64>,
+    #[serde(default)]
+    style: MapStyle,
+    #[serde(default)]
+    grid: MapGrid,
+    /// Radius of the terrain value measure
+    hops: Option<u32>,
+    /// Reject the map if its fairness score is lower
+    min_fairness: Option<f32>,
+}
+
+fn default_territories() -> usize {
+    75
+}
+
+fn default_players() -> usize {
+    9
+}
+
+/// A generated map and how fair its starts are
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MapPreview {
+    /// Seed that regenerates this map
+    pub seed: u64,
+    pub state: GameState,
+    pub fairness: FairnessReport,
+}
+
+/// Generate a map without starting a game and report its fairness
+#[utoipa::path(
+    get,
+    path = "/maps/preview",
+    tag = "strategy-game",
+    params(
+        ("territories" = Option<usize>, Query, description = "Territory count, default 75"),
+        ("players" = Option<usize>, Query, description = "Player count, default 9"),
+        ("seed" = Option<u64>, Query, description = "Map seed, random if omitted"),
+        ("style" = Option<MapStyle>, Query, description = "`scattered` (default), `continents`, `islands` or `pangaea`"),
+        ("grid" = Option<MapGrid>, Query, description = "`voronoi` (default) or `hex`"),
+        ("hops" = Option<u32>, Query, description = "Radius of the terrain value measure, default 2"),
+        ("min_fairness" = Option<f32>, Query, description = "Reject maps scoring below this, from 0 to 1")
+    ),
+    responses(
+        (status = 200, description = "Generated map with its fairness report", body = MapPreview),
+        (status = 400, description = "Invalid map size"),
+        (status = 422, description = "Map is less fair than `min_fairness`; the preview is still returned", body = MapPreview)
+    )
+)]
+pub async fn map_preview_handler(State(lobby): State<Arc<Lobby>>, Query(query): Query<MapPreviewQuery>) -> Response {
+    if query.players < 2 || query.territories < query.players {
+        return (StatusCode::BAD_REQUEST, "Need at least two players and a territory for each").into_response();
+    }
+    if query.territories > lobby.max_territories {
+        let error = lobby.admission.rejected(AdmissionError::MapTooLarge { territories: query.territories, max: lobby.max_territories });
+        return (StatusCode::BAD_REQUEST, error.to_string()).into_response();
+    }
+
+    let seed = query.seed.unwrap_or_else(rand::random);
+    let state = MapGenerator::new(query.territories, query.players).with_style(query.style).with_grid(query.grid).generate_seeded(seed);
+    let fairness = analyze(&state, query.hops.unwrap_or(DEFAULT_FAIRNESS_HOPS));
+    let status = if query.min_fairness.is_some_and(|min| fairness.score < min) {
+        StatusCode::UNPROCESSABLE_ENTITY
+    } else {
+        StatusCode::OK
+    };
+    (status, Json(MapPreview { seed, state, fairness })).into_response()
+}
+
+/// Import a hand-made map; create a room on it by passing its `map_id` as `map`
+#[utoipa::path(
+    post,
+    path = "/maps",
+    tag = "strategy-game",
+    request_body = MapDefinition,
+    responses(
+        (status = 201, description = "The map passed validation", body = ImportedMap),
+        (status = 400, description = "The map is invalid, e.g. a neighbor that doesn't list the territory back or a part that can't be reached")
+    )
+)]
+pub async fn import_map_handler(State(lobby): State<Arc<Lobby>>, Json(map): Json<MapDefinition>) -> Response {
+    match lobby.import_map(map).await {
+        Ok(imported) => (StatusCode::CREATED, Json(imported)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,118 @@
+use axum::{extract::State, http::header, response::IntoResponse};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::lobby::Lobby;
+use crate::types::*;
+
+/// Prometheus-style metrics for the main game, and the number of rooms
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "strategy-game",
+    responses((status = 200, description = "Metrics in Prometheus text format", body = String))
+)]
+pub async fn metrics_handler(State(lobby): State<Arc<Lobby>>) -> impl IntoResponse {
+    let rooms = lobby.sessions().await;
+    let running = rooms.iter().filter(|s| !s.is_stopped()).count();
+    let game_session = lobby.main_room().await;
+    let stats = game_session.perf_stats().await;
+    let tick = game_session.engine.read().await.state.tick;
+    let (connections, players) = {
+        let clients = game_session.clients.read().await;
+        let players: HashSet<_> = clients.iter().map(|c| c.player_id).collect();
+        (clients.iter().map(|c| c.stats()).collect::<Vec<_>>(), players.len())
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# TYPE game_tick counter");
+    let _ = writeln!(out, "game_tick {}", tick);
+    let _ = writeln!(out, "# TYPE game_connected_clients gauge");
+    let _ = writeln!(out, "game_connected_clients {}", connections.len());
+    let _ = writeln!(out, "# TYPE game_connected_players gauge");
+    let _ = writeln!(out, "game_connected_players {}", players);
+    let _ = writeln!(out, "# TYPE game_rooms gauge");
+    let _ = writeln!(out, "game_rooms {}", rooms.len());
+    let _ = writeln!(out, "# TYPE game_rooms_running gauge");
+    let _ = writeln!(out, "game_rooms_running {}", running);
+    write_admission_metrics(&mut out, &lobby);
+    write_section_metrics(&mut out, &stats);
+    write_connection_metrics(&mut out, &connections);
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out)
+}
+
+fn write_admission_metrics(out: &mut String, lobby: &Lobby) {
+    let _ = writeln!(out, "# TYPE game_capacity_limit gauge");
+    let limits = [
+        ("rooms", lobby.max_rooms),
+        ("rooms_per_client_per_minute", lobby.rooms_per_client_per_minute),
+        ("clients_per_game", lobby.max_clients_per_game),
+        ("territories", lobby.max_territories),
+        ("gyms", lobby.max_gyms),
+    ];
+    for (limit, value) in limits {
+        let _ = writeln!(out, "game_capacity_limit{{limit=\"{}\"}} {}", limit, value);
+    }
+    let _ = writeln!(out, "# TYPE game_admitted_total counter");
+    for (kind, count) in lobby.admission.admitted() {
+        let _ = writeln!(out, "game_admitted_total{{kind=\"{}\"}} {}", kind, count);
+    }
+    let _ = writeln!(out, "# TYPE game_rejected_total counter");
+    for (reason, count) in lobby.admission.rejections() {
+        let _ = writeln!(out, "game_rejected_total{{reason=\"{}\"}} {}", reason, count);
+    }
+}
+
+/// Reads one counter of a connection
+type ConnectionField = fn(&ConnectionStats) -> u64;
+
+fn write_connection_metrics(out: &mut String, connections: &[ConnectionStats]) {
+    let series: [(&str, &str, ConnectionField); 4] = [
+        ("game_client_queue_depth", "gauge", |c| c.queue_depth),
+        ("game_client_messages_sent_total", "counter", |c| c.messages_sent),
+        ("game_client_dropped_frames_total", "counter", |c| c.dropped_frames),
+        ("game_client_serialization_failures_total", "counter", |c| c.serialization_failures),
+    ];
+
+    for (metric, kind, field) in series {
+        let _ = writeln!(out, "# TYPE {} {}", metric, kind);
+        for c in connections {
+            let _ = writeln!(
+                out,
+                "{}{{connection=\"{}\",player=\"{}\"}} {}",
+                metric,
+                c.connection_id,
+                c.player_id,
+                field(c)
+            );
+        }
+    }
+}
+
+fn write_section_metrics(out: &mut String, stats: &PerfStats) {
+    let sections = [
+        ("tick", &stats.tick),
+        ("ai", &stats.ai),
+        ("combat", &stats.combat),
+        ("serialization", &stats.serialization),
+    ];
+
+    let _ = writeln!(out, "# TYPE game_section_samples_total counter");
+    for (name, timing) in sections {
+        let _ = writeln!(out, "game_section_samples_total{{section=\"{}\"}} {}", name, timing.samples);
+    }
+    let _ = writeln!(out, "# TYPE game_section_duration_microseconds_total counter");
+    for (name, timing) in sections {
+        let _ = writeln!(out, "game_section_duration_microseconds_total{{section=\"{}\"}} {}", name, timing.total_us);
+    }
+    let _ = writeln!(out, "# TYPE game_section_duration_microseconds_last gauge");
+    for (name, timing) in sections {
+        let _ = writeln!(out, "game_section_duration_microseconds_last{{section=\"{}\"}} {}", name, timing.last_us);
+    }
+    let _ = writeln!(out, "# TYPE game_section_duration_microseconds_max gauge");
+    for (name, timing) in sections {
+        let _ = writeln!(out, "game_section_duration_microseconds_max{{section=\"{}\"}} {}", name, timing.max_us);
+    }
+}
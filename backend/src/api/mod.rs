@@ -0,0 +1,23 @@
+pub mod community;
+pub mod dashboard;
+pub mod games;
+pub mod gym;
+pub mod ladder;
+pub mod maps;
+pub mod metrics;
+pub mod profiling;
+pub mod quickplay;
+pub mod rooms;
+pub mod simulations;
+
+pub use community::*;
+pub use dashboard::*;
+pub use games::*;
+pub use gym::*;
+pub use ladder::*;
+pub use maps::*;
+pub use metrics::*;
+pub use profiling::*;
+pub use quickplay::*;
+pub use rooms::*;
+pub use simulations::*;
@@ -0,0 +1,85 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::lobby::Lobby;
+
+/// Samples taken per second while profiling
+const SAMPLE_FREQUENCY: i32 = 99;
+/// Longest profile a request may ask for, in seconds
+const MAX_PROFILE_SECONDS: u64 = 60;
+
+#[derive(Debug, Deserialize)]
+pub struct ProfileQuery {
+    pub token: Option<String>,
+    /// How long to sample for; 10 seconds by default
+    pub seconds: Option<u64>,
+}
+
+/// CPU profile of the whole server, every game and connection included, as a
+/// flamegraph; needs the admin token
+#[utoipa::path(
+    get,
+    path = "/debug/pprof/profile",
+    tag = "strategy-game",
+    params(
+        ("token" = String, Query, description = "Admin token"),
+        ("seconds" = Option<u64>, Query, description = "Seconds to sample for, 1 to 60 (default 10)")
+    ),
+    responses(
+        (status = 200, description = "Flamegraph of the sampled stacks", body = String, content_type = "image/svg+xml"),
+        (status = 204, description = "The server was idle; nothing was sampled"),
+        (status = 400, description = "Sampling time out of range"),
+        (status = 403, description = "Missing or wrong admin token"),
+        (status = 409, description = "Another profile is being taken")
+    )
+)]
+pub async fn cpu_profile_handler(State(lobby): State<Arc<Lobby>>, Query(query): Query<ProfileQuery>) -> Response {
+    if !lobby.main_room().await.admin.authorize(query.token.as_deref()) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    let seconds = query.seconds.unwrap_or(10);
+    if !(1..=MAX_PROFILE_SECONDS).contains(&seconds) {
+        return (StatusCode::BAD_REQUEST, format!("Profiles last 1 to {} seconds", MAX_PROFILE_SECONDS)).into_response();
+    }
+
+    // The profiler samples every thread while this one sleeps
+    let profile = tokio::task::spawn_blocking(move || take_profile(Duration::from_secs(seconds))).await;
+    match profile {
+        Ok(Ok(svg)) if svg.is_empty() => StatusCode::NO_CONTENT.into_response(),
+        Ok(Ok(svg)) => ([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response(),
+        Ok(Err(pprof::Error::Running)) => StatusCode::CONFLICT.into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Profiling failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => {
+            tracing::error!("Profiling failed: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+/// Sample the server for `duration` and draw the stacks as a flamegraph;
+/// empty if nothing ran
+fn take_profile(duration: Duration) -> pprof::Result<Vec<u8>> {
+    // Unwinding through these from a signal handler can deadlock
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(SAMPLE_FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()?;
+    std::thread::sleep(duration);
+
+    let mut svg = Vec::new();
+    let report = guard.report().build()?;
+    if !report.data.is_empty() {
+        report.flamegraph(&mut svg)?;
+    }
+    tracing::info!(seconds = duration.as_secs(), "CPU profile taken");
+    Ok(svg)
+}
@@ -0,0 +1,65 @@
+use axum::{extract::{ConnectInfo, State}, http::{header, HeaderMap, StatusCode}, response::{IntoResponse, Response}, Json};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::lobby::Lobby;
+
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct QuickplayRequest {
+    /// Token from an earlier quick-play game, to get back into it while it runs
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Where to connect for a quick-play game
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuickplayResponse {
+    #[schema(value_type = String, format = "uuid")]
+    pub room_id: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub player_id: Uuid,
+    /// Session token reserving the player; only connections presenting it play as them
+    pub token: String,
+    /// WebSocket URL to connect to, token included
+    pub ws_url: String,
+}
+
+/// Start a solo game against AI with default settings in one call
+#[utoipa::path(
+    post,
+    path = "/quickplay",
+    tag = "strategy-game",
+    request_body(content = Option<QuickplayRequest>, description = "Optional token to resume an earlier game"),
+    responses(
+        (status = 200, description = "The game to connect to", body = QuickplayResponse),
+        (status = 429, description = "The client opened as many rooms as it may this minute"),
+        (status = 503, description = "The server hosts as many rooms as it may")
+    )
+)]
+pub async fn quickplay_handler(
+    State(lobby): State<Arc<Lobby>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    request: Option<Json<QuickplayRequest>>,
+) -> Response {
+    if let Err(e) = lobby.admit_room(client.ip()) {
+        return (StatusCode::TOO_MANY_REQUESTS, e.to_string()).into_response();
+    }
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let seat = match lobby.quickplay(request.token.as_deref()).await {
+        Ok(seat) => seat,
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    };
+
+    let host = headers.get(header::HOST).and_then(|h| h.to_str().ok()).unwrap_or("localhost:3000");
+    Json(QuickplayResponse {
+        room_id: seat.room_id,
+        player_id: seat.player_id,
+        ws_url: format!("ws://{}/ws?token={}", host, seat.token),
+        token: seat.token,
+    })
+    .into_response()
+}
@@ -0,0 +1,56 @@
+use axum::{extract::{ConnectInfo, State}, http::StatusCode, response::{IntoResponse, Response}, Json};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::admission::AdmissionError;
+use crate::lobby::{CreateRoomRequest, Lobby, RoomInfo};
+
+/// Rooms hosted by this server, oldest first
+#[utoipa::path(
+    get,
+    path = "/rooms",
+    tag = "strategy-game",
+    responses((status = 200, description = "Hosted rooms", body = [RoomInfo]))
+)]
+pub async fn list_rooms_handler(State(lobby): State<Arc<Lobby>>) -> Json<Vec<RoomInfo>> {
+    Json(lobby.list().await)
+}
+
+/// Open a room with its own game; join it at `/ws?room=<room_id>`
+#[utoipa::path(
+    post,
+    path = "/rooms",
+    tag = "strategy-game",
+    request_body = CreateRoomRequest,
+    responses(
+        (status = 201, description = "The new room, already running", body = RoomInfo),
+        (status = 400, description = "Invalid map size, or a map bigger than the server allows"),
+        (status = 404, description = "No imported map has the given `map` id"),
+        (status = 429, description = "The client opened as many rooms as it may this minute"),
+        (status = 503, description = "The server hosts as many rooms as it may")
+    )
+)]
+pub async fn create_room_handler(
+    State(lobby): State<Arc<Lobby>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(request): Json<CreateRoomRequest>,
+) -> Response {
+    if let Err(e) = lobby.admit_room(client.ip()) {
+        return (StatusCode::TOO_MANY_REQUESTS, e.to_string()).into_response();
+    }
+    if let Err(e) = request.validate() {
+        return (StatusCode::BAD_REQUEST, e.to_string()).into_response();
+    }
+    if let Some(map_id) = request.map {
+        if lobby.custom_map(map_id).await.is_none() {
+            return (StatusCode::NOT_FOUND, "Unknown map").into_response();
+        }
+    }
+    match lobby.create(request).await {
+        Ok(room) => (StatusCode::CREATED, Json(room)).into_response(),
+        Err(e) => match e.downcast_ref::<AdmissionError>() {
+            Some(AdmissionError::MapTooLarge { .. }) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+            _ => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+        },
+    }
+}
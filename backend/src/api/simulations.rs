@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::game::simulation::{run_sweep, summaries_to_csv, SimulationPanicked, SweepRequest, SweepSummary};
+use crate::lobby::Lobby;
+
+#[derive(Debug, Deserialize)]
+pub struct SweepQuery {
+    pub token: Option<String>,
+    /// `json` (default) or `csv`
+    format: Option<String>,
+}
+
+/// Run a balance sweep of headless AI-only games and summarize the results;
+/// needs the admin token
+#[utoipa::path(
+    post,
+    path = "/simulations",
+    tag = "strategy-game",
+    request_body = SweepRequest,
+    params(
+        ("token" = String, Query, description = "Admin token"),
+        ("format" = Option<String>, Query, description = "`json` (default) or `csv`")
+    ),
+    responses(
+        (status = 200, description = "One summary per parameter value", body = [SweepSummary]),
+        (status = 400, description = "Invalid sweep"),
+        (status = 403, description = "Missing or wrong admin token")
+    )
+)]
+pub async fn run_simulations_handler(
+    State(lobby): State<Arc<Lobby>>,
+    Query(query): Query<SweepQuery>,
+    Json(request): Json<SweepRequest>,
+) -> Response {
+    if !lobby.main_room().await.admin.authorize(query.token.as_deref()) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    // Simulations are CPU bound; keep them off the async workers
+    let summaries = match tokio::task::spawn_blocking(move || run_sweep(&request)).await {
+        Ok(Ok(summaries)) => summaries,
+        Ok(Err(e)) if !e.is::<SimulationPanicked>() => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("Simulation sweep failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        Err(e) => {
+            tracing::error!("Simulation sweep failed: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    match query.format.as_deref() {
+        Some("csv") => ([(header::CONTENT_TYPE, "text/csv")], summaries_to_csv(&summaries)).into_response(),
+        _ => Json(summaries).into_response(),
+    }
+}
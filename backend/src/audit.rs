@@ -0,0 +1,165 @@
+//! Append-only audit log of client commands.
+//!
+//! Each received command is written as one JSON line together with the
+//! connection that sent it and whether it was accepted. Files rotate once
+//! they reach a size limit (`audit.log` -> `audit.log.1` -> ...).
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use uuid::Uuid;
+
+use crate::types::*;
+
+const LOG_FILE_NAME: &str = "audit.log";
+
+/// One received command and its outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub game_id: Uuid,
+    pub connection_id: Uuid,
+    pub player_id: Uuid,
+    pub tick: u64,
+    pub command: ClientMessage,
+    pub accepted: bool,
+    /// Why the command was rejected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// Handle to the background audit writer
+#[derive(Clone)]
+pub struct AuditLog {
+    tx: mpsc::Sender<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Start writing to `dir/audit.log`, rotating at `max_bytes` and keeping `max_files` old files
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let mut writer = RotatingWriter::open(dir, max_bytes, max_files)?;
+
+        // Disk I/O stays off the async runtime
+        let (tx, rx) = mpsc::channel::<AuditEntry>();
+        thread::spawn(move || {
+            for entry in rx {
+                if let Err(e) = writer.write_entry(&entry) {
+                    tracing::error!("Failed to write audit entry: {}", e);
+                }
+            }
+        });
+
+        Ok(Self { tx })
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        let _ = self.tx.send(entry);
+    }
+}
+
+struct RotatingWriter {
+    dir: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl RotatingWriter {
+    fn open(dir: PathBuf, max_bytes: u64, max_files: usize) -> std::io::Result<Self> {
+        let path = dir.join(LOG_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { dir, file, size, max_bytes, max_files })
+    }
+
+    fn write_entry(&mut self, entry: &AuditEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        if self.size > 0 && self.size + line.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn rotated_path(dir: &Path, index: usize) -> PathBuf {
+        dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        // Shift audit.log.N-1 -> audit.log.N, dropping the oldest
+        for index in (1..self.max_files).rev() {
+            let from = Self::rotated_path(&self.dir, index);
+            if from.exists() {
+                fs::rename(&from, Self::rotated_path(&self.dir, index + 1))?;
+            }
+        }
+
+        let current = self.dir.join(LOG_FILE_NAME);
+        if self.max_files > 0 {
+            fs::rename(&current, Self::rotated_path(&self.dir, 1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&current)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(accepted: bool) -> AuditEntry {
+        AuditEntry {
+            timestamp: Utc::now(),
+            game_id: Uuid::new_v4(),
+            connection_id: Uuid::new_v4(),
+            player_id: Uuid::new_v4(),
+            tick: 42,
+            command: ClientMessage::SetTroopRatio { ratio: 0.5 },
+            accepted,
+            reason: (!accepted).then(|| "Player not found".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_rotation_keeps_bounded_files() {
+        let dir = std::env::temp_dir().join(format!("audit-test-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut writer = RotatingWriter::open(dir.clone(), 1_000, 2).unwrap();
+
+        for i in 0..100 {
+            writer.write_entry(&entry(i % 2 == 0)).unwrap();
+        }
+
+        assert!(dir.join("audit.log").exists());
+        assert!(dir.join("audit.log.1").exists());
+        assert!(dir.join("audit.log.2").exists());
+        assert!(!dir.join("audit.log.3").exists());
+
+        for name in ["audit.log", "audit.log.1", "audit.log.2"] {
+            let contents = fs::read_to_string(dir.join(name)).unwrap();
+            assert!(contents.len() <= 1_000);
+            for line in contents.lines() {
+                let parsed: AuditEntry = serde_json::from_str(line).unwrap();
+                assert_eq!(parsed.tick, 42);
+                assert_eq!(parsed.accepted, parsed.reason.is_none());
+            }
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,329 @@
+//! Cross-instance coordination.
+//!
+//! When several server processes host games behind one address, they share a
+//! Redis backplane (`BACKPLANE_URL=redis://host:6379`): games are claimed by
+//! exactly one instance, every instance can list every game, and state frames
+//! are published on a per-game channel so spectators can watch a game from any
+//! instance. Without a URL the same interface is served in-process.
+//!
+//! Frames are handed to a publisher task, so a slow Redis never holds up a
+//! tick, and only games someone is subscribed to are published at all.
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use futures_util::StreamExt;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex as StdMutex, Weak};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::types::GameListing;
+
+const ASSIGNMENTS_KEY: &str = "strategy-game:assignments";
+const LISTINGS_KEY: &str = "strategy-game:listings";
+/// Listings not refreshed within this window belong to dead instances
+const LISTING_TTL_SECONDS: i64 = 30;
+const LOCAL_CHANNEL_BUFFER: usize = 256;
+/// Frames waiting for the publisher; more are dropped, and spectators get the next one
+const PUBLISH_QUEUE: usize = 64;
+/// How often the games hosted here are checked for spectators on any instance
+const SUBSCRIBER_REFRESH: Duration = Duration::from_secs(1);
+
+fn state_channel(game_id: Uuid) -> String {
+    format!("strategy-game:game:{}", game_id)
+}
+
+/// Shared game registry and state fan-out
+pub enum Backplane {
+    Local(LocalBackplane),
+    Redis(RedisBackplane),
+}
+
+impl Backplane {
+    pub fn local(instance_id: String) -> Self {
+        let (states, _) = broadcast::channel(LOCAL_CHANNEL_BUFFER);
+        Backplane::Local(LocalBackplane {
+            instance_id,
+            assignments: StdMutex::new(HashMap::new()),
+            listings: StdMutex::new(HashMap::new()),
+            states,
+        })
+    }
+
+    pub async fn redis(url: &str, instance_id: String) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = client.get_connection_manager().await?;
+        let watch = Arc::new(RedisWatch::default());
+
+        let (frames, queue) = mpsc::channel(PUBLISH_QUEUE);
+        tokio::spawn(publish_frames(connection.clone(), queue));
+        tokio::spawn(refresh_subscribers(connection.clone(), Arc::downgrade(&watch)));
+
+        Ok(Backplane::Redis(RedisBackplane { instance_id, client, connection, frames, watch }))
+    }
+
+    pub fn instance_id(&self) -> &str {
+        match self {
+            Backplane::Local(local) => &local.instance_id,
+            Backplane::Redis(redis) => &redis.instance_id,
+        }
+    }
+
+    /// Assign a game to this instance; false if another instance owns it
+    pub async fn claim_game(&self, game_id: Uuid) -> Result<bool> {
+        match self {
+            Backplane::Local(local) => {
+                let mut assignments = local.assignments.lock().unwrap();
+                let owner = assignments.entry(game_id).or_insert_with(|| local.instance_id.clone());
+                Ok(*owner == local.instance_id)
+            }
+            Backplane::Redis(redis) => {
+                let game = game_id.to_string();
+                let mut connection = redis.connection.clone();
+                let _: bool = connection.hset_nx(ASSIGNMENTS_KEY, &game, &redis.instance_id).await?;
+                let owner: Option<String> = connection.hget(ASSIGNMENTS_KEY, &game).await?;
+                let owned = owner.as_deref() == Some(redis.instance_id.as_str());
+                if owned {
+                    redis.watch.hosted.lock().unwrap().insert(game_id);
+                }
+                Ok(owned)
+            }
+        }
+    }
+
+    /// Publish or refresh the listing of a game hosted here
+    pub async fn announce(&self, listing: &GameListing) -> Result<()> {
+        match self {
+            Backplane::Local(local) => {
+                local.listings.lock().unwrap().insert(listing.game_id, listing.clone());
+                Ok(())
+            }
+            Backplane::Redis(redis) => {
+                let json = serde_json::to_string(listing)?;
+                let _: () = redis.connection.clone().hset(LISTINGS_KEY, listing.game_id.to_string(), json).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Drop a finished game's listing and assignment
+    pub async fn retire_game(&self, game_id: Uuid) -> Result<()> {
+        match self {
+            Backplane::Local(local) => {
+                local.listings.lock().unwrap().remove(&game_id);
+                local.assignments.lock().unwrap().remove(&game_id);
+                Ok(())
+            }
+            Backplane::Redis(redis) => {
+                redis.watch.hosted.lock().unwrap().remove(&game_id);
+                redis.watch.watched.lock().unwrap().remove(&game_id);
+                let game = game_id.to_string();
+                let mut connection = redis.connection.clone();
+                let _: () = connection.hdel(LISTINGS_KEY, &game).await?;
+                let _: () = connection.hdel(ASSIGNMENTS_KEY, &game).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Every live game across all instances
+    pub async fn list_games(&self) -> Result<Vec<GameListing>> {
+        let listings: Vec<GameListing> = match self {
+            Backplane::Local(local) => local.listings.lock().unwrap().values().cloned().collect(),
+            Backplane::Redis(redis) => {
+                let values: Vec<String> = redis.connection.clone().hvals(LISTINGS_KEY).await?;
+                values.iter().filter_map(|json| serde_json::from_str(json).ok()).collect()
+            }
+        };
+
+        let cutoff = Utc::now() - chrono::Duration::seconds(LISTING_TTL_SECONDS);
+        Ok(listings.into_iter().filter(|l| l.updated_at >= cutoff).collect())
+    }
+
+    /// Whether publishing a game's state frames can reach anyone
+    pub fn has_subscribers(&self, game_id: Uuid) -> bool {
+        match self {
+            Backplane::Local(local) => local.states.receiver_count() > 0,
+            // As of the last refresh; subscribers may be on other instances
+            Backplane::Redis(redis) => redis.watch.watched.lock().unwrap().contains(&game_id),
+        }
+    }
+
+    /// Fan out a serialized state frame to spectators on every instance,
+    /// without waiting for it to be delivered
+    pub fn publish_state(&self, game_id: Uuid, json: String) -> Result<()> {
+        match self {
+            Backplane::Local(local) => {
+                let _ = local.states.send((game_id, json));
+                Ok(())
+            }
+            Backplane::Redis(redis) => {
+                redis.frames.try_send((game_id, json)).map_err(|_| anyhow!("Backplane publish queue is full"))
+            }
+        }
+    }
+
+    /// Receive state frames of one game, wherever it is hosted; the
+    /// subscription ends once the receiver is dropped
+    pub async fn subscribe_state(&self, game_id: Uuid) -> Result<mpsc::Receiver<String>> {
+        let (tx, rx) = mpsc::channel(LOCAL_CHANNEL_BUFFER);
+
+        match self {
+            Backplane::Local(local) => {
+                let mut states = local.states.subscribe();
+                tokio::spawn(async move {
+                    loop {
+                        let state = tokio::select! {
+                            state = states.recv() => state,
+                            _ = tx.closed() => break,
+                        };
+                        match state {
+                            Ok((id, json)) if id == game_id => {
+                                if tx.send(json).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        }
+                    }
+                });
+            }
+            Backplane::Redis(redis) => {
+                // Subscribed connections can't issue other commands, so use a fresh one
+                let mut pubsub = redis.client.get_async_pubsub().await?;
+                pubsub.subscribe(state_channel(game_id)).await?;
+                tokio::spawn(async move {
+                    let mut messages = pubsub.into_on_message();
+                    loop {
+                        let message = tokio::select! {
+                            message = messages.next() => message,
+                            _ = tx.closed() => break,
+                        };
+                        let Some(message) = message else { break };
+                        let Ok(json) = message.get_payload::<String>() else { continue };
+                        if tx.send(json).await.is_err() {
+                            break;
+                        }
+                    }
+                    // Dropping the connection ends the subscription
+                });
+            }
+        }
+
+        Ok(rx)
+    }
+}
+
+/// Single-process backplane
+pub struct LocalBackplane {
+    instance_id: String,
+    assignments: StdMutex<HashMap<Uuid, String>>,
+    listings: StdMutex<HashMap<Uuid, GameListing>>,
+    states: broadcast::Sender<(Uuid, String)>,
+}
+
+/// Backplane shared through a Redis server
+pub struct RedisBackplane {
+    instance_id: String,
+    client: redis::Client,
+    /// Command connection, re-established after errors
+    connection: ConnectionManager,
+    /// Frames for the publisher task
+    frames: mpsc::Sender<(Uuid, String)>,
+    watch: Arc<RedisWatch>,
+}
+
+/// Which games hosted here have spectators anywhere
+#[derive(Default)]
+struct RedisWatch {
+    hosted: StdMutex<HashSet<Uuid>>,
+    watched: StdMutex<HashSet<Uuid>>,
+}
+
+/// PUBLISH queued frames until the backplane is dropped
+async fn publish_frames(mut connection: ConnectionManager, mut queue: mpsc::Receiver<(Uuid, String)>) {
+    while let Some((game_id, json)) = queue.recv().await {
+        if let Err(e) = connection.publish::<_, _, usize>(state_channel(game_id), json).await {
+            warn!("Failed to publish state to backplane: {}", e);
+        }
+    }
+}
+
+/// Keep `watched` up to date with the subscriber counts of the hosted games
+async fn refresh_subscribers(mut connection: ConnectionManager, watch: Weak<RedisWatch>) {
+    let mut interval = tokio::time::interval(SUBSCRIBER_REFRESH);
+    loop {
+        interval.tick().await;
+        let Some(watch) = watch.upgrade() else { break };
+        let hosted: Vec<Uuid> = watch.hosted.lock().unwrap().iter().copied().collect();
+        if hosted.is_empty() {
+            watch.watched.lock().unwrap().clear();
+            continue;
+        }
+
+        let channels: Vec<String> = hosted.iter().map(|&id| state_channel(id)).collect();
+        let counts: Vec<(String, usize)> =
+            match redis::cmd("PUBSUB").arg("NUMSUB").arg(&channels).query_async(&mut connection).await {
+                Ok(counts) => counts,
+                Err(e) => {
+                    warn!("Failed to count backplane subscribers: {}", e);
+                    continue;
+                }
+            };
+        let watched = hosted.into_iter().zip(counts).filter(|(_, (_, count))| *count > 0).map(|(id, _)| id).collect();
+        *watch.watched.lock().unwrap() = watched;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_claims_and_stale_listings() {
+        let backplane = Backplane::local("a".to_string());
+        let game_id = Uuid::new_v4();
+        assert!(backplane.claim_game(game_id).await.unwrap());
+
+        let mut listing = GameListing {
+            game_id,
+            instance_id: "a".to_string(),
+            tick: 0,
+            players_alive: 2,
+            connected_clients: 0,
+            updated_at: Utc::now(),
+        };
+        backplane.announce(&listing).await.unwrap();
+        assert_eq!(backplane.list_games().await.unwrap().len(), 1);
+
+        listing.updated_at = Utc::now() - chrono::Duration::seconds(LISTING_TTL_SECONDS + 1);
+        backplane.announce(&listing).await.unwrap();
+        assert!(backplane.list_games().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscription_ends_with_its_receiver() {
+        let backplane = Backplane::local("a".to_string());
+        let game_id = Uuid::new_v4();
+        let mut frames = backplane.subscribe_state(game_id).await.unwrap();
+        assert!(backplane.has_subscribers(game_id));
+
+        backplane.publish_state(game_id, "{}".to_string()).unwrap();
+        assert_eq!(frames.recv().await.unwrap(), "{}");
+
+        // The forwarding task quits without waiting for another frame
+        drop(frames);
+        for _ in 0..50 {
+            if !backplane.has_subscribers(game_id) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("subscription outlived its receiver");
+    }
+}
@@ -0,0 +1,276 @@
+//! Load-testing bot swarm.
+//!
+//! Opens many WebSocket connections against a running server, issues a
+//! realistic mix of commands and reports latency percentiles and dropped
+//! state frames.
+//!
+//! Usage: cargo run --release --bin loadtest -- [--url ws://localhost:3000/ws]
+//!        [--clients 200] [--duration 30] [--rate 2]
+
+use futures_util::{SinkExt, StreamExt};
+use rand::Rng;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+
+/// Broadcasts are sent every 5 ticks; a larger gap means frames were dropped
+const BROADCAST_TICK_INTERVAL: u64 = 5;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+struct Config {
+    url: String,
+    clients: usize,
+    duration: Duration,
+    /// Commands per second per client
+    rate: f64,
+}
+
+impl Config {
+    fn from_args() -> Self {
+        let mut config = Self {
+            url: "ws://localhost:3000/ws".to_string(),
+            clients: 200,
+            duration: Duration::from_secs(30),
+            rate: 2.0,
+        };
+
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        for pair in args.chunks(2) {
+            let value = pair.get(1).map(String::as_str).unwrap_or_default();
+            match pair[0].as_str() {
+                "--url" => config.url = value.to_string(),
+                "--clients" => config.clients = value.parse().expect("--clients expects a number"),
+                "--duration" => config.duration = Duration::from_secs(value.parse().expect("--duration expects seconds")),
+                "--rate" => config.rate = value.parse().expect("--rate expects commands per second"),
+                other => panic!("unknown argument: {}", other),
+            }
+        }
+
+        config
+    }
+}
+
+/// Per-connection measurements
+#[derive(Default)]
+struct BotReport {
+    connected: bool,
+    connect_latency: Option<Duration>,
+    request_latencies: Vec<Duration>,
+    commands_sent: u64,
+    messages_received: u64,
+    timed_out_requests: u64,
+    dropped_frames: u64,
+    errors: u64,
+}
+
+/// The slice of game state a bot needs to pick plausible commands
+#[derive(Default)]
+struct WorldView {
+    player_id: Option<String>,
+    owned: Vec<(String, Vec<String>)>,
+    territories: Vec<String>,
+}
+
+impl WorldView {
+    fn update(&mut self, state: &Value) {
+        if self.player_id.is_none() {
+            self.player_id = state["players"]
+                .as_array()
+                .and_then(|players| players.iter().find(|p| p["is_ai"] == false))
+                .and_then(|p| p["id"].as_str())
+                .map(str::to_string);
+        }
+
+        let territories = state["territories"].as_array().cloned().unwrap_or_default();
+        self.territories = territories.iter().filter_map(|t| t["id"].as_str().map(str::to_string)).collect();
+        self.owned = territories
+            .iter()
+            .filter(|t| t["owner"].as_str().is_some() && t["owner"].as_str() == self.player_id.as_deref())
+            .map(|t| {
+                let neighbors = t["neighbors"]
+                    .as_array()
+                    .map(|n| n.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+                    .unwrap_or_default();
+                (t["id"].as_str().unwrap_or_default().to_string(), neighbors)
+            })
+            .collect();
+    }
+
+    fn random_command(&self, rng: &mut impl Rng) -> Value {
+        let roll: f32 = rng.gen();
+        if roll < 0.3 {
+            json!({ "type": "get_game_state" })
+        } else if roll < 0.5 {
+            json!({ "type": "set_troop_ratio", "ratio": rng.gen_range(0.2..0.8) })
+        } else if roll < 0.6 {
+            json!({ "type": "set_attack_ratio", "ratio": rng.gen_range(0.1..0.5) })
+        } else if roll < 0.9 && !self.owned.is_empty() {
+            let (from, neighbors) = &self.owned[rng.gen_range(0..self.owned.len())];
+            match neighbors.get(rng.gen_range(0..neighbors.len().max(1))) {
+                Some(to) => json!({ "type": "attack", "from": from, "to": to }),
+                None => json!({ "type": "get_game_state" }),
+            }
+        } else if !self.territories.is_empty() {
+            let territory = &self.territories[rng.gen_range(0..self.territories.len())];
+            let building = ["city", "defense_post", "gold_mine"][rng.gen_range(0..3)];
+            json!({ "type": "build_structure", "territory": territory, "building_type": building })
+        } else {
+            json!({ "type": "get_game_state" })
+        }
+    }
+}
+
+async fn run_bot(config: &Config) -> BotReport {
+    let mut report = BotReport::default();
+
+    let started = Instant::now();
+    let socket = match connect_async(config.url.as_str()).await {
+        Ok((socket, _)) => socket,
+        Err(_) => {
+            report.errors += 1;
+            return report;
+        }
+    };
+    report.connected = true;
+    report.connect_latency = Some(started.elapsed());
+
+    let (mut sender, mut receiver) = socket.split();
+    if sender.send(Message::Text(json!({ "type": "join_game" }).to_string())).await.is_err() {
+        report.errors += 1;
+        return report;
+    }
+    let mut world = WorldView::default();
+    let mut pending: VecDeque<Instant> = VecDeque::new();
+    let mut last_tick: Option<u64> = None;
+
+    let deadline = tokio::time::Instant::now() + config.duration;
+    let period = Duration::from_secs_f64(1.0 / config.rate.max(0.01));
+    // Stagger bots so they don't all fire on the same instant
+    let offset = Duration::from_secs_f64(rand::thread_rng().gen_range(0.0..period.as_secs_f64()));
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + offset, period);
+
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            _ = interval.tick() => {
+                let command = world.random_command(&mut rand::thread_rng());
+                if command["type"] == "get_game_state" {
+                    pending.push_back(Instant::now());
+                }
+                if sender.send(Message::Text(command.to_string())).await.is_err() {
+                    report.errors += 1;
+                    break;
+                }
+                report.commands_sent += 1;
+
+                while pending.front().is_some_and(|sent| sent.elapsed() > REQUEST_TIMEOUT) {
+                    pending.pop_front();
+                    report.timed_out_requests += 1;
+                }
+            }
+            frame = receiver.next() => {
+                let text = match frame {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(_)) => continue,
+                    _ => {
+                        report.errors += 1;
+                        break;
+                    }
+                };
+                report.messages_received += 1;
+
+                let message: Value = match serde_json::from_str(&text) {
+                    Ok(message) => message,
+                    Err(_) => {
+                        report.errors += 1;
+                        continue;
+                    }
+                };
+
+                if message["type"] == "game_state_update" {
+                    let state = &message["state"];
+                    world.update(state);
+
+                    if let Some(tick) = state["tick"].as_u64() {
+                        if let Some(previous) = last_tick {
+                            let gap = tick.saturating_sub(previous);
+                            if gap > BROADCAST_TICK_INTERVAL {
+                                report.dropped_frames += gap / BROADCAST_TICK_INTERVAL - 1;
+                            }
+                        }
+                        last_tick = Some(tick);
+                    }
+
+                    // Replies and broadcasts share a message type, so a pending request
+                    // is answered by the next state frame
+                    if let Some(sent) = pending.pop_front() {
+                        report.request_latencies.push(sent.elapsed());
+                    }
+                }
+            }
+        }
+    }
+
+    report.timed_out_requests += pending.len() as u64;
+    let _ = sender.close().await;
+    report
+}
+
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let idx = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[idx]
+}
+
+fn print_latencies(label: &str, mut samples: Vec<Duration>) {
+    samples.sort();
+    println!(
+        "{:<18} n={:<8} p50={:>8.2?} p90={:>8.2?} p99={:>8.2?} max={:>8.2?}",
+        label,
+        samples.len(),
+        percentile(&samples, 0.5),
+        percentile(&samples, 0.9),
+        percentile(&samples, 0.99),
+        samples.last().copied().unwrap_or_default(),
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let config = std::sync::Arc::new(Config::from_args());
+    println!(
+        "Starting {} bots against {} for {:?} at {} cmd/s each",
+        config.clients, config.url, config.duration, config.rate
+    );
+
+    let bots: Vec<_> = (0..config.clients)
+        .map(|_| {
+            let config = config.clone();
+            tokio::spawn(async move { run_bot(&config).await })
+        })
+        .collect();
+
+    let mut reports = Vec::with_capacity(bots.len());
+    for bot in bots {
+        if let Ok(report) = bot.await {
+            reports.push(report);
+        }
+    }
+
+    let connected = reports.iter().filter(|r| r.connected).count();
+    let sum = |f: fn(&BotReport) -> u64| reports.iter().map(f).sum::<u64>();
+
+    println!();
+    println!("Connections        {}/{}", connected, config.clients);
+    println!("Commands sent      {}", sum(|r| r.commands_sent));
+    println!("Messages received  {}", sum(|r| r.messages_received));
+    println!("Timed out requests {}", sum(|r| r.timed_out_requests));
+    println!("Dropped frames     {}", sum(|r| r.dropped_frames));
+    println!("Errors             {}", sum(|r| r.errors));
+    println!();
+    print_latencies("connect", reports.iter().filter_map(|r| r.connect_latency).collect());
+    print_latencies("get_game_state", reports.iter().flat_map(|r| r.request_latencies.iter().copied()).collect());
+}
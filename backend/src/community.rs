@@ -0,0 +1,235 @@
+//! Player profiles and the lobby chat.
+//!
+//! There are no accounts, so, like the ladder, profiles are keyed by the
+//! identity derived from a client's `player_key`; humans who play without a
+//! key get no profile. Each keeps lifetime totals and the player's most recent
+//! matches. Profiles live in memory and are loaded from the match `Storage`
+//! at startup; changed ones are saved by a background task, so recording a
+//! game never waits on the database.
+//!
+//! The lobby chat is one room outside any game, kept by the match `Storage`;
+//! clients poll it with the id of the last message they saw. Only players
+//! holding a session token may post, under their in-game name.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::warn;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::ladder::MatchParticipant;
+use crate::storage::Storage;
+
+/// Matches kept per profile
+const MAX_MATCH_HISTORY: usize = 20;
+/// Chat messages kept for late arrivals
+pub const MAX_CHAT_MESSAGES: usize = 100;
+const MAX_CHAT_LENGTH: usize = 300;
+
+/// A finished game from one player's side
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MatchRecord {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+    pub finished_at: DateTime<Utc>,
+    pub won: bool,
+    /// Everyone else in the game, AI players included
+    pub opponents: Vec<String>,
+    pub duration_seconds: u32,
+}
+
+/// A player's totals and recent matches, newest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Profile {
+    /// Ladder identity of the player
+    #[schema(value_type = String, format = "uuid")]
+    pub player: Uuid,
+    /// The name the player last played under
+    pub name: String,
+    pub games: u32,
+    pub wins: u32,
+    pub recent_matches: Vec<MatchRecord>,
+}
+
+/// A message in the lobby chat
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ChatMessage {
+    /// Increases with every message; poll with the last one seen
+    pub id: u64,
+    pub name: String,
+    pub text: String,
+    pub sent_at: DateTime<Utc>,
+}
+
+pub struct Community {
+    profiles: Mutex<HashMap<Uuid, Profile>>,
+    /// Changed profiles, for the saving task
+    writes: Option<mpsc::UnboundedSender<Profile>>,
+}
+
+impl Community {
+    /// Load profiles from `storage` and keep saving them there
+    pub async fn open(storage: Arc<dyn Storage>) -> Result<Self> {
+        let profiles = storage.profiles().await?.into_iter().map(|p| (p.player, p)).collect();
+        let (writes, queue) = mpsc::unbounded_channel();
+        tokio::spawn(save_profiles(storage, queue));
+        Ok(Self { profiles: Mutex::new(profiles), writes: Some(writes) })
+    }
+
+    /// Profiles that are never saved
+    #[cfg(test)]
+    pub fn in_memory() -> Self {
+        Self { profiles: Mutex::new(HashMap::new()), writes: None }
+    }
+
+    /// Add a finished game to the profile of every human who played it with a key
+    pub fn record_match(
+        &self,
+        game_id: Uuid,
+        participants: &[MatchParticipant],
+        duration_seconds: u32,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let mut changed = Vec::new();
+        let mut profiles = self.profiles.lock().unwrap();
+        for (i, player) in participants.iter().enumerate() {
+            let Some(identity) = player.identity.filter(|_| !player.is_ai) else { continue };
+            let profile = profiles.entry(identity).or_insert_with(|| Profile { player: identity, ..Profile::default() });
+            profile.name = player.name.clone();
+            profile.games += 1;
+            profile.wins += u32::from(player.won);
+            profile.recent_matches.insert(
+                0,
+                MatchRecord {
+                    game_id,
+                    finished_at: now,
+                    won: player.won,
+                    opponents: participants.iter().enumerate().filter(|&(j, _)| j != i).map(|(_, p)| p.name.clone()).collect(),
+                    duration_seconds,
+                },
+            );
+            profile.recent_matches.truncate(MAX_MATCH_HISTORY);
+            changed.push(profile.clone());
+        }
+        drop(profiles);
+
+        let Some(writes) = &self.writes else { return Ok(()) };
+        for profile in changed {
+            writes.send(profile).map_err(|_| anyhow!("Profiles are no longer being saved"))?;
+        }
+        Ok(())
+    }
+
+    pub fn profile(&self, player: Uuid) -> Option<Profile> {
+        self.profiles.lock().unwrap().get(&player).cloned()
+    }
+}
+
+/// Save queued profiles until the community is dropped
+async fn save_profiles(storage: Arc<dyn Storage>, mut queue: mpsc::UnboundedReceiver<Profile>) {
+    while let Some(profile) = queue.recv().await {
+        if let Err(e) = storage.save_profile(&profile).await {
+            warn!(player = %profile.player, "Failed to save profile: {}", e);
+        }
+    }
+}
+
+/// A lobby chat message as posted, trimmed; empty and overlong ones are refused
+pub fn chat_text(text: &str) -> Result<&str> {
+    let text = text.trim();
+    if text.is_empty() || text.chars().count() > MAX_CHAT_LENGTH {
+        return Err(anyhow!("Messages must be 1 to {} characters", MAX_CHAT_LENGTH));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ladder::player_identity;
+    use crate::storage::MemoryStorage;
+
+    fn identity(name: &str) -> Uuid {
+        player_identity(&format!("{}'s secret player key", name)).unwrap()
+    }
+
+    fn human(name: &str, won: bool) -> MatchParticipant {
+        MatchParticipant { name: name.to_string(), is_ai: false, won, identity: Some(identity(name)) }
+    }
+
+    fn game(winner: &str, loser: &str) -> Vec<MatchParticipant> {
+        vec![
+            human(winner, true),
+            human(loser, false),
+            MatchParticipant { name: "AI 1".to_string(), is_ai: true, won: false, identity: None },
+        ]
+    }
+
+    #[test]
+    fn test_matches_are_kept_per_human_newest_first() {
+        let community = Community::in_memory();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        community.record_match(first, &game("alice", "bob"), 300, Utc::now()).unwrap();
+        community.record_match(second, &game("bob", "alice"), 600, Utc::now()).unwrap();
+
+        let alice = community.profile(identity("alice")).unwrap();
+        assert_eq!((alice.games, alice.wins), (2, 1));
+        assert_eq!(alice.recent_matches.iter().map(|m| m.game_id).collect::<Vec<_>>(), [second, first]);
+        assert_eq!(alice.recent_matches[1].opponents, ["bob", "AI 1"]);
+
+        for _ in 0..MAX_MATCH_HISTORY {
+            community.record_match(Uuid::new_v4(), &game("alice", "bob"), 60, Utc::now()).unwrap();
+        }
+        let alice = community.profile(identity("alice")).unwrap();
+        assert_eq!(alice.games as usize, MAX_MATCH_HISTORY + 2);
+        assert_eq!(alice.recent_matches.len(), MAX_MATCH_HISTORY);
+    }
+
+    #[test]
+    fn test_profiles_follow_the_key_not_the_name() {
+        let community = Community::in_memory();
+        community.record_match(Uuid::new_v4(), &game("alice", "bob"), 300, Utc::now()).unwrap();
+
+        // Someone else calling themselves alice starts their own profile
+        let mut impostor = game("alice", "bob");
+        impostor[0].identity = Some(identity("mallory"));
+        community.record_match(Uuid::new_v4(), &impostor, 300, Utc::now()).unwrap();
+        // And nobody is recorded without a key
+        impostor[0].identity = None;
+        community.record_match(Uuid::new_v4(), &impostor, 300, Utc::now()).unwrap();
+
+        assert_eq!(community.profile(identity("alice")).unwrap().games, 1);
+        assert_eq!(community.profile(identity("mallory")).unwrap().name, "alice");
+        assert_eq!(community.profile(identity("bob")).unwrap().games, 3);
+        assert_eq!(community.profiles.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_chat_text_is_trimmed_and_bounded() {
+        assert_eq!(chat_text(" sure ").unwrap(), "sure");
+        assert!(chat_text("   ").is_err());
+        assert!(chat_text(&"x".repeat(MAX_CHAT_LENGTH + 1)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_community_survives_restart() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        let community = Community::open(storage.clone()).await.unwrap();
+        community.record_match(Uuid::new_v4(), &game("alice", "bob"), 300, Utc::now()).unwrap();
+
+        // Saved in the background
+        for _ in 0..50 {
+            if storage.profiles().await.unwrap().len() == 2 {
+                let reopened = Community::open(storage).await.unwrap();
+                assert_eq!(reopened.profile(identity("bob")).unwrap().games, 1);
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the profiles were never saved");
+    }
+}
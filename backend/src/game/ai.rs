@@ -1,23 +1,69 @@
+use rand::seq::SliceRandom;
 use rand::Rng;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use uuid::Uuid;
 
 use crate::types::*;
+use super::plugins::AiScript;
 use super::GameEngine;
 
 pub struct AIEngine;
 
+/// Decisions kept per AI player
+const MAX_AI_DECISIONS: usize = 20;
+/// How many troops' worth of weakness a resource deposit adds to a target
+const DEPOSIT_VALUE: f64 = 50.0;
+
+/// Recent decisions of every AI player, kept in debug mode
+#[derive(Default)]
+pub struct AiDecisionLog {
+    decisions: HashMap<Uuid, VecDeque<AiDecision>>,
+}
+
+impl AiDecisionLog {
+    fn record(&mut self, player: Uuid, decision: AiDecision) {
+        let decisions = self.decisions.entry(player).or_default();
+        if decisions.len() == MAX_AI_DECISIONS {
+            decisions.pop_front();
+        }
+        decisions.push_back(decision);
+    }
+
+    /// A player's recent decisions, oldest first
+    pub fn last(&self, player: Uuid) -> Vec<AiDecision> {
+        self.decisions.get(&player).map(|d| d.iter().cloned().collect()).unwrap_or_default()
+    }
+}
+
+/// A scenario script an AI player follows, with the tick its next wave is due
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(super) struct ScriptedAi {
+    script: AiScript,
+    next_wave: u64,
+}
+
 impl AIEngine {
     /// Execute AI actions for all AI players
     pub fn tick_all(engine: &mut GameEngine) {
-        let ai_players: Vec<_> = engine.state.players
+        for (player_id, personality) in Self::turn_order(engine) {
+            Self::execute_ai_turn(engine, player_id.into(), personality);
+        }
+    }
+
+    /// The living AI players in the order they act this tick. Acting first
+    /// wins races for the same target, so the order is shuffled every tick
+    /// with the engine's generator: no AI keeps the advantage for having
+    /// joined first, and a seeded game still plays out the same way.
+    fn turn_order(engine: &mut GameEngine) -> Vec<(Uuid, AIPersonality)> {
+        let mut ai_players: Vec<_> = engine.state.players
             .iter()
             .filter(|p| p.is_ai && p.is_alive)
             .map(|p| (p.id, p.ai_personality.unwrap()))
             .collect();
-
-        for (player_id, personality) in ai_players {
-            Self::execute_ai_turn(engine, player_id.into(), personality);
-        }
+        ai_players.shuffle(&mut engine.rng);
+        ai_players
     }
 
     fn execute_ai_turn(engine: &mut GameEngine, player_id: PlayerId, personality: AIPersonality) {
@@ -25,13 +71,28 @@ impl AIEngine {
         Self::update_ratios(engine, player_id, personality);
 
         // Decide whether to build
-        if let Err(_) = Self::try_build(engine, player_id, personality) {
+        if Self::try_build(engine, player_id, personality).is_err() {
             // Building failed, that's ok
         }
 
-        // Decide whether to attack
-        if let Err(_) = Self::try_attack(engine, player_id, personality) {
-            // Attack failed, that's ok
+        // Keep a research going
+        let _ = Self::try_research(engine, player_id, personality);
+
+        // Decide whether to attack, unless a scenario decides for them
+        match engine.ai_scripts.get(&player_id.into()).copied() {
+            None => {
+                if Self::try_attack(engine, player_id, personality).is_err() {
+                    // Attack failed, that's ok
+                }
+            }
+            Some(ScriptedAi { script: AiScript::Waves { every }, next_wave }) if engine.state.tick >= next_wave => {
+                Self::launch_wave(engine, player_id, personality);
+                if let Some(scripted) = engine.ai_scripts.get_mut(&player_id.into()) {
+                    scripted.next_wave = engine.state.tick + every;
+                }
+            }
+            // Holding back until provoked or until the next wave
+            Some(_) => {}
         }
     }
 
@@ -77,53 +138,120 @@ impl AIEngine {
             }
         };
 
+        // Defensive players train infantry; the aggressive ones cavalry to hit hard
+        let (infantry, cavalry, siege) = match personality {
+            AIPersonality::Turtle => (1.0, 0.0, 0.0),
+            AIPersonality::Aggressor => (0.5, 0.3, 0.2),
+            AIPersonality::Balanced => (0.6, 0.25, 0.15),
+            AIPersonality::Opportunist => (0.6, 0.4, 0.0),
+            AIPersonality::Rusher => (0.3, 0.7, 0.0),
+        };
+
         let _ = engine.set_troop_ratio(player_id, troop_ratio);
         let _ = engine.set_attack_ratio(player_id, attack_ratio);
+        let _ = engine.set_unit_mix(player_id, UnitMix { infantry, cavalry, siege });
     }
 
     fn try_build(engine: &mut GameEngine, player_id: PlayerId, personality: AIPersonality) -> Result<()> {
-        let mut rng = rand::thread_rng();
+        let mut rng = engine.fork_rng();
 
         let player = engine.get_player(player_id)?;
         let gold = player.gold;
 
         // Decide what to build based on personality
-        let building_priority = match personality {
+        let mut building_priority = match personality {
             AIPersonality::Turtle => vec![BuildingType::DefensePost, BuildingType::City, BuildingType::GoldMine],
             AIPersonality::Aggressor => vec![BuildingType::City, BuildingType::GoldMine, BuildingType::DefensePost],
             AIPersonality::Balanced => vec![BuildingType::GoldMine, BuildingType::City, BuildingType::DefensePost],
             AIPersonality::Opportunist => vec![BuildingType::GoldMine, BuildingType::DefensePost, BuildingType::City],
             AIPersonality::Rusher => vec![BuildingType::City, BuildingType::GoldMine, BuildingType::DefensePost],
         };
+        // A population that can't grow for want of food needs a Farm first
+        if player.food_per_second <= 0.0 && player.population < player.max_population {
+            building_priority.insert(0, BuildingType::Farm);
+        }
+        // Hemmed in by water, the only way on is by sea
+        let has_harbor = engine.state.territories.iter().any(|t| t.owner == Some(player_id.into()) && t.building == Some(BuildingType::Harbor));
+        if !has_harbor && Self::stranded(engine, player_id) {
+            building_priority.insert(0, BuildingType::Harbor);
+        }
 
-        // Find affordable building
-        for building_type in building_priority {
-            if gold >= building_type.cost() {
+        // Score affordable buildings by priority; the best gets a random free site
+        let mut options = Vec::new();
+        let mut chosen = None;
+        for (rank, &building_type) in building_priority.iter().enumerate() {
+            let mut option = AiOption { from: None, territory: None, building_type: Some(building_type), score: None };
+            if gold >= engine.building_cost(player_id, building_type) {
                 // Find a territory without a building
-                let territories: Vec<_> = engine.state.territories
+                let mut territories: Vec<_> = engine.state.territories
                     .iter()
                     .filter(|t| t.owner == Some(player_id.into()) && t.building.is_none())
+                    .filter(|t| building_type != BuildingType::Harbor || t.coastal)
                     .map(|t| t.id)
                     .collect();
 
+                // Defenses pay off most where few paths lead in
+                if building_type == BuildingType::DefensePost
+                    && territories.iter().any(|id| engine.state.is_chokepoint(*id))
+                {
+                    territories.retain(|id| engine.state.is_chokepoint(*id));
+                }
+
                 if !territories.is_empty() {
-                    let territory_id = territories[rng.gen_range(0..territories.len())];
-                    return engine.build_structure(player_id, territory_id.into(), building_type);
+                    option.score = Some((building_priority.len() - rank) as f64);
+                    if chosen.is_none() {
+                        option.territory = Some(territories[rng.gen_range(0..territories.len())]);
+                        chosen = Some(rank);
+                    }
                 }
             }
+            options.push(option);
         }
 
-        Ok(())
+        let Some(rank) = chosen else { return Ok(()) };
+        let territory = options[rank].territory.unwrap();
+        let tick = engine.state.tick;
+        let result = engine.build_structure(player_id, territory.into(), building_priority[rank]);
+        let outcome = match &result {
+            Ok(()) => "Built".to_string(),
+            Err(e) => e.to_string(),
+        };
+        Self::record(engine, player_id, AiDecision { tick, kind: AiDecisionKind::Build, personality, options, chosen, outcome });
+        result
+    }
+
+    fn try_research(engine: &mut GameEngine, player_id: PlayerId, personality: AIPersonality) -> Result<()> {
+        let player = engine.get_player(player_id)?;
+        if player.research.is_some() {
+            return Ok(());
+        }
+
+        let priority = match personality {
+            AIPersonality::Turtle => [Tech::Masonry, Tech::Agriculture, Tech::Drill, Tech::Tactics],
+            AIPersonality::Aggressor | AIPersonality::Rusher => [Tech::Drill, Tech::Tactics, Tech::Agriculture, Tech::Masonry],
+            AIPersonality::Balanced => [Tech::Agriculture, Tech::Masonry, Tech::Drill, Tech::Tactics],
+            AIPersonality::Opportunist => [Tech::Masonry, Tech::Drill, Tech::Agriculture, Tech::Tactics],
+        };
+        let available = priority.into_iter().find(|&tech| {
+            !engine.has_tech(player_id, tech) && tech.requires().is_none_or(|required| engine.has_tech(player_id, required))
+        });
+
+        // Only start what the stockpile could pay for outright, so building goes on
+        match available {
+            Some(tech) if player.gold >= tech.cost() => engine.start_research(player_id, tech),
+            _ => Ok(()),
+        }
     }
 
     fn try_attack(engine: &mut GameEngine, player_id: PlayerId, personality: AIPersonality) -> Result<()> {
-        let mut rng = rand::thread_rng();
+        let mut rng = engine.fork_rng();
+        let tick = engine.state.tick;
 
         // Find owned territories
         let owned_territories: Vec<_> = engine.state.territories
             .iter()
             .filter(|t| t.owner == Some(player_id.into()))
-            .map(|t| (t.id, t.neighbors.clone()))
+            .map(|t| (t.id, [t.neighbors.clone(), engine.naval_targets(t.id.into())].concat()))
             .collect();
 
         if owned_territories.is_empty() {
@@ -142,10 +270,10 @@ impl AIEngine {
                     continue;
                 }
 
-                // Get defender info
-                if let Some(defender_id) = neighbor.owner {
+                // Get defender info; treaty and truce partners are left alone
+                if let Some(defender_id) = neighbor.owner.filter(|&d| !engine.state.diplomacy.at_peace(player_id.into(), d)) {
                     let defender = engine.get_player(defender_id.into())?;
-                    let defender_troops = neighbor.troops;
+                    let defender_troops = neighbor.troops();
 
                     attack_options.push((
                         territory_id,
@@ -158,69 +286,247 @@ impl AIEngine {
         }
 
         if attack_options.is_empty() {
+            // No rival next door: grab a deposit within reach, or push through neutral
+            // land toward the closest rival; with no land route left, take the rest of
+            // the island, then cross the water
+            let plan = if Self::stranded(engine, player_id) {
+                Self::plan_claim(engine, player_id, false).or_else(|| Self::plan_landing(engine, player_id))
+            } else {
+                Self::plan_claim(engine, player_id, true).or_else(|| Self::plan_advance(engine, player_id))
+            };
+            if let Some((from, to, hops)) = plan {
+                let options = vec![AiOption { from: Some(from), territory: Some(to), building_type: None, score: Some(-(hops as f64)) }];
+                let outcome = Self::roll_attack(engine, &mut rng, player_id, personality, from, to);
+                Self::record(engine, player_id, AiDecision { tick, kind: AiDecisionKind::Advance, personality, options, chosen: Some(0), outcome });
+            }
             return Ok(());
         }
 
-        // Choose target based on personality
-        let target = match personality {
-            AIPersonality::Turtle => {
-                // Rarely attack, only if heavily outnumber
-                let player = engine.get_player(player_id)?;
-                let our_troops = player.troops();
+        // Score targets the way the personality sees them
+        let our_troops = engine.get_player(player_id)?.troops();
+        let options: Vec<AiOption> = attack_options
+            .iter()
+            .map(|&(from, to, troops, territories)| AiOption {
+                from: Some(from),
+                territory: Some(to),
+                building_type: None,
+                score: Self::attack_score(personality, our_troops, troops, territories)
+                    .map(|score| score + Self::deposit_value(engine, to)),
+            })
+            .collect();
+        let chosen = match personality {
+            // Attack randomly, frequently
+            AIPersonality::Rusher => Some(rng.gen_range(0..options.len())),
+            _ => Self::best_option(&options),
+        };
 
-                attack_options
-                    .iter()
-                    .filter(|(_, _, defender_troops, _)| our_troops > *defender_troops * 3)
-                    .min_by_key(|(_, _, troops, _)| *troops)
-            }
-            AIPersonality::Aggressor => {
-                // Attack anyone, prefer weakest
-                attack_options.iter().min_by_key(|(_, _, troops, _)| *troops)
+        let outcome = match chosen {
+            Some(idx) => {
+                let (from, to, _, _) = attack_options[idx];
+                Self::roll_attack(engine, &mut rng, player_id, personality, from, to)
             }
-            AIPersonality::Balanced => {
-                // Attack if we have advantage
-                let player = engine.get_player(player_id)?;
-                let our_troops = player.troops();
+            None => "No target worth attacking".to_string(),
+        };
+        Self::record(engine, player_id, AiDecision { tick, kind: AiDecisionKind::Attack, personality, options, chosen, outcome });
+        Ok(())
+    }
 
-                attack_options
+    /// Throw every garrison on the border at its weakest neighbor
+    fn launch_wave(engine: &mut GameEngine, player_id: PlayerId, personality: AIPersonality) {
+        let owner = Some(Uuid::from(player_id));
+        let rival = |t: &Territory| {
+            t.owner != owner
+                && !t.owner.is_some_and(|d| engine.state.diplomacy.at_peace(player_id.into(), d))
+                && (engine.rules.passable_water || t.terrain != TerrainType::Water)
+        };
+        let attacks: Vec<(Uuid, Uuid, u32)> = engine.state.territories
+            .iter()
+            .filter(|t| t.owner == owner && t.troops() > 0)
+            .filter_map(|t| {
+                let target = t.neighbors
                     .iter()
-                    .filter(|(_, _, defender_troops, _)| our_troops > *defender_troops)
-                    .min_by_key(|(_, _, troops, _)| *troops)
+                    .filter_map(|id| engine.get_territory((*id).into()).ok())
+                    .filter(|n| rival(n))
+                    .min_by_key(|n| n.troops())?;
+                Some((t.id, target.id, target.troops()))
+            })
+            .collect();
+
+        let _ = engine.set_attack_ratio(player_id, 1.0);
+        let landed = attacks
+            .iter()
+            .filter(|&&(from, to, _)| engine.launch_attack(player_id, from.into(), to.into()).is_ok())
+            .count();
+
+        let options = attacks
+            .iter()
+            .map(|&(from, to, troops)| AiOption { from: Some(from), territory: Some(to), building_type: None, score: Some(-(troops as f64)) })
+            .collect();
+        let outcome = format!("Launched {} of {} attacks", landed, attacks.len());
+        let tick = engine.state.tick;
+        Self::record(engine, player_id, AiDecision { tick, kind: AiDecisionKind::Wave, personality, options, chosen: None, outcome });
+    }
+
+    /// How much a personality wants to attack a territory; `None` rules it out
+    fn attack_score(personality: AIPersonality, our_troops: u64, defender_troops: u32, defender_territories: u32) -> Option<f64> {
+        let weakness = -(defender_troops as f64);
+        match personality {
+            // Rarely attack, only if heavily outnumber
+            AIPersonality::Turtle => (our_troops > defender_troops as u64 * 3).then_some(weakness),
+            // Attack anyone, prefer weakest
+            AIPersonality::Aggressor => Some(weakness),
+            // Attack if we have advantage
+            AIPersonality::Balanced => (our_troops > defender_troops as u64).then_some(weakness),
+            // Attack the weakest garrison, then the smallest player
+            AIPersonality::Opportunist => Some(weakness - defender_territories as f64 / 1e4),
+            // Any target will do
+            AIPersonality::Rusher => Some(0.0),
+        }
+    }
+
+    /// What holding a territory's deposit is worth to an attacker
+    fn deposit_value(engine: &GameEngine, territory: Uuid) -> f64 {
+        match engine.get_territory(territory.into()).map(|t| t.resource) {
+            Ok(Some(_)) => DEPOSIT_VALUE,
+            _ => 0.0,
+        }
+    }
+
+    /// Index of the highest-scoring option, the first one on ties
+    fn best_option(options: &[AiOption]) -> Option<usize> {
+        let mut best: Option<(usize, f64)> = None;
+        for (idx, option) in options.iter().enumerate() {
+            if let Some(score) = option.score {
+                if best.is_none_or(|(_, top)| score > top) {
+                    best = Some((idx, score));
+                }
             }
-            AIPersonality::Opportunist => {
-                // Attack weakest player
-                attack_options
+        }
+        best.map(|(idx, _)| idx)
+    }
+
+    /// Attack with the personality's odds, describing what happened
+    fn roll_attack(
+        engine: &mut GameEngine,
+        rng: &mut impl Rng,
+        player_id: PlayerId,
+        personality: AIPersonality,
+        from: Uuid,
+        to: Uuid,
+    ) -> String {
+        let (roll, chance) = (rng.gen::<f32>(), Self::attack_chance(personality));
+        if roll >= chance {
+            return format!("Held back: rolled {:.2} against a {:.2} attack chance", roll, chance);
+        }
+        match engine.launch_attack(player_id, from.into(), to.into()) {
+            Ok(Some(_)) => "Attacked".to_string(),
+            Ok(None) => "Went into battle".to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn record(engine: &mut GameEngine, player_id: PlayerId, decision: AiDecision) {
+        if let Some(log) = engine.ai_log.as_mut() {
+            log.record(player_id.into(), decision);
+        }
+    }
+
+    fn attack_chance(personality: AIPersonality) -> f32 {
+        match personality {
+            AIPersonality::Turtle => 0.1,
+            AIPersonality::Aggressor => 0.8,
+            AIPersonality::Balanced => 0.4,
+            AIPersonality::Opportunist => 0.5,
+            AIPersonality::Rusher => 0.9,
+        }
+    }
+
+    /// First step from the strongest border territory along the shortest land
+    /// route to a rival, if it is a neutral the garrison can overwhelm, with
+    /// the route's length in hops
+    fn plan_advance(engine: &GameEngine, player_id: PlayerId) -> Option<(Uuid, Uuid, usize)> {
+        let owner = Some(Uuid::from(player_id));
+        let origin = engine.state.territories
+            .iter()
+            .filter(|t| t.owner == owner)
+            .filter(|t| t.neighbors.iter().any(|id| engine.get_territory((*id).into()).is_ok_and(|n| n.owner != owner)))
+            .max_by_key(|t| t.troops())?;
+
+        let land = TraversalRules { land_only: true, ..TraversalRules::default() };
+        let path = engine.path_to_nearest(origin.id.into(), land, |t| t.owner.is_some() && t.owner != owner)?;
+
+        let step = engine.get_territory(path[1].into()).ok()?;
+        (step.owner.is_none() && origin.troops() > step.troops().saturating_mul(2)).then_some((origin.id, step.id, path.len() - 1))
+    }
+
+    /// The weakest neutral land next to the player that a garrison can
+    /// overwhelm, only among deposits if `deposits_only`
+    fn plan_claim(engine: &GameEngine, player_id: PlayerId, deposits_only: bool) -> Option<(Uuid, Uuid, usize)> {
+        let owner = Some(Uuid::from(player_id));
+        engine.state.territories
+            .iter()
+            .filter(|t| t.owner == owner)
+            .flat_map(|t| {
+                t.neighbors
                     .iter()
-                    .min_by_key(|(_, _, troops, territory_count)| (*troops, *territory_count))
-            }
-            AIPersonality::Rusher => {
-                // Attack randomly, frequently
-                attack_options.get(rng.gen_range(0..attack_options.len()))
-            }
-        };
+                    .filter_map(|id| engine.get_territory((*id).into()).ok())
+                    .filter(|n| n.owner.is_none() && n.terrain != TerrainType::Water && t.troops() > n.troops().saturating_mul(2))
+                    .filter(|n| !deposits_only || n.resource.is_some())
+                    .map(move |n| (t.id, n.id, n.troops()))
+            })
+            .min_by_key(|&(_, _, troops)| troops)
+            .map(|(from, to, _)| (from, to, 1))
+    }
 
-        if let Some((from, to, _, _)) = target {
-            // Execute with probability based on personality
-            let attack_chance = match personality {
-                AIPersonality::Turtle => 0.1,
-                AIPersonality::Aggressor => 0.8,
-                AIPersonality::Balanced => 0.4,
-                AIPersonality::Opportunist => 0.5,
-                AIPersonality::Rusher => 0.9,
-            };
+    /// The weakest neutral land a Harbor's garrison can overwhelm across the water
+    fn plan_landing(engine: &GameEngine, player_id: PlayerId) -> Option<(Uuid, Uuid, usize)> {
+        let owner = Some(Uuid::from(player_id));
+        engine.state.territories
+            .iter()
+            .filter(|t| t.owner == owner && t.building == Some(BuildingType::Harbor))
+            .flat_map(|harbor| {
+                engine.naval_targets(harbor.id.into())
+                    .into_iter()
+                    .filter_map(|id| engine.get_territory(id.into()).ok())
+                    .filter(|t| t.owner.is_none() && harbor.troops() > t.troops().saturating_mul(2))
+                    .map(move |t| (harbor.id, t.id, t.troops()))
+            })
+            .min_by_key(|&(_, _, troops)| troops)
+            .map(|(from, to, _)| (from, to, 1))
+    }
 
-            if rng.gen::<f32>() < attack_chance {
-                let _ = engine.execute_attack(player_id, (*from).into(), (*to).into());
+    /// Whether no rival can be reached from the player's land without crossing water
+    fn stranded(engine: &GameEngine, player_id: PlayerId) -> bool {
+        if engine.rules.passable_water {
+            return false;
+        }
+        let owner = Some(Uuid::from(player_id));
+        let mut seen: HashSet<Uuid> = engine.state.territories.iter().filter(|t| t.owner == owner).map(|t| t.id).collect();
+        let mut stack: Vec<Uuid> = seen.iter().copied().collect();
+        while let Some(id) = stack.pop() {
+            let Ok(territory) = engine.get_territory(id.into()) else { continue };
+            if territory.owner.is_some() && territory.owner != owner {
+                return false;
+            }
+            for neighbor in &territory.neighbors {
+                let land = engine.get_territory((*neighbor).into()).is_ok_and(|n| n.terrain != TerrainType::Water);
+                if land && seen.insert(*neighbor) {
+                    stack.push(*neighbor);
+                }
             }
         }
-
-        Ok(())
+        true
     }
 }
 
 impl GameEngine {
     /// Run AI logic for all AI players
     pub fn tick_ai(&mut self) {
+        // AI players wait out a pause like everyone else
+        if self.state.is_paused {
+            return;
+        }
+
         // Distribute troops for all players at beginning of each tick
         let all_player_ids: Vec<_> = self.state.players
             .iter()
@@ -235,4 +541,99 @@ impl GameEngine {
         // Run AI decision making
         AIEngine::tick_all(self);
     }
+
+    /// Put an AI player under a scenario script, or back under their personality
+    pub(super) fn script_ai(&mut self, player: Uuid, script: Option<AiScript>) {
+        match script {
+            Some(script) => {
+                let next_wave = match script {
+                    AiScript::Waves { every } => self.state.tick + every,
+                    AiScript::Passive => 0,
+                };
+                self.ai_scripts.insert(player, ScriptedAi { script, next_wave });
+            }
+            None => {
+                self.ai_scripts.remove(&player);
+            }
+        }
+    }
+
+    /// A passive AI that gets attacked fights back from then on
+    pub(super) fn provoke(&mut self, defender: Uuid) {
+        if self.ai_scripts.get(&defender).is_some_and(|s| s.script == AiScript::Passive) {
+            self.ai_scripts.remove(&defender);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_decisions_are_logged_with_their_options() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 4).generate(), 100);
+        for player in &mut engine.state.players {
+            player.is_ai = true;
+            player.ai_personality = Some(AIPersonality::Aggressor);
+            player.gold = 100_000;
+        }
+        engine.ai_log = Some(AiDecisionLog::default());
+        for _ in 0..40 {
+            engine.tick();
+            engine.tick_ai();
+        }
+
+        let log = engine.ai_log.as_ref().unwrap();
+        let decisions: Vec<AiDecision> = engine.state.players.iter().flat_map(|p| log.last(p.id)).collect();
+        assert!(!decisions.is_empty());
+        for decision in &decisions {
+            let chosen = &decision.options[decision.chosen.unwrap()];
+            assert!(chosen.score.is_some());
+            if decision.kind == AiDecisionKind::Build {
+                assert!(chosen.territory.is_some() && chosen.building_type.is_some());
+            } else {
+                // An aggressor goes for the weakest garrison
+                let best = decision.options.iter().filter_map(|o| o.score).fold(f64::MIN, f64::max);
+                assert_eq!(chosen.score, Some(best));
+            }
+        }
+    }
+
+    #[test]
+    fn test_turn_order_is_shuffled_by_the_seed() {
+        let state = MapGenerator::new(30, 4).generate_seeded(1);
+        let orders = |seed: u64| {
+            let mut engine = GameEngine::new(state.clone(), 100);
+            engine.reseed(seed);
+            (0..40).map(|_| AIEngine::turn_order(&mut engine)[0].0).collect::<Vec<_>>()
+        };
+
+        // Every AI gets to go first, and the same seed gives the same order
+        let firsts = orders(7);
+        let ai: Vec<Uuid> = state.players.iter().filter(|p| p.is_ai).map(|p| p.id).collect();
+        assert!(ai.len() > 1 && ai.iter().all(|id| firsts.contains(id)));
+        assert_eq!(firsts, orders(7));
+    }
+
+    #[test]
+    fn test_log_keeps_the_latest_decisions() {
+        let mut log = AiDecisionLog::default();
+        let player = Uuid::new_v4();
+        for tick in 0..30 {
+            let decision = AiDecision {
+                tick,
+                kind: AiDecisionKind::Attack,
+                personality: AIPersonality::Balanced,
+                options: Vec::new(),
+                chosen: None,
+                outcome: "No target worth attacking".to_string(),
+            };
+            log.record(player, decision);
+        }
+        let ticks: Vec<u64> = log.last(player).iter().map(|d| d.tick).collect();
+        assert_eq!(ticks, (10..30).collect::<Vec<_>>());
+        assert!(log.last(Uuid::new_v4()).is_empty());
+    }
 }
@@ -0,0 +1,381 @@
+//! Battles fought over several ticks.
+//!
+//! With `battle_rounds` set, an attack doesn't settle at once: the stack
+//! leaves its origin and fights the garrison a round per tick, each round
+//! taking its share of the casualties the combat model deals. The last round
+//! decides the territory, unless the garrison is wiped out earlier. Sending
+//! another attack at the same territory reinforces the stack; the defender
+//! reinforces by moving troops in or training them. A battle breaks off when
+//! the territory changes hands in between, and the attacker can retreat at
+//! any time, survivors returning to where they set out from.
+//!
+//! With `siege_ticks` set, an attack on a Defense Post lays siege first: no
+//! blows are traded, but every tick the garrison loses troops unless its
+//! owner holds a neighboring territory to supply it from. A garrison starved
+//! to nothing gives up the territory; otherwise the besiegers assault once the
+//! siege is over and the battle is fought out in rounds.
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Attack as the rules have it: settled at once, returning the result, or
+    /// as a battle over the coming ticks, returning `None`
+    pub fn launch_attack(
+        &mut self,
+        attacker_id: PlayerId,
+        from_territory: TerritoryId,
+        to_territory: TerritoryId,
+    ) -> Result<Option<CombatResult>> {
+        let besieged = self.rules.siege_ticks > 0
+            && self.get_territory(to_territory).is_ok_and(|t| t.working_building(self.state.tick) == Some(BuildingType::DefensePost));
+        if self.rules.battle_rounds == 0 && !besieged {
+            return self.execute_attack(attacker_id, from_territory, to_territory).map(Some);
+        }
+
+        let defender_id = self.check_attack(attacker_id, from_territory, to_territory)?;
+        self.settle_garrisons(attacker_id);
+        if let Some(defender_player_id) = defender_id {
+            self.settle_garrisons(defender_player_id.into());
+        }
+        let units = self.muster(attacker_id, from_territory, to_territory)?;
+        self.get_territory_mut(from_territory)?.units.remove(units);
+
+        let (attacker, territory): (Uuid, Uuid) = (attacker_id.into(), to_territory.into());
+        if let Some(battle) = self.state.battles.iter_mut().find(|b| b.attacker_id == attacker && b.territory == territory) {
+            battle.attackers.add(units);
+            battle.committed.add(units);
+            return Ok(None);
+        }
+
+        let id = self.next_battle_id;
+        self.next_battle_id += 1;
+        let defenders = self.get_territory(to_territory)?.units;
+        self.state.battles.push(Battle {
+            id,
+            attacker_id: attacker,
+            defender_id,
+            from_territory: from_territory.into(),
+            territory,
+            attackers: units,
+            committed: units,
+            defenders,
+            attacker_losses: Units::default(),
+            defender_losses: Units::default(),
+            started_tick: self.state.tick,
+            rounds_left: self.rules.battle_rounds.max(1),
+            siege_ticks_left: if besieged { self.rules.siege_ticks } else { 0 },
+        });
+        if let Some(defender_player_id) = defender_id {
+            self.provoke(defender_player_id);
+        }
+        Ok(None)
+    }
+
+    /// Call off one of the player's battles; survivors march back, less
+    /// those cut down breaking away
+    pub fn retreat(&mut self, player_id: PlayerId, battle_id: u64) -> Result<CombatResult> {
+        let idx = self.state.battles
+            .iter()
+            .position(|b| b.id == battle_id && b.attacker_id == Uuid::from(player_id))
+            .ok_or_else(|| anyhow!("You have no battle with that id"))?;
+        let mut battle = self.state.battles.remove(idx);
+
+        let fallen = (battle.attackers.total() as f32 * self.rules.retreat_losses.clamp(0.0, 1.0)).ceil() as u32;
+        let fallen = battle.attackers.portion(fallen);
+        battle.attackers.remove(fallen);
+        battle.attacker_losses.add(fallen);
+        if let Ok(player) = self.get_player_mut(player_id) {
+            player.lose_troops(fallen.total() as u64);
+        }
+        self.end_battle(battle, false)
+    }
+
+    /// Fight a round of every battle, ending those that are decided
+    pub(super) fn update_battles(&mut self) {
+        let mut idx = 0;
+        while idx < self.state.battles.len() {
+            let mut battle = self.state.battles[idx].clone();
+            match self.fight_round(&mut battle) {
+                None => {
+                    self.state.battles[idx] = battle;
+                    idx += 1;
+                }
+                Some(conquered) => {
+                    self.state.battles.remove(idx);
+                    if let Err(e) = self.end_battle(battle, conquered) {
+                        tracing::warn!("Battle could not be ended: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// One round of a battle; returns whether the territory fell once the battle is over
+    fn fight_round(&mut self, battle: &mut Battle) -> Option<bool> {
+        let attacker_id: PlayerId = battle.attacker_id.into();
+        let (from, to): (TerritoryId, TerritoryId) = (battle.from_territory.into(), battle.territory.into());
+        let Ok(target) = self.get_territory(to) else { return Some(false) };
+        let broken_off = target.owner != battle.defender_id
+            || battle.attackers.is_empty()
+            || !self.get_player(attacker_id).is_ok_and(|p| p.is_alive)
+            || battle.defender_id.is_some_and(|defender| self.state.diplomacy.has_treaty(battle.attacker_id, defender));
+        if broken_off {
+            return Some(false);
+        }
+        if battle.siege_ticks_left > 0 {
+            battle.siege_ticks_left -= 1;
+            return self.starve(battle, to);
+        }
+
+        let (attacker_losses, defender_losses, conquered) = self.calculate_combat(attacker_id, battle.attackers, target.units, from, to);
+        // Each round takes its share of what the whole battle would cost now
+        let rounds = battle.rounds_left.max(1);
+        let share = |losses: Units| losses.portion(losses.total().div_ceil(rounds));
+        let (attacker_losses, defender_losses) = (share(attacker_losses), share(defender_losses));
+        battle.rounds_left = rounds - 1;
+
+        battle.attackers.remove(attacker_losses);
+        battle.attacker_losses.add(attacker_losses);
+        if let Ok(attacker) = self.get_player_mut(attacker_id) {
+            attacker.lose_troops(attacker_losses.total() as u64);
+        }
+        battle.defender_losses.add(defender_losses);
+        if let Some(defender) = battle.defender_id {
+            if let Ok(defender) = self.get_player_mut(defender.into()) {
+                defender.lose_troops(defender_losses.total() as u64);
+            }
+        }
+        let garrison = &mut self.get_territory_mut(to).ok()?.units;
+        garrison.remove(defender_losses);
+
+        if conquered && (rounds == 1 || garrison.is_empty()) {
+            Some(true)
+        } else if rounds == 1 || battle.attackers.is_empty() {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// A tick of siege: the garrison dwindles unless relieved, and the
+    /// territory falls once it is empty
+    fn starve(&mut self, battle: &mut Battle, to: TerritoryId) -> Option<bool> {
+        let target = self.get_territory(to).ok()?;
+        let relieved = battle.defender_id.is_some_and(|defender| {
+            target.neighbors.iter().any(|&n| self.get_territory(n.into()).is_ok_and(|t| t.owner == Some(defender)))
+        });
+        if relieved {
+            return None;
+        }
+        let units = target.units;
+
+        // Fractions of a troop are lost at random, so small garrisons starve too
+        let seconds = self.tick_rate_ms as f64 / 1000.0 * self.state.game_speed as f64;
+        let share = (self.rules.siege_attrition_per_second as f64 * seconds).clamp(0.0, 1.0);
+        let expected = if share.is_nan() { 0.0 } else { units.total() as f64 * share };
+        let lost = expected.floor() as u32 + u32::from(self.rng.gen_bool(expected.fract()));
+        let lost = units.portion(lost);
+        battle.defender_losses.add(lost);
+        if let Some(defender) = battle.defender_id {
+            if let Ok(defender) = self.get_player_mut(defender.into()) {
+                defender.lose_troops(lost.total() as u64);
+            }
+        }
+        let garrison = &mut self.get_territory_mut(to).ok()?.units;
+        garrison.remove(lost);
+        garrison.is_empty().then_some(true)
+    }
+
+    /// Settle a battle taken off the list: survivors occupy the territory or
+    /// march back, and the outcome is announced like an attack's
+    fn end_battle(&mut self, battle: Battle, conquered: bool) -> Result<CombatResult> {
+        let attacker_id: PlayerId = battle.attacker_id.into();
+        let (from, to): (TerritoryId, TerritoryId) = (battle.from_territory.into(), battle.territory.into());
+        if conquered {
+            self.conquer(attacker_id, battle.defender_id, to, battle.attackers)?;
+        } else {
+            // Survivors whose origin was lost join the army's next reinforcements instead
+            let origin = self.get_territory_mut(from)?;
+            if origin.owner == Some(battle.attacker_id) {
+                origin.units.add(battle.attackers);
+            }
+        }
+
+        let troops_involved = battle.committed.total().saturating_add(battle.defenders.total());
+        self.record_battle(attacker_id, battle.defender_id, to, troops_involved, conquered);
+        let result = CombatResult {
+            attacker_id: battle.attacker_id,
+            defender_id: battle.defender_id.unwrap_or(Uuid::nil()),
+            from_territory: battle.from_territory,
+            to_territory: battle.territory,
+            attacker_troops_committed: battle.committed.total(),
+            defender_troops: battle.defenders.total(),
+            attacker_losses: battle.attacker_losses.total(),
+            defender_losses: battle.defender_losses.total(),
+            territory_conquered: conquered,
+            attacker_units: battle.committed,
+            defender_units: battle.defenders,
+            attacker_unit_losses: battle.attacker_losses,
+            defender_unit_losses: battle.defender_losses,
+        };
+        self.record_battle_replay(&result);
+        self.events.push(ServerMessage::AttackResult { result: result.clone() });
+        if conquered {
+            self.events.push(ServerMessage::TerritoryConquered {
+                territory_id: result.to_territory,
+                old_owner: Some(result.defender_id),
+                new_owner: result.attacker_id,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Troops the player has away fighting
+    pub(super) fn committed_troops(&self, player_id: PlayerId) -> u64 {
+        self.state.battles
+            .iter()
+            .filter(|b| b.attacker_id == Uuid::from(player_id))
+            .map(|b| b.attackers.total() as u64)
+            .sum()
+    }
+
+    /// Shrink the player's fighting stacks in proportion if the army fell
+    /// below them, returning the troops still away fighting
+    pub(super) fn settle_battles(&mut self, player_id: PlayerId, army: u64) -> u64 {
+        let committed = self.committed_troops(player_id);
+        if committed <= army {
+            return committed;
+        }
+        for battle in self.state.battles.iter_mut().filter(|b| b.attacker_id == Uuid::from(player_id)) {
+            let keep = (battle.attackers.total() as u128 * army as u128 / committed as u128) as u32;
+            battle.attackers = battle.attackers.portion(keep);
+        }
+        self.committed_troops(player_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Engine with the human's 500 troops at index 0, next to a neutral plain with 100 at index 1
+    fn setup() -> (GameEngine, PlayerId, TerritoryId, TerritoryId) {
+        let mut state = MapGenerator::new(20, 2).generate();
+        let player = state.players[0].id;
+        for territory in &mut state.territories {
+            territory.owner = None;
+        }
+        let (from, to) = (state.territories[0].id, state.territories[1].id);
+        state.territories[0].owner = Some(player);
+        state.territories[0].units = Units::infantry(500);
+        state.territories[1].units = Units::infantry(100);
+        state.territories[1].terrain = TerrainType::Plains;
+        state.territories[1].building = None;
+        if !state.territories[0].neighbors.contains(&to) {
+            state.territories[0].neighbors.push(to);
+            state.territories[1].neighbors.push(from);
+        }
+        let mut engine = GameEngine::new(state, 100);
+        let human = engine.get_player_mut(player.into()).unwrap();
+        human.population = 1_000;
+        human.troop_ratio = 0.5;
+        human.trained_ratio = 0.5;
+        human.attack_ratio = 0.25;
+        (engine, player.into(), from.into(), to.into())
+    }
+
+    #[test]
+    fn test_battles_last_rounds_and_can_be_reinforced_or_called_off() {
+        let (mut engine, player, from, to) = setup();
+        engine.rules.battle_rounds = 3;
+
+        // The stack leaves at once but the territory holds out for now
+        assert!(engine.launch_attack(player, from, to).unwrap().is_none());
+        assert_eq!(engine.get_territory(from).unwrap().troops(), 375);
+        engine.tick();
+        let battle = engine.state.battles[0].clone();
+        assert_eq!(battle.rounds_left, 2);
+        assert!(battle.defender_losses.total() > 0);
+        assert_eq!(engine.get_territory(to).unwrap().owner, None);
+        engine.audit_troops(player).unwrap();
+
+        // A second attack on the same territory joins the first, and a retreat brings everyone home
+        engine.launch_attack(player, from, to).unwrap();
+        let battle = engine.state.battles[0].clone();
+        assert_eq!(engine.state.battles.len(), 1);
+        assert!(battle.committed.total() > 125);
+        let home = engine.get_territory(from).unwrap().troops();
+        let result = engine.retreat(player, battle.id).unwrap();
+        assert!(!result.territory_conquered);
+        assert_eq!(result.attacker_troops_committed, battle.committed.total());
+        // A fifth of the stack is lost breaking away
+        let fallen = (battle.attackers.total() as f32 * 0.2).ceil() as u32;
+        assert_eq!(result.attacker_losses, battle.attacker_losses.total() + fallen);
+        assert_eq!(engine.get_territory(from).unwrap().troops(), home + battle.attackers.total() - fallen);
+        assert!(engine.retreat(player, battle.id).is_err());
+        engine.audit_troops(player).unwrap();
+
+        // Fought to the end, the battle takes the territory
+        engine.launch_attack(player, from, to).unwrap();
+        for _ in 0..3 {
+            engine.tick();
+        }
+        assert!(engine.state.battles.is_empty());
+        assert_eq!(engine.get_territory(to).unwrap().owner, Some(player.into()));
+        assert!(engine.events.iter().any(|e| matches!(e, ServerMessage::AttackResult { result } if result.territory_conquered)));
+        engine.check_invariants().unwrap();
+    }
+
+    #[test]
+    fn test_sieges_starve_fortified_garrisons_unless_relieved() {
+        let besieged = || {
+            let (mut engine, player, from, to) = setup();
+            engine.rules.siege_ticks = 20;
+            engine.rules.siege_attrition_per_second = 1.0;
+            engine.rules.neutral_fortify_per_second = 0.0;
+            engine.rules.neutral_merge_chance = 0.0;
+            engine.get_territory_mut(to).unwrap().building = Some(BuildingType::DefensePost);
+            (engine, player, from, to)
+        };
+
+        // Even with attacks settled at once, a Defense Post is besieged first
+        let (mut engine, player, from, to) = besieged();
+        assert!(engine.launch_attack(player, from, to).unwrap().is_none());
+        for _ in 0..10 {
+            engine.tick();
+        }
+        let battle = engine.state.battles[0].clone();
+        assert_eq!(battle.siege_ticks_left, 10);
+        assert!(battle.attacker_losses.is_empty());
+        let garrison = engine.get_territory(to).unwrap().troops();
+        assert!(garrison < 100 && garrison + battle.defender_losses.total() == 100, "garrison {}", garrison);
+        engine.audit_troops(player).unwrap();
+
+        // Starved out, the territory falls without a fight
+        engine.rules.siege_attrition_per_second = 10.0;
+        engine.tick();
+        assert!(engine.state.battles.is_empty());
+        assert_eq!(engine.get_territory(to).unwrap().owner, Some(player.into()));
+        engine.check_invariants().unwrap();
+
+        // A garrison its owner can supply from next door holds out
+        let (mut engine, player, from, to) = besieged();
+        let rival = engine.state.players[1].id;
+        for territory in &mut engine.state.territories {
+            if territory.id == Uuid::from(to) || (territory.neighbors.contains(&to.into()) && territory.id != Uuid::from(from)) {
+                territory.owner = Some(rival);
+            }
+        }
+        engine.launch_attack(player, from, to).unwrap();
+        for _ in 0..10 {
+            engine.tick();
+        }
+        assert!(engine.state.battles[0].defender_losses.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+//! Buildings changing hands.
+//!
+//! A building's effects follow whoever holds its territory. The moment a
+//! territory changes hands the old owner's population cap shrinks, and any
+//! people above the new cap drift away over the following seconds. A
+//! conquered building is out of action for `building_capture_ticks` before
+//! it works for the conqueror, or is razed in the fighting under
+//! `CapturedBuildings::Raze`; gifted territories come with their buildings in
+//! working order. Owners may also demolish their own buildings, to deny them
+//! to an attacker or to make room for another.
+
+use anyhow::{anyhow, Result};
+
+use crate::types::*;
+use super::{CapturedBuildings, GameEngine};
+
+impl GameEngine {
+    /// Settle the building on a territory that just changed hands, by
+    /// conquest if `captured`
+    pub(super) fn building_changed_hands(&mut self, territory_id: TerritoryId, captured: bool) -> Result<()> {
+        let tick = self.state.tick;
+        let (rule, idle_ticks) = (self.rules.captured_buildings, self.rules.building_capture_ticks);
+        let territory = self.get_territory_mut(territory_id)?;
+        if captured && territory.building.is_some() {
+            match rule {
+                CapturedBuildings::Keep => territory.building_idle_until = tick.saturating_add(idle_ticks),
+                CapturedBuildings::Raze => clear_building(territory),
+            }
+        }
+        self.update_population_caps();
+        Ok(())
+    }
+
+    /// Tear down a building on the player's own territory, with no refund
+    pub fn demolish_building(&mut self, player_id: PlayerId, territory_id: TerritoryId) -> Result<()> {
+        let territory = self.get_territory_mut(territory_id)?;
+        if territory.owner != Some(player_id.into()) {
+            return Err(anyhow!("You don't own this territory"));
+        }
+        if territory.building.is_none() {
+            return Err(anyhow!("There is no building to demolish"));
+        }
+        clear_building(territory);
+        self.update_population_caps();
+        Ok(())
+    }
+}
+
+fn clear_building(territory: &mut Territory) {
+    territory.building = None;
+    territory.building_level = 1;
+    territory.building_idle_until = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Two players, the second also holding a City on what was neutral land
+    fn city_held_by_second_player() -> (GameEngine, PlayerId, PlayerId, TerritoryId) {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let (a, b) = (engine.state.players[0].id, engine.state.players[1].id);
+        let city = engine.state.territories
+            .iter()
+            .position(|t| t.owner.is_none() && t.terrain != TerrainType::Water)
+            .unwrap();
+        engine.state.territories[city].owner = Some(b);
+        engine.state.territories[city].building = Some(BuildingType::City);
+        engine.tick();
+        let city = engine.state.territories[city].id.into();
+        (engine, a.into(), b.into(), city)
+    }
+
+    fn cap(engine: &GameEngine, player: PlayerId) -> u64 {
+        engine.get_player(player).unwrap().max_population
+    }
+
+    #[test]
+    fn test_captured_buildings_leave_the_loser_and_idle_for_the_winner() {
+        let (mut engine, a, b, city) = city_held_by_second_player();
+        let city_bonus = BuildingType::City.max_population_bonus(1);
+        let per_territory = engine.rules.population_cap_per_territory;
+        let (a_cap, b_cap) = (cap(&engine, a), cap(&engine, b));
+        // Half workers, whatever the AI's personality, so some are free to leave
+        let loser = engine.get_player_mut(b).unwrap();
+        loser.population = b_cap;
+        loser.trained_ratio = 0.5;
+
+        engine.conquer(a, Some(b.into()), city, Units::infantry(10)).unwrap();
+        assert_eq!(cap(&engine, b), b_cap - per_territory - city_bonus);
+        assert_eq!(cap(&engine, a), a_cap + per_territory);
+        assert_eq!(engine.get_territory(city).unwrap().working_building(engine.state.tick), None);
+
+        // The loser's surplus people drift away rather than vanish at once
+        engine.tick();
+        let population = engine.get_player(b).unwrap().population;
+        assert!(population < b_cap && population > cap(&engine, b));
+
+        // Once back in working order, the City counts for the conqueror
+        for _ in 0..engine.rules.building_capture_ticks {
+            engine.tick();
+        }
+        assert_eq!(cap(&engine, a), a_cap + per_territory + city_bonus);
+    }
+
+    #[test]
+    fn test_razed_and_demolished_buildings_are_gone() {
+        let (mut engine, a, b, city) = city_held_by_second_player();
+        engine.rules.captured_buildings = CapturedBuildings::Raze;
+        let a_cap = cap(&engine, a);
+        engine.conquer(a, Some(b.into()), city, Units::infantry(10)).unwrap();
+        assert_eq!(engine.get_territory(city).unwrap().building, None);
+        assert_eq!(cap(&engine, a), a_cap + engine.rules.population_cap_per_territory);
+
+        // Demolishing is for the owner only, and frees the plot for something else
+        assert_eq!(engine.demolish_building(a, city).unwrap_err().to_string(), "There is no building to demolish");
+        engine.get_territory_mut(city).unwrap().building = Some(BuildingType::City);
+        engine.get_territory_mut(city).unwrap().building_level = 2;
+        engine.tick();
+        let a_cap = cap(&engine, a);
+        assert!(engine.demolish_building(b, city).is_err());
+        engine.demolish_building(a, city).unwrap();
+        assert_eq!(cap(&engine, a), a_cap - BuildingType::City.max_population_bonus(2));
+        assert_eq!(engine.get_territory(city).unwrap().building_level, 1);
+        engine.get_player_mut(a).unwrap().gold = 1_000;
+        engine.build_structure(a, city, BuildingType::Farm).unwrap();
+    }
+}
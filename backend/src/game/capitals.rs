@@ -0,0 +1,88 @@
+//! Capitals.
+//!
+//! Every player's starting territory is their capital. Held by its player it
+//! defends better and produces more gold. Losing it costs whatever the
+//! `capital_loss` rule says: only those bonuses, half the player's income
+//! until they win it back, or the game.
+
+use uuid::Uuid;
+
+use crate::types::*;
+use super::{CapitalLoss, GameEngine};
+
+impl GameEngine {
+    /// A player's capital, whoever holds it now
+    pub fn capital(&self, player_id: PlayerId) -> Option<&Territory> {
+        self.state.territories.iter().find(|t| t.capital_of == Some(player_id.into()))
+    }
+
+    /// Whether someone else holds the player's capital
+    pub fn capital_lost(&self, player_id: PlayerId) -> bool {
+        self.capital(player_id).is_some_and(|t| t.owner != t.capital_of)
+    }
+
+    /// Note a conquered capital and apply the capital loss rule to its player
+    pub(super) fn capital_taken(&mut self, territory_id: TerritoryId, conqueror: PlayerId) {
+        let Ok(territory) = self.get_territory(territory_id) else { return };
+        let Some(victim) = territory.capital_of.filter(|&victim| victim != Uuid::from(conqueror)) else { return };
+        let territory_name = territory.name.clone();
+        let name = |engine: &Self, id: PlayerId| engine.get_player(id).map(|p| p.name.clone()).unwrap_or_default();
+
+        let params = [("attacker", name(self, conqueror)), ("territory", territory_name), ("defender", name(self, victim.into()))];
+        let entities = [
+            EntityRef::Player { id: conqueror.into() },
+            EntityRef::Territory { id: territory_id.into() },
+            EntityRef::Player { id: victim },
+        ];
+        self.events.push(
+            ServerMessage::notification(NotificationKey::CapitalCaptured, NotificationLevel::Warning, params).about(entities),
+        );
+
+        if self.rules.capital_loss == CapitalLoss::Eliminate {
+            self.eliminate_player(victim.into(), Some(conqueror.into()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Player 0 next to the undefended capital of player 1, who holds one more territory
+    fn border_war(rule: CapitalLoss) -> (GameEngine, PlayerId, PlayerId, Uuid, Uuid) {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate_seeded(4), 100);
+        engine.rules.capital_loss = rule;
+        let (a, b): (PlayerId, PlayerId) = (engine.state.players[0].id.into(), engine.state.players[1].id.into());
+        for player in [a, b] {
+            assert_eq!(engine.capital(player).unwrap().owner, Some(player.into()));
+        }
+
+        let capital = engine.capital(b).unwrap().clone();
+        let outpost = capital.neighbors[0];
+        let spare = engine.state.territories.iter().find(|t| t.owner.is_none() && !capital.neighbors.contains(&t.id) && t.id != capital.id).unwrap().id;
+        engine.get_territory_mut(outpost.into()).unwrap().owner = Some(a.into());
+        engine.get_territory_mut(spare.into()).unwrap().owner = Some(b.into());
+        engine.get_territory_mut(capital.id.into()).unwrap().units = Units::infantry(0);
+        engine.tick();
+        (engine, a, b, outpost, capital.id)
+    }
+
+    #[test]
+    fn test_taking_a_capital_applies_the_rule() {
+        let mut income = Vec::new();
+        for rule in [CapitalLoss::Nothing, CapitalLoss::HalveIncome, CapitalLoss::Eliminate] {
+            let (mut engine, a, b, outpost, capital) = border_war(rule);
+            assert!(engine.execute_attack(a, outpost.into(), capital.into()).unwrap().territory_conquered);
+            assert!(engine.capital_lost(b));
+            assert_eq!(engine.get_territory(capital.into()).unwrap().capital_of, Some(b.into()));
+
+            engine.tick();
+            let victim = engine.get_player(b).unwrap();
+            assert_eq!(victim.is_alive, rule != CapitalLoss::Eliminate);
+            income.push(victim.gold_per_second);
+        }
+        assert!((income[1] - income[0] * 0.5).abs() < 0.01, "{:?}", income);
+        assert_eq!(income[2], 0.0);
+    }
+}
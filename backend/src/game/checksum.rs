@@ -0,0 +1,217 @@
+//! Cheap fingerprint of the simulation state for desync detection.
+//!
+//! The checksum is a 32-bit FNV-1a hash, small enough to travel as a JSON
+//! number JavaScript reads exactly. It covers every field the simulation
+//! decides, in state order: the tick and game time, then each territory's id,
+//! owner, building, building level, idle tick and troops by unit type, then
+//! each player's id, liveness, population, cap, trained share, gold, food,
+//! techs and research, then diplomacy, victory progress and battles.
+//!
+//! Integers are hashed little-endian, floats as their bits, uuids as their 16
+//! bytes and enums as one byte (1 + their position in the type), a missing
+//! value as zeroes and a list after its length. Cosmetic fields, player
+//! settings and the rates derived for display are left out, so any client can
+//! recompute it from a state update.
+
+use crate::types::*;
+use uuid::Uuid;
+
+const FNV_OFFSET_BASIS: u32 = 0x811c_9dc5;
+const FNV_PRIME: u32 = 0x0100_0193;
+
+struct Fnv(u32);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u32::from(*byte);
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.u32(value.to_bits());
+    }
+
+    fn uuid(&mut self, id: Option<Uuid>) {
+        self.write(id.unwrap_or_default().as_bytes());
+    }
+
+    fn tag(&mut self, tag: Option<u8>) {
+        self.write(&[tag.map_or(0, |t| t + 1)]);
+    }
+
+    fn len(&mut self, len: usize) {
+        self.u32(len as u32);
+    }
+
+    fn units(&mut self, units: &Units) {
+        self.u32(units.infantry);
+        self.u32(units.cavalry);
+        self.u32(units.siege);
+    }
+}
+
+impl GameState {
+    pub fn checksum(&self) -> u32 {
+        let mut hash = Fnv(FNV_OFFSET_BASIS);
+        hash.u64(self.tick);
+        hash.u32(self.game_time_seconds);
+
+        hash.len(self.territories.len());
+        for territory in &self.territories {
+            hash.uuid(Some(territory.id));
+            hash.uuid(territory.owner);
+            hash.tag(territory.building.map(|b| b as u8));
+            hash.write(&[territory.building_level]);
+            hash.u64(territory.building_idle_until);
+            hash.units(&territory.units);
+        }
+
+        hash.len(self.players.len());
+        for player in &self.players {
+            hash.uuid(Some(player.id));
+            hash.write(&[player.is_alive as u8]);
+            hash.u64(player.population);
+            hash.u64(player.max_population);
+            hash.f32(player.trained_ratio);
+            hash.u64(player.gold);
+            hash.u64(player.food);
+            hash.u32(player.territories_controlled);
+            hash.len(player.techs.len());
+            for tech in &player.techs {
+                hash.tag(Some(*tech as u8));
+            }
+            hash.tag(player.research.map(|r| r.tech as u8));
+            hash.f32(player.research.map_or(0.0, |r| r.progress));
+        }
+
+        let diplomacy = &self.diplomacy;
+        hash.len(diplomacy.treaties.len());
+        for treaty in &diplomacy.treaties {
+            treaty.players.iter().for_each(|&p| hash.uuid(Some(p)));
+            hash.u64(treaty.signed_tick);
+        }
+        hash.len(diplomacy.offers.len());
+        for offer in &diplomacy.offers {
+            hash.uuid(Some(offer.from));
+            hash.uuid(Some(offer.to));
+        }
+        hash.len(diplomacy.truces.len());
+        for truce in &diplomacy.truces {
+            truce.players.iter().for_each(|&p| hash.uuid(Some(p)));
+            hash.u64(truce.remaining_ticks);
+        }
+
+        hash.len(self.victory.len());
+        for progress in &self.victory {
+            hash.len(progress.condition.len());
+            hash.write(progress.condition.as_bytes());
+            hash.uuid(Some(progress.player_id));
+            hash.u32(progress.current);
+            hash.u32(progress.target);
+        }
+
+        hash.len(self.battles.len());
+        for battle in &self.battles {
+            hash.u64(battle.id);
+            hash.uuid(Some(battle.attacker_id));
+            hash.uuid(battle.defender_id);
+            hash.uuid(Some(battle.from_territory));
+            hash.uuid(Some(battle.territory));
+            hash.units(&battle.attackers);
+            hash.units(&battle.committed);
+            hash.units(&battle.defenders);
+            hash.units(&battle.attacker_losses);
+            hash.units(&battle.defender_losses);
+            hash.u64(battle.started_tick);
+            hash.u32(battle.rounds_left);
+            hash.u64(battle.siege_ticks_left);
+        }
+        hash.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::MapGenerator;
+    use crate::types::*;
+
+    #[test]
+    fn test_checksum_follows_the_simulation_only() {
+        let state = MapGenerator::new(20, 3).generate();
+        assert_eq!(state.checksum(), state.clone().checksum());
+
+        let mut moved = state.clone();
+        moved.territories[0].units.infantry += 1;
+        assert_ne!(moved.checksum(), state.checksum());
+
+        let mut ticked = state.clone();
+        ticked.tick += 1;
+        assert_ne!(ticked.checksum(), state.checksum());
+
+        // Settings and cosmetics don't count
+        let mut renamed = state.clone();
+        renamed.players[0].name = "Renamed".to_string();
+        renamed.players[0].troop_ratio = 0.9;
+        renamed.players[0].latency_ms = Some(40);
+        assert_eq!(renamed.checksum(), state.checksum());
+    }
+
+    #[test]
+    fn test_checksum_covers_every_simulated_field() {
+        let mut state = MapGenerator::new(20, 3).generate();
+        state.territories[0].units = Units::infantry(5);
+        let changes: Vec<fn(&mut GameState)> = vec![
+            |s| s.territories[0].building_level += 1,
+            |s| s.territories[0].building_idle_until += 1,
+            // Same troop count, different unit mix
+            |s| s.territories[0].units = Units { cavalry: 5, ..Units::default() },
+            |s| s.players[0].food += 1,
+            |s| s.players[0].trained_ratio += 0.1,
+            |s| s.players[0].techs.push(Tech::Drill),
+            |s| s.players[0].research = Some(ResearchProgress { tech: Tech::Masonry, progress: 0.5 }),
+            |s| s.game_time_seconds += 1,
+            |s| {
+                let (a, b) = (s.players[0].id, s.players[1].id);
+                s.diplomacy.truces.push(Truce { players: [a, b], remaining_ticks: 10 });
+            },
+            |s| {
+                let (a, b) = (s.players[0].id, s.players[1].id);
+                s.victory.push(VictoryProgress { condition: "domination".to_string(), player_id: a, current: 1, target: 2 });
+                s.victory.push(VictoryProgress { condition: "domination".to_string(), player_id: b, current: 1, target: 2 });
+            },
+            |s| {
+                s.battles.push(Battle {
+                    id: 1,
+                    attacker_id: s.players[0].id,
+                    defender_id: None,
+                    from_territory: s.territories[0].id,
+                    territory: s.territories[1].id,
+                    attackers: Units::infantry(5),
+                    committed: Units::infantry(5),
+                    defenders: Units::default(),
+                    attacker_losses: Units::default(),
+                    defender_losses: Units::default(),
+                    started_tick: 0,
+                    rounds_left: 3,
+                    siege_ticks_left: 0,
+                })
+            },
+        ];
+
+        for (i, change) in changes.iter().enumerate() {
+            let mut changed = state.clone();
+            change(&mut changed);
+            assert_ne!(changed.checksum(), state.checksum(), "change {} went unnoticed", i);
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use anyhow::{anyhow, Result};
+use std::time::Instant;
 use uuid::Uuid;
 
 use crate::types::*;
+use super::combat_model::{casualties, Engagement};
+use super::plugins::{AttackIntent, HookOutcome};
 use super::GameEngine;
 
 impl GameEngine {
@@ -12,19 +15,115 @@ impl GameEngine {
         from_territory: TerritoryId,
         to_territory: TerritoryId,
     ) -> Result<CombatResult> {
+        let started = Instant::now();
+        let result = self.resolve_attack(attacker_id, from_territory, to_territory);
+        self.perf.combat.record(started.elapsed());
+        if let Ok(result) = &result {
+            self.record_battle_replay(result);
+        }
+        result
+    }
+
+    fn resolve_attack(
+        &mut self,
+        attacker_id: PlayerId,
+        from_territory: TerritoryId,
+        to_territory: TerritoryId,
+    ) -> Result<CombatResult> {
+        let defender_id = self.check_attack(attacker_id, from_territory, to_territory)?;
+
+        // Garrisons are only topped up once a tick; before drawing on them, give
+        // up any troops that no longer exist in either army since then
+        self.settle_garrisons(attacker_id);
+        if let Some(defender_player_id) = defender_id {
+            self.settle_garrisons(defender_player_id.into());
+        }
+
+        let attacker_units = self.muster(attacker_id, from_territory, to_territory)?;
+        let attacker_troops = attacker_units.total();
+        let defender_units = self.get_territory(to_territory)?.units;
+        let defender_troops = defender_units.total();
+
+        // Calculate combat result; neither side can lose more than it put in
+        let (attacker_unit_losses, defender_unit_losses, territory_conquered) =
+            self.calculate_combat(
+                attacker_id,
+                attacker_units,
+                defender_units,
+                from_territory,
+                to_territory,
+            );
+        let (attacker_losses, defender_losses) = (attacker_unit_losses.total(), defender_unit_losses.total());
+
+        // Fallen troops come out of each side's army
+        self.get_player_mut(attacker_id)?.lose_troops(attacker_losses as u64);
+        if let Some(defender_player_id) = defender_id {
+            self.get_player_mut(defender_player_id.into())?.lose_troops(defender_losses as u64);
+        }
+
+        // The committed stack leaves the origin; survivors occupy the target or march back
+        let mut survivors = attacker_units;
+        survivors.remove(attacker_unit_losses);
+        let from = self.get_territory_mut(from_territory)?;
+        from.units.remove(attacker_units);
+        if !territory_conquered {
+            from.units.add(survivors);
+        }
+
+        if territory_conquered {
+            self.conquer(attacker_id, defender_id, to_territory, survivors)?;
+        } else {
+            self.get_territory_mut(to_territory)?.units.remove(defender_unit_losses);
+        }
+
+        debug_assert!(self.audit_troops(attacker_id).is_ok(), "{:?}", self.audit_troops(attacker_id));
+        if let Some(defender_player_id) = defender_id {
+            debug_assert!(self.audit_troops(defender_player_id.into()).is_ok(), "{:?}", self.audit_troops(defender_player_id.into()));
+            self.provoke(defender_player_id);
+        }
+        self.record_battle(attacker_id, defender_id, to_territory, attacker_troops.saturating_add(defender_troops), territory_conquered);
+
+        Ok(CombatResult {
+            attacker_id: attacker_id.into(),
+            defender_id: defender_id.unwrap_or(Uuid::nil()), // Use nil UUID for neutral
+            from_territory: from_territory.into(),
+            to_territory: to_territory.into(),
+            attacker_troops_committed: attacker_troops,
+            defender_troops,
+            attacker_losses,
+            defender_losses,
+            territory_conquered,
+            attacker_units,
+            defender_units,
+            attacker_unit_losses,
+            defender_unit_losses,
+        })
+    }
+
+    /// Whether the player may attack from one territory to the other,
+    /// returning who holds the target
+    pub(super) fn check_attack(
+        &self,
+        attacker_id: PlayerId,
+        from_territory: TerritoryId,
+        to_territory: TerritoryId,
+    ) -> Result<Option<Uuid>> {
         // Validate attacker owns the from territory
         let from = self.get_territory(from_territory)?;
         if from.owner != Some(attacker_id.into()) {
             return Err(anyhow!("You don't own the attacking territory"));
         }
 
-        // Validate territories are neighbors
-        if !from.neighbors.contains(&to_territory.into()) {
-            return Err(anyhow!("Territories are not neighbors"));
-        }
-
         // Get defender
         let to = self.get_territory(to_territory)?;
+        if to.terrain == TerrainType::Water && !self.rules.passable_water {
+            return Err(anyhow!("Water can't be held; attack across it from a Harbor"));
+        }
+
+        // Validate territories are neighbors, or a Harbor's crossing apart
+        if !from.neighbors.contains(&to_territory.into()) && !self.naval_targets(from_territory).contains(&to_territory.into()) {
+            return Err(anyhow!("Territories are not neighbors"));
+        }
 
         // Check if attacking own territory
         if to.owner == Some(Into::<Uuid>::into(attacker_id)) {
@@ -32,124 +131,459 @@ impl GameEngine {
         }
 
         let defender_id = to.owner; // Can be None for neutral territories
+        if defender_id.is_some_and(|defender| self.state.diplomacy.has_treaty(attacker_id.into(), defender)) {
+            return Err(anyhow!("You have a treaty with this player"));
+        }
+        if let Some(remaining) = defender_id.and_then(|defender| self.state.diplomacy.truce_remaining(attacker_id.into(), defender)) {
+            return Err(anyhow!("Your truce with this player ends in {} ticks", remaining));
+        }
+        Ok(defender_id)
+    }
 
-        // Calculate attacking force
+    /// The stack the player's attack ratio sends from a territory, limited to
+    /// what is stationed there and drawn from every unit type alike; rule
+    /// plugins may still call the attack off
+    pub(super) fn muster(&mut self, attacker_id: PlayerId, from_territory: TerritoryId, to_territory: TerritoryId) -> Result<Units> {
+        let from = self.get_territory(from_territory)?;
         let attacker = self.get_player(attacker_id)?;
         let total_attacker_troops = attacker.troops();
-        let attacker_troops = (total_attacker_troops as f32 * attacker.attack_ratio) as u32;
+        let attacker_troops = (total_attacker_troops as f64 * attacker.attack_ratio as f64) as u64;
+        let attacker_troops = u32::try_from(attacker_troops).unwrap_or(u32::MAX).min(from.troops());
 
         if attacker_troops == 0 {
             return Err(anyhow!("No troops available to attack"));
         }
+        let units = from.units.portion(attacker_troops);
 
-        // Get defender troops
-        let defender_troops = to.troops;
-
-        // Calculate combat result
-        let (attacker_losses, defender_losses, territory_conquered) =
-            self.calculate_combat(
-                attacker_troops,
-                defender_troops,
-                to_territory,
-            );
+        let intent = AttackIntent {
+            attacker: attacker_id.into(),
+            from: from_territory.into(),
+            to: to_territory.into(),
+            troops: attacker_troops,
+        };
+        if let HookOutcome::Veto(reason) = self.plugins.on_attack(&self.state, &intent) {
+            return Err(anyhow!(reason));
+        }
+        Ok(units)
+    }
 
-        // Apply losses to attacker
-        let attacker = self.get_player_mut(attacker_id)?;
-        attacker.population = attacker.population.saturating_sub(attacker_losses);
+    /// Hand a territory to the attacker, garrisoned by the surviving stack
+    pub(super) fn conquer(&mut self, attacker_id: PlayerId, defender_id: Option<Uuid>, territory: TerritoryId, occupiers: Units) -> Result<()> {
+        let to = self.get_territory_mut(territory)?;
+        to.owner = Some(attacker_id.into());
+        to.units = occupiers;
 
-        // Apply losses to defender (if they have an owner)
+        // Keep territory counts current even when no tick runs in between (e.g. while paused)
+        self.get_player_mut(attacker_id)?.territories_controlled += 1;
         if let Some(defender_player_id) = defender_id {
             let defender = self.get_player_mut(defender_player_id.into())?;
-            defender.population = defender.population.saturating_sub(defender_losses);
+            defender.territories_controlled = defender.territories_controlled.saturating_sub(1);
+            self.record_conquest(defender_player_id, attacker_id.into());
         }
+        self.building_changed_hands(territory, true)?;
+        self.capital_taken(territory, attacker_id);
+        Ok(())
+    }
 
-        // Update territory
-        let to = self.get_territory_mut(to_territory)?;
+    /// Count the battle, announce captured buildings and keep the battle if it is the biggest so far
+    pub(super) fn record_battle(
+        &mut self,
+        attacker_id: PlayerId,
+        defender_id: Option<Uuid>,
+        territory: TerritoryId,
+        troops_involved: u32,
+        territory_conquered: bool,
+    ) {
+        self.battles += 1;
+        self.record_territory_battle(territory.into(), troops_involved, territory_conquered);
+        let name = |engine: &Self, id: PlayerId| engine.get_player(id).map(|p| p.name.clone()).unwrap_or_default();
+        let territory_name = self.get_territory(territory).map(|t| t.name.clone()).unwrap_or_default();
 
-        if territory_conquered {
-            to.owner = Some(attacker_id.into());
-            to.troops = attacker_troops - attacker_losses;
-        } else {
-            to.troops = defender_troops.saturating_sub(defender_losses);
+        // Losing a developed territory to another player is worth telling everyone
+        let building = self.get_territory(territory).ok().and_then(|t| t.building);
+        if let (true, Some(defender), Some(building)) = (territory_conquered, defender_id, building) {
+            let params = [
+                ("attacker", name(self, attacker_id)),
+                ("territory", territory_name.clone()),
+                ("building", crate::i18n::building_param(building)),
+                ("defender", name(self, defender.into())),
+            ];
+            let entities = [
+                EntityRef::Player { id: attacker_id.into() },
+                EntityRef::Territory { id: territory.into() },
+                EntityRef::Player { id: defender },
+            ];
+            self.events.push(
+                ServerMessage::notification(NotificationKey::TerritoryCaptured, NotificationLevel::Info, params).about(entities),
+            );
         }
 
-        Ok(CombatResult {
-            attacker_id: attacker_id.into(),
-            defender_id: defender_id.unwrap_or(Uuid::nil()), // Use nil UUID for neutral
-            from_territory: from_territory.into(),
-            to_territory: to_territory.into(),
-            attacker_troops_committed: attacker_troops,
-            defender_troops,
-            attacker_losses,
-            defender_losses,
+        if self.biggest_battle.as_ref().is_some_and(|b| b.troops_involved >= troops_involved) {
+            return;
+        }
+        self.biggest_battle = Some(BattleHighlight {
+            attacker_name: name(self, attacker_id),
+            defender_name: defender_id.map(|id| name(self, id.into())),
+            territory_id: territory.into(),
+            territory_name,
+            troops_involved,
             territory_conquered,
-        })
+            game_time_seconds: self.state.game_time_seconds,
+        });
     }
 
-    /// Calculate combat outcome based on troop counts and modifiers
-    fn calculate_combat(
-        &self,
-        attacker_troops: u32,
-        defender_troops: u32,
+    /// Calculate combat outcome with the game's combat model, after unit
+    /// types, terrain and building modifiers
+    pub(super) fn calculate_combat(
+        &mut self,
+        attacker_id: PlayerId,
+        attacker_units: Units,
+        defender_units: Units,
+        attacker_territory: TerritoryId,
         defender_territory: TerritoryId,
-    ) -> (u32, u32, bool) {
+    ) -> (Units, Units, bool) {
         // Get terrain and building bonuses
         let territory = self.get_territory(defender_territory).unwrap();
         let mut defense_multiplier = territory.terrain.defense_multiplier();
 
-        if let Some(building) = territory.building {
-            defense_multiplier *= building.defense_multiplier();
-        }
-
-        // Base combat formula from design doc
-        let (base_attacker_losses, base_defender_losses) = if attacker_troops > defender_troops {
-            // Attacker wins
-            let attacker_losses = (defender_troops as f32 * 0.3) as u32;
-            let defender_losses = defender_troops;
-            (attacker_losses, defender_losses)
-        } else if attacker_troops < defender_troops {
-            // Defender wins
-            let attacker_losses = attacker_troops;
-            let defender_losses = (attacker_troops as f32 * 0.5) as u32;
-            (attacker_losses, defender_losses)
+        if let Some(building) = territory.working_building(self.state.tick) {
+            defense_multiplier *= building.defense_multiplier(territory.building_level);
+        }
+        if territory.capital_of.is_some() && territory.capital_of == territory.owner {
+            defense_multiplier *= self.rules.capital_defense_multiplier;
+        }
+
+        // Iron arms the attacker
+        let origin = self.get_territory(attacker_territory).unwrap();
+        if self.deposits_held(attacker_id, ResourceType::Iron) > 0 {
+            defense_multiplier *= 1.0 + self.rules.iron_attack_bonus;
+        }
+        // So does research
+        defense_multiplier *= 1.0 + self.tech_bonus(attacker_id, Tech::attack_bonus);
+
+        // An unbridged river on the border favors the defender
+        let bridged = origin.building == Some(BuildingType::Bridge) || territory.building == Some(BuildingType::Bridge);
+        if territory.river_to(origin.id) && !bridged {
+            defense_multiplier *= self.rules.river_defense_multiplier;
+        }
+
+        let engagement = Engagement::between(&attacker_units, &defender_units, defense_multiplier)
+            .with_variance(self.rules.combat_variance, &mut self.rng);
+        let outcome = self.rules.combat().resolve(&engagement, &mut self.rng);
+
+        // Losses come back as strength; spread them over the soldiers that gave it
+        let attacker_losses = casualties(&attacker_units, outcome.attacker_losses, engagement.attacker_troops);
+        let defender_losses = if outcome.territory_conquered {
+            defender_units
         } else {
-            // Equal forces
-            let attacker_losses = (attacker_troops as f32 * 0.7) as u32;
-            let defender_losses = (defender_troops as f32 * 0.7) as u32;
-            (attacker_losses, defender_losses)
+            casualties(&defender_units, outcome.defender_losses, engagement.defender_troops)
         };
+        (attacker_losses, defender_losses, outcome.territory_conquered)
+    }
 
-        // Apply defense multiplier (reduces defender losses)
-        let defender_losses = (base_defender_losses as f32 * defense_multiplier) as u32;
-        let attacker_losses = base_attacker_losses;
-
-        // Territory is conquered if defender loses all troops
-        let territory_conquered = defender_troops <= defender_losses;
+    /// Move troops between two neighboring territories of the player's. The
+    /// stack stays where it was sent: reinforcements are only ever added on
+    /// top, and a shrinking army draws on every garrison alike
+    pub fn move_troops(&mut self, player_id: PlayerId, from_territory: TerritoryId, to_territory: TerritoryId, amount: u32) -> Result<()> {
+        let from = self.get_territory(from_territory)?;
+        let to = self.get_territory(to_territory)?;
+        if from.owner != Some(player_id.into()) || to.owner != Some(player_id.into()) {
+            return Err(anyhow!("You can only move troops between your own territories"));
+        }
+        if !from.neighbors.contains(&to_territory.into()) {
+            return Err(anyhow!("Territories are not neighbors"));
+        }
+        if amount == 0 {
+            return Err(anyhow!("Move at least one troop"));
+        }
 
-        (attacker_losses, defender_losses, territory_conquered)
+        // Only troops that still exist can march
+        self.settle_garrisons(player_id);
+        let from = self.get_territory_mut(from_territory)?;
+        if amount > from.troops() {
+            return Err(anyhow!("Only {} troops are stationed there", from.troops()));
+        }
+        let moved = from.units.portion(amount);
+        from.units.remove(moved);
+        self.get_territory_mut(to_territory)?.units.add(moved);
+        Ok(())
     }
 
-    /// Distribute troops across all player territories
+    /// Reconcile garrisons with the player's army: newly trained troops are
+    /// spread evenly, and a shrinking army is drawn from every garrison in proportion
     pub fn distribute_troops(&mut self, player_id: PlayerId) {
         let player = match self.get_player(player_id) {
             Ok(p) => p,
             Err(_) => return,
         };
 
-        let total_troops = player.troops();
-        let territory_count = player.territories_controlled;
+        let mix = player.unit_mix;
+        // Troops away fighting are part of the army but of no garrison
+        let army = player.troops();
+        let total_troops = army - self.settle_battles(player_id, army);
+        let owned: Vec<usize> = self.state.territories
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.owner == Some(player_id.into()))
+            .map(|(idx, _)| idx)
+            .collect();
 
-        if territory_count == 0 {
+        if owned.is_empty() {
             return;
         }
 
-        let troops_per_territory = total_troops / territory_count;
+        let stationed: u64 = owned.iter().map(|&idx| self.state.territories[idx].troops() as u64).sum();
+
+        if stationed > total_troops {
+            self.shrink_garrisons(&owned, stationed, total_troops);
+        } else {
+            let reinforcements = total_troops - stationed;
+            let per_territory = reinforcements / owned.len() as u64;
+            let remainder = (reinforcements % owned.len() as u64) as usize;
 
-        // Update all territories owned by this player
-        for territory in &mut self.state.territories {
-            if territory.owner == Some(player_id.into()) {
-                territory.troops = troops_per_territory;
+            for (i, &idx) in owned.iter().enumerate() {
+                let extra = per_territory + u64::from(i < remainder);
+                let garrison = &mut self.state.territories[idx].units;
+                let room = u32::MAX - garrison.total();
+                garrison.add_mixed(u32::try_from(extra).unwrap_or(u32::MAX).min(room), &mix);
             }
         }
     }
+
+    /// Draw a shrunken army from every garrison in proportion, leaving
+    /// reinforcements for the next `distribute_troops`
+    pub(super) fn settle_garrisons(&mut self, player_id: PlayerId) {
+        let Ok(player) = self.get_player(player_id) else { return };
+        let army = player.troops();
+        let total_troops = army - self.settle_battles(player_id, army);
+        let owned: Vec<usize> = self.state.territories
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.owner == Some(player_id.into()))
+            .map(|(idx, _)| idx)
+            .collect();
+        let stationed: u64 = owned.iter().map(|&idx| self.state.territories[idx].troops() as u64).sum();
+        if stationed > total_troops {
+            self.shrink_garrisons(&owned, stationed, total_troops);
+        }
+    }
+
+    fn shrink_garrisons(&mut self, owned: &[usize], stationed: u64, total_troops: u64) {
+        let mut kept: Vec<u32> = owned
+            .iter()
+            .map(|&idx| (self.state.territories[idx].troops() as u128 * total_troops as u128 / stationed as u128) as u32)
+            .collect();
+        // Hand back what rounding down took
+        let lost_to_rounding = total_troops - kept.iter().map(|&k| k as u64).sum::<u64>();
+        for k in kept.iter_mut().take(lost_to_rounding as usize) {
+            *k += 1;
+        }
+        // Every unit type shrinks alike
+        for (&idx, keep) in owned.iter().zip(kept) {
+            let garrison = &mut self.state.territories[idx].units;
+            *garrison = garrison.portion(keep);
+            garrison.infantry += keep - garrison.total();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Engine with the human's start at index 0, adjacent to a neutral at index 1
+    fn setup(origin_troops: u32, target_troops: u32) -> (GameEngine, PlayerId, TerritoryId, TerritoryId) {
+        let mut state = MapGenerator::new(20, 2).generate();
+        let player = state.players[0].id;
+        for territory in &mut state.territories {
+            territory.owner = None;
+        }
+        let (from, to) = (state.territories[0].id, state.territories[1].id);
+        state.territories[0].owner = Some(player);
+        state.territories[0].units = Units::infantry(origin_troops);
+        state.territories[1].units = Units::infantry(target_troops);
+        state.territories[1].terrain = TerrainType::Plains;
+        state.territories[1].building = None;
+        if !state.territories[0].neighbors.contains(&to) {
+            state.territories[0].neighbors.push(to);
+            state.territories[1].neighbors.push(from);
+        }
+
+        let mut engine = GameEngine::new(state, 100);
+        let human = engine.get_player_mut(player.into()).unwrap();
+        human.population = 1_000;
+        human.troop_ratio = 0.5;
+        human.trained_ratio = 0.5;
+        human.attack_ratio = 0.5;
+        (engine, player.into(), from.into(), to.into())
+    }
+
+    #[test]
+    fn test_survivors_leave_origin_and_occupy_target() {
+        let (mut engine, player, from, to) = setup(500, 100);
+        let workers = engine.get_player(player).unwrap().workers();
+
+        let result = engine.execute_attack(player, from, to).unwrap();
+
+        assert!(result.territory_conquered);
+        assert_eq!(result.attacker_troops_committed, 250);
+        assert_eq!(engine.get_territory(from).unwrap().troops(), 250);
+        assert_eq!(engine.get_territory(to).unwrap().troops(), 250 - result.attacker_losses);
+        // Losses are soldiers, not workers
+        assert_eq!(engine.get_player(player).unwrap().workers(), workers);
+        assert_eq!(engine.get_player(player).unwrap().troops(), 500 - result.attacker_losses as u64);
+    }
+
+    #[test]
+    fn test_failed_attack_survivors_return() {
+        let (mut engine, player, from, to) = setup(500, 1_000);
+
+        let result = engine.execute_attack(player, from, to).unwrap();
+
+        assert!(!result.territory_conquered);
+        assert_eq!(engine.get_territory(from).unwrap().troops(), 500 - result.attacker_losses);
+        assert_eq!(engine.get_territory(to).unwrap().troops(), 1_000 - result.defender_losses);
+    }
+
+    #[test]
+    fn test_attack_is_limited_to_origin_garrison() {
+        let (mut engine, player, from, to) = setup(40, 10);
+
+        let result = engine.execute_attack(player, from, to).unwrap();
+        assert_eq!(result.attacker_troops_committed, 40);
+
+        engine.get_territory_mut(from).unwrap().units = Units::infantry(0);
+        assert!(engine.execute_attack(player, from, to).is_err());
+    }
+
+    #[test]
+    fn test_reinforcements_keep_existing_stacks() {
+        let (mut engine, player, from, to) = setup(500, 100);
+        engine.execute_attack(player, from, to).unwrap();
+        engine.state.players[0].territories_controlled = 2;
+        let before = (engine.get_territory(from).unwrap().troops(), engine.get_territory(to).unwrap().troops());
+
+        engine.distribute_troops(player);
+
+        let after = (engine.get_territory(from).unwrap().troops(), engine.get_territory(to).unwrap().troops());
+        let total = engine.get_player(player).unwrap().troops();
+        assert_eq!(after.0 as u64 + after.1 as u64, total);
+        assert!(after.0 >= before.0 && after.1 >= before.1);
+    }
+
+    #[test]
+    fn test_back_to_back_attacks_draw_on_troops_that_exist() {
+        let (mut engine, player, from, to) = setup(500, 5_000);
+        // The army shrank since garrisons were last reconciled
+        engine.state.players[0].trained_ratio = 0.2;
+
+        let mut army = engine.get_player(player).unwrap().troops();
+        for _ in 0..3 {
+            let result = engine.execute_attack(player, from, to).unwrap();
+            assert!(result.attacker_troops_committed as u64 <= army / 2);
+            army = engine.get_player(player).unwrap().troops();
+            assert_eq!(engine.get_territory(from).unwrap().troops() as u64, army);
+            engine.audit_troops(player).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_moved_troops_stay_where_they_were_sent() {
+        let (mut engine, player, from, to) = setup(500, 0);
+        engine.get_territory_mut(to).unwrap().owner = Some(player.into());
+        engine.state.players[0].territories_controlled = 2;
+        let neutral = engine.state.territories.iter().find(|t| t.owner.is_none()).unwrap().id;
+
+        assert!(engine.move_troops(player, from, neutral.into(), 10).is_err());
+        assert!(engine.move_troops(player, from, to, 501).is_err());
+        engine.move_troops(player, from, to, 400).unwrap();
+        assert_eq!(engine.get_territory(from).unwrap().troops(), 100);
+        assert_eq!(engine.get_territory(to).unwrap().troops(), 400);
+
+        // Reconciling the army leaves the border stack in place
+        engine.distribute_troops(player);
+        assert_eq!(engine.get_territory(to).unwrap().troops(), 400);
+        engine.audit_troops(player).unwrap();
+    }
+
+    #[test]
+    fn test_losses_are_broken_down_by_unit_type() {
+        let (mut engine, player, from, to) = setup(0, 200);
+        engine.get_territory_mut(from).unwrap().units = Units { infantry: 200, cavalry: 200, siege: 100 };
+
+        let result = engine.execute_attack(player, from, to).unwrap();
+
+        // Half of every type goes; worth 344 against infantry worth 240 braced for cavalry
+        assert_eq!(result.attacker_units, Units { infantry: 100, cavalry: 100, siege: 50 });
+        assert_eq!(result.defender_units, Units::infantry(200));
+        assert!(result.territory_conquered);
+        assert_eq!(result.attacker_unit_losses.total(), result.attacker_losses);
+        assert_eq!(result.defender_unit_losses, Units::infantry(200));
+        let mut survivors = result.attacker_units;
+        survivors.remove(result.attacker_unit_losses);
+        assert_eq!(engine.get_territory(to).unwrap().units, survivors);
+        assert_eq!(engine.get_territory(from).unwrap().units, Units { infantry: 100, cavalry: 100, siege: 50 });
+
+        // Garrisons saved before unit types are all infantry
+        let territory: Territory = serde_json::from_value(serde_json::json!({
+            "id": Uuid::nil(), "owner": null, "terrain": "plains", "building": null,
+            "troops": 40, "neighbors": [], "position": [0.0, 0.0],
+        }))
+        .unwrap();
+        assert_eq!(territory.units, Units::infantry(40));
+    }
+
+    #[test]
+    fn test_combat_variance_is_reproducible_under_a_seed() {
+        let (mut engine, player, from, to) = setup(500, 250);
+        for territory in &mut engine.state.territories {
+            territory.borders.iter_mut().for_each(|b| b.river = false);
+        }
+        let start = engine.state.clone();
+        let fight = |engine: &mut GameEngine, seed: u64| {
+            engine.state = start.clone();
+            engine.reseed(seed);
+            let result = engine.execute_attack(player, from, to).unwrap();
+            (result.attacker_losses, result.defender_losses, result.territory_conquered)
+        };
+
+        // Without variance an even fight always ends the same way
+        let even = fight(&mut engine, 1);
+        assert_eq!(even, fight(&mut engine, 2));
+
+        // With it the seed decides, and the same seed decides the same way
+        engine.rules.combat_variance = 0.15;
+        let outcomes: Vec<_> = (0..20).map(|seed| fight(&mut engine, seed)).collect();
+        assert_eq!(outcomes[3], fight(&mut engine, 3));
+        assert!(outcomes.iter().any(|(_, _, conquered)| *conquered));
+        assert!(outcomes.iter().any(|(_, _, conquered)| !conquered));
+        assert!(outcomes.iter().any(|&(losses, _, _)| losses != even.0));
+    }
+
+    #[test]
+    fn test_capturing_a_building_is_announced_by_name() {
+        let (mut engine, player, from, to) = setup(500, 100);
+        let defender = engine.state.players[1].id;
+        let target = engine.get_territory_mut(to).unwrap();
+        target.owner = Some(defender);
+        target.building = Some(BuildingType::GoldMine);
+        target.name = "Ironhold".to_string();
+        engine.take_events();
+
+        assert!(engine.execute_attack(player, from, to).unwrap().territory_conquered);
+        let expected = format!(
+            "{} captured Ironhold (Gold Mine) from {}",
+            engine.state.players[0].name, engine.state.players[1].name
+        );
+        let events = engine.take_events();
+        let [ServerMessage::Notification { message, category, entities, .. }] = &events[..] else {
+            panic!("unexpected events: {:?}", events);
+        };
+        assert_eq!(*message, expected);
+        assert_eq!(*category, EventCategory::Combat);
+        assert!(entities.contains(&EntityRef::Territory { id: to.into() }));
+        assert!(entities.contains(&EntityRef::Player { id: defender }));
+        assert_eq!(engine.biggest_battle.as_ref().unwrap().territory_name, "Ironhold");
+    }
 }
@@ -0,0 +1,336 @@
+//! Combat resolution models.
+//!
+//! The engine validates an attack, gathers the forces involved into an
+//! `Engagement` and asks the game's `CombatModel` for the losses on each side;
+//! moving troops and ownership stays with the engine. New models implement the
+//! trait and are registered by name in the game's `CombatModels`, next to the
+//! built-in ones, so its rules can select them.
+//!
+//! Models see each side as a single strength: every soldier counts for its
+//! unit type's attack or defense weight, and for more against a side made up
+//! of the unit type it counters. Losses in strength are spread back over the
+//! soldiers afterwards, so an all-infantry battle is fought troop for troop.
+
+use rand::{Rng, RngCore};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{UnitType, Units};
+
+/// Model games use unless their rules pick another
+pub const DEFAULT_COMBAT_MODEL: &str = "threshold";
+
+/// Extra worth of a soldier against a side made up entirely of the unit type
+/// it counters
+const COUNTER_BONUS: f64 = 0.5;
+
+/// Forces meeting in a single attack
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Engagement {
+    pub attacker_troops: u32,
+    pub defender_troops: u32,
+    /// Terrain and building factor on the defender's losses; below 1 favors the defender
+    pub defense_multiplier: f32,
+}
+
+impl Engagement {
+    /// Weigh two sides by unit type
+    pub fn between(attackers: &Units, defenders: &Units, defense_multiplier: f32) -> Self {
+        Engagement {
+            attacker_troops: strength(attackers, defenders, UnitType::attack_weight),
+            defender_troops: strength(defenders, attackers, UnitType::defense_weight),
+            defense_multiplier,
+        }
+    }
+
+    /// Luck of the day: each side fights at its strength times its own draw
+    /// from `1 ± variance`. Without variance the rng is left untouched
+    pub fn with_variance(self, variance: f32, rng: &mut dyn RngCore) -> Self {
+        let variance = variance.clamp(0.0, 1.0) as f64;
+        if variance == 0.0 {
+            return self;
+        }
+        let mut vary = |troops: u32| {
+            let factor = rng.gen_range(1.0 - variance..=1.0 + variance);
+            ((troops as f64 * factor).round().min(u32::MAX as f64) as u32).max(u32::from(troops > 0))
+        };
+        Engagement {
+            attacker_troops: vary(self.attacker_troops),
+            defender_troops: vary(self.defender_troops),
+            ..self
+        }
+    }
+}
+
+/// What a side is worth against an enemy; never 0 while it has soldiers
+fn strength(side: &Units, enemy: &Units, weight: fn(&UnitType) -> f32) -> u32 {
+    let enemy_total = enemy.total().max(1) as f64;
+    let strength: f64 = UnitType::ALL
+        .iter()
+        .map(|unit_type| {
+            let countered = enemy.get(unit_type.counters()) as f64 / enemy_total;
+            side.get(*unit_type) as f64 * weight(unit_type) as f64 * (1.0 + COUNTER_BONUS * countered)
+        })
+        .sum();
+    (strength.round().min(u32::MAX as f64) as u32).max(u32::from(!side.is_empty()))
+}
+
+/// The soldiers a side of `strength` loses with `losses` of it
+pub fn casualties(side: &Units, losses: u32, strength: u32) -> Units {
+    if losses >= strength {
+        return *side;
+    }
+    side.portion((side.total() as u64 * losses as u64 / strength as u64) as u32)
+}
+
+/// Losses on each side and whether the attacker took the territory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CombatOutcome {
+    pub attacker_losses: u32,
+    pub defender_losses: u32,
+    pub territory_conquered: bool,
+}
+
+/// Turns an engagement into losses; must never report more losses than troops
+pub trait CombatModel: Send + Sync {
+    fn resolve(&self, engagement: &Engagement, rng: &mut dyn RngCore) -> CombatOutcome;
+}
+
+/// Combat models a game can select, by case-insensitive name; the built-in
+/// `threshold`, `lanchester` and `dice` are always there
+#[derive(Clone)]
+pub struct CombatModels {
+    models: HashMap<String, Arc<dyn CombatModel>>,
+}
+
+impl Default for CombatModels {
+    fn default() -> Self {
+        let mut models = CombatModels { models: HashMap::new() };
+        models.register(DEFAULT_COMBAT_MODEL, Arc::new(ThresholdCombat));
+        models.register("lanchester", Arc::new(LanchesterCombat));
+        models.register("dice", Arc::new(DiceCombat));
+        models
+    }
+}
+
+impl CombatModels {
+    /// Add a model, replacing any registered under the same name
+    pub fn register(&mut self, name: &str, model: Arc<dyn CombatModel>) {
+        self.models.insert(name.to_ascii_lowercase(), model);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CombatModel> {
+        self.models.get(&name.to_ascii_lowercase()).map(|model| model.as_ref())
+    }
+
+    /// Names of every registered model, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.models.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+impl fmt::Debug for CombatModels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+/// The original deterministic rules: the larger side wins outright
+pub struct ThresholdCombat;
+
+impl CombatModel for ThresholdCombat {
+    fn resolve(&self, engagement: &Engagement, _rng: &mut dyn RngCore) -> CombatOutcome {
+        let Engagement { attacker_troops, defender_troops, defense_multiplier } = *engagement;
+
+        // Base combat formula from design doc
+        let (attacker_losses, base_defender_losses) = if attacker_troops > defender_troops {
+            ((defender_troops as f32 * 0.3) as u32, defender_troops)
+        } else if attacker_troops < defender_troops {
+            (attacker_troops, (attacker_troops as f32 * 0.5) as u32)
+        } else {
+            ((attacker_troops as f32 * 0.7) as u32, (defender_troops as f32 * 0.7) as u32)
+        };
+
+        // Apply defense multiplier (reduces defender losses)
+        let defender_losses = (base_defender_losses as f32 * defense_multiplier) as u32;
+
+        CombatOutcome {
+            attacker_losses,
+            defender_losses,
+            territory_conquered: defender_troops <= defender_losses,
+        }
+    }
+}
+
+/// Square law: each side's strength is its size squared, so the winner keeps
+/// `sqrt(a² - d²)` troops and concentrated forces pay off
+pub struct LanchesterCombat;
+
+impl CombatModel for LanchesterCombat {
+    fn resolve(&self, engagement: &Engagement, _rng: &mut dyn RngCore) -> CombatOutcome {
+        let attackers = engagement.attacker_troops as f64;
+        let defenders = engagement.defender_troops as f64;
+        // Good ground makes every defender count for more
+        let effective_defenders = defenders / engagement.defense_multiplier.max(0.01) as f64;
+
+        if attackers > effective_defenders {
+            let survivors = (attackers * attackers - effective_defenders * effective_defenders).sqrt();
+            CombatOutcome {
+                attacker_losses: (attackers - survivors).round() as u32,
+                defender_losses: engagement.defender_troops,
+                territory_conquered: true,
+            }
+        } else {
+            let survivors = (effective_defenders * effective_defenders - attackers * attackers).sqrt()
+                * engagement.defense_multiplier as f64;
+            CombatOutcome {
+                attacker_losses: engagement.attacker_troops,
+                defender_losses: (defenders - survivors).round().clamp(0.0, defenders) as u32,
+                territory_conquered: false,
+            }
+        }
+    }
+}
+
+/// Rounds of up to three attacking dice against two defending dice, highest
+/// against highest with ties going to the defender. Large armies fight in
+/// squads so a battle takes at most a few thousand rounds
+pub struct DiceCombat;
+
+/// Upper bound on squads across both sides
+const DICE_MAX_SQUADS: u32 = 2000;
+
+impl CombatModel for DiceCombat {
+    fn resolve(&self, engagement: &Engagement, rng: &mut dyn RngCore) -> CombatOutcome {
+        let Engagement { attacker_troops, defender_troops, defense_multiplier } = *engagement;
+        let total = attacker_troops as u64 + defender_troops as u64;
+        let squad = total.div_ceil(DICE_MAX_SQUADS as u64).max(1) as u32;
+        let mut attackers = attacker_troops.div_ceil(squad);
+        let mut defenders = defender_troops.div_ceil(squad);
+        let (mut attackers_lost, mut defenders_lost) = (0, 0);
+
+        while attackers > 0 && defenders > 0 {
+            let mut attack_dice = roll(rng, attackers.min(3));
+            let mut defend_dice = roll(rng, defenders.min(2));
+            attack_dice.sort_unstable_by(|a, b| b.cmp(a));
+            defend_dice.sort_unstable_by(|a, b| b.cmp(a));
+
+            for (a, d) in attack_dice.iter().zip(&defend_dice) {
+                if a > d {
+                    // Fortified defenders shrug off some hits
+                    if rng.gen::<f32>() < defense_multiplier {
+                        defenders -= 1;
+                        defenders_lost += 1;
+                    }
+                } else {
+                    attackers -= 1;
+                    attackers_lost += 1;
+                }
+            }
+        }
+
+        CombatOutcome {
+            attacker_losses: (attackers_lost * squad).min(attacker_troops),
+            defender_losses: (defenders_lost * squad).min(defender_troops),
+            territory_conquered: defenders == 0,
+        }
+    }
+}
+
+fn roll(rng: &mut dyn RngCore, dice: u32) -> Vec<u8> {
+    (0..dice).map(|_| rng.gen_range(1..=6)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameRules;
+
+    fn engagement(attacker_troops: u32, defender_troops: u32) -> Engagement {
+        Engagement { attacker_troops, defender_troops, defense_multiplier: 1.0 }
+    }
+
+    #[test]
+    fn test_threshold_matches_original_rules() {
+        let outcome = ThresholdCombat.resolve(&engagement(200, 100), &mut rand::thread_rng());
+        assert_eq!(outcome, CombatOutcome { attacker_losses: 30, defender_losses: 100, territory_conquered: true });
+
+        let outcome = ThresholdCombat.resolve(&engagement(100, 200), &mut rand::thread_rng());
+        assert_eq!(outcome, CombatOutcome { attacker_losses: 100, defender_losses: 50, territory_conquered: false });
+    }
+
+    #[test]
+    fn test_lanchester_square_law() {
+        let outcome = LanchesterCombat.resolve(&engagement(500, 300), &mut rand::thread_rng());
+        assert_eq!(outcome, CombatOutcome { attacker_losses: 100, defender_losses: 300, territory_conquered: true });
+
+        // Mountains turn the same attack into a defeat
+        let fortified = Engagement { defense_multiplier: 0.5, ..engagement(500, 300) };
+        let outcome = LanchesterCombat.resolve(&fortified, &mut rand::thread_rng());
+        assert_eq!(outcome, CombatOutcome { attacker_losses: 500, defender_losses: 134, territory_conquered: false });
+    }
+
+    #[test]
+    fn test_dice_battles_end_with_one_side_destroyed() {
+        let mut rng = rand::thread_rng();
+        for (attackers, defenders) in [(10, 3), (3, 10), (5000, 4000), (1, 0)] {
+            let outcome = DiceCombat.resolve(&engagement(attackers, defenders), &mut rng);
+            assert!(outcome.attacker_losses <= attackers && outcome.defender_losses <= defenders);
+            if outcome.territory_conquered {
+                assert_eq!(outcome.defender_losses, defenders);
+            } else {
+                assert_eq!(outcome.attacker_losses, attackers);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unit_types_weigh_in_and_counter() {
+        let infantry = Units::infantry(100);
+        let cavalry = Units { cavalry: 100, ..Default::default() };
+        let siege = Units { siege: 100, ..Default::default() };
+
+        // Infantry against infantry is fought troop for troop
+        assert_eq!(Engagement::between(&infantry, &infantry, 1.0), engagement(100, 100));
+        // Cavalry charges hard but holds ground badly, and infantry counters it
+        assert_eq!(Engagement::between(&cavalry, &siege, 1.0), engagement(225, 50));
+        assert_eq!(Engagement::between(&cavalry, &infantry, 1.0), engagement(150, 150));
+        assert_eq!(Engagement::between(&siege, &infantry, 1.0), engagement(188, 100));
+
+        // Losses in strength come back as soldiers of every type alike
+        let mixed = Units { infantry: 60, cavalry: 30, siege: 10 };
+        assert_eq!(casualties(&mixed, 50, 100), Units { infantry: 30, cavalry: 15, siege: 5 });
+        assert_eq!(casualties(&mixed, 120, 100), mixed);
+    }
+
+    /// Attackers always win without a scratch
+    struct Blitz;
+
+    impl CombatModel for Blitz {
+        fn resolve(&self, engagement: &Engagement, _rng: &mut dyn RngCore) -> CombatOutcome {
+            CombatOutcome { attacker_losses: 0, defender_losses: engagement.defender_troops, territory_conquered: true }
+        }
+    }
+
+    #[test]
+    fn test_rules_pick_models_by_name() {
+        let mut rules = GameRules::default();
+        assert_eq!(rules.combat_models.names(), ["dice", "lanchester", "threshold"]);
+        rules.set_combat_model("Dice").unwrap();
+        assert_eq!(rules.combat_model, "dice");
+        assert!(rules.set_combat_model("blitz").is_err());
+
+        rules.combat_models.register("Blitz", Arc::new(Blitz));
+        rules.set_combat_model("blitz").unwrap();
+        let outcome = rules.combat().resolve(&engagement(1, 500), &mut rand::thread_rng());
+        assert_eq!(outcome, CombatOutcome { attacker_losses: 0, defender_losses: 500, territory_conquered: true });
+
+        // A saved game comes back without its custom models
+        let loaded: GameRules = serde_json::from_value(serde_json::to_value(&rules).unwrap()).unwrap();
+        assert_eq!(loaded.combat_model, "blitz");
+        assert_eq!(loaded.combat().resolve(&engagement(1, 500), &mut rand::thread_rng()).attacker_losses, 1);
+    }
+}
@@ -0,0 +1,328 @@
+//! Hand-made maps.
+//!
+//! A map definition lists its territories under keys of the author's choosing,
+//! with their terrain, neighbors and where players start. Before a game is
+//! built on it, the definition is checked: every neighbor must exist and list
+//! the territory back, and the whole map must be one connected piece, so the
+//! engine can rely on the same guarantees as for generated maps.
+//!
+//! The map of any game can be exported in the same format, keyed by territory
+//! id, to be archived, edited or imported again.
+
+use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::map_gen::MapGenerator;
+use super::names::name_map;
+use super::regions::annotate_map;
+
+/// Most territories a map may have
+pub const MAX_CUSTOM_TERRITORIES: usize = 1000;
+
+/// A map drawn by hand
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MapDefinition {
+    #[serde(default)]
+    pub name: String,
+    pub territories: Vec<TerritoryDefinition>,
+    /// Keys of the starting territories, one per player in player order
+    pub starts: Vec<String>,
+    /// How to read the territory positions
+    #[serde(default)]
+    pub grid: MapGrid,
+}
+
+/// A territory of a hand-made map
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TerritoryDefinition {
+    /// Unique within the map; neighbors and starts refer to it
+    pub key: String,
+    /// Display name; generated if blank
+    #[serde(default)]
+    pub name: String,
+    pub terrain: TerrainType,
+    /// Keys of the neighboring territories, which must list this one back
+    pub neighbors: Vec<String>,
+    /// Keys of the neighbors across a river, which must list this one back too
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rivers: Vec<String>,
+    #[serde(default)]
+    pub position: (f32, f32),
+    #[serde(default)]
+    pub polygon: Vec<(f32, f32)>,
+    #[serde(default)]
+    pub building: Option<BuildingType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<ResourceType>,
+}
+
+impl MapDefinition {
+    /// The map of a game: every territory's geometry, terrain, neighbors and
+    /// building, starting each player on the first territory they hold
+    pub fn export(name: &str, state: &GameState) -> Self {
+        let territories = state
+            .territories
+            .iter()
+            .map(|t| TerritoryDefinition {
+                key: t.id.to_string(),
+                name: t.name.clone(),
+                terrain: t.terrain,
+                neighbors: t.neighbors.iter().map(Uuid::to_string).collect(),
+                rivers: t.borders.iter().filter(|b| b.river).map(|b| b.neighbor.to_string()).collect(),
+                position: t.position,
+                polygon: t.polygon.clone(),
+                building: t.building,
+                resource: t.resource,
+            })
+            .collect();
+        let starts = state
+            .players
+            .iter()
+            .filter_map(|p| state.territories.iter().find(|t| t.owner == Some(p.id)))
+            .map(|t| t.id.to_string())
+            .collect();
+        MapDefinition { name: name.to_string(), territories, starts, grid: state.grid }
+    }
+
+    /// Check that a game can be played on this map
+    pub fn validate(&self) -> Result<()> {
+        if self.territories.len() > MAX_CUSTOM_TERRITORIES {
+            return Err(anyhow!("A map can have at most {} territories", MAX_CUSTOM_TERRITORIES));
+        }
+        if self.starts.len() < 2 {
+            return Err(anyhow!("A map needs starts for at least two players"));
+        }
+        if self.territories.len() < self.starts.len() {
+            return Err(anyhow!("A map needs a territory for each start"));
+        }
+
+        let mut index = HashMap::new();
+        for (i, territory) in self.territories.iter().enumerate() {
+            if territory.key.is_empty() {
+                return Err(anyhow!("Territory {} has no key", i + 1));
+            }
+            if index.insert(territory.key.as_str(), i).is_some() {
+                return Err(anyhow!("Territory key '{}' is used twice", territory.key));
+            }
+            let (x, y) = territory.position;
+            if !x.is_finite() || !y.is_finite() || territory.polygon.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+                return Err(anyhow!("Territory '{}' has an invalid position or polygon", territory.key));
+            }
+        }
+
+        for territory in &self.territories {
+            let mut seen = HashSet::new();
+            for neighbor in &territory.neighbors {
+                let Some(&j) = index.get(neighbor.as_str()) else {
+                    return Err(anyhow!("Territory '{}' lists unknown neighbor '{}'", territory.key, neighbor));
+                };
+                if *neighbor == territory.key {
+                    return Err(anyhow!("Territory '{}' lists itself as a neighbor", territory.key));
+                }
+                if !seen.insert(neighbor) {
+                    return Err(anyhow!("Territory '{}' lists neighbor '{}' twice", territory.key, neighbor));
+                }
+                if !self.territories[j].neighbors.contains(&territory.key) {
+                    return Err(anyhow!(
+                        "Territory '{}' lists '{}' as a neighbor, but '{}' does not list it back",
+                        territory.key,
+                        neighbor,
+                        neighbor
+                    ));
+                }
+            }
+            for river in &territory.rivers {
+                if !territory.neighbors.contains(river) {
+                    return Err(anyhow!("Territory '{}' has a river to '{}', which is not its neighbor", territory.key, river));
+                }
+                if !self.territories[index[river.as_str()]].rivers.contains(&territory.key) {
+                    return Err(anyhow!(
+                        "Territory '{}' has a river to '{}', but '{}' has none back",
+                        territory.key,
+                        river,
+                        river
+                    ));
+                }
+            }
+        }
+
+        // Every territory must be reachable from every other
+        let mut reached = vec![false; self.territories.len()];
+        let mut queue = VecDeque::from([0]);
+        reached[0] = true;
+        while let Some(i) = queue.pop_front() {
+            for neighbor in &self.territories[i].neighbors {
+                let j = index[neighbor.as_str()];
+                if !reached[j] {
+                    reached[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+        if let Some(i) = reached.iter().position(|r| !r) {
+            return Err(anyhow!("Territory '{}' can't be reached from '{}'", self.territories[i].key, self.territories[0].key));
+        }
+
+        let mut starts = HashSet::new();
+        for start in &self.starts {
+            let Some(&i) = index.get(start.as_str()) else {
+                return Err(anyhow!("Unknown start territory '{}'", start));
+            };
+            if !starts.insert(start) {
+                return Err(anyhow!("Territory '{}' is the start of two players", start));
+            }
+            if self.territories[i].terrain == TerrainType::Water {
+                return Err(anyhow!("Start territory '{}' is water", start));
+            }
+        }
+        Ok(())
+    }
+
+    /// A new game on this map, one player per start with the first
+    /// `human_slots` for humans; the seed picks names, AI personalities and
+    /// neutral garrisons
+    pub fn build(&self, human_slots: usize, seed: u64) -> Result<GameState> {
+        self.validate()?;
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let ids: HashMap<&str, Uuid> = self.territories.iter().map(|t| (t.key.as_str(), Uuid::new_v4())).collect();
+        let mut territories: Vec<Territory> = self
+            .territories
+            .iter()
+            .map(|t| Territory {
+                id: ids[t.key.as_str()],
+                name: t.name.clone(),
+                owner: None,
+                terrain: t.terrain,
+                building: t.building,
+                building_level: 1,
+                building_idle_until: 0,
+                units: Units::default(),
+                neighbors: t.neighbors.iter().map(|key| ids[key.as_str()]).collect(),
+                position: t.position,
+                region: 0,
+                coastal: false,
+                polygon: t.polygon.clone(),
+                capital_of: None,
+                resource: t.resource,
+                borders: t.rivers.iter().map(|key| Border { neighbor: ids[key.as_str()], river: true }).collect(),
+            })
+            .collect();
+
+        let players = MapGenerator::new(self.territories.len(), self.starts.len())
+            .with_human_slots(human_slots)
+            .generate_players(&mut rng);
+        for (player, start) in players.iter().zip(&self.starts) {
+            let territory = territories.iter_mut().find(|t| t.id == ids[start.as_str()]).unwrap();
+            territory.owner = Some(player.id);
+            territory.capital_of = Some(player.id);
+            territory.units = Units::infantry(500);
+        }
+        for territory in territories.iter_mut().filter(|t| t.owner.is_none()) {
+            let troops = rng.gen_range(50..150);
+            territory.units = Units::infantry(if territory.terrain == TerrainType::Water { 0 } else { troops });
+        }
+
+        let mut state = GameState {
+            territories,
+            players,
+            tick: 0,
+            game_speed: 1.0,
+            is_paused: false,
+            game_time_seconds: 0,
+            regions: Vec::new(),
+            chokepoints: Vec::new(),
+            diplomacy: Diplomacy::default(),
+            grid: self.grid,
+            victory: Vec::new(),
+            battles: Vec::new(),
+        };
+        annotate_map(&mut state);
+        name_map(&mut state, &mut rng);
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn territory(key: &str, terrain: TerrainType, neighbors: &[&str]) -> TerritoryDefinition {
+        TerritoryDefinition {
+            key: key.to_string(),
+            name: String::new(),
+            terrain,
+            neighbors: neighbors.iter().map(|n| n.to_string()).collect(),
+            rivers: Vec::new(),
+            position: (0.0, 0.0),
+            polygon: Vec::new(),
+            building: None,
+            resource: None,
+        }
+    }
+
+    #[test]
+    fn test_custom_maps_are_validated_before_play() {
+        let mut map = MapDefinition {
+            name: "Triangle".to_string(),
+            territories: vec![
+                territory("a", TerrainType::Plains, &["b", "c"]),
+                territory("b", TerrainType::Water, &["a", "c"]),
+                territory("c", TerrainType::Forests, &["a", "b"]),
+            ],
+            starts: vec!["a".to_string(), "c".to_string()],
+            grid: MapGrid::Voronoi,
+        };
+        let state = map.build(1, 7).unwrap();
+        assert_eq!(state.players.len(), 2);
+        assert_eq!(state.territories[0].owner, Some(state.players[0].id));
+        assert_eq!(state.territories[2].owner, Some(state.players[1].id));
+        assert!(state.territories[1].neighbors.contains(&state.territories[0].id));
+        assert!(state.territories.iter().all(|t| !t.name.is_empty()));
+
+        let error = |map: &MapDefinition| map.validate().unwrap_err().to_string();
+        map.territories[0].rivers = vec!["c".to_string()];
+        assert_eq!(error(&map), "Territory 'a' has a river to 'c', but 'c' has none back");
+        map.territories[2].rivers = vec!["a".to_string()];
+        let river = map.build(1, 7).unwrap();
+        assert!(river.territories[0].river_to(river.territories[2].id));
+        map.territories[2].neighbors.retain(|n| n != "b");
+        assert_eq!(error(&map), "Territory 'b' lists 'c' as a neighbor, but 'c' does not list it back");
+        map.territories[1].neighbors.retain(|n| n != "c");
+        map.territories.push(territory("d", TerrainType::Plains, &[]));
+        assert_eq!(error(&map), "Territory 'd' can't be reached from 'a'");
+        map.territories.pop();
+        map.starts[1] = "b".to_string();
+        assert_eq!(error(&map), "Start territory 'b' is water");
+        map.starts[1] = "z".to_string();
+        assert_eq!(error(&map), "Unknown start territory 'z'");
+    }
+
+    #[test]
+    fn test_exported_maps_import_as_the_same_map() {
+        let state = MapGenerator::new(30, 3).with_grid(MapGrid::Hex).generate_seeded(11);
+        let map = MapDefinition::export("Archived", &state);
+        map.validate().unwrap();
+        assert_eq!(map.starts.len(), 3);
+
+        // Stable JSON: the same map exports to the same text
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, serde_json::to_string(&MapDefinition::export("Archived", &state)).unwrap());
+
+        let imported: MapDefinition = serde_json::from_str(&json).unwrap();
+        let rebuilt = imported.build(0, 1).unwrap();
+        assert_eq!(rebuilt.grid, MapGrid::Hex);
+        for (original, copy) in state.territories.iter().zip(&rebuilt.territories) {
+            assert_eq!((original.terrain, original.position, original.neighbors.len()), (copy.terrain, copy.position, copy.neighbors.len()));
+            assert_eq!(original.polygon, copy.polygon);
+            assert_eq!((original.borders.len(), original.resource), (copy.borders.len(), copy.resource));
+        }
+        assert_eq!(rebuilt.territories.iter().filter(|t| t.owner.is_some()).count(), 3);
+    }
+}
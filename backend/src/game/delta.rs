@@ -0,0 +1,108 @@
+//! Diffs between broadcast states.
+//!
+//! Between keyframes the game loop sends each client only the territories and
+//! players that changed since the previous broadcast. A delta only exists when
+//! the map is the same and players were only added, which is all that happens
+//! during play; anything else needs a full state.
+
+use crate::types::*;
+
+impl GameState {
+    /// What changed since `base`, or `None` if the map or player list was reshaped
+    pub fn delta_since(&self, base: &GameState) -> Option<StateDelta> {
+        let same_map = self.territories.len() == base.territories.len()
+            && self.territories.iter().zip(&base.territories).all(|(t, b)| t.id == b.id);
+        let players_kept = base.players.len() <= self.players.len()
+            && self.players.iter().zip(&base.players).all(|(p, b)| p.id == b.id);
+        if !same_map || !players_kept {
+            return None;
+        }
+
+        let territories = self.territories.iter().zip(&base.territories).filter(|(t, b)| t != b).map(|(t, _)| t.clone()).collect();
+        let players = self
+            .players
+            .iter()
+            .enumerate()
+            .filter(|(idx, p)| base.players.get(*idx) != Some(*p))
+            .map(|(_, p)| p.clone())
+            .collect();
+        Some(StateDelta {
+            base_tick: base.tick,
+            tick: self.tick,
+            game_speed: self.game_speed,
+            is_paused: self.is_paused,
+            game_time_seconds: self.game_time_seconds,
+            territories,
+            players,
+            diplomacy: (self.diplomacy != base.diplomacy).then(|| self.diplomacy.clone()),
+            victory: (self.victory != base.victory).then(|| self.victory.clone()),
+            battles: (self.battles != base.battles).then(|| self.battles.clone()),
+            checksum: self.checksum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{anyhow, Result};
+
+    use crate::game::{GameEngine, MapGenerator};
+    use crate::types::*;
+
+    /// What a client does with a delta made against its state
+    fn apply_delta(state: &mut GameState, delta: &StateDelta) -> Result<()> {
+        if delta.base_tick != state.tick {
+            return Err(anyhow!("Delta is based on tick {}, state is at tick {}", delta.base_tick, state.tick));
+        }
+        for territory in &delta.territories {
+            let slot = state.territories.iter_mut().find(|t| t.id == territory.id).ok_or_else(|| anyhow!("Territory not found"))?;
+            *slot = territory.clone();
+        }
+        for player in &delta.players {
+            match state.players.iter_mut().find(|p| p.id == player.id) {
+                Some(slot) => *slot = player.clone(),
+                None => state.players.push(player.clone()),
+            }
+        }
+        if let Some(victory) = &delta.victory {
+            state.victory = victory.clone();
+        }
+        if let Some(battles) = &delta.battles {
+            state.battles = battles.clone();
+        }
+        if let Some(diplomacy) = &delta.diplomacy {
+            state.diplomacy = diplomacy.clone();
+        }
+        state.tick = delta.tick;
+        state.game_speed = delta.game_speed;
+        state.is_paused = delta.is_paused;
+        state.game_time_seconds = delta.game_time_seconds;
+        Ok(())
+    }
+
+    #[test]
+    fn test_delta_carries_only_changes_and_rebuilds_the_state() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 3).generate(), 100);
+        let base = engine.state.clone();
+        engine.state.territories[4].units.infantry += 10;
+        engine.state.players[1].gold += 5;
+        engine.state.tick += 5;
+
+        let delta = engine.state.delta_since(&base).unwrap();
+        assert_eq!(delta.territories.len(), 1);
+        assert_eq!(delta.players.len(), 1);
+
+        let mut client = base.clone();
+        apply_delta(&mut client, &delta).unwrap();
+        assert_eq!(client.checksum(), delta.checksum);
+        assert_eq!(client.territories, engine.state.territories);
+        assert!(apply_delta(&mut client, &delta).is_err());
+    }
+
+    #[test]
+    fn test_reshaped_map_needs_a_full_state() {
+        let base = MapGenerator::new(30, 3).generate();
+        let other = MapGenerator::new(30, 3).generate();
+        assert!(other.delta_since(&base).is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! Treaties between players and territory gifts between treaty partners.
+//!
+//! A treaty is signed once both players have offered it; either side can
+//! break it at any time. Partners can't attack each other, and may give each
+//! other territories that border the receiver's land. Signing also starts a
+//! truce that outlasts a treaty broken early: until it runs out, the two
+//! players still can't attack each other.
+
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Offer a treaty, or accept the one `to` offered; returns true once signed
+    pub fn propose_treaty(&mut self, from: PlayerId, to: PlayerId) -> Result<bool> {
+        let (from, to): (Uuid, Uuid) = (from.into(), to.into());
+        if from == to {
+            return Err(anyhow!("You can't sign a treaty with yourself"));
+        }
+        let name = |engine: &Self, id: Uuid| -> Result<String> {
+            let player = engine.get_player(id.into())?;
+            if !player.is_alive {
+                return Err(anyhow!("{} has been eliminated", player.name));
+            }
+            Ok(player.name.clone())
+        };
+        let (from_name, to_name) = (name(self, from)?, name(self, to)?);
+
+        let diplomacy = &mut self.state.diplomacy;
+        if diplomacy.has_treaty(from, to) {
+            return Err(anyhow!("You already have a treaty with {}", to_name));
+        }
+        if let Some(idx) = diplomacy.offers.iter().position(|o| o.from == to && o.to == from) {
+            diplomacy.offers.remove(idx);
+            diplomacy.offers.retain(|o| !(o.from == from && o.to == to));
+            diplomacy.treaties.push(Treaty { players: [to, from], signed_tick: self.state.tick });
+            diplomacy.truces.retain(|t| !(t.players.contains(&from) && t.players.contains(&to)));
+            if self.rules.truce_ticks > 0 {
+                diplomacy.truces.push(Truce { players: [to, from], remaining_ticks: self.rules.truce_ticks });
+            }
+            let entities = vec![EntityRef::Player { id: to }, EntityRef::Player { id: from }];
+            self.events.push(
+                ServerMessage::notification(
+                    NotificationKey::TreatySigned,
+                    NotificationLevel::Info,
+                    [("player", to_name), ("other", from_name)],
+                )
+                .about(entities),
+            );
+            return Ok(true);
+        }
+
+        if !diplomacy.offers.iter().any(|o| o.from == from && o.to == to) {
+            diplomacy.offers.push(TreatyOffer { from, to });
+        }
+        Ok(false)
+    }
+
+    /// End a treaty, or withdraw an offer that wasn't accepted yet
+    pub fn break_treaty(&mut self, from: PlayerId, to: PlayerId) -> Result<()> {
+        let (from, to): (Uuid, Uuid) = (from.into(), to.into());
+        let diplomacy = &mut self.state.diplomacy;
+        let offers = diplomacy.offers.len();
+        diplomacy.offers.retain(|o| !(o.from == from && o.to == to));
+        if diplomacy.offers.len() < offers {
+            return Ok(());
+        }
+
+        let idx = diplomacy
+            .treaties
+            .iter()
+            .position(|t| t.players.contains(&from) && t.players.contains(&to))
+            .ok_or_else(|| anyhow!("You have no treaty with that player"))?;
+        diplomacy.treaties.remove(idx);
+
+        let name = |id: Uuid| self.get_player(id.into()).map(|p| p.name.clone()).unwrap_or_default();
+        let (from_name, to_name) = (name(from), name(to));
+        let entities = vec![EntityRef::Player { id: from }, EntityRef::Player { id: to }];
+        self.events.push(
+            ServerMessage::notification(
+                NotificationKey::TreatyBroken,
+                NotificationLevel::Warning,
+                [("player", from_name), ("other", to_name)],
+            )
+            .about(entities),
+        );
+        Ok(())
+    }
+
+    /// Hand a territory, with its building, to a treaty partner whose land it borders
+    pub fn gift_territory(&mut self, from: PlayerId, territory_id: TerritoryId, to: PlayerId) -> Result<()> {
+        let (giver, receiver): (Uuid, Uuid) = (from.into(), to.into());
+        let territory = self.get_territory(territory_id)?;
+        if territory.owner != Some(giver) {
+            return Err(anyhow!("You don't own this territory"));
+        }
+        if !self.state.diplomacy.has_treaty(giver, receiver) || giver == receiver {
+            return Err(anyhow!("You can only give territory to a treaty partner"));
+        }
+        let borders_receiver = territory
+            .neighbors
+            .iter()
+            .any(|id| self.get_territory((*id).into()).is_ok_and(|n| n.owner == Some(receiver)));
+        if !borders_receiver {
+            return Err(anyhow!("The territory must border the receiver's land"));
+        }
+        if self.get_player(from)?.territories_controlled <= 1 {
+            return Err(anyhow!("You can't give away your last territory"));
+        }
+        let tick = self.state.tick;
+        if let Some(&last) = self.last_gift.get(&giver) {
+            let ready = last.saturating_add(self.rules.gift_cooldown_ticks);
+            if tick < ready {
+                return Err(anyhow!("You can give territory again in {} ticks", ready - tick));
+            }
+        }
+
+        // The garrison stays in the giver's army and is redeployed on their own land
+        let territory = self.get_territory_mut(territory_id)?;
+        territory.owner = Some(receiver);
+        territory.units = Units::infantry(0);
+        self.get_player_mut(from)?.territories_controlled -= 1;
+        self.get_player_mut(to)?.territories_controlled += 1;
+        self.last_gift.insert(giver, tick);
+        self.record_change_of_hands(territory_id.into());
+        self.building_changed_hands(territory_id, false)?;
+
+        self.events.push(ServerMessage::TerritoryTransferred {
+            territory_id: territory_id.into(),
+            from: giver,
+            to: receiver,
+        });
+        Ok(())
+    }
+
+    /// Count down running truces, ending those that ran out
+    pub(super) fn tick_truces(&mut self) {
+        let truces = &mut self.state.diplomacy.truces;
+        for truce in truces.iter_mut() {
+            truce.remaining_ticks = truce.remaining_ticks.saturating_sub(1);
+        }
+        truces.retain(|t| t.remaining_ticks > 0);
+    }
+
+    /// Drop every treaty, offer and truce involving an eliminated player
+    pub(super) fn forget_relations(&mut self, player: Uuid) {
+        let diplomacy = &mut self.state.diplomacy;
+        diplomacy.treaties.retain(|t| !t.players.contains(&player));
+        diplomacy.offers.retain(|o| o.from != player && o.to != player);
+        diplomacy.truces.retain(|t| !t.players.contains(&player));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Two players at peace, with the first owning a spare territory next to the second's land
+    fn partners() -> (GameEngine, PlayerId, PlayerId, TerritoryId) {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let (a, b) = (engine.state.players[0].id, engine.state.players[1].id);
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(b)).unwrap();
+        let border = engine.state.territories[home].neighbors.iter().find_map(|id| {
+            engine.state.territories.iter().position(|t| t.id == *id && t.owner.is_none())
+        });
+        let border = border.unwrap();
+        engine.state.territories[border].owner = Some(a);
+        engine.state.territories[border].units = Units::infantry(40);
+        engine.tick();
+
+        assert!(!engine.propose_treaty(a.into(), b.into()).unwrap());
+        assert!(engine.propose_treaty(b.into(), a.into()).unwrap());
+        let border = engine.state.territories[border].id.into();
+        (engine, a.into(), b.into(), border)
+    }
+
+    #[test]
+    fn test_treaty_needs_both_sides_and_stops_attacks() {
+        let (mut engine, a, b, border) = partners();
+        assert!(engine.state.diplomacy.has_treaty(a.into(), b.into()));
+        assert!(engine.state.diplomacy.offers.is_empty());
+
+        let target = engine.state.territories.iter().find(|t| t.owner == Some(b.into())).unwrap().id;
+        let err = engine.execute_attack(a, border, target.into()).unwrap_err();
+        assert_eq!(err.to_string(), "You have a treaty with this player");
+
+        engine.break_treaty(b, a).unwrap();
+        assert!(engine.state.diplomacy.treaties.is_empty());
+        assert!(engine.break_treaty(b, a).is_err());
+    }
+
+    #[test]
+    fn test_truce_outlasts_a_broken_treaty() {
+        let (mut engine, a, b, border) = partners();
+        let truce = engine.rules.truce_ticks;
+        assert_eq!(engine.state.diplomacy.truce_remaining(a.into(), b.into()), Some(truce));
+        engine.break_treaty(a, b).unwrap();
+
+        engine.tick();
+        assert_eq!(engine.state.diplomacy.truce_remaining(b.into(), a.into()), Some(truce - 1));
+        let target = engine.state.territories.iter().find(|t| t.owner == Some(b.into())).unwrap().id;
+        let err = engine.execute_attack(a, border, target.into()).unwrap_err();
+        assert_eq!(err.to_string(), format!("Your truce with this player ends in {} ticks", truce - 1));
+
+        for _ in 1..truce {
+            engine.tick();
+        }
+        assert!(engine.state.diplomacy.truces.is_empty());
+        engine.execute_attack(a, border, target.into()).unwrap();
+    }
+
+    #[test]
+    fn test_gift_moves_territory_between_partners() {
+        let (mut engine, a, b, border) = partners();
+        let before = (engine.get_player(a).unwrap().territories_controlled, engine.get_player(b).unwrap().territories_controlled);
+        engine.take_events();
+
+        engine.gift_territory(a, border, b).unwrap();
+
+        assert_eq!(engine.get_territory(border).unwrap().owner, Some(b.into()));
+        assert_eq!(engine.get_player(a).unwrap().territories_controlled, before.0 - 1);
+        assert_eq!(engine.get_player(b).unwrap().territories_controlled, before.1 + 1);
+        assert!(matches!(
+            &engine.take_events()[..],
+            [ServerMessage::TerritoryTransferred { territory_id, .. }] if *territory_id == Uuid::from(border)
+        ));
+        engine.check_invariants().unwrap();
+
+        // Another gift has to wait for the cooldown
+        engine.get_territory_mut(border).unwrap().owner = Some(a.into());
+        engine.tick();
+        let err = engine.gift_territory(a, border, b).unwrap_err();
+        assert!(err.to_string().starts_with("You can give territory again in"), "{}", err);
+        engine.state.tick += engine.rules.gift_cooldown_ticks;
+        engine.gift_territory(a, border, b).unwrap();
+    }
+}
@@ -0,0 +1,313 @@
+//! Map and rule draft for competitive lobbies.
+//!
+//! Before the game starts, every player may veto one map from the ballot and
+//! vote on each rule switch, and the host may add, remove or swap AI
+//! opponents. When the host starts the game, the first map nobody vetoed is
+//! generated for the humans and the host's AI opponents, and each rule with
+//! more yes than no votes is switched on.
+
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::{Difficulty, GameEngine, GameRules, MapGenerator};
+
+pub struct Draft {
+    host: PlayerId,
+    maps: Vec<MapOption>,
+    /// Map index vetoed by each player
+    vetoes: HashMap<PlayerId, usize>,
+    votes: BTreeMap<RuleOption, HashMap<PlayerId, bool>>,
+    /// AI opponents picked by the host, if they changed them
+    ai_opponents: Option<Vec<AIPersonality>>,
+    start_requested: bool,
+    result: Option<DraftResult>,
+}
+
+impl Draft {
+    pub fn new(host: PlayerId, maps: Vec<MapOption>, rules: &[RuleOption]) -> Result<Self> {
+        if maps.is_empty() {
+            return Err(anyhow!("A draft needs at least one map"));
+        }
+        Ok(Self {
+            host,
+            maps,
+            vetoes: HashMap::new(),
+            votes: rules.iter().map(|rule| (*rule, HashMap::new())).collect(),
+            ai_opponents: None,
+            start_requested: false,
+            result: None,
+        })
+    }
+
+    /// Strike a map from the ballot; each player has one veto and the last map can't go
+    pub fn veto(&mut self, player: PlayerId, map: usize) -> Result<()> {
+        self.ensure_open()?;
+        if map >= self.maps.len() {
+            return Err(anyhow!("No such map"));
+        }
+        if self.vetoes.contains_key(&player) {
+            return Err(anyhow!("You already vetoed a map"));
+        }
+        if self.vetoes.values().any(|&m| m == map) {
+            return Err(anyhow!("That map is already vetoed"));
+        }
+        if self.vetoes.len() + 1 >= self.maps.len() {
+            return Err(anyhow!("The last map can't be vetoed"));
+        }
+        self.vetoes.insert(player, map);
+        Ok(())
+    }
+
+    /// Cast or change a vote on a rule switch
+    pub fn vote(&mut self, player: PlayerId, rule: RuleOption, enabled: bool) -> Result<()> {
+        self.ensure_open()?;
+        let votes = self.votes.get_mut(&rule).ok_or_else(|| anyhow!("That rule is not up for a vote"))?;
+        votes.insert(player, enabled);
+        Ok(())
+    }
+
+    /// Replace the AI opponents with one per personality; host only. The game
+    /// needs two players, and no more than `max_players`
+    pub fn set_ai_opponents(&mut self, player: PlayerId, opponents: Vec<AIPersonality>, humans: usize, max_players: usize) -> Result<()> {
+        self.ensure_open()?;
+        if player != self.host {
+            return Err(anyhow!("Only the host can change the AI opponents"));
+        }
+        if humans + opponents.len() < 2 {
+            return Err(anyhow!("A game needs at least two players"));
+        }
+        if humans + opponents.len() > max_players {
+            return Err(anyhow!("This game has room for at most {} players", max_players));
+        }
+        self.ai_opponents = Some(opponents);
+        Ok(())
+    }
+
+    /// Only the host may end the draft
+    pub fn request_start(&mut self, player: PlayerId) -> Result<()> {
+        self.ensure_open()?;
+        if player != self.host {
+            return Err(anyhow!("Only the host can start the game"));
+        }
+        self.start_requested = true;
+        Ok(())
+    }
+
+    pub fn start_requested(&self) -> bool {
+        self.start_requested
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.result.is_none()
+    }
+
+    pub fn status(&self) -> DraftStatus {
+        let mut vetoed: Vec<u32> = self.vetoes.values().map(|&m| m as u32).collect();
+        vetoed.sort_unstable();
+        DraftStatus {
+            host: self.host.into(),
+            maps: self.maps.clone(),
+            vetoed,
+            rules: self
+                .votes
+                .iter()
+                .map(|(rule, votes)| {
+                    let yes = votes.values().filter(|&&v| v).count() as u32;
+                    RuleTally { rule: *rule, yes, no: votes.len() as u32 - yes }
+                })
+                .collect(),
+            ai_opponents: self.ai_opponents.clone(),
+        }
+    }
+
+    /// Close the draft and settle the map and rules
+    pub fn resolve(&mut self) -> DraftResult {
+        if let Some(result) = &self.result {
+            return result.clone();
+        }
+        let vetoed = |idx: &usize| self.vetoes.values().any(|m| m == idx);
+        let map = (0..self.maps.len()).find(|idx| !vetoed(idx)).unwrap_or(0);
+        let result = DraftResult {
+            map: self.maps[map],
+            vetoed_maps: (0..self.maps.len()).filter(vetoed).map(|idx| self.maps[idx]).collect(),
+            enabled_rules: self
+                .status()
+                .rules
+                .into_iter()
+                .filter(|tally| tally.yes > tally.no)
+                .map(|tally| tally.rule)
+                .collect(),
+            ai_opponents: self.ai_opponents.clone(),
+        };
+        self.result = Some(result.clone());
+        result
+    }
+
+    pub fn result(&self) -> Option<&DraftResult> {
+        self.result.as_ref()
+    }
+
+    fn ensure_open(&self) -> Result<()> {
+        if self.is_open() {
+            Ok(())
+        } else {
+            Err(anyhow!("The draft is over"))
+        }
+    }
+}
+
+impl GameEngine {
+    /// Play the drafted map with the drafted rules and AI opponents
+    pub fn apply_draft(&mut self, result: &DraftResult) {
+        let territories = self.state.territories.len();
+        let map = match &result.ai_opponents {
+            None => MapGenerator::new(territories, self.state.players.len()),
+            Some(opponents) => {
+                let humans = self.state.players.iter().filter(|p| !p.is_ai).count();
+                MapGenerator::new(territories, humans + opponents.len())
+                    .with_human_slots(humans)
+                    .with_ai_personalities(opponents.clone())
+            }
+        }
+        .with_style(result.map.style)
+        .generate_seeded(result.map.seed);
+
+        // Humans keep their seats; the AI players become the map's
+        if result.ai_opponents.is_some() {
+            let tick = self.state.tick;
+            self.state.players.retain(|p| !p.is_ai);
+            self.state.players.extend(map.players.iter().filter(|p| p.is_ai).cloned());
+            self.player_map = self.state.players.iter().enumerate().map(|(idx, p)| (p.id.into(), idx)).collect();
+            self.joined_at.retain(|id, _| self.player_map.contains_key(&(*id).into()));
+            for player in map.players.iter().filter(|p| p.is_ai) {
+                self.joined_at.insert(player.id, tick);
+            }
+        }
+        self.replace_map(map);
+
+        for rule in &result.enabled_rules {
+            match rule {
+                RuleOption::LateJoin => self.rules.allow_late_join = true,
+                RuleOption::DiceCombat => self.rules.combat_model = "dice".to_string(),
+                RuleOption::AggressiveNeutrals => {
+                    let hard = GameRules::for_difficulty(Difficulty::Hard);
+                    self.rules.neutral_fortify_per_second = hard.neutral_fortify_per_second;
+                    self.rules.neutral_max_garrison = hard.neutral_max_garrison;
+                    self.rules.neutral_merge_chance = hard.neutral_merge_chance;
+                    self.rules.neutral_merge_threshold = hard.neutral_merge_threshold;
+                }
+            }
+        }
+    }
+
+    /// Swap in the territories of another map with the same number of players,
+    /// keeping the current players; the map's players hand their starts over in order
+    fn replace_map(&mut self, map: GameState) {
+        let owners: HashMap<Uuid, Uuid> =
+            map.players.iter().zip(&self.state.players).map(|(new, current)| (new.id, current.id)).collect();
+
+        self.state.territories = map.territories;
+        for territory in &mut self.state.territories {
+            territory.owner = territory.owner.and_then(|owner| owners.get(&owner).copied());
+            territory.capital_of = territory.capital_of.and_then(|owner| owners.get(&owner).copied());
+        }
+        self.state.regions = map.regions;
+        self.state.chokepoints = map.chokepoints;
+        self.territory_map = self.state.territories.iter().enumerate().map(|(idx, t)| (t.id.into(), idx)).collect();
+        self.graph_changed();
+
+        for player in &mut self.state.players {
+            player.territories_controlled =
+                self.state.territories.iter().filter(|t| t.owner == Some(player.id)).count() as u32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballot() -> Vec<MapOption> {
+        (1..=3).map(|seed| MapOption { style: MapStyle::Continents, seed }).collect()
+    }
+
+    #[test]
+    fn test_vetoes_and_votes_decide_the_game() {
+        let players: Vec<PlayerId> = (0..3).map(|_| Uuid::new_v4().into()).collect();
+        let mut draft = Draft::new(players[0], ballot(), &[RuleOption::LateJoin, RuleOption::DiceCombat]).unwrap();
+
+        draft.veto(players[1], 0).unwrap();
+        assert!(draft.veto(players[1], 1).is_err());
+        assert!(draft.veto(players[2], 0).is_err());
+        // Vetoing map 1 too would leave map 2 as the only choice, which is allowed
+        draft.veto(players[2], 1).unwrap();
+        assert!(draft.veto(players[0], 2).is_err());
+
+        draft.vote(players[0], RuleOption::DiceCombat, true).unwrap();
+        draft.vote(players[1], RuleOption::DiceCombat, true).unwrap();
+        draft.vote(players[2], RuleOption::LateJoin, true).unwrap();
+        draft.vote(players[1], RuleOption::LateJoin, false).unwrap();
+        assert!(draft.vote(players[0], RuleOption::AggressiveNeutrals, true).is_err());
+
+        assert!(draft.request_start(players[1]).is_err());
+        draft.request_start(players[0]).unwrap();
+        assert!(draft.start_requested());
+
+        let result = draft.resolve();
+        assert_eq!(result.map.seed, 3);
+        assert_eq!(result.vetoed_maps.len(), 2);
+        assert_eq!(result.enabled_rules, [RuleOption::DiceCombat]);
+        assert!(draft.vote(players[0], RuleOption::LateJoin, true).is_err());
+    }
+
+    #[test]
+    fn test_drafted_map_keeps_the_players() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 3).generate(), 100);
+        let players: Vec<Uuid> = engine.state.players.iter().map(|p| p.id).collect();
+        let result = DraftResult {
+            map: MapOption { style: MapStyle::Continents, seed: 42 },
+            vetoed_maps: Vec::new(),
+            enabled_rules: vec![RuleOption::DiceCombat],
+            ai_opponents: None,
+        };
+
+        engine.apply_draft(&result);
+
+        let expected = MapGenerator::new(30, 3).with_style(MapStyle::Continents).generate_seeded(42);
+        let terrain = |state: &GameState| state.territories.iter().map(|t| t.terrain).collect::<Vec<_>>();
+        assert_eq!(terrain(&engine.state), terrain(&expected));
+        assert_eq!(engine.state.players.iter().map(|p| p.id).collect::<Vec<_>>(), players);
+        for player in &players {
+            let start = engine.state.territories.iter().find(|t| t.owner == Some(*player)).unwrap();
+            assert!(engine.get_territory(start.id.into()).is_ok());
+        }
+        assert_eq!(engine.rules.combat_model, "dice");
+    }
+
+    #[test]
+    fn test_host_changes_the_ai_opponents() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 3).with_human_slots(2).generate(), 100);
+        let humans: Vec<Uuid> = engine.state.players.iter().filter(|p| !p.is_ai).map(|p| p.id).collect();
+        let mut draft = Draft::new(humans[0].into(), ballot(), &[]).unwrap();
+
+        let opponents = vec![AIPersonality::Turtle, AIPersonality::Aggressor, AIPersonality::Turtle];
+        assert!(draft.set_ai_opponents(humans[1].into(), opponents.clone(), 2, 30).is_err());
+        assert!(draft.set_ai_opponents(humans[0].into(), Vec::new(), 1, 30).is_err());
+        assert!(draft.set_ai_opponents(humans[0].into(), opponents.clone(), 2, 4).is_err());
+        draft.set_ai_opponents(humans[0].into(), opponents.clone(), 2, 30).unwrap();
+        assert_eq!(draft.status().ai_opponents.as_ref(), Some(&opponents));
+
+        engine.apply_draft(&draft.resolve());
+
+        let players = &engine.state.players;
+        assert_eq!(players.iter().filter(|p| !p.is_ai).map(|p| p.id).collect::<Vec<_>>(), humans);
+        let personalities: Vec<_> = players.iter().filter_map(|p| p.ai_personality).collect();
+        assert_eq!(personalities, opponents);
+        for player in players {
+            assert_eq!(player.territories_controlled, 1, "{} has no start", player.name);
+            assert!(engine.get_player(player.id.into()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Map fairness analysis.
+//!
+//! Scores how evenly a map treats its players by comparing their starting
+//! territories: how many neighbors each start has, how many hops it is from the
+//! nearest opponent, and how valuable the terrain within a few hops is. Each
+//! measure is reduced to the ratio of the worst start to the best, and the
+//! map's score is their average: 1.0 means every start is alike.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::MapGenerator;
+
+/// Radius used for terrain value unless a caller picks another
+pub const DEFAULT_FAIRNESS_HOPS: u32 = 2;
+
+/// How one player's start compares
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StartReport {
+    #[schema(value_type = String, format = "uuid")]
+    pub player_id: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub territory_id: Uuid,
+    pub neighbors: u32,
+    /// Hops to the closest territory held by another player; `None` if unreachable
+    #[schema(nullable = true)]
+    pub nearest_opponent_hops: Option<u32>,
+    /// Summed terrain bonuses of the territories within `hops` of the start
+    pub terrain_value: f32,
+}
+
+/// Fairness of a map's starting positions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FairnessReport {
+    /// Radius of the terrain value measure
+    pub hops: u32,
+    pub starts: Vec<StartReport>,
+    /// Worst to best ratio of neighbor counts
+    pub neighbor_balance: f32,
+    /// Worst to best ratio of distances to the nearest opponent
+    pub distance_balance: f32,
+    /// Worst to best ratio of nearby terrain value
+    pub terrain_balance: f32,
+    /// Average of the three balances, from 0 (unfair) to 1 (every start alike)
+    pub score: f32,
+}
+
+/// Score the starting territory of every player that holds one
+pub fn analyze(state: &GameState, hops: u32) -> FairnessReport {
+    let territories: HashMap<Uuid, &Territory> = state.territories.iter().map(|t| (t.id, t)).collect();
+
+    let starts: Vec<StartReport> = state
+        .players
+        .iter()
+        .filter_map(|player| {
+            let start = state.territories.iter().find(|t| t.owner == Some(player.id))?;
+            let distances = hop_distances(&territories, start.id);
+
+            let nearest_opponent_hops = distances
+                .iter()
+                .filter(|(id, _)| territories[*id].owner.is_some_and(|owner| owner != player.id))
+                .map(|(_, &hops)| hops)
+                .min();
+            let terrain_value = distances
+                .iter()
+                .filter(|(_, &distance)| distance <= hops)
+                .map(|(id, _)| terrain_value(territories[id].terrain))
+                .sum();
+
+            Some(StartReport {
+                player_id: player.id,
+                territory_id: start.id,
+                neighbors: start.neighbors.len() as u32,
+                nearest_opponent_hops,
+                terrain_value,
+            })
+        })
+        .collect();
+
+    let neighbor_balance = balance(starts.iter().map(|s| s.neighbors as f32));
+    // An opponent that can never be reached is as far away as it gets
+    let distance_balance = if starts.iter().any(|s| s.nearest_opponent_hops.is_none()) && starts.len() > 1 {
+        0.0
+    } else {
+        balance(starts.iter().filter_map(|s| s.nearest_opponent_hops).map(|h| h as f32))
+    };
+    let terrain_balance = balance(starts.iter().map(|s| s.terrain_value));
+
+    FairnessReport {
+        hops,
+        starts,
+        neighbor_balance,
+        distance_balance,
+        terrain_balance,
+        score: (neighbor_balance + distance_balance + terrain_balance) / 3.0,
+    }
+}
+
+/// Generate up to `attempts` maps, returning the first scoring at least
+/// `min_score`, or the fairest one if none does
+pub fn generate_fair(generator: &MapGenerator, min_score: f32, attempts: u32) -> (GameState, FairnessReport) {
+    let mut best: Option<(GameState, FairnessReport)> = None;
+    for attempt in 0..attempts.max(1) {
+        // Seeded generators reroll through the following seeds, so the result is reproducible too
+        let state = match generator.seed {
+            Some(seed) => generator.generate_seeded(seed.wrapping_add(attempt as u64)),
+            None => generator.generate(),
+        };
+        let report = analyze(&state, DEFAULT_FAIRNESS_HOPS);
+        if report.score >= min_score {
+            return (state, report);
+        }
+        if best.as_ref().is_none_or(|(_, b)| report.score > b.score) {
+            best = Some((state, report));
+        }
+    }
+    best.unwrap()
+}
+
+/// Economic and defensive bonus of a terrain, counting a plain tile as 1
+pub fn terrain_value(terrain: TerrainType) -> f32 {
+    terrain.gold_multiplier() + terrain.population_growth_multiplier() - terrain.defense_multiplier()
+}
+
+/// Breadth-first hop count from `start` to every reachable territory
+fn hop_distances(territories: &HashMap<Uuid, &Territory>, start: Uuid) -> HashMap<Uuid, u32> {
+    let mut distances = HashMap::from([(start, 0)]);
+    let mut seen = HashSet::from([start]);
+    let mut queue = VecDeque::from([start]);
+    while let Some(id) = queue.pop_front() {
+        let distance = distances[&id];
+        for neighbor in &territories[&id].neighbors {
+            if territories.contains_key(neighbor) && seen.insert(*neighbor) {
+                distances.insert(*neighbor, distance + 1);
+                queue.push_back(*neighbor);
+            }
+        }
+    }
+    distances
+}
+
+/// Ratio of the smallest value to the largest; 1 when all are equal
+fn balance(values: impl Iterator<Item = f32>) -> f32 {
+    let (min, max) = values.fold((f32::INFINITY, 0.0f32), |(min, max), v| (min.min(v), max.max(v)));
+    if max <= 0.0 {
+        1.0
+    } else {
+        min / max
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ring of `n` plains with players on the given indices
+    fn ring(n: usize, starts: &[usize]) -> GameState {
+        let mut state = MapGenerator::new(n, starts.len()).generate_seeded(1);
+        let ids: Vec<Uuid> = state.territories.iter().map(|t| t.id).collect();
+        for (i, territory) in state.territories.iter_mut().enumerate() {
+            territory.owner = None;
+            territory.terrain = TerrainType::Plains;
+            territory.neighbors = vec![ids[(i + 1) % n], ids[(i + n - 1) % n]];
+        }
+        for (player, &idx) in state.players.iter().zip(starts) {
+            state.territories[idx].owner = Some(player.id);
+        }
+        state
+    }
+
+    #[test]
+    fn test_symmetric_map_is_perfectly_fair() {
+        let report = analyze(&ring(12, &[0, 4, 8]), 2);
+        assert_eq!(report.starts.len(), 3);
+        assert!(report.starts.iter().all(|s| s.nearest_opponent_hops == Some(4) && s.neighbors == 2));
+        assert_eq!(report.score, 1.0);
+    }
+
+    #[test]
+    fn test_crowded_starts_lower_the_score() {
+        let mut state = ring(12, &[0, 2, 8]);
+        state.territories[10].terrain = TerrainType::Water;
+        let report = analyze(&state, 2);
+
+        let hops: Vec<_> = report.starts.iter().map(|s| s.nearest_opponent_hops.unwrap()).collect();
+        assert_eq!(hops, [2, 2, 4]);
+        assert_eq!(report.distance_balance, 0.5);
+        assert!(report.terrain_balance < 1.0);
+        assert!(report.score < 1.0);
+    }
+}
@@ -0,0 +1,86 @@
+//! Food.
+//!
+//! Every player's homeland grows some food, each Plains territory grows more
+//! and a Farm more again, while every person eats. A population only grows
+//! while it has a surplus; the surplus fills a stockpile, and a shortfall
+//! draws it down. Once the stockpile is empty, the people the harvest can't
+//! feed starve a few at a time until it can.
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Food the player's land grows per second at normal speed
+    pub(super) fn food_production(&self, player_id: PlayerId) -> f32 {
+        let mut food = self.rules.base_food_per_second;
+        for territory in self.state.territories.iter().filter(|t| t.owner == Some(player_id.into())) {
+            if territory.terrain == TerrainType::Plains {
+                food += self.rules.plains_food_per_second;
+            }
+            if territory.working_building(self.state.tick) == Some(BuildingType::Farm) {
+                food += self.rules.farm_food_per_second * BuildingType::level_scale(territory.building_level);
+            }
+        }
+        food
+    }
+
+    /// Fill or draw down every stockpile, starving the unfed once it runs out
+    pub(super) fn update_food(&mut self) {
+        let tick_rate_sec = self.tick_rate_ms as f32 / 1000.0;
+        let food_per_person = self.rules.food_per_person;
+        let starvation_rate = self.rules.starvation_rate;
+        let max_food = self.rules.max_food;
+
+        for player in self.state.players.iter_mut().filter(|p| p.is_alive) {
+            let change = (player.food_per_second * tick_rate_sec) as i64;
+            if change >= 0 || player.food >= change.unsigned_abs() {
+                player.food = player.food.saturating_add_signed(change).min(max_food);
+                continue;
+            }
+
+            // The stockpile is empty: a share of the people beyond what the
+            // harvest feeds starves
+            player.food = 0;
+            if food_per_person > 0.0 {
+                let unfed = -player.food_per_second / food_per_person;
+                let starved = (unfed * starvation_rate * tick_rate_sec).ceil() as u64;
+                player.population = player.population.saturating_sub(starved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_population_needs_food_and_starves_without_it() {
+        let mut engine = GameEngine::new(MapGenerator::new(10, 2).generate_seeded(1), 100);
+        let player: PlayerId = engine.state.players[0].id.into();
+        for territory in &mut engine.state.territories {
+            territory.terrain = TerrainType::Forests;
+            territory.building = None;
+        }
+        engine.rules.base_food_per_second = 0.0;
+        engine.state.players[0].food = 0;
+
+        // No harvest: no growth, and the population starves
+        let population = engine.state.players[0].population;
+        engine.tick();
+        let player_state = engine.get_player(player).unwrap();
+        assert_eq!(player_state.population_growth_per_second, 0.0);
+        assert!(player_state.food_per_second < 0.0);
+        assert!(player_state.population < population);
+
+        // A Farm feeds them all with food to spare, which is stockpiled
+        let owned = engine.state.territories.iter().position(|t| t.owner == Some(player.into())).unwrap();
+        engine.state.territories[owned].building = Some(BuildingType::Farm);
+        engine.tick();
+        engine.tick();
+        let player_state = engine.get_player(player).unwrap();
+        assert!(player_state.food_per_second > 0.0 && player_state.population_growth_per_second > 0.0);
+        assert!(player_state.food > 0);
+    }
+}
@@ -0,0 +1,269 @@
+//! Stepped games for machine-learning agents.
+//!
+//! A `GymEnv` is a headless game its caller advances, in the style of an RL
+//! gym: `reset` deals a seeded map with the agent in the first slot and the
+//! built-in AIs in the rest, and `step` applies the agent's actions, plays a
+//! number of ticks and returns what the agent sees. Observations are flat
+//! arrays indexed the same way every step, so they turn into tensors as is.
+//! Nothing runs between steps, however long the agent takes to decide.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::{Difficulty, GameEngine, GameRules, MapGenerator};
+
+/// Upper bound on ticks played in one step
+pub const MAX_STEP_TICKS: u32 = 1_000;
+const GYM_TICK_RATE_MS: u64 = 100;
+
+/// Settings for a new environment
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GymConfig {
+    /// Seeds the map and every random choice the game makes
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default = "default_territories")]
+    pub territories: usize,
+    /// The agent and the AIs it plays against
+    #[serde(default = "default_players")]
+    pub players: usize,
+    #[serde(default)]
+    pub style: MapStyle,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+fn default_territories() -> usize {
+    40
+}
+
+fn default_players() -> usize {
+    4
+}
+
+impl GymConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.players < 2 || self.territories < self.players {
+            return Err(anyhow!("Need at least two players and a territory for each"));
+        }
+        Ok(())
+    }
+}
+
+/// The game as the agent sees it. Territory arrays follow `territory_ids`
+/// and player arrays follow `player_ids`, whose first entry is the agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Observation {
+    pub tick: u64,
+    #[schema(value_type = Vec<String>)]
+    pub territory_ids: Vec<Uuid>,
+    /// Index of each territory's owner in the player arrays, -1 if neutral
+    pub owner: Vec<i32>,
+    pub troops: Vec<u32>,
+    /// Terrain as 0 plains, 1 mountains, 2 forests, 3 water
+    pub terrain: Vec<u8>,
+    /// Building as its position in `BuildingType`, -1 if none
+    pub building: Vec<i32>,
+    /// Borders as pairs of territory indices, each listed once
+    pub edges: Vec<[u32; 2]>,
+    #[schema(value_type = Vec<String>)]
+    pub player_ids: Vec<Uuid>,
+    pub gold: Vec<u64>,
+    pub population: Vec<u64>,
+    pub army: Vec<u64>,
+    pub territories_held: Vec<u32>,
+    pub alive: Vec<bool>,
+}
+
+/// Actions for one step and how far to play it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StepRequest {
+    /// Commands as the agent would send them over the WebSocket, applied in order
+    #[serde(default)]
+    pub actions: Vec<ClientMessage>,
+    /// Ticks to play after the actions
+    #[serde(default = "default_step_ticks")]
+    pub ticks: u32,
+}
+
+fn default_step_ticks() -> u32 {
+    1
+}
+
+/// What a step led to
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StepResult {
+    pub observation: Observation,
+    /// Territories the agent gained over the step, negative if it lost ground
+    pub reward: f64,
+    /// The game is over or the agent was eliminated; further steps change nothing
+    pub done: bool,
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub winner: Option<Uuid>,
+    /// Why each rejected action was rejected, by its position in `actions`
+    pub errors: Vec<ActionError>,
+}
+
+/// An action the game refused
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ActionError {
+    pub index: usize,
+    pub message: String,
+}
+
+/// A headless game an agent plays in steps
+pub struct GymEnv {
+    engine: GameEngine,
+    agent: PlayerId,
+    winner: Option<Uuid>,
+}
+
+impl GymEnv {
+    /// Deal a fresh game and show the agent where it starts
+    pub fn reset(config: &GymConfig) -> Result<(Self, Observation)> {
+        config.validate()?;
+        let state = MapGenerator::new(config.territories, config.players)
+            .with_style(config.style)
+            .generate_seeded(config.seed);
+        let mut engine = GameEngine::with_rules(state, GYM_TICK_RATE_MS, GameRules::for_difficulty(config.difficulty));
+        engine.reseed(config.seed);
+        let agent = engine.state.players.iter().find(|p| !p.is_ai).map(|p| p.id.into())
+            .ok_or_else(|| anyhow!("The map has no slot for the agent"))?;
+        let env = GymEnv { engine, agent, winner: None };
+        let observation = env.observe();
+        Ok((env, observation))
+    }
+
+    fn done(&self) -> bool {
+        self.winner.is_some() || !self.engine.get_player(self.agent).is_ok_and(|p| p.is_alive)
+    }
+
+    /// Apply the agent's actions, then play up to `ticks` ticks with the AIs
+    /// taking their turns
+    pub fn step(&mut self, request: &StepRequest) -> Result<StepResult> {
+        if request.ticks > MAX_STEP_TICKS {
+            return Err(anyhow!("A step plays at most {} ticks", MAX_STEP_TICKS));
+        }
+        let held = |engine: &GameEngine, agent| engine.get_player(agent).map_or(0, |p| p.territories_controlled) as f64;
+        let before = held(&self.engine, self.agent);
+
+        let mut errors = Vec::new();
+        for (index, action) in request.actions.iter().enumerate() {
+            if let Err(e) = self.act(action) {
+                errors.push(ActionError { index, message: e.to_string() });
+            }
+        }
+
+        for _ in 0..request.ticks {
+            if self.done() {
+                break;
+            }
+            self.engine.tick();
+            self.engine.tick_ai();
+            self.engine.take_events();
+            if let Some(stats) = self.engine.check_game_over() {
+                self.winner = Some(stats.winner);
+            }
+        }
+
+        Ok(StepResult {
+            reward: held(&self.engine, self.agent) - before,
+            done: self.done(),
+            winner: self.winner,
+            errors,
+            observation: self.observe(),
+        })
+    }
+
+    fn act(&mut self, action: &ClientMessage) -> Result<()> {
+        if self.done() {
+            return Err(anyhow!("The game is over"));
+        }
+        match action {
+            ClientMessage::PauseGame | ClientMessage::ResumeGame | ClientMessage::SetGameSpeed { .. } => {
+                Err(anyhow!("Pausing and game speed don't apply to a stepped game"))
+            }
+            action if action.changes_game() => self.engine.apply_command(self.agent, action),
+            action => Err(anyhow!("{} is not an action", action.name())),
+        }
+    }
+
+    /// The game as flat arrays
+    pub fn observe(&self) -> Observation {
+        let state = &self.engine.state;
+        let territory_index = |id: &Uuid| state.territories.iter().position(|t| t.id == *id);
+        let player_index = |id: &Uuid| state.players.iter().position(|p| p.id == *id);
+
+        let mut edges = Vec::new();
+        for (i, territory) in state.territories.iter().enumerate() {
+            for neighbor in territory.neighbors.iter().filter_map(territory_index) {
+                if i < neighbor {
+                    edges.push([i as u32, neighbor as u32]);
+                }
+            }
+        }
+
+        Observation {
+            tick: state.tick,
+            territory_ids: state.territories.iter().map(|t| t.id).collect(),
+            owner: state.territories.iter().map(|t| t.owner.as_ref().and_then(player_index).map_or(-1, |i| i as i32)).collect(),
+            troops: state.territories.iter().map(|t| t.troops()).collect(),
+            terrain: state.territories.iter().map(|t| t.terrain as u8).collect(),
+            building: state.territories.iter().map(|t| t.building.map_or(-1, |b| b as i32)).collect(),
+            edges,
+            player_ids: state.players.iter().map(|p| p.id).collect(),
+            gold: state.players.iter().map(|p| p.gold).collect(),
+            population: state.players.iter().map(|p| p.population).collect(),
+            army: state.players.iter().map(|p| p.troops()).collect(),
+            territories_held: state.players.iter().map(|p| p.territories_controlled).collect(),
+            alive: state.players.iter().map(|p| p.is_alive).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_play_out_the_same_for_a_seed() {
+        let config = GymConfig { seed: 3, territories: 30, players: 3, style: MapStyle::default(), difficulty: Difficulty::default() };
+        // Attack a land neighbor of the start; the pause and the query are refused
+        let play = || {
+            let (mut env, start) = GymEnv::reset(&config).unwrap();
+            assert_eq!(start.player_ids[0], Uuid::from(env.agent));
+            assert_eq!(start.owner.iter().filter(|&&o| o == 0).count(), 1);
+            assert!(!start.edges.is_empty() && start.edges.iter().all(|[a, b]| a < b));
+
+            let home = start.owner.iter().position(|&o| o == 0).unwrap();
+            let target = start.edges.iter()
+                .filter_map(|&[a, b]| if a as usize == home { Some(b) } else if b as usize == home { Some(a) } else { None })
+                .find(|&t| start.terrain[t as usize] != 3)
+                .unwrap();
+            let step = StepRequest {
+                actions: vec![
+                    ClientMessage::Attack { from: start.territory_ids[home], to: start.territory_ids[target as usize] },
+                    ClientMessage::PauseGame,
+                    ClientMessage::GetGameState,
+                ],
+                ticks: 50,
+            };
+            let result = env.step(&step).unwrap();
+            assert!(env.step(&StepRequest { actions: Vec::new(), ticks: MAX_STEP_TICKS + 1 }).is_err());
+            result
+        };
+
+        let result = play();
+        assert_eq!(result.observation.tick, 50);
+        assert_eq!(result.errors.iter().map(|e| e.index).collect::<Vec<_>>(), vec![1, 2]);
+
+        // A second environment on the same seed plays exactly the same game, ids aside
+        let repeat = play();
+        let anonymous = |o: &Observation| Observation { territory_ids: Vec::new(), player_ids: Vec::new(), ..o.clone() };
+        assert_eq!(anonymous(&repeat.observation), anonymous(&result.observation));
+        assert_eq!(repeat.reward, result.reward);
+    }
+}
@@ -0,0 +1,211 @@
+//! Noise-based terrain.
+//!
+//! Two layers of fractal Perlin noise give every point of the map an elevation
+//! and a moisture value. Low ground floods into lakes and seas, high ground
+//! rises into mountain ranges, and moisture decides between forest belts and
+//! open plains, so terrain forms coherent continents instead of a patchwork.
+//!
+//! Each layout shapes the same noise differently: continents use it as is,
+//! islands sample it finely under a higher sea, and pangaea lifts the middle
+//! of the map and sinks its rim so all land joins up inside one ocean.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::types::*;
+
+const SEA_LEVEL: f32 = 0.42;
+/// Mountains start this far above the sea
+const MOUNTAIN_HEIGHT: f32 = 0.15;
+const FOREST_MOISTURE: f32 = 0.52;
+/// Features per map width for the first octave
+const BASE_FREQUENCY: f32 = 2.5;
+const OCTAVES: u32 = 4;
+
+/// Sea level and feature size of the island layout
+const ISLAND_SEA_LEVEL: f32 = 0.5;
+const ISLAND_FREQUENCY: f32 = 5.0;
+/// Share of a pangaea's elevation that comes from the distance to the map center
+const PANGAEA_CENTER_WEIGHT: f32 = 0.55;
+
+/// Elevation and moisture over the unit square
+pub struct Heightmap {
+    elevation: Perlin,
+    moisture: Perlin,
+    frequency: f32,
+    sea_level: f32,
+    /// How strongly land is pulled toward the map center
+    center_weight: f32,
+}
+
+impl Heightmap {
+    pub fn new(rng: &mut impl Rng) -> Self {
+        Self {
+            elevation: Perlin::new(rng),
+            moisture: Perlin::new(rng),
+            frequency: BASE_FREQUENCY,
+            sea_level: SEA_LEVEL,
+            center_weight: 0.0,
+        }
+    }
+
+    /// Heightmap shaped for a map style; `None` for styles that roll terrain per territory
+    pub fn for_style(style: MapStyle, rng: &mut impl Rng) -> Option<Self> {
+        let heightmap = match style {
+            MapStyle::Scattered => return None,
+            MapStyle::Continents => Self::new(rng),
+            MapStyle::Islands => Self { frequency: ISLAND_FREQUENCY, sea_level: ISLAND_SEA_LEVEL, ..Self::new(rng) },
+            MapStyle::Pangaea => Self { center_weight: PANGAEA_CENTER_WEIGHT, ..Self::new(rng) },
+        };
+        Some(heightmap)
+    }
+
+    /// Elevation at a map position, roughly within 0..1
+    pub fn elevation(&self, x: f32, y: f32) -> f32 {
+        let noise = self.elevation.fractal(x * self.frequency, y * self.frequency);
+        if self.center_weight == 0.0 {
+            return noise;
+        }
+        // 1 in the middle, 0 at the corners
+        let center = 1.0 - ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt() / std::f32::consts::FRAC_1_SQRT_2;
+        noise * (1.0 - self.center_weight) + center * self.center_weight
+    }
+
+    pub fn moisture(&self, x: f32, y: f32) -> f32 {
+        self.moisture.fractal(x * self.frequency, y * self.frequency)
+    }
+
+    pub fn terrain(&self, x: f32, y: f32) -> TerrainType {
+        let elevation = self.elevation(x, y);
+        if elevation < self.sea_level {
+            TerrainType::Water
+        } else if elevation > self.sea_level + MOUNTAIN_HEIGHT {
+            TerrainType::Mountains
+        } else if self.moisture(x, y) > FOREST_MOISTURE {
+            TerrainType::Forests
+        } else {
+            TerrainType::Plains
+        }
+    }
+}
+
+/// Classic 2D gradient noise over a shuffled permutation table
+struct Perlin {
+    permutation: [u8; 512],
+}
+
+impl Perlin {
+    fn new(rng: &mut impl Rng) -> Self {
+        let mut table: Vec<u8> = (0..=255).collect();
+        table.shuffle(rng);
+        let mut permutation = [0; 512];
+        for (i, slot) in permutation.iter_mut().enumerate() {
+            *slot = table[i % 256];
+        }
+        Self { permutation }
+    }
+
+    /// Noise in about -1..1
+    fn noise(&self, x: f32, y: f32) -> f32 {
+        let (xi, yi) = (x.floor() as i32 & 255, y.floor() as i32 & 255);
+        let (xf, yf) = (x - x.floor(), y - y.floor());
+        let (u, v) = (fade(xf), fade(yf));
+
+        let p = &self.permutation;
+        let hash = |dx: i32, dy: i32| p[p[(xi + dx) as usize] as usize + (yi + dy) as usize];
+        let top = lerp(gradient(hash(0, 0), xf, yf), gradient(hash(1, 0), xf - 1.0, yf), u);
+        let bottom = lerp(gradient(hash(0, 1), xf, yf - 1.0), gradient(hash(1, 1), xf - 1.0, yf - 1.0), u);
+        lerp(top, bottom, v)
+    }
+
+    /// Octaves of noise at doubling frequency and halving weight, mapped to 0..1
+    fn fractal(&self, x: f32, y: f32) -> f32 {
+        let (mut total, mut amplitude, mut frequency, mut weight) = (0.0, 1.0, 1.0, 0.0);
+        for _ in 0..OCTAVES {
+            total += self.noise(x * frequency, y * frequency) * amplitude;
+            weight += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        (total / weight * 0.5 + 0.5).clamp(0.0, 1.0)
+    }
+}
+
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + t * (b - a)
+}
+
+fn gradient(hash: u8, x: f32, y: f32) -> f32 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_noise_is_smooth_and_bounded() {
+        let heightmap = Heightmap::new(&mut StdRng::seed_from_u64(5));
+        for i in 0..100 {
+            let (x, y) = (i as f32 / 100.0, (i * 7 % 100) as f32 / 100.0);
+            let elevation = heightmap.elevation(x, y);
+            assert!((0.0..=1.0).contains(&elevation));
+            // Nearby points have nearly the same height
+            assert!((heightmap.elevation(x + 0.001, y) - elevation).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_terrain_forms_contiguous_areas() {
+        let heightmap = Heightmap::new(&mut StdRng::seed_from_u64(5));
+        let grid: Vec<Vec<TerrainType>> = (0..40)
+            .map(|y| (0..40).map(|x| heightmap.terrain(x as f32 / 40.0, y as f32 / 40.0)).collect())
+            .collect();
+
+        // Coherent terrain mostly matches its neighbor; independent rolls would
+        // match well under half the time
+        let same = (0..40).flat_map(|y| (1..40).map(move |x| (x, y))).filter(|&(x, y)| grid[y][x] == grid[y][x - 1]).count();
+        assert!(same as f32 / (40.0 * 39.0) > 0.7);
+        assert_eq!("Continents".parse::<MapStyle>().unwrap(), MapStyle::Continents);
+    }
+
+    #[test]
+    fn test_layouts_place_water_differently() {
+        let water_share = |style: MapStyle, cells: &dyn Fn(usize, usize) -> bool| {
+            let (mut water, mut total) = (0, 0);
+            for seed in 0..10 {
+                let heightmap = Heightmap::for_style(style, &mut StdRng::seed_from_u64(seed)).unwrap();
+                for (x, y) in (0..20).flat_map(|y| (0..20).map(move |x| (x, y))).filter(|&(x, y)| cells(x, y)) {
+                    total += 1;
+                    water += (heightmap.terrain(x as f32 / 19.0, y as f32 / 19.0) == TerrainType::Water) as usize;
+                }
+            }
+            water as f32 / total as f32
+        };
+        let everywhere = |_, _| true;
+        let rim = |x: usize, y: usize| x < 3 || y < 3 || x > 16 || y > 16;
+        let middle = |x: usize, y: usize| (6..14).contains(&x) && (6..14).contains(&y);
+
+        // A pangaea is ringed by sea around dry land
+        assert!(water_share(MapStyle::Pangaea, &rim) > 0.6);
+        assert!(water_share(MapStyle::Pangaea, &middle) < 0.05);
+        assert!(water_share(MapStyle::Islands, &everywhere) > water_share(MapStyle::Continents, &everywhere));
+        assert!(Heightmap::for_style(MapStyle::Scattered, &mut StdRng::seed_from_u64(1)).is_none());
+        assert_eq!("pangaea".parse::<MapStyle>().unwrap(), MapStyle::Pangaea);
+    }
+}
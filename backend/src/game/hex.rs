@@ -0,0 +1,67 @@
+//! Hexagonal lattice maps.
+//!
+//! Territories sit on a rectangle of pointy-top hexes, every other row shifted
+//! by half a hex. Positions are axial coordinates (q, r): neighboring hexes
+//! differ by (±1, 0), (0, ±1), (1, -1) or (-1, 1), so every hex away from the
+//! edge of the map borders exactly six others.
+
+use std::collections::HashMap;
+
+pub type Axial = (i32, i32);
+
+const DIRECTIONS: [Axial; 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+
+/// Corners of the hex at the origin, counter-clockwise; each is the middle of
+/// three neighboring hex centers
+const CORNERS: [(f32, f32); 6] = [
+    (2.0 / 3.0, -1.0 / 3.0),
+    (1.0 / 3.0, 1.0 / 3.0),
+    (-1.0 / 3.0, 2.0 / 3.0),
+    (-2.0 / 3.0, 1.0 / 3.0),
+    (-1.0 / 3.0, -1.0 / 3.0),
+    (1.0 / 3.0, -2.0 / 3.0),
+];
+
+/// One hex of the lattice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hex {
+    pub axial: Axial,
+    /// Where the hex lies on the unit square, for sampling terrain
+    pub uv: (f32, f32),
+}
+
+/// `count` hexes filling a roughly square rectangle row by row
+pub fn lattice(count: usize) -> Vec<Hex> {
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = count.div_ceil(columns);
+    (0..count)
+        .map(|i| {
+            let (column, row) = ((i % columns) as i32, (i / columns) as i32);
+            let shift = (row & 1) as f32 / 2.0;
+            Hex {
+                axial: (column - (row - (row & 1)) / 2, row),
+                uv: (
+                    (column as f32 + shift + 0.5) / (columns as f32 + 0.5),
+                    (row as f32 + 0.5) / rows as f32,
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Indices of every hex's neighbors in the lattice
+pub fn neighbors(hexes: &[Hex]) -> Vec<Vec<usize>> {
+    let index: HashMap<Axial, usize> = hexes.iter().enumerate().map(|(i, hex)| (hex.axial, i)).collect();
+    hexes
+        .iter()
+        .map(|hex| {
+            let (q, r) = hex.axial;
+            DIRECTIONS.iter().filter_map(|(dq, dr)| index.get(&(q + dq, r + dr)).copied()).collect()
+        })
+        .collect()
+}
+
+/// Outline of a hex in axial coordinates, counter-clockwise
+pub fn corners((q, r): Axial) -> Vec<(f32, f32)> {
+    CORNERS.iter().map(|(dq, dr)| (q as f32 + dq, r as f32 + dr)).collect()
+}
@@ -0,0 +1,101 @@
+//! What each territory has been through.
+//!
+//! Every battle is tallied against the territory it was fought over, along
+//! with each change of owner, by conquest or gift. The tallies are kept for
+//! the whole game, saves included, so clients can ask which territories are
+//! the most contested and the summary can name them.
+
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+/// Territories the end-of-game summary names as most contested
+const SUMMARY_CONTESTED_TERRITORIES: usize = 3;
+
+impl GameEngine {
+    fn territory_record(&mut self, territory: Uuid) -> &mut TerritoryStats {
+        let name = self.get_territory(territory.into()).map(|t| t.name.clone()).unwrap_or_default();
+        self.territory_stats.entry(territory).or_insert_with(|| TerritoryStats {
+            territory_id: territory,
+            territory_name: name,
+            battles: 0,
+            times_changed_hands: 0,
+            troops_involved: 0,
+            last_battle_seconds: 0,
+        })
+    }
+
+    /// Tally a battle fought over `territory`
+    pub(super) fn record_territory_battle(&mut self, territory: Uuid, troops_involved: u32, conquered: bool) {
+        let seconds = self.state.game_time_seconds;
+        let record = self.territory_record(territory);
+        record.battles += 1;
+        record.troops_involved += troops_involved as u64;
+        record.last_battle_seconds = seconds;
+        if conquered {
+            record.times_changed_hands += 1;
+        }
+    }
+
+    /// Tally a change of owner without a battle
+    pub(super) fn record_change_of_hands(&mut self, territory: Uuid) {
+        self.territory_record(territory).times_changed_hands += 1;
+    }
+
+    /// Every territory that has seen a battle or changed hands, the most
+    /// contested first
+    pub fn territory_stats(&self) -> Vec<TerritoryStats> {
+        let mut stats: Vec<TerritoryStats> = self.territory_stats.values().cloned().collect();
+        stats.sort_by(|a, b| {
+            (b.battles, b.times_changed_hands, b.troops_involved)
+                .cmp(&(a.battles, a.times_changed_hands, a.troops_involved))
+                .then(a.territory_id.cmp(&b.territory_id))
+        });
+        stats
+    }
+
+    /// The territories fought over most, for the end-of-game summary
+    pub(super) fn most_contested(&self) -> Vec<TerritoryStats> {
+        self.territory_stats().into_iter().filter(|t| t.battles > 0).take(SUMMARY_CONTESTED_TERRITORIES).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_battles_leave_scars_on_their_territory() {
+        let mut state = MapGenerator::new(20, 2).generate_seeded(3);
+        let player = state.players[0].id;
+        let from = state.territories.iter().position(|t| t.owner == Some(player)).unwrap();
+        let target = state.territories[from].neighbors[0];
+        let to = state.territories.iter().position(|t| t.id == target).unwrap();
+        state.territories[to].owner = None;
+        state.territories[to].units = Units::infantry(100_000);
+
+        let mut engine = GameEngine::new(state, 100);
+        let (from, to) = (engine.state.territories[from].id, engine.state.territories[to].id);
+        for _ in 0..2 {
+            let result = engine.execute_attack(player.into(), from.into(), to.into()).unwrap();
+            assert!(!result.territory_conquered);
+        }
+        engine.get_territory_mut(to.into()).unwrap().units = Units::infantry(0);
+        engine.execute_attack(player.into(), from.into(), to.into()).unwrap();
+
+        let stats = engine.territory_stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].territory_id, stats[0].battles, stats[0].times_changed_hands), (to, 3, 1));
+        assert!(!stats[0].territory_name.is_empty());
+
+        // Tallies survive a save
+        let path = std::env::temp_dir().join(format!("scars-{}.json", Uuid::new_v4()));
+        engine.save(&path).unwrap();
+        let loaded = GameEngine::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.territory_stats(), stats);
+        assert_eq!(loaded.most_contested()[0].territory_id, to);
+    }
+}
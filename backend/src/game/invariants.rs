@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::types::PlayerId;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Verify engine invariants that must hold at every tick boundary
+    pub fn check_invariants(&self) -> Result<()> {
+        let mut owned: HashMap<Uuid, u32> = HashMap::new();
+        let mut stationed: HashMap<Uuid, u64> = HashMap::new();
+
+        for territory in &self.state.territories {
+            if let Some(owner) = territory.owner {
+                if self.get_player(owner.into()).is_err() {
+                    return Err(anyhow!("Territory {} owned by unknown player {}", territory.id, owner));
+                }
+                *owned.entry(owner).or_default() += 1;
+                *stationed.entry(owner).or_default() += territory.troops() as u64;
+            }
+
+            for neighbor in &territory.neighbors {
+                let other = self.get_territory((*neighbor).into())
+                    .map_err(|_| anyhow!("Territory {} has unknown neighbor {}", territory.id, neighbor))?;
+                if !other.neighbors.contains(&territory.id) {
+                    return Err(anyhow!("Adjacency {} -> {} is not bidirectional", territory.id, neighbor));
+                }
+            }
+        }
+        for battle in &self.state.battles {
+            *stationed.entry(battle.attacker_id).or_default() += battle.attackers.total() as u64;
+        }
+
+        for player in &self.state.players {
+            let territories = owned.get(&player.id).copied().unwrap_or(0);
+            if player.territories_controlled != territories {
+                return Err(anyhow!(
+                    "{} reports {} territories but owns {}",
+                    player.name, player.territories_controlled, territories
+                ));
+            }
+
+            if ![player.troop_ratio, player.trained_ratio, player.attack_ratio].iter().all(|r| (0.0..=1.0).contains(r)) {
+                return Err(anyhow!("{} has ratios out of range", player.name));
+            }
+
+            if player.troops() > player.population {
+                return Err(anyhow!("{} has more troops than population", player.name));
+            }
+
+            if stationed.get(&player.id).copied().unwrap_or(0) > player.population {
+                return Err(anyhow!("{} has more troops stationed than population", player.name));
+            }
+
+            if player.gold > self.rules.max_gold || player.population > self.rules.max_population {
+                return Err(anyhow!("{} exceeds the configured resource caps", player.name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that a player's garrisons and battles hold no more troops than their army,
+    /// and the army no more than their population. Holds after every attack;
+    /// between a tick and the next `distribute_troops` the army may have shrunk
+    pub fn audit_troops(&self, player_id: PlayerId) -> Result<()> {
+        let player = self.get_player(player_id)?;
+        let stationed: u64 = self.state.territories
+            .iter()
+            .filter(|t| t.owner == Some(player.id))
+            .map(|t| t.troops() as u64)
+            .sum::<u64>()
+            + self.committed_troops(player_id);
+        if stationed > player.troops() {
+            return Err(anyhow!("{} has {} troops stationed but an army of {}", player.name, stationed, player.troops()));
+        }
+        if player.troops() > player.population {
+            return Err(anyhow!("{} has more troops than population", player.name));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+    use crate::types::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_command(engine: &GameEngine, rng: &mut StdRng) -> ClientMessage {
+        let territories = &engine.state.territories;
+        let pick = |rng: &mut StdRng| -> Uuid {
+            // Mix in unknown IDs so missing-entity paths get exercised
+            if rng.gen_bool(0.1) {
+                Uuid::new_v4()
+            } else {
+                territories[rng.gen_range(0..territories.len())].id
+            }
+        };
+
+        match rng.gen_range(0..11) {
+            0 | 1 => {
+                let from = pick(rng);
+                let to = territories
+                    .iter()
+                    .find(|t| t.id == from)
+                    .and_then(|t| t.neighbors.first().copied())
+                    .filter(|_| rng.gen_bool(0.8))
+                    .unwrap_or_else(|| pick(rng));
+                ClientMessage::Attack { from, to }
+            }
+            2 if rng.gen_bool(0.3) => ClientMessage::UpgradeBuilding { territory: pick(rng) },
+            2 if rng.gen_bool(0.1) => ClientMessage::DemolishBuilding { territory: pick(rng) },
+            2 => ClientMessage::BuildStructure {
+                territory: pick(rng),
+                building_type: [BuildingType::City, BuildingType::DefensePost, BuildingType::GoldMine][rng.gen_range(0..3)],
+            },
+            // Ratios deliberately stray outside 0.0-1.0
+            3 => ClientMessage::SetTroopRatio { ratio: rng.gen_range(-0.5..1.5) },
+            4 => ClientMessage::SetAttackRatio { ratio: rng.gen_range(-0.5..1.5) },
+            5 => ClientMessage::SetGameSpeed { speed: rng.gen_range(0.0..10.0) },
+            6 => ClientMessage::SetUnitMix {
+                infantry: rng.gen_range(-0.5..1.0),
+                cavalry: rng.gen_range(0.0..1.0),
+                siege: rng.gen_range(0.0..1.0),
+            },
+            7 => {
+                let from = pick(rng);
+                let to = territories
+                    .iter()
+                    .find(|t| t.id == from)
+                    .and_then(|t| t.neighbors.first().copied())
+                    .unwrap_or_else(|| pick(rng));
+                ClientMessage::MoveTroops { from, to, amount: rng.gen_range(0..1_000) }
+            }
+            8 => {
+                let battle = engine.state.battles.first().map_or(rng.gen(), |b| b.id);
+                ClientMessage::Retreat { battle }
+            }
+            9 => {
+                let name = ["turtle", "all_in", "mine", ""][rng.gen_range(0..4)].to_string();
+                if rng.gen_bool(0.5) {
+                    ClientMessage::ApplyPreset { name }
+                } else {
+                    ClientMessage::SavePreset { name, troop_ratio: rng.gen_range(-0.5..1.5), attack_ratio: rng.gen_range(-0.5..1.5) }
+                }
+            }
+            _ => if rng.gen_bool(0.5) { ClientMessage::PauseGame } else { ClientMessage::ResumeGame },
+        }
+    }
+
+    fn apply(engine: &mut GameEngine, player_id: PlayerId, command: ClientMessage) {
+        match command {
+            ClientMessage::Attack { from, to } => {
+                let _ = engine.launch_attack(player_id, from.into(), to.into());
+            }
+            ClientMessage::Retreat { battle } => {
+                let _ = engine.retreat(player_id, battle);
+            }
+            ClientMessage::MoveTroops { from, to, amount } => {
+                let _ = engine.move_troops(player_id, from.into(), to.into(), amount);
+            }
+            ClientMessage::BuildStructure { territory, building_type } => {
+                let _ = engine.build_structure(player_id, territory.into(), building_type);
+            }
+            ClientMessage::UpgradeBuilding { territory } => {
+                let _ = engine.upgrade_building(player_id, territory.into());
+            }
+            ClientMessage::DemolishBuilding { territory } => {
+                let _ = engine.demolish_building(player_id, territory.into());
+            }
+            ClientMessage::SetTroopRatio { ratio } => {
+                let _ = engine.set_troop_ratio(player_id, ratio);
+            }
+            ClientMessage::SetAttackRatio { ratio } => {
+                let _ = engine.set_attack_ratio(player_id, ratio);
+            }
+            ClientMessage::SavePreset { name, troop_ratio, attack_ratio } => {
+                let _ = engine.save_preset(player_id, RatioPreset { name, troop_ratio, attack_ratio });
+            }
+            ClientMessage::ApplyPreset { name } => {
+                let _ = engine.apply_preset(player_id, &name);
+            }
+            ClientMessage::SetUnitMix { infantry, cavalry, siege } => {
+                let _ = engine.set_unit_mix(player_id, UnitMix { infantry, cavalry, siege });
+            }
+            ClientMessage::SetGameSpeed { speed } => engine.set_game_speed(speed),
+            ClientMessage::PauseGame => engine.set_paused(true),
+            ClientMessage::ResumeGame => engine.set_paused(false),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn test_invariants_hold_under_random_commands() {
+        for seed in 0..8 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let state = MapGenerator::new(40, 6).generate();
+            let mut engine = GameEngine::new(state, 100);
+            // Half the games fight battles over several ticks, and two in three lay siege to Defense Posts
+            engine.rules.battle_rounds = (seed % 2 * 3) as u32;
+            engine.rules.siege_ticks = seed % 3 * 20;
+            let human: PlayerId = engine.state.players[0].id.into();
+
+            for tick in 0..2_000 {
+                for _ in 0..rng.gen_range(0..4) {
+                    // Commands from unknown players must be rejected, not panic
+                    let player_id = if rng.gen_bool(0.05) { PlayerId::new(Uuid::new_v4()) } else { human };
+                    let command = random_command(&engine, &mut rng);
+                    apply(&mut engine, player_id, command);
+                }
+
+                engine.tick_ai();
+                engine.tick();
+
+                if let Err(e) = engine.check_invariants() {
+                    panic!("seed {} tick {}: {}", seed, tick, e);
+                }
+
+                if engine.check_game_over().is_some() {
+                    break;
+                }
+            }
+        }
+    }
+}
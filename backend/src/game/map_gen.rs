@@ -1,11 +1,35 @@
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::types::*;
+use super::heightmap::Heightmap;
+use super::hex;
+use super::names::name_map;
+use super::regions::annotate_map;
+use super::resources::place_resources;
+use super::rivers::lay_rivers;
+use super::voronoi;
+
+/// Player colors, assigned in join order
+pub const PLAYER_COLORS: [&str; 9] = [
+    "#FF0000", "#00FF00", "#0000FF", "#FFFF00",
+    "#FF00FF", "#00FFFF", "#FF8800", "#8800FF", "#00FF88",
+];
 
 pub struct MapGenerator {
     pub territory_count: usize,
     pub player_count: usize,
+    /// Leading player slots reserved for humans
+    pub human_slots: usize,
+    pub style: MapStyle,
+    /// Seed `generate` uses; a fresh random map each time if unset
+    pub seed: Option<u64>,
+    /// Shape of the territories
+    pub grid: MapGrid,
+    /// Personalities of the AI players in order; random past the end
+    pub ai_personalities: Vec<AIPersonality>,
 }
 
 impl MapGenerator {
@@ -13,30 +37,86 @@ impl MapGenerator {
         Self {
             territory_count,
             player_count,
+            human_slots: 1,
+            style: MapStyle::default(),
+            seed: None,
+            grid: MapGrid::default(),
+            ai_personalities: Vec::new(),
         }
     }
 
+    /// Pick how terrain is laid out
+    pub fn with_style(mut self, style: MapStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Reserve the first `slots` players for humans instead of one
+    pub fn with_human_slots(mut self, slots: usize) -> Self {
+        self.human_slots = slots;
+        self
+    }
+
+    /// Pick the shape of the territories
+    pub fn with_grid(mut self, grid: MapGrid) -> Self {
+        self.grid = grid;
+        self
+    }
+
+    /// Give the AI players these personalities instead of random ones
+    pub fn with_ai_personalities(mut self, personalities: Vec<AIPersonality>) -> Self {
+        self.ai_personalities = personalities;
+        self
+    }
+
+    /// Generate the same map on every call, for tournaments, bug reports and balance tests
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
     /// Generate a complete game with map and players
     pub fn generate(&self) -> GameState {
-        let mut rng = rand::thread_rng();
+        match self.seed {
+            Some(seed) => self.generate_seeded(seed),
+            None => self.generate_with(&mut rand::thread_rng()),
+        }
+    }
 
+    /// Generate the same game every time for a given seed
+    pub fn generate_seeded(&self, seed: u64) -> GameState {
+        self.generate_with(&mut StdRng::seed_from_u64(seed))
+    }
+
+    fn generate_with(&self, rng: &mut impl Rng) -> GameState {
         // Generate territories
-        let mut territories = self.generate_territories(&mut rng);
+        let mut territories = self.generate_territories(rng);
 
         // Generate players
-        let players = self.generate_players(&mut rng);
+        let mut players = self.generate_players(rng);
 
         // Assign starting territories to players
-        self.assign_starting_territories(&mut territories, &players, &mut rng);
+        self.assign_starting_territories(&mut territories, &mut players, rng);
 
-        GameState {
+        let mut state = GameState {
             territories,
             players,
             tick: 0,
             game_speed: 1.0,
             is_paused: false,
             game_time_seconds: 0,
-        }
+            regions: Vec::new(),
+            chokepoints: Vec::new(),
+            diplomacy: Diplomacy::default(),
+            grid: self.grid,
+            victory: Vec::new(),
+            battles: Vec::new(),
+        };
+        annotate_map(&mut state);
+        name_map(&mut state, rng);
+        lay_rivers(&mut state, rng);
+        place_resources(&mut state, rng);
+        state
     }
 
     fn generate_territories(&self, rng: &mut impl Rng) -> Vec<Territory> {
@@ -44,35 +124,64 @@ impl MapGenerator {
 
         // Generate territories in a grid-like pattern for connectivity
         let grid_size = (self.territory_count as f32).sqrt().ceil() as usize;
+        let heightmap = Heightmap::for_style(self.style, rng);
+        let hexes = (self.grid == MapGrid::Hex).then(|| hex::lattice(self.territory_count));
 
         for i in 0..self.territory_count {
-            let x = (i % grid_size) as f32 / grid_size as f32;
-            let y = (i / grid_size) as f32 / grid_size as f32;
-
-            // Add some randomness to positions
-            let x = (x + rng.gen::<f32>() * 0.1 - 0.05).clamp(0.0, 1.0);
-            let y = (y + rng.gen::<f32>() * 0.1 - 0.05).clamp(0.0, 1.0);
+            // Where the territory lies on the unit square, and its position
+            let ((x, y), position) = match &hexes {
+                Some(hexes) => (hexes[i].uv, (hexes[i].axial.0 as f32, hexes[i].axial.1 as f32)),
+                None => {
+                    let x = (i % grid_size) as f32 / grid_size as f32;
+                    let y = (i / grid_size) as f32 / grid_size as f32;
+
+                    // Add some randomness to positions
+                    let x = (x + rng.gen::<f32>() * 0.1 - 0.05).clamp(0.0, 1.0);
+                    let y = (y + rng.gen::<f32>() * 0.1 - 0.05).clamp(0.0, 1.0);
+                    ((x, y), (x, y))
+                }
+            };
 
-            let terrain = self.generate_terrain(x, y, rng);
+            let terrain = match &heightmap {
+                Some(heightmap) => heightmap.terrain(x, y),
+                None => self.generate_terrain(rng),
+            };
 
             territories.push(Territory {
                 id: Uuid::new_v4(),
+                name: String::new(),
                 owner: None,
                 terrain,
                 building: None,
-                troops: 0,
+                building_level: 1,
+                building_idle_until: 0,
+                units: Units::default(),
                 neighbors: Vec::new(),
-                position: (x, y),
+                position,
+                region: 0,
+                coastal: false,
+                borders: Vec::new(),
+                capital_of: None,
+                resource: None,
+                polygon: hexes.as_ref().map_or_else(Vec::new, |hexes| hex::corners(hexes[i].axial)),
             });
         }
 
-        // Generate neighbors based on distance
-        self.connect_territories(&mut territories);
+        // Neighbors are the territories whose cells touch
+        match &hexes {
+            Some(hexes) => {
+                for (i, neighbors) in hex::neighbors(hexes).into_iter().enumerate() {
+                    territories[i].neighbors = neighbors.into_iter().map(|j| territories[j].id).collect();
+                }
+            }
+            None => self.connect_territories(&mut territories),
+        }
+        bridge_components(&mut territories);
 
         territories
     }
 
-    fn generate_terrain(&self, _x: f32, _y: f32, rng: &mut impl Rng) -> TerrainType {
+    fn generate_terrain(&self, rng: &mut impl Rng) -> TerrainType {
         let rand_val: f32 = rng.gen();
 
         // Terrain distribution: 40% Plains, 25% Mountains, 25% Forests, 10% Water
@@ -87,105 +196,93 @@ impl MapGenerator {
         }
     }
 
+    /// Give each territory its Voronoi cell and border the territories whose cells share an edge
     fn connect_territories(&self, territories: &mut [Territory]) {
-        let n = territories.len();
-
-        for i in 0..n {
-            let pos_i = territories[i].position;
-            let mut distances: Vec<(usize, f32)> = Vec::new();
-
-            // Calculate distances to all other territories
-            for j in 0..n {
-                if i == j {
-                    continue;
-                }
-
-                let pos_j = territories[j].position;
-                let dx = pos_i.0 - pos_j.0;
-                let dy = pos_i.1 - pos_j.1;
-                let distance = (dx * dx + dy * dy).sqrt();
-
-                distances.push((j, distance));
-            }
-
-            // Sort by distance
-            distances.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
-
-            // Connect to 3-6 nearest neighbors
-            let neighbor_count = rand::thread_rng().gen_range(3..=6).min(distances.len());
-
-            for (j, _) in distances.iter().take(neighbor_count) {
-                territories[i].neighbors.push(territories[*j].id);
-            }
+        let positions: Vec<_> = territories.iter().map(|t| t.position).collect();
+        let mut borders = Vec::new();
+        for (i, cell) in voronoi::cells(&positions).into_iter().enumerate() {
+            borders.extend(cell.borders.iter().filter(|(j, _)| i < *j).map(|&(j, length)| (i, j, length)));
+            territories[i].polygon = cell.polygon;
         }
 
-        // Ensure connectivity is bidirectional
-        for i in 0..n {
-            let neighbors: Vec<_> = territories[i].neighbors.clone();
-            for neighbor_id in neighbors {
-                if let Some(neighbor_idx) = territories.iter().position(|t| t.id == neighbor_id) {
-                    if !territories[neighbor_idx].neighbors.contains(&territories[i].id) {
-                        territories[neighbor_idx].neighbors.push(territories[i].id);
-                    }
-                }
+        // Keep every territory at 6 or fewer neighbors by dropping the shortest borders
+        borders.sort_by(|a, b| b.2.total_cmp(&a.2));
+        for (i, j, _) in borders {
+            if territories[i].neighbors.len() >= 6 || territories[j].neighbors.len() >= 6 {
+                continue;
             }
+
+            // Connectivity is always bidirectional
+            let (id_i, id_j) = (territories[i].id, territories[j].id);
+            territories[i].neighbors.push(id_j);
+            territories[j].neighbors.push(id_i);
         }
     }
 
-    fn generate_players(&self, rng: &mut impl Rng) -> Vec<Player> {
-        let colors = vec![
-            "#FF0000", "#00FF00", "#0000FF", "#FFFF00",
-            "#FF00FF", "#00FFFF", "#FF8800", "#8800FF", "#00FF88",
-        ];
-
-        let ai_personalities = vec![
-            AIPersonality::Turtle,
-            AIPersonality::Aggressor,
-            AIPersonality::Balanced,
-            AIPersonality::Opportunist,
-            AIPersonality::Rusher,
-        ];
-
+    pub(super) fn generate_players(&self, rng: &mut impl Rng) -> Vec<Player> {
         let mut players = Vec::new();
 
-        // First player is human
-        players.push(Player {
-            id: Uuid::new_v4(),
-            name: "Player".to_string(),
-            is_ai: false,
-            ai_personality: None,
-            color: colors[0].to_string(),
-            population: 1000,
-            max_population: 10_000,
-            gold: 500,
-            troop_ratio: 0.5,
-            attack_ratio: 0.2,
-            territories_controlled: 0,
-            is_alive: true,
-        });
+        // The first slots are for humans
+        for i in 0..self.human_slots.min(self.player_count) {
+            let name = if i == 0 { "Player".to_string() } else { format!("Player {}", i + 1) };
+            players.push(Player {
+                id: Uuid::new_v4(),
+                name,
+                is_ai: false,
+                ai_personality: None,
+                color: PLAYER_COLORS[i % PLAYER_COLORS.len()].to_string(),
+                population: 1000,
+                max_population: 10_000,
+                gold: 500,
+                gold_per_second: 0.0,
+                population_growth_per_second: 0.0,
+                food: 500,
+                food_per_second: 0.0,
+                techs: Vec::new(),
+                research: None,
+                troop_ratio: 0.5,
+                trained_ratio: 0.5,
+                attack_ratio: 0.2,
+                unit_mix: UnitMix::default(),
+                presets: Vec::new(),
+                territories_controlled: 0,
+                is_alive: true,
+                latency_ms: None,
+            });
+        }
 
         // Rest are AI
-        for i in 1..self.player_count {
-            let personality = ai_personalities[rng.gen_range(0..ai_personalities.len())];
+        let humans = players.len();
+        for i in humans..self.player_count {
+            let personality = match self.ai_personalities.get(i - humans) {
+                Some(&personality) => personality,
+                None => random_personality(rng),
+            };
+            let starting_ratio = starting_troop_ratio(personality);
 
             players.push(Player {
                 id: Uuid::new_v4(),
-                name: format!("AI {}", i),
+                name: format!("AI {}", i + 1 - self.human_slots),
                 is_ai: true,
                 ai_personality: Some(personality),
-                color: colors[i % colors.len()].to_string(),
+                color: PLAYER_COLORS[i % PLAYER_COLORS.len()].to_string(),
                 population: 1000,
                 max_population: 10_000,
                 gold: 500,
-                troop_ratio: match personality {
-                    AIPersonality::Rusher => 1.0,
-                    AIPersonality::Turtle => 0.3,
-                    AIPersonality::Aggressor => 0.7,
-                    _ => 0.5,
-                },
+                gold_per_second: 0.0,
+                population_growth_per_second: 0.0,
+                food: 500,
+                food_per_second: 0.0,
+                techs: Vec::new(),
+                research: None,
+                troop_ratio: starting_ratio,
+                trained_ratio: starting_ratio,
                 attack_ratio: 0.2,
+                unit_mix: UnitMix::default(),
+                presets: Vec::new(),
                 territories_controlled: 0,
                 is_alive: true,
+                latency_ms: None,
             });
         }
 
@@ -195,7 +292,7 @@ impl MapGenerator {
     fn assign_starting_territories(
         &self,
         territories: &mut [Territory],
-        players: &[Player],
+        players: &mut [Player],
         rng: &mut impl Rng,
     ) {
         // Each player gets ONE starting territory
@@ -203,29 +300,132 @@ impl MapGenerator {
         let territory_count = territories.len();
         let step = territory_count / players.len();
 
-        for (i, player) in players.iter().enumerate() {
-            // Pick a starting territory roughly evenly distributed
+        for (i, player) in players.iter_mut().enumerate() {
+            // Pick a starting territory roughly evenly distributed, on the next free land
             let start_idx = (i * step + rng.gen_range(0..step.min(5))) % territory_count;
+            let start_idx = (0..territory_count)
+                .map(|k| (start_idx + k) % territory_count)
+                .find(|&idx| territories[idx].owner.is_none() && territories[idx].terrain != TerrainType::Water)
+                .unwrap_or(start_idx);
 
             territories[start_idx].owner = Some(player.id);
+            territories[start_idx].capital_of = Some(player.id);
             // Start with 500 troops (half of starting population)
-            territories[start_idx].troops = 500;
+            territories[start_idx].units = Units::infantry(500);
+            player.territories_controlled = 1;
         }
 
         // All other territories remain neutral (owner = None)
         // They will have minimal troops for defense
         for territory in territories.iter_mut() {
             if territory.owner.is_none() {
-                // Neutral territories have small defensive force
-                territory.troops = rng.gen_range(50..150);
+                // Neutral territories have small defensive force; nobody holds the water
+                let troops = rng.gen_range(50..150);
+                territory.units = Units::infantry(if territory.terrain == TerrainType::Water { 0 } else { troops });
             }
         }
     }
 }
 
+/// Join parts of the map no border reaches with the shortest links between
+/// them, preferring territories that have room for another neighbor, so no
+/// player starts stranded
+fn bridge_components(territories: &mut [Territory]) {
+    fn find(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let index: HashMap<Uuid, usize> = territories.iter().enumerate().map(|(i, t)| (t.id, i)).collect();
+    let mut parent: Vec<usize> = (0..territories.len()).collect();
+    let mut components = territories.len();
+    for (i, territory) in territories.iter().enumerate() {
+        for neighbor in &territory.neighbors {
+            let (a, b) = (find(&mut parent, i), find(&mut parent, index[neighbor]));
+            if a != b {
+                parent[a] = b;
+                components -= 1;
+            }
+        }
+    }
+    if components <= 1 {
+        return;
+    }
+
+    let distance = |a: (f32, f32), b: (f32, f32)| (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2);
+    let mut links: Vec<(usize, usize, f32)> = Vec::new();
+    for i in 0..territories.len() {
+        for j in i + 1..territories.len() {
+            if find(&mut parent, i) != find(&mut parent, j) {
+                links.push((i, j, distance(territories[i].position, territories[j].position)));
+            }
+        }
+    }
+    links.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    for crowded_ok in [false, true] {
+        for &(i, j, _) in &links {
+            if components == 1 {
+                return;
+            }
+            let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+            let crowded = territories[i].neighbors.len() >= 6 || territories[j].neighbors.len() >= 6;
+            if a == b || (crowded && !crowded_ok) {
+                continue;
+            }
+            parent[a] = b;
+            components -= 1;
+            let (id_i, id_j) = (territories[i].id, territories[j].id);
+            territories[i].neighbors.push(id_j);
+            territories[j].neighbors.push(id_i);
+        }
+    }
+}
+
+/// Pick an AI personality uniformly at random
+pub fn random_personality(rng: &mut impl Rng) -> AIPersonality {
+    const PERSONALITIES: [AIPersonality; 5] = [
+        AIPersonality::Turtle,
+        AIPersonality::Aggressor,
+        AIPersonality::Balanced,
+        AIPersonality::Opportunist,
+        AIPersonality::Rusher,
+    ];
+    PERSONALITIES[rng.gen_range(0..PERSONALITIES.len())]
+}
+
+/// Troop ratio an AI starts with; starting armies are already trained
+pub fn starting_troop_ratio(personality: AIPersonality) -> f32 {
+    match personality {
+        AIPersonality::Rusher => 1.0,
+        AIPersonality::Turtle => 0.3,
+        AIPersonality::Aggressor => 0.7,
+        _ => 0.5,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
+
+    /// Territories reachable from the first one
+    fn reachable(territories: &[Territory]) -> usize {
+        let by_id: HashMap<Uuid, &Territory> = territories.iter().map(|t| (t.id, t)).collect();
+        let mut seen = HashSet::from([territories[0].id]);
+        let mut stack = vec![&territories[0]];
+        while let Some(territory) = stack.pop() {
+            for neighbor in &territory.neighbors {
+                if seen.insert(*neighbor) {
+                    stack.push(by_id[neighbor]);
+                }
+            }
+        }
+        seen.len()
+    }
 
     #[test]
     fn test_map_generation() {
@@ -234,7 +434,7 @@ mod tests {
 
         assert_eq!(state.territories.len(), 50);
         assert_eq!(state.players.len(), 5);
-        assert_eq!(state.players[0].is_ai, false);
+        assert!(!state.players[0].is_ai);
 
         // Check all territories have neighbors
         for territory in &state.territories {
@@ -246,4 +446,76 @@ mod tests {
         let owned_count = state.territories.iter().filter(|t| t.owner.is_some()).count();
         assert_eq!(owned_count, 5);
     }
+
+    #[test]
+    fn test_maps_are_one_connected_piece() {
+        for seed in 0..20 {
+            let state = MapGenerator::new(60, 4).generate_seeded(seed);
+            assert_eq!(reachable(&state.territories), 60, "seed {}", seed);
+        }
+
+        // Two islands far apart are joined by their closest pair
+        let mut territories = MapGenerator::new(8, 2).generate_seeded(1).territories;
+        for (i, territory) in territories.iter_mut().enumerate() {
+            territory.neighbors.clear();
+            territory.position = (i as f32 + if i < 4 { 0.0 } else { 10.0 }, 0.0);
+        }
+        let ids: Vec<Uuid> = territories.iter().map(|t| t.id).collect();
+        for i in [0, 1, 2, 4, 5, 6] {
+            territories[i].neighbors.push(ids[i + 1]);
+            territories[i + 1].neighbors.push(ids[i]);
+        }
+        bridge_components(&mut territories);
+        assert_eq!(reachable(&territories), 8);
+        assert_eq!((territories[3].neighbors.last(), territories[4].neighbors.last()), (Some(&ids[4]), Some(&ids[3])));
+    }
+
+    #[test]
+    fn test_hex_grid() {
+        let state = MapGenerator::new(49, 4).with_grid(MapGrid::Hex).generate();
+        assert_eq!(state.grid, MapGrid::Hex);
+
+        let by_id: HashMap<Uuid, &Territory> = state.territories.iter().map(|t| (t.id, t)).collect();
+        for territory in &state.territories {
+            let (q, r) = territory.position;
+            assert_eq!((q.fract(), r.fract()), (0.0, 0.0));
+            assert_eq!(territory.polygon.len(), 6);
+
+            // Axial neighbors are one step away in one of six directions
+            for neighbor in &territory.neighbors {
+                let (nq, nr) = by_id[neighbor].position;
+                let step = ((nq - q) as i32, (nr - r) as i32);
+                assert!([(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)].contains(&step));
+                assert!(by_id[neighbor].neighbors.contains(&territory.id));
+            }
+        }
+
+        // Inside a 7 by 7 rectangle, every hex has all six neighbors
+        let interior: Vec<_> =
+            state.territories.iter().enumerate().filter(|(i, _)| (1..6).contains(&(i % 7)) && (1..6).contains(&(i / 7))).collect();
+        assert_eq!(interior.len(), 25);
+        assert!(interior.iter().all(|(_, t)| t.neighbors.len() == 6));
+    }
+
+    #[test]
+    fn test_human_slots() {
+        let state = MapGenerator::new(30, 5).with_human_slots(2).generate();
+
+        let names: Vec<_> = state.players.iter().map(|p| (p.name.as_str(), p.is_ai)).collect();
+        assert_eq!(names, [("Player", false), ("Player 2", false), ("AI 1", true), ("AI 2", true), ("AI 3", true)]);
+    }
+
+    #[test]
+    fn test_seeded_generation_is_reproducible() {
+        let gen = MapGenerator::new(30, 4);
+        let (a, b) = (gen.generate_seeded(7), gen.generate_seeded(7));
+
+        let layout = |state: &GameState| -> Vec<_> {
+            state.territories.iter().map(|t| (t.position, t.terrain, t.troops(), t.neighbors.len())).collect()
+        };
+        assert_eq!(layout(&a), layout(&b));
+        assert_eq!(layout(&MapGenerator::new(30, 4).with_seed(7).generate()), layout(&a));
+        let personalities = |state: &GameState| -> Vec<_> { state.players.iter().map(|p| p.ai_personality).collect() };
+        assert_eq!(personalities(&a), personalities(&b));
+    }
 }
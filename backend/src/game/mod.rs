@@ -1,7 +1,45 @@
 pub mod state;
 pub mod combat;
+pub mod combat_model;
+pub mod battles;
+pub mod buildings;
 pub mod map_gen;
+pub mod heightmap;
+pub mod voronoi;
+pub mod hex;
+pub mod fairness;
+pub mod custom_map;
+pub mod names;
+pub mod regions;
+pub mod rivers;
+pub mod pathfinding;
+pub mod diplomacy;
+pub mod naval;
+pub mod capitals;
+pub mod resources;
+pub mod food;
+pub mod research;
+pub mod pacing;
+pub mod orders;
 pub mod ai;
+pub mod invariants;
+pub mod neutral;
+pub mod spawn;
+pub mod start_pick;
+pub mod draft;
+pub mod plugins;
+pub mod wasm_plugin;
+pub mod scripting;
+pub mod rules;
+pub mod simulation;
+pub mod gym;
+pub mod summary;
+pub mod history;
+pub mod checksum;
+pub mod delta;
+pub mod snapshot;
+pub mod replay;
 
 pub use state::*;
 pub use map_gen::*;
+pub use rules::*;
@@ -0,0 +1,89 @@
+//! Seeded place names for territories and regions.
+//!
+//! Names are built from fantasy-flavored syllables ("Iron" + "hold"), unique
+//! within a map. Only blank names are filled in, so maps that bring their own
+//! names keep them.
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::collections::HashSet;
+
+use crate::types::*;
+
+const PREFIXES: [&str; 32] = [
+    "Iron", "Stone", "Ash", "Frost", "Raven", "Storm", "Gold", "Silver", "Thorn", "Wolf", "Oak", "Ember",
+    "Black", "White", "Red", "Grey", "High", "Low", "Elder", "Mist", "Sun", "Moon", "Star", "Bright",
+    "Dusk", "Dawn", "Salt", "Copper", "Willow", "Hollow", "Crow", "Bear",
+];
+const SUFFIXES: [&str; 24] = [
+    "hold", "ford", "haven", "gate", "watch", "moor", "fell", "crest", "vale", "mere", "wick", "stead",
+    "march", "keep", "reach", "field", "wood", "brook", "cliff", "hollow", "spire", "barrow", "fall", "rock",
+];
+const REGION_NOUNS: [&str; 12] = [
+    "Marches", "Reach", "Wilds", "Expanse", "Highlands", "Lowlands", "Dominion", "Basin", "Frontier",
+    "Heartland", "Coast", "Downs",
+];
+
+/// Give every unnamed territory and region a name not used elsewhere on the map
+pub fn name_map(state: &mut GameState, rng: &mut impl Rng) {
+    let mut used: HashSet<String> = state
+        .territories
+        .iter()
+        .map(|t| t.name.clone())
+        .chain(state.regions.iter().map(|r| r.name.clone()))
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    for territory in state.territories.iter_mut().filter(|t| t.name.is_empty()) {
+        territory.name = unique(&mut used, rng, |rng| {
+            format!("{}{}", PREFIXES.choose(rng).unwrap(), SUFFIXES.choose(rng).unwrap())
+        });
+    }
+    for region in state.regions.iter_mut().filter(|r| r.name.is_empty()) {
+        region.name = unique(&mut used, rng, |rng| {
+            format!("The {} {}", PREFIXES.choose(rng).unwrap(), REGION_NOUNS.choose(rng).unwrap())
+        });
+    }
+}
+
+/// Draw names until one is free; numbered once the combinations run out
+fn unique<R: Rng>(used: &mut HashSet<String>, rng: &mut R, mut draw: impl FnMut(&mut R) -> String) -> String {
+    for _ in 0..20 {
+        let name = draw(rng);
+        if used.insert(name.clone()) {
+            return name;
+        }
+    }
+    let base = draw(rng);
+    let name = (2..).map(|n| format!("{} {}", base, n)).find(|name| !used.contains(name)).unwrap();
+    used.insert(name.clone());
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_names_are_unique_and_reproducible() {
+        let state = MapGenerator::new(400, 4).generate_seeded(9);
+        let names: HashSet<&str> = state.territories.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names.len(), 400);
+        assert!(state.regions.iter().all(|r| r.name.starts_with("The ")));
+
+        let again = MapGenerator::new(400, 4).generate_seeded(9);
+        assert_eq!(state.territories[17].name, again.territories[17].name);
+    }
+
+    #[test]
+    fn test_supplied_names_are_kept() {
+        let mut state = MapGenerator::new(10, 2).generate_seeded(1);
+        state.territories[0].name = "Home".to_string();
+        state.territories[1].name.clear();
+        name_map(&mut state, &mut rand::thread_rng());
+
+        assert_eq!(state.territories[0].name, "Home");
+        assert!(!state.territories[1].name.is_empty());
+    }
+}
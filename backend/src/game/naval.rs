@@ -0,0 +1,139 @@
+//! Crossing water.
+//!
+//! Water can't be held or marched through unless the game's rules say
+//! otherwise. A Harbor on a coastal territory lets its garrison sail instead:
+//! across up to `naval_range` connected water territories, landing on any land
+//! that borders the water it reached.
+
+use std::collections::{HashSet, VecDeque};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Land a Harbor at `from` can attack across water; empty without a Harbor
+    pub fn naval_targets(&self, from: TerritoryId) -> Vec<Uuid> {
+        let Ok(origin) = self.get_territory(from) else { return Vec::new() };
+        if origin.building != Some(BuildingType::Harbor) {
+            return Vec::new();
+        }
+        let water = |id: &Uuid| self.get_territory((*id).into()).ok().filter(|t| t.terrain == TerrainType::Water);
+
+        let mut seen: HashSet<Uuid> = HashSet::new();
+        let mut queue: VecDeque<(Uuid, u32)> = VecDeque::new();
+        for sea in origin.neighbors.iter().filter_map(water) {
+            seen.insert(sea.id);
+            queue.push_back((sea.id, 1));
+        }
+
+        let mut targets = Vec::new();
+        while let Some((id, hops)) = queue.pop_front() {
+            let Ok(sea) = self.get_territory(id.into()) else { continue };
+            for neighbor in &sea.neighbors {
+                if *neighbor == origin.id || !seen.insert(*neighbor) {
+                    continue;
+                }
+                if water(neighbor).is_some() {
+                    if hops < self.rules.naval_range {
+                        queue.push_back((*neighbor, hops + 1));
+                    }
+                } else {
+                    targets.push(*neighbor);
+                }
+            }
+        }
+        targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// A strip of land, two seas and land: 0 - 1~ - 2~ - 3, with 4 next to 0
+    fn strait() -> (GameEngine, PlayerId, Vec<Uuid>) {
+        let mut state = MapGenerator::new(5, 2).generate_seeded(1);
+        let ids: Vec<Uuid> = state.territories.iter().map(|t| t.id).collect();
+        let edges = [(0, 1), (1, 2), (2, 3), (0, 4)];
+        for (i, territory) in state.territories.iter_mut().enumerate() {
+            territory.owner = None;
+            territory.building = None;
+            territory.units = Units::infantry(10);
+            territory.terrain = if i == 1 || i == 2 { TerrainType::Water } else { TerrainType::Plains };
+            territory.neighbors = edges
+                .iter()
+                .filter_map(|&(a, b)| if a == i { Some(ids[b]) } else if b == i { Some(ids[a]) } else { None })
+                .collect();
+        }
+        let player = state.players[0].id;
+        state.territories[0].owner = Some(player);
+        state.territories[0].units = Units::infantry(500);
+        state.territories[0].coastal = true;
+        let mut engine = GameEngine::new(state, 100);
+        engine.state.players[0].gold = 10_000;
+        (engine, player.into(), ids)
+    }
+
+    #[test]
+    fn test_harbors_carry_attacks_across_water() {
+        let (mut engine, player, ids) = strait();
+        let error = |engine: &mut GameEngine, to: usize| engine.execute_attack(player, ids[0].into(), ids[to].into()).unwrap_err().to_string();
+
+        assert_eq!(error(&mut engine, 1), "Water can't be held; attack across it from a Harbor");
+        assert_eq!(error(&mut engine, 3), "Territories are not neighbors");
+        assert!(engine.build_structure(player, ids[4].into(), BuildingType::Harbor).is_err());
+
+        engine.build_structure(player, ids[0].into(), BuildingType::Harbor).unwrap();
+        assert_eq!(engine.naval_targets(ids[0].into()), [ids[3]]);
+        let result = engine.execute_attack(player, ids[0].into(), ids[3].into()).unwrap();
+        assert!(result.territory_conquered);
+
+        // Out of range when the crossing is longer than the rules allow
+        engine.rules.naval_range = 1;
+        assert!(engine.naval_targets(ids[0].into()).is_empty());
+
+        // Land-like water is the old behavior
+        engine.rules.passable_water = true;
+        engine.get_territory_mut(ids[1].into()).unwrap().units = Units::infantry(0);
+        assert!(engine.execute_attack(player, ids[0].into(), ids[1].into()).unwrap().territory_conquered);
+    }
+
+    #[test]
+    fn test_stranded_ai_takes_its_island_then_sails() {
+        let (mut engine, player, ids) = strait();
+        let rival = engine.state.players[1].id;
+        engine.state.players[0].is_ai = true;
+        engine.state.players[0].ai_personality = Some(AIPersonality::Balanced);
+        engine.get_territory_mut(ids[3].into()).unwrap().owner = Some(rival);
+        engine.state.players[1].territories_controlled = 1;
+        engine.state.players[1].is_ai = false;
+        // A defenseless rival, so only the water stands in the way
+        engine.state.players[1].troop_ratio = 0.0;
+        engine.state.players[1].trained_ratio = 0.0;
+
+        engine.state.players[0].gold = 0;
+        engine.reseed(5);
+
+        let owner = |engine: &GameEngine, i: usize| engine.get_territory(ids[i].into()).unwrap().owner;
+        let play_until = |engine: &mut GameEngine, i: usize| {
+            for _ in 0..3_000 {
+                engine.tick();
+                engine.tick_ai();
+                if owner(engine, i) == Some(player.into()) {
+                    break;
+                }
+            }
+        };
+        // Too poor for a Harbor, it takes the rest of its island first
+        play_until(&mut engine, 4);
+        assert_eq!(owner(&engine, 4), Some(player.into()));
+        assert_eq!(engine.get_territory(ids[0].into()).unwrap().building, None);
+
+        engine.state.players[0].gold = 10_000;
+        play_until(&mut engine, 3);
+        assert_eq!(engine.get_territory(ids[0].into()).unwrap().building, Some(BuildingType::Harbor));
+        assert_eq!(owner(&engine, 3), Some(player.into()));
+    }
+}
@@ -0,0 +1,101 @@
+use rand::Rng;
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Neutral territories never attack; they slowly fortify, and strong
+    /// neighboring neutrals occasionally merge into a single stronghold
+    pub(super) fn tick_neutrals(&mut self) {
+        let seconds = self.tick_rate_ms as f64 / 1000.0 * self.state.game_speed as f64;
+        let growth = self.rules.neutral_fortify_per_second as f64 * seconds;
+        let max_garrison = self.rules.neutral_max_garrison;
+
+        // Carry fractional growth across ticks so slow rates still add up
+        let tick = self.state.tick as f64;
+        let gained = ((growth * tick).floor() - (growth * (tick - 1.0)).floor()) as u32;
+
+        let passable_water = self.rules.passable_water;
+        let holdable = |t: &Territory| passable_water || t.terrain != TerrainType::Water;
+        for territory in self.state.territories.iter_mut().filter(|t| t.owner.is_none() && holdable(t)) {
+            // Neutrals only ever raise infantry
+            let room = max_garrison.saturating_sub(territory.troops());
+            territory.units.infantry = territory.units.infantry.saturating_add(gained.min(room));
+        }
+
+        if self.rules.neutral_merge_chance > 0.0 && self.rng.gen_bool(self.rules.neutral_merge_chance.min(1.0)) {
+            self.merge_neutral_garrisons();
+        }
+    }
+
+    fn merge_neutral_garrisons(&mut self) {
+        let threshold = self.rules.neutral_merge_threshold;
+        let strong: Vec<usize> = self.state.territories
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.owner.is_none() && t.troops() >= threshold)
+            .map(|(idx, _)| idx)
+            .collect();
+
+        if strong.is_empty() {
+            return;
+        }
+
+        let target = strong[self.rng.gen_range(0..strong.len())];
+        let donor = self.state.territories[target].neighbors
+            .iter()
+            .filter_map(|id| self.get_territory((*id).into()).ok())
+            .filter(|t| t.owner.is_none() && t.troops() >= threshold)
+            .max_by_key(|t| t.troops())
+            .map(|t| t.id);
+
+        if let Some(donor) = donor {
+            let Ok(donor) = self.get_territory_mut(donor.into()) else { return };
+            let moved = donor.units.portion(donor.troops() / 2);
+            donor.units.remove(moved);
+            self.state.territories[target].units.add(moved);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{Difficulty, GameEngine, GameRules, MapGenerator};
+    use crate::types::{TerrainType, Units};
+
+    #[test]
+    fn test_neutrals_fortify_up_to_cap() {
+        let rules = GameRules { neutral_merge_chance: 0.0, ..GameRules::for_difficulty(Difficulty::Hard) };
+        let mut engine = GameEngine::with_rules(MapGenerator::new(20, 2).generate(), 100, rules);
+        let neutral = engine.state.territories.iter().position(|t| t.owner.is_none() && t.terrain != TerrainType::Water).unwrap();
+        engine.state.territories[neutral].units = Units::infantry(10);
+
+        // 2 troops/sec at 100 ms ticks
+        for _ in 0..50 {
+            engine.tick();
+        }
+        assert_eq!(engine.state.territories[neutral].troops(), 20);
+
+        for _ in 0..5_000 {
+            engine.tick();
+        }
+        assert!(engine.state.territories.iter().filter(|t| t.owner.is_none()).all(|t| t.troops() <= 400));
+    }
+
+    #[test]
+    fn test_strong_neutrals_merge_without_losing_troops() {
+        let rules = GameRules { neutral_merge_chance: 1.0, neutral_fortify_per_second: 0.0, ..GameRules::default() };
+        let mut state = MapGenerator::new(20, 2).generate();
+        for territory in &mut state.territories {
+            territory.owner = None;
+            territory.units = Units::infantry(200);
+        }
+        let total: u64 = state.territories.iter().map(|t| t.troops() as u64).sum();
+        let mut engine = GameEngine::with_rules(state, 100, rules);
+
+        engine.tick_neutrals();
+
+        assert_eq!(engine.state.territories.iter().map(|t| t.troops() as u64).sum::<u64>(), total);
+        assert!(engine.state.territories.iter().any(|t| t.troops() == 300));
+    }
+}
@@ -0,0 +1,131 @@
+//! Order queues.
+//!
+//! Besides acting at once, players can queue attacks, builds and troop moves
+//! for later ticks. Each player's orders run in the order they were queued,
+//! each at its tick or as soon as the ones before it are done, at the start
+//! of the tick before anything else moves. Only the player who queued an
+//! order hears whether it was carried out.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::types::*;
+use super::GameEngine;
+
+/// Orders a player can have waiting at once
+const MAX_QUEUED_ORDERS: usize = 20;
+
+/// An order waiting for its tick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct QueuedOrder {
+    id: u64,
+    order: Order,
+    tick: u64,
+}
+
+impl GameEngine {
+    /// Queue an order for `tick`, or the next tick; returns the id it will be
+    /// reported under and the tick it is due at
+    pub fn queue_order(&mut self, player_id: PlayerId, order: Order, tick: Option<u64>) -> Result<(u64, u64)> {
+        self.get_player(player_id)?;
+        let tick = match tick {
+            Some(tick) if tick <= self.state.tick => return Err(anyhow!("Tick {} has already been played", tick)),
+            Some(tick) => tick,
+            None => self.state.tick + 1,
+        };
+        let queue = self.orders.entry(player_id.into()).or_default();
+        if queue.len() >= MAX_QUEUED_ORDERS {
+            return Err(anyhow!("You can't queue more than {} orders", MAX_QUEUED_ORDERS));
+        }
+
+        self.next_order_id += 1;
+        let id = self.next_order_id;
+        queue.push_back(QueuedOrder { id, order, tick });
+        Ok((id, tick))
+    }
+
+    /// Drop every order the player still has queued
+    pub fn clear_orders(&mut self, player_id: PlayerId) {
+        self.orders.remove(&player_id.into());
+    }
+
+    /// Carry out every order that is due, reporting each to its player
+    pub(super) fn execute_orders(&mut self) {
+        let tick = self.state.tick;
+        for player in self.state.players.iter().filter(|p| !p.is_alive) {
+            self.orders.remove(&player.id);
+        }
+
+        let mut players: Vec<_> = self.orders.keys().copied().collect();
+        // Map order is random; players' orders go in the order they joined
+        players.sort_by_key(|id| self.player_map.get(&(*id).into()).copied());
+        for player in players {
+            while let Some(queued) = self.orders.get_mut(&player).and_then(|q| q.pop_front_if(|o| o.tick <= tick)) {
+                let message = match self.execute_order(player.into(), &queued.order) {
+                    Ok(()) => ServerMessage::OrderExecuted { player_id: player, order_id: queued.id, tick },
+                    Err(e) => ServerMessage::OrderFailed { player_id: player, order_id: queued.id, reason: e.to_string() },
+                };
+                self.events.push(message);
+            }
+            if self.orders.get(&player).is_some_and(|q| q.is_empty()) {
+                self.orders.remove(&player);
+            }
+        }
+    }
+
+    /// Carry out one order as if the player had just sent it, announcing the outcome as then
+    fn execute_order(&mut self, player_id: PlayerId, order: &Order) -> Result<()> {
+        match *order {
+            Order::Attack { from, to } => {
+                let Some(result) = self.launch_attack(player_id, from.into(), to.into())? else { return Ok(()) };
+                let conquered = result.territory_conquered.then_some(ServerMessage::TerritoryConquered {
+                    territory_id: result.to_territory,
+                    old_owner: Some(result.defender_id),
+                    new_owner: result.attacker_id,
+                });
+                self.events.push(ServerMessage::AttackResult { result });
+                self.events.extend(conquered);
+            }
+            Order::BuildStructure { territory, building_type } => {
+                self.build_structure(player_id, territory.into(), building_type)?;
+                self.events.push(ServerMessage::BuildingCompleted { territory_id: territory, building_type, player_id: player_id.into() });
+                let notice = ServerMessage::notification(NotificationKey::BuildingCompleted, NotificationLevel::Success, [])
+                    .about([EntityRef::Player { id: player_id.into() }, EntityRef::Territory { id: territory }]);
+                self.events.push(notice);
+            }
+            Order::MoveTroops { from, to, amount } => self.move_troops(player_id, from.into(), to.into(), amount)?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_queued_orders_run_in_sequence_on_their_ticks() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate_seeded(1), 100);
+        let player: PlayerId = engine.state.players[0].id.into();
+        let home = engine.state.territories.iter().find(|t| t.owner == Some(player.into())).unwrap().id;
+        let elsewhere = engine.state.territories.iter().find(|t| t.owner != Some(player.into())).unwrap().id;
+
+        // A move that can't happen, then a build waiting for it, due two ticks out
+        let (failing, _) = engine.queue_order(player, Order::MoveTroops { from: home, to: elsewhere, amount: 1 }, Some(2)).unwrap();
+        let build = Order::BuildStructure { territory: home, building_type: BuildingType::Farm };
+        let (building, due) = engine.queue_order(player, build, None).unwrap();
+        assert_eq!(due, 1);
+        assert!(engine.queue_order(player, Order::MoveTroops { from: home, to: home, amount: 1 }, Some(0)).is_err());
+
+        // The build is due first but waits its turn
+        engine.tick();
+        assert!(engine.take_events().is_empty());
+        engine.tick();
+        let events = engine.take_events();
+        assert!(matches!(&events[0], ServerMessage::OrderFailed { order_id, .. } if *order_id == failing));
+        assert!(matches!(events.last(), Some(ServerMessage::OrderExecuted { order_id, tick: 2, .. }) if *order_id == building));
+        assert_eq!(engine.get_territory(home.into()).unwrap().building, Some(BuildingType::Farm));
+        assert_eq!(events[0].recipient(), Some(player));
+        assert!(engine.orders.is_empty());
+    }
+}
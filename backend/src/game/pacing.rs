@@ -0,0 +1,98 @@
+//! Pausing and game speed.
+//!
+//! Players ask for a pause, a resume or another speed, and the change waits
+//! for the start of the next tick, where everyone is told who made it. Pauses
+//! can't land in the middle of a battle that way, and the rules can cap how
+//! often each player pauses and lock the speed once the game is running.
+
+use anyhow::{anyhow, Result};
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Ask for the game to be paused or resumed at the next tick
+    pub fn request_pause(&mut self, player_id: PlayerId, paused: bool) -> Result<()> {
+        let name = self.get_player(player_id)?.name.clone();
+        let current = self.pending_pause.as_ref().map_or(self.state.is_paused, |(_, paused)| *paused);
+        if current == paused {
+            return Err(anyhow!(if paused { "The game is already paused" } else { "The game is not paused" }));
+        }
+
+        if paused {
+            let used = self.pauses_used.entry(player_id.into()).or_default();
+            if self.rules.pauses_per_player.is_some_and(|budget| *used >= budget) {
+                return Err(anyhow!("You have used all of your pauses"));
+            }
+            *used += 1;
+        }
+        self.pending_pause = Some((name, paused));
+        Ok(())
+    }
+
+    /// Ask for another game speed at the next tick
+    pub fn request_game_speed(&mut self, player_id: PlayerId, speed: f32) -> Result<()> {
+        let name = self.get_player(player_id)?.name.clone();
+        if self.rules.lock_speed_after_start && self.state.tick > 0 {
+            return Err(anyhow!("The game speed is locked once the game has started"));
+        }
+        self.pending_speed = Some((name, speed));
+        Ok(())
+    }
+
+    /// Whether the game is paused with no resume waiting for the next tick
+    pub fn stays_paused(&self) -> bool {
+        self.state.is_paused && self.pending_pause.as_ref().is_none_or(|(_, paused)| *paused)
+    }
+
+    /// Apply the changes asked for since the last tick and announce them
+    pub(super) fn apply_pacing(&mut self) {
+        if let Some((player, speed)) = self.pending_speed.take() {
+            self.set_game_speed(speed);
+            let speed = self.state.game_speed.to_string();
+            self.events.push(ServerMessage::notification(
+                NotificationKey::GameSpeedChanged,
+                NotificationLevel::Info,
+                [("player", player), ("speed", speed)],
+            ));
+        }
+        if let Some((player, paused)) = self.pending_pause.take() {
+            self.set_paused(paused);
+            let key = if paused { NotificationKey::GamePaused } else { NotificationKey::GameResumed };
+            self.events.push(ServerMessage::notification(key, NotificationLevel::Info, [("player", player)]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_pauses_and_speed_wait_for_the_tick() {
+        let mut engine = GameEngine::new(MapGenerator::new(10, 2).generate_seeded(1), 100);
+        engine.rules.pauses_per_player = Some(1);
+        engine.rules.lock_speed_after_start = true;
+        let player: PlayerId = engine.state.players[0].id.into();
+
+        engine.request_game_speed(player, 2.0).unwrap();
+        engine.request_pause(player, true).unwrap();
+        assert!(!engine.state.is_paused && engine.state.game_speed == 1.0);
+        assert!(engine.request_pause(player, true).is_err());
+
+        // Both land at the tick boundary, announced; the paused tick doesn't advance
+        engine.tick();
+        assert!(engine.state.is_paused && engine.state.game_speed == 2.0);
+        assert_eq!(engine.state.tick, 0);
+        assert_eq!(engine.take_events().len(), 2);
+
+        engine.request_pause(player, false).unwrap();
+        engine.tick();
+        assert!(!engine.state.is_paused && engine.state.tick == 1);
+
+        // The budget is spent and the speed is locked now the game runs
+        assert!(engine.request_pause(player, true).is_err());
+        assert!(engine.request_game_speed(player, 4.0).is_err());
+    }
+}
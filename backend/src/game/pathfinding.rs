@@ -0,0 +1,173 @@
+//! Shortest routes over the territory graph.
+//!
+//! Paths are found breadth-first, so they have the fewest hops, and ties go to
+//! the neighbor listed first. Routes that may cross anyone's land are cached
+//! per engine, for the graph version they were found on: the engine bumps the
+//! version whenever neighbor lists or terrain change, e.g. when a draft swaps
+//! the map. Routes restricted to one owner's land change with every conquest,
+//! so they are searched afresh.
+
+use anyhow::Result;
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+/// Routes found since the graph last changed
+#[derive(Default)]
+pub struct PathCache {
+    graph_version: u64,
+    paths: HashMap<(Uuid, Uuid, TraversalRules), Option<Vec<Uuid>>>,
+}
+
+impl GameEngine {
+    /// Territories on the shortest route from `from` to `to`, both ends
+    /// included; `None` if the rules leave no route
+    pub fn path_between(&self, from: TerritoryId, to: TerritoryId, rules: TraversalRules) -> Result<Option<Vec<Uuid>>> {
+        self.get_territory(from)?;
+        self.get_territory(to)?;
+
+        if rules.through_owner.is_some() {
+            return Ok(self.find_path(from, to, rules));
+        }
+        let key = (from.into(), to.into(), rules);
+        let mut cache = self.path_cache.lock().unwrap();
+        if cache.graph_version != self.graph_version {
+            cache.paths.clear();
+            cache.graph_version = self.graph_version;
+        }
+        let path = cache.paths.entry(key).or_insert_with(|| self.find_path(from, to, rules));
+        Ok(path.clone())
+    }
+
+    /// Shortest route from `from` to the nearest territory `target` accepts,
+    /// both ends included; one search however many territories qualify
+    pub fn path_to_nearest(&self, from: TerritoryId, rules: TraversalRules, target: impl Fn(&Territory) -> bool) -> Option<Vec<Uuid>> {
+        let from: Uuid = from.into();
+        let passable = |territory: &Territory| !(rules.land_only && territory.terrain == TerrainType::Water);
+        let transit = |territory: &Territory| rules.through_owner.is_none_or(|owner| territory.owner == Some(owner));
+
+        let start = self.get_territory(from.into()).ok()?;
+        if !passable(start) || (!target(start) && !transit(start)) {
+            return None;
+        }
+
+        let mut came_from: HashMap<Uuid, Uuid> = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            let territory = self.get_territory(current.into()).ok()?;
+            if target(territory) {
+                let mut path = vec![current];
+                let mut step = current;
+                while step != from {
+                    step = came_from[&step];
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for &neighbor in &territory.neighbors {
+                if came_from.contains_key(&neighbor) {
+                    continue;
+                }
+                let Ok(next) = self.get_territory(neighbor.into()) else { continue };
+                if passable(next) && (target(next) || transit(next)) {
+                    came_from.insert(neighbor, current);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    fn find_path(&self, from: TerritoryId, to: TerritoryId, rules: TraversalRules) -> Option<Vec<Uuid>> {
+        let to: Uuid = to.into();
+        self.path_to_nearest(from, rules, |territory| territory.id == to)
+    }
+
+    /// Drop the cached routes; call after changing neighbor lists or terrain
+    pub fn graph_changed(&mut self) {
+        self.graph_version += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    /// Five territories in a row, each linked to the next
+    fn line() -> GameEngine {
+        let mut state = MapGenerator::new(5, 2).generate();
+        let ids: Vec<Uuid> = state.territories.iter().map(|t| t.id).collect();
+        for (i, territory) in state.territories.iter_mut().enumerate() {
+            territory.terrain = TerrainType::Plains;
+            territory.owner = None;
+            territory.neighbors = [i.checked_sub(1), Some(i + 1).filter(|&j| j < ids.len())]
+                .into_iter()
+                .flatten()
+                .map(|j| ids[j])
+                .collect();
+        }
+        GameEngine::new(state, 100)
+    }
+
+    fn id(engine: &GameEngine, idx: usize) -> TerritoryId {
+        engine.state.territories[idx].id.into()
+    }
+
+    #[test]
+    fn test_path_follows_the_rules() {
+        let mut engine = line();
+        let (first, last) = (id(&engine, 0), id(&engine, 4));
+        let ids: Vec<Uuid> = engine.state.territories.iter().map(|t| t.id).collect();
+
+        assert_eq!(engine.path_between(first, last, TraversalRules::default()).unwrap(), Some(ids.clone()));
+        assert_eq!(engine.path_between(first, first, TraversalRules::default()).unwrap(), Some(vec![ids[0]]));
+        assert!(engine.path_between(first, Uuid::new_v4().into(), TraversalRules::default()).is_err());
+
+        engine.state.territories[2].terrain = TerrainType::Water;
+        engine.graph_changed();
+        let land = TraversalRules { land_only: true, ..TraversalRules::default() };
+        assert_eq!(engine.path_between(first, last, land).unwrap(), None);
+        assert!(engine.path_between(first, last, TraversalRules::default()).unwrap().is_some());
+
+        // A supply route stays on the owner's land up to the destination
+        let owner = engine.state.players[0].id;
+        for territory in &mut engine.state.territories[..4] {
+            territory.owner = Some(owner);
+        }
+        let supply = TraversalRules { through_owner: Some(owner), ..TraversalRules::default() };
+        assert_eq!(engine.path_between(first, last, supply).unwrap(), Some(ids.clone()));
+        engine.state.territories[1].owner = None;
+        assert_eq!(engine.path_between(first, last, supply).unwrap(), None);
+    }
+
+    #[test]
+    fn test_nearest_target_takes_one_search() {
+        let mut engine = line();
+        let ids: Vec<Uuid> = engine.state.territories.iter().map(|t| t.id).collect();
+        let rival = engine.state.players[1].id;
+        engine.state.territories[3].owner = Some(rival);
+        engine.state.territories[4].owner = Some(rival);
+
+        let path = engine.path_to_nearest(id(&engine, 0), TraversalRules::default(), |t| t.owner == Some(rival));
+        assert_eq!(path, Some(ids[..4].to_vec()));
+        assert_eq!(engine.path_to_nearest(id(&engine, 0), TraversalRules::default(), |_| false), None);
+    }
+
+    #[test]
+    fn test_cache_is_dropped_when_edges_change() {
+        let mut engine = line();
+        let (first, last) = (id(&engine, 0), id(&engine, 4));
+        assert_eq!(engine.path_between(first, last, TraversalRules::default()).unwrap().unwrap().len(), 5);
+
+        let (a, b) = (first.into(), last.into());
+        engine.state.territories[0].neighbors.push(b);
+        engine.state.territories[4].neighbors.push(a);
+        engine.graph_changed();
+        assert_eq!(engine.path_between(first, last, TraversalRules::default()).unwrap(), Some(vec![a, b]));
+    }
+}
@@ -0,0 +1,475 @@
+//! Rule plugin interface.
+//!
+//! Plugins observe the game through read-only hooks and influence it only by
+//! vetoing actions or returning a small set of `PluginAction`s, so the engine
+//! stays in charge of its own invariants. Every hook call is timed against a
+//! budget and isolated from panics; a plugin that keeps overrunning or panics
+//! is disabled for the rest of the game, as is one that reports a fault. The
+//! interface is plain data in and out, so the same hooks are served by native
+//! Rust implementations and by sandboxed WASM modules (see `wasm_plugin.rs`).
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::types::*;
+
+/// An attack about to be resolved
+#[derive(Debug, Clone, Serialize)]
+pub struct AttackIntent {
+    pub attacker: Uuid,
+    pub from: Uuid,
+    pub to: Uuid,
+    pub troops: u32,
+}
+/// A building about to be constructed or upgraded
+/// A building about to be constructed
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildIntent {
+    pub player: Uuid,
+    pub territory: Uuid,
+    pub building_type: BuildingType,
+}
+
+/// Whether a plugin lets an action proceed
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    Allow,
+    Veto(String),
+}
+
+/// Scripted behavior that replaces an AI player's personality when attacking
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiScript {
+    /// Never attack, until another player attacks them
+    Passive,
+    /// Hold back, then attack from every border territory every `every` ticks
+    Waves { every: u64 },
+}
+
+/// Effects a plugin may request from `on_tick`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum PluginAction {
+    AdjustGold { player: Uuid, delta: i64 },
+    AdjustPopulation { player: Uuid, delta: i64 },
+    /// Show a message to every connected client
+    Announce { message: String },
+    /// Script an AI player, or hand them back to their personality with `None`
+    ScriptAi { player: Uuid, script: Option<AiScript> },
+}
+
+/// Hooks for custom game rules; every hook has a no-op default
+pub trait RulePlugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn on_tick(&mut self, _state: &GameState) -> Vec<PluginAction> {
+        Vec::new()
+    }
+
+    fn on_attack(&mut self, _state: &GameState, _attack: &AttackIntent) -> HookOutcome {
+        HookOutcome::Allow
+    }
+
+    fn on_build(&mut self, _state: &GameState, _build: &BuildIntent) -> HookOutcome {
+        HookOutcome::Allow
+    }
+
+    /// Declare a winner under custom victory conditions
+    fn check_victory(&mut self, _state: &GameState) -> Option<Uuid> {
+        None
+    }
+
+    /// How close players are to the plugin's victory conditions, for clients
+    fn victory_progress(&mut self, _state: &GameState) -> Vec<VictoryProgress> {
+        Vec::new()
+    }
+
+    /// Why the plugin can't go on, e.g. a module that ran out of fuel
+    fn fault(&self) -> Option<&str> {
+        None
+    }
+}
+
+struct LoadedPlugin {
+    plugin: Box<dyn RulePlugin>,
+    overruns: u32,
+    enabled: bool,
+}
+
+/// Runs plugins with per-hook time budgets and panic isolation
+pub struct PluginHost {
+    plugins: Vec<LoadedPlugin>,
+    /// Time a single hook call may take
+    pub hook_budget: Duration,
+    /// Budget overruns tolerated before a plugin is disabled
+    pub max_overruns: u32,
+}
+
+impl Default for PluginHost {
+    fn default() -> Self {
+        Self {
+            plugins: Vec::new(),
+            hook_budget: Duration::from_millis(2),
+            max_overruns: 10,
+        }
+    }
+}
+
+impl PluginHost {
+    pub fn register(&mut self, plugin: Box<dyn RulePlugin>) {
+        self.plugins.push(LoadedPlugin { plugin, overruns: 0, enabled: true });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.plugins.is_empty()
+    }
+
+    /// Names of plugins still running
+    pub fn active(&self) -> Vec<&str> {
+        self.plugins.iter().filter(|p| p.enabled).map(|p| p.plugin.name()).collect()
+    }
+
+    /// Call a hook on every enabled plugin, enforcing the budget
+    fn run<T>(&mut self, mut hook: impl FnMut(&mut dyn RulePlugin) -> T) -> Vec<T> {
+        let budget = self.hook_budget;
+        let max_overruns = self.max_overruns;
+        let mut results = Vec::new();
+
+        for loaded in self.plugins.iter_mut().filter(|p| p.enabled) {
+            let started = Instant::now();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| hook(loaded.plugin.as_mut())));
+            let elapsed = started.elapsed();
+
+            match result {
+                Ok(_) if loaded.plugin.fault().is_some() => {
+                    let fault = loaded.plugin.fault().unwrap_or_default();
+                    warn!("Plugin {} failed and was disabled: {}", loaded.plugin.name(), fault);
+                    loaded.enabled = false;
+                    continue;
+                }
+                Ok(value) => results.push(value),
+                Err(_) => {
+                    warn!("Plugin {} panicked and was disabled", loaded.plugin.name());
+                    loaded.enabled = false;
+                    continue;
+                }
+            }
+
+            if elapsed > budget {
+                loaded.overruns += 1;
+                if loaded.overruns >= max_overruns {
+                    warn!("Plugin {} exceeded its time budget too often and was disabled", loaded.plugin.name());
+                    loaded.enabled = false;
+                }
+            }
+        }
+
+        results
+    }
+
+    pub fn on_tick(&mut self, state: &GameState) -> Vec<PluginAction> {
+        self.run(|plugin| plugin.on_tick(state)).into_iter().flatten().collect()
+    }
+
+    pub fn on_attack(&mut self, state: &GameState, attack: &AttackIntent) -> HookOutcome {
+        let outcome = self.first_veto(|plugin| plugin.on_attack(state, attack));
+        if let HookOutcome::Veto(reason) = &outcome {
+            debug!(
+                attacker = %attack.attacker, from = %attack.from, to = %attack.to, troops = attack.troops,
+                "Attack vetoed by plugin: {}", reason
+            );
+        }
+        outcome
+    }
+
+    pub fn on_build(&mut self, state: &GameState, build: &BuildIntent) -> HookOutcome {
+        let outcome = self.first_veto(|plugin| plugin.on_build(state, build));
+        if let HookOutcome::Veto(reason) = &outcome {
+            debug!(
+                player = %build.player, territory = %build.territory, building = ?build.building_type,
+                "Build vetoed by plugin: {}", reason
+            );
+        }
+        outcome
+    }
+
+    pub fn check_victory(&mut self, state: &GameState) -> Option<Uuid> {
+        self.run(|plugin| plugin.check_victory(state)).into_iter().flatten().next()
+    }
+
+    pub fn victory_progress(&mut self, state: &GameState) -> Vec<VictoryProgress> {
+        self.run(|plugin| plugin.victory_progress(state)).into_iter().flatten().collect()
+    }
+
+    fn first_veto(&mut self, hook: impl FnMut(&mut dyn RulePlugin) -> HookOutcome) -> HookOutcome {
+        self.run(hook)
+            .into_iter()
+            .find(|outcome| matches!(outcome, HookOutcome::Veto(_)))
+            .unwrap_or(HookOutcome::Allow)
+    }
+}
+
+/// Create a built-in plugin from a `name[=value]` spec
+pub fn builtin_plugin(spec: &str) -> Result<Box<dyn RulePlugin>> {
+    let (name, value) = match spec.split_once('=') {
+        Some((name, value)) => (name.trim(), Some(value.trim())),
+        None => (spec.trim(), None),
+    };
+
+    match name {
+        "peace_time" => {
+            let until_tick = value.unwrap_or("600").parse().map_err(|_| anyhow!("peace_time expects a tick count"))?;
+            Ok(Box::new(PeaceTimePlugin { until_tick }))
+        }
+        "domination" => {
+            let share: f32 = value.unwrap_or("0.6").parse().map_err(|_| anyhow!("domination expects a share"))?;
+            Ok(Box::new(DominationVictoryPlugin { share: share.clamp(0.0, 1.0) }))
+        }
+        "underdog" => Ok(Box::new(UnderdogPlugin)),
+        _ => Err(anyhow!("Unknown plugin: {}", name)),
+    }
+}
+
+/// Forbids attacks between players (not on neutral land) before a given tick
+pub struct PeaceTimePlugin {
+    pub until_tick: u64,
+}
+
+impl RulePlugin for PeaceTimePlugin {
+    fn name(&self) -> &str {
+        "peace_time"
+    }
+
+    fn on_attack(&mut self, state: &GameState, attack: &AttackIntent) -> HookOutcome {
+        let defended = state.territories.iter().any(|t| t.id == attack.to && t.owner.is_some());
+        if state.tick < self.until_tick && defended {
+            HookOutcome::Veto(format!("Peace time lasts until tick {}", self.until_tick))
+        } else {
+            HookOutcome::Allow
+        }
+    }
+}
+
+/// Wins the game for the first player holding a share of all territories
+pub struct DominationVictoryPlugin {
+    pub share: f32,
+}
+
+impl RulePlugin for DominationVictoryPlugin {
+    fn name(&self) -> &str {
+        "domination"
+    }
+
+    fn check_victory(&mut self, state: &GameState) -> Option<Uuid> {
+        let needed = self.needed(state);
+        state.players
+            .iter()
+            .find(|p| p.is_alive && p.territories_controlled >= needed)
+            .map(|p| p.id)
+    }
+
+    fn victory_progress(&mut self, state: &GameState) -> Vec<VictoryProgress> {
+        let target = self.needed(state);
+        state.players
+            .iter()
+            .filter(|p| p.is_alive)
+            .map(|p| VictoryProgress {
+                condition: "domination".to_string(),
+                player_id: p.id,
+                current: p.territories_controlled,
+                target,
+            })
+            .collect()
+    }
+}
+
+impl DominationVictoryPlugin {
+    /// Territories a player has to hold to win
+    fn needed(&self, state: &GameState) -> u32 {
+        ((state.territories.len() as f32 * self.share).ceil() as u32).max(1)
+    }
+}
+
+/// Every 100 ticks, players down to their last territory get a small boost
+pub struct UnderdogPlugin;
+
+impl RulePlugin for UnderdogPlugin {
+    fn name(&self) -> &str {
+        "underdog"
+    }
+
+    fn on_tick(&mut self, state: &GameState) -> Vec<PluginAction> {
+        if !state.tick.is_multiple_of(100) {
+            return Vec::new();
+        }
+
+        state.players
+            .iter()
+            .filter(|p| p.is_alive && p.territories_controlled == 1)
+            .flat_map(|p| [
+                PluginAction::AdjustGold { player: p.id, delta: 100 },
+                PluginAction::AdjustPopulation { player: p.id, delta: 50 },
+            ])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEngine, MapGenerator};
+
+    struct SlowPlugin;
+
+    impl RulePlugin for SlowPlugin {
+        fn name(&self) -> &str {
+            "slow"
+        }
+
+        fn on_tick(&mut self, _state: &GameState) -> Vec<PluginAction> {
+            std::thread::sleep(Duration::from_millis(5));
+            Vec::new()
+        }
+    }
+
+    struct PanickingPlugin;
+
+    impl RulePlugin for PanickingPlugin {
+        fn name(&self) -> &str {
+            "panicking"
+        }
+
+        fn on_build(&mut self, _state: &GameState, _build: &BuildIntent) -> HookOutcome {
+            panic!("plugin bug");
+        }
+    }
+
+    struct NoBuildingPlugin;
+
+    impl RulePlugin for NoBuildingPlugin {
+        fn name(&self) -> &str {
+            "no_building"
+        }
+
+        fn on_build(&mut self, _state: &GameState, _build: &BuildIntent) -> HookOutcome {
+            HookOutcome::Veto("Building is forbidden".to_string())
+        }
+    }
+
+    fn engine() -> GameEngine {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 3).generate(), 100);
+        engine.tick();
+        engine
+    }
+
+    #[test]
+    fn test_peace_time_vetoes_player_attacks_only() {
+        let mut engine = engine();
+        engine.plugins.register(builtin_plugin("peace_time=1000").unwrap());
+
+        let attacker = engine.state.players[0].id;
+        let victim = engine.state.players[1].id;
+        let from = engine.state.territories.iter().find(|t| t.owner == Some(attacker)).unwrap().id;
+        let to = engine.state.territories.iter().find(|t| t.owner == Some(victim)).unwrap().id;
+
+        // Make the two starts adjacent so only the plugin can object
+        let from_idx = engine.state.territories.iter().position(|t| t.id == from).unwrap();
+        let to_idx = engine.state.territories.iter().position(|t| t.id == to).unwrap();
+        engine.state.territories[from_idx].neighbors.push(to);
+        engine.state.territories[to_idx].neighbors.push(from);
+
+        let err = engine.execute_attack(attacker.into(), from.into(), to.into()).unwrap_err();
+        assert!(err.to_string().contains("Peace time"));
+    }
+
+    #[test]
+    fn test_slow_plugin_is_disabled_after_overruns() {
+        let mut engine = engine();
+        engine.plugins.max_overruns = 3;
+        engine.plugins.register(Box::new(SlowPlugin));
+
+        for _ in 0..5 {
+            engine.tick();
+        }
+        assert!(engine.plugins.active().is_empty());
+    }
+
+    #[test]
+    fn test_panicking_plugin_is_disabled_without_crashing() {
+        let mut engine = engine();
+        engine.plugins.register(Box::new(PanickingPlugin));
+
+        let player_id = engine.state.players[0].id;
+        let territory = engine.state.territories.iter().find(|t| t.owner == Some(player_id)).unwrap().id;
+        engine.get_player_mut(player_id.into()).unwrap().gold = 10_000;
+
+        engine.build_structure(player_id.into(), territory.into(), BuildingType::GoldMine).unwrap();
+        assert!(engine.plugins.active().is_empty());
+    }
+
+    #[test]
+    fn test_build_veto_also_blocks_upgrades() {
+        let mut engine = engine();
+        let player_id = engine.state.players[0].id;
+        let territory = engine.state.territories.iter().find(|t| t.owner == Some(player_id)).unwrap().id;
+        engine.get_player_mut(player_id.into()).unwrap().gold = 10_000;
+        engine.build_structure(player_id.into(), territory.into(), BuildingType::City).unwrap();
+        let gold = engine.get_player(player_id.into()).unwrap().gold;
+
+        engine.plugins.register(Box::new(NoBuildingPlugin));
+        let err = engine.upgrade_building(player_id.into(), territory.into()).unwrap_err();
+        assert!(err.to_string().contains("forbidden"));
+        assert_eq!(engine.get_territory(territory.into()).unwrap().building_level, 1);
+        assert_eq!(engine.get_player(player_id.into()).unwrap().gold, gold);
+    }
+
+    #[test]
+    fn test_domination_victory_ends_game() {
+        let mut engine = engine();
+        engine.plugins.register(builtin_plugin("domination=0.5").unwrap());
+        assert!(engine.check_game_over().is_none());
+
+        let winner = engine.state.players[2].id;
+        for territory in engine.state.territories.iter_mut().take(10) {
+            territory.owner = Some(winner);
+        }
+        engine.tick();
+
+        assert_eq!(engine.check_game_over().unwrap().winner, winner);
+    }
+
+    #[test]
+    fn test_victory_progress_covers_every_condition() {
+        let mut engine = engine();
+        engine.plugins.register(builtin_plugin("domination=0.5").unwrap());
+        let player = engine.state.players[2].id;
+        engine.state.players[1].is_alive = false;
+        engine.tick();
+
+        let progress = |condition: &str| {
+            engine.state.victory.iter().find(|v| v.condition == condition && v.player_id == player).cloned().unwrap()
+        };
+        let players = engine.state.players.len() as u32;
+        assert_eq!((progress("last_standing").current, progress("last_standing").target), (1, players - 1));
+        let held = engine.state.territories.iter().filter(|t| t.owner == Some(player)).count() as u32;
+        assert_eq!((progress("domination").current, progress("domination").target), (held, 10));
+        assert!(engine.state.victory.iter().all(|v| v.player_id != engine.state.players[1].id));
+    }
+
+    #[test]
+    fn test_underdog_grants_resources() {
+        let mut engine = engine();
+        engine.plugins.register(builtin_plugin("underdog").unwrap());
+        let gold_before = engine.state.players[0].gold;
+
+        while !engine.state.tick.is_multiple_of(100) {
+            engine.tick();
+        }
+        assert!(engine.state.players[0].gold >= gold_before + 100);
+    }
+}
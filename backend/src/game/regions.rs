@@ -0,0 +1,166 @@
+//! Higher-level map structure.
+//!
+//! Groups territories into regions by flooding the neighbor graph from
+//! well-spread seed territories, marks edges between regions that touch
+//! through only a couple of connections as chokepoints, and flags land next
+//! to water as coastal. The result is deterministic for a given map.
+
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::types::*;
+
+/// Territories per region, on average
+const REGION_SIZE: usize = 8;
+/// Region borders with at most this many edges are chokepoints
+const CHOKEPOINT_MAX_LINKS: usize = 2;
+
+/// Compute regions, chokepoints and coastal flags for a map
+pub fn annotate_map(state: &mut GameState) {
+    let index: HashMap<Uuid, usize> = state.territories.iter().enumerate().map(|(i, t)| (t.id, i)).collect();
+    let neighbors: Vec<Vec<usize>> = state
+        .territories
+        .iter()
+        .map(|t| t.neighbors.iter().filter_map(|id| index.get(id).copied()).collect())
+        .collect();
+
+    let assignment = assign_regions(state, &neighbors);
+    for (territory, &region) in state.territories.iter_mut().zip(&assignment) {
+        territory.region = region;
+    }
+
+    let region_count = assignment.iter().max().map_or(0, |&r| r as usize + 1);
+    state.regions = (0..region_count as u32)
+        .map(|id| {
+            let members: Vec<&Territory> = state.territories.iter().filter(|t| t.region == id).collect();
+            let count = members.len().max(1) as f32;
+            let (x, y) = members.iter().fold((0.0, 0.0), |(x, y), t| (x + t.position.0, y + t.position.1));
+            Region { id, name: String::new(), territories: members.iter().map(|t| t.id).collect(), center: (x / count, y / count) }
+        })
+        .collect();
+
+    // Every edge between each pair of regions, listed once
+    let mut borders: HashMap<(u32, u32), Vec<(usize, usize)>> = HashMap::new();
+    for (i, adjacent) in neighbors.iter().enumerate() {
+        for &j in adjacent.iter().filter(|&&j| i < j) {
+            let (a, b) = (assignment[i], assignment[j]);
+            if a != b {
+                borders.entry((a.min(b), a.max(b))).or_default().push((i, j));
+            }
+        }
+    }
+    let mut chokepoints: Vec<Chokepoint> = borders
+        .into_values()
+        .filter(|edges| edges.len() <= CHOKEPOINT_MAX_LINKS)
+        .flatten()
+        .map(|(i, j)| Chokepoint { from: state.territories[i].id, to: state.territories[j].id })
+        .collect();
+    chokepoints.sort_by_key(|c| (c.from, c.to));
+    state.chokepoints = chokepoints;
+
+    let water: Vec<bool> = state.territories.iter().map(|t| t.terrain == TerrainType::Water).collect();
+    for (i, territory) in state.territories.iter_mut().enumerate() {
+        territory.coastal = !water[i] && neighbors[i].iter().any(|&j| water[j]);
+    }
+}
+
+/// Region index of every territory
+fn assign_regions(state: &GameState, neighbors: &[Vec<usize>]) -> Vec<u32> {
+    let n = state.territories.len();
+    let mut assignment = vec![u32::MAX; n];
+    if n == 0 {
+        return assignment;
+    }
+
+    // Farthest-point sampling spreads the seeds over the map
+    let distance = |a: usize, b: usize| {
+        let (pa, pb) = (state.territories[a].position, state.territories[b].position);
+        (pa.0 - pb.0).powi(2) + (pa.1 - pb.1).powi(2)
+    };
+    let mut seeds = vec![0];
+    let mut nearest_seed: Vec<f32> = (0..n).map(|i| distance(i, 0)).collect();
+    while seeds.len() < n.div_ceil(REGION_SIZE) {
+        let next = (0..n).max_by(|&a, &b| nearest_seed[a].total_cmp(&nearest_seed[b])).unwrap();
+        if nearest_seed[next] == 0.0 {
+            break;
+        }
+        seeds.push(next);
+        for (i, nearest) in nearest_seed.iter_mut().enumerate() {
+            *nearest = nearest.min(distance(i, next));
+        }
+    }
+
+    // Grow all regions at once so each stays connected
+    let mut queue = VecDeque::new();
+    for (region, &seed) in seeds.iter().enumerate() {
+        assignment[seed] = region as u32;
+        queue.push_back(seed);
+    }
+    let mut next_region = seeds.len() as u32;
+    loop {
+        while let Some(i) = queue.pop_front() {
+            for &j in &neighbors[i] {
+                if assignment[j] == u32::MAX {
+                    assignment[j] = assignment[i];
+                    queue.push_back(j);
+                }
+            }
+        }
+        // Islands the seeds can't reach become regions of their own
+        let Some(island) = assignment.iter().position(|&r| r == u32::MAX) else { break };
+        assignment[island] = next_region;
+        next_region += 1;
+        queue.push_back(island);
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_every_territory_is_in_one_connected_region() {
+        let state = MapGenerator::new(75, 9).generate_seeded(3);
+        assert_eq!(state.regions.len(), 10);
+        assert_eq!(state.regions.iter().map(|r| r.territories.len()).sum::<usize>(), 75);
+
+        for region in &state.regions {
+            let members: Vec<&Territory> = state.territories.iter().filter(|t| t.region == region.id).collect();
+            assert_eq!(members.len(), region.territories.len());
+
+            // Flood within the region from its first territory
+            let mut reached = vec![members[0].id];
+            let mut i = 0;
+            while i < reached.len() {
+                let territory = members.iter().find(|t| t.id == reached[i]).unwrap();
+                for neighbor in &territory.neighbors {
+                    if members.iter().any(|t| t.id == *neighbor) && !reached.contains(neighbor) {
+                        reached.push(*neighbor);
+                    }
+                }
+                i += 1;
+            }
+            assert_eq!(reached.len(), members.len());
+        }
+    }
+
+    #[test]
+    fn test_chokepoints_and_coasts() {
+        let state = MapGenerator::new(75, 9).generate_seeded(3);
+        let region_of: HashMap<Uuid, u32> = state.territories.iter().map(|t| (t.id, t.region)).collect();
+        for chokepoint in &state.chokepoints {
+            assert_ne!(region_of[&chokepoint.from], region_of[&chokepoint.to]);
+            let from = state.territories.iter().find(|t| t.id == chokepoint.from).unwrap();
+            assert!(from.neighbors.contains(&chokepoint.to));
+        }
+
+        for territory in &state.territories {
+            let by_water = territory.neighbors.iter().any(|id| {
+                state.territories.iter().any(|t| t.id == *id && t.terrain == TerrainType::Water)
+            });
+            assert_eq!(territory.coastal, territory.terrain != TerrainType::Water && by_water);
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Replays of whole matches.
+//!
+//! While a replay is being recorded, the engine appends every applied player
+//! command and every battle to it under the tick it happened in. Together with
+//! the rules and the state the recording started from, that is enough to
+//! follow the match tick by tick. A command is logged once it has been
+//! applied, so an attack's battle comes just before the command behind it.
+//! Every `REPLAY_KEYFRAME_TICKS` the whole state is kept too, so playback can
+//! show growth, income and AI moves that no event describes.
+//!
+//! Recording reseeds the engine and keeps the seed, so `resimulate` can also
+//! play the commands back through a fresh engine and arrive at the very same
+//! state. Only the commands feed that: joins, kicks, loads and rule plugins
+//! are not in the log.
+//!
+//! Replay files are JSON lines: a header with the starting state, then one
+//! line per tick that had any events or a keyframe. Quiet ticks take no space.
+
+use anyhow::{anyhow, Result};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::{GameEngine, GameRules};
+
+/// Replay format written by this server
+const REPLAY_VERSION: u32 = 1;
+/// Ticks between full states in a replay
+const REPLAY_KEYFRAME_TICKS: u64 = 50;
+
+/// Something that happened during a recorded tick
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReplayEvent {
+    /// A player's command the engine accepted
+    Command { player: Uuid, command: ClientMessage },
+    /// An attack by anyone, AI players included
+    Battle { result: CombatResult },
+}
+
+/// The events of one tick, in the order they happened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayTick {
+    pub tick: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ReplayEvent>,
+    /// State at the end of the tick, on keyframe ticks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<GameState>,
+}
+
+impl ReplayTick {
+    /// What a client watching the game live would have been sent
+    pub fn messages(&self) -> Vec<ServerMessage> {
+        let mut messages = Vec::new();
+        for event in &self.events {
+            match event {
+                ReplayEvent::Command { player, command } => {
+                    messages.push(ServerMessage::ReplayCommand { player_id: *player, command: command.clone() });
+                }
+                ReplayEvent::Battle { result } => {
+                    messages.push(ServerMessage::AttackResult { result: result.clone() });
+                    if result.territory_conquered {
+                        messages.push(ServerMessage::TerritoryConquered {
+                            territory_id: result.to_territory,
+                            old_owner: Some(result.defender_id),
+                            new_owner: result.attacker_id,
+                        });
+                    }
+                }
+            }
+        }
+        if let Some(state) = &self.state {
+            messages.push(ServerMessage::state_update(state.clone()));
+        }
+        messages
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader<'a> {
+    version: u32,
+    game_id: Uuid,
+    seed: u64,
+    tick_rate_ms: u64,
+    rules: Cow<'a, GameRules>,
+    initial: Cow<'a, GameState>,
+}
+
+/// Append-only log of a match
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub game_id: Uuid,
+    /// Seed of the engine's random numbers when recording started
+    pub seed: u64,
+    pub tick_rate_ms: u64,
+    pub rules: GameRules,
+    /// State the recording started from
+    pub initial: GameState,
+    /// Ticks with events, oldest first
+    pub ticks: Vec<ReplayTick>,
+}
+
+impl Replay {
+    /// Entry of `tick`, added if it is not the latest yet
+    fn tick_mut(&mut self, tick: u64) -> &mut ReplayTick {
+        if self.ticks.last().is_none_or(|last| last.tick != tick) {
+            self.ticks.push(ReplayTick { tick, events: Vec::new(), state: None });
+        }
+        self.ticks.last_mut().unwrap()
+    }
+
+    fn record(&mut self, tick: u64, event: ReplayEvent) {
+        self.tick_mut(tick).events.push(event);
+    }
+
+    /// Events recorded so far
+    pub fn len(&self) -> usize {
+        self.ticks.iter().map(|t| t.events.len()).sum()
+    }
+
+    /// How far the recording has got, for `rewind`
+    pub(super) fn position(&self) -> (usize, usize) {
+        (self.ticks.len(), self.ticks.last().map_or(0, |t| t.events.len()))
+    }
+
+    /// Drop what was recorded after `position`
+    pub(super) fn rewind(&mut self, (ticks, events): (usize, usize)) {
+        self.ticks.truncate(ticks);
+        if let Some(last) = self.ticks.last_mut() {
+            last.events.truncate(events);
+        }
+    }
+
+    /// Play the recorded commands through a fresh engine, up to the last
+    /// recorded tick. Battles are left for the engine to fight again.
+    pub fn resimulate(&self) -> GameEngine {
+        let mut engine = GameEngine::with_rules(self.initial.clone(), self.tick_rate_ms, self.rules.clone());
+        engine.reseed(self.seed);
+        let last_tick = self.ticks.last().map_or(self.initial.tick, |t| t.tick);
+
+        let mut ticks = self.ticks.iter().peekable();
+        loop {
+            // Commands arrive between ticks, after the AI has moved
+            if let Some(recorded) = ticks.next_if(|t| t.tick == engine.state.tick) {
+                for event in &recorded.events {
+                    if let ReplayEvent::Command { player, command } = event {
+                        // Rejected commands were never recorded
+                        let _ = engine.apply_command((*player).into(), command);
+                    }
+                }
+            }
+            if engine.state.tick >= last_tick || engine.state.is_paused {
+                break;
+            }
+            engine.tick();
+            engine.tick_ai();
+            engine.take_events();
+        }
+        engine
+    }
+
+    /// Write the replay to a file
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        let header = ReplayHeader {
+            version: REPLAY_VERSION,
+            game_id: self.game_id,
+            seed: self.seed,
+            tick_rate_ms: self.tick_rate_ms,
+            rules: Cow::Borrowed(&self.rules),
+            initial: Cow::Borrowed(&self.initial),
+        };
+        let mut out = serde_json::to_string(&header)?;
+        for tick in &self.ticks {
+            out.push('\n');
+            out.push_str(&serde_json::to_string(tick)?);
+        }
+        out.push('\n');
+
+        // Write then rename, so a crash never leaves half a file
+        let path = path.as_ref();
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, out)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a replay written by `write`
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let text = std::fs::read_to_string(path.as_ref()).map_err(|_| anyhow!("No such replay"))?;
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header: ReplayHeader = serde_json::from_str(lines.next().ok_or_else(|| anyhow!("Empty replay file"))?)?;
+        if header.version > REPLAY_VERSION {
+            return Err(anyhow!("This replay was written by a newer server"));
+        }
+
+        let mut ticks = Vec::new();
+        for (line_number, line) in lines.enumerate() {
+            let tick: ReplayTick = serde_json::from_str(line)
+                .map_err(|e| anyhow!("Corrupt replay at line {}: {}", line_number + 2, e))?;
+            ticks.push(tick);
+        }
+
+        Ok(Self {
+            game_id: header.game_id,
+            seed: header.seed,
+            tick_rate_ms: header.tick_rate_ms,
+            rules: header.rules.into_owned(),
+            initial: header.initial.into_owned(),
+            ticks,
+        })
+    }
+}
+
+impl GameEngine {
+    /// Start recording a replay from the current state, dropping any earlier recording
+    pub fn start_replay(&mut self, game_id: Uuid) {
+        let seed = self.rng.gen();
+        self.reseed(seed);
+        self.replay = Some(Replay {
+            game_id,
+            seed,
+            tick_rate_ms: self.tick_rate_ms,
+            rules: self.rules.clone(),
+            initial: self.state.clone(),
+            ticks: Vec::new(),
+        });
+    }
+
+    /// Log a player's applied command, if a replay is being recorded
+    pub fn record_command(&mut self, player: PlayerId, command: &ClientMessage) {
+        if let Some(replay) = &mut self.replay {
+            replay.record(self.state.tick, ReplayEvent::Command { player: player.into(), command: command.clone() });
+        }
+    }
+
+    /// Apply a command that acts on the game, the way a player's session would
+    pub fn apply_command(&mut self, player: PlayerId, command: &ClientMessage) -> Result<()> {
+        match *command {
+            ClientMessage::Attack { from, to } => self.launch_attack(player, from.into(), to.into()).map(|_| ()),
+            ClientMessage::Retreat { battle } => self.retreat(player, battle).map(|_| ()),
+            ClientMessage::MoveTroops { from, to, amount } => self.move_troops(player, from.into(), to.into(), amount),
+            ClientMessage::BuildStructure { territory, building_type } => {
+                self.build_structure(player, territory.into(), building_type)
+            }
+            ClientMessage::SetTroopRatio { ratio } => self.set_troop_ratio(player, ratio),
+            ClientMessage::UpgradeBuilding { territory } => self.upgrade_building(player, territory.into()),
+            ClientMessage::DemolishBuilding { territory } => self.demolish_building(player, territory.into()),
+            ClientMessage::SetAttackRatio { ratio } => self.set_attack_ratio(player, ratio),
+            ClientMessage::SavePreset { ref name, troop_ratio, attack_ratio } => {
+                self.save_preset(player, RatioPreset { name: name.clone(), troop_ratio, attack_ratio })
+            }
+            ClientMessage::ApplyPreset { ref name } => self.apply_preset(player, name),
+            ClientMessage::SetUnitMix { infantry, cavalry, siege } => {
+                self.set_unit_mix(player, UnitMix { infantry, cavalry, siege })
+            }
+            ClientMessage::QueueOrder { ref order, tick } => self.queue_order(player, order.clone(), tick).map(|_| ()),
+            ClientMessage::ClearOrders => {
+                self.clear_orders(player);
+                Ok(())
+            }
+            ClientMessage::StartResearch { tech } => self.start_research(player, tech),
+            ClientMessage::PauseGame => self.request_pause(player, true),
+            ClientMessage::ResumeGame => self.request_pause(player, false),
+            ClientMessage::SetGameSpeed { speed } => self.request_game_speed(player, speed),
+            ClientMessage::ProposeTreaty { player: other } => self.propose_treaty(player, other.into()).map(|_| ()),
+            ClientMessage::BreakTreaty { player: other } => self.break_treaty(player, other.into()),
+            ClientMessage::GiftTerritory { territory, to_player } => {
+                self.gift_territory(player, territory.into(), to_player.into())
+            }
+            _ => Err(anyhow!("{} doesn't act on the game", command.name())),
+        }
+    }
+
+    pub(super) fn record_battle_replay(&mut self, result: &CombatResult) {
+        if let Some(replay) = &mut self.replay {
+            replay.record(self.state.tick, ReplayEvent::Battle { result: result.clone() });
+        }
+    }
+
+    /// Keep the whole state on keyframe ticks; called once the tick is over,
+    /// AI moves included
+    pub(super) fn record_replay_keyframe(&mut self) {
+        let tick = self.state.tick;
+        if let Some(replay) = self.replay.as_mut().filter(|r| tick.is_multiple_of(REPLAY_KEYFRAME_TICKS) && tick != r.initial.tick) {
+            replay.tick_mut(tick).state = Some(self.state.clone());
+        }
+    }
+
+    /// Stop recording, returning the replay with the final state as its last keyframe
+    pub fn finish_replay(&mut self) -> Option<Replay> {
+        let mut replay = self.replay.take()?;
+        replay.tick_mut(self.state.tick).state = Some(self.state.clone());
+        Some(replay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_replay_records_commands_and_battles_per_tick() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 20);
+        engine.tick();
+        engine.start_replay(Uuid::new_v4());
+        let initial_tick = engine.state.tick;
+
+        let player = engine.state.players[0].id;
+        let command = ClientMessage::SetTroopRatio { ratio: 0.4 };
+        engine.set_troop_ratio(player.into(), 0.4).unwrap();
+        engine.record_command(player.into(), &command);
+        engine.tick();
+        engine.tick();
+
+        // Any land border will do for a battle
+        let (from, to) = engine
+            .state
+            .territories
+            .iter()
+            .filter(|t| t.owner == Some(player) && t.troops() > 0)
+            .find_map(|t| {
+                let target = t.neighbors.iter().find(|n| {
+                    let neighbor = engine.get_territory((**n).into()).unwrap();
+                    neighbor.owner != Some(player) && neighbor.terrain != TerrainType::Water
+                })?;
+                Some((t.id, *target))
+            })
+            .unwrap();
+        let result = engine.execute_attack(player.into(), from.into(), to.into()).unwrap();
+        engine.record_command(player.into(), &ClientMessage::Attack { from, to });
+
+        let replay = engine.replay.as_ref().unwrap();
+        assert_eq!(replay.initial.tick, initial_tick);
+        assert_eq!(replay.ticks.iter().map(|t| t.tick).collect::<Vec<_>>(), [initial_tick, initial_tick + 2]);
+        assert_eq!(replay.len(), 3);
+        assert!(matches!(
+            &replay.ticks[1].events[0],
+            ReplayEvent::Battle { result: battle } if battle.to_territory == result.to_territory
+        ));
+
+        let path = std::env::temp_dir().join(format!("replay-{}.replay", Uuid::new_v4()));
+        replay.write(&path).unwrap();
+        let read = Replay::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.game_id, replay.game_id);
+        assert_eq!(read.initial.territories.len(), replay.initial.territories.len());
+        assert_eq!(read.len(), 3);
+        assert!(matches!(
+            &read.ticks[1].events[1],
+            ReplayEvent::Command { command: ClientMessage::Attack { to: target, .. }, .. } if *target == to
+        ));
+    }
+
+    #[test]
+    fn test_resimulating_a_replay_reaches_the_same_state() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 4).generate(), 20);
+        engine.start_replay(Uuid::new_v4());
+        let player: PlayerId = engine.state.players[0].id.into();
+
+        for tick in 0..300 {
+            engine.tick();
+            engine.tick_ai();
+            engine.take_events();
+            if tick % 40 == 0 {
+                let command = ClientMessage::SetAttackRatio { ratio: 0.3 + (tick % 80) as f32 / 200.0 };
+                engine.apply_command(player, &command).unwrap();
+                engine.record_command(player, &command);
+            }
+        }
+        let replay = engine.finish_replay().unwrap();
+        assert!(replay.len() > 8, "AI players should have fought");
+
+        let rerun = replay.resimulate();
+        assert_eq!(rerun.state.tick, engine.state.tick);
+        assert_eq!(serde_json::to_value(&rerun.state).unwrap(), serde_json::to_value(&engine.state).unwrap());
+    }
+}
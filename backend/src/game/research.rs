@@ -0,0 +1,111 @@
+//! Research.
+//!
+//! A player researches one tech at a time. The price is paid a little every
+//! tick rather than up front, and research stalls while the player can't
+//! afford the next instalment. A finished tech lasts for the rest of the
+//! game and is announced to everyone with `research_completed`.
+
+use anyhow::{anyhow, Result};
+
+use crate::types::*;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Start researching a tech
+    pub fn start_research(&mut self, player_id: PlayerId, tech: Tech) -> Result<()> {
+        let player = self.get_player(player_id)?;
+        if player.techs.contains(&tech) {
+            return Err(anyhow!("You have already researched this"));
+        }
+        if let Some(research) = player.research {
+            return Err(anyhow!("You are already researching {:?}", research.tech));
+        }
+        if tech.requires().is_some_and(|required| !player.techs.contains(&required)) {
+            return Err(anyhow!("Research {:?} first", tech.requires().unwrap()));
+        }
+
+        self.get_player_mut(player_id)?.research = Some(ResearchProgress { tech, progress: 0.0 });
+        Ok(())
+    }
+
+    /// Whether the player has researched a tech
+    pub fn has_tech(&self, player_id: PlayerId, tech: Tech) -> bool {
+        self.get_player(player_id).is_ok_and(|p| p.techs.contains(&tech))
+    }
+
+    /// Sum of a bonus over every tech the player has
+    pub(super) fn tech_bonus(&self, player_id: PlayerId, bonus: fn(&Tech) -> f32) -> f32 {
+        self.get_player(player_id).map_or(0.0, |p| p.techs.iter().map(bonus).sum())
+    }
+
+    /// What a building costs the player, discounts included
+    pub fn building_cost(&self, player_id: PlayerId, building_type: BuildingType) -> u64 {
+        let discount = self.tech_bonus(player_id, Tech::building_discount).min(1.0);
+        (building_type.cost() as f32 * (1.0 - discount)).round() as u64
+    }
+
+    /// Pay this tick's share of every research and finish those that are done
+    pub(super) fn update_research(&mut self) {
+        let seconds = self.tick_rate_ms as f32 / 1000.0 * self.state.game_speed;
+        let mut finished = Vec::new();
+
+        for player in self.state.players.iter_mut().filter(|p| p.is_alive) {
+            let Some(research) = &mut player.research else { continue };
+            let step = (seconds / research.tech.seconds()).min(1.0 - research.progress);
+            let instalment = (research.tech.cost() as f32 * step).ceil() as u64;
+            if player.gold < instalment {
+                continue;
+            }
+            player.gold -= instalment;
+            research.progress += step;
+
+            if research.progress >= 1.0 - f32::EPSILON {
+                let tech = research.tech;
+                player.techs.push(tech);
+                player.research = None;
+                finished.push((player.id, tech));
+            }
+        }
+
+        for (player_id, tech) in finished {
+            self.events.push(ServerMessage::ResearchCompleted { player_id, tech });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_research_is_paid_over_time_and_unlocks_upgrades() {
+        let mut engine = GameEngine::new(MapGenerator::new(10, 2).generate_seeded(1), 1000);
+        let player: PlayerId = engine.state.players[0].id.into();
+        assert!(engine.start_research(player, Tech::Tactics).is_err());
+
+        // Masonry takes 45 seconds and 600 gold, paid as it goes
+        engine.start_research(player, Tech::Masonry).unwrap();
+        assert!(engine.start_research(player, Tech::Drill).is_err());
+        engine.state.players[0].gold = 100;
+        engine.update_research();
+        let research = engine.get_player(player).unwrap().research.unwrap();
+        assert!(research.progress > 0.0 && research.progress < 0.1);
+        assert!(engine.get_player(player).unwrap().gold < 100);
+
+        // Broke, it stalls
+        engine.state.players[0].gold = 0;
+        engine.update_research();
+        assert_eq!(engine.get_player(player).unwrap().research.unwrap().progress, research.progress);
+
+        engine.state.players[0].gold = 10_000;
+        for _ in 0..45 {
+            engine.update_research();
+        }
+        assert!(engine.has_tech(player, Tech::Masonry));
+        assert!(engine.get_player(player).unwrap().research.is_none());
+        assert!(matches!(engine.take_events()[..], [ServerMessage::ResearchCompleted { tech: Tech::Masonry, .. }]));
+        assert_eq!(engine.building_cost(player, BuildingType::City), 800);
+        assert!(engine.start_research(player, Tech::Masonry).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+//! Resource deposits.
+//!
+//! Some land territories hold a deposit of iron, horses or gems. Whoever
+//! owns the territory gets its bonus: Iron makes their attacks hit harder,
+//! each Horses deposit speeds up troop training and each Gems deposit adds
+//! gold every second. Deposits are placed when the map is generated.
+
+use rand::Rng;
+
+use crate::types::*;
+use super::GameEngine;
+
+/// Chance that a land territory holds a deposit
+const RESOURCE_CHANCE: f64 = 0.12;
+
+const RESOURCES: [ResourceType; 3] = [ResourceType::Iron, ResourceType::Horses, ResourceType::Gems];
+
+/// Sprinkle deposits over the land of a generated map, starts included
+pub fn place_resources(state: &mut GameState, rng: &mut impl Rng) {
+    for territory in state.territories.iter_mut().filter(|t| t.terrain != TerrainType::Water) {
+        if rng.gen_bool(RESOURCE_CHANCE) {
+            territory.resource = Some(RESOURCES[rng.gen_range(0..RESOURCES.len())]);
+        }
+    }
+}
+
+impl GameEngine {
+    /// Deposits of a kind the player owns
+    pub fn deposits_held(&self, player_id: PlayerId, resource: ResourceType) -> u32 {
+        self.state
+            .territories
+            .iter()
+            .filter(|t| t.owner == Some(player_id.into()) && t.resource == Some(resource))
+            .count() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_deposits_pay_their_owners() {
+        let state = MapGenerator::new(100, 2).generate_seeded(8);
+        for resource in RESOURCES {
+            assert!(state.territories.iter().any(|t| t.resource == Some(resource)));
+        }
+        assert!(state.territories.iter().all(|t| t.resource.is_none() || t.terrain != TerrainType::Water));
+
+        let mut engine = GameEngine::new(MapGenerator::new(10, 2).generate_seeded(1), 100);
+        for territory in &mut engine.state.territories {
+            territory.resource = None;
+        }
+        let player: PlayerId = engine.state.players[0].id.into();
+        engine.tick();
+        let trained = engine.state.players[0].trained_ratio;
+
+        // Two deposits on the player's land, one not yet theirs
+        let mut plain = GameEngine::new(engine.state.clone(), 100);
+        let owned: Vec<usize> = (0..10).filter(|&i| engine.state.territories[i].owner == Some(player.into())).collect();
+        engine.state.territories[owned[0]].resource = Some(ResourceType::Gems);
+        let spare = engine.state.territories.iter().position(|t| t.owner.is_none()).unwrap();
+        engine.state.territories[spare].resource = Some(ResourceType::Gems);
+        engine.tick();
+        plain.tick();
+        assert_eq!(engine.deposits_held(player, ResourceType::Gems), 1);
+        let expected = plain.get_player(player).unwrap().gold_per_second + engine.rules.gems_gold_per_second;
+        assert!((engine.get_player(player).unwrap().gold_per_second - expected).abs() < 0.01);
+
+        // Horses speed up training toward a higher troop ratio
+        let mut plain = GameEngine::new(engine.state.clone(), 100);
+        engine.state.territories[owned[0]].resource = Some(ResourceType::Horses);
+        for engine in [&mut engine, &mut plain] {
+            engine.state.players[0].trained_ratio = trained;
+            engine.state.players[0].troop_ratio = 1.0;
+            engine.tick();
+        }
+        assert!(engine.state.players[0].trained_ratio > plain.state.players[0].trained_ratio);
+    }
+}
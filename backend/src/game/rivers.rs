@@ -0,0 +1,87 @@
+//! Rivers.
+//!
+//! Rivers follow the borders between regions: each pair of touching land
+//! regions has a chance of being split by one, which puts a river on every
+//! land border between them. Attacks across a river are weaker unless a
+//! Bridge stands on either bank, so rivers make lines worth holding.
+
+use rand::Rng;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use crate::types::*;
+
+/// Chance that two touching land regions are split by a river
+const RIVER_CHANCE: f64 = 0.3;
+
+/// Lay rivers along some of the region borders of an annotated map
+pub fn lay_rivers(state: &mut GameState, rng: &mut impl Rng) {
+    let index: HashMap<Uuid, usize> = state.territories.iter().enumerate().map(|(i, t)| (t.id, i)).collect();
+    let land = |t: &Territory| t.terrain != TerrainType::Water;
+
+    // Land edges between each pair of regions, in a stable order for the rng
+    let mut borders: BTreeMap<(u32, u32), Vec<(usize, usize)>> = BTreeMap::new();
+    for (i, territory) in state.territories.iter().enumerate().filter(|(_, t)| land(t)) {
+        for &j in territory.neighbors.iter().filter_map(|id| index.get(id)) {
+            let other = &state.territories[j];
+            if i < j && land(other) && territory.region != other.region {
+                let (a, b) = (territory.region, other.region);
+                borders.entry((a.min(b), a.max(b))).or_default().push((i, j));
+            }
+        }
+    }
+
+    for edges in borders.into_values() {
+        if !rng.gen_bool(RIVER_CHANCE) {
+            continue;
+        }
+        for (i, j) in edges {
+            let (a, b) = (state.territories[i].id, state.territories[j].id);
+            state.territories[i].borders.push(Border { neighbor: b, river: true });
+            state.territories[j].borders.push(Border { neighbor: a, river: true });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEngine, MapGenerator};
+
+    #[test]
+    fn test_rivers_shield_defenders_until_bridged() {
+        let state = MapGenerator::new(60, 2).generate_seeded(3);
+        let rivers: Vec<(&Territory, &Border)> =
+            state.territories.iter().flat_map(|t| t.borders.iter().map(move |b| (t, b))).collect();
+        assert!(!rivers.is_empty());
+        for (territory, border) in rivers {
+            let other = state.territories.iter().find(|t| t.id == border.neighbor).unwrap();
+            assert!(territory.neighbors.contains(&other.id) && other.river_to(territory.id));
+            assert!(territory.terrain != TerrainType::Water && other.terrain != TerrainType::Water);
+        }
+
+        // Two plains with a river between them; the attacker outnumbers the defender
+        let attack = |bridge: Option<usize>| {
+            let mut state = MapGenerator::new(2, 2).generate_seeded(1);
+            let ids: Vec<Uuid> = state.territories.iter().map(|t| t.id).collect();
+            for (i, territory) in state.territories.iter_mut().enumerate() {
+                territory.terrain = TerrainType::Plains;
+                territory.owner = None;
+                territory.units = Units::infantry(10);
+                territory.building = None;
+                territory.borders = vec![Border { neighbor: ids[1 - i], river: true }];
+            }
+            let player = state.players[0].id;
+            state.territories[0].owner = Some(player);
+            state.territories[0].units = Units::infantry(500);
+            let mut engine = GameEngine::new(state, 100);
+            if let Some(i) = bridge {
+                engine.get_territory_mut(ids[i].into()).unwrap().building = Some(BuildingType::Bridge);
+            }
+            engine.execute_attack(player.into(), ids[0].into(), ids[1].into()).unwrap().territory_conquered
+        };
+        assert!(!attack(None));
+        assert!(attack(Some(0)));
+        assert!(attack(Some(1)));
+    }
+}
@@ -0,0 +1,291 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use super::combat_model::{CombatModel, CombatModels, DEFAULT_COMBAT_MODEL};
+
+/// Difficulty presets for a game's rules
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl FromStr for Difficulty {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(anyhow!("Unknown difficulty: {}", s)),
+        }
+    }
+}
+
+/// What happens to a player whose capital is taken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapitalLoss {
+    /// Nothing beyond losing the capital's bonuses
+    #[default]
+    Nothing,
+    /// The player is eliminated on the spot
+    Eliminate,
+    /// The player's gold income is halved until they take it back
+    HalveIncome,
+}
+
+impl FromStr for CapitalLoss {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nothing" => Ok(CapitalLoss::Nothing),
+            "eliminate" => Ok(CapitalLoss::Eliminate),
+            "halve_income" => Ok(CapitalLoss::HalveIncome),
+            _ => Err(anyhow!("Unknown capital loss rule: {}", s)),
+        }
+    }
+}
+
+/// What happens to a building on a conquered territory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapturedBuildings {
+    /// The conqueror gets it, once it has been out of action for `building_capture_ticks`
+    #[default]
+    Keep,
+    /// It is destroyed in the fighting
+    Raze,
+}
+
+impl FromStr for CapturedBuildings {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "keep" => Ok(CapturedBuildings::Keep),
+            "raze" => Ok(CapturedBuildings::Raze),
+            _ => Err(anyhow!("Unknown captured buildings rule: {}", s)),
+        }
+    }
+}
+
+/// Tunable rules and hard limits for a single game; rules missing from a
+/// saved game take their defaults
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    /// Upper bound on a player's gold stockpile
+    pub max_gold: u64,
+    /// Upper bound on a player's population, regardless of buildings
+    pub max_population: u64,
+    /// Population cap of a player before territory and buildings
+    pub base_population_cap: u64,
+    /// Population cap added by each territory a player owns
+    pub population_cap_per_territory: u64,
+    /// Troops trained (or demobilized) per second when the troop ratio changes
+    pub troop_training_per_second: u64,
+    /// Extra training speed per Barracks, as a fraction of the base rate
+    pub barracks_training_bonus: f32,
+    /// Ticks a player may hold no territory (or be newly joined) before elimination
+    pub elimination_grace_ticks: u64,
+    /// Troops each neutral territory gains per second
+    pub neutral_fortify_per_second: f32,
+    /// Neutral garrisons stop fortifying at this size
+    pub neutral_max_garrison: u32,
+    /// Per-tick chance that a strong neutral pulls in a strong neutral neighbor's troops
+    pub neutral_merge_chance: f64,
+    /// Garrison both neutrals need before they can merge
+    pub neutral_merge_threshold: u32,
+    /// Gold production multiplier of a territory with a Gold Mine
+    pub gold_mine_multiplier: f32,
+    /// How attacks are resolved: the name of a model in `combat_models`
+    pub combat_model: String,
+    /// Models `combat_model` may name; not saved, so a game loaded from a
+    /// snapshot has to register its custom models again
+    #[serde(skip)]
+    pub combat_models: CombatModels,
+    /// Spread of the luck factor on each side's strength in an attack, e.g.
+    /// 0.15 for ±15%; drawn from the engine's seeded rng
+    pub combat_variance: f32,
+    /// Whether new players may join a game already in progress
+    pub allow_late_join: bool,
+    /// Neutral territories handed to a player joining late
+    pub late_join_territories: usize,
+    /// Extra starting gold for a late joiner per minute of game time
+    pub late_join_gold_per_minute: u64,
+    /// Extra starting population for a late joiner per minute of game time
+    pub late_join_population_per_minute: u64,
+    /// Ticks a player must wait between giving away territories
+    pub gift_cooldown_ticks: u64,
+    /// Ticks after signing a treaty during which the two players can't attack each other
+    pub truce_ticks: u64,
+    /// Whether water can be attacked and held like land
+    pub passable_water: bool,
+    /// Water territories an attack from a Harbor can cross
+    pub naval_range: u32,
+    /// Ticks an attack is fought over, with casualties every tick; 0 settles
+    /// it at once
+    pub battle_rounds: u32,
+    /// Share of a retreating stack cut down on the way home
+    pub retreat_losses: f32,
+    /// Ticks an attack on a Defense Post besieges it before the assault; 0
+    /// attacks fortified territories like any other
+    pub siege_ticks: u64,
+    /// Share of a besieged garrison lost per second while no neighboring
+    /// territory of its owner relieves it
+    pub siege_attrition_per_second: f32,
+    /// Whether buildings on conquered territories are kept or razed
+    pub captured_buildings: CapturedBuildings,
+    /// Ticks a captured building does nothing for its new owner
+    pub building_capture_ticks: u64,
+    /// Share of the people above a player's population cap who leave per second
+    pub over_cap_emigration: f32,
+    /// Factor on the defender's losses when attacked across a river no Bridge spans
+    pub river_defense_multiplier: f32,
+    /// Factor on the defender's losses at a capital held by its player
+    pub capital_defense_multiplier: f32,
+    /// Gold production multiplier of a capital held by its player
+    pub capital_gold_multiplier: f32,
+    /// What losing your capital costs
+    pub capital_loss: CapitalLoss,
+    /// Extra losses attacks inflict while the attacker holds any Iron
+    pub iron_attack_bonus: f32,
+    /// Extra training speed per Horses deposit held, as a fraction of the base rate
+    pub horses_training_bonus: f32,
+    /// Gold per second each Gems deposit yields
+    pub gems_gold_per_second: f32,
+    /// Food every player's homeland grows per second
+    pub base_food_per_second: f32,
+    /// Food each Plains territory grows per second
+    pub plains_food_per_second: f32,
+    /// Food each Farm grows per second
+    pub farm_food_per_second: f32,
+    /// Food each person eats per second
+    pub food_per_person: f32,
+    /// Share of the unfed who starve per second once the stockpile is empty
+    pub starvation_rate: f32,
+    /// Upper bound on a player's food stockpile
+    pub max_food: u64,
+    /// Pauses each player may call in a game; unlimited if unset
+    pub pauses_per_player: Option<u32>,
+    /// Whether the game speed stays as it was when the game started
+    pub lock_speed_after_start: bool,
+}
+
+impl GameRules {
+    pub fn for_difficulty(difficulty: Difficulty) -> Self {
+        let defaults = Self::default();
+        match difficulty {
+            Difficulty::Easy => Self {
+                neutral_fortify_per_second: 0.5,
+                neutral_max_garrison: 150,
+                neutral_merge_chance: 0.0,
+                ..defaults
+            },
+            Difficulty::Medium => defaults,
+            Difficulty::Hard => Self {
+                neutral_fortify_per_second: 2.0,
+                neutral_max_garrison: 400,
+                neutral_merge_chance: 0.005,
+                neutral_merge_threshold: 100,
+                ..defaults
+            },
+        }
+    }
+}
+
+impl GameRules {
+    /// Resolve attacks with the model registered under `name`
+    pub fn set_combat_model(&mut self, name: &str) -> Result<()> {
+        if self.combat_models.get(name).is_none() {
+            return Err(anyhow!("Unknown combat model: {} (registered: {})", name, self.combat_models.names().join(", ")));
+        }
+        self.combat_model = name.to_ascii_lowercase();
+        Ok(())
+    }
+
+    /// The combat model in use; the default one if `combat_model` names a
+    /// model that isn't registered, such as a custom one after a load
+    pub fn combat(&self) -> &dyn CombatModel {
+        self.combat_models
+            .get(&self.combat_model)
+            .or_else(|| self.combat_models.get(DEFAULT_COMBAT_MODEL))
+            .expect("the default combat model is always registered")
+    }
+
+    /// Set a numeric balance parameter by name, for parameter sweeps
+    pub fn set_parameter(&mut self, name: &str, value: f64) -> Result<()> {
+        match name {
+            "gold_mine_multiplier" => self.gold_mine_multiplier = value as f32,
+            "troop_training_per_second" => self.troop_training_per_second = value as u64,
+            "barracks_training_bonus" => self.barracks_training_bonus = value as f32,
+            "neutral_fortify_per_second" => self.neutral_fortify_per_second = value as f32,
+            "neutral_max_garrison" => self.neutral_max_garrison = value as u32,
+            "neutral_merge_chance" => self.neutral_merge_chance = value,
+            "base_population_cap" => self.base_population_cap = value as u64,
+            "population_cap_per_territory" => self.population_cap_per_territory = value as u64,
+            _ => return Err(anyhow!("Unknown rule parameter: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            max_gold: 1_000_000_000,
+            max_population: 100_000_000,
+            base_population_cap: 9_000,
+            population_cap_per_territory: 1_000,
+            troop_training_per_second: 50,
+            barracks_training_bonus: 0.5,
+            elimination_grace_ticks: 50,
+            neutral_fortify_per_second: 1.0,
+            neutral_max_garrison: 250,
+            neutral_merge_chance: 0.002,
+            neutral_merge_threshold: 120,
+            gold_mine_multiplier: 1.5,
+            combat_model: DEFAULT_COMBAT_MODEL.to_string(),
+            combat_models: CombatModels::default(),
+            combat_variance: 0.0,
+            allow_late_join: false,
+            late_join_territories: 3,
+            late_join_gold_per_minute: 150,
+            late_join_population_per_minute: 250,
+            gift_cooldown_ticks: 300,
+            truce_ticks: 600,
+            passable_water: false,
+            naval_range: 3,
+            battle_rounds: 0,
+            retreat_losses: 0.2,
+            siege_ticks: 0,
+            siege_attrition_per_second: 0.05,
+            captured_buildings: CapturedBuildings::Keep,
+            building_capture_ticks: 300,
+            over_cap_emigration: 0.05,
+            river_defense_multiplier: 0.7,
+            capital_defense_multiplier: 0.8,
+            capital_gold_multiplier: 1.5,
+            capital_loss: CapitalLoss::Nothing,
+            iron_attack_bonus: 0.15,
+            horses_training_bonus: 0.25,
+            gems_gold_per_second: 10.0,
+            base_food_per_second: 100.0,
+            plains_food_per_second: 30.0,
+            farm_food_per_second: 150.0,
+            food_per_person: 0.01,
+            starvation_rate: 0.05,
+            max_food: 1_000_000,
+            pauses_per_player: None,
+            lock_speed_after_start: false,
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Scenario scripts.
+//!
+//! Scenario authors add events in Rhai instead of Rust. The engine is
+//! sandboxed: no modules or file access, and every call is capped in
+//! operations, call depth and data sizes. Scripts only see read-only queries
+//! over the game state and can only act through `PluginAction`s, and run as a
+//! `RulePlugin` so they get the same time budget and panic isolation as any
+//! other plugin; a script that errors is disabled.
+//!
+//! The script registers triggers when it loads; each fires once, the first
+//! tick its condition holds:
+//!
+//! ```text
+//! // Reward whoever takes the capital
+//! when(|| owns("Player", 12), || { give_gold("Player", 500); announce("The capital has fallen!"); });
+//! when(|| tick() >= 600 && territories("AI 1") < 3, || give_population("AI 1", 2000));
+//! when(|| gold("Player") >= 50000, || win("Player"));
+//! when(|| tick() >= 0, || { passive("AI 1"); waves("AI 2", 300); });
+//! ```
+//!
+//! Players are referenced by name, territories by map index or id. Queries:
+//! `tick()`, `gold(p)`, `population(p)`, `territories(p)`, `owns(p, t)`,
+//! `eliminated(p)`. Actions: `give_gold(p, n)`, `give_population(p, n)`,
+//! `announce(text)`, `win(p)`, and for AI players `passive(p)` (no attacks
+//! until attacked), `waves(p, every)` (an attack from the whole border every
+//! `every` ticks, nothing in between) and `release(p)` (back to their
+//! personality). Unknown players count as having nothing, and actions on them
+//! are ignored.
+
+use anyhow::{anyhow, Result};
+use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, AST};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+use super::plugins::{AiScript, PluginAction, RulePlugin};
+use crate::types::*;
+
+/// Most triggers a script may register, to keep per-tick evaluation cheap
+const MAX_TRIGGERS: usize = 256;
+/// Rhai operations one condition or action may take
+const MAX_OPERATIONS: u64 = 100_000;
+
+struct Trigger {
+    condition: FnPtr,
+    action: FnPtr,
+    fired: bool,
+}
+
+/// What the script's functions see and do during one call
+#[derive(Default)]
+struct Context {
+    tick: u64,
+    players: Vec<Player>,
+    /// Territory ids and owners, in map order
+    territories: Vec<(Uuid, Option<Uuid>)>,
+    effects: Vec<PluginAction>,
+    winner: Option<Uuid>,
+    /// Triggers registered while the script loads
+    triggers: Vec<Trigger>,
+}
+
+impl Context {
+    fn player(&self, name: &str) -> Option<&Player> {
+        self.players.iter().find(|p| p.name == name)
+    }
+
+    fn act(&mut self, name: &str, action: impl FnOnce(Uuid) -> PluginAction) {
+        if let Some(player) = self.player(name) {
+            let action = action(player.id);
+            self.effects.push(action);
+        }
+    }
+}
+
+type Shared = Arc<Mutex<Context>>;
+
+fn clamp(value: u64) -> i64 {
+    value.min(i64::MAX as u64) as i64
+}
+
+fn sandboxed_engine(context: &Shared) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4 * 1024)
+        .set_max_array_size(1024)
+        .set_max_map_size(1024)
+        .on_print(|text| tracing::debug!("Scenario script: {}", text))
+        .on_debug(|text, _, _| tracing::debug!("Scenario script: {}", text));
+
+    let ctx = context.clone();
+    engine.register_fn("when", move |condition: FnPtr, action: FnPtr| -> Result<(), Box<EvalAltResult>> {
+        let mut ctx = ctx.lock().unwrap();
+        if ctx.triggers.len() >= MAX_TRIGGERS {
+            return Err(format!("scripts are limited to {} triggers", MAX_TRIGGERS).into());
+        }
+        ctx.triggers.push(Trigger { condition, action, fired: false });
+        Ok(())
+    });
+
+    // Queries
+    let ctx = context.clone();
+    engine.register_fn("tick", move || clamp(ctx.lock().unwrap().tick));
+    let ctx = context.clone();
+    engine.register_fn("gold", move |name: &str| ctx.lock().unwrap().player(name).map_or(0, |p| clamp(p.gold)));
+    let ctx = context.clone();
+    engine.register_fn("population", move |name: &str| ctx.lock().unwrap().player(name).map_or(0, |p| clamp(p.population)));
+    let ctx = context.clone();
+    engine.register_fn("territories", move |name: &str| {
+        ctx.lock().unwrap().player(name).map_or(0, |p| p.territories_controlled as i64)
+    });
+    let ctx = context.clone();
+    engine.register_fn("eliminated", move |name: &str| ctx.lock().unwrap().player(name).is_some_and(|p| !p.is_alive));
+    let ctx = context.clone();
+    engine.register_fn("owns", move |name: &str, index: i64| {
+        let ctx = ctx.lock().unwrap();
+        let owner = usize::try_from(index).ok().and_then(|i| ctx.territories.get(i)).and_then(|(_, owner)| *owner);
+        ctx.player(name).is_some_and(|p| owner == Some(p.id))
+    });
+    let ctx = context.clone();
+    engine.register_fn("owns", move |name: &str, id: &str| {
+        let ctx = ctx.lock().unwrap();
+        let id = id.parse::<Uuid>().ok();
+        let owner = ctx.territories.iter().find(|(t, _)| Some(*t) == id).and_then(|(_, owner)| *owner);
+        ctx.player(name).is_some_and(|p| owner == Some(p.id))
+    });
+
+    // Actions
+    let ctx = context.clone();
+    engine.register_fn("give_gold", move |name: &str, delta: i64| {
+        ctx.lock().unwrap().act(name, |player| PluginAction::AdjustGold { player, delta })
+    });
+    let ctx = context.clone();
+    engine.register_fn("give_population", move |name: &str, delta: i64| {
+        ctx.lock().unwrap().act(name, |player| PluginAction::AdjustPopulation { player, delta })
+    });
+    let ctx = context.clone();
+    engine.register_fn("announce", move |message: &str| {
+        ctx.lock().unwrap().effects.push(PluginAction::Announce { message: message.to_string() })
+    });
+    let ctx = context.clone();
+    engine.register_fn("win", move |name: &str| {
+        let mut ctx = ctx.lock().unwrap();
+        if let Some(player) = ctx.player(name).map(|p| p.id) {
+            ctx.winner.get_or_insert(player);
+        }
+    });
+    let ctx = context.clone();
+    engine.register_fn("passive", move |name: &str| {
+        ctx.lock().unwrap().act(name, |player| PluginAction::ScriptAi { player, script: Some(AiScript::Passive) })
+    });
+    let ctx = context.clone();
+    engine.register_fn("waves", move |name: &str, every: i64| -> Result<(), Box<EvalAltResult>> {
+        let every = u64::try_from(every).ok().filter(|&every| every > 0).ok_or("waves need a positive interval")?;
+        ctx.lock().unwrap().act(name, |player| PluginAction::ScriptAi { player, script: Some(AiScript::Waves { every }) });
+        Ok(())
+    });
+    let ctx = context.clone();
+    engine.register_fn("release", move |name: &str| {
+        ctx.lock().unwrap().act(name, |player| PluginAction::ScriptAi { player, script: None })
+    });
+
+    engine
+}
+
+/// A loaded scenario script, run as a rule plugin
+pub struct ScenarioScript {
+    engine: Engine,
+    ast: AST,
+    context: Shared,
+    triggers: Vec<Trigger>,
+    fault: Option<String>,
+}
+
+impl ScenarioScript {
+    /// Compile a script and run it to register its triggers
+    pub fn parse(source: &str) -> Result<Self> {
+        let context = Shared::default();
+        let engine = sandboxed_engine(&context);
+        let ast = engine.compile(source).map_err(|e| anyhow!("{}", e))?;
+        engine.run_ast(&ast).map_err(|e| anyhow!("{}", e))?;
+
+        let triggers = {
+            let mut ctx = context.lock().unwrap();
+            ctx.effects.clear();
+            std::mem::take(&mut ctx.triggers)
+        };
+        Ok(Self { engine, ast, context, triggers, fault: None })
+    }
+}
+
+impl RulePlugin for ScenarioScript {
+    fn name(&self) -> &str {
+        "scenario"
+    }
+
+    fn on_tick(&mut self, state: &GameState) -> Vec<PluginAction> {
+        if self.fault.is_some() {
+            return Vec::new();
+        }
+        {
+            let mut ctx = self.context.lock().unwrap();
+            ctx.tick = state.tick;
+            ctx.players = state.players.clone();
+            ctx.territories = state.territories.iter().map(|t| (t.id, t.owner)).collect();
+        }
+
+        for trigger in self.triggers.iter_mut().filter(|t| !t.fired) {
+            let fired = trigger.condition.call::<bool>(&self.engine, &self.ast, ()).and_then(|holds| {
+                if holds {
+                    trigger.fired = true;
+                    let _: Dynamic = trigger.action.call(&self.engine, &self.ast, ())?;
+                }
+                Ok(())
+            });
+            if let Err(e) = fired {
+                self.fault = Some(e.to_string());
+                break;
+            }
+        }
+
+        std::mem::take(&mut self.context.lock().unwrap().effects)
+    }
+
+    fn check_victory(&mut self, _state: &GameState) -> Option<Uuid> {
+        self.context.lock().unwrap().winner
+    }
+
+    fn fault(&self) -> Option<&str> {
+        self.fault.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEngine, MapGenerator};
+
+    fn engine_with(script: &str) -> GameEngine {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 3).generate(), 100);
+        engine.plugins.register(Box::new(ScenarioScript::parse(script).unwrap()));
+        engine
+    }
+
+    #[test]
+    fn test_parse_errors_report_line() {
+        let err = ScenarioScript::parse("// intro\n\nwhen(|| tick() >= 5, || {").err().unwrap();
+        assert!(err.to_string().contains("line 3"), "{}", err);
+
+        // Sandboxed: no modules to import
+        assert!(ScenarioScript::parse("import \"std\" as std;").is_err());
+    }
+
+    #[test]
+    fn test_capture_trigger_fires_once() {
+        let mut engine = engine_with(
+            "when(|| owns(\"Player\", 0), || { give_gold(\"Player\", 1_000); announce(\"Territory 0 taken\"); });",
+        );
+        let player_id = engine.state.players[0].id;
+        assert_eq!(engine.state.players[0].name, "Player");
+
+        engine.state.territories[0].owner = None;
+        engine.tick();
+        assert!(engine.take_events().is_empty());
+
+        engine.state.territories[0].owner = Some(player_id);
+        let gold_before = engine.state.players[0].gold;
+        engine.tick();
+        assert!(engine.state.players[0].gold >= gold_before + 1_000);
+        let events = engine.take_events();
+        assert!(matches!(events.as_slice(), [ServerMessage::Notification { message, .. }] if message == "Territory 0 taken"));
+
+        engine.tick();
+        assert!(engine.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_win_action_ends_game() {
+        let mut engine = engine_with("when(|| tick() >= 3 && territories(\"AI 1\") >= 1, || win(\"AI 1\"));");
+        let winner = engine.state.players[1].id;
+
+        engine.tick();
+        engine.tick();
+        assert!(engine.check_game_over().is_none());
+
+        engine.tick();
+        assert_eq!(engine.check_game_over().unwrap().winner, winner);
+    }
+
+    #[test]
+    fn test_failing_or_runaway_scripts_are_disabled() {
+        let mut engine = engine_with("when(|| tick() >= 1, || waves(\"AI 2\", 0));");
+        engine.tick();
+        assert!(engine.plugins.active().is_empty());
+
+        let mut engine = engine_with("when(|| { loop {} }, || win(\"AI 1\"));");
+        engine.tick();
+        assert!(engine.plugins.active().is_empty());
+        engine.tick();
+        assert!(engine.check_game_over().is_none());
+    }
+
+    #[test]
+    fn test_scripted_ai_holds_back_between_waves_and_until_provoked() {
+        let mut engine = engine_with("when(|| tick() >= 1, || { passive(\"AI 1\"); waves(\"AI 2\", 10); });");
+        engine.ai_log = Some(crate::game::ai::AiDecisionLog::default());
+        let (human, passive, waves) = (engine.state.players[0].id, engine.state.players[1].id, engine.state.players[2].id);
+        for _ in 0..12 {
+            engine.tick();
+            engine.tick_ai();
+        }
+
+        let log = engine.ai_log.as_ref().unwrap();
+        let kinds = |player| log.last(player).into_iter().filter(|d| d.kind != AiDecisionKind::Build).map(|d| (d.tick, d.kind)).collect::<Vec<_>>();
+        assert_eq!(kinds(passive), []);
+        assert_eq!(kinds(waves), [(11, AiDecisionKind::Wave)]);
+
+        // Attacking the passive AI ends its script
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(passive)).unwrap();
+        let border = engine.state.territories[home].neighbors[0];
+        let border = engine.state.territories.iter().position(|t| t.id == border).unwrap();
+        engine.state.territories[border].owner = Some(human);
+        engine.state.territories[border].units = Units::infantry(1_000);
+        engine.get_player_mut(human.into()).unwrap().trained_ratio = 1.0;
+        let (from, to) = (engine.state.territories[border].id, engine.state.territories[home].id);
+        engine.execute_attack(human.into(), from.into(), to.into()).unwrap();
+        assert!(!engine.ai_scripts.contains_key(&passive));
+        assert!(engine.ai_scripts.contains_key(&waves));
+    }
+}
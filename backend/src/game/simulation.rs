@@ -0,0 +1,538 @@
+//! Headless batch simulation for balance tuning.
+//!
+//! Runs AI-only games as fast as the CPU allows, one parameter value at a
+//! time, and summarizes who won and how long it took. Maps and the engine are
+//! seeded so a sweep compares every value on the same set of games.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use utoipa::ToSchema;
+
+use crate::types::*;
+use super::{GameEngine, GameRules, MapGenerator};
+
+/// Upper bound on games in one sweep
+pub const MAX_SWEEP_GAMES: u32 = 10_000;
+/// Upper bound on the ticks a simulated game may run for
+pub const MAX_SIMULATION_TICKS: u64 = 100_000;
+/// Upper bound on the territories of a simulated map
+pub const MAX_SIMULATION_TERRITORIES: usize = 1_000;
+/// A simulated game panicked, which is the server's fault rather than the request's
+#[derive(Debug)]
+pub struct SimulationPanicked;
+
+impl fmt::Display for SimulationPanicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a simulated game panicked")
+    }
+}
+
+impl std::error::Error for SimulationPanicked {}
+
+const SIMULATION_TICK_RATE_MS: u64 = 100;
+const PERSONALITIES: [AIPersonality; 5] = [
+    AIPersonality::Turtle,
+    AIPersonality::Aggressor,
+    AIPersonality::Balanced,
+    AIPersonality::Opportunist,
+    AIPersonality::Rusher,
+];
+
+/// A balance parameter sweep
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SweepRequest {
+    /// Rule to vary, e.g. `gold_mine_multiplier`
+    pub parameter: String,
+    /// Values to try
+    pub values: Vec<f64>,
+    /// Games played per value, on maps seeded `seed`, `seed + 1`, ...
+    pub games_per_value: u32,
+    #[serde(default)]
+    pub seed: u64,
+    /// Games still running after this many ticks count as unfinished
+    #[serde(default = "default_max_ticks")]
+    pub max_ticks: u64,
+    #[serde(default = "default_territories")]
+    pub territories: usize,
+    #[serde(default = "default_players")]
+    pub players: usize,
+}
+
+fn default_max_ticks() -> u64 {
+    6_000
+}
+
+fn default_territories() -> usize {
+    75
+}
+
+fn default_players() -> usize {
+    8
+}
+
+/// Outcome of one simulated game
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationResult {
+    pub ticks: u64,
+    /// Personality of the winner, if the game finished
+    pub winner: Option<AIPersonality>,
+    /// Personality holding the most territories when the game ended or was cut off
+    pub leader: Option<AIPersonality>,
+}
+
+/// Aggregated results for one parameter value
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SweepSummary {
+    pub value: f64,
+    pub games: u32,
+    pub finished_games: u32,
+    /// Average length of finished games, in ticks
+    pub avg_ticks: f64,
+    /// Wins per AI personality
+    pub wins: BTreeMap<String, u32>,
+    /// Territory leaders of unfinished games, per AI personality
+    pub leads: BTreeMap<String, u32>,
+}
+
+/// Play one AI-only game on the map for `seed`
+pub fn simulate(seed: u64, territories: usize, players: usize, rules: GameRules, max_ticks: u64) -> SimulationResult {
+    let (engine, winner) = play_out(MapGenerator::new(territories, players), seed, rules, max_ticks);
+    if let Some(winner) = winner {
+        return SimulationResult { ticks: engine.state.tick, winner: Some(winner), leader: Some(winner) };
+    }
+
+    let leader = engine.state.players
+        .iter()
+        .filter(|p| p.is_alive)
+        .max_by_key(|p| p.territories_controlled)
+        .and_then(|p| p.ai_personality);
+    SimulationResult { ticks: engine.state.tick, winner: None, leader }
+}
+
+/// Play a game to its end or to `max_ticks`, with the winner's personality if it ended
+fn play_out(map_gen: MapGenerator, seed: u64, rules: GameRules, max_ticks: u64) -> (GameEngine, Option<AIPersonality>) {
+    let state = map_gen.with_human_slots(0).generate_seeded(seed);
+    let mut engine = GameEngine::with_rules(state, SIMULATION_TICK_RATE_MS, rules);
+    engine.reseed(seed);
+
+    while engine.state.tick < max_ticks {
+        engine.tick();
+        engine.tick_ai();
+        engine.take_events();
+
+        if let Some(stats) = engine.check_game_over() {
+            let winner = engine.get_player(stats.winner.into()).ok().and_then(|p| p.ai_personality);
+            return (engine, winner);
+        }
+    }
+    (engine, None)
+}
+
+/// Run a sweep across all cores, one summary per value in request order
+pub fn run_sweep(request: &SweepRequest) -> Result<Vec<SweepSummary>> {
+    let total = (request.values.len() as u64).saturating_mul(request.games_per_value as u64);
+    if total == 0 || total > MAX_SWEEP_GAMES as u64 {
+        return Err(anyhow!("A sweep must run between 1 and {} games", MAX_SWEEP_GAMES));
+    }
+    if request.players < 2 || request.territories < request.players {
+        return Err(anyhow!("Need at least two players and a territory for each"));
+    }
+    if request.max_ticks > MAX_SIMULATION_TICKS {
+        return Err(anyhow!("Games run for at most {} ticks", MAX_SIMULATION_TICKS));
+    }
+    if request.territories > MAX_SIMULATION_TERRITORIES {
+        return Err(anyhow!("Maps can have at most {} territories", MAX_SIMULATION_TERRITORIES));
+    }
+
+    // Fail on a bad parameter before spending any CPU
+    let mut rule_sets = Vec::with_capacity(request.values.len());
+    for &value in &request.values {
+        let mut rules = GameRules::default();
+        rules.set_parameter(&request.parameter, value)?;
+        rule_sets.push(rules);
+    }
+
+    let jobs: Vec<(usize, u64)> = (0..rule_sets.len())
+        .flat_map(|value_idx| (0..request.games_per_value as u64).map(move |game| (value_idx, request.seed.wrapping_add(game))))
+        .collect();
+    let results = run_jobs(&jobs, |&(value_idx, seed)| {
+        let rules = rule_sets[value_idx].clone();
+        (value_idx, simulate(seed, request.territories, request.players, rules, request.max_ticks))
+    })?;
+
+    let mut summaries: Vec<SweepSummary> = request
+        .values
+        .iter()
+        .map(|&value| SweepSummary {
+            value,
+            games: 0,
+            finished_games: 0,
+            avg_ticks: 0.0,
+            wins: BTreeMap::new(),
+            leads: BTreeMap::new(),
+        })
+        .collect();
+    for (value_idx, result) in results {
+        let summary = &mut summaries[value_idx];
+        summary.games += 1;
+        if let Some(winner) = result.winner {
+            summary.finished_games += 1;
+            summary.avg_ticks += result.ticks as f64;
+            *summary.wins.entry(personality_name(winner).to_string()).or_default() += 1;
+        } else if let Some(leader) = result.leader {
+            *summary.leads.entry(personality_name(leader).to_string()).or_default() += 1;
+        }
+    }
+    for summary in &mut summaries {
+        if summary.finished_games > 0 {
+            summary.avg_ticks /= summary.finished_games as f64;
+        }
+    }
+
+    Ok(summaries)
+}
+
+/// Play every job on its own core, results in job order
+fn run_jobs<J: Sync, R: Send>(jobs: &[J], play: impl Fn(&J) -> R + Sync) -> Result<Vec<R>> {
+    let workers = std::thread::available_parallelism().map_or(1, |n| n.get()).min(jobs.len()).max(1);
+    let chunk_size = jobs.len().div_ceil(workers).max(1);
+
+    std::thread::scope(|scope| {
+        let play = &play;
+        let handles: Vec<_> = jobs
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(play).collect::<Vec<_>>()))
+            .collect();
+        let mut results = Vec::with_capacity(jobs.len());
+        for handle in handles {
+            results.extend(handle.join().map_err(|_| SimulationPanicked)?);
+        }
+        Ok(results)
+    })
+}
+
+fn personality_name(personality: AIPersonality) -> &'static str {
+    match personality {
+        AIPersonality::Turtle => "turtle",
+        AIPersonality::Aggressor => "aggressor",
+        AIPersonality::Balanced => "balanced",
+        AIPersonality::Opportunist => "opportunist",
+        AIPersonality::Rusher => "rusher",
+    }
+}
+
+/// Head-to-head games between every pair of AI personalities
+#[derive(Debug, Clone)]
+pub struct BenchmarkRequest {
+    /// Duels per pair of personalities, on maps seeded `seed`, `seed + 1`, ...
+    /// Each seed is played twice, once from each side of the map
+    pub games_per_pair: u32,
+    pub seed: u64,
+    /// Duels still running after this many ticks count as unfinished
+    pub max_ticks: u64,
+    pub territories: usize,
+}
+
+impl Default for BenchmarkRequest {
+    fn default() -> Self {
+        Self { games_per_pair: 10, seed: 0, max_ticks: default_max_ticks(), territories: 30 }
+    }
+}
+
+/// How one personality fared against another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Matchup {
+    pub personality: String,
+    pub opponent: String,
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    /// Unfinished duels where the personality held more territories
+    pub leads: u32,
+    /// Unfinished duels where the opponent held more territories
+    pub trails: u32,
+    /// Average length of finished duels, in ticks
+    pub avg_ticks: f64,
+}
+
+impl Matchup {
+    /// Share of the duels won, or leading when they were cut off
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 { 0.0 } else { (self.wins + self.leads) as f64 / self.games as f64 }
+    }
+}
+
+/// Win rates of every personality against every other, both ways round
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub games_per_pair: u32,
+    pub seed: u64,
+    pub max_ticks: u64,
+    pub territories: usize,
+    pub matchups: Vec<Matchup>,
+}
+
+/// Duel every personality against every other on the same maps, from both
+/// sides of each map so neither gets the better start
+pub fn run_benchmark(request: &BenchmarkRequest) -> Result<BenchmarkReport> {
+    let pairs = PERSONALITIES.len() * (PERSONALITIES.len() - 1) / 2;
+    let total = (pairs as u64).saturating_mul(request.games_per_pair as u64);
+    if total == 0 || total > MAX_SWEEP_GAMES as u64 {
+        return Err(anyhow!("A benchmark must run between 1 and {} games", MAX_SWEEP_GAMES));
+    }
+    if request.territories < 2 {
+        return Err(anyhow!("Need at least two players and a territory for each"));
+    }
+    if request.max_ticks > MAX_SIMULATION_TICKS {
+        return Err(anyhow!("Games run for at most {} ticks", MAX_SIMULATION_TICKS));
+    }
+
+    let mut jobs = Vec::new();
+    for (i, &first) in PERSONALITIES.iter().enumerate() {
+        for &second in &PERSONALITIES[i + 1..] {
+            for game in 0..request.games_per_pair as u64 {
+                let sides = if game % 2 == 0 { [first, second] } else { [second, first] };
+                jobs.push((sides, request.seed.wrapping_add(game / 2)));
+            }
+        }
+    }
+    let results = run_jobs(&jobs, |&(sides, seed)| {
+        let map_gen = MapGenerator::new(request.territories, 2).with_ai_personalities(sides.to_vec());
+        let (engine, winner) = play_out(map_gen, seed, GameRules::default(), request.max_ticks);
+        // A duel still running goes to the side holding more land, if either does
+        let held = |personality| engine.state.players.iter()
+            .find(|p| p.ai_personality == Some(personality))
+            .map_or(0, |p| p.territories_controlled);
+        let leader = winner.or_else(|| match held(sides[0]).cmp(&held(sides[1])) {
+            std::cmp::Ordering::Greater => Some(sides[0]),
+            std::cmp::Ordering::Less => Some(sides[1]),
+            std::cmp::Ordering::Equal => None,
+        });
+        SimulationResult { ticks: engine.state.tick, winner, leader }
+    })?;
+
+    let mut matchups = Vec::new();
+    for &personality in &PERSONALITIES {
+        for &opponent in PERSONALITIES.iter().filter(|&&o| o != personality) {
+            let duels: Vec<&SimulationResult> = jobs.iter().zip(&results)
+                .filter(|((sides, _), _)| sides.contains(&personality) && sides.contains(&opponent))
+                .map(|(_, result)| result)
+                .collect();
+            let finished: Vec<u64> = duels.iter().filter(|r| r.winner.is_some()).map(|r| r.ticks).collect();
+            let count = |won: &dyn Fn(&SimulationResult) -> bool| duels.iter().filter(|r| won(r)).count() as u32;
+            matchups.push(Matchup {
+                personality: personality_name(personality).to_string(),
+                opponent: personality_name(opponent).to_string(),
+                games: duels.len() as u32,
+                wins: count(&|r| r.winner == Some(personality)),
+                losses: count(&|r| r.winner == Some(opponent)),
+                leads: count(&|r| r.winner.is_none() && r.leader == Some(personality)),
+                trails: count(&|r| r.winner.is_none() && r.leader == Some(opponent)),
+                avg_ticks: if finished.is_empty() { 0.0 } else { finished.iter().sum::<u64>() as f64 / finished.len() as f64 },
+            });
+        }
+    }
+
+    Ok(BenchmarkReport {
+        games_per_pair: request.games_per_pair,
+        seed: request.seed,
+        max_ticks: request.max_ticks,
+        territories: request.territories,
+        matchups,
+    })
+}
+
+impl BenchmarkReport {
+    fn matchup(&self, personality: &str, opponent: &str) -> Option<&Matchup> {
+        self.matchups.iter().find(|m| m.personality == personality && m.opponent == opponent)
+    }
+
+    /// Win-rate matrix (row against column) and average duel lengths, as text
+    pub fn to_table(&self) -> String {
+        let names: Vec<&str> = PERSONALITIES.iter().map(|&p| personality_name(p)).collect();
+        let mut out = String::new();
+        let header = |out: &mut String, title: &str| {
+            let _ = write!(out, "{:<12}", title);
+            for name in &names {
+                let _ = write!(out, "{:>12}", name);
+            }
+        };
+
+        header(&mut out, "win rate");
+        out.push_str("     overall\n");
+        for row in &names {
+            let _ = write!(out, "{:<12}", row);
+            let (mut wins, mut games) = (0, 0);
+            for column in &names {
+                match self.matchup(row, column) {
+                    Some(m) => {
+                        let _ = write!(out, "{:>11.0}%", m.win_rate() * 100.0);
+                        wins += m.wins + m.leads;
+                        games += m.games;
+                    }
+                    None => {
+                        let _ = write!(out, "{:>12}", "-");
+                    }
+                }
+            }
+            let overall = if games == 0 { 0.0 } else { wins as f64 / games as f64 };
+            let _ = writeln!(out, "{:>11.0}%", overall * 100.0);
+        }
+
+        out.push('\n');
+        header(&mut out, "avg ticks");
+        out.push('\n');
+        for row in &names {
+            let _ = write!(out, "{:<12}", row);
+            for column in &names {
+                match self.matchup(row, column) {
+                    Some(m) if m.wins + m.losses > 0 => {
+                        let _ = write!(out, "{:>12.0}", m.avg_ticks);
+                    }
+                    _ => {
+                        let _ = write!(out, "{:>12}", "-");
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        let unfinished: u32 = self.matchups.iter().map(|m| m.games - m.wins - m.losses).sum::<u32>() / 2;
+        let _ = writeln!(
+            out,
+            "\n{} duels per pair on {} territories, seeds from {}; {} cut off at {} ticks and scored by territories held",
+            self.games_per_pair, self.territories, self.seed, unfinished, self.max_ticks
+        );
+        out
+    }
+
+    /// Matchups whose win rate moved more than `tolerance` since `baseline`, one line each
+    pub fn regressions(&self, baseline: &BenchmarkReport, tolerance: f64) -> Vec<String> {
+        self.matchups
+            .iter()
+            .filter(|m| m.personality < m.opponent)
+            .filter_map(|m| {
+                let before = baseline.matchup(&m.personality, &m.opponent)?.win_rate();
+                let after = m.win_rate();
+                ((after - before).abs() > tolerance).then(|| {
+                    format!(
+                        "{} vs {}: {:.0}% -> {:.0}%",
+                        m.personality, m.opponent, before * 100.0, after * 100.0
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// Render summaries as CSV with win and lead columns per personality
+pub fn summaries_to_csv(summaries: &[SweepSummary]) -> String {
+    let mut out = String::from("value,games,finished_games,avg_ticks");
+    for personality in PERSONALITIES {
+        let _ = write!(out, ",{}_wins", personality_name(personality));
+    }
+    for personality in PERSONALITIES {
+        let _ = write!(out, ",{}_leads", personality_name(personality));
+    }
+    out.push('\n');
+
+    for summary in summaries {
+        let _ = write!(out, "{},{},{},{:.1}", summary.value, summary.games, summary.finished_games, summary.avg_ticks);
+        for counts in [&summary.wins, &summary.leads] {
+            for personality in PERSONALITIES {
+                let _ = write!(out, ",{}", counts.get(personality_name(personality)).copied().unwrap_or(0));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(values: Vec<f64>, games_per_value: u32) -> SweepRequest {
+        SweepRequest {
+            parameter: "gold_mine_multiplier".to_string(),
+            values,
+            games_per_value,
+            seed: 1,
+            max_ticks: 300,
+            territories: 12,
+            players: 3,
+        }
+    }
+
+    #[test]
+    fn test_panicking_job_is_reported() {
+        let result = run_jobs(&[1, 2, 3], |&job| if job == 2 { panic!("broken game") } else { job });
+
+        assert!(result.unwrap_err().is::<SimulationPanicked>());
+        assert_eq!(run_jobs(&[1, 2, 3], |&job| job * 2).unwrap(), [2, 4, 6]);
+    }
+
+    #[test]
+    fn test_sweep_summarizes_every_value() {
+        let summaries = run_sweep(&request(vec![1.3, 1.7], 3)).unwrap();
+
+        assert_eq!(summaries.iter().map(|s| s.value).collect::<Vec<_>>(), [1.3, 1.7]);
+        for summary in &summaries {
+            assert_eq!(summary.games, 3);
+            assert_eq!(summary.wins.values().sum::<u32>(), summary.finished_games);
+            assert_eq!(summary.leads.values().sum::<u32>(), summary.games - summary.finished_games);
+        }
+
+        let csv = summaries_to_csv(&summaries);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("value,games,finished_games,avg_ticks,turtle_wins"));
+        assert!(lines[1].starts_with("1.3,3,"));
+    }
+
+    #[test]
+    fn test_sweep_rejects_bad_requests() {
+        assert!(run_sweep(&request(vec![1.5], 0)).is_err());
+        assert!(run_sweep(&request(vec![1.5], MAX_SWEEP_GAMES + 1)).is_err());
+        let unknown = SweepRequest { parameter: "gravity".to_string(), ..request(vec![1.5], 1) };
+        assert!(run_sweep(&unknown).is_err());
+        let endless = SweepRequest { max_ticks: MAX_SIMULATION_TICKS + 1, ..request(vec![1.5], 1) };
+        assert!(run_sweep(&endless).is_err());
+        let huge = SweepRequest { territories: MAX_SIMULATION_TERRITORIES + 1, ..request(vec![1.5], 1) };
+        assert!(run_sweep(&huge).is_err());
+
+        // Seeds wrap around rather than overflow
+        let last_seed = SweepRequest { seed: u64::MAX, max_ticks: 10, ..request(vec![1.5], 2) };
+        assert_eq!(run_sweep(&last_seed).unwrap()[0].games, 2);
+    }
+
+    #[test]
+    fn test_benchmark_plays_every_pair_from_both_sides() {
+        let request = BenchmarkRequest { games_per_pair: 2, seed: 1, max_ticks: 300, territories: 12 };
+        let report = run_benchmark(&request).unwrap();
+
+        assert_eq!(report.matchups.len(), 20);
+        for m in &report.matchups {
+            assert_eq!(m.games, 2);
+            let other = report.matchup(&m.opponent, &m.personality).unwrap();
+            assert_eq!((m.wins, m.losses, m.leads, m.trails), (other.losses, other.wins, other.trails, other.leads));
+            assert!(m.wins + m.losses + m.leads + m.trails <= m.games);
+        }
+        let table = report.to_table();
+        assert!(table.starts_with("win rate") && table.contains("rusher"));
+
+        // The same seeds play the same duels, so nothing has moved
+        assert_eq!(run_benchmark(&request).unwrap(), report);
+        assert!(report.regressions(&report, 0.0).is_empty());
+        let wins = |report: &BenchmarkReport, wins| {
+            let mut report = report.clone();
+            for m in report.matchups.iter_mut().filter(|m| m.personality == "aggressor" && m.opponent == "turtle") {
+                (m.wins, m.losses, m.leads, m.trails) = (wins, 2 - wins, 0, 0);
+            }
+            report
+        };
+        assert_eq!(wins(&report, 2).regressions(&wins(&report, 0), 0.1), ["aggressor vs turtle: 0% -> 100%"]);
+        assert!(run_benchmark(&BenchmarkRequest { games_per_pair: 0, ..request }).is_err());
+    }
+}
@@ -0,0 +1,362 @@
+//! Whole games saved to disk, so a long match can be resumed after a restart.
+//!
+//! A snapshot holds everything the engine needs to go on where it stopped:
+//! the game state, the rules, the tick rate, the random number generator,
+//! queued orders, the replay being recorded, the summary timeline and the
+//! per-player bookkeeping behind eliminations, pauses and gifts. Plugins, bot
+//! memory and timings are not part of it and start fresh on load. Snapshots
+//! written before version 2 hold only the state, rules and battle tallies.
+//!
+//! A `Checkpoint` is the in-memory kind the server rolls a game back to after
+//! a crashed tick; it leaves the recording out and only notes how far it had
+//! got, so it is cheap enough to take every few ticks.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::ai::ScriptedAi;
+use super::orders::QueuedOrder;
+use super::replay::Replay;
+use super::{GameEngine, GameRules};
+
+/// Snapshot format written by this server
+const SNAPSHOT_VERSION: u32 = 2;
+
+/// A game engine frozen in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    version: u32,
+    pub saved_at: DateTime<Utc>,
+    tick_rate_ms: u64,
+    rules: GameRules,
+    pub state: GameState,
+    #[serde(default)]
+    territory_stats: Vec<TerritoryStats>,
+    #[serde(default)]
+    rng: Option<ChaCha12Rng>,
+    #[serde(default)]
+    orders: HashMap<Uuid, VecDeque<QueuedOrder>>,
+    #[serde(default)]
+    next_order_id: u64,
+    #[serde(default)]
+    next_battle_id: Option<u64>,
+    #[serde(default)]
+    replay: Option<Replay>,
+    #[serde(default)]
+    ai_scripts: HashMap<Uuid, ScriptedAi>,
+    #[serde(default)]
+    joined_at: HashMap<Uuid, u64>,
+    #[serde(default)]
+    landless_since: HashMap<Uuid, u64>,
+    #[serde(default)]
+    last_conquered_by: HashMap<Uuid, Uuid>,
+    #[serde(default)]
+    last_gift: HashMap<Uuid, u64>,
+    #[serde(default)]
+    pauses_used: HashMap<Uuid, u32>,
+    #[serde(default)]
+    pending_pause: Option<(String, bool)>,
+    #[serde(default)]
+    pending_speed: Option<(String, f32)>,
+    #[serde(default)]
+    elapsed_seconds: Option<f64>,
+    #[serde(default)]
+    battles: u32,
+    #[serde(default)]
+    biggest_battle: Option<BattleHighlight>,
+    #[serde(default)]
+    timeline: Vec<TimelineEntry>,
+}
+
+/// A game to roll back to, without a copy of its recording
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    snapshot: GameSnapshot,
+    /// Position of the recording, if the game was recording
+    replay: Option<(usize, usize)>,
+}
+
+impl GameEngine {
+    /// The whole game as it stands
+    pub fn snapshot(&self) -> GameSnapshot {
+        self.snapshot_with(self.replay.clone())
+    }
+
+    fn snapshot_with(&self, replay: Option<Replay>) -> GameSnapshot {
+        GameSnapshot {
+            version: SNAPSHOT_VERSION,
+            saved_at: Utc::now(),
+            tick_rate_ms: self.tick_rate_ms,
+            rules: self.rules.clone(),
+            state: self.state.clone(),
+            territory_stats: self.territory_stats(),
+            rng: Some(self.rng.clone()),
+            orders: self.orders.clone(),
+            next_order_id: self.next_order_id,
+            next_battle_id: Some(self.next_battle_id),
+            replay,
+            ai_scripts: self.ai_scripts.clone(),
+            joined_at: self.joined_at.clone(),
+            landless_since: self.landless_since.clone(),
+            last_conquered_by: self.last_conquered_by.clone(),
+            last_gift: self.last_gift.clone(),
+            pauses_used: self.pauses_used.clone(),
+            pending_pause: self.pending_pause.clone(),
+            pending_speed: self.pending_speed.clone(),
+            elapsed_seconds: Some(self.elapsed_seconds),
+            battles: self.battles,
+            biggest_battle: self.biggest_battle.clone(),
+            timeline: self.timeline.clone(),
+        }
+    }
+
+    /// Pick a game up from a snapshot
+    pub fn restore(snapshot: GameSnapshot) -> Result<Self> {
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(anyhow!("This save was written by a newer server"));
+        }
+        let mut engine = Self::with_rules(snapshot.state, snapshot.tick_rate_ms, snapshot.rules);
+        engine.territory_stats = snapshot.territory_stats.into_iter().map(|t| (t.territory_id, t)).collect();
+        if let Some(rng) = snapshot.rng {
+            engine.rng = rng;
+        }
+        engine.orders = snapshot.orders;
+        engine.next_order_id = snapshot.next_order_id;
+        engine.next_battle_id = snapshot.next_battle_id.unwrap_or(engine.next_battle_id);
+        engine.replay = snapshot.replay;
+        engine.ai_scripts = snapshot.ai_scripts;
+        engine.joined_at.extend(snapshot.joined_at);
+        engine.landless_since = snapshot.landless_since;
+        engine.last_conquered_by = snapshot.last_conquered_by;
+        engine.last_gift = snapshot.last_gift;
+        engine.pauses_used = snapshot.pauses_used;
+        engine.pending_pause = snapshot.pending_pause;
+        engine.pending_speed = snapshot.pending_speed;
+        engine.elapsed_seconds = snapshot.elapsed_seconds.unwrap_or(engine.elapsed_seconds);
+        engine.battles = snapshot.battles;
+        engine.biggest_battle = snapshot.biggest_battle;
+        engine.timeline = snapshot.timeline;
+        Ok(engine)
+    }
+
+    /// The game as it stands, to `roll_back` to
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint { snapshot: self.snapshot_with(None), replay: self.replay.as_ref().map(Replay::position) }
+    }
+
+    /// Put the game back as it was at `checkpoint`, lookups and bookkeeping
+    /// included. Plugins, bot memory and timings carry on, and the recording
+    /// is cut back to where it was.
+    pub fn roll_back(&mut self, checkpoint: Checkpoint) -> Result<()> {
+        let mut engine = Self::restore(checkpoint.snapshot)?;
+        engine.plugins = std::mem::take(&mut self.plugins);
+        engine.ai_log = self.ai_log.take();
+        engine.perf = std::mem::take(&mut self.perf);
+        engine.replay = self.replay.take();
+        if let (Some(replay), Some(position)) = (engine.replay.as_mut(), checkpoint.replay) {
+            replay.rewind(position);
+        }
+        *self = engine;
+        Ok(())
+    }
+
+    /// Write the game to a JSON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        // Write then rename, so a crash never leaves half a file
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.snapshot())?)?;
+        std::fs::rename(tmp, path)?;
+        Ok(())
+    }
+
+    /// Read a game written by `save`
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let json = std::fs::read(path.as_ref()).map_err(|_| anyhow!("No such save"))?;
+        Self::restore(serde_json::from_slice(&json)?)
+    }
+
+    /// Seat `players` in the game's human slots: those already in the game keep
+    /// their own, and the rest take over the other human players in order,
+    /// ids included. A game loaded from another session keeps its connections
+    /// this way.
+    pub fn seat_players(&mut self, players: &[PlayerId]) -> Result<()> {
+        let humans: Vec<Uuid> = self.state.players.iter().filter(|p| !p.is_ai).map(|p| p.id).collect();
+        let players: Vec<Uuid> = players.iter().map(|&p| p.into()).collect();
+        let mut free = humans.iter().filter(|h| !players.contains(h));
+
+        let mut ids: HashMap<Uuid, Uuid> = HashMap::new();
+        for &player in players.iter().filter(|p| !humans.contains(p)) {
+            let &seat = free.next().ok_or_else(|| anyhow!("This save has only {} human players", humans.len()))?;
+            ids.insert(seat, player);
+        }
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        // Every place a player id is stored
+        let rename = |id: &mut Uuid| {
+            if let Some(new) = ids.get(id) {
+                *id = *new;
+            }
+        };
+        let state = &mut self.state;
+        state.players.iter_mut().for_each(|p| rename(&mut p.id));
+        state.territories.iter_mut().filter_map(|t| t.owner.as_mut()).for_each(rename);
+        state.territories.iter_mut().filter_map(|t| t.capital_of.as_mut()).for_each(rename);
+        let diplomacy = &mut state.diplomacy;
+        diplomacy.treaties.iter_mut().flat_map(|t| t.players.iter_mut()).for_each(rename);
+        diplomacy.truces.iter_mut().flat_map(|t| t.players.iter_mut()).for_each(rename);
+        diplomacy.offers.iter_mut().for_each(|o| {
+            rename(&mut o.from);
+            rename(&mut o.to);
+        });
+        state.victory.iter_mut().for_each(|v| rename(&mut v.player_id));
+        state.battles.iter_mut().for_each(|b| {
+            rename(&mut b.attacker_id);
+            if let Some(defender) = b.defender_id.as_mut() {
+                rename(defender);
+            }
+        });
+
+        // And the engine's own bookkeeping by player
+        for (seat, player) in &ids {
+            rekey(&mut self.orders, seat, *player);
+            rekey(&mut self.pauses_used, seat, *player);
+            rekey(&mut self.last_gift, seat, *player);
+            rekey(&mut self.landless_since, seat, *player);
+            rekey(&mut self.last_conquered_by, seat, *player);
+        }
+        self.last_conquered_by.values_mut().for_each(rename);
+
+        self.player_map = self.state.players.iter().enumerate().map(|(idx, p)| (p.id.into(), idx)).collect();
+        self.joined_at = self.state.players.iter().map(|p| (p.id, self.state.tick)).collect();
+        Ok(())
+    }
+}
+
+fn rekey<V>(map: &mut HashMap<Uuid, V>, from: &Uuid, to: Uuid) {
+    if let Some(value) = map.remove(from) {
+        map.insert(to, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    fn temp_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("snapshot-{}.json", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 4).generate(), 100);
+        engine.rules.truce_ticks = 42;
+        for _ in 0..30 {
+            engine.tick();
+        }
+        let path = temp_path();
+        engine.save(&path).unwrap();
+
+        let loaded = GameEngine::load(&path).unwrap();
+        assert_eq!(loaded.state.tick, 30);
+        assert_eq!(loaded.state.checksum(), engine.state.checksum());
+        assert_eq!(loaded.rules.truce_ticks, 42);
+        loaded.check_invariants().unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(GameEngine::load(temp_path()).err().unwrap().to_string(), "No such save");
+    }
+
+    #[test]
+    fn test_restored_game_plays_on_exactly_as_the_original() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 4).generate(), 100);
+        engine.reseed(9);
+        for _ in 0..10 {
+            engine.tick();
+        }
+        let player: PlayerId = engine.state.players[0].id.into();
+        let home = engine.state.territories.iter().find(|t| t.owner == Some(player.into())).unwrap().id;
+        let build = Order::BuildStructure { territory: home, building_type: BuildingType::Farm };
+        let (order_id, _) = engine.queue_order(player, build, Some(engine.state.tick + 5)).unwrap();
+
+        let json = serde_json::to_vec(&engine.snapshot()).unwrap();
+        let mut restored = GameEngine::restore(serde_json::from_slice(&json).unwrap()).unwrap();
+        for _ in 0..20 {
+            engine.tick();
+            restored.tick();
+            let events = |engine: &mut GameEngine| serde_json::to_string(&engine.take_events()).unwrap();
+            assert_eq!(events(&mut restored), events(&mut engine));
+        }
+        assert_eq!(restored.state.checksum(), engine.state.checksum());
+        assert_eq!(restored.next_order_id, order_id);
+    }
+
+    #[test]
+    fn test_connected_players_take_over_human_seats() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 4).with_human_slots(2).generate(), 100);
+        engine.tick();
+        let humans: Vec<Uuid> = engine.state.players.iter().filter(|p| !p.is_ai).map(|p| p.id).collect();
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(humans[1])).unwrap();
+        let seat = engine.state.territories.iter().filter(|t| t.owner == Some(humans[0])).count();
+
+        // One player was in the saved game, the other is new
+        let newcomer = Uuid::new_v4();
+        engine.seat_players(&[newcomer.into(), humans[1].into()]).unwrap();
+        assert_eq!(engine.state.players.iter().filter(|p| !p.is_ai).map(|p| p.id).collect::<Vec<_>>(), [newcomer, humans[1]]);
+        assert_eq!(engine.state.territories.iter().filter(|t| t.owner == Some(newcomer)).count(), seat);
+        assert_eq!(engine.state.territories[home].owner, Some(humans[1]));
+        assert!(engine.get_player(humans[0].into()).is_err());
+        engine.check_invariants().unwrap();
+
+        let crowd: Vec<PlayerId> = (0..3).map(|_| Uuid::new_v4().into()).collect();
+        assert!(engine.seat_players(&crowd).is_err());
+    }
+
+    #[test]
+    fn test_battles_survive_a_load_into_new_seats() {
+        // The human holds territory 0, next to a neutral plain
+        let mut state = MapGenerator::new(20, 2).with_human_slots(1).generate();
+        let human = state.players.iter().find(|p| !p.is_ai).unwrap().id;
+        for territory in &mut state.territories {
+            territory.owner = None;
+        }
+        let (from, to) = (state.territories[0].id, state.territories[1].id);
+        state.territories[0].owner = Some(human);
+        state.territories[0].units = Units::infantry(500);
+        state.territories[1].terrain = TerrainType::Plains;
+        state.territories[1].building = None;
+        if !state.territories[0].neighbors.contains(&to) {
+            state.territories[0].neighbors.push(to);
+            state.territories[1].neighbors.push(from);
+        }
+        let mut engine = GameEngine::new(state, 100);
+        engine.rules.battle_rounds = 100;
+        let player = engine.get_player_mut(human.into()).unwrap();
+        (player.population, player.trained_ratio, player.attack_ratio) = (1_000, 0.5, 0.5);
+        engine.launch_attack(human.into(), from.into(), to.into()).unwrap();
+        let path = temp_path();
+        engine.save(&path).unwrap();
+
+        // The saved battle is the newcomer's now, and ending it brings the survivors home
+        let newcomer = Uuid::new_v4();
+        let mut loaded = GameEngine::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        loaded.seat_players(&[newcomer.into()]).unwrap();
+        let battle = loaded.state.battles[0].clone();
+        assert_eq!(battle.attacker_id, newcomer);
+        let home = loaded.get_territory(from.into()).unwrap().troops();
+        loaded.retreat(newcomer.into(), battle.id).unwrap();
+        assert!(loaded.get_territory(from.into()).unwrap().troops() > home);
+        loaded.audit_troops(newcomer.into()).unwrap();
+    }
+
+}
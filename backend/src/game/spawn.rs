@@ -0,0 +1,146 @@
+use anyhow::{anyhow, Result};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::map_gen::PLAYER_COLORS;
+use super::GameEngine;
+
+impl GameEngine {
+    /// Add a fresh human faction to a game in progress. It starts on a cluster
+    /// of neutral territories away from everyone else, with resources scaled
+    /// up by how long the game has been running
+    pub fn spawn_player(&mut self, name: String) -> Result<PlayerId> {
+        if !self.rules.allow_late_join {
+            return Err(anyhow!("Joining games in progress is disabled"));
+        }
+        let cluster = self.find_spawn_cluster(self.rules.late_join_territories.max(1))?;
+
+        let minutes = self.state.game_time_seconds as u64 / 60;
+        let population = (1000 + self.rules.late_join_population_per_minute.saturating_mul(minutes))
+            .min(self.rules.max_population);
+        let gold = (500 + self.rules.late_join_gold_per_minute.saturating_mul(minutes)).min(self.rules.max_gold);
+
+        let id = Uuid::new_v4();
+        let player = Player {
+            id,
+            name: name.clone(),
+            is_ai: false,
+            ai_personality: None,
+            color: PLAYER_COLORS[self.state.players.len() % PLAYER_COLORS.len()].to_string(),
+            population,
+            // Derived from the new territories below
+            max_population: 0,
+            gold,
+            gold_per_second: 0.0,
+            population_growth_per_second: 0.0,
+            food: 500,
+            food_per_second: 0.0,
+            techs: Vec::new(),
+            research: None,
+            troop_ratio: 0.5,
+            trained_ratio: 0.5,
+            attack_ratio: 0.2,
+            unit_mix: UnitMix::default(),
+            presets: Vec::new(),
+            territories_controlled: cluster.len() as u32,
+            is_alive: true,
+            latency_ms: None,
+        };
+
+        // Garrison the whole army across the new territories
+        let troops = player.troops() as u32;
+        let mix = player.unit_mix;
+        let share = troops / cluster.len() as u32;
+        for (i, &idx) in cluster.iter().enumerate() {
+            let territory = &mut self.state.territories[idx];
+            territory.owner = Some(id);
+            if i == 0 {
+                territory.capital_of = Some(id);
+            }
+            territory.units = Units::default();
+            territory.units.add_mixed(share + if i == 0 { troops % cluster.len() as u32 } else { 0 }, &mix);
+        }
+
+        self.player_map.insert(id.into(), self.state.players.len());
+        self.state.players.push(player);
+        self.update_population_caps();
+        self.update_income_projections();
+        self.joined_at.insert(id, self.state.tick);
+        self.events.push(
+            ServerMessage::notification(NotificationKey::PlayerJoined, NotificationLevel::Info, [("player", name)])
+                .about([EntityRef::Player { id }]),
+        );
+
+        Ok(id.into())
+    }
+
+    /// Indices of a neutral territory with the most neutral neighbors (furthest
+    /// from any owned territory on ties) plus up to `size - 1` of those neighbors
+    fn find_spawn_cluster(&self, size: usize) -> Result<Vec<usize>> {
+        let territories = &self.state.territories;
+        let owned: Vec<(f32, f32)> = territories.iter().filter(|t| t.owner.is_some()).map(|t| t.position).collect();
+        let neutral_neighbors = |idx: usize| -> Vec<usize> {
+            territories[idx].neighbors
+                .iter()
+                .filter_map(|id| self.territory_map.get(&(*id).into()).copied())
+                .filter(|&n| territories[n].owner.is_none() && territories[n].terrain != TerrainType::Water)
+                .collect()
+        };
+        let distance_to_owned = |idx: usize| -> f32 {
+            let (x, y) = territories[idx].position;
+            owned.iter().map(|(ox, oy)| (x - ox).hypot(y - oy)).fold(f32::INFINITY, f32::min)
+        };
+
+        let seed = (0..territories.len())
+            .filter(|&idx| territories[idx].owner.is_none() && territories[idx].terrain != TerrainType::Water)
+            .max_by(|&a, &b| {
+                let by_room = neutral_neighbors(a).len().min(size).cmp(&neutral_neighbors(b).len().min(size));
+                by_room.then(distance_to_owned(a).total_cmp(&distance_to_owned(b)))
+            })
+            .ok_or_else(|| anyhow!("No neutral territory left to spawn on"))?;
+
+        // Weakest neighbors first so the newcomer's land is easy to hold
+        let mut neighbors = neutral_neighbors(seed);
+        neighbors.sort_by_key(|&idx| territories[idx].troops());
+
+        let mut cluster = vec![seed];
+        cluster.extend(neighbors.into_iter().take(size - 1));
+        Ok(cluster)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameRules, MapGenerator};
+
+    fn engine(allow_late_join: bool) -> GameEngine {
+        let rules = GameRules { allow_late_join, ..GameRules::default() };
+        GameEngine::with_rules(MapGenerator::new(30, 3).generate(), 100, rules)
+    }
+
+    #[test]
+    fn test_late_join_requires_rule() {
+        let mut engine = engine(false);
+        assert!(engine.spawn_player("Late".to_string()).is_err());
+        assert_eq!(engine.state.players.len(), 3);
+    }
+
+    #[test]
+    fn test_late_joiner_gets_neutral_cluster_and_scaled_resources() {
+        let mut engine = engine(true);
+        engine.state.game_time_seconds = 10 * 60 + 30;
+        let neutral_before: Vec<Uuid> = engine.state.territories.iter().filter(|t| t.owner.is_none()).map(|t| t.id).collect();
+
+        let id = engine.spawn_player("Late".to_string()).unwrap();
+
+        let player = engine.get_player(id).unwrap();
+        assert_eq!(player.gold, 500 + 150 * 10);
+        assert_eq!(player.population, 1000 + 250 * 10);
+        let held: Vec<&Territory> = engine.state.territories.iter().filter(|t| t.owner == Some(id.into())).collect();
+        assert!(!held.is_empty() && held.len() <= 3);
+        assert!(held.iter().all(|t| neutral_before.contains(&t.id)));
+        assert_eq!(held.iter().map(|t| t.troops() as u64).sum::<u64>(), player.troops());
+        assert!(matches!(engine.take_events()[..], [ServerMessage::Notification { .. }]));
+    }
+}
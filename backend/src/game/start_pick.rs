@@ -0,0 +1,147 @@
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+use crate::types::*;
+use super::fairness::terrain_value;
+use super::GameEngine;
+
+/// Troops a player starts with on their picked territory
+pub const START_TROOPS: u32 = 500;
+/// Garrison left on a start that is handed back before picking
+const RELEASED_START_TROOPS: u32 = 100;
+/// Minimum hops between two starts, while the map has room for it
+const MIN_START_HOPS: u32 = 3;
+
+impl GameEngine {
+    /// Hand every generated start back to neutral so players can pick their own
+    pub fn release_starts(&mut self) {
+        for territory in self.state.territories.iter_mut().filter(|t| t.owner.is_some()) {
+            territory.owner = None;
+            territory.capital_of = None;
+            territory.units = Units::infantry(RELEASED_START_TROOPS);
+        }
+        for player in &mut self.state.players {
+            player.territories_controlled = 0;
+        }
+    }
+
+    /// Territories a player may start on: neutral land away from every other
+    /// start, or any neutral territory once the map is too crowded for that
+    pub fn start_candidates(&self) -> Vec<Uuid> {
+        let hops = self.hops_to_owned(MIN_START_HOPS);
+        let neutral = || self.state.territories.iter().filter(|t| t.owner.is_none());
+        let spaced: Vec<Uuid> = neutral()
+            .filter(|t| t.terrain != TerrainType::Water && !hops.contains_key(&t.id))
+            .map(|t| t.id)
+            .collect();
+        if spaced.is_empty() {
+            neutral().map(|t| t.id).collect()
+        } else {
+            spaced
+        }
+    }
+
+    /// Place a player's start on one of the candidates
+    pub fn claim_start(&mut self, player_id: PlayerId, territory_id: TerritoryId) -> Result<()> {
+        if self.state.territories.iter().any(|t| t.owner == Some(player_id.into())) {
+            return Err(anyhow!("You already picked a start"));
+        }
+        if !self.start_candidates().contains(&territory_id.into()) {
+            return Err(anyhow!("You can't start there"));
+        }
+
+        let territory = self.get_territory_mut(territory_id)?;
+        territory.owner = Some(player_id.into());
+        territory.capital_of = Some(player_id.into());
+        territory.units = Units::infantry(START_TROOPS);
+        self.get_player_mut(player_id)?.territories_controlled = 1;
+        Ok(())
+    }
+
+    /// The candidate an AI would pick: rich surroundings, far from rivals
+    pub fn suggest_start(&self) -> Option<Uuid> {
+        let hops = self.hops_to_owned(8);
+        let score = |id: &Uuid| {
+            let territory = self.get_territory((*id).into()).ok()?;
+            let surroundings: f32 = territory.neighbors
+                .iter()
+                .filter_map(|n| self.get_territory((*n).into()).ok())
+                .map(|n| terrain_value(n.terrain))
+                .sum::<f32>()
+                + terrain_value(territory.terrain);
+            let distance = hops.get(id).copied().unwrap_or(8) as f32;
+            Some(surroundings + distance * 0.25)
+        };
+        self.start_candidates()
+            .into_iter()
+            .filter_map(|id| score(&id).map(|s| (id, s)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(id, _)| id)
+    }
+
+    /// Hops from the nearest owned territory, for territories closer than `limit`
+    fn hops_to_owned(&self, limit: u32) -> HashMap<Uuid, u32> {
+        let mut hops: HashMap<Uuid, u32> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for territory in self.state.territories.iter().filter(|t| t.owner.is_some()) {
+            hops.insert(territory.id, 0);
+            queue.push_back(territory.id);
+        }
+        while let Some(id) = queue.pop_front() {
+            let distance = hops[&id] + 1;
+            if distance >= limit {
+                continue;
+            }
+            let Ok(territory) = self.get_territory(id.into()) else { continue };
+            for neighbor in &territory.neighbors {
+                if !hops.contains_key(neighbor) {
+                    hops.insert(*neighbor, distance);
+                    queue.push_back(*neighbor);
+                }
+            }
+        }
+        hops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_picked_starts_are_spaced_apart() {
+        let mut engine = GameEngine::new(MapGenerator::new(75, 4).generate(), 100);
+        engine.release_starts();
+        assert!(engine.state.territories.iter().all(|t| t.owner.is_none()));
+
+        let players: Vec<PlayerId> = engine.state.players.iter().map(|p| p.id.into()).collect();
+        for &player in &players {
+            let pick = engine.suggest_start().unwrap();
+            engine.claim_start(player, pick.into()).unwrap();
+        }
+
+        let starts: Vec<&Territory> = engine.state.territories.iter().filter(|t| t.owner.is_some()).collect();
+        assert_eq!(starts.len(), 4);
+        for start in &starts {
+            assert_eq!(start.troops(), START_TROOPS);
+            assert!(start.neighbors.iter().all(|n| !starts.iter().any(|s| s.id == *n)));
+        }
+        assert!(engine.state.players.iter().all(|p| p.territories_controlled == 1));
+    }
+
+    #[test]
+    fn test_claims_are_validated() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 2).generate(), 100);
+        engine.release_starts();
+        let (first, second) = (engine.state.players[0].id.into(), engine.state.players[1].id.into());
+        let pick = engine.suggest_start().unwrap();
+        engine.claim_start(first, pick.into()).unwrap();
+
+        assert!(engine.claim_start(first, engine.suggest_start().unwrap().into()).is_err());
+        assert!(engine.claim_start(second, pick.into()).is_err());
+        let neighbor = engine.get_territory(pick.into()).unwrap().neighbors[0];
+        assert!(engine.claim_start(second, neighbor.into()).is_err());
+    }
+}
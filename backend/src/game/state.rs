@@ -1,17 +1,87 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
 use anyhow::{anyhow, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use uuid::Uuid;
 
 use crate::types::*;
+use super::ai::{AiDecisionLog, ScriptedAi};
+use super::orders::QueuedOrder;
+use super::pathfinding::PathCache;
+use super::plugins::{BuildIntent, HookOutcome, PluginAction, PluginHost};
+use super::replay::Replay;
+use super::{CapitalLoss, GameRules};
 
 pub struct GameEngine {
     pub state: GameState,
-    territory_map: HashMap<TerritoryId, usize>,
-    player_map: HashMap<PlayerId, usize>,
+    pub rules: GameRules,
+    pub(super) territory_map: HashMap<TerritoryId, usize>,
+    pub(super) player_map: HashMap<PlayerId, usize>,
     pub tick_rate_ms: u64,
+    /// Timings of engine sections, filled in by the game loop and combat
+    pub perf: PerfStats,
+    /// Custom rule plugins consulted by the engine hooks
+    pub plugins: PluginHost,
+    /// Events raised inside the engine, waiting to be broadcast
+    pub(super) events: Vec<ServerMessage>,
+    /// Tick each player joined at, for elimination grace
+    pub(super) joined_at: HashMap<Uuid, u64>,
+    /// Tick each player lost their last territory at
+    pub(super) landless_since: HashMap<Uuid, u64>,
+    /// Who last took a territory from each player
+    pub(super) last_conquered_by: HashMap<Uuid, Uuid>,
+    /// Unrounded game time behind `state.game_time_seconds`
+    pub(super) elapsed_seconds: f64,
+    /// Attacks resolved so far
+    pub(super) battles: u32,
+    pub(super) biggest_battle: Option<BattleHighlight>,
+    /// Battles and changes of hands of every territory that has seen any
+    pub(super) territory_stats: HashMap<Uuid, TerritoryStats>,
+    /// Highlights for the end-of-game summary
+    pub(super) timeline: Vec<TimelineEntry>,
+    /// Tick each player last gave away a territory at
+    pub(super) last_gift: HashMap<Uuid, u64>,
+    /// Routes found by `path_between`
+    pub(super) path_cache: Mutex<PathCache>,
+    /// Bumped by `graph_changed` whenever neighbor lists or terrain change
+    pub(super) graph_version: u64,
+    /// AI decisions with the options they weighed; only kept if set
+    pub ai_log: Option<AiDecisionLog>,
+    /// AI players following a scenario script instead of their personality
+    pub(super) ai_scripts: HashMap<Uuid, ScriptedAi>,
+    /// Commands and battles of the match; only kept if set
+    pub replay: Option<Replay>,
+    /// Pause or resume asked for since the last tick, with who asked
+    pub(super) pending_pause: Option<(String, bool)>,
+    /// Game speed asked for since the last tick, with who asked
+    pub(super) pending_speed: Option<(String, f32)>,
+    /// Pauses each player has called
+    pub(super) pauses_used: HashMap<Uuid, u32>,
+    /// Orders each player has queued for later ticks, due first at the front
+    pub(super) orders: HashMap<Uuid, VecDeque<QueuedOrder>>,
+    /// Id of the last order queued
+    pub(super) next_order_id: u64,
+    /// Id the next multi-tick battle gets
+    pub(super) next_battle_id: u64,
+    /// Source of every random choice the engine and AI make
+    pub(super) rng: ChaCha12Rng,
 }
 
+/// Timeline entries kept for the summary; the oldest go first
+const MAX_TIMELINE_ENTRIES: usize = 100;
+/// Ratio presets a player can save
+const MAX_PRESETS: usize = 10;
+/// Characters allowed in a preset's name
+const MAX_PRESET_NAME: usize = 24;
+
 impl GameEngine {
     pub fn new(state: GameState, tick_rate_ms: u64) -> Self {
+        Self::with_rules(state, tick_rate_ms, GameRules::default())
+    }
+
+    pub fn with_rules(state: GameState, tick_rate_ms: u64, rules: GameRules) -> Self {
         let territory_map = state
             .territories
             .iter()
@@ -26,68 +96,271 @@ impl GameEngine {
             .map(|(idx, p)| (p.id.into(), idx))
             .collect();
 
-        Self {
+        let joined_at = state.players.iter().map(|p| (p.id, state.tick)).collect();
+        let elapsed_seconds = state.game_time_seconds as f64;
+        let next_battle_id = state.battles.iter().map(|b| b.id + 1).max().unwrap_or(0);
+
+        let mut engine = Self {
             state,
+            rules,
             territory_map,
             player_map,
             tick_rate_ms,
-        }
+            perf: PerfStats::default(),
+            plugins: PluginHost::default(),
+            events: Vec::new(),
+            joined_at,
+            landless_since: HashMap::new(),
+            last_conquered_by: HashMap::new(),
+            elapsed_seconds,
+            battles: 0,
+            biggest_battle: None,
+            territory_stats: HashMap::new(),
+            timeline: Vec::new(),
+            last_gift: HashMap::new(),
+            path_cache: Mutex::new(PathCache::default()),
+            graph_version: 0,
+            ai_log: None,
+            ai_scripts: HashMap::new(),
+            replay: None,
+            pending_pause: None,
+            pending_speed: None,
+            pauses_used: HashMap::new(),
+            orders: HashMap::new(),
+            next_order_id: 0,
+            next_battle_id,
+            rng: ChaCha12Rng::seed_from_u64(rand::random()),
+        };
+        engine.update_population_caps();
+        engine.update_income_projections();
+        engine.update_victory_progress();
+        engine
+    }
+
+    /// Restart the engine's random numbers from `seed`. From then on the same
+    /// state, commands and seed always play out the same way.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = ChaCha12Rng::seed_from_u64(seed);
+    }
+
+    /// A generator of its own for a caller that needs the engine too
+    pub(super) fn fork_rng(&mut self) -> StdRng {
+        StdRng::seed_from_u64(self.rng.gen())
     }
 
     /// Update game state by one tick
     pub fn tick(&mut self) {
+        self.apply_pacing();
         if self.state.is_paused {
             return;
         }
 
+        // The last tick is over, AI moves included
+        self.record_replay_keyframe();
+
         self.state.tick += 1;
 
         // Update game time based on speed
-        let time_increment = (self.tick_rate_ms as f32 * self.state.game_speed) / 1000.0;
-        self.state.game_time_seconds = (self.state.game_time_seconds as f32 + time_increment) as u32;
+        self.elapsed_seconds += self.tick_rate_ms as f64 * self.state.game_speed as f64 / 1000.0;
+        self.state.game_time_seconds = self.elapsed_seconds as u32;
+
+        // Orders queued for this tick go first
+        self.execute_orders();
+
+        // Battles under way fight another round
+        self.update_battles();
+
+        // Population caps follow what each player holds right now
+        self.update_population_caps();
 
         // Update resources for all players
+        self.update_income_projections();
         self.update_resources();
 
+        // Feed every population; the unfed starve
+        self.update_food();
+
+        // Pay for research under way
+        self.update_research();
+
+        // Move trained troops toward each player's target ratio
+        self.update_training();
+
+        // Neutral garrisons fortify
+        self.tick_neutrals();
+
+        // Truces run down
+        self.tick_truces();
+
         // Update territory control counts
         self.update_territory_counts();
-    }
 
-    /// Update population growth and gold generation
-    fn update_resources(&mut self) {
-        let tick_rate_sec = self.tick_rate_ms as f32 / 1000.0;
+        // Let rule plugins react to the new state
+        if !self.plugins.is_empty() {
+            let actions = self.plugins.on_tick(&self.state);
+            self.apply_plugin_actions(actions);
+        }
+
+        self.update_victory_progress();
+    }
 
-        // Collect player IDs first to avoid borrow issues
-        let player_ids: Vec<PlayerId> = self.state.players
+    /// Work out how close every living player is to winning: by outlasting
+    /// everyone else, and by any condition a rule plugin adds
+    pub(super) fn update_victory_progress(&mut self) {
+        let target = self.state.players.len().saturating_sub(1) as u32;
+        let eliminated = self.state.players.iter().filter(|p| !p.is_alive).count() as u32;
+        let mut victory: Vec<VictoryProgress> = self.state.players
             .iter()
             .filter(|p| p.is_alive)
-            .map(|p| p.id.into())
+            .map(|p| VictoryProgress {
+                condition: "last_standing".to_string(),
+                player_id: p.id,
+                current: eliminated,
+                target,
+            })
             .collect();
+        if !self.plugins.is_empty() {
+            victory.extend(self.plugins.victory_progress(&self.state));
+        }
+        self.state.victory = victory;
+    }
 
-        for player_id in player_ids {
-            let player = match self.get_player(player_id) {
-                Ok(p) => p,
-                Err(_) => continue,
-            };
+    fn apply_plugin_actions(&mut self, actions: Vec<PluginAction>) {
+        let max_gold = self.rules.max_gold;
+        let max_population = self.rules.max_population;
 
-            let territories_controlled = player.territories_controlled;
-            let workers = player.workers();
+        for action in actions {
+            match action {
+                PluginAction::AdjustGold { player, delta } => {
+                    if let Ok(player) = self.get_player_mut(player.into()) {
+                        player.gold = player.gold.saturating_add_signed(delta).min(max_gold);
+                    }
+                }
+                PluginAction::AdjustPopulation { player, delta } => {
+                    if let Ok(player) = self.get_player_mut(player.into()) {
+                        player.population = player.population.saturating_add_signed(delta).min(max_population);
+                    }
+                }
+                PluginAction::Announce { message } => {
+                    self.events.push(ServerMessage::notification(
+                        NotificationKey::Announcement,
+                        NotificationLevel::Info,
+                        [("text", message)],
+                    ));
+                }
+                PluginAction::ScriptAi { player, script } => self.script_ai(player, script),
+            }
+        }
+    }
+
+    /// Add an event to the game summary's timeline if it is one of the
+    /// highlights; events raised by the engine itself are noted as they are taken
+    pub fn note_event(&mut self, event: &ServerMessage) {
+        let (text, category, entities) = match event {
+            ServerMessage::Notification { message, key, category, entities, .. } if key.is_highlight() => {
+                (message.clone(), *category, entities.clone())
+            }
+            ServerMessage::ResearchCompleted { player_id, tech } => {
+                let name = self.get_player((*player_id).into()).map(|p| p.name.clone()).unwrap_or_default();
+                (format!("{} researched {:?}", name, tech), EventCategory::Economy, vec![EntityRef::Player { id: *player_id }])
+            }
+            _ => return,
+        };
+        if self.timeline.len() >= MAX_TIMELINE_ENTRIES {
+            self.timeline.remove(0);
+        }
+        self.timeline.push(TimelineEntry { game_time_seconds: self.state.game_time_seconds, text, category, entities });
+    }
+
+    /// Drain events raised since the last call
+    pub fn take_events(&mut self) -> Vec<ServerMessage> {
+        let events = std::mem::take(&mut self.events);
+        for event in &events {
+            self.note_event(event);
+        }
+        events
+    }
 
-            // Population growth: 10/sec per territory + terrain bonuses
-            let base_growth = 10.0 * territories_controlled as f32;
-            let terrain_bonus = self.calculate_population_growth_bonus(player_id);
-            let population_growth = (base_growth * terrain_bonus * tick_rate_sec * self.state.game_speed) as u32;
+    /// Work out every player's gold income and population growth per second
+    /// at the current game speed, with all bonuses applied
+    pub(super) fn update_income_projections(&mut self) {
+        let mut projections = Vec::with_capacity(self.state.players.len());
+        for player in &self.state.players {
+            if !player.is_alive {
+                projections.push((0.0, 0.0, 0.0));
+                continue;
+            }
+            let player_id: PlayerId = player.id.into();
+
+            // Food: the harvest less what every person eats
+            let food = self.food_production(player_id) - player.population as f32 * self.rules.food_per_person;
+
+            // Population growth: 10/sec per territory + terrain bonuses, none at the cap or without a surplus
+            let population_growth = if player.population < player.max_population && food > 0.0 {
+                10.0 * player.territories_controlled as f32
+                    * self.calculate_population_growth_bonus(player_id)
+                    * (1.0 + self.tech_bonus(player_id, Tech::growth_bonus))
+            } else {
+                0.0
+            };
 
             // Gold generation: 1 gold per 10 workers per second + terrain/building bonuses
-            let base_gold = workers as f32 / 10.0;
-            let gold_bonus = self.calculate_gold_generation_bonus(player_id);
-            let gold_generation = (base_gold * gold_bonus * tick_rate_sec * self.state.game_speed) as u32;
-
-            // Apply updates
-            if let Ok(player) = self.get_player_mut(player_id) {
-                player.population = (player.population + population_growth).min(player.max_population);
-                player.gold += gold_generation;
+            let mut gold = player.workers() as f32 / 10.0 * self.calculate_gold_generation_bonus(player_id);
+            gold += self.rules.gems_gold_per_second * self.deposits_held(player_id, ResourceType::Gems) as f32;
+            if self.rules.capital_loss == CapitalLoss::HalveIncome && self.capital_lost(player_id) {
+                gold *= 0.5;
             }
+
+            let speed = self.state.game_speed;
+            projections.push((gold * speed, population_growth * speed, food * speed));
+        }
+
+        for (player, (gold, growth, food)) in self.state.players.iter_mut().zip(projections) {
+            player.gold_per_second = gold;
+            player.population_growth_per_second = growth;
+            player.food_per_second = food;
+        }
+    }
+
+    /// Update population growth and gold generation
+    fn update_resources(&mut self) {
+        let tick_rate_sec = self.tick_rate_ms as f32 / 1000.0;
+        let max_gold = self.rules.max_gold;
+        let emigration = self.rules.over_cap_emigration;
+        let seconds = tick_rate_sec * self.state.game_speed;
+
+        for player in self.state.players.iter_mut().filter(|p| p.is_alive) {
+            // Float-to-int casts saturate, so extreme speeds can't wrap
+            let population_growth = (player.population_growth_per_second * tick_rate_sec) as u64;
+            let gold_generation = (player.gold_per_second * tick_rate_sec) as u64;
+
+            let cap = player.max_population;
+            // Never grow past the cap; workers above it, e.g. after losing a City, drift away
+            if player.population < cap {
+                player.population = player.population.saturating_add(population_growth).min(cap);
+            } else {
+                let excess = player.population - cap;
+                let leaving = (excess as f32 * emigration * seconds).ceil() as u64;
+                player.lose_workers(leaving.min(excess));
+            }
+            player.gold = player.gold.saturating_add(gold_generation).min(max_gold);
+        }
+    }
+
+    /// Derive every player's population cap from their territories and cities
+    pub(super) fn update_population_caps(&mut self) {
+        let mut caps: HashMap<Uuid, u64> = HashMap::new();
+        for territory in &self.state.territories {
+            if let Some(owner) = territory.owner {
+                let bonus = territory.working_building(self.state.tick).map_or(0, |building| building.max_population_bonus(territory.building_level));
+                let cap = caps.entry(owner).or_default();
+                *cap = cap.saturating_add(self.rules.population_cap_per_territory).saturating_add(bonus);
+            }
+        }
+
+        for player in &mut self.state.players {
+            let held = caps.get(&player.id).copied().unwrap_or(0);
+            player.max_population = self.rules.base_population_cap.saturating_add(held).min(self.rules.max_population);
         }
     }
 
@@ -116,8 +389,15 @@ impl GameEngine {
         for territory in &self.state.territories {
             if territory.owner == Some(player_id.into()) {
                 let mut multiplier = territory.terrain.gold_multiplier();
-                if let Some(building) = territory.building {
-                    multiplier *= building.gold_multiplier();
+                match territory.working_building(self.state.tick) {
+                    Some(BuildingType::GoldMine) => {
+                        multiplier *= 1.0 + (self.rules.gold_mine_multiplier - 1.0) * BuildingType::level_scale(territory.building_level)
+                    }
+                    Some(building) => multiplier *= building.gold_multiplier(territory.building_level),
+                    None => {}
+                }
+                if territory.capital_of == territory.owner {
+                    multiplier *= self.rules.capital_gold_multiplier;
                 }
                 total_multiplier += multiplier;
                 territory_count += 1;
@@ -131,6 +411,41 @@ impl GameEngine {
         }
     }
 
+    fn update_training(&mut self) {
+        let seconds = self.tick_rate_ms as f64 / 1000.0 * self.state.game_speed as f64;
+        let base_rate = self.rules.troop_training_per_second as f64;
+        let barracks_bonus = self.rules.barracks_training_bonus as f64;
+
+        let horses_bonus = self.rules.horses_training_bonus as f64;
+
+        let mut barracks: HashMap<Uuid, f32> = HashMap::new();
+        let mut horses: HashMap<Uuid, u32> = HashMap::new();
+        for territory in &self.state.territories {
+            if let (Some(owner), Some(BuildingType::Barracks)) = (territory.owner, territory.working_building(self.state.tick)) {
+                *barracks.entry(owner).or_default() += BuildingType::level_scale(territory.building_level);
+            }
+            if let (Some(owner), Some(ResourceType::Horses)) = (territory.owner, territory.resource) {
+                *horses.entry(owner).or_default() += 1;
+            }
+        }
+
+        for player in self.state.players.iter_mut().filter(|p| p.is_alive) {
+            let target = player.troop_ratio.clamp(0.0, 1.0);
+            if player.population == 0 {
+                player.trained_ratio = target;
+                continue;
+            }
+
+            let rate = base_rate
+                * (1.0
+                    + barracks_bonus * barracks.get(&player.id).copied().unwrap_or(0.0) as f64
+                    + horses_bonus * horses.get(&player.id).copied().unwrap_or(0) as f64);
+            let max_step = (rate * seconds / player.population as f64) as f32;
+            let gap = target - player.trained_ratio;
+            player.trained_ratio = (player.trained_ratio + gap.clamp(-max_step, max_step)).clamp(0.0, 1.0);
+        }
+    }
+
     fn update_territory_counts(&mut self) {
         // Reset all counts
         for player in &mut self.state.players {
@@ -146,12 +461,117 @@ impl GameEngine {
             }
         }
 
-        // Check for eliminated players
-        for player in &mut self.state.players {
-            if player.territories_controlled == 0 && player.is_alive {
-                player.is_alive = false;
+        self.check_eliminations();
+    }
+
+    /// Eliminate players who have been without territory for the grace period.
+    /// The grace absorbs same-tick races (a last territory falling while the
+    /// player's own attack lands) and protects players who just joined.
+    fn check_eliminations(&mut self) {
+        let tick = self.state.tick;
+        let grace = self.rules.elimination_grace_ticks;
+        let mut eliminated = Vec::new();
+
+        for player in self.state.players.iter().filter(|p| p.is_alive) {
+            if player.territories_controlled > 0 {
+                self.landless_since.remove(&player.id);
+                continue;
             }
+
+            let landless_since = *self.landless_since.entry(player.id).or_insert(tick);
+            let joined_at = self.joined_at.get(&player.id).copied().unwrap_or(0);
+            if tick >= landless_since.saturating_add(grace) && tick >= joined_at.saturating_add(grace) {
+                eliminated.push(player.id);
+            }
+        }
+
+        for player_id in eliminated {
+            let eliminated_by = self.last_conquered_by.get(&player_id).copied();
+            self.eliminate_player(player_id.into(), eliminated_by);
+        }
+    }
+
+    /// Remove a player from the game and announce it
+    pub fn eliminate_player(&mut self, player_id: PlayerId, eliminated_by: Option<Uuid>) {
+        let Ok(player) = self.get_player_mut(player_id) else { return };
+        if !player.is_alive {
+            return;
         }
+        player.is_alive = false;
+        let name = player.name.clone();
+
+        // Anything still held reverts to neutral
+        for territory in &mut self.state.territories {
+            if territory.owner == Some(player_id.into()) {
+                territory.owner = None;
+            }
+        }
+        if let Ok(player) = self.get_player_mut(player_id) {
+            player.territories_controlled = 0;
+        }
+        self.landless_since.remove(&player_id.into());
+        self.forget_relations(player_id.into());
+
+        let mut entities = vec![EntityRef::Player { id: player_id.into() }];
+        entities.extend(eliminated_by.map(|id| EntityRef::Player { id }));
+        self.events.push(ServerMessage::PlayerEliminated {
+            player_id: player_id.into(),
+            eliminated_by: eliminated_by.unwrap_or(Uuid::nil()),
+        });
+        self.events.push(
+            ServerMessage::notification(NotificationKey::PlayerEliminated, NotificationLevel::Warning, [("player", name)])
+                .about(entities),
+        );
+    }
+
+    /// Hand every human slot nobody claimed over to an AI with a random
+    /// personality, so no faction sits idle for the whole game
+    pub fn backfill_ai(&mut self, claimed: &[PlayerId]) -> Vec<PlayerId> {
+        let idle: Vec<(PlayerId, String)> = self.state.players
+            .iter()
+            .filter(|p| !p.is_ai && p.is_alive && !claimed.contains(&p.id.into()))
+            .map(|p| (p.id.into(), p.name.clone()))
+            .collect();
+
+        let mut replaced = Vec::new();
+        for (player_id, name) in idle {
+            if let Ok(ai_name) = self.hand_to_ai(player_id) {
+                self.events.push(
+                    ServerMessage::notification(
+                        NotificationKey::SlotFilledByAi,
+                        NotificationLevel::Info,
+                        [("player", name), ("ai", ai_name)],
+                    )
+                    .about([EntityRef::Player { id: player_id.into() }]),
+                );
+                replaced.push(player_id);
+            }
+        }
+
+        replaced
+    }
+
+    /// Put an AI with a random personality in charge of a human's faction,
+    /// returning the name it plays under
+    pub fn hand_to_ai(&mut self, player_id: PlayerId) -> Result<String> {
+        let ai_name = format!("AI {}", self.state.players.iter().filter(|p| p.is_ai).count() + 1);
+        let personality = super::map_gen::random_personality(&mut self.rng);
+        let player = self.get_player_mut(player_id)?;
+        if player.is_ai {
+            return Err(anyhow!("{} is already played by the AI", player.name));
+        }
+
+        player.name = ai_name.clone();
+        player.is_ai = true;
+        player.ai_personality = Some(personality);
+        player.troop_ratio = super::map_gen::starting_troop_ratio(personality);
+        player.trained_ratio = player.troop_ratio;
+        Ok(ai_name)
+    }
+
+    /// Note who took a territory from whom, for elimination credit
+    pub(super) fn record_conquest(&mut self, victim: Uuid, conqueror: Uuid) {
+        self.last_conquered_by.insert(victim, conqueror);
     }
 
     /// Get territory by ID
@@ -168,6 +588,11 @@ impl GameEngine {
         Ok(&mut self.state.territories[*idx])
     }
 
+    /// The first human player, who may run the lobby and save or load the game
+    pub fn host(&self) -> Option<PlayerId> {
+        self.state.players.iter().find(|p| !p.is_ai).map(|p| p.id.into())
+    }
+
     /// Get player by ID
     pub fn get_player(&self, id: PlayerId) -> Result<&Player> {
         let idx = self.player_map.get(&id)
@@ -198,6 +623,61 @@ impl GameEngine {
         Ok(())
     }
 
+    /// Save a ratio preset for the player, replacing one of the same name
+    pub fn save_preset(&mut self, player_id: PlayerId, preset: RatioPreset) -> Result<()> {
+        let name = preset.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_PRESET_NAME {
+            return Err(anyhow!("Preset names must be 1 to {} characters", MAX_PRESET_NAME));
+        }
+        if !preset.troop_ratio.is_finite() || !preset.attack_ratio.is_finite() {
+            return Err(anyhow!("Preset ratios must be numbers"));
+        }
+        let preset = RatioPreset {
+            name: name.to_string(),
+            troop_ratio: preset.troop_ratio.clamp(0.0, 1.0),
+            attack_ratio: preset.attack_ratio.clamp(0.0, 1.0),
+        };
+        let player = self.get_player_mut(player_id)?;
+        if let Some(saved) = player.presets.iter_mut().find(|p| p.name == preset.name) {
+            *saved = preset;
+        } else if player.presets.len() >= MAX_PRESETS {
+            return Err(anyhow!("You can save at most {} presets", MAX_PRESETS));
+        } else {
+            player.presets.push(preset);
+        }
+        Ok(())
+    }
+
+    /// Set both of the player's ratios from a saved or built-in preset
+    pub fn apply_preset(&mut self, player_id: PlayerId, name: &str) -> Result<()> {
+        let player = self.get_player_mut(player_id)?;
+        let preset = player.presets
+            .iter()
+            .find(|p| p.name == name)
+            .cloned()
+            .or_else(|| RatioPreset::builtin().into_iter().find(|p| p.name == name))
+            .ok_or_else(|| anyhow!("No preset named {}", name))?;
+        player.troop_ratio = preset.troop_ratio;
+        player.attack_ratio = preset.attack_ratio;
+        Ok(())
+    }
+
+    /// Set how new troops are split between unit types; troops already
+    /// trained keep their type
+    pub fn set_unit_mix(&mut self, player_id: PlayerId, mix: UnitMix) -> Result<()> {
+        let shares = [mix.infantry, mix.cavalry, mix.siege];
+        if shares.iter().any(|s| !s.is_finite() || *s < 0.0) || shares.iter().sum::<f32>() <= 0.0 {
+            return Err(anyhow!("Unit shares must be positive numbers"));
+        }
+        let player = self.get_player_mut(player_id)?;
+        player.unit_mix = UnitMix {
+            infantry: mix.share(UnitType::Infantry),
+            cavalry: mix.share(UnitType::Cavalry),
+            siege: mix.share(UnitType::Siege),
+        };
+        Ok(())
+    }
+
     /// Build a structure in a territory
     pub fn build_structure(&mut self, player_id: PlayerId, territory_id: TerritoryId, building_type: BuildingType) -> Result<()> {
         // Validate ownership
@@ -210,43 +690,97 @@ impl GameEngine {
         if territory.building.is_some() {
             return Err(anyhow!("Territory already has a building"));
         }
+        if building_type == BuildingType::Harbor && !territory.coastal {
+            return Err(anyhow!("Harbors can only be built on the coast"));
+        }
+        if building_type == BuildingType::Bridge && !territory.borders.iter().any(|b| b.river) {
+            return Err(anyhow!("Bridges can only be built on a river"));
+        }
 
         // Check if player has enough gold
         let player = self.get_player(player_id)?;
-        let cost = building_type.cost();
+        let cost = self.building_cost(player_id, building_type);
         if player.gold < cost {
             return Err(anyhow!("Not enough gold"));
         }
 
+        let intent = BuildIntent {
+            player: player_id.into(),
+            territory: territory_id.into(),
+            building_type,
+        };
+        if let HookOutcome::Veto(reason) = self.plugins.on_build(&self.state, &intent) {
+            return Err(anyhow!(reason));
+        }
+
         // Deduct gold and build
         let player = self.get_player_mut(player_id)?;
         player.gold -= cost;
 
-        // Add building bonuses
-        if building_type == BuildingType::City {
-            player.max_population += building_type.max_population_bonus();
-        }
-
         let territory = self.get_territory_mut(territory_id)?;
         territory.building = Some(building_type);
+        territory.building_level = 1;
+        self.update_population_caps();
 
         Ok(())
     }
 
+    /// Raise the building in a territory a level, for its cost times the new level
+    pub fn upgrade_building(&mut self, player_id: PlayerId, territory_id: TerritoryId) -> Result<()> {
+        let territory = self.get_territory(territory_id)?;
+        if territory.owner != Some(player_id.into()) {
+            return Err(anyhow!("You don't own this territory"));
+        }
+        let Some(building_type) = territory.building else {
+            return Err(anyhow!("There is no building to upgrade"));
+        };
+        if !building_type.upgradeable() {
+            return Err(anyhow!("{} can't be upgraded", building_type.display_name()));
+        }
+        let level = territory.building_level.max(1) + 1;
+        if level > MAX_BUILDING_LEVEL {
+            return Err(anyhow!("{} is already at the highest level", building_type.display_name()));
+        }
+
+        let cost = self.building_cost(player_id, building_type) * level as u64;
+        if self.get_player(player_id)?.gold < cost {
+            return Err(anyhow!("Not enough gold"));
+        }
+
+        let intent = BuildIntent {
+            player: player_id.into(),
+            territory: territory_id.into(),
+            building_type,
+        };
+        if let HookOutcome::Veto(reason) = self.plugins.on_build(&self.state, &intent) {
+            return Err(anyhow!(reason));
+        }
+
+        self.get_player_mut(player_id)?.gold -= cost;
+
+        self.get_territory_mut(territory_id)?.building_level = level;
+        self.update_population_caps();
+        Ok(())
+    }
+
     /// Check if game is over
-    pub fn check_game_over(&self) -> Option<GameStats> {
+    pub fn check_game_over(&mut self) -> Option<GameStats> {
+        // Plugin victory conditions take precedence over last-player-standing
+        let plugin_winner = self.plugins.check_victory(&self.state)
+            .and_then(|id| self.get_player(id.into()).ok());
+
         let alive_players: Vec<_> = self.state.players.iter()
             .filter(|p| p.is_alive)
             .collect();
 
-        if alive_players.len() == 1 {
-            let winner = alive_players[0];
+        let winner = plugin_winner.or_else(|| (alive_players.len() == 1).then(|| alive_players[0]));
+        if let Some(winner) = winner {
             return Some(GameStats {
                 winner: winner.id,
                 game_duration_seconds: self.state.game_time_seconds,
                 territories_captured: winner.territories_controlled,
-                total_battles: 0, // TODO: track this
-                final_score: winner.territories_controlled * 100 + winner.gold / 10,
+                total_battles: self.battles,
+                final_score: (winner.territories_controlled as u64 * 100).saturating_add(winner.gold / 10),
             });
         }
 
@@ -263,3 +797,280 @@ impl GameEngine {
         self.state.game_speed = speed.clamp(0.5, 4.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_resources_saturate_at_caps_under_extreme_settings() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let rules = GameRules {
+            max_gold: 50_000,
+            max_population: 40_000,
+            base_population_cap: u64::MAX,
+            ..GameRules::default()
+        };
+        let mut engine = GameEngine::with_rules(MapGenerator::new(30, 4).generate(), 100, rules);
+
+        // Start everyone next to the caps with huge garrisons worth of territory
+        for player in &mut engine.state.players {
+            player.population = 39_999;
+            player.gold = 49_999;
+        }
+        for territory in &mut engine.state.territories {
+            territory.owner = Some(engine.state.players[0].id);
+        }
+
+        for _ in 0..5_000 {
+            let player_id: PlayerId = engine.state.players[0].id.into();
+            engine.set_troop_ratio(player_id, rng.gen_range(-1.0..2.0)).unwrap();
+            engine.set_game_speed(if rng.gen_bool(0.5) { f32::MAX } else { rng.gen_range(0.0..100.0) });
+            engine.tick();
+
+            for player in &engine.state.players {
+                assert!(player.gold <= 50_000);
+                assert!(player.population <= 40_000);
+                assert_eq!(player.troops() + player.workers(), player.population);
+            }
+        }
+    }
+
+    #[test]
+    fn test_troops_never_exceed_population_for_any_ratio() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut player = MapGenerator::new(10, 2).generate().players.remove(0);
+
+        for _ in 0..100_000 {
+            player.population = match rng.gen_range(0..3) {
+                0 => rng.gen_range(0..1_000),
+                1 => rng.gen_range(16_777_000..16_778_000), // f32 precision boundary
+                _ => rng.gen(),
+            };
+            player.trained_ratio = rng.gen_range(0.0..=1.0);
+
+            assert!(player.troops() <= player.population);
+            assert_eq!(player.troops() + player.workers(), player.population);
+        }
+    }
+
+    #[test]
+    fn test_troop_ratio_changes_train_over_time() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let player_id: PlayerId = engine.state.players[0].id.into();
+        engine.get_player_mut(player_id).unwrap().population = 1_000;
+        engine.set_troop_ratio(player_id, 1.0).unwrap();
+
+        // 50 troops/sec on 1000 population moves the ratio 0.005 per 100ms tick
+        engine.tick();
+        let trained = engine.get_player(player_id).unwrap().trained_ratio;
+        assert!(trained > 0.5 && trained < 0.51, "trained ratio {}", trained);
+
+        // A barracks speeds training up
+        let territory = engine.state.territories.iter().position(|t| t.owner == Some(player_id.into())).unwrap();
+        engine.state.territories[territory].building = Some(BuildingType::Barracks);
+        let before = engine.get_player(player_id).unwrap().trained_ratio;
+        engine.tick();
+        let step = engine.get_player(player_id).unwrap().trained_ratio - before;
+        assert!(step > 0.007, "step {}", step);
+
+        // Demobilizing is just as gradual
+        engine.set_troop_ratio(player_id, 0.0).unwrap();
+        for _ in 0..5 {
+            engine.tick();
+        }
+        assert!(engine.get_player(player_id).unwrap().trained_ratio > 0.4);
+    }
+
+    #[test]
+    fn test_presets_set_both_ratios_at_once() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let player_id: PlayerId = engine.state.players[0].id.into();
+        let ratios = |engine: &GameEngine| {
+            let player = engine.get_player(player_id).unwrap();
+            (player.troop_ratio, player.attack_ratio)
+        };
+
+        engine.apply_preset(player_id, "turtle").unwrap();
+        assert_eq!(ratios(&engine), (0.3, 0.1));
+        assert!(engine.apply_preset(player_id, "blitz").is_err());
+
+        // Saved presets are clamped and may shadow a built-in one
+        let preset = |name: &str, troop_ratio, attack_ratio| RatioPreset { name: name.to_string(), troop_ratio, attack_ratio };
+        engine.save_preset(player_id, preset(" blitz ", 0.8, 1.5)).unwrap();
+        engine.save_preset(player_id, preset("turtle", 0.2, 0.0)).unwrap();
+        engine.apply_preset(player_id, "blitz").unwrap();
+        assert_eq!(ratios(&engine), (0.8, 1.0));
+        engine.apply_preset(player_id, "turtle").unwrap();
+        assert_eq!(ratios(&engine), (0.2, 0.0));
+        assert!(engine.save_preset(player_id, preset("", 0.5, 0.5)).is_err());
+        assert!(engine.save_preset(player_id, preset("odd", f32::NAN, 0.5)).is_err());
+
+        // Saving under a taken name replaces the preset instead of adding one
+        engine.save_preset(player_id, preset("blitz", 0.9, 0.9)).unwrap();
+        assert_eq!(engine.get_player(player_id).unwrap().presets.len(), 2);
+        for n in 0..8 {
+            engine.save_preset(player_id, preset(&n.to_string(), 0.5, 0.5)).unwrap();
+        }
+        assert!(engine.save_preset(player_id, preset("one more", 0.5, 0.5)).is_err());
+    }
+
+    #[test]
+    fn test_elimination_waits_for_grace_and_credits_conqueror() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let (victim, conqueror) = (engine.state.players[1].id, engine.state.players[0].id);
+
+        // Past the join protection
+        for _ in 0..engine.rules.elimination_grace_ticks {
+            engine.tick();
+        }
+        engine.take_events();
+
+        for territory in &mut engine.state.territories {
+            if territory.owner == Some(victim) {
+                territory.owner = Some(conqueror);
+            }
+        }
+        engine.record_conquest(victim, conqueror);
+
+        // Losing the last territory starts the grace period rather than ending the game
+        engine.tick();
+        assert!(engine.state.players[1].is_alive);
+
+        for _ in 0..engine.rules.elimination_grace_ticks {
+            engine.tick();
+        }
+        assert!(!engine.state.players[1].is_alive);
+
+        let events = engine.take_events();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            ServerMessage::PlayerEliminated { player_id, eliminated_by } if *player_id == victim && *eliminated_by == conqueror
+        )));
+    }
+
+    #[test]
+    fn test_recovering_territory_resets_grace() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let victim = engine.state.players[1].id;
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(victim)).unwrap();
+        let grace = engine.rules.elimination_grace_ticks;
+
+        for _ in 0..grace {
+            engine.tick();
+        }
+
+        engine.state.territories[home].owner = None;
+        for _ in 0..grace - 1 {
+            engine.tick();
+        }
+        engine.state.territories[home].owner = Some(victim);
+        engine.tick();
+
+        engine.state.territories[home].owner = None;
+        for _ in 0..grace - 1 {
+            engine.tick();
+        }
+        assert!(engine.state.players[1].is_alive);
+    }
+
+    #[test]
+    fn test_unclaimed_human_slots_become_ai() {
+        let state = MapGenerator::new(20, 4).with_human_slots(3).generate();
+        let mut engine = GameEngine::new(state, 100);
+        let claimed: PlayerId = engine.state.players[0].id.into();
+
+        let replaced = engine.backfill_ai(&[claimed]);
+
+        assert_eq!(replaced.len(), 2);
+        assert!(!engine.state.players[0].is_ai);
+        let names: Vec<_> = engine.state.players.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Player", "AI 2", "AI 3", "AI 1"]);
+        for player in &engine.state.players[1..] {
+            assert!(player.is_ai);
+            assert!(player.ai_personality.is_some());
+        }
+        assert_eq!(engine.take_events().len(), 2);
+    }
+
+    #[test]
+    fn test_population_cap_follows_territory_and_cities() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let player = engine.state.players[0].id;
+        let (base, per_territory) = (engine.rules.base_population_cap, engine.rules.population_cap_per_territory);
+        let city_bonus = BuildingType::City.max_population_bonus(1);
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(player)).unwrap();
+        let neutral = engine.state.territories.iter().position(|t| t.owner.is_none()).unwrap();
+        assert_eq!(engine.state.players[0].max_population, base + per_territory);
+
+        engine.state.territories[neutral].owner = Some(player);
+        engine.state.players[0].gold = BuildingType::City.cost();
+        let neutral_id = engine.state.territories[neutral].id;
+        engine.build_structure(player.into(), neutral_id.into(), BuildingType::City).unwrap();
+        assert_eq!(engine.state.players[0].max_population, base + 2 * per_territory + city_bonus);
+
+        // Losing the city takes its bonus with it
+        engine.state.territories[neutral].owner = None;
+        engine.tick();
+        assert_eq!(engine.state.players[0].max_population, base + per_territory);
+        engine.state.territories[home].owner = None;
+        engine.tick();
+        assert_eq!(engine.state.players[0].max_population, base);
+    }
+
+    #[test]
+    fn test_upgrades_raise_a_buildings_bonus_for_escalating_costs() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let player = engine.state.players[0].id;
+        let home = engine.state.territories.iter().find(|t| t.owner == Some(player)).unwrap().id;
+        let (player, home): (PlayerId, TerritoryId) = (player.into(), home.into());
+        let cap = |engine: &GameEngine| engine.get_player(player).unwrap().max_population;
+        assert!(engine.upgrade_building(player, home).is_err());
+
+        engine.state.players[0].gold = 1_000 + 2_000 + 3_000;
+        engine.build_structure(player, home, BuildingType::City).unwrap();
+        let city_one = cap(&engine);
+        engine.upgrade_building(player, home).unwrap();
+        assert_eq!(engine.get_territory(home).unwrap().building_level, 2);
+        assert_eq!(engine.get_player(player).unwrap().gold, 3_000);
+        assert_eq!(cap(&engine), city_one + BuildingType::City.max_population_bonus(2) - BuildingType::City.max_population_bonus(1));
+
+        // Level III costs three times the City, and is as high as it goes
+        engine.upgrade_building(player, home).unwrap();
+        assert_eq!(engine.get_player(player).unwrap().gold, 0);
+        engine.state.players[0].gold = 10_000;
+        assert!(engine.upgrade_building(player, home).is_err());
+        assert_eq!(engine.get_player(player).unwrap().gold, 10_000);
+
+        // Defense Posts hold better with every level, Harbors have nothing to upgrade
+        assert!(BuildingType::DefensePost.defense_multiplier(3) < BuildingType::DefensePost.defense_multiplier(1));
+        engine.get_territory_mut(home).unwrap().building = Some(BuildingType::Harbor);
+        engine.get_territory_mut(home).unwrap().building_level = 1;
+        assert!(engine.upgrade_building(player, home).is_err());
+    }
+
+    #[test]
+    fn test_income_projections_match_what_a_tick_pays() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 1_000);
+        engine.set_game_speed(2.0);
+        let home = engine.state.territories.iter().position(|t| t.owner == Some(engine.state.players[0].id)).unwrap();
+        engine.state.territories[home].building = Some(BuildingType::GoldMine);
+        engine.update_income_projections();
+
+        let before = engine.state.players[0].clone();
+        assert!(before.gold_per_second > before.workers() as f32 / 10.0 * 2.0);
+        engine.tick();
+        let after = &engine.state.players[0];
+        assert_eq!(after.gold - before.gold, after.gold_per_second as u64);
+        assert_eq!(after.population - before.population, after.population_growth_per_second as u64);
+        assert_eq!(after.population_growth_per_second, before.population_growth_per_second);
+
+        // Nothing grows at the cap
+        engine.state.players[0].population = engine.state.players[0].max_population;
+        engine.tick();
+        assert_eq!(engine.state.players[0].population_growth_per_second, 0.0);
+    }
+}
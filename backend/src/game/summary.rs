@@ -0,0 +1,154 @@
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::types::*;
+use super::GameEngine;
+
+/// Discord rejects messages longer than this
+const MAX_SUMMARY_TEXT_CHARS: usize = 2000;
+
+impl GameEngine {
+    /// Shareable report of a finished game, built from its stats and the
+    /// highlights of its event log
+    pub fn summary(&self, game_id: Uuid, stats: &GameStats) -> GameSummary {
+        let winner_name = self.get_player(stats.winner.into()).map(|p| p.name.clone()).unwrap_or_default();
+        let biggest_battle = self.biggest_battle.clone();
+        let most_contested = self.most_contested();
+        let text = summary_text(&winner_name, stats, biggest_battle.as_ref(), &most_contested, &self.timeline);
+
+        GameSummary {
+            game_id,
+            stats: stats.clone(),
+            winner_name,
+            biggest_battle,
+            most_contested,
+            timeline: self.timeline.clone(),
+            text,
+            draft: None,
+        }
+    }
+}
+
+fn clock(seconds: u32) -> String {
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Markdown summary; the oldest timeline entries are left out if it gets too long
+fn summary_text(
+    winner: &str,
+    stats: &GameStats,
+    battle: Option<&BattleHighlight>,
+    contested: &[TerritoryStats],
+    timeline: &[TimelineEntry],
+) -> String {
+    let mut header = format!("**🏆 {} wins!**\n", winner);
+    let _ = writeln!(
+        header,
+        "Duration {} · {} battles · {} territories · score {}",
+        clock(stats.game_duration_seconds),
+        stats.total_battles,
+        stats.territories_captured,
+        stats.final_score
+    );
+    if let Some(battle) = battle {
+        let defender = battle.defender_name.as_deref().unwrap_or("neutral forces");
+        let outcome = if battle.territory_conquered { "territory taken" } else { "attack repelled" };
+        let _ = writeln!(
+            header,
+            "Biggest battle: {} vs {} for {} at {}, {} troops ({})",
+            battle.attacker_name,
+            defender,
+            battle.territory_name,
+            clock(battle.game_time_seconds),
+            battle.troops_involved,
+            outcome
+        );
+    }
+    if !contested.is_empty() {
+        let territories: Vec<String> = contested
+            .iter()
+            .map(|t| format!("{} ({} battles, changed hands {} times)", t.territory_name, t.battles, t.times_changed_hands))
+            .collect();
+        let _ = writeln!(header, "Most contested: {}", territories.join(", "));
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut length = header.chars().count() + "**Timeline**\n".len();
+    for entry in timeline.iter().rev() {
+        let line = format!("`{}` {}\n", clock(entry.game_time_seconds), entry.text);
+        length += line.chars().count();
+        if length > MAX_SUMMARY_TEXT_CHARS {
+            break;
+        }
+        lines.push(line);
+    }
+
+    if !lines.is_empty() {
+        header.push_str("**Timeline**\n");
+        header.extend(lines.into_iter().rev());
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    #[test]
+    fn test_summary_covers_battles_and_timeline() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 100);
+        let (winner, loser) = (engine.state.players[0].id, engine.state.players[1].id);
+        engine.state.game_time_seconds = 125;
+        engine.eliminate_player(loser.into(), Some(winner));
+        engine.battles = 3;
+        engine.biggest_battle = Some(BattleHighlight {
+            attacker_name: "Player".to_string(),
+            defender_name: None,
+            territory_id: Uuid::new_v4(),
+            territory_name: "Ironhold".to_string(),
+            troops_involved: 640,
+            territory_conquered: true,
+            game_time_seconds: 61,
+        });
+        let ironhold = engine.state.territories[5].id;
+        engine.state.territories[5].name = "Ironhold".to_string();
+        engine.record_territory_battle(ironhold, 640, true);
+        engine.take_events();
+
+        let stats = engine.check_game_over().unwrap();
+        let summary = engine.summary(Uuid::new_v4(), &stats);
+
+        assert_eq!(summary.winner_name, "Player");
+        assert_eq!(summary.stats.total_battles, 3);
+        assert_eq!(summary.timeline, [TimelineEntry {
+            game_time_seconds: 125,
+            text: "AI 1 has been eliminated".to_string(),
+            category: EventCategory::Combat,
+            entities: vec![EntityRef::Player { id: loser }, EntityRef::Player { id: winner }],
+        }]);
+        assert!(summary.text.starts_with("**🏆 Player wins!**\n"));
+        assert!(summary.text.contains("Biggest battle: Player vs neutral forces for Ironhold at 01:01, 640 troops (territory taken)"));
+        assert_eq!(summary.most_contested[0].territory_id, ironhold);
+        assert!(summary.text.contains("Most contested: Ironhold (1 battles, changed hands 1 times)\n"));
+        assert!(summary.text.ends_with("`02:05` AI 1 has been eliminated\n"));
+    }
+
+    #[test]
+    fn test_summary_text_fits_discord_limit() {
+        let stats = GameStats { winner: Uuid::nil(), game_duration_seconds: 0, territories_captured: 0, total_battles: 0, final_score: 0 };
+        let timeline: Vec<TimelineEntry> = (0..100)
+            .map(|i| TimelineEntry {
+                game_time_seconds: i,
+                text: format!("{} {}", i, "x".repeat(60)),
+                category: EventCategory::System,
+                entities: Vec::new(),
+            })
+            .collect();
+
+        let text = summary_text("Player", &stats, None, &[], &timeline);
+        assert!(text.chars().count() <= MAX_SUMMARY_TEXT_CHARS);
+        // The latest moments are the ones kept
+        assert!(text.ends_with(&format!("`01:39` 99 {}\n", "x".repeat(60))));
+    }
+}
@@ -0,0 +1,129 @@
+//! Voronoi cells around territory positions.
+//!
+//! Each territory owns the part of the map that is closer to its position than
+//! to any other. A cell starts as the whole unit square and is cut along the
+//! perpendicular bisector towards each nearby position in turn. Every edge
+//! remembers which cut made it, so cells that share an edge are known to border
+//! each other.
+
+pub type Point = (f32, f32);
+
+/// Borders shorter than this are corners touching, not shared edges
+const MIN_BORDER: f32 = 1e-4;
+
+/// The cell of one site, clipped to the unit square
+#[derive(Debug, Clone, Default)]
+pub struct Cell {
+    /// Corners in counter-clockwise order
+    pub polygon: Vec<Point>,
+    /// Sites whose cells share an edge with this one, with the edge's length
+    pub borders: Vec<(usize, f32)>,
+}
+
+/// A polygon corner and the site whose bisector runs from it to the next corner
+type Corner = (Point, Option<usize>);
+
+/// Cells of every site, in the order of `sites`
+pub fn cells(sites: &[Point]) -> Vec<Cell> {
+    (0..sites.len()).map(|i| cell(sites, i)).collect()
+}
+
+fn cell(sites: &[Point], i: usize) -> Cell {
+    let site = sites[i];
+    let mut corners: Vec<Corner> = vec![((0.0, 0.0), None), ((1.0, 0.0), None), ((1.0, 1.0), None), ((0.0, 1.0), None)];
+
+    let mut others: Vec<(usize, f32)> = (0..sites.len()).filter(|&j| j != i).map(|j| (j, distance(site, sites[j]))).collect();
+    others.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    for (j, d) in others {
+        // A site more than twice as far as the furthest corner cannot cut the cell
+        let reach = corners.iter().map(|(p, _)| distance(site, *p)).fold(0.0, f32::max);
+        if d > 2.0 * reach {
+            break;
+        }
+        corners = clip(&corners, site, sites[j], j);
+    }
+
+    let mut borders = Vec::new();
+    for (k, (from, edge)) in corners.iter().enumerate() {
+        let (to, _) = corners[(k + 1) % corners.len()];
+        if let Some(j) = edge {
+            let length = distance(*from, to);
+            if length > MIN_BORDER {
+                borders.push((*j, length));
+            }
+        }
+    }
+    Cell { polygon: corners.into_iter().map(|(p, _)| p).collect(), borders }
+}
+
+/// Keep the part of a polygon closer to `site` than to `other`
+fn clip(corners: &[Corner], site: Point, other: Point, j: usize) -> Vec<Corner> {
+    let normal = (other.0 - site.0, other.1 - site.1);
+    let mid = ((site.0 + other.0) / 2.0, (site.1 + other.1) / 2.0);
+    // Positive on `other`'s side of the bisector
+    let side = |p: Point| (p.0 - mid.0) * normal.0 + (p.1 - mid.1) * normal.1;
+    if normal == (0.0, 0.0) {
+        return corners.to_vec();
+    }
+
+    let mut clipped = Vec::with_capacity(corners.len() + 1);
+    for (k, &(a, edge)) in corners.iter().enumerate() {
+        let (b, _) = corners[(k + 1) % corners.len()];
+        let (sa, sb) = (side(a), side(b));
+        let crossing = || {
+            let t = sa / (sa - sb);
+            (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+        };
+        match (sa <= 0.0, sb <= 0.0) {
+            (true, true) => clipped.push((a, edge)),
+            // Leaving the cell: the bisector runs from here to where the polygon comes back
+            (true, false) => {
+                clipped.push((a, edge));
+                clipped.push((crossing(), Some(j)));
+            }
+            (false, true) => clipped.push((crossing(), edge)),
+            (false, false) => {}
+        }
+    }
+    clipped
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(polygon: &[Point]) -> f32 {
+        let twice: f32 = (0..polygon.len())
+            .map(|k| {
+                let (a, b) = (polygon[k], polygon[(k + 1) % polygon.len()]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum();
+        twice / 2.0
+    }
+
+    #[test]
+    fn test_cells_tile_the_map_and_share_edges() {
+        let sites = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75), (0.5, 0.5)];
+        let cells = cells(&sites);
+
+        // Counter-clockwise cells covering the square exactly once
+        let total: f32 = cells.iter().map(|c| area(&c.polygon)).sum();
+        assert!((total - 1.0).abs() < 1e-4);
+        assert!(cells.iter().all(|c| area(&c.polygon) > 0.0));
+
+        // The middle cell is a diamond bordering all four corners; diagonal corners only touch
+        assert_eq!(cells[4].polygon.len(), 4);
+        let mut middle: Vec<usize> = cells[4].borders.iter().map(|(j, _)| *j).collect();
+        middle.sort();
+        assert_eq!(middle, [0, 1, 2, 3]);
+        assert!(!cells[0].borders.iter().any(|(j, _)| *j == 3));
+        let (_, length) = cells[0].borders.iter().find(|(j, _)| *j == 1).unwrap();
+        assert!((length - 0.25).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,241 @@
+//! Rule plugins as sandboxed WASM modules.
+//!
+//! A module is loaded with no imports at all, so it can reach nothing but its
+//! own memory. Each hook call gets a fixed amount of fuel and the memory is
+//! capped; a module that runs out of either, traps or answers garbage reports
+//! a fault, and the plugin host disables it.
+//!
+//! The module exports `memory`, `alloc(len: i32) -> i32` and any of the hooks
+//! `on_tick`, `on_attack`, `on_build`, `check_victory` and
+//! `victory_progress`, each as `(ptr: i32, len: i32) -> i64`. The host calls
+//! `alloc`, writes the hook's input there as JSON and calls the hook, which
+//! returns `ptr << 32 | len` of its JSON answer, or 0 for the default:
+//!
+//! - input: `{"state": GameState}`, plus `"attack"` or `"build"` for those hooks
+//! - `on_tick`: a list of actions such as `{"action": "announce", "message": "..."}`
+//! - `on_attack`, `on_build`: `{"veto": "reason"}` to stop the action
+//! - `check_victory`: the winner's player id
+//! - `victory_progress`: a list of `VictoryProgress`
+
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+use wasmtime::{Config, Engine, Instance, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, TypedFunc};
+
+use super::plugins::{AttackIntent, BuildIntent, HookOutcome, PluginAction, RulePlugin};
+use crate::types::*;
+
+/// Roughly the WASM instructions one hook call may execute
+const FUEL_PER_HOOK: u64 = 20_000_000;
+/// Largest linear memory a module may grow to
+const MAX_MEMORY_BYTES: usize = 64 * 1024 * 1024;
+
+const HOOKS: [&str; 5] = ["on_tick", "on_attack", "on_build", "check_victory", "victory_progress"];
+
+type Hook = TypedFunc<(i32, i32), i64>;
+
+#[derive(Serialize)]
+struct HookInput<'a> {
+    state: &'a GameState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attack: Option<&'a AttackIntent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build: Option<&'a BuildIntent>,
+}
+
+impl<'a> HookInput<'a> {
+    fn state(state: &'a GameState) -> Self {
+        Self { state, attack: None, build: None }
+    }
+}
+
+#[derive(Deserialize)]
+struct Veto {
+    veto: String,
+}
+
+/// A rule plugin running in a WASM sandbox
+pub struct WasmPlugin {
+    name: String,
+    store: Store<StoreLimits>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    hooks: HashMap<&'static str, Hook>,
+    fault: Option<String>,
+}
+
+impl WasmPlugin {
+    /// Load a `.wasm` (or `.wat`) file, named after the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("wasm").to_string();
+        Self::new(name, std::fs::read(path)?)
+    }
+
+    pub fn new(name: String, module: impl AsRef<[u8]>) -> Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, module)?;
+
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY_BYTES).instances(1).memories(1).tables(1).build();
+        let mut store = Store::new(&engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_HOOK)?;
+
+        // No imports: the module gets nothing from the host but its inputs
+        let instance = Instance::new(&mut store, &module, &[])?;
+        let memory = instance.get_memory(&mut store, "memory").ok_or_else(|| anyhow!("{} exports no memory", name))?;
+        let alloc = instance.get_typed_func(&mut store, "alloc")?;
+        let mut hooks = HashMap::new();
+        for hook in HOOKS {
+            if instance.get_export(&mut store, hook).is_some() {
+                hooks.insert(hook, instance.get_typed_func(&mut store, hook)?);
+            }
+        }
+
+        Ok(Self { name, store, memory, alloc, hooks, fault: None })
+    }
+
+    /// Run a hook; `None` if the module doesn't have it, answered with the
+    /// default, or failed, in which case the plugin is faulted
+    fn call<T: DeserializeOwned>(&mut self, hook: &str, input: &HookInput) -> Option<T> {
+        if self.fault.is_some() {
+            return None;
+        }
+        let func = self.hooks.get(hook)?.clone();
+        match self.try_call(func, input) {
+            Ok(reply) => reply,
+            Err(e) => {
+                self.fault = Some(format!("{}: {:#}", hook, e));
+                None
+            }
+        }
+    }
+
+    fn try_call<T: DeserializeOwned>(&mut self, func: Hook, input: &HookInput) -> Result<Option<T>> {
+        let input = serde_json::to_vec(input)?;
+        let len = i32::try_from(input.len())?;
+        self.store.set_fuel(FUEL_PER_HOOK)?;
+
+        let ptr = self.alloc.call(&mut self.store, len)?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, &input)?;
+        let reply = func.call(&mut self.store, (ptr, len))?;
+        if reply == 0 {
+            return Ok(None);
+        }
+
+        let (start, len) = ((reply as u64 >> 32) as usize, reply as u32 as usize);
+        let bytes = self.memory.data(&self.store).get(start..start + len).ok_or_else(|| anyhow!("answer out of bounds"))?;
+        Ok(Some(serde_json::from_slice(bytes)?))
+    }
+
+    fn veto(&mut self, hook: &str, input: &HookInput) -> HookOutcome {
+        match self.call::<Veto>(hook, input) {
+            Some(Veto { veto }) => HookOutcome::Veto(veto),
+            None => HookOutcome::Allow,
+        }
+    }
+}
+
+impl RulePlugin for WasmPlugin {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn on_tick(&mut self, state: &GameState) -> Vec<PluginAction> {
+        self.call("on_tick", &HookInput::state(state)).unwrap_or_default()
+    }
+
+    fn on_attack(&mut self, state: &GameState, attack: &AttackIntent) -> HookOutcome {
+        self.veto("on_attack", &HookInput { attack: Some(attack), ..HookInput::state(state) })
+    }
+
+    fn on_build(&mut self, state: &GameState, build: &BuildIntent) -> HookOutcome {
+        self.veto("on_build", &HookInput { build: Some(build), ..HookInput::state(state) })
+    }
+
+    fn check_victory(&mut self, state: &GameState) -> Option<Uuid> {
+        self.call("check_victory", &HookInput::state(state))
+    }
+
+    fn victory_progress(&mut self, state: &GameState) -> Vec<VictoryProgress> {
+        self.call("victory_progress", &HookInput::state(state)).unwrap_or_default()
+    }
+
+    fn fault(&self) -> Option<&str> {
+        self.fault.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEngine, MapGenerator};
+
+    /// A module answering `hook` with `answer` and reading inputs at 64 KiB
+    fn answering(hook: &str, answer: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") 16)
+                (func (export "alloc") (param i32) (result i32) (i32.const 65536))
+                (data (i32.const 1024) "{}")
+                (func (export "{}") (param i32 i32) (result i64) (i64.const {})))"#,
+            answer.replace('"', "\\\""),
+            hook,
+            (1024u64 << 32) | answer.len() as u64
+        )
+    }
+
+    fn engine() -> GameEngine {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 3).generate(), 100);
+        engine.tick();
+        engine
+    }
+
+    #[test]
+    fn test_module_hooks_act_on_the_game() {
+        let mut engine = engine();
+        let announce = answering("on_tick", r#"[{"action": "announce", "message": "Sandboxed hello"}]"#);
+        engine.plugins.register(Box::new(WasmPlugin::new("hello".to_string(), announce).unwrap()));
+        let veto = answering("on_build", r#"{"veto": "No building here"}"#);
+        engine.plugins.register(Box::new(WasmPlugin::new("no_building".to_string(), veto).unwrap()));
+
+        engine.tick();
+        assert!(engine.take_events().iter().any(|e| matches!(e, ServerMessage::Notification { message, .. } if message == "Sandboxed hello")));
+
+        let player = engine.state.players[0].id;
+        let territory = engine.state.territories.iter().find(|t| t.owner == Some(player)).unwrap().id;
+        engine.get_player_mut(player.into()).unwrap().gold = 10_000;
+        let err = engine.build_structure(player.into(), territory.into(), BuildingType::GoldMine).unwrap_err();
+        assert!(err.to_string().contains("No building here"));
+        assert_eq!(engine.plugins.active(), ["hello", "no_building"]);
+    }
+
+    #[test]
+    fn test_runaway_module_runs_out_of_fuel_and_is_disabled() {
+        let mut engine = engine();
+        let spin = r#"(module
+            (memory (export "memory") 16)
+            (func (export "alloc") (param i32) (result i32) (i32.const 65536))
+            (func (export "on_tick") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))"#;
+        engine.plugins.register(Box::new(WasmPlugin::new("spin".to_string(), spin).unwrap()));
+
+        engine.tick();
+        assert!(engine.plugins.active().is_empty());
+        // The game goes on without it
+        engine.tick();
+    }
+
+    #[test]
+    fn test_modules_get_no_imports() {
+        let wasi = r#"(module
+            (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "alloc") (param i32) (result i32) (i32.const 0)))"#;
+        assert!(WasmPlugin::new("wasi".to_string(), wasi).is_err());
+    }
+}
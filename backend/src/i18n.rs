@@ -0,0 +1,254 @@
+//! Translations of server notifications.
+//!
+//! Notifications carry a `NotificationKey` and named parameters next to their
+//! text. The text is rendered from this catalog in the locale a client picked
+//! when connecting, English otherwise, so clients can either show it as is or
+//! word the key themselves. Templates name their parameters in braces; a
+//! `building` parameter holds a building type and is translated as well.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::types::*;
+
+/// Languages the catalog has text for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Pl,
+}
+
+impl Locale {
+    /// The locale for a tag such as `de` or `pl-PL`; English for anything unknown
+    pub fn from_tag(tag: &str) -> Self {
+        let language = tag.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        serde_json::from_value(serde_json::Value::String(language)).unwrap_or_default()
+    }
+}
+
+fn template(locale: Locale, key: NotificationKey) -> &'static str {
+    use Locale::*;
+    use NotificationKey::*;
+    match (locale, key) {
+        (_, Announcement) => "{text}",
+
+        (En, PlayerJoined) => "{player} joined the game",
+        (De, PlayerJoined) => "{player} ist dem Spiel beigetreten",
+        (Es, PlayerJoined) => "{player} se unió a la partida",
+        (Pl, PlayerJoined) => "{player} dołącza do gry",
+
+        (En, PlayerEliminated) => "{player} has been eliminated",
+        (De, PlayerEliminated) => "{player} wurde ausgeschaltet",
+        (Es, PlayerEliminated) => "{player} ha sido eliminado",
+        (Pl, PlayerEliminated) => "{player} odpada z gry",
+
+        (En, SlotFilledByAi) => "{player} did not join and was replaced by {ai}",
+        (De, SlotFilledByAi) => "{player} ist nicht beigetreten und wurde durch {ai} ersetzt",
+        (Es, SlotFilledByAi) => "{player} no se unió y fue reemplazado por {ai}",
+        (Pl, SlotFilledByAi) => "{player} nie dołącza, zastępuje go {ai}",
+
+        (En, TerritoryCaptured) => "{attacker} captured {territory} ({building}) from {defender}",
+        (De, TerritoryCaptured) => "{attacker} hat {territory} ({building}) von {defender} erobert",
+        (Es, TerritoryCaptured) => "{attacker} capturó {territory} ({building}) a {defender}",
+        (Pl, TerritoryCaptured) => "{attacker} zdobywa {territory} ({building}) od {defender}",
+
+        (En, CapitalCaptured) => "{attacker} took {territory}, the capital of {defender}",
+        (De, CapitalCaptured) => "{attacker} hat {territory} eingenommen, die Hauptstadt von {defender}",
+        (Es, CapitalCaptured) => "{attacker} tomó {territory}, la capital de {defender}",
+        (Pl, CapitalCaptured) => "{attacker} zdobywa {territory}, stolicę gracza {defender}",
+
+        (En, TreatySigned) => "{player} and {other} signed a treaty",
+        (De, TreatySigned) => "{player} und {other} haben einen Vertrag geschlossen",
+        (Es, TreatySigned) => "{player} y {other} firmaron un tratado",
+        (Pl, TreatySigned) => "{player} i {other} podpisują traktat",
+
+        (En, TreatyBroken) => "{player} broke their treaty with {other}",
+        (De, TreatyBroken) => "{player} hat den Vertrag mit {other} gebrochen",
+        (Es, TreatyBroken) => "{player} rompió su tratado con {other}",
+        (Pl, TreatyBroken) => "{player} zrywa traktat z {other}",
+
+        (En, BuildingCompleted) => "Building completed!",
+        (De, BuildingCompleted) => "Gebäude fertiggestellt!",
+        (Es, BuildingCompleted) => "¡Edificio completado!",
+        (Pl, BuildingCompleted) => "Budowa ukończona!",
+
+        (En, GameSaved) => "Game saved as {name}",
+        (De, GameSaved) => "Spiel gespeichert als {name}",
+        (Es, GameSaved) => "Partida guardada como {name}",
+        (Pl, GameSaved) => "Gra zapisana jako {name}",
+
+        (En, GameLoaded) => "The host loaded a saved game at tick {tick}",
+        (De, GameLoaded) => "Der Host hat einen Spielstand bei Tick {tick} geladen",
+        (Es, GameLoaded) => "El anfitrión cargó una partida guardada en el tick {tick}",
+        (Pl, GameLoaded) => "Gospodarz wczytał zapis gry z ticku {tick}",
+
+        (En, VoteKickProgress) => "{voter} votes to kick {player} ({votes}/{needed})",
+        (De, VoteKickProgress) => "{voter} stimmt dafür, {player} zu entfernen ({votes}/{needed})",
+        (Es, VoteKickProgress) => "{voter} vota expulsar a {player} ({votes}/{needed})",
+        (Pl, VoteKickProgress) => "{voter} głosuje za wyrzuceniem {player} ({votes}/{needed})",
+
+        (En, VoteKickFailed) => "The vote to kick {player} failed",
+        (De, VoteKickFailed) => "Die Abstimmung über {player} ist gescheitert",
+        (Es, VoteKickFailed) => "La votación para expulsar a {player} fracasó",
+        (Pl, VoteKickFailed) => "Głosowanie nad wyrzuceniem {player} nie powiodło się",
+
+        (En, VoteKicked) => "{player} was vote-kicked and replaced by {ai}",
+        (De, VoteKicked) => "{player} wurde per Abstimmung entfernt und durch {ai} ersetzt",
+        (Es, VoteKicked) => "{player} fue expulsado por votación y reemplazado por {ai}",
+        (Pl, VoteKicked) => "{player} zostaje wyrzucony w głosowaniu, zastępuje go {ai}",
+
+        (En, GameRecovered) => "The game hit a server error and resumed from tick {tick}",
+        (De, GameRecovered) => "Das Spiel hatte einen Serverfehler und läuft ab Tick {tick} weiter",
+        (Es, GameRecovered) => "La partida sufrió un error del servidor y se reanudó desde el tick {tick}",
+        (Pl, GameRecovered) => "Błąd serwera, gra wznowiona od ticku {tick}",
+
+        (En, GameStopped) => "The game was stopped after repeated server errors",
+        (De, GameStopped) => "Das Spiel wurde nach wiederholten Serverfehlern beendet",
+        (Es, GameStopped) => "La partida se detuvo tras repetidos errores del servidor",
+        (Pl, GameStopped) => "Gra została zatrzymana po powtarzających się błędach serwera",
+
+        (En, ReplayEnded) => "The replay has ended",
+        (De, ReplayEnded) => "Die Wiederholung ist zu Ende",
+        (Es, ReplayEnded) => "La repetición ha terminado",
+        (Pl, ReplayEnded) => "Powtórka dobiegła końca",
+
+        (En, GamePaused) => "{player} paused the game",
+        (De, GamePaused) => "{player} hat das Spiel pausiert",
+        (Es, GamePaused) => "{player} pausó la partida",
+        (Pl, GamePaused) => "{player} wstrzymuje grę",
+
+        (En, GameResumed) => "{player} resumed the game",
+        (De, GameResumed) => "{player} hat das Spiel fortgesetzt",
+        (Es, GameResumed) => "{player} reanudó la partida",
+        (Pl, GameResumed) => "{player} wznawia grę",
+
+        (En, GameSpeedChanged) => "{player} set the game speed to {speed}x",
+        (De, GameSpeedChanged) => "{player} hat die Spielgeschwindigkeit auf {speed}x gesetzt",
+        (Es, GameSpeedChanged) => "{player} cambió la velocidad de la partida a {speed}x",
+        (Pl, GameSpeedChanged) => "{player} ustawia prędkość gry na {speed}x",
+    }
+}
+
+fn building_name(locale: Locale, building: BuildingType) -> &'static str {
+    use BuildingType::*;
+    match (locale, building) {
+        (Locale::En, _) => building.display_name(),
+        (Locale::De, City) => "Stadt",
+        (Locale::De, DefensePost) => "Verteidigungsposten",
+        (Locale::De, GoldMine) => "Goldmine",
+        (Locale::De, Barracks) => "Kaserne",
+        (Locale::De, Harbor) => "Hafen",
+        (Locale::De, Bridge) => "Brücke",
+        (Locale::De, Farm) => "Bauernhof",
+        (Locale::Es, City) => "Ciudad",
+        (Locale::Es, DefensePost) => "Puesto defensivo",
+        (Locale::Es, GoldMine) => "Mina de oro",
+        (Locale::Es, Barracks) => "Cuartel",
+        (Locale::Es, Harbor) => "Puerto",
+        (Locale::Es, Bridge) => "Puente",
+        (Locale::Es, Farm) => "Granja",
+        (Locale::Pl, City) => "Miasto",
+        (Locale::Pl, DefensePost) => "Posterunek obronny",
+        (Locale::Pl, GoldMine) => "Kopalnia złota",
+        (Locale::Pl, Barracks) => "Koszary",
+        (Locale::Pl, Harbor) => "Port",
+        (Locale::Pl, Bridge) => "Most",
+        (Locale::Pl, Farm) => "Farma",
+    }
+}
+
+/// A building as a `building` parameter: its type, as in the protocol
+pub fn building_param(building: BuildingType) -> String {
+    serde_json::to_value(building).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+}
+
+/// Text of a notification in `locale`
+pub fn render(locale: Locale, key: NotificationKey, params: &BTreeMap<String, String>) -> String {
+    // One pass, so braces in player names are never taken for placeholders
+    let mut text = String::new();
+    let mut rest = template(locale, key);
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else { break };
+        text.push_str(&rest[..start]);
+        let name = &rest[start + 1..start + len];
+        let value = params.get(name).map_or("", String::as_str);
+        let building = (name == "building")
+            .then(|| serde_json::from_value::<BuildingType>(serde_json::Value::String(value.to_string())).ok())
+            .flatten();
+        text.push_str(building.map_or(value, |building| building_name(locale, building)));
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(rest);
+    text
+}
+
+impl ServerMessage {
+    /// A notification with its text rendered in English
+    pub fn notification<'a>(
+        key: NotificationKey,
+        severity: NotificationLevel,
+        params: impl IntoIterator<Item = (&'a str, String)>,
+    ) -> Self {
+        let params: BTreeMap<String, String> = params.into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+        ServerMessage::Notification {
+            message: render(Locale::En, key, &params),
+            severity,
+            key,
+            params,
+            category: key.category(),
+            entities: Vec::new(),
+        }
+    }
+
+    /// Name the players and territories a notification concerns
+    pub fn about(mut self, subjects: impl IntoIterator<Item = EntityRef>) -> Self {
+        if let ServerMessage::Notification { entities, .. } = &mut self {
+            entities.extend(subjects);
+        }
+        self
+    }
+
+    /// The message as a client in `locale` should see it
+    pub fn localized(self, locale: Locale) -> Self {
+        match self {
+            ServerMessage::Notification { message: _, severity, key, params, category, entities } if locale != Locale::En => {
+                let message = render(locale, key, &params);
+                ServerMessage::Notification { message, severity, key, params, category, entities }
+            }
+            other => other,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifications_render_in_the_clients_locale() {
+        let params = [
+            ("attacker", "Ada".to_string()),
+            ("territory", "Ironhold".to_string()),
+            ("building", building_param(BuildingType::GoldMine)),
+            ("defender", "AI 2".to_string()),
+        ];
+        let message = ServerMessage::notification(NotificationKey::TerritoryCaptured, NotificationLevel::Info, params);
+        assert!(matches!(&message, ServerMessage::Notification { message, .. } if message == "Ada captured Ironhold (Gold Mine) from AI 2"));
+
+        let localized = message.localized(Locale::from_tag("de-AT"));
+        assert!(matches!(
+            &localized,
+            ServerMessage::Notification { message, key: NotificationKey::TerritoryCaptured, params, .. }
+                if message == "Ada hat Ironhold (Goldmine) von AI 2 erobert" && params["building"] == "gold_mine"
+        ));
+        assert_eq!(Locale::from_tag("xx"), Locale::En);
+        let params = [("player", "{other}".to_string()), ("other", "Bo".to_string())];
+        let message = ServerMessage::notification(NotificationKey::TreatySigned, NotificationLevel::Info, params);
+        assert!(matches!(message, ServerMessage::Notification { message, .. } if message == "{other} and Bo signed a treaty"));
+        assert_eq!(Locale::from_tag("PL"), Locale::Pl);
+    }
+}
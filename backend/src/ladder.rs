@@ -0,0 +1,372 @@
+//! Ranked seasonal ladder.
+//!
+//! Human players carry an Elo rating through fixed-length seasons. Each
+//! season starts with a few placement matches, during which ratings move
+//! faster and the player is kept off the leaderboard. When a season ends,
+//! every rating is pulled halfway back to the starting rating for the next
+//! one.
+//!
+//! Players are keyed by an identity derived from the `player_key` their
+//! client joins games with, so a rating can't be played by someone else who
+//! picks the same display name; humans who join without a key are rated as a
+//! fresh player and don't appear on the ladder. The ladder lives in memory
+//! and is loaded from the match `Storage` at startup; changed entries are
+//! saved by a background task, in order, so rating a game never waits on the
+//! database.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tracing::warn;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::storage::Storage;
+
+const STARTING_RATING: f64 = 1500.0;
+/// AI opponents are rated as a fixed average player
+const AI_RATING: f64 = 1500.0;
+const PLACEMENT_MATCHES: u32 = 5;
+const K_FACTOR: f64 = 32.0;
+const PLACEMENT_K_FACTOR: f64 = 64.0;
+/// Shortest and longest player key accepted, so keys can't be guessed
+pub const MIN_PLAYER_KEY_CHARS: usize = 16;
+pub const MAX_PLAYER_KEY_CHARS: usize = 128;
+
+/// The ladder identity of a player key; keys themselves are never stored
+pub fn player_identity(key: &str) -> Result<Uuid> {
+    if !(MIN_PLAYER_KEY_CHARS..=MAX_PLAYER_KEY_CHARS).contains(&key.chars().count()) {
+        return Err(anyhow!("Player keys must be {} to {} characters", MIN_PLAYER_KEY_CHARS, MAX_PLAYER_KEY_CHARS));
+    }
+    let digest = Sha256::digest(key.as_bytes());
+    Ok(Uuid::from_slice(&digest[..16])?)
+}
+
+/// One player's record in a season
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LadderEntry {
+    #[schema(value_type = String, format = "uuid")]
+    pub player: Uuid,
+    /// The name the player last played a rated game under
+    pub name: String,
+    pub rating: f64,
+    pub games: u32,
+    pub wins: u32,
+}
+
+impl LadderEntry {
+    fn new(player: Uuid, name: &str, rating: f64) -> Self {
+        Self { player, name: name.to_string(), rating, games: 0, wins: 0 }
+    }
+
+    pub fn placed(&self) -> bool {
+        self.games >= PLACEMENT_MATCHES
+    }
+}
+
+/// A season and everyone who played in it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Season {
+    pub number: u32,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub entries: BTreeMap<Uuid, LadderEntry>,
+}
+
+/// Placed players of a season, best first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Leaderboard {
+    pub season: u32,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    pub standings: Vec<LadderEntry>,
+    /// Players still in their placement matches
+    pub unplaced_players: u32,
+}
+
+/// A participant in a finished game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MatchParticipant {
+    pub name: String,
+    pub is_ai: bool,
+    pub won: bool,
+    /// Ladder identity of a human who joined with a player key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub identity: Option<Uuid>,
+}
+
+#[derive(Debug, Default)]
+struct LadderData {
+    seasons: Vec<Season>,
+}
+
+pub struct Ladder {
+    season_length: Duration,
+    data: Mutex<LadderData>,
+    /// Seasons carrying the entries that changed, for the saving task
+    writes: Option<mpsc::UnboundedSender<Season>>,
+}
+
+impl Ladder {
+    /// Load the ladder from `storage` and keep saving it there
+    pub async fn open(storage: Arc<dyn Storage>, season_length: Duration) -> Result<Self> {
+        let seasons = storage.ladder_seasons().await?;
+        let (writes, queue) = mpsc::unbounded_channel();
+        tokio::spawn(save_seasons(storage, queue));
+        Ok(Self { season_length, data: Mutex::new(LadderData { seasons }), writes: Some(writes) })
+    }
+
+    /// A ladder that is never saved
+    #[cfg(test)]
+    pub fn in_memory(season_length: Duration) -> Self {
+        Self { season_length, data: Mutex::new(LadderData::default()), writes: None }
+    }
+
+    /// Rate a finished game: the winner beats every other participant, and
+    /// every human loser loses to the winner
+    pub fn record_match(&self, participants: &[MatchParticipant], now: DateTime<Utc>) -> Result<()> {
+        let Some(winner) = participants.iter().find(|p| p.won) else { return Ok(()) };
+        if participants.iter().all(|p| p.is_ai) {
+            return Ok(());
+        }
+
+        let mut data = self.data.lock().unwrap();
+        let season = self.current_season(&mut data, now);
+        let rating_of = |season: &Season, p: &MatchParticipant| match p.identity {
+            _ if p.is_ai => AI_RATING,
+            Some(identity) => season.entries.get(&identity).map_or(STARTING_RATING, |e| e.rating),
+            None => STARTING_RATING,
+        };
+
+        let winner_rating = rating_of(season, winner);
+        let mut changes: Vec<(&MatchParticipant, f64)> = Vec::new();
+        let mut winner_change = 0.0;
+        for loser in participants.iter().filter(|p| !p.won) {
+            let loser_rating = rating_of(season, loser);
+            let expected = 1.0 / (1.0 + 10f64.powf((loser_rating - winner_rating) / 400.0));
+            winner_change += k_factor(season, winner) * (1.0 - expected);
+            changes.push((loser, -k_factor(season, loser) * (1.0 - expected)));
+        }
+        changes.push((winner, winner_change));
+
+        let mut changed = BTreeMap::new();
+        for (participant, change) in changes {
+            let Some(identity) = participant.identity.filter(|_| !participant.is_ai) else { continue };
+            let entry = season
+                .entries
+                .entry(identity)
+                .or_insert_with(|| LadderEntry::new(identity, &participant.name, STARTING_RATING));
+            entry.name = participant.name.clone();
+            entry.rating += change;
+            entry.games += 1;
+            entry.wins += u32::from(participant.won);
+            changed.insert(identity, entry.clone());
+        }
+
+        let changed = Season { entries: changed, ..season.clone() };
+        drop(data);
+        self.save(changed)
+    }
+
+    /// Standings of a season, or of the current one
+    pub fn leaderboard(&self, season: Option<u32>, now: DateTime<Utc>) -> Option<Leaderboard> {
+        let mut data = self.data.lock().unwrap();
+        let season = match season {
+            Some(number) => data.seasons.iter().find(|s| s.number == number)?,
+            None => self.current_season(&mut data, now),
+        };
+
+        let mut standings: Vec<LadderEntry> = season.entries.values().filter(|e| e.placed()).cloned().collect();
+        standings.sort_by(|a, b| b.rating.total_cmp(&a.rating));
+        Some(Leaderboard {
+            season: season.number,
+            started_at: season.started_at,
+            ends_at: season.ends_at,
+            unplaced_players: (season.entries.len() - standings.len()) as u32,
+            standings,
+        })
+    }
+
+    /// The season running at `now`, rolling over (with a soft rating reset) if the last one ended
+    fn current_season<'a>(&self, data: &'a mut LadderData, now: DateTime<Utc>) -> &'a mut Season {
+        let expired = data.seasons.last().is_none_or(|s| now >= s.ends_at);
+        if expired {
+            let previous = data.seasons.last();
+            let number = previous.map_or(1, |s| s.number + 1);
+            // After a quiet spell, the season in progress at `now` on the usual schedule
+            let started_at = previous.map_or(now, |s| {
+                let missed = (now - s.ends_at).num_seconds() / self.season_length.num_seconds().max(1);
+                s.ends_at + self.season_length * missed as i32
+            });
+            let entries = previous
+                .map(|s| {
+                    s.entries
+                        .iter()
+                        .map(|(&player, e)| (player, LadderEntry::new(player, &e.name, (e.rating + STARTING_RATING) / 2.0)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let season = Season { number, started_at, ends_at: started_at + self.season_length, entries };
+            // The save can only fail once the server is shutting down
+            let _ = self.save(season.clone());
+            data.seasons.push(season);
+        }
+        data.seasons.last_mut().unwrap()
+    }
+
+    /// Queue a season with the entries to save
+    fn save(&self, season: Season) -> Result<()> {
+        let Some(writes) = &self.writes else { return Ok(()) };
+        writes.send(season).map_err(|_| anyhow!("The ladder is no longer being saved"))
+    }
+}
+
+/// Save queued seasons until the ladder is dropped
+async fn save_seasons(storage: Arc<dyn Storage>, mut queue: mpsc::UnboundedReceiver<Season>) {
+    while let Some(season) = queue.recv().await {
+        if let Err(e) = storage.save_ladder(&season).await {
+            warn!(season = season.number, "Failed to save ladder: {}", e);
+        }
+    }
+}
+
+fn k_factor(season: &Season, participant: &MatchParticipant) -> f64 {
+    let placed = participant.identity.and_then(|identity| season.entries.get(&identity)).is_some_and(|e| e.placed());
+    if participant.is_ai || placed {
+        K_FACTOR
+    } else {
+        PLACEMENT_K_FACTOR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    fn identity(name: &str) -> Uuid {
+        player_identity(&format!("{}'s secret player key", name)).unwrap()
+    }
+
+    fn human(name: &str, won: bool) -> MatchParticipant {
+        MatchParticipant { name: name.to_string(), is_ai: false, won, identity: Some(identity(name)) }
+    }
+
+    fn game(winner: &str, loser: &str) -> Vec<MatchParticipant> {
+        vec![
+            human(winner, true),
+            human(loser, false),
+            MatchParticipant { name: "AI 1".to_string(), is_ai: true, won: false, identity: None },
+        ]
+    }
+
+    #[test]
+    fn test_players_appear_after_placement() {
+        let ladder = Ladder::in_memory(Duration::days(30));
+        let now = Utc::now();
+
+        for _ in 0..PLACEMENT_MATCHES - 1 {
+            ladder.record_match(&game("alice", "bob"), now).unwrap();
+        }
+        let board = ladder.leaderboard(None, now).unwrap();
+        assert!(board.standings.is_empty());
+        assert_eq!(board.unplaced_players, 2);
+
+        ladder.record_match(&game("alice", "bob"), now).unwrap();
+        let board = ladder.leaderboard(None, now).unwrap();
+        assert_eq!(board.standings.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["alice", "bob"]);
+        assert_eq!(board.standings[0].wins, PLACEMENT_MATCHES);
+        assert!(board.standings[0].rating > STARTING_RATING && board.standings[1].rating < STARTING_RATING);
+    }
+
+    #[test]
+    fn test_players_are_rated_by_key_not_name() {
+        let ladder = Ladder::in_memory(Duration::days(30));
+        let now = Utc::now();
+        ladder.record_match(&game("alice", "bob"), now).unwrap();
+
+        // Someone else calling themselves alice doesn't get her rating
+        let mut impostor = game("bob", "alice");
+        impostor[1].identity = Some(identity("mallory"));
+        ladder.record_match(&impostor, now).unwrap();
+        // Nor does anyone without a key
+        impostor[1].identity = None;
+        ladder.record_match(&impostor, now).unwrap();
+
+        let data = ladder.data.lock().unwrap();
+        let entries = &data.seasons[0].entries;
+        assert_eq!(entries.len(), 3);
+        assert_eq!((entries[&identity("alice")].games, entries[&identity("alice")].wins), (1, 1));
+        assert_eq!(entries[&identity("mallory")].name, "alice");
+        assert!(player_identity("short").is_err());
+    }
+
+    #[test]
+    fn test_new_season_soft_resets_ratings() {
+        let ladder = Ladder::in_memory(Duration::days(30));
+        let start = Utc::now();
+        ladder.record_match(&game("alice", "bob"), start).unwrap();
+        let alice = ladder.data.lock().unwrap().seasons[0].entries[&identity("alice")].rating;
+
+        let later = start + Duration::days(31);
+        let board = ladder.leaderboard(None, later).unwrap();
+        assert_eq!(board.season, 2);
+        {
+            let data = ladder.data.lock().unwrap();
+            assert_eq!(data.seasons[1].entries[&identity("alice")].rating, (alice + STARTING_RATING) / 2.0);
+            assert_eq!(data.seasons[1].entries[&identity("alice")].games, 0);
+        }
+
+        // Past seasons stay queryable
+        assert_eq!(ladder.leaderboard(Some(1), later).unwrap().unplaced_players, 2);
+        assert!(ladder.leaderboard(Some(7), later).is_none());
+    }
+
+    #[test]
+    fn test_season_after_a_long_gap_covers_now() {
+        let ladder = Ladder::in_memory(Duration::days(30));
+        let start = Utc::now();
+        ladder.record_match(&game("alice", "bob"), start).unwrap();
+        let alice = ladder.data.lock().unwrap().seasons[0].entries[&identity("alice")].rating;
+
+        let later = start + Duration::days(100);
+        ladder.record_match(&game("alice", "bob"), later).unwrap();
+        let board = ladder.leaderboard(None, later + Duration::days(1)).unwrap();
+        assert_eq!(board.season, 2);
+        assert!(board.started_at <= later && later < board.ends_at);
+        assert_eq!(board.started_at, start + Duration::days(90));
+
+        // One soft reset, and the game counted in the season that is running
+        let data = ladder.data.lock().unwrap();
+        assert_eq!(data.seasons.len(), 2);
+        let entry = &data.seasons[1].entries[&identity("alice")];
+        assert_eq!(entry.games, 1);
+        assert!(entry.rating > (alice + STARTING_RATING) / 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_ladder_survives_restart() {
+        let storage: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        let now = Utc::now();
+        let ladder = Ladder::open(storage.clone(), Duration::days(30)).await.unwrap();
+        ladder.record_match(&game("alice", "bob"), now).unwrap();
+        ladder.record_match(&game("carol", "bob"), now).unwrap();
+        let expected = ladder.data.lock().unwrap().seasons.clone();
+
+        // Saved in the background
+        for _ in 0..50 {
+            if storage.ladder_seasons().await.unwrap() == expected {
+                let reopened = Ladder::open(storage, Duration::days(30)).await.unwrap();
+                assert_eq!(reopened.leaderboard(None, now).unwrap().unplaced_players, 3);
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("the ladder was never saved");
+    }
+}
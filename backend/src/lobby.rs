@@ -0,0 +1,787 @@
+//! Game rooms hosted side by side.
+//!
+//! The server starts with one main room configured from the environment.
+//! Clients can open more rooms, each with its own engine and tick loop, list
+//! them, and join one by connecting with its id. New rooms share the main
+//! room's services and options. Rooms whose game has ended stay listed, so
+//! their summaries and replays can still be fetched, until their slot is
+//! needed for a new room or, with an archive grace period, until it is over;
+//! they are then archived to storage and dropped. With a room store, opened
+//! rooms are saved to storage periodically and reopened after a restart,
+//! reserved seats included. Rooms, map sizes and connections
+//! per game are capped by the lobby's admission limits.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::admission::{AdmissionError, AdmissionStats, RoomQuota};
+use crate::game::custom_map::{MapDefinition, MAX_CUSTOM_TERRITORIES};
+use crate::game::gym::{GymConfig, GymEnv, Observation};
+use crate::game::{Difficulty, GameEngine, GameRules, MapGenerator};
+use crate::room_store::SavedRoom;
+use crate::storage::Storage;
+use crate::types::*;
+use crate::websocket::{ConnectionSlot, GameSession};
+
+/// Rooms hosted at once unless configured otherwise
+pub const DEFAULT_MAX_ROOMS: usize = 16;
+/// Rooms a client may open a minute, quick-play games included, unless configured otherwise
+pub const DEFAULT_ROOMS_PER_CLIENT_PER_MINUTE: usize = 5;
+/// Connections a game takes unless configured otherwise
+pub const DEFAULT_MAX_CLIENTS_PER_GAME: usize = 64;
+/// Opened rooms end once nobody has been connected for this long, unless the
+/// main room sets its own idle timeout
+const ROOM_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Minimum time between lobby chat messages from one player
+const LOBBY_CHAT_COOLDOWN: Duration = Duration::from_secs(3);
+const ROOM_TICK_RATE_MS: u64 = 100;
+/// Imported maps kept at once; the oldest is forgotten to make space
+const MAX_CUSTOM_MAPS: usize = 64;
+/// Agent environments kept at once unless configured otherwise
+pub const DEFAULT_MAX_GYMS: usize = 32;
+/// Agent environments not stepped for this long are dropped
+const GYM_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+type SharedGym = Arc<std::sync::Mutex<GymEnv>>;
+
+/// Settings of a room to open
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateRoomRequest {
+    /// Shown in the room list; generated if omitted
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default = "default_territories")]
+    pub territories: usize,
+    #[serde(default = "default_players")]
+    pub players: usize,
+    /// Players reserved for humans, the rest are AI
+    #[serde(default = "default_human_slots")]
+    pub human_slots: usize,
+    #[serde(default)]
+    pub style: MapStyle,
+    #[serde(default)]
+    pub grid: MapGrid,
+    /// Map seed, random if omitted
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub allow_late_join: bool,
+    /// Imported map to play on instead of a generated one, with a player for
+    /// each of its starts; `territories`, `players`, `style` and `grid` are ignored
+    #[serde(default)]
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub map: Option<Uuid>,
+}
+
+impl CreateRoomRequest {
+    pub fn validate(&self) -> Result<()> {
+        if self.map.is_none() && (self.players < 2 || self.territories < self.players) {
+            return Err(anyhow!("Need at least two players and a territory for each"));
+        }
+        Ok(())
+    }
+}
+
+fn default_territories() -> usize {
+    75
+}
+
+fn default_players() -> usize {
+    9
+}
+
+fn default_human_slots() -> usize {
+    1
+}
+
+/// A room as shown in the room list
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RoomInfo {
+    #[schema(value_type = String, format = "uuid")]
+    pub room_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub human_slots: u32,
+    /// Human slots nobody has claimed yet
+    pub open_slots: u32,
+    pub connected_clients: u32,
+    pub tick: u64,
+    /// The game has ended and the room can't be joined
+    pub finished: bool,
+    pub lifecycle: Lifecycle,
+}
+
+/// A map accepted by `POST /maps`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportedMap {
+    /// Pass as `map` when creating a room
+    #[schema(value_type = String, format = "uuid")]
+    pub map_id: Uuid,
+    pub name: String,
+    pub territories: u32,
+    pub players: u32,
+}
+
+/// A reserved player in a running room
+#[derive(Debug, Clone)]
+pub struct Seat {
+    pub room_id: Uuid,
+    pub player_id: Uuid,
+    /// Joins the room as this player; keep it to reconnect
+    pub token: String,
+}
+
+struct Room {
+    name: String,
+    created_at: DateTime<Utc>,
+    session: Arc<GameSession>,
+}
+
+pub struct Lobby {
+    main_room: Uuid,
+    rooms: RwLock<HashMap<Uuid, Room>>,
+    pub max_rooms: usize,
+    /// Rooms each client may open a minute
+    pub rooms_per_client_per_minute: usize,
+    room_quota: RoomQuota,
+    /// Player connections each game takes; spectators don't count
+    pub max_clients_per_game: usize,
+    /// Territories of the biggest map a room may be opened on
+    pub max_territories: usize,
+    /// Rooms and connections let in or turned away
+    pub admission: AdmissionStats,
+    /// Where opened rooms are saved, if anywhere
+    pub store: Option<Arc<dyn Storage>>,
+    /// Imported maps, oldest first
+    maps: RwLock<VecDeque<(Uuid, MapDefinition)>>,
+    /// Agent environments hosted at once
+    pub max_gyms: usize,
+    /// Stepped games for agents, with when each was last stepped
+    gyms: RwLock<HashMap<Uuid, (SharedGym, Instant)>>,
+    /// When each player last posted to the lobby chat
+    chat_sent: std::sync::Mutex<HashMap<Uuid, Instant>>,
+}
+
+impl Lobby {
+    /// A lobby around the server's main game, whose tick loop the caller starts
+    pub fn new(main: Arc<GameSession>) -> Self {
+        let room = Room { name: "Main".to_string(), created_at: Utc::now(), session: main.clone() };
+        Self {
+            main_room: main.id,
+            rooms: RwLock::new(HashMap::from([(main.id, room)])),
+            max_rooms: DEFAULT_MAX_ROOMS,
+            rooms_per_client_per_minute: DEFAULT_ROOMS_PER_CLIENT_PER_MINUTE,
+            room_quota: RoomQuota::default(),
+            max_clients_per_game: DEFAULT_MAX_CLIENTS_PER_GAME,
+            max_territories: MAX_CUSTOM_TERRITORIES,
+            admission: AdmissionStats::default(),
+            store: None,
+            maps: RwLock::new(VecDeque::new()),
+            max_gyms: DEFAULT_MAX_GYMS,
+            gyms: RwLock::new(HashMap::new()),
+            chat_sent: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The game configured at startup, joined by clients that name no room
+    pub async fn main_room(&self) -> Arc<GameSession> {
+        self.rooms.read().await[&self.main_room].session.clone()
+    }
+
+    pub async fn room(&self, room_id: Uuid) -> Option<Arc<GameSession>> {
+        self.rooms.read().await.get(&room_id).map(|room| room.session.clone())
+    }
+
+    /// Every hosted game, the main room included
+    pub async fn sessions(&self) -> Vec<Arc<GameSession>> {
+        self.rooms.read().await.values().map(|room| room.session.clone()).collect()
+    }
+
+    /// All rooms, oldest first
+    pub async fn list(&self) -> Vec<RoomInfo> {
+        let rooms: Vec<(String, DateTime<Utc>, Arc<GameSession>)> = self
+            .rooms
+            .read()
+            .await
+            .values()
+            .map(|room| (room.name.clone(), room.created_at, room.session.clone()))
+            .collect();
+
+        let mut infos = Vec::with_capacity(rooms.len());
+        for (name, created_at, session) in rooms {
+            infos.push(Self::info(name, created_at, &session).await);
+        }
+        infos.sort_by_key(|info| info.created_at);
+        infos
+    }
+
+    /// Open a room and start its game loop; finished rooms are closed to make space
+    pub async fn create(&self, request: CreateRoomRequest) -> Result<RoomInfo> {
+        request.validate()?;
+        let territories = match request.map {
+            Some(map_id) => self.custom_map(map_id).await.map_or(0, |map| map.territories.len()),
+            None => request.territories,
+        };
+        if territories > self.max_territories {
+            return Err(self.admission.rejected(AdmissionError::MapTooLarge { territories, max: self.max_territories }).into());
+        }
+
+        let map_gen = MapGenerator::new(request.territories, request.players)
+            .with_human_slots(request.human_slots)
+            .with_style(request.style)
+            .with_grid(request.grid);
+        let seed = request.seed.unwrap_or_else(rand::random);
+        let state = match request.map {
+            Some(map_id) => {
+                let map = self.custom_map(map_id).await.ok_or_else(|| anyhow!("Unknown map {}", map_id))?;
+                map.build(request.human_slots, seed)?
+            }
+            None => map_gen.generate_seeded(seed),
+        };
+        let mut engine = GameEngine::new(state, ROOM_TICK_RATE_MS);
+        engine.reseed(seed);
+        engine.rules = GameRules::for_difficulty(request.difficulty);
+        engine.rules.allow_late_join = request.allow_late_join;
+
+        let session = self.main_room().await.sibling(engine);
+        let (name, created_at, session) = self.open(session, request.name, Utc::now()).await?;
+        Ok(Self::info(name, created_at, &session).await)
+    }
+
+    /// Keep a validated map for rooms to be created on
+    pub async fn import_map(&self, map: MapDefinition) -> Result<ImportedMap> {
+        map.validate()?;
+        let imported = ImportedMap {
+            map_id: Uuid::new_v4(),
+            name: map.name.clone(),
+            territories: map.territories.len() as u32,
+            players: map.starts.len() as u32,
+        };
+
+        let mut maps = self.maps.write().await;
+        if maps.len() >= MAX_CUSTOM_MAPS {
+            maps.pop_front();
+        }
+        maps.push_back((imported.map_id, map));
+        Ok(imported)
+    }
+
+    pub async fn custom_map(&self, map_id: Uuid) -> Option<MapDefinition> {
+        self.maps.read().await.iter().find(|(id, _)| *id == map_id).map(|(_, map)| map.clone())
+    }
+
+    /// Deal a stepped game for an agent, returning its id and first observation;
+    /// environments left idle are dropped first, but live ones never are
+    pub async fn open_gym(&self, config: &GymConfig) -> Result<(Uuid, Observation)> {
+        if config.territories > self.max_territories {
+            return Err(self.admission.rejected(AdmissionError::MapTooLarge { territories: config.territories, max: self.max_territories }).into());
+        }
+        {
+            let mut gyms = self.gyms.write().await;
+            gyms.retain(|_, (_, stepped)| stepped.elapsed() < GYM_IDLE_TIMEOUT);
+            if gyms.len() >= self.max_gyms {
+                return Err(self.admission.rejected(AdmissionError::GymsFull { max: self.max_gyms }).into());
+            }
+        }
+        let (env, observation) = GymEnv::reset(config)?;
+        let env_id = Uuid::new_v4();
+
+        let mut gyms = self.gyms.write().await;
+        if gyms.len() >= self.max_gyms {
+            return Err(self.admission.rejected(AdmissionError::GymsFull { max: self.max_gyms }).into());
+        }
+        gyms.insert(env_id, (Arc::new(std::sync::Mutex::new(env)), Instant::now()));
+        Ok((env_id, observation))
+    }
+
+    /// An agent's environment, marked as just stepped
+    pub async fn gym(&self, env_id: Uuid) -> Option<SharedGym> {
+        let mut gyms = self.gyms.write().await;
+        let (env, stepped) = gyms.get_mut(&env_id)?;
+        *stepped = Instant::now();
+        Some(env.clone())
+    }
+
+    /// Drop an agent's environment; false if there was none
+    pub async fn close_gym(&self, env_id: Uuid) -> bool {
+        self.gyms.write().await.remove(&env_id).is_some()
+    }
+
+    /// Register a room and start its game loop; finished rooms are closed to make space
+    async fn open(
+        &self,
+        mut session: GameSession,
+        name: Option<String>,
+        created_at: DateTime<Utc>,
+    ) -> Result<(String, DateTime<Utc>, Arc<GameSession>)> {
+        session.idle_timeout = session.idle_timeout.or(Some(ROOM_IDLE_TIMEOUT));
+        let session = Arc::new(session);
+
+        let mut rooms = self.rooms.write().await;
+        if rooms.len() >= self.max_rooms {
+            let finished: Vec<Uuid> = rooms
+                .iter()
+                .filter(|(id, room)| **id != self.main_room && room.session.is_stopped())
+                .map(|(id, _)| *id)
+                .collect();
+            for room_id in finished {
+                if let Some(room) = rooms.remove(&room_id) {
+                    tokio::spawn(async move { Self::archive(&room.session).await });
+                }
+                self.forget(room_id);
+            }
+        }
+        if rooms.len() >= self.max_rooms {
+            return Err(self.admission.rejected(AdmissionError::RoomsFull { max: self.max_rooms }).into());
+        }
+        self.admission.room_admitted();
+        let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| format!("Room {}", rooms.len() + 1));
+        rooms.insert(session.id, Room { name: name.clone(), created_at, session: session.clone() });
+        drop(rooms);
+
+        tracing::info!(game_id = %session.id, "Opened room {}", name);
+        session.clone().start_game_loop().await;
+        Ok((name, created_at, session))
+    }
+
+    /// Save every opened room to the store, and remove finished ones
+    pub async fn save_rooms(&self) {
+        let Some(store) = &self.store else { return };
+        let rooms: Vec<(String, DateTime<Utc>, Arc<GameSession>)> = self
+            .rooms
+            .read()
+            .await
+            .iter()
+            .filter(|(id, _)| **id != self.main_room)
+            .map(|(_, room)| (room.name.clone(), room.created_at, room.session.clone()))
+            .collect();
+
+        for (name, created_at, session) in rooms {
+            if session.is_stopped() {
+                self.forget(session.id);
+                continue;
+            }
+            Self::save_session(store.as_ref(), name, created_at, &session).await;
+        }
+    }
+
+    /// Save a single opened room to the store
+    pub async fn save_room(&self, room_id: Uuid) {
+        let Some(store) = &self.store else { return };
+        let room = self.rooms.read().await.get(&room_id).map(|room| (room.name.clone(), room.created_at, room.session.clone()));
+        if let Some((name, created_at, session)) = room {
+            Self::save_session(store.as_ref(), name, created_at, &session).await;
+        }
+    }
+
+    async fn save_session(store: &dyn Storage, name: String, created_at: DateTime<Utc>, session: &GameSession) {
+        let game = session.engine.read().await.snapshot();
+        let seats = session.seats().into_iter().map(|(token_hash, player)| (token_hash, player.into())).collect();
+        let saved = SavedRoom { room_id: session.id, name, created_at, game, seats };
+        if let Err(e) = store.save_room(&saved).await {
+            tracing::warn!(game_id = %session.id, "Failed to save room: {}", e);
+        }
+    }
+
+    /// Save the rooms every `interval` for as long as the server runs
+    pub async fn save_periodically(self: Arc<Self>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.save_rooms().await;
+        }
+    }
+
+    /// Reopen the rooms saved before the last shutdown, with their reserved seats
+    pub async fn restore_rooms(&self) -> Result<usize> {
+        let Some(store) = &self.store else { return Ok(0) };
+        let main = self.main_room().await;
+        let mut restored = 0;
+        for saved in store.saved_rooms().await? {
+            let engine = match GameEngine::restore(saved.game) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    tracing::warn!(game_id = %saved.room_id, "Could not reopen saved room: {}", e);
+                    continue;
+                }
+            };
+            let mut session = main.sibling(engine);
+            session.id = saved.room_id;
+            for (token_hash, player_id) in saved.seats {
+                session.restore_seat(token_hash, player_id.into());
+            }
+            match self.open(session, Some(saved.name), saved.created_at).await {
+                Ok(_) => restored += 1,
+                Err(e) => tracing::warn!(game_id = %saved.room_id, "Could not reopen saved room: {}", e),
+            }
+        }
+        Ok(restored)
+    }
+
+    /// Archive the rooms whose game is over, abandoned ones included, and drop
+    /// them: after the game's grace period, or at once if it has none. The
+    /// main room is archived after its grace period but stays
+    pub async fn archive_finished(&self) {
+        let due: Vec<Arc<GameSession>> = self
+            .rooms
+            .read()
+            .await
+            .iter()
+            .filter(|(id, room)| {
+                let ended = **id != self.main_room && room.session.archive_after.is_none() && room.session.is_stopped();
+                ended || room.session.archive_due()
+            })
+            .map(|(_, room)| room.session.clone())
+            .collect();
+        for session in due {
+            if !Self::archive(&session).await || session.id == self.main_room {
+                continue;
+            }
+            self.rooms.write().await.remove(&session.id);
+            self.forget(session.id);
+        }
+    }
+
+    /// Look for ended games to archive every `interval` for as long as the server runs
+    pub async fn archive_periodically(self: Arc<Self>, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            self.archive_finished().await;
+        }
+    }
+
+    async fn archive(session: &GameSession) -> bool {
+        match session.archive().await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(game_id = %session.id, "Failed to archive game: {}", e);
+                false
+            }
+        }
+    }
+
+    fn forget(&self, room_id: Uuid) {
+        if let Some(store) = self.store.clone() {
+            tokio::spawn(async move {
+                if let Err(e) = store.remove_room(room_id).await {
+                    tracing::warn!(game_id = %room_id, "Failed to remove saved room: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Let a client open a room unless it has opened its share this minute
+    pub fn admit_room(&self, client: IpAddr) -> Result<(), AdmissionError> {
+        if !self.room_quota.take(client, self.rooms_per_client_per_minute, Instant::now()) {
+            return Err(self.admission.rejected(AdmissionError::RoomQuota { per_minute: self.rooms_per_client_per_minute }));
+        }
+        Ok(())
+    }
+
+    /// Let a connection into a game unless it has as many as it takes; the
+    /// connection holds the slot until it closes
+    pub fn admit_client(&self, session: &GameSession) -> Result<ConnectionSlot, AdmissionError> {
+        let slot = session
+            .reserve_connection(self.max_clients_per_game)
+            .ok_or_else(|| self.admission.rejected(AdmissionError::GameFull { max: self.max_clients_per_game }))?;
+        self.admission.client_admitted();
+        Ok(slot)
+    }
+
+    /// Whether a player may post to the lobby chat now, counting it as a post if so
+    pub fn may_chat(&self, player: Uuid, now: Instant) -> bool {
+        let mut sent = self.chat_sent.lock().unwrap();
+        sent.retain(|_, last| now.duration_since(*last) < LOBBY_CHAT_COOLDOWN);
+        if sent.contains_key(&player) {
+            return false;
+        }
+        sent.insert(player, now);
+        true
+    }
+
+    /// The room and player a session token was issued for
+    pub async fn find_seat(&self, token: &str) -> Option<(Arc<GameSession>, PlayerId)> {
+        let rooms = self.rooms.read().await;
+        rooms.values().find_map(|room| room.session.seat(token).map(|player| (room.session.clone(), player)))
+    }
+
+    /// A solo game against AI with default settings; a token from an earlier
+    /// quick-play game still running returns that game instead
+    pub async fn quickplay(&self, token: Option<&str>) -> Result<Seat> {
+        if let Some(token) = token {
+            if let Some((session, player_id)) = self.find_seat(token).await {
+                if !session.is_stopped() {
+                    return Ok(Seat { room_id: session.id, player_id: player_id.into(), token: token.to_string() });
+                }
+            }
+        }
+
+        let request = CreateRoomRequest {
+            name: Some("Quick play".to_string()),
+            territories: default_territories(),
+            players: default_players(),
+            human_slots: 1,
+            style: MapStyle::default(),
+            grid: MapGrid::default(),
+            seed: None,
+            difficulty: Difficulty::default(),
+            allow_late_join: false,
+            map: None,
+        };
+        let room = self.create(request).await?;
+        let session = self.room(room.room_id).await.ok_or_else(|| anyhow!("Room closed before it could be joined"))?;
+        let player_id = session.unclaimed_slots().await.first().copied().ok_or_else(|| anyhow!("No human slot"))?;
+        let token = session.reserve_seat(player_id);
+        // The token has to outlive a restart right away, not only after the next periodic save
+        self.save_room(room.room_id).await;
+        Ok(Seat { room_id: room.room_id, player_id: player_id.into(), token })
+    }
+
+    async fn info(name: String, created_at: DateTime<Utc>, session: &GameSession) -> RoomInfo {
+        let status = session.status().await;
+        let human_slots = session.engine.read().await.state.players.iter().filter(|p| !p.is_ai).count() as u32;
+        RoomInfo {
+            room_id: session.id,
+            name,
+            created_at,
+            human_slots,
+            open_slots: session.unclaimed_slots().await.len() as u32,
+            connected_clients: status.connected_clients,
+            tick: status.tick,
+            finished: session.is_stopped(),
+            lifecycle: session.lifecycle(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+    use crate::websocket::PendingClient;
+
+    fn request(players: usize) -> CreateRoomRequest {
+        serde_json::from_value(serde_json::json!({ "territories": 20, "players": players })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rooms_run_independent_games() {
+        let main = Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20)));
+        let mut lobby = Lobby::new(main.clone());
+        lobby.max_rooms = 2;
+
+        let room = lobby.create(request(3)).await.unwrap();
+        assert_eq!(room.name, "Room 2");
+        assert_eq!(room.human_slots, 1);
+        assert!(lobby.create(request(3)).await.is_err());
+        assert!(lobby.create(request(1)).await.is_err());
+
+        let session = lobby.room(room.room_id).await.unwrap();
+        assert_ne!(session.id, main.id);
+        assert_eq!(session.engine.read().await.state.players.len(), 3);
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert!(session.engine.read().await.state.tick > 0);
+        assert_eq!(main.engine.read().await.state.tick, 0);
+
+        let listed: Vec<Uuid> = lobby.list().await.iter().map(|r| r.room_id).collect();
+        assert_eq!(listed, [main.id, room.room_id]);
+    }
+
+    #[tokio::test]
+    async fn test_requests_past_the_limits_are_turned_away() {
+        let main = Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20)));
+        let mut lobby = Lobby::new(main.clone());
+        lobby.max_rooms = 2;
+        lobby.max_territories = 30;
+        lobby.max_clients_per_game = 1;
+        lobby.rooms_per_client_per_minute = 2;
+
+        // Each client has its own room quota
+        let (client, other): (IpAddr, IpAddr) = ([10, 0, 0, 1].into(), [10, 0, 0, 2].into());
+        assert!(lobby.admit_room(client).is_ok());
+        assert!(lobby.admit_room(client).is_ok());
+        assert_eq!(lobby.admit_room(client), Err(AdmissionError::RoomQuota { per_minute: 2 }));
+        assert!(lobby.admit_room(other).is_ok());
+
+        let mut huge = request(3);
+        huge.territories = 10_000;
+        let error = lobby.create(huge).await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&AdmissionError::MapTooLarge { territories: 10_000, max: 30 }));
+        lobby.create(request(3)).await.unwrap();
+        let error = lobby.create(request(3)).await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&AdmissionError::RoomsFull { max: 2 }));
+
+        // A connection counts from its admission, before it has joined
+        let slot = lobby.admit_client(&main).unwrap();
+        assert_eq!(lobby.admit_client(&main).err(), Some(AdmissionError::GameFull { max: 1 }));
+        let client = PendingClient { connection_id: Uuid::new_v4(), tx: crate::websocket::outbox::Outbox::new().0, update_rate: None };
+        main.join(&client, None, None, None, None).await.unwrap();
+        drop(slot);
+        assert!(lobby.admit_client(&main).is_ok());
+
+        assert_eq!(lobby.admission.admitted(), [("room", 1), ("client", 2)]);
+        assert_eq!(lobby.admission.rejections(), [("rooms_full", 1), ("map_too_large", 1), ("game_full", 1), ("gyms_full", 0), ("room_quota", 1)]);
+    }
+
+    #[tokio::test]
+    async fn test_full_gym_turns_agents_away_instead_of_dropping_one() {
+        let mut lobby = Lobby::new(Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20))));
+        lobby.max_gyms = 2;
+        let config: GymConfig = serde_json::from_str(r#"{"seed": 1, "territories": 20, "players": 2}"#).unwrap();
+
+        let (first, _) = lobby.open_gym(&config).await.unwrap();
+        let (second, _) = lobby.open_gym(&config).await.unwrap();
+        let error = lobby.open_gym(&config).await.unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&AdmissionError::GymsFull { max: 2 }));
+        assert!(lobby.gym(first).await.is_some());
+        assert!(lobby.gym(second).await.is_some());
+
+        // Closing one makes space again
+        assert!(lobby.close_gym(first).await);
+        lobby.open_gym(&config).await.unwrap();
+        assert_eq!(lobby.admission.rejections()[3], ("gyms_full", 1));
+    }
+
+    #[tokio::test]
+    async fn test_rooms_play_on_imported_maps() {
+        let lobby = Lobby::new(Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20))));
+        let map: MapDefinition = serde_json::from_value(serde_json::json!({
+            "name": "Duel",
+            "territories": [
+                { "key": "west", "terrain": "plains", "neighbors": ["middle"] },
+                { "key": "middle", "terrain": "mountains", "neighbors": ["west", "east"] },
+                { "key": "east", "terrain": "plains", "neighbors": ["middle"] },
+            ],
+            "starts": ["west", "east"],
+        }))
+        .unwrap();
+        let mut broken = map.clone();
+        broken.territories[2].neighbors.clear();
+        assert!(lobby.import_map(broken).await.is_err());
+
+        let imported = lobby.import_map(map).await.unwrap();
+        assert_eq!((imported.territories, imported.players), (3, 2));
+        let mut request = request(1);
+        request.map = Some(imported.map_id);
+        assert!(request.validate().is_ok());
+        let room = lobby.create(request.clone()).await.unwrap();
+
+        let session = lobby.room(room.room_id).await.unwrap();
+        let engine = session.engine.read().await;
+        assert_eq!(engine.state.players.len(), 2);
+        assert_eq!(engine.state.territories.len(), 3);
+        request.map = Some(Uuid::new_v4());
+        assert!(lobby.create(request).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_rooms_without_a_grace_period_are_dropped() {
+        let mut main = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        main.idle_timeout = Some(Duration::from_millis(50));
+        let lobby = Lobby::new(Arc::new(main));
+
+        let room = lobby.create(request(3)).await.unwrap();
+        let session = lobby.room(room.room_id).await.unwrap();
+        lobby.archive_finished().await;
+        assert!(lobby.room(room.room_id).await.is_some());
+
+        for _ in 0..100 {
+            if session.is_stopped() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        lobby.archive_finished().await;
+        assert!(lobby.room(room.room_id).await.is_none());
+        assert!(lobby.room(lobby.main_room).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_finished_rooms_are_archived_after_their_grace_period() {
+        let mut main = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        let storage = Arc::new(crate::storage::MemoryStorage::default());
+        main.storage = Some(storage.clone());
+        main.idle_timeout = Some(Duration::from_millis(50));
+        main.archive_after = Some(Duration::from_millis(300));
+        let lobby = Lobby::new(Arc::new(main));
+
+        // Nobody joins, so the game is ended as abandoned but kept for its grace period
+        let room = lobby.create(request(3)).await.unwrap();
+        let session = lobby.room(room.room_id).await.unwrap();
+        for _ in 0..100 {
+            if session.lifecycle() == Lifecycle::Finished {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(session.lifecycle(), Lifecycle::Finished);
+        lobby.archive_finished().await;
+        assert!(lobby.room(room.room_id).await.is_some());
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        lobby.archive_finished().await;
+        assert!(lobby.room(room.room_id).await.is_none());
+        assert_eq!(session.lifecycle(), Lifecycle::Archived);
+        let archive = storage.archived_game(room.room_id).await.unwrap().unwrap();
+        assert_eq!(archive.state.tick, session.engine.read().await.state.tick);
+        assert_eq!(archive.replay.unwrap().game_id, room.room_id);
+        assert!(session.archive().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_saved_rooms_and_seats_survive_a_restart() {
+        let store: Arc<dyn Storage> = Arc::new(MemoryStorage::default());
+        let main = || Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20)));
+
+        let mut lobby = Lobby::new(main());
+        lobby.store = Some(store.clone());
+        lobby.create(request(3)).await.unwrap();
+        let seat = lobby.quickplay(None).await.unwrap();
+        let state = lobby.room(seat.room_id).await.unwrap().engine.read().await.state.clone();
+
+        // Quick play saves only its own room; the others wait for the periodic save
+        let saved = store.saved_rooms().await.unwrap();
+        assert_eq!(saved.iter().map(|room| room.room_id).collect::<Vec<_>>(), vec![seat.room_id]);
+
+        // Tokens are only kept hashed
+        let saved = store.saved_rooms().await.unwrap();
+        assert!(!serde_json::to_string(&saved).unwrap().contains(&seat.token));
+
+        // A new server process on the same store
+        let mut restarted = Lobby::new(main());
+        restarted.store = Some(store);
+        assert_eq!(restarted.restore_rooms().await.unwrap(), 1);
+
+        let (session, player_id) = restarted.find_seat(&seat.token).await.unwrap();
+        assert_eq!(session.id, seat.room_id);
+        assert_eq!(Uuid::from(player_id), seat.player_id);
+        let engine = session.engine.read().await;
+        assert!(engine.state.tick >= state.tick);
+        let ids = |state: &GameState| state.players.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(&engine.state), ids(&state));
+        drop(engine);
+        let resumed = restarted.quickplay(Some(&seat.token)).await.unwrap();
+        assert_eq!(resumed.room_id, seat.room_id);
+    }
+
+    #[test]
+    fn test_lobby_chat_cooldown_is_per_player() {
+        let lobby = Lobby::new(Arc::new(GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20))));
+        let (alice, bob, start) = (Uuid::new_v4(), Uuid::new_v4(), Instant::now());
+        assert!(lobby.may_chat(alice, start));
+        assert!(!lobby.may_chat(alice, start + Duration::from_secs(1)));
+        assert!(lobby.may_chat(bob, start + Duration::from_secs(1)));
+        assert!(lobby.may_chat(alice, start + LOBBY_CHAT_COOLDOWN));
+    }
+}
@@ -1,37 +1,156 @@
+mod admission;
+mod api;
+mod audit;
+mod backplane;
+mod community;
 mod types;
 mod game;
+mod i18n;
+mod ladder;
+mod lobby;
+mod msgpack;
+mod otlp;
+mod protobuf;
+mod room_store;
+mod storage;
+mod webhook;
 mod websocket;
+#[cfg(test)]
+mod test_support;
 
 use axum::{
-    routing::get,
+    routing::{delete, get, post},
     Router,
 };
 use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
-use tracing_subscriber;
+use tracing_subscriber::filter::{EnvFilter, LevelFilter};
+use tracing_subscriber::prelude::*;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
-use game::{GameEngine, MapGenerator};
-use websocket::{GameSession, websocket_handler};
+use game::{GameEngine, GameRules, MapGenerator};
+use websocket::{
+    admin_websocket_handler, replay_websocket_handler, spectate_websocket_handler, websocket_handler, AdminHub,
+    CommandRateLimit, GameSession, RateLimiter,
+};
 use types::*;
 
 #[derive(OpenApi)]
 #[openapi(
+    paths(
+        api::metrics_handler,
+        api::dashboard_handler,
+        api::cpu_profile_handler,
+        api::list_games_handler,
+        api::game_summary_handler,
+        api::game_map_handler,
+        api::ai_decisions_handler,
+        api::ladder_handler,
+        api::profile_handler,
+        api::lobby_chat_handler,
+        api::post_lobby_chat_handler,
+        api::list_matches_handler,
+        api::map_preview_handler,
+        api::import_map_handler,
+        api::list_rooms_handler,
+        api::create_room_handler,
+        api::quickplay_handler,
+        api::run_simulations_handler,
+        api::open_gym_handler,
+        api::gym_step_handler,
+        api::close_gym_handler,
+    ),
     components(schemas(
         // Entity types
         Territory,
+        Region,
+        Chokepoint,
+        Border,
+        TraversalRules,
+        Diplomacy,
+        Treaty,
+        TreatyOffer,
+        Truce,
         Player,
         TerrainType,
         BuildingType,
+        Tech,
+        ResearchProgress,
+        UnitType,
+        Units,
+        UnitMix,
+        RatioPreset,
         AIPersonality,
         GameState,
+        VictoryProgress,
+        Battle,
         CombatResult,
+        AiDecision,
+        AiDecisionKind,
+        AiOption,
         GameStats,
         NotificationLevel,
+        PerfStats,
+        ConnectionStats,
+        SectionTiming,
         // Message types
         ClientMessage,
+        Order,
         ServerMessage,
+        QuickChatId,
+        NotificationKey,
+        AdminEvent,
+        GameEndReason,
+        GameStatus,
+        StateDelta,
+        GameListing,
+        GameSummary,
+        TimelineEntry,
+        EventCategory,
+        EntityRef,
+        BattleHighlight,
+        TerritoryStats,
+        MapStyle,
+        MapGrid,
+        MapOption,
+        RuleOption,
+        RuleTally,
+        DraftStatus,
+        DraftResult,
+        ladder::Leaderboard,
+        lobby::RoomInfo,
+        Lifecycle,
+        lobby::CreateRoomRequest,
+        api::QuickplayRequest,
+        api::QuickplayResponse,
+        game::Difficulty,
+        ladder::LadderEntry,
+        // Profiles and lobby chat
+        community::Profile,
+        community::MatchRecord,
+        community::ChatMessage,
+        api::ChatPost,
+        // Match history
+        storage::MatchResult,
+        ladder::MatchParticipant,
+        // Balance sweeps
+        game::simulation::SweepRequest,
+        game::simulation::SweepSummary,
+        // Agent environments
+        game::gym::GymConfig,
+        game::gym::Observation,
+        game::gym::StepRequest,
+        game::gym::StepResult,
+        game::gym::ActionError,
+        api::GymOpened,
+        // Map analysis
+        api::MapPreview,
+        game::custom_map::MapDefinition,
+        game::custom_map::TerritoryDefinition,
+        lobby::ImportedMap,
+        game::fairness::FairnessReport,
+        game::fairness::StartReport,
     )),
     tags(
         (name = "strategy-game", description = "Strategy game API")
@@ -39,43 +158,439 @@ use types::*;
 )]
 struct ApiDoc;
 
+/// Build the HTTP/WebSocket router for the lobby's rooms
+fn app(lobby: Arc<lobby::Lobby>) -> Router {
+    // CORS configuration
+    let cors = CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any);
+
+    Router::new()
+        .route("/ws", get(websocket_handler))
+        .route("/ws/admin", get(admin_websocket_handler))
+        .route("/ws/spectate/:game_id", get(spectate_websocket_handler))
+        .route("/ws/replay/:game_id", get(replay_websocket_handler))
+        .route("/games", get(api::list_games_handler))
+        .route("/games/:game_id/summary", get(api::game_summary_handler))
+        .route("/games/:game_id/map", get(api::game_map_handler))
+        .route("/games/:game_id/ai/:player_id/last_decisions", get(api::ai_decisions_handler))
+        .route("/ladder", get(api::ladder_handler))
+        .route("/profiles/:player", get(api::profile_handler))
+        .route("/lobby/chat", get(api::lobby_chat_handler).post(api::post_lobby_chat_handler))
+        .route("/matches", get(api::list_matches_handler))
+        .route("/rooms", get(api::list_rooms_handler).post(api::create_room_handler))
+        .route("/quickplay", post(api::quickplay_handler))
+        .route("/maps", post(api::import_map_handler))
+        .route("/maps/preview", get(api::map_preview_handler))
+        .route("/metrics", get(api::metrics_handler))
+        .route("/dashboard", get(api::dashboard_handler))
+        .route("/debug/pprof/profile", get(api::cpu_profile_handler))
+        .route("/simulations", post(api::run_simulations_handler))
+        .route("/gym", post(api::open_gym_handler))
+        .route("/gym/:env_id/step", post(api::gym_step_handler))
+        .route("/gym/:env_id", delete(api::close_gym_handler))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+        .layer(cors)
+        .with_state(lobby)
+}
+
+/// `benchmark [--games 10] [--seed 0] [--max-ticks 6000] [--territories 30]
+/// [--save report.json] [--baseline report.json] [--tolerance 0.1]`: duel the AI
+/// personalities headlessly and print the win-rate matrix. With `--baseline`,
+/// fails if any matchup's win rate moved more than the tolerance
+fn benchmark(args: &[String]) -> anyhow::Result<bool> {
+    use anyhow::{anyhow, Context};
+    use game::simulation::{run_benchmark, BenchmarkReport, BenchmarkRequest};
+
+    let mut request = BenchmarkRequest::default();
+    let (mut save, mut baseline, mut tolerance) = (None, None, 0.1);
+    for pair in args.chunks(2) {
+        let value = pair.get(1).ok_or_else(|| anyhow!("{} expects a value", pair[0]))?;
+        match pair[0].as_str() {
+            "--games" => request.games_per_pair = value.parse().context("--games expects a number")?,
+            "--seed" => request.seed = value.parse().context("--seed expects a number")?,
+            "--max-ticks" => request.max_ticks = value.parse().context("--max-ticks expects a number")?,
+            "--territories" => request.territories = value.parse().context("--territories expects a number")?,
+            "--save" => save = Some(value.clone()),
+            "--baseline" => baseline = Some(value.clone()),
+            "--tolerance" => tolerance = value.parse().context("--tolerance expects a fraction")?,
+            other => return Err(anyhow!("Unknown argument: {}", other)),
+        }
+    }
+
+    let report = run_benchmark(&request)?;
+    print!("{}", report.to_table());
+    if let Some(path) = save {
+        std::fs::write(&path, serde_json::to_string_pretty(&report)?).with_context(|| format!("Couldn't write {}", path))?;
+    }
+    let Some(path) = baseline else {
+        return Ok(true);
+    };
+    let baseline: BenchmarkReport = serde_json::from_str(&std::fs::read_to_string(&path).with_context(|| format!("Couldn't read {}", path))?)
+        .with_context(|| format!("{} is not a benchmark report", path))?;
+    if (baseline.games_per_pair, baseline.seed, baseline.max_ticks, baseline.territories)
+        != (report.games_per_pair, report.seed, report.max_ticks, report.territories)
+    {
+        return Err(anyhow!("{} was played with different settings", path));
+    }
+    let moved = report.regressions(&baseline, tolerance);
+    if moved.is_empty() {
+        println!("\nNo win rate moved more than {:.0}% since {}", tolerance * 100.0, path);
+    } else {
+        println!("\nWin rates that moved more than {:.0}% since {}:", tolerance * 100.0, path);
+        for line in &moved {
+            println!("  {}", line);
+        }
+    }
+    Ok(moved.is_empty())
+}
+
 #[tokio::main]
 async fn main() {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    if std::env::args().nth(1).as_deref() == Some("benchmark") {
+        let args: Vec<String> = std::env::args().skip(2).collect();
+        match benchmark(&args) {
+            Ok(true) => return,
+            Ok(false) => std::process::exit(1),
+            Err(e) => {
+                eprintln!("{:#}", e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // Initialize tracing, exporting spans over OTLP when a collector is configured
+    let otlp = std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT")
+        .and_then(|_| otlp::layer().map_err(|e| eprintln!("OTLP export disabled: {:#}", e)).ok())
+        .map(|layer| layer.with_filter(LevelFilter::INFO));
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_filter(EnvFilter::from_default_env()))
+        .with(otlp)
+        .init();
 
     // Generate game
-    let map_gen = MapGenerator::new(75, 9); // 75 territories, 9 players (1 human + 8 AI)
-    let initial_state = map_gen.generate();
+    // HUMAN_SLOTS=N reserves the first N of the 9 players for humans
+    let human_slots = std::env::var("HUMAN_SLOTS").ok().and_then(|n| n.parse().ok()).unwrap_or(1);
+    // MAP_STYLE=scattered|continents|islands|pangaea
+    let map_style = std::env::var("MAP_STYLE")
+        .ok()
+        .and_then(|s| s.parse().map_err(|e| tracing::error!("{}", e)).ok())
+        .unwrap_or_default();
+    // MAP_GRID=voronoi|hex
+    let map_grid = std::env::var("MAP_GRID")
+        .ok()
+        .and_then(|s| s.parse().map_err(|e| tracing::error!("{}", e)).ok())
+        .unwrap_or_default();
+    // GAME_SEED=N regenerates the same map and plays out the same random choices
+    let seed = std::env::var("GAME_SEED")
+        .ok()
+        .and_then(|s| s.parse().map_err(|e| tracing::error!("Invalid GAME_SEED: {}", e)).ok())
+        .unwrap_or_else(rand::random);
+    tracing::info!(seed, "Generating map");
+    let map_gen = MapGenerator::new(75, 9) // 75 territories, 9 players
+        .with_human_slots(human_slots)
+        .with_style(map_style)
+        .with_grid(map_grid)
+        .with_seed(seed);
+    // MIN_MAP_FAIRNESS=0..1 rerolls the map (up to 50 times) until its starts are that balanced
+    let initial_state = match std::env::var("MIN_MAP_FAIRNESS").ok().and_then(|s| s.parse().ok()) {
+        Some(min_score) => {
+            let (state, report) = game::fairness::generate_fair(&map_gen, min_score, 50);
+            if report.score < min_score {
+                tracing::warn!("No map reached fairness {}, using the fairest at {:.2}", min_score, report.score);
+            }
+            state
+        }
+        None => map_gen.generate(),
+    };
+    // MAP_FILE=map.json plays the main game on a hand-made map, in the format `POST /maps` takes
+    let initial_state = match std::env::var("MAP_FILE") {
+        Ok(path) => match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(serde_json::from_str::<game::custom_map::MapDefinition>(&json)?))
+            .and_then(|map| map.build(human_slots, seed))
+        {
+            Ok(state) => state,
+            Err(e) => {
+                tracing::error!("Failed to load map {}, using a generated one: {}", path, e);
+                initial_state
+            }
+        },
+        Err(_) => initial_state,
+    };
 
     // Create game engine
-    let engine = GameEngine::new(initial_state, 100); // 100ms tick rate
+    // GAME_DIFFICULTY=easy|medium|hard
+    let difficulty = std::env::var("GAME_DIFFICULTY")
+        .ok()
+        .and_then(|d| d.parse().map_err(|e| tracing::error!("{}", e)).ok())
+        .unwrap_or_default();
+    let mut engine = GameEngine::new(initial_state, 100); // 100ms tick rate
+    engine.reseed(seed);
+    engine.rules = GameRules::for_difficulty(difficulty);
+    engine.rules.allow_late_join = std::env::var("ALLOW_LATE_JOIN").is_ok();
+    // PASSABLE_WATER=1 lets water be attacked and held like land
+    engine.rules.passable_water = std::env::var("PASSABLE_WATER").is_ok();
+    // Water territories an attack from a Harbor can cross
+    if let Some(range) = std::env::var("NAVAL_RANGE").ok().and_then(|r| r.parse().ok()) {
+        engine.rules.naval_range = range;
+    }
+    // Ticks each attack is fought over, e.g. 5; unset settles attacks at once
+    if let Some(rounds) = std::env::var("BATTLE_ROUNDS").ok().and_then(|r| r.parse().ok()) {
+        engine.rules.battle_rounds = rounds;
+    }
+    // Ticks an attack on a Defense Post besieges it before assaulting, e.g. 300
+    if let Some(ticks) = std::env::var("SIEGE_TICKS").ok().and_then(|t| t.parse().ok()) {
+        engine.rules.siege_ticks = ticks;
+    }
+    // Share of an unrelieved besieged garrison lost per second, e.g. 0.05
+    if let Some(attrition) = std::env::var("SIEGE_ATTRITION").ok().and_then(|a| a.parse().ok()) {
+        engine.rules.siege_attrition_per_second = attrition;
+    }
+    // Share of a retreating stack lost on the way home, e.g. 0.2
+    if let Some(losses) = std::env::var("RETREAT_LOSSES").ok().and_then(|l| l.parse().ok()) {
+        engine.rules.retreat_losses = losses;
+    }
+    // Factor on the defender's losses when attacked across an unbridged river, e.g. 0.7
+    if let Some(multiplier) = std::env::var("RIVER_DEFENSE").ok().and_then(|m| m.parse().ok()) {
+        engine.rules.river_defense_multiplier = multiplier;
+    }
+    // CAPTURED_BUILDINGS=keep|raze
+    if let Ok(rule) = std::env::var("CAPTURED_BUILDINGS") {
+        match rule.parse() {
+            Ok(rule) => engine.rules.captured_buildings = rule,
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+    // Ticks a captured building does nothing for its new owner, e.g. 300
+    if let Some(ticks) = std::env::var("BUILDING_CAPTURE_TICKS").ok().and_then(|t| t.parse().ok()) {
+        engine.rules.building_capture_ticks = ticks;
+    }
+    // CAPITAL_LOSS=nothing|eliminate|halve_income
+    if let Ok(rule) = std::env::var("CAPITAL_LOSS") {
+        match rule.parse() {
+            Ok(rule) => engine.rules.capital_loss = rule,
+            Err(e) => tracing::error!("{}", e),
+        }
+    }
+    // Pauses each player may call in a game, e.g. 3
+    if let Some(pauses) = std::env::var("PAUSES_PER_PLAYER").ok().and_then(|p| p.parse().ok()) {
+        engine.rules.pauses_per_player = Some(pauses);
+    }
+    // LOCK_GAME_SPEED=1 keeps the speed the game started with
+    engine.rules.lock_speed_after_start = std::env::var("LOCK_GAME_SPEED").is_ok();
+    // COMBAT_MODEL=threshold|lanchester|dice, or any other registered model
+    if let Ok(model) = std::env::var("COMBAT_MODEL") {
+        if let Err(e) = engine.rules.set_combat_model(&model) {
+            tracing::error!("{}", e);
+        }
+    }
+    // Luck in combat as a fraction of each side's strength, e.g. 0.15 for ±15%
+    if let Some(variance) = std::env::var("COMBAT_VARIANCE").ok().and_then(|v| v.parse().ok()) {
+        engine.rules.combat_variance = variance;
+    }
+
+    // Rule plugins, e.g. GAME_PLUGINS="peace_time=600,domination=0.6,rules/koth.wasm"
+    for spec in std::env::var("GAME_PLUGINS").unwrap_or_default().split(',').filter(|s| !s.trim().is_empty()) {
+        let plugin = if spec.trim().ends_with(".wasm") {
+            game::wasm_plugin::WasmPlugin::load(spec.trim()).map(|p| Box::new(p) as Box<dyn game::plugins::RulePlugin>)
+        } else {
+            game::plugins::builtin_plugin(spec)
+        };
+        match plugin {
+            Ok(plugin) => engine.plugins.register(plugin),
+            Err(e) => tracing::error!("Skipping plugin {}: {}", spec, e),
+        }
+    }
+    // Scenario scripts in Rhai, see game/scripting.rs for the functions they get
+    if let Ok(path) = std::env::var("SCENARIO_SCRIPT") {
+        let script = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|source| game::scripting::ScenarioScript::parse(&source));
+        match script {
+            Ok(script) => engine.plugins.register(Box::new(script)),
+            Err(e) => tracing::error!("Failed to load scenario script {}: {}", path, e),
+        }
+    }
+
+    if !engine.plugins.is_empty() {
+        tracing::info!("Rule plugins enabled: {}", engine.plugins.active().join(", "));
+    }
 
     // Create game session
-    let game_session = Arc::new(GameSession::new(engine));
+    let mut game_session = GameSession::new(engine);
+    game_session.debug = std::env::var("GAME_DEBUG").is_ok();
+    game_session.admin = Arc::new(AdminHub::new(std::env::var("ADMIN_TOKEN").ok()));
+    if let Ok(dir) = std::env::var("AUDIT_LOG_DIR") {
+        // 10 MB per file, 10 rotated files
+        match audit::AuditLog::open(dir, 10 * 1024 * 1024, 10) {
+            Ok(log) => game_session.audit = Some(log),
+            Err(e) => tracing::error!("Failed to open audit log: {}", e),
+        }
+    }
+    // Empty human slots go to AI once LOBBY_WAIT_SECONDS have passed
+    game_session.lobby_wait = std::env::var("LOBBY_WAIT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(std::time::Duration::from_secs);
+    // START_PICK_SECONDS gives each human that long to pick a starting territory
+    game_session.start_pick_time = std::env::var("START_PICK_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(std::time::Duration::from_secs);
+    // DRAFT_MAPS=continents:7,scattered:12 lets the lobby veto maps, and
+    // DRAFT_RULES=late_join,dice_combat vote on rules, until the host starts
+    if let Ok(maps) = std::env::var("DRAFT_MAPS") {
+        let maps: Vec<MapOption> =
+            maps.split(',').filter_map(|m| m.parse().map_err(|e| tracing::error!("{}", e)).ok()).collect();
+        let rules: Vec<RuleOption> = std::env::var("DRAFT_RULES")
+            .unwrap_or_default()
+            .split(',')
+            .filter(|r| !r.is_empty())
+            .filter_map(|r| r.parse().map_err(|e| tracing::error!("{}", e)).ok())
+            .collect();
+        let host = game_session.engine.read().await.host();
+        match host.ok_or_else(|| anyhow::anyhow!("A draft needs a human host")).and_then(|host| {
+            game::draft::Draft::new(host, maps, &rules)
+        }) {
+            Ok(draft) => game_session.draft = Some(std::sync::Mutex::new(draft)),
+            Err(e) => tracing::error!("Draft disabled: {}", e),
+        }
+    }
+    // Games without connected players are ended after IDLE_GAME_TIMEOUT_SECONDS,
+    // or with FINISH_IDLE_GAMES set, played out by the AI
+    game_session.idle_timeout = std::env::var("IDLE_GAME_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(std::time::Duration::from_secs);
+    game_session.finish_idle_headless = std::env::var("FINISH_IDLE_GAMES").is_ok();
+    // SLEEP_EMPTY_GAMES=1 stops simulating games whose human players have all disconnected
+    game_session.sleep_when_empty = std::env::var("SLEEP_EMPTY_GAMES").is_ok();
+    game_session.summary_webhook = std::env::var("GAME_SUMMARY_WEBHOOK_URL").ok();
+    // Finished games keep their state, replay and summary in memory for
+    // ARCHIVE_AFTER_SECONDS, then are archived to the match storage and let go of
+    game_session.archive_after = std::env::var("ARCHIVE_AFTER_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(std::time::Duration::from_secs);
+    // Finished games are recorded in the Postgres database at DATABASE_URL, or
+    // kept in memory until the server stops
+    let match_storage: Arc<dyn storage::Storage> = match std::env::var("DATABASE_URL") {
+        Ok(url) => match storage::PostgresStorage::connect(&url).await {
+            Ok(storage) => Arc::new(storage),
+            Err(e) => {
+                tracing::error!("Failed to connect to the match database, keeping matches in memory: {}", e);
+                Arc::new(storage::MemoryStorage::default())
+            }
+        },
+        Err(_) => Arc::new(storage::MemoryStorage::default()),
+    };
+    // LADDER=1 runs a ranked ladder kept in the match storage, with
+    // SEASON_LENGTH_DAYS long seasons (default 30)
+    if std::env::var("LADDER").is_ok() {
+        let days = std::env::var("SEASON_LENGTH_DAYS").ok().and_then(|d| d.parse().ok()).unwrap_or(30);
+        match ladder::Ladder::open(match_storage.clone(), chrono::Duration::days(days)).await {
+            Ok(ladder) => game_session.ladder = Some(Arc::new(ladder)),
+            Err(e) => tracing::error!("Failed to load the ladder: {}", e),
+        }
+    }
+    // PROFILES=1 keeps player profiles with match history in the match storage
+    if std::env::var("PROFILES").is_ok() {
+        match community::Community::open(match_storage.clone()).await {
+            Ok(community) => game_session.community = Some(Arc::new(community)),
+            Err(e) => tracing::error!("Failed to load player profiles: {}", e),
+        }
+    }
+    game_session.storage = Some(match_storage.clone());
+    // COMMAND_RATE_LIMIT commands per second per connection; connections are closed
+    // after COMMAND_RATE_VIOLATIONS rejected commands (default 100)
+    if let Some(per_second) = std::env::var("COMMAND_RATE_LIMIT").ok().and_then(|n| n.parse().ok()) {
+        let max_violations = std::env::var("COMMAND_RATE_VIOLATIONS").ok().and_then(|n| n.parse().ok()).unwrap_or(100);
+        game_session.rate_limiter = Some(RateLimiter::new(CommandRateLimit { per_second, max_violations }));
+    }
+    // Clients pick their state update rate within MIN_UPDATE_RATE and MAX_UPDATE_RATE
+    // updates per second (default 0.5 and 10)
+    if let Some(rate) = std::env::var("MIN_UPDATE_RATE").ok().and_then(|r| r.parse().ok()) {
+        game_session.min_update_rate = rate;
+    }
+    if let Some(rate) = std::env::var("MAX_UPDATE_RATE").ok().and_then(|r| r.parse().ok()) {
+        game_session.max_update_rate = rate;
+    }
+    game_session.crash_dir = std::env::var("CRASH_SNAPSHOT_DIR").ok().map(std::path::PathBuf::from);
+    // The host can save and load games by name in SAVE_DIR
+    game_session.save_dir = std::env::var("SAVE_DIR").ok().map(std::path::PathBuf::from);
+    // Finished games are written to REPLAY_DIR/<game id>.replay
+    game_session.replay_dir = std::env::var("REPLAY_DIR").ok().map(std::path::PathBuf::from);
+    // Spectators watch SPECTATOR_DELAY_SECONDS behind live play
+    game_session.spectator_delay = std::env::var("SPECTATOR_DELAY_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(std::time::Duration::from_secs);
+    if let Ok(url) = std::env::var("BACKPLANE_URL") {
+        let instance_id = std::env::var("INSTANCE_ID").unwrap_or_else(|_| uuid::Uuid::new_v4().to_string());
+        match backplane::Backplane::redis(&url, instance_id).await {
+            Ok(backplane) => game_session.backplane = Arc::new(backplane),
+            Err(e) => tracing::error!("Failed to connect to backplane, running standalone: {}", e),
+        }
+    }
+    let game_session = Arc::new(game_session);
 
     // Start game loop
     game_session.clone().start_game_loop().await;
 
-    // CORS configuration
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
-
-    // Build router
-    let app = Router::new()
-        .route("/ws", get(websocket_handler))
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .layer(cors)
-        .with_state(game_session);
+    // Build router; clients can open more rooms next to the main game, up to MAX_ROOMS
+    let mut lobby = lobby::Lobby::new(game_session);
+    if let Some(max_rooms) = std::env::var("MAX_ROOMS").ok().and_then(|n| n.parse().ok()) {
+        lobby.max_rooms = max_rooms;
+    }
+    // Rooms and quick-play games each client may open a minute, ROOMS_PER_CLIENT_PER_MINUTE (default 5)
+    if let Some(per_minute) = std::env::var("ROOMS_PER_CLIENT_PER_MINUTE").ok().and_then(|n| n.parse().ok()) {
+        lobby.rooms_per_client_per_minute = per_minute;
+    }
+    // Player connections per game, MAX_CLIENTS_PER_GAME (default 64), and the
+    // biggest map a room or preview may ask for, MAX_TERRITORIES (default 1000)
+    if let Some(max_clients) = std::env::var("MAX_CLIENTS_PER_GAME").ok().and_then(|n| n.parse().ok()) {
+        lobby.max_clients_per_game = max_clients;
+    }
+    if let Some(max_territories) = std::env::var("MAX_TERRITORIES").ok().and_then(|n| n.parse().ok()) {
+        lobby.max_territories = max_territories;
+    }
+    // Agent environments hosted at once, MAX_GYMS (default 32)
+    if let Some(max_gyms) = std::env::var("MAX_GYMS").ok().and_then(|n| n.parse().ok()) {
+        lobby.max_gyms = max_gyms;
+    }
+    // SAVE_ROOMS=1 saves opened rooms to the match storage every ROOM_SAVE_SECONDS
+    // (default 10) and reopens them on startup
+    if std::env::var("SAVE_ROOMS").is_ok() {
+        if std::env::var("DATABASE_URL").is_err() {
+            tracing::warn!("SAVE_ROOMS keeps rooms in memory without DATABASE_URL, so they won't survive a restart");
+        }
+        lobby.store = Some(match_storage);
+    }
+    match lobby.restore_rooms().await {
+        Ok(0) => {}
+        Ok(restored) => tracing::info!("Reopened {} saved rooms", restored),
+        Err(e) => tracing::error!("Failed to reopen saved rooms: {}", e),
+    }
+    let lobby = Arc::new(lobby);
+    if lobby.store.is_some() {
+        let every = std::env::var("ROOM_SAVE_SECONDS").ok().and_then(|s| s.parse().ok()).unwrap_or(10);
+        tokio::spawn(lobby.clone().save_periodically(std::time::Duration::from_secs(every)));
+    }
+    // Ended rooms are archived and dropped after their grace period, or within a minute without one
+    let grace = lobby.main_room().await.archive_after.unwrap_or(std::time::Duration::MAX);
+    tokio::spawn(lobby.clone().archive_periodically(grace.clamp(std::time::Duration::from_secs(1), std::time::Duration::from_secs(60))));
+    let app = app(lobby);
 
     // Start server
     let addr = "0.0.0.0:3000";
     println!("🎮 Strategy Game Server running on {}", addr);
     println!("📚 Swagger UI: http://localhost:3000/swagger-ui");
+    println!("📊 Dashboard: http://localhost:3000/dashboard?token=<ADMIN_TOKEN>");
     println!("🔌 WebSocket: ws://localhost:3000/ws");
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    // Client addresses key the per-client room quota
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await.unwrap();
 }
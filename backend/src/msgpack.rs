@@ -0,0 +1,50 @@
+//! MessagePack encoding for outgoing WebSocket messages.
+//!
+//! Values are laid out the way `serde_json` lays them out: structs become
+//! maps keyed by field name, enums keep their serde tags and ids stay
+//! strings, so a client decodes the same objects from either format. Only
+//! encoding is needed; clients keep sending their commands as JSON.
+
+use serde::Serialize;
+
+/// Encode a value as MessagePack
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut out = Vec::new();
+    value.serialize(&mut rmp_serde::Serializer::new(&mut out).with_struct_map().with_human_readable())?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    use crate::game::MapGenerator;
+    use crate::test_support::decode_msgpack;
+    use crate::types::*;
+
+    #[test]
+    fn test_messages_decode_to_their_json_shape() {
+        let state = MapGenerator::new(20, 4).generate();
+        let messages = [
+            ServerMessage::state_update(state),
+            ServerMessage::Error { message: "x".repeat(40) },
+            ServerMessage::Pong { nonce: 7, server_tick: 300 },
+        ];
+        for message in messages {
+            let bytes = to_vec(&message).unwrap();
+            assert_eq!(decode_msgpack(&bytes), serde_json::to_value(&message).unwrap());
+        }
+
+        // Flattened maps have no length up front
+        #[derive(serde::Serialize)]
+        struct Flat {
+            kind: &'static str,
+            #[serde(flatten)]
+            extra: HashMap<String, Vec<i32>>,
+        }
+        let flat = Flat { kind: "flat", extra: HashMap::from([("a".into(), vec![-1, 2]), ("b".into(), vec![])]) };
+        assert_eq!(decode_msgpack(&to_vec(&flat).unwrap()), json!({ "kind": "flat", "a": [-1, 2], "b": [] }));
+    }
+}
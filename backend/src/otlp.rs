@@ -0,0 +1,33 @@
+//! OTLP span export through the OpenTelemetry SDK.
+//!
+//! Enabled by setting `OTEL_EXPORTER_OTLP_ENDPOINT` (e.g. `http://localhost:4318`).
+//! Spans from `tracing` are batched and posted over OTLP/HTTP to
+//! `{endpoint}/v1/traces`, so any OpenTelemetry collector, Jaeger or Tempo can
+//! ingest them. The SDK's other `OTEL_*` variables, such as headers or the
+//! sampler, apply as usual.
+
+use anyhow::Result;
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_sdk::trace::{SdkTracerProvider, Tracer};
+use opentelemetry_sdk::Resource;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+const SERVICE_NAME: &str = "strategy-game-backend";
+
+/// Tracing layer that hands closed spans to the OTLP exporter
+pub fn layer<S>() -> Result<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+    let provider = SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(SERVICE_NAME).build())
+        .build();
+    let tracer = provider.tracer(SERVICE_NAME);
+    // The global provider keeps the exporter running for the life of the process
+    opentelemetry::global::set_tracer_provider(provider);
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
@@ -0,0 +1,364 @@
+//! Protobuf encoding of the WebSocket protocol, as described by
+//! `proto/game.proto`.
+//!
+//! build.rs generates the wire types with prost-build; this module only maps
+//! them to and from the game's own types. Messages without a typed field in
+//! the schema are carried as JSON.
+
+use anyhow::{anyhow, Result};
+use prost::Message as _;
+use uuid::Uuid;
+
+use crate::types::*;
+
+/// Types generated from `proto/game.proto`
+#[allow(clippy::all)]
+mod pb {
+    include!(concat!(env!("OUT_DIR"), "/claudefront.rs"));
+}
+
+use pb::client_message::Message as Command;
+use pb::server_message::Message as Server;
+
+/// Encode a server message as a protobuf `ServerMessage`
+pub fn encode_server_message(message: &ServerMessage) -> Result<Vec<u8>> {
+    let message = match message {
+        ServerMessage::Joined { game_id, player_id, name, color } => Server::Joined(pb::Joined {
+            game_id: game_id.to_string(),
+            player_id: player_id.to_string(),
+            name: name.clone(),
+            color: color.clone(),
+        }),
+        ServerMessage::GameStateUpdate { state, checksum } => Server::GameStateUpdate(pb::GameStateUpdate {
+            state: Some(game_state(state)),
+            checksum: *checksum,
+        }),
+        ServerMessage::StateDelta { delta } => Server::StateDelta(state_delta(delta)),
+        ServerMessage::Error { message } => Server::Error(pb::Error { message: message.clone() }),
+        ServerMessage::Pong { nonce, server_tick } => Server::Pong(pb::Pong { nonce: *nonce, server_tick: *server_tick }),
+        ServerMessage::TerritoryConquered { territory_id, old_owner, new_owner } => {
+            Server::TerritoryConquered(pb::TerritoryConquered {
+                territory_id: territory_id.to_string(),
+                old_owner: old_owner.map(|id| id.to_string()),
+                new_owner: new_owner.to_string(),
+            })
+        }
+        other => Server::Json(serde_json::to_string(other)?),
+    };
+    Ok(pb::ServerMessage { message: Some(message) }.encode_to_vec())
+}
+
+/// Decode a protobuf `ClientMessage`, with its `command_id` if it has one
+pub fn decode_client_message(bytes: &[u8]) -> Result<(ClientMessage, Option<String>)> {
+    let pb::ClientMessage { message, command_id } = pb::ClientMessage::decode(bytes)?;
+    let message = match message.ok_or_else(|| anyhow!("Empty message"))? {
+        Command::JoinGame(join) => ClientMessage::JoinGame { name: join.name, color: join.color, player_key: join.player_key },
+        Command::Attack(attack) => ClientMessage::Attack { from: uuid(&attack.from)?, to: uuid(&attack.to)? },
+        Command::BuildStructure(build) => ClientMessage::BuildStructure {
+            territory: uuid(&build.territory)?,
+            building_type: building_from_proto(build.building_type)?,
+        },
+        Command::SetTroopRatio(set) => ClientMessage::SetTroopRatio { ratio: set.ratio },
+        Command::SetAttackRatio(set) => ClientMessage::SetAttackRatio { ratio: set.ratio },
+        Command::Ping(ping) => ClientMessage::Ping { nonce: ping.nonce },
+        Command::GetGameState(_) => ClientMessage::GetGameState,
+        Command::Json(json) => serde_json::from_str(&json)?,
+    };
+    Ok((message, Some(command_id).filter(|id| !id.is_empty())))
+}
+
+fn uuid(value: &str) -> Result<Uuid> {
+    Ok(Uuid::parse_str(value)?)
+}
+
+fn ids(ids: &[Uuid]) -> Vec<String> {
+    ids.iter().map(Uuid::to_string).collect()
+}
+
+fn terrain_to_proto(terrain: TerrainType) -> pb::TerrainType {
+    match terrain {
+        TerrainType::Plains => pb::TerrainType::Plains,
+        TerrainType::Mountains => pb::TerrainType::Mountains,
+        TerrainType::Forests => pb::TerrainType::Forests,
+        TerrainType::Water => pb::TerrainType::Water,
+    }
+}
+
+fn resource_to_proto(resource: ResourceType) -> pb::ResourceType {
+    match resource {
+        ResourceType::Iron => pb::ResourceType::Iron,
+        ResourceType::Horses => pb::ResourceType::Horses,
+        ResourceType::Gems => pb::ResourceType::Gems,
+    }
+}
+
+fn tech_to_proto(tech: Tech) -> pb::Tech {
+    match tech {
+        Tech::Drill => pb::Tech::Drill,
+        Tech::Tactics => pb::Tech::Tactics,
+        Tech::Masonry => pb::Tech::Masonry,
+        Tech::Agriculture => pb::Tech::Agriculture,
+    }
+}
+
+fn building_to_proto(building: BuildingType) -> pb::BuildingType {
+    match building {
+        BuildingType::City => pb::BuildingType::City,
+        BuildingType::DefensePost => pb::BuildingType::DefensePost,
+        BuildingType::GoldMine => pb::BuildingType::GoldMine,
+        BuildingType::Barracks => pb::BuildingType::Barracks,
+        BuildingType::Harbor => pb::BuildingType::Harbor,
+        BuildingType::Bridge => pb::BuildingType::Bridge,
+        BuildingType::Farm => pb::BuildingType::Farm,
+    }
+}
+
+fn building_from_proto(value: i32) -> Result<BuildingType> {
+    let building = match pb::BuildingType::try_from(value).map_err(|_| anyhow!("Unknown building type {}", value))? {
+        pb::BuildingType::City => BuildingType::City,
+        pb::BuildingType::DefensePost => BuildingType::DefensePost,
+        pb::BuildingType::GoldMine => BuildingType::GoldMine,
+        pb::BuildingType::Barracks => BuildingType::Barracks,
+        pb::BuildingType::Harbor => BuildingType::Harbor,
+        pb::BuildingType::Bridge => BuildingType::Bridge,
+        pb::BuildingType::Farm => BuildingType::Farm,
+    };
+    Ok(building)
+}
+
+fn personality_to_proto(personality: AIPersonality) -> pb::AiPersonality {
+    match personality {
+        AIPersonality::Turtle => pb::AiPersonality::Turtle,
+        AIPersonality::Aggressor => pb::AiPersonality::Aggressor,
+        AIPersonality::Balanced => pb::AiPersonality::Balanced,
+        AIPersonality::Opportunist => pb::AiPersonality::Opportunist,
+        AIPersonality::Rusher => pb::AiPersonality::Rusher,
+    }
+}
+
+fn game_state(state: &GameState) -> pb::GameState {
+    let grid = match state.grid {
+        MapGrid::Voronoi => pb::MapGrid::Voronoi,
+        MapGrid::Hex => pb::MapGrid::Hex,
+    };
+    pb::GameState {
+        territories: state.territories.iter().map(territory).collect(),
+        players: state.players.iter().map(player).collect(),
+        tick: state.tick,
+        game_speed: state.game_speed,
+        is_paused: state.is_paused,
+        game_time_seconds: state.game_time_seconds,
+        regions: state
+            .regions
+            .iter()
+            .map(|r| pb::Region {
+                id: r.id,
+                name: r.name.clone(),
+                territories: ids(&r.territories),
+                center_x: r.center.0,
+                center_y: r.center.1,
+            })
+            .collect(),
+        chokepoints: state
+            .chokepoints
+            .iter()
+            .map(|c| pb::Chokepoint { from: c.from.to_string(), to: c.to.to_string() })
+            .collect(),
+        diplomacy: Some(diplomacy(&state.diplomacy)),
+        grid: grid.into(),
+        victory: state.victory.iter().map(victory_progress).collect(),
+        battles: state.battles.iter().map(battle).collect(),
+    }
+}
+
+fn state_delta(delta: &StateDelta) -> pb::StateDelta {
+    pb::StateDelta {
+        base_tick: delta.base_tick,
+        tick: delta.tick,
+        game_speed: delta.game_speed,
+        is_paused: delta.is_paused,
+        game_time_seconds: delta.game_time_seconds,
+        territories: delta.territories.iter().map(territory).collect(),
+        players: delta.players.iter().map(player).collect(),
+        checksum: delta.checksum,
+        diplomacy: delta.diplomacy.as_ref().map(diplomacy),
+        victory: delta
+            .victory
+            .as_ref()
+            .map(|victory| pb::Victory { progress: victory.iter().map(victory_progress).collect() }),
+        battles: delta.battles.as_ref().map(|battles| pb::Battles { battle: battles.iter().map(battle).collect() }),
+    }
+}
+
+fn battle(b: &Battle) -> pb::Battle {
+    pb::Battle {
+        id: b.id,
+        attacker_id: b.attacker_id.to_string(),
+        defender_id: b.defender_id.map(|id| id.to_string()),
+        from_territory: b.from_territory.to_string(),
+        territory: b.territory.to_string(),
+        attackers: Some(units(&b.attackers)),
+        committed: Some(units(&b.committed)),
+        defenders: Some(units(&b.defenders)),
+        attacker_losses: Some(units(&b.attacker_losses)),
+        defender_losses: Some(units(&b.defender_losses)),
+        started_tick: b.started_tick,
+        rounds_left: b.rounds_left,
+        siege_ticks_left: b.siege_ticks_left,
+    }
+}
+
+fn units(u: &Units) -> pb::Units {
+    pb::Units { infantry: u.infantry, cavalry: u.cavalry, siege: u.siege }
+}
+
+fn victory_progress(v: &VictoryProgress) -> pb::VictoryProgress {
+    pb::VictoryProgress {
+        condition: v.condition.clone(),
+        player_id: v.player_id.to_string(),
+        current: v.current,
+        target: v.target,
+    }
+}
+
+fn territory(t: &Territory) -> pb::Territory {
+    // The level and idle time only mean something with a building
+    let built = t.building.is_some();
+    pb::Territory {
+        id: t.id.to_string(),
+        name: t.name.clone(),
+        owner: t.owner.map(|id| id.to_string()),
+        terrain: terrain_to_proto(t.terrain).into(),
+        building: t.building.map(|b| building_to_proto(b).into()),
+        troops: t.troops(),
+        neighbors: ids(&t.neighbors),
+        x: t.position.0,
+        y: t.position.1,
+        region: t.region,
+        coastal: t.coastal,
+        polygon: t.polygon.iter().map(|&(x, y)| pb::Point { x, y }).collect(),
+        borders: t
+            .borders
+            .iter()
+            .map(|b| pb::Border { neighbor: b.neighbor.to_string(), river: b.river })
+            .collect(),
+        capital_of: t.capital_of.map(|id| id.to_string()),
+        resource: t.resource.map(|r| resource_to_proto(r).into()),
+        units: Some(units(&t.units)),
+        building_level: if built { t.building_level as u32 } else { 0 },
+        building_idle_until: if built { t.building_idle_until } else { 0 },
+    }
+}
+
+fn player(p: &Player) -> pb::Player {
+    pb::Player {
+        id: p.id.to_string(),
+        name: p.name.clone(),
+        is_ai: p.is_ai,
+        ai_personality: p.ai_personality.map(|a| personality_to_proto(a).into()),
+        color: p.color.clone(),
+        population: p.population,
+        max_population: p.max_population,
+        gold: p.gold,
+        troop_ratio: p.troop_ratio,
+        trained_ratio: p.trained_ratio,
+        attack_ratio: p.attack_ratio,
+        territories_controlled: p.territories_controlled,
+        is_alive: p.is_alive,
+        latency_ms: p.latency_ms,
+        food: p.food,
+        food_per_second: p.food_per_second,
+        techs: p.techs.iter().map(|&tech| tech_to_proto(tech).into()).collect(),
+        research: p.research.map(|r| pb::Research { tech: tech_to_proto(r.tech).into(), progress: r.progress }),
+        unit_mix: Some(pb::UnitMix {
+            infantry: p.unit_mix.infantry,
+            cavalry: p.unit_mix.cavalry,
+            siege: p.unit_mix.siege,
+        }),
+        presets: p
+            .presets
+            .iter()
+            .map(|preset| pb::RatioPreset {
+                name: preset.name.clone(),
+                troop_ratio: preset.troop_ratio,
+                attack_ratio: preset.attack_ratio,
+            })
+            .collect(),
+    }
+}
+
+fn diplomacy(d: &Diplomacy) -> pb::Diplomacy {
+    pb::Diplomacy {
+        treaties: d
+            .treaties
+            .iter()
+            .map(|t| pb::Treaty { players: ids(&t.players), signed_tick: t.signed_tick })
+            .collect(),
+        offers: d
+            .offers
+            .iter()
+            .map(|o| pb::TreatyOffer { from: o.from.to_string(), to: o.to.to_string() })
+            .collect(),
+        truces: d
+            .truces
+            .iter()
+            .map(|t| pb::Truce { players: ids(&t.players), remaining_ticks: t.remaining_ticks })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+
+    fn decode(bytes: &[u8]) -> Server {
+        pb::ServerMessage::decode(bytes).unwrap().message.unwrap()
+    }
+
+    fn command(message: Command, command_id: &str) -> Vec<u8> {
+        pb::ClientMessage { message: Some(message), command_id: command_id.into() }.encode_to_vec()
+    }
+
+    #[test]
+    fn test_state_update_layout_follows_the_schema() {
+        let state = MapGenerator::new(20, 4).generate();
+        let bytes = encode_server_message(&ServerMessage::state_update(state.clone())).unwrap();
+
+        let Server::GameStateUpdate(update) = decode(&bytes) else { panic!("not a state update") };
+        let encoded = update.state.unwrap();
+        assert_eq!(encoded.territories.len(), state.territories.len());
+        assert_eq!(encoded.territories[0].id, state.territories[0].id.to_string());
+        assert_eq!(encoded.territories[0].neighbors.len(), state.territories[0].neighbors.len());
+        assert_eq!(encoded.game_speed, state.game_speed);
+        assert_eq!(encoded.players.len(), state.players.len());
+    }
+
+    #[test]
+    fn test_other_messages_fall_back_to_json() {
+        let message = ServerMessage::notification(NotificationKey::Announcement, NotificationLevel::Info, [("text", "hi".into())]);
+        let Server::Json(json) = decode(&encode_server_message(&message).unwrap()) else { panic!("not json") };
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["type"], "notification");
+    }
+
+    #[test]
+    fn test_client_commands_decode() {
+        let (from, to) = (Uuid::new_v4(), Uuid::new_v4());
+        let attack = command(Command::Attack(pb::Attack { from: from.to_string(), to: to.to_string() }), "");
+        assert!(matches!(decode_client_message(&attack).unwrap(), (ClientMessage::Attack { from: f, to: t }, None) if f == from && t == to));
+
+        let ratio = command(Command::SetTroopRatio(pb::SetTroopRatio { ratio: 0.75 }), "");
+        assert!(matches!(decode_client_message(&ratio).unwrap().0, ClientMessage::SetTroopRatio { ratio } if ratio == 0.75));
+
+        let json = command(Command::Json(r#"{"type":"pause_game"}"#.into()), "");
+        assert!(matches!(decode_client_message(&json).unwrap().0, ClientMessage::PauseGame));
+
+        let retried = command(Command::Json(r#"{"type":"resume_game"}"#.into()), "retry-1");
+        assert!(matches!(decode_client_message(&retried).unwrap(), (ClientMessage::ResumeGame, Some(id)) if id == "retry-1"));
+
+        let unknown = command(Command::BuildStructure(pb::BuildStructure { territory: from.to_string(), building_type: 99 }), "");
+        assert!(decode_client_message(&unknown).is_err());
+        assert!(decode_client_message(&[0x0a, 0x05, 0x01]).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! Rooms saved to storage so they survive a restart.
+//!
+//! Each opened room is saved as a full engine snapshot with its name and the
+//! reserved seats. Seats are kept by a hash of their session token, never the
+//! token itself, so a leaked save can't be used to take over a seat. On
+//! startup the lobby reopens every saved room with the same id, so players
+//! can reconnect with their tokens after a redeploy. Finished rooms are
+//! removed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::game::snapshot::GameSnapshot;
+
+/// Everything needed to reopen a room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRoom {
+    pub room_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub game: GameSnapshot,
+    /// Hash of each reserved seat's session token, and the player it plays as
+    pub seats: HashMap<String, Uuid>,
+}
+
+/// What a session token is kept as
+pub fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
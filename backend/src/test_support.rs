@@ -0,0 +1,218 @@
+//! Helpers for end-to-end tests: boot the real router on an ephemeral port and
+//! drive it with scripted WebSocket clients.
+
+use futures_util::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::game::{GameEngine, MapGenerator};
+use crate::lobby::Lobby;
+use crate::types::*;
+use crate::websocket::{AdminHub, GameSession};
+
+const RECV_TIMEOUT: Duration = Duration::from_secs(5);
+pub const TEST_ADMIN_TOKEN: &str = "test-admin-token";
+
+/// A server running on a random local port
+pub struct TestServer {
+    pub addr: SocketAddr,
+    /// The main room
+    pub session: Arc<GameSession>,
+    pub lobby: Arc<Lobby>,
+}
+
+impl TestServer {
+    /// Start a server with a small generated map; the tick loop only runs if requested
+    pub async fn start(run_game_loop: bool) -> Self {
+        let state = MapGenerator::new(20, 4).generate();
+        Self::with_session(GameSession::new(GameEngine::new(state, 20)), run_game_loop).await
+    }
+
+    /// Start a server like `start` whose map has `humans` human players, so
+    /// as many clients can join
+    pub async fn with_humans(humans: usize, run_game_loop: bool) -> Self {
+        let state = MapGenerator::new(20, 4).with_human_slots(humans).generate();
+        Self::with_session(GameSession::new(GameEngine::new(state, 20)), run_game_loop).await
+    }
+
+    /// Start a server around a prepared session
+    pub async fn with_session(mut session: GameSession, run_game_loop: bool) -> Self {
+        session.admin = Arc::new(AdminHub::new(Some(TEST_ADMIN_TOKEN.to_string())));
+        let session = Arc::new(session);
+
+        if run_game_loop {
+            session.clone().start_game_loop().await;
+        }
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let lobby = Arc::new(Lobby::new(session.clone()));
+        let app = crate::app(lobby.clone());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+
+        Self { addr, session, lobby }
+    }
+
+    /// Connect a new simulated client and join the main game
+    pub async fn connect(&self) -> TestClient {
+        self.join("/ws").await.unwrap()
+    }
+
+    /// Connect a new simulated client and join a room other than the main one
+    pub async fn connect_to_room(&self, room_id: uuid::Uuid) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        self.join(&format!("/ws?room={}", room_id)).await
+    }
+
+    /// Connect and join as the player a session token reserves
+    pub async fn connect_with_token(&self, token: &str) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        self.join(&format!("/ws?token={}", token)).await
+    }
+
+    /// Open a WebSocket without joining; the server waits for `join_game`
+    pub async fn open(&self, path: &str) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        let (socket, _) = connect_async(format!("ws://{}{}", self.addr, path)).await?;
+        Ok(TestClient { socket })
+    }
+
+    async fn join(&self, path: &str) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        let mut client = self.open(path).await?;
+        client.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        match client.recv().await {
+            ServerMessage::Joined { .. } => Ok(client),
+            other => panic!("expected to join, got {:?}", other),
+        }
+    }
+
+    /// Connect to the admin channel; fails if the token is rejected
+    pub async fn connect_admin(&self, token: &str) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        let (socket, _) = connect_async(format!("ws://{}/ws/admin?token={}", self.addr, token)).await?;
+        Ok(TestClient { socket })
+    }
+
+    /// Watch a game through the spectator channel; fails if the game is unknown
+    pub async fn spectate(&self, game_id: uuid::Uuid) -> Result<TestClient, tokio_tungstenite::tungstenite::Error> {
+        let (socket, _) = connect_async(format!("ws://{}/ws/spectate/{}", self.addr, game_id)).await?;
+        Ok(TestClient { socket })
+    }
+
+    /// Connect several clients at once
+    pub async fn connect_many(&self, count: usize) -> Vec<TestClient> {
+        let mut clients = Vec::with_capacity(count);
+        for _ in 0..count {
+            clients.push(self.connect().await);
+        }
+        clients
+    }
+}
+
+/// A scripted WebSocket client speaking the JSON protocol
+pub struct TestClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl TestClient {
+    pub async fn send(&mut self, message: ClientMessage) {
+        let json = serde_json::to_string(&message).unwrap();
+        self.socket.send(Message::Text(json)).await.unwrap();
+    }
+
+    /// Send a raw text frame, bypassing the typed protocol
+    pub async fn send_raw(&mut self, text: &str) {
+        self.socket.send(Message::Text(text.to_string())).await.unwrap();
+    }
+
+    /// Receive the next server message, failing the test on timeout
+    pub async fn recv(&mut self) -> ServerMessage {
+        self.recv_as().await
+    }
+
+    /// Receive the next text frame decoded as any message type
+    pub async fn recv_as<T: DeserializeOwned>(&mut self) -> T {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.socket.next())
+                .await
+                .expect("timed out waiting for server message")
+                .expect("connection closed")
+                .unwrap();
+
+            if let Message::Text(text) = frame {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    /// Send a binary frame
+    pub async fn send_binary(&mut self, bytes: Vec<u8>) {
+        self.socket.send(Message::Binary(bytes)).await.unwrap();
+    }
+
+    /// Receive the next binary frame, failing on text
+    pub async fn recv_binary(&mut self) -> Vec<u8> {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.socket.next())
+                .await
+                .expect("timed out waiting for server message")
+                .expect("connection closed")
+                .unwrap();
+            match frame {
+                Message::Binary(bytes) => return bytes,
+                Message::Text(text) => panic!("expected a binary frame, got {}", text),
+                _ => {}
+            }
+        }
+    }
+
+    /// Receive the next binary frame as the MessagePack message it carries
+    pub async fn recv_msgpack(&mut self) -> ServerMessage {
+        serde_json::from_value(decode_msgpack(&self.recv_binary().await)).unwrap()
+    }
+
+    /// Skip messages until one matches, returning it
+    pub async fn recv_until<F>(&mut self, mut predicate: F) -> ServerMessage
+    where
+        F: FnMut(&ServerMessage) -> bool,
+    {
+        loop {
+            let message = self.recv().await;
+            if predicate(&message) {
+                return message;
+            }
+        }
+    }
+
+    /// Assert that nothing arrives within the given window
+    pub async fn expect_silence(&mut self, window: Duration) {
+        if let Ok(Some(Ok(Message::Text(text)))) = tokio::time::timeout(window, self.socket.next()).await {
+            panic!("expected no message, got {}", text);
+        }
+    }
+
+    /// Assert that the server closes the connection, skipping any messages sent before
+    pub async fn expect_closed(&mut self) {
+        loop {
+            let frame = tokio::time::timeout(RECV_TIMEOUT, self.socket.next())
+                .await
+                .expect("timed out waiting for the connection to close");
+            match frame {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+                Some(Ok(_)) => {}
+            }
+        }
+    }
+
+    pub async fn close(mut self) {
+        let _ = self.socket.close(None).await;
+    }
+}
+
+/// Decode a MessagePack frame into the JSON value it stands for
+pub fn decode_msgpack(bytes: &[u8]) -> Value {
+    rmp_serde::from_slice(bytes).unwrap()
+}
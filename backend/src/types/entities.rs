@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
@@ -60,7 +61,7 @@ pub enum TerrainType {
     Mountains,
     /// +20% population growth
     Forests,
-    /// No bonus, visual variety
+    /// Can't be held; crossed from a Harbor
     Water,
 }
 
@@ -87,6 +88,21 @@ impl TerrainType {
     }
 }
 
+/// Deposits a territory can hold; whoever owns it gets the bonus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ResourceType {
+    /// Attacks deal more losses
+    Iron,
+    /// Faster troop training
+    Horses,
+    /// Extra gold every second
+    Gems,
+}
+
+/// Highest level a building can be upgraded to
+pub const MAX_BUILDING_LEVEL: u8 = 3;
+
 /// Building types that can be constructed in territories
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "snake_case")]
@@ -97,55 +113,514 @@ pub enum BuildingType {
     DefensePost,
     /// +50% gold generation, costs 750 gold
     GoldMine,
+    /// +50% troop training speed, costs 400 gold
+    Barracks,
+    /// Attacks across water, coastal territories only, costs 600 gold
+    Harbor,
+    /// Spans the rivers on the territory's borders, costs 300 gold
+    Bridge,
+    /// +150 food per second, costs 250 gold
+    Farm,
 }
 
 impl BuildingType {
-    pub fn cost(&self) -> u32 {
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            BuildingType::City => "City",
+            BuildingType::DefensePost => "Defense Post",
+            BuildingType::GoldMine => "Gold Mine",
+            BuildingType::Barracks => "Barracks",
+            BuildingType::Harbor => "Harbor",
+            BuildingType::Bridge => "Bridge",
+            BuildingType::Farm => "Farm",
+        }
+    }
+
+    pub fn cost(&self) -> u64 {
         match self {
             BuildingType::City => 1000,
             BuildingType::DefensePost => 500,
             BuildingType::GoldMine => 750,
+            BuildingType::Barracks => 400,
+            BuildingType::Harbor => 600,
+            BuildingType::Bridge => 300,
+            BuildingType::Farm => 250,
         }
     }
 
-    pub fn max_population_bonus(&self) -> u32 {
+    /// Share of its bonus a building gives at `level`: all of it at level 1,
+    /// and half as much again with every upgrade
+    pub fn level_scale(level: u8) -> f32 {
+        1.0 + 0.5 * level.saturating_sub(1) as f32
+    }
+
+    /// Whether upgrading the building raises its bonus; Harbors and Bridges have none to raise
+    pub fn upgradeable(&self) -> bool {
+        !matches!(self, BuildingType::Harbor | BuildingType::Bridge)
+    }
+
+    pub fn max_population_bonus(&self, level: u8) -> u64 {
         match self {
-            BuildingType::City => 25_000,
+            BuildingType::City => (25_000.0 * Self::level_scale(level)) as u64,
             _ => 0,
         }
     }
 
-    pub fn defense_multiplier(&self) -> f32 {
+    pub fn defense_multiplier(&self, level: u8) -> f32 {
         match self {
-            BuildingType::DefensePost => 0.8, // Reduces defender losses by 20%
+            BuildingType::DefensePost => 1.0 - 0.2 * Self::level_scale(level), // Reduces defender losses by 20%, 30%, 40%
             _ => 1.0,
         }
     }
 
-    pub fn gold_multiplier(&self) -> f32 {
+    pub fn gold_multiplier(&self, level: u8) -> f32 {
         match self {
-            BuildingType::GoldMine => 1.5,
+            BuildingType::GoldMine => 1.0 + 0.5 * Self::level_scale(level),
             _ => 1.0,
         }
     }
 }
 
+/// Upgrades players research with gold over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Tech {
+    /// Attacks inflict 10% more losses
+    Drill,
+    /// Attacks inflict another 10% more losses, needs Drill
+    Tactics,
+    /// Buildings cost 20% less
+    Masonry,
+    /// Population grows 25% faster
+    Agriculture,
+}
+
+impl Tech {
+    /// Gold spent over the whole research
+    pub fn cost(&self) -> u64 {
+        match self {
+            Tech::Drill => 800,
+            Tech::Tactics => 1500,
+            Tech::Masonry => 600,
+            Tech::Agriculture => 700,
+        }
+    }
+
+    /// Game seconds the research takes while it is paid for
+    pub fn seconds(&self) -> f32 {
+        match self {
+            Tech::Drill => 60.0,
+            Tech::Tactics => 120.0,
+            Tech::Masonry => 45.0,
+            Tech::Agriculture => 60.0,
+        }
+    }
+
+    /// Tech that has to be researched first
+    pub fn requires(&self) -> Option<Tech> {
+        match self {
+            Tech::Tactics => Some(Tech::Drill),
+            _ => None,
+        }
+    }
+
+    /// Extra losses the researcher's attacks inflict
+    pub fn attack_bonus(&self) -> f32 {
+        match self {
+            Tech::Drill | Tech::Tactics => 0.1,
+            _ => 0.0,
+        }
+    }
+
+    /// Share taken off the price of every building
+    pub fn building_discount(&self) -> f32 {
+        match self {
+            Tech::Masonry => 0.2,
+            _ => 0.0,
+        }
+    }
+
+    /// Extra population growth
+    pub fn growth_bonus(&self) -> f32 {
+        match self {
+            Tech::Agriculture => 0.25,
+            _ => 0.0,
+        }
+    }
+}
+
+/// A research under way
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ResearchProgress {
+    pub tech: Tech,
+    /// Share done, from 0 to 1
+    pub progress: f32,
+}
+
+/// Kinds of soldier; each counters one of the others
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitType {
+    /// Holds ground well; counters cavalry
+    Infantry,
+    /// Hits hard but defends poorly; counters siege
+    Cavalry,
+    /// Breaks defenses but can barely hold them; counters infantry
+    Siege,
+}
+
+impl UnitType {
+    pub const ALL: [UnitType; 3] = [UnitType::Infantry, UnitType::Cavalry, UnitType::Siege];
+
+    /// What one soldier is worth in an attack
+    pub fn attack_weight(&self) -> f32 {
+        match self {
+            UnitType::Infantry => 1.0,
+            UnitType::Cavalry => 1.5,
+            UnitType::Siege => 1.25,
+        }
+    }
+
+    /// What one soldier is worth holding a territory
+    pub fn defense_weight(&self) -> f32 {
+        match self {
+            UnitType::Infantry => 1.0,
+            UnitType::Cavalry => 0.75,
+            UnitType::Siege => 0.5,
+        }
+    }
+
+    /// The unit type this one is strong against
+    pub fn counters(&self) -> UnitType {
+        match self {
+            UnitType::Infantry => UnitType::Cavalry,
+            UnitType::Cavalry => UnitType::Siege,
+            UnitType::Siege => UnitType::Infantry,
+        }
+    }
+}
+
+/// Soldiers of every unit type in one place
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, ToSchema)]
+pub struct Units {
+    pub infantry: u32,
+    pub cavalry: u32,
+    pub siege: u32,
+}
+
+impl Units {
+    pub fn infantry(count: u32) -> Self {
+        Units { infantry: count, ..Default::default() }
+    }
+
+    pub fn total(&self) -> u32 {
+        self.infantry.saturating_add(self.cavalry).saturating_add(self.siege)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    pub fn get(&self, unit_type: UnitType) -> u32 {
+        match unit_type {
+            UnitType::Infantry => self.infantry,
+            UnitType::Cavalry => self.cavalry,
+            UnitType::Siege => self.siege,
+        }
+    }
+
+    pub fn get_mut(&mut self, unit_type: UnitType) -> &mut u32 {
+        match unit_type {
+            UnitType::Infantry => &mut self.infantry,
+            UnitType::Cavalry => &mut self.cavalry,
+            UnitType::Siege => &mut self.siege,
+        }
+    }
+
+    /// Add `count` soldiers split by `mix`, rounding in favor of infantry
+    pub fn add_mixed(&mut self, count: u32, mix: &UnitMix) {
+        let cavalry = (count as f64 * mix.share(UnitType::Cavalry) as f64) as u32;
+        let siege = ((count as f64 * mix.share(UnitType::Siege) as f64) as u32).min(count - cavalry);
+        self.cavalry = self.cavalry.saturating_add(cavalry);
+        self.siege = self.siege.saturating_add(siege);
+        self.infantry = self.infantry.saturating_add(count - cavalry - siege);
+    }
+
+    /// Add every soldier of another group
+    pub fn add(&mut self, other: Units) {
+        for unit_type in UnitType::ALL {
+            *self.get_mut(unit_type) = self.get(unit_type).saturating_add(other.get(unit_type));
+        }
+    }
+
+    /// Take soldiers of another group away, as many as there are
+    pub fn remove(&mut self, other: Units) {
+        for unit_type in UnitType::ALL {
+            *self.get_mut(unit_type) = self.get(unit_type).saturating_sub(other.get(unit_type));
+        }
+    }
+
+    /// `count` soldiers drawn evenly from every unit type, for detachments
+    /// and losses; remainders come from the types in order
+    pub fn portion(&self, count: u32) -> Units {
+        let total = self.total();
+        if count >= total {
+            return *self;
+        }
+        let mut portion = Units::default();
+        for unit_type in UnitType::ALL {
+            *portion.get_mut(unit_type) = (self.get(unit_type) as u64 * count as u64 / total as u64) as u32;
+        }
+        for unit_type in UnitType::ALL {
+            let missing = count - portion.total();
+            let spare = self.get(unit_type) - portion.get(unit_type);
+            *portion.get_mut(unit_type) += missing.min(spare);
+        }
+        portion
+    }
+}
+
+impl<'de> Deserialize<'de> for Units {
+    /// Accepts a plain number as well, as in saves from before unit types:
+    /// every soldier then is infantry
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Composition {
+            #[serde(default)]
+            infantry: u32,
+            #[serde(default)]
+            cavalry: u32,
+            #[serde(default)]
+            siege: u32,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Total(u32),
+            Composition(Composition),
+        }
+
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Total(count) => Units::infantry(count),
+            Stored::Composition(Composition { infantry, cavalry, siege }) => Units { infantry, cavalry, siege },
+        })
+    }
+}
+
+/// Shares of new recruits trained as each unit type
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct UnitMix {
+    pub infantry: f32,
+    pub cavalry: f32,
+    pub siege: f32,
+}
+
+impl Default for UnitMix {
+    fn default() -> Self {
+        UnitMix { infantry: 1.0, cavalry: 0.0, siege: 0.0 }
+    }
+}
+
+impl UnitMix {
+    /// Share of recruits of a unit type, from 0 to 1
+    pub fn share(&self, unit_type: UnitType) -> f32 {
+        let total = self.infantry + self.cavalry + self.siege;
+        if total <= 0.0 {
+            return if unit_type == UnitType::Infantry { 1.0 } else { 0.0 };
+        }
+        match unit_type {
+            UnitType::Infantry => self.infantry / total,
+            UnitType::Cavalry => self.cavalry / total,
+            UnitType::Siege => self.siege / total,
+        }
+    }
+}
+
+/// A named pair of troop and attack ratios a player can switch to with one command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct RatioPreset {
+    pub name: String,
+    pub troop_ratio: f32,
+    pub attack_ratio: f32,
+}
+
+impl RatioPreset {
+    /// Presets every player has unless they save their own under the same name
+    pub fn builtin() -> [RatioPreset; 2] {
+        [
+            RatioPreset { name: "turtle".to_string(), troop_ratio: 0.3, attack_ratio: 0.1 },
+            RatioPreset { name: "all_in".to_string(), troop_ratio: 1.0, attack_ratio: 0.5 },
+        ]
+    }
+}
+
+/// How the map generator picks terrain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MapStyle {
+    /// Every territory rolls its terrain independently
+    #[default]
+    Scattered,
+    /// Terrain follows elevation and moisture maps
+    Continents,
+    /// Many small landmasses scattered across the sea
+    Islands,
+    /// One landmass in the middle of the map, ringed by ocean
+    Pangaea,
+}
+
+impl FromStr for MapStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "scattered" => Ok(MapStyle::Scattered),
+            "continents" => Ok(MapStyle::Continents),
+            "islands" => Ok(MapStyle::Islands),
+            "pangaea" => Ok(MapStyle::Pangaea),
+            _ => Err(anyhow::anyhow!("Unknown map style: {}", s)),
+        }
+    }
+}
+
+/// How territories are shaped and placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MapGrid {
+    /// Voronoi cells around scattered points; positions are normalized 0-1
+    #[default]
+    Voronoi,
+    /// A lattice of hexes; positions are axial coordinates (q, r)
+    Hex,
+}
+
+impl FromStr for MapGrid {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "voronoi" => Ok(MapGrid::Voronoi),
+            "hex" => Ok(MapGrid::Hex),
+            _ => Err(anyhow::anyhow!("Unknown map grid: {}", s)),
+        }
+    }
+}
+
 /// A territory on the map
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Territory {
     #[schema(value_type = String, format = "uuid")]
     pub id: Uuid,
+    /// Display name, unique within the map
+    #[serde(default)]
+    pub name: String,
     #[schema(value_type = String, format = "uuid", nullable = true)]
     pub owner: Option<Uuid>,
     pub terrain: TerrainType,
     pub building: Option<BuildingType>,
-    /// Current troops stationed in this territory
-    pub troops: u32,
+    /// Level of the building, from 1; upgrades raise it
+    #[serde(default = "first_building_level")]
+    pub building_level: u8,
+    /// Tick until which a captured building does nothing for its new owner
+    #[serde(default)]
+    pub building_idle_until: u64,
+    /// Troops stationed in this territory, by unit type; a plain number is
+    /// read as that many infantry
+    #[serde(alias = "troops")]
+    pub units: Units,
     /// Neighboring territory IDs
     #[schema(nullable = true)]
     pub neighbors: Vec<Uuid>,
-    /// Visual position for rendering (x, y normalized 0-1)
+    /// Visual position for rendering (x, y normalized 0-1), or axial
+    /// coordinates (q, r) on hex maps
     pub position: (f32, f32),
+    /// Index of the region this territory belongs to
+    #[serde(default)]
+    pub region: u32,
+    /// Land bordering a water territory
+    #[serde(default)]
+    pub coastal: bool,
+    /// Outline of the territory's cell or hex, counter-clockwise, in the same
+    /// coordinates as `position`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub polygon: Vec<(f32, f32)>,
+    /// Borders with a natural feature, such as a river; the others are open
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub borders: Vec<Border>,
+    /// Player whose capital this is, their starting territory; it stays
+    /// theirs to take back when conquered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = String, format = "uuid", nullable = true)]
+    pub capital_of: Option<Uuid>,
+    /// Deposit in the territory, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<ResourceType>,
+}
+
+fn first_building_level() -> u8 {
+    1
+}
+
+impl Territory {
+    /// Troops stationed here, every unit type together
+    pub fn troops(&self) -> u32 {
+        self.units.total()
+    }
+
+    /// The building, unless it was captured too recently to work for its owner
+    pub fn working_building(&self, tick: u64) -> Option<BuildingType> {
+        self.building.filter(|_| tick >= self.building_idle_until)
+    }
+
+    /// Whether a river runs along the border with this neighbor
+    pub fn river_to(&self, neighbor: Uuid) -> bool {
+        self.borders.iter().any(|b| b.neighbor == neighbor && b.river)
+    }
+}
+
+/// Features of the border between a territory and one of its neighbors,
+/// listed on both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Border {
+    #[schema(value_type = String, format = "uuid")]
+    pub neighbor: Uuid,
+    /// Attacks across a river are weaker unless either side has a Bridge
+    #[serde(default)]
+    pub river: bool,
+}
+
+/// A group of neighboring territories, computed by the map generator
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Region {
+    pub id: u32,
+    #[serde(default)]
+    pub name: String,
+    #[schema(value_type = Vec<String>)]
+    pub territories: Vec<Uuid>,
+    /// Average position of the region's territories
+    pub center: (f32, f32),
+}
+
+/// A border between two regions crossed by very few connections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Chokepoint {
+    #[schema(value_type = String, format = "uuid")]
+    pub from: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub to: Uuid,
+}
+
+/// Which territories a path may pass through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub struct TraversalRules {
+    /// Never enter water territories
+    #[serde(default)]
+    pub land_only: bool,
+    /// Stay on this player's territories; only the destination may belong to someone else
+    #[serde(default)]
+    #[schema(value_type = String, format = "uuid", nullable = true)]
+    pub through_owner: Option<Uuid>,
 }
 
 /// AI personality type determining behavior
@@ -165,7 +640,7 @@ pub enum AIPersonality {
 }
 
 /// A player in the game (human or AI)
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Player {
     #[schema(value_type = String, format = "uuid")]
     pub id: Uuid,
@@ -175,28 +650,85 @@ pub struct Player {
     pub color: String, // Hex color like "#FF0000"
 
     // Resources
-    pub population: u32,
-    pub max_population: u32,
-    pub gold: u32,
+    pub population: u64,
+    pub max_population: u64,
+    pub gold: u64,
+    /// Gold earned per second at the current game speed, all bonuses included
+    #[serde(default)]
+    pub gold_per_second: f32,
+    /// Population growth per second at the current game speed; 0 at the cap
+    /// or without a food surplus
+    #[serde(default)]
+    pub population_growth_per_second: f32,
+    /// Food stockpiled
+    #[serde(default)]
+    pub food: u64,
+    /// Food harvested minus food eaten per second at the current game speed;
+    /// the population starves while this is negative and nothing is stockpiled
+    #[serde(default)]
+    pub food_per_second: f32,
+    /// Techs researched, in the order they were finished
+    #[serde(default)]
+    pub techs: Vec<Tech>,
+    /// Research under way, if any
+    #[serde(default)]
+    pub research: Option<ResearchProgress>,
 
     // Ratios (0.0 to 1.0)
-    /// Percentage of population used as troops (rest are workers)
+    /// Target percentage of population used as troops (rest are workers)
     pub troop_ratio: f32,
+    /// Percentage actually trained; follows `troop_ratio` at the training rate
+    #[serde(default)]
+    pub trained_ratio: f32,
     /// Percentage of troops committed per attack
     pub attack_ratio: f32,
+    /// How new troops are split between unit types
+    #[serde(default)]
+    pub unit_mix: UnitMix,
+    /// Ratio presets the player saved; the built-in ones aren't listed
+    #[serde(default)]
+    pub presets: Vec<RatioPreset>,
 
     // Stats
     pub territories_controlled: u32,
     pub is_alive: bool,
+    /// Round-trip time to the player's client, while connected
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
 }
 
 impl Player {
-    pub fn troops(&self) -> u32 {
-        (self.population as f32 * self.troop_ratio) as u32
+    pub fn troops(&self) -> u64 {
+        // f64 keeps large populations exact enough; the min guards against rounding up
+        let troops = (self.population as f64 * self.trained_ratio.clamp(0.0, 1.0) as f64) as u64;
+        troops.min(self.population)
     }
 
-    pub fn workers(&self) -> u32 {
-        self.population - self.troops()
+    pub fn workers(&self) -> u64 {
+        self.population.saturating_sub(self.troops())
+    }
+
+    /// Remove fallen troops without touching the workforce
+    pub fn lose_troops(&mut self, losses: u64) {
+        self.split(self.workers(), self.troops().saturating_sub(losses));
+    }
+
+    /// Remove people from the workforce without touching the army
+    pub fn lose_workers(&mut self, losses: u64) {
+        self.split(self.workers().saturating_sub(losses), self.troops());
+    }
+
+    fn split(&mut self, workers: u64, troops: u64) {
+        self.population = workers + troops;
+        self.trained_ratio = if self.population == 0 {
+            0.0
+        } else {
+            (troops as f64 / self.population as f64) as f32
+        };
+        // Undo f32 rounding so the army stays exactly as large
+        while self.troops() < troops {
+            self.trained_ratio = self.trained_ratio.next_up();
+        }
     }
 }
 
@@ -209,6 +741,138 @@ pub struct GameState {
     pub game_speed: f32, // 1.0 = normal, 2.0 = 2x speed, etc.
     pub is_paused: bool,
     pub game_time_seconds: u32,
+    /// Map structure for strategic overlays
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    #[serde(default)]
+    pub chokepoints: Vec<Chokepoint>,
+    #[serde(default)]
+    pub diplomacy: Diplomacy,
+    /// How to read territory positions
+    #[serde(default)]
+    pub grid: MapGrid,
+    /// How close every living player is to each active victory condition,
+    /// updated every tick
+    #[serde(default)]
+    pub victory: Vec<VictoryProgress>,
+    /// Attacks still being fought, when battles last several ticks
+    #[serde(default)]
+    pub battles: Vec<Battle>,
+}
+
+/// A player's way toward one victory condition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VictoryProgress {
+    /// `last_standing`, `domination`, or a rule plugin's own condition
+    pub condition: String,
+    #[schema(value_type = String, format = "uuid")]
+    pub player_id: Uuid,
+    /// How far the player has come, in the condition's own unit such as
+    /// territories held or rivals eliminated
+    pub current: u32,
+    /// Where the player wins
+    pub target: u32,
+}
+
+impl GameState {
+    /// Whether a territory is an end of a chokepoint edge
+    pub fn is_chokepoint(&self, territory: Uuid) -> bool {
+        self.chokepoints.iter().any(|c| c.from == territory || c.to == territory)
+    }
+}
+
+/// A peace treaty; partners can't attack each other and may trade territory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Treaty {
+    #[schema(value_type = [String; 2])]
+    pub players: [Uuid; 2],
+    pub signed_tick: u64,
+}
+
+/// A treaty proposed by one player and not yet accepted by the other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TreatyOffer {
+    #[schema(value_type = String, format = "uuid")]
+    pub from: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub to: Uuid,
+}
+
+/// A period after a peace deal in which two players can't attack each
+/// other, even if their treaty is broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Truce {
+    #[schema(value_type = [String; 2])]
+    pub players: [Uuid; 2],
+    pub remaining_ticks: u64,
+}
+
+/// Relations between players
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Diplomacy {
+    pub treaties: Vec<Treaty>,
+    pub offers: Vec<TreatyOffer>,
+    #[serde(default)]
+    pub truces: Vec<Truce>,
+}
+
+impl Diplomacy {
+    /// Whether two players are at peace
+    pub fn has_treaty(&self, a: Uuid, b: Uuid) -> bool {
+        self.treaties.iter().any(|t| t.players.contains(&a) && t.players.contains(&b))
+    }
+
+    /// Whether two players can't attack each other, by treaty or truce
+    pub fn at_peace(&self, a: Uuid, b: Uuid) -> bool {
+        self.has_treaty(a, b) || self.truce_remaining(a, b).is_some()
+    }
+
+    /// Ticks left in a truce between two players, if one is running
+    pub fn truce_remaining(&self, a: Uuid, b: Uuid) -> Option<u64> {
+        self.truces
+            .iter()
+            .find(|t| t.players.contains(&a) && t.players.contains(&b))
+            .map(|t| t.remaining_ticks)
+    }
+}
+
+/// What an AI decision was about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AiDecisionKind {
+    Build,
+    Attack,
+    /// A push into neutral land toward a rival that isn't adjacent yet
+    Advance,
+    /// A scripted attack from every border territory at once
+    Wave,
+}
+
+/// One option an AI weighed; the highest score wins
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AiOption {
+    /// Territory an attack starts from
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub from: Option<Uuid>,
+    /// Territory attacked or built on
+    #[schema(value_type = Option<String>, format = "uuid")]
+    pub territory: Option<Uuid>,
+    pub building_type: Option<BuildingType>,
+    /// `None` if the personality ruled the option out
+    pub score: Option<f64>,
+}
+
+/// A recorded AI decision, for tuning and bug reports
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AiDecision {
+    pub tick: u64,
+    pub kind: AiDecisionKind,
+    pub personality: AIPersonality,
+    pub options: Vec<AiOption>,
+    /// Index of the option picked
+    pub chosen: Option<usize>,
+    /// What came of it, such as a failed roll or the engine's error
+    pub outcome: String,
 }
 
 /// Combat result after an attack
@@ -227,17 +891,231 @@ pub struct CombatResult {
     pub attacker_losses: u32,
     pub defender_losses: u32,
     pub territory_conquered: bool,
+    /// The committed troops by unit type
+    #[serde(default)]
+    pub attacker_units: Units,
+    /// The defending garrison by unit type
+    #[serde(default)]
+    pub defender_units: Units,
+    /// `attacker_losses` by unit type
+    #[serde(default)]
+    pub attacker_unit_losses: Units,
+    /// `defender_losses` by unit type
+    #[serde(default)]
+    pub defender_unit_losses: Units,
+}
+
+/// An attack fought over several ticks, taking casualties every tick until
+/// the territory falls, the attackers are spent or they retreat. An attack on
+/// a fortified territory starts as a siege
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct Battle {
+    pub id: u64,
+    #[schema(value_type = String, format = "uuid")]
+    pub attacker_id: Uuid,
+    /// `None` for a neutral territory
+    #[schema(value_type = String, format = "uuid", nullable = true)]
+    pub defender_id: Option<Uuid>,
+    /// Where the attack was launched from, and where survivors return to
+    #[schema(value_type = String, format = "uuid")]
+    pub from_territory: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub territory: Uuid,
+    /// Attackers still fighting
+    pub attackers: Units,
+    /// Every troop sent in, reinforcements included
+    pub committed: Units,
+    /// The garrison when the battle began
+    pub defenders: Units,
+    pub attacker_losses: Units,
+    pub defender_losses: Units,
+    pub started_tick: u64,
+    /// Rounds until the battle is decided
+    pub rounds_left: u32,
+    /// Ticks the attackers still lay siege before they assault; 0 once the
+    /// fighting has started
+    #[serde(default)]
+    pub siege_ticks_left: u64,
 }
 
 /// Game statistics at end of game
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct GameStats {
     #[schema(value_type = String, format = "uuid")]
     pub winner: Uuid,
     pub game_duration_seconds: u32,
     pub territories_captured: u32,
     pub total_battles: u32,
-    pub final_score: u32,
+    pub final_score: u64,
+}
+
+/// What an event is about, for filtering and screen readers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EventCategory {
+    /// Battles, conquests and eliminations
+    Combat,
+    /// Buildings, gold and population
+    Economy,
+    /// Treaties and truces
+    Diplomacy,
+    /// Joins, departures and vote-kicks
+    Players,
+    /// Saves, loads, server trouble and anything else
+    #[default]
+    System,
+}
+
+/// A player or territory an event concerns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntityRef {
+    Player {
+        #[schema(value_type = String, format = "uuid")]
+        id: Uuid,
+    },
+    Territory {
+        #[schema(value_type = String, format = "uuid")]
+        id: Uuid,
+    },
+}
+
+/// A notable moment in a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TimelineEntry {
+    pub game_time_seconds: u32,
+    pub text: String,
+    #[serde(default)]
+    pub category: EventCategory,
+    /// Players and territories involved
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<EntityRef>,
+}
+
+/// The largest battle of a game
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BattleHighlight {
+    pub attacker_name: String,
+    /// `None` for a neutral territory
+    pub defender_name: Option<String>,
+    #[schema(value_type = String, format = "uuid")]
+    pub territory_id: Uuid,
+    pub territory_name: String,
+    /// Attacking plus defending troops
+    pub troops_involved: u32,
+    pub territory_conquered: bool,
+    pub game_time_seconds: u32,
+}
+
+/// How much fighting a territory has seen
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TerritoryStats {
+    #[schema(value_type = String, format = "uuid")]
+    pub territory_id: Uuid,
+    pub territory_name: String,
+    /// Attacks on the territory, failed ones included
+    pub battles: u32,
+    /// Conquests and gifts of the territory
+    pub times_changed_hands: u32,
+    /// Attacking plus defending troops over all its battles
+    pub troops_involved: u64,
+    pub last_battle_seconds: u32,
+}
+
+/// Shareable end-of-game report
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GameSummary {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+    pub stats: GameStats,
+    pub winner_name: String,
+    pub biggest_battle: Option<BattleHighlight>,
+    /// Territories fought over most, the most contested first
+    #[serde(default)]
+    pub most_contested: Vec<TerritoryStats>,
+    pub timeline: Vec<TimelineEntry>,
+    /// Ready-to-post Markdown, within Discord's message length limit
+    pub text: String,
+    /// Outcome of the lobby's map and rule draft, if it held one
+    #[serde(default)]
+    pub draft: Option<DraftResult>,
+}
+
+/// A map on a lobby's ballot, regenerated from its seed if picked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MapOption {
+    pub style: MapStyle,
+    pub seed: u64,
+}
+
+/// Parses `style:seed`, e.g. `continents:7`
+impl FromStr for MapOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (style, seed) = s.split_once(':').ok_or_else(|| anyhow::anyhow!("Expected style:seed, got {}", s))?;
+        let seed = seed.trim().parse().map_err(|_| anyhow::anyhow!("Invalid map seed: {}", seed))?;
+        Ok(MapOption { style: style.trim().parse()?, seed })
+    }
+}
+
+/// Rule switches lobby players vote on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleOption {
+    /// New players may join the game in progress
+    LateJoin,
+    /// Battles are fought with dice rolls
+    DiceCombat,
+    /// Neutral territories fortify and merge as on hard difficulty
+    AggressiveNeutrals,
+}
+
+impl FromStr for RuleOption {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "late_join" => Ok(RuleOption::LateJoin),
+            "dice_combat" => Ok(RuleOption::DiceCombat),
+            "aggressive_neutrals" => Ok(RuleOption::AggressiveNeutrals),
+            _ => Err(anyhow::anyhow!("Unknown rule option: {}", s)),
+        }
+    }
+}
+
+/// Votes cast on one rule switch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RuleTally {
+    pub rule: RuleOption,
+    pub yes: u32,
+    pub no: u32,
+}
+
+/// State of a lobby's draft, sent after every veto and vote
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DraftStatus {
+    /// The player who may start the game
+    #[schema(value_type = String, format = "uuid")]
+    pub host: Uuid,
+    pub maps: Vec<MapOption>,
+    /// Indices into `maps`
+    pub vetoed: Vec<u32>,
+    pub rules: Vec<RuleTally>,
+    /// AI opponents the host picked, in order; `null` keeps those the game was set up with
+    #[serde(default)]
+    pub ai_opponents: Option<Vec<AIPersonality>>,
+}
+
+/// What a lobby's draft settled on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DraftResult {
+    pub map: MapOption,
+    pub vetoed_maps: Vec<MapOption>,
+    pub enabled_rules: Vec<RuleOption>,
+    /// AI opponents the host picked, in order; `null` keeps those the game was set up with
+    #[serde(default)]
+    pub ai_opponents: Option<Vec<AIPersonality>>,
 }
 
 /// Notification severity level
@@ -2,27 +2,174 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 use super::{
-    BuildingType, CombatResult, GameState, GameStats, NotificationLevel,
+    AIPersonality, BuildingType, CombatResult, DraftResult, DraftStatus, EntityRef, EventCategory, GameState, GameStats,
+    NotificationLevel, PerfStats, Player, PlayerId, Diplomacy, RuleOption, Tech, Territory, TerritoryStats, TraversalRules,
+    VictoryProgress, Battle,
 };
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+/// Predefined quick-chat messages and emotes; clients render them in the
+/// reader's language, so there is no free text to abuse
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuickChatId {
+    Hello,
+    GoodLuck,
+    GoodGame,
+    WellPlayed,
+    Thanks,
+    Sorry,
+    Oops,
+    Wait,
+    AttackNow,
+    HoldPosition,
+    NeedHelp,
+    Laugh,
+}
+
+/// What a notification is about; see `src/i18n.rs` for each key's parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKey {
+    /// Free text from a rule plugin or scenario script: `text`
+    Announcement,
+    /// `player`
+    PlayerJoined,
+    /// `player`
+    PlayerEliminated,
+    /// A reserved seat nobody took went to the AI: `player`, `ai`
+    SlotFilledByAi,
+    /// A built-up territory changed hands: `attacker`, `territory`, `building`, `defender`
+    TerritoryCaptured,
+    /// A player's capital fell: `attacker`, `territory`, `defender`
+    CapitalCaptured,
+    /// `player`, `other`
+    TreatySigned,
+    /// `player`, `other`
+    TreatyBroken,
+    BuildingCompleted,
+    /// `name`
+    GameSaved,
+    /// `tick`
+    GameLoaded,
+    /// `voter`, `player`, `votes`, `needed`
+    VoteKickProgress,
+    /// `player`
+    VoteKickFailed,
+    /// `player`, `ai`
+    VoteKicked,
+    /// The tick loop panicked and the game went on: `tick`
+    GameRecovered,
+    /// The tick loop panicked too often and the game was stopped
+    GameStopped,
+    ReplayEnded,
+    /// `player`
+    GamePaused,
+    /// `player`
+    GameResumed,
+    /// `player`, `speed`
+    GameSpeedChanged,
+}
+
+impl NotificationKey {
+    pub fn category(&self) -> EventCategory {
+        match self {
+            NotificationKey::PlayerEliminated | NotificationKey::TerritoryCaptured | NotificationKey::CapitalCaptured => {
+                EventCategory::Combat
+            }
+            NotificationKey::BuildingCompleted => EventCategory::Economy,
+            NotificationKey::TreatySigned | NotificationKey::TreatyBroken => EventCategory::Diplomacy,
+            NotificationKey::PlayerJoined
+            | NotificationKey::SlotFilledByAi
+            | NotificationKey::VoteKickProgress
+            | NotificationKey::VoteKickFailed
+            | NotificationKey::VoteKicked => EventCategory::Players,
+            NotificationKey::Announcement
+            | NotificationKey::GameSaved
+            | NotificationKey::GameLoaded
+            | NotificationKey::GameRecovered
+            | NotificationKey::GameStopped
+            | NotificationKey::ReplayEnded
+            | NotificationKey::GamePaused
+            | NotificationKey::GameResumed
+            | NotificationKey::GameSpeedChanged => EventCategory::System,
+        }
+    }
+
+    /// Whether the game summary's timeline mentions it
+    pub fn is_highlight(&self) -> bool {
+        matches!(
+            self,
+            NotificationKey::Announcement
+                | NotificationKey::PlayerJoined
+                | NotificationKey::PlayerEliminated
+                | NotificationKey::SlotFilledByAi
+                | NotificationKey::VoteKicked
+                | NotificationKey::CapitalCaptured
+                | NotificationKey::TreatySigned
+                | NotificationKey::TreatyBroken
+        )
+    }
+}
+
 /// Messages sent from client to server
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientMessage {
-    /// Attack a neighboring territory
+    /// First message on every connection: take a seat in the game, optionally
+    /// with a display name, a `#RRGGBB` color and a ladder player key
+    JoinGame {
+        #[serde(default)]
+        #[schema(nullable = true)]
+        name: Option<String>,
+        #[serde(default)]
+        #[schema(nullable = true)]
+        color: Option<String>,
+        /// Secret the client keeps across games, 16 to 128 characters; games
+        /// played with it are rated on the ladder under one identity
+        #[serde(default)]
+        #[schema(nullable = true)]
+        player_key: Option<String>,
+    },
+    /// Attack a neighboring territory; when battles last several ticks, a
+    /// second attack on the same territory reinforces the first
     Attack {
         #[schema(value_type = String, format = "uuid")]
         from: Uuid,
         #[schema(value_type = String, format = "uuid")]
         to: Uuid,
     },
+    /// Call off one of your battles, bringing the survivors home at the
+    /// `retreat_losses` cost
+    Retreat {
+        battle: u64,
+    },
+    /// Move troops to a neighboring territory of your own, every unit type alike
+    MoveTroops {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+        amount: u32,
+    },
     /// Build a structure in a territory
     BuildStructure {
         #[schema(value_type = String, format = "uuid")]
         territory: Uuid,
         building_type: BuildingType,
     },
+    /// Raise the building in one of your territories a level
+    UpgradeBuilding {
+        #[schema(value_type = String, format = "uuid")]
+        territory: Uuid,
+    },
+    /// Tear down the building in one of your territories, with no refund
+    DemolishBuilding {
+        #[schema(value_type = String, format = "uuid")]
+        territory: Uuid,
+    },
     /// Set the troop/worker ratio (0.0-1.0)
     SetTroopRatio {
         ratio: f32,
@@ -31,6 +178,38 @@ pub enum ClientMessage {
     SetAttackRatio {
         ratio: f32,
     },
+    /// Save a troop and attack ratio pair under a name, replacing any saved
+    /// preset of that name
+    SavePreset {
+        name: String,
+        troop_ratio: f32,
+        attack_ratio: f32,
+    },
+    /// Switch to a saved or built-in (`turtle`, `all_in`) preset's ratios at once
+    ApplyPreset {
+        name: String,
+    },
+    /// Set the shares of new troops trained as each unit type; they need
+    /// not add up to 1
+    SetUnitMix {
+        infantry: f32,
+        cavalry: f32,
+        siege: f32,
+    },
+    /// Queue an order to be carried out at `tick`, or at the next tick; it
+    /// waits for the orders queued before it
+    QueueOrder {
+        order: Order,
+        #[serde(default)]
+        #[schema(nullable = true)]
+        tick: Option<u64>,
+    },
+    /// Drop every order still queued
+    ClearOrders,
+    /// Research a tech, paying for it over time
+    StartResearch {
+        tech: Tech,
+    },
     /// Pause the game
     PauseGame,
     /// Resume the game
@@ -39,17 +218,224 @@ pub enum ClientMessage {
     SetGameSpeed {
         speed: f32,
     },
+    /// Offer a treaty to a player, or accept theirs
+    ProposeTreaty {
+        #[schema(value_type = String, format = "uuid")]
+        player: Uuid,
+    },
+    /// End a treaty with a player, or withdraw the offer made to them
+    BreakTreaty {
+        #[schema(value_type = String, format = "uuid")]
+        player: Uuid,
+    },
+    /// Give one of your territories to a treaty partner whose land it borders
+    GiftTerritory {
+        #[schema(value_type = String, format = "uuid")]
+        territory: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to_player: Uuid,
+    },
     /// Request full game state
     GetGameState,
+    /// Request only these territories, answered with `territories`
+    GetTerritories {
+        #[schema(value_type = Vec<String>)]
+        ids: Vec<Uuid>,
+    },
+    /// Request only these players, answered with `players`
+    GetPlayers {
+        #[schema(value_type = Vec<String>)]
+        ids: Vec<Uuid>,
+    },
+    /// Request the shortest route between two territories, answered with `path`
+    GetPath {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+        #[serde(default)]
+        rules: TraversalRules,
+    },
+    /// Compare the checksum of the client's state at `tick` with the server's;
+    /// a mismatch is answered with a full state update
+    VerifyState {
+        tick: u64,
+        checksum: u32,
+    },
+    /// Request the battle tallies of every territory that has seen fighting,
+    /// answered with `territory_stats`
+    GetTerritoryStats,
+    /// Request game loop timings (debug servers only)
+    GetPerfStats,
+    /// Latency probe, answered with `Pong`
+    Ping {
+        nonce: u64,
+    },
+    /// Send a quick-chat message to everyone, or only to `target`'s player
+    QuickChat {
+        id: QuickChatId,
+        #[serde(default)]
+        #[schema(value_type = String, format = "uuid", nullable = true)]
+        target: Option<Uuid>,
+    },
+    /// Choose a starting territory during your turn of the pick phase
+    PickStart {
+        #[schema(value_type = String, format = "uuid")]
+        territory: Uuid,
+    },
+    /// Strike a map from the lobby draft's ballot, by its index
+    VetoMap {
+        map: u32,
+    },
+    /// Vote on one of the lobby draft's rule switches
+    VoteRule {
+        rule: RuleOption,
+        enabled: bool,
+    },
+    /// Replace the AI opponents with one per personality, in the lobby draft;
+    /// host only
+    SetAiOpponents {
+        personalities: Vec<AIPersonality>,
+    },
+    /// End the lobby draft and start the game; host only
+    StartGame,
+    /// Save the game under a name in the server's save directory; host only
+    SaveGame {
+        name: String,
+    },
+    /// Replace the game with a saved one, connected players taking over its
+    /// human seats; host only
+    LoadGame {
+        name: String,
+    },
+    /// Vote to hand another human's faction to the AI, starting a vote if none is running
+    VoteKick {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+    },
+}
+
+impl ClientMessage {
+    /// Wire name of the command, matching its serialized `type` tag
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientMessage::JoinGame { .. } => "join_game",
+            ClientMessage::Attack { .. } => "attack",
+            ClientMessage::Retreat { .. } => "retreat",
+            ClientMessage::MoveTroops { .. } => "move_troops",
+            ClientMessage::BuildStructure { .. } => "build_structure",
+            ClientMessage::UpgradeBuilding { .. } => "upgrade_building",
+            ClientMessage::DemolishBuilding { .. } => "demolish_building",
+            ClientMessage::SetTroopRatio { .. } => "set_troop_ratio",
+            ClientMessage::SetAttackRatio { .. } => "set_attack_ratio",
+            ClientMessage::SavePreset { .. } => "save_preset",
+            ClientMessage::ApplyPreset { .. } => "apply_preset",
+            ClientMessage::SetUnitMix { .. } => "set_unit_mix",
+            ClientMessage::QueueOrder { .. } => "queue_order",
+            ClientMessage::ClearOrders => "clear_orders",
+            ClientMessage::StartResearch { .. } => "start_research",
+            ClientMessage::PauseGame => "pause_game",
+            ClientMessage::ResumeGame => "resume_game",
+            ClientMessage::SetGameSpeed { .. } => "set_game_speed",
+            ClientMessage::ProposeTreaty { .. } => "propose_treaty",
+            ClientMessage::BreakTreaty { .. } => "break_treaty",
+            ClientMessage::GiftTerritory { .. } => "gift_territory",
+            ClientMessage::GetGameState => "get_game_state",
+            ClientMessage::GetTerritories { .. } => "get_territories",
+            ClientMessage::GetPlayers { .. } => "get_players",
+            ClientMessage::GetPath { .. } => "get_path",
+            ClientMessage::VerifyState { .. } => "verify_state",
+            ClientMessage::GetTerritoryStats => "get_territory_stats",
+            ClientMessage::GetPerfStats => "get_perf_stats",
+            ClientMessage::Ping { .. } => "ping",
+            ClientMessage::QuickChat { .. } => "quick_chat",
+            ClientMessage::PickStart { .. } => "pick_start",
+            ClientMessage::VetoMap { .. } => "veto_map",
+            ClientMessage::VoteRule { .. } => "vote_rule",
+            ClientMessage::SetAiOpponents { .. } => "set_ai_opponents",
+            ClientMessage::StartGame => "start_game",
+            ClientMessage::SaveGame { .. } => "save_game",
+            ClientMessage::LoadGame { .. } => "load_game",
+            ClientMessage::VoteKick { .. } => "vote_kick",
+        }
+    }
+
+    /// Whether the command acts on the game itself, rather than asking about
+    /// it, talking to other players or running the lobby
+    pub fn changes_game(&self) -> bool {
+        matches!(
+            self,
+            ClientMessage::Attack { .. }
+                | ClientMessage::Retreat { .. }
+                | ClientMessage::MoveTroops { .. }
+                | ClientMessage::BuildStructure { .. }
+                | ClientMessage::UpgradeBuilding { .. }
+                | ClientMessage::DemolishBuilding { .. }
+                | ClientMessage::SetTroopRatio { .. }
+                | ClientMessage::SetAttackRatio { .. }
+                | ClientMessage::SavePreset { .. }
+                | ClientMessage::ApplyPreset { .. }
+                | ClientMessage::SetUnitMix { .. }
+                | ClientMessage::QueueOrder { .. }
+                | ClientMessage::ClearOrders
+                | ClientMessage::StartResearch { .. }
+                | ClientMessage::PauseGame
+                | ClientMessage::ResumeGame
+                | ClientMessage::SetGameSpeed { .. }
+                | ClientMessage::ProposeTreaty { .. }
+                | ClientMessage::BreakTreaty { .. }
+                | ClientMessage::GiftTerritory { .. }
+        )
+    }
+}
+
+/// A command queued for a later tick
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Order {
+    Attack {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+    },
+    BuildStructure {
+        #[schema(value_type = String, format = "uuid")]
+        territory: Uuid,
+        building_type: BuildingType,
+    },
+    MoveTroops {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+        amount: u32,
+    },
 }
 
 /// Messages sent from server to client
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ServerMessage {
+    /// Reply to `join_game`: the player this connection plays as
+    Joined {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        name: String,
+        color: String,
+    },
     /// Full game state update
     GameStateUpdate {
         state: GameState,
+        /// `state`'s checksum, for clients checking they are in sync
+        #[serde(default)]
+        checksum: u32,
+    },
+    /// What changed since the state at `delta.base_tick`
+    StateDelta {
+        delta: StateDelta,
     },
     /// Result of a combat action
     AttackResult {
@@ -64,6 +450,20 @@ pub enum ServerMessage {
         #[schema(value_type = String, format = "uuid")]
         new_owner: Uuid,
     },
+    /// A player offers you a treaty; `propose_treaty` back to accept
+    TreatyOffered {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+    },
+    /// A territory was given away under a treaty
+    TerritoryTransferred {
+        #[schema(value_type = String, format = "uuid")]
+        territory_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+    },
     /// Building was constructed
     BuildingCompleted {
         #[schema(value_type = String, format = "uuid")]
@@ -72,10 +472,40 @@ pub enum ServerMessage {
         #[schema(value_type = String, format = "uuid")]
         player_id: Uuid,
     },
+    /// An order was queued; sent to its player only
+    OrderQueued {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        order_id: u64,
+        order: Order,
+        /// Tick it is due at
+        tick: u64,
+    },
+    /// A queued order was carried out; sent to its player only
+    OrderExecuted {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        order_id: u64,
+        tick: u64,
+    },
+    /// A queued order could not be carried out, such as an attack from a
+    /// territory lost in the meantime; sent to its player only
+    OrderFailed {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        order_id: u64,
+        reason: String,
+    },
+    /// A player finished researching a tech
+    ResearchCompleted {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        tech: Tech,
+    },
     /// Player was eliminated
     PlayerEliminated {
         #[schema(value_type = String, format = "uuid")]
-        player_id_test: Uuid,
+        player_id: Uuid,
         #[schema(value_type = String, format = "uuid")]
         eliminated_by: Uuid,
     },
@@ -85,11 +515,311 @@ pub enum ServerMessage {
     },
     /// General notification
     Notification {
+        /// Text in the locale the client connected with
         message: String,
         severity: NotificationLevel,
+        /// What happened, for clients that word notifications themselves
+        key: NotificationKey,
+        /// Values of the placeholders in the key's text
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        params: BTreeMap<String, String>,
+        /// Follows from the key
+        #[serde(default)]
+        category: EventCategory,
+        /// Players and territories involved, for screen readers and filters
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        entities: Vec<EntityRef>,
     },
     /// Error response
     Error {
         message: String,
     },
+    /// A command failed through no fault of the player; the server logged the
+    /// details under `incident_id`
+    InternalError {
+        #[schema(value_type = String, format = "uuid")]
+        incident_id: Uuid,
+        message: String,
+    },
+    /// Requested territories, in request order; unknown ids are left out
+    Territories {
+        tick: u64,
+        territories: Vec<Territory>,
+    },
+    /// Requested players, in request order; unknown ids are left out
+    Players {
+        tick: u64,
+        players: Vec<Player>,
+    },
+    /// Shortest route from `from` to `to`, both included; `null` if there is none
+    Path {
+        tick: u64,
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        to: Uuid,
+        #[schema(value_type = Vec<String>, nullable = true)]
+        path: Option<Vec<Uuid>>,
+    },
+    /// Battle tallies of every territory that has seen fighting, the most contested first
+    TerritoryStats {
+        tick: u64,
+        territories: Vec<TerritoryStats>,
+    },
+    /// Game loop timings for debugging
+    PerfStats {
+        stats: PerfStats,
+    },
+    /// Reply to a client `Ping`
+    Pong {
+        nonce: u64,
+        server_tick: u64,
+    },
+    /// Outcome of a command sent with a `command_id`; a retry of a command
+    /// that already ran is answered with `duplicate` set and isn't run again
+    CommandAck {
+        command_id: String,
+        accepted: bool,
+        duplicate: bool,
+    },
+    /// Quick-chat message from another player (or echoed back to the sender)
+    QuickChat {
+        #[schema(value_type = String, format = "uuid")]
+        from: Uuid,
+        id: QuickChatId,
+        /// Set when only the target player was meant to see it
+        #[schema(value_type = String, format = "uuid", nullable = true)]
+        target: Option<Uuid>,
+    },
+    /// A player's turn to pick a start; the server picks for them when time runs out
+    StartPickTurn {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        #[schema(value_type = Vec<String>)]
+        candidates: Vec<Uuid>,
+        seconds: u32,
+    },
+    /// A player's starting territory was chosen
+    StartPicked {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        territory_id: Uuid,
+    },
+    /// Ballot and votes of the lobby draft so far
+    DraftUpdate {
+        draft: DraftStatus,
+    },
+    /// The lobby draft is over; the game is played on `result.map`
+    DraftResolved {
+        result: DraftResult,
+    },
+    /// A command a player sent in the game being replayed
+    ReplayCommand {
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        command: ClientMessage,
+    },
+}
+
+impl ServerMessage {
+    /// Full state update stamped with the state's checksum
+    pub fn state_update(state: GameState) -> Self {
+        let checksum = state.checksum();
+        ServerMessage::GameStateUpdate { state, checksum }
+    }
+
+    /// The player a message is meant for, if it is not for everyone
+    pub fn recipient(&self) -> Option<PlayerId> {
+        match self {
+            ServerMessage::OrderQueued { player_id, .. }
+            | ServerMessage::OrderExecuted { player_id, .. }
+            | ServerMessage::OrderFailed { player_id, .. } => Some((*player_id).into()),
+            _ => None,
+        }
+    }
+}
+
+/// Territories and players that changed between two broadcast states
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StateDelta {
+    /// Tick of the state this delta applies to
+    pub base_tick: u64,
+    pub tick: u64,
+    pub game_speed: f32,
+    pub is_paused: bool,
+    pub game_time_seconds: u32,
+    /// Changed territories, replacing those with the same id
+    pub territories: Vec<Territory>,
+    /// Changed players, replacing those with the same id; new players are appended
+    pub players: Vec<Player>,
+    /// Treaties and offers, if they changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diplomacy: Option<Diplomacy>,
+    /// Victory progress, if it changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub victory: Option<Vec<VictoryProgress>>,
+    /// Battles under way, if they changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battles: Option<Vec<Battle>>,
+    /// Checksum of the state once the delta is applied
+    pub checksum: u32,
+}
+
+/// Where a game is in its life; it only ever moves forward
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    /// Waiting for players, the draft or start picks
+    #[default]
+    Lobby,
+    /// The tick loop is running
+    Running,
+    /// The game has ended; its state, replay and summary are kept for a while
+    Finished,
+    /// Written to storage and let go of
+    Archived,
+}
+
+/// Live snapshot of one running game, for operators
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GameStatus {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+    #[serde(default)]
+    pub lifecycle: Lifecycle,
+    pub tick: u64,
+    pub game_time_seconds: u32,
+    pub is_paused: bool,
+    pub players_alive: u32,
+    pub connected_clients: u32,
+    /// Time budget of a tick
+    #[serde(default)]
+    pub tick_rate_ms: u64,
+    pub perf: PerfStats,
+    /// Delivery health of each connection
+    #[serde(default)]
+    pub connections: Vec<ConnectionStats>,
+}
+
+/// Outgoing delivery counters of one connection
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConnectionStats {
+    #[schema(value_type = String, format = "uuid")]
+    pub connection_id: Uuid,
+    #[schema(value_type = String, format = "uuid")]
+    pub player_id: Uuid,
+    /// Events and critical messages waiting to be sent
+    pub queue_depth: u64,
+    pub messages_sent: u64,
+    /// State updates replaced by a newer one before they could be sent
+    pub dropped_frames: u64,
+    /// Messages skipped because they could not be serialized
+    pub serialization_failures: u64,
+    pub latency_ms: Option<u32>,
+}
+
+/// A game as advertised to every server instance
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GameListing {
+    #[schema(value_type = String, format = "uuid")]
+    pub game_id: Uuid,
+    /// Server instance hosting the game
+    pub instance_id: String,
+    pub tick: u64,
+    pub players_alive: u32,
+    pub connected_clients: u32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Server-wide events streamed to the admin channel
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AdminEvent {
+    /// A client opened a game connection
+    ClientConnected {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        connection_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+    },
+    /// A client connection closed
+    ClientDisconnected {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        connection_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+    },
+    /// A command was rejected
+    CommandRejected {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        connection_id: Uuid,
+        command: String,
+        reason: String,
+    },
+    /// A connection exceeded its command rate limit
+    RateLimitExceeded {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        connection_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        violations: u32,
+        /// The connection was closed as a penalty
+        disconnected: bool,
+    },
+    /// A tick took longer than the tick rate
+    TickOverrun {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        tick: u64,
+        elapsed_ms: u64,
+        budget_ms: u64,
+    },
+    /// Enough players voted to hand a player's faction to the AI
+    PlayerVoteKicked {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        #[schema(value_type = String, format = "uuid")]
+        player_id: Uuid,
+        name: String,
+        votes: u32,
+    },
+    /// The tick loop panicked; `restarted` tells whether the game was recovered
+    GameLoopPanicked {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        tick: u64,
+        message: String,
+        restarted: bool,
+    },
+    /// Periodic per-game summary
+    GameStatus {
+        status: GameStatus,
+    },
+    /// A game's loop stopped for good
+    GameEnded {
+        #[schema(value_type = String, format = "uuid")]
+        game_id: Uuid,
+        reason: GameEndReason,
+        /// Final result, if the game was played to a finish
+        stats: Option<GameStats>,
+    },
+}
+
+/// Why a game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GameEndReason {
+    /// A player won
+    Victory,
+    /// Every human left and the game was not worth finishing
+    Abandoned,
 }
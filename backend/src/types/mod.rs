@@ -1,5 +1,7 @@
 pub mod entities;
 pub mod messages;
+pub mod perf;
 
 pub use entities::*;
 pub use messages::*;
+pub use perf::*;
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// Timing statistics for one instrumented section of the game loop
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct SectionTiming {
+    pub samples: u64,
+    pub total_us: u64,
+    pub last_us: u64,
+    pub max_us: u64,
+}
+
+impl SectionTiming {
+    pub fn record(&mut self, elapsed: Duration) {
+        let us = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        self.samples += 1;
+        self.total_us = self.total_us.saturating_add(us);
+        self.last_us = us;
+        self.max_us = self.max_us.max(us);
+    }
+}
+
+/// Per-section timings of the game loop
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct PerfStats {
+    /// Economy and bookkeeping in `GameEngine::tick`
+    pub tick: SectionTiming,
+    /// Troop distribution and AI decisions
+    pub ai: SectionTiming,
+    /// Individual attack resolutions (human and AI)
+    pub combat: SectionTiming,
+    /// Serializing outgoing messages for clients
+    pub serialization: SectionTiming,
+}
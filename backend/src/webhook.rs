@@ -0,0 +1,25 @@
+//! Game-over notifications.
+//!
+//! With `GAME_SUMMARY_WEBHOOK_URL` set, the summary of every finished game is
+//! POSTed as a Discord-style webhook payload: `content` holds the ready-made
+//! Markdown and `summary` the structured report. `https://` URLs, Discord's
+//! included, are posted to directly.
+
+use serde_json::json;
+use std::time::Duration;
+
+use crate::types::GameSummary;
+
+/// How long a webhook may take to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn post_summary(url: &str, summary: &GameSummary) -> reqwest::Result<()> {
+    let client = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT).build()?;
+    client
+        .post(url)
+        .json(&json!({ "content": summary.text, "summary": summary }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Query, State, WebSocketUpgrade,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::info;
+
+use crate::lobby::Lobby;
+use crate::types::*;
+
+/// Events buffered per admin connection before the oldest are skipped
+const ADMIN_EVENT_BUFFER: usize = 1024;
+
+/// Fan-out point for server-wide admin events
+pub struct AdminHub {
+    tx: broadcast::Sender<AdminEvent>,
+    /// Shared secret required to open the admin channel; disabled when unset
+    token: Option<String>,
+}
+
+impl AdminHub {
+    pub fn new(token: Option<String>) -> Self {
+        let (tx, _) = broadcast::channel(ADMIN_EVENT_BUFFER);
+        Self { tx, token }
+    }
+
+    pub fn publish(&self, event: AdminEvent) {
+        // No receivers just means no admin is watching
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<AdminEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn authorize(&self, token: Option<&str>) -> bool {
+        matches!((&self.token, token), (Some(expected), Some(given)) if expected == given)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AdminQuery {
+    pub token: Option<String>,
+}
+
+/// Admin monitoring WebSocket handler
+pub async fn admin_websocket_handler(
+    ws: WebSocketUpgrade,
+    Query(query): Query<AdminQuery>,
+    State(lobby): State<Arc<Lobby>>,
+) -> Response {
+    if !lobby.main_room().await.admin.authorize(query.token.as_deref()) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    ws.on_upgrade(move |socket| handle_admin_socket(socket, lobby))
+}
+
+async fn handle_admin_socket(socket: WebSocket, lobby: Arc<Lobby>) {
+    let (mut sender, mut receiver) = socket.split();
+    let mut events = lobby.main_room().await.admin.subscribe();
+
+    info!("Admin connected");
+
+    // Start with the current picture of every room rather than waiting for the next summaries
+    for session in lobby.sessions().await {
+        let status = AdminEvent::GameStatus { status: session.status().await };
+        let Ok(json) = serde_json::to_string(&status) else { continue };
+        if sender.send(Message::Text(json)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_string(&event) else { continue };
+                    if sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                // A slow admin misses events instead of stalling the game
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    info!("Admin disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::{TestServer, TEST_ADMIN_TOKEN};
+    use crate::types::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_admin_channel_requires_token() {
+        let server = TestServer::start(false).await;
+        assert!(server.connect_admin("wrong").await.is_err());
+        assert!(server.connect_admin(TEST_ADMIN_TOKEN).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_admin_channel_streams_connections_and_rejections() {
+        let server = TestServer::start(false).await;
+        let mut admin = server.connect_admin(TEST_ADMIN_TOKEN).await.unwrap();
+
+        match admin.recv_as::<AdminEvent>().await {
+            AdminEvent::GameStatus { status } => {
+                assert_eq!(status.game_id, server.session.id);
+                assert_eq!(status.connected_clients, 0);
+            }
+            other => panic!("unexpected first event: {:?}", other),
+        }
+
+        let mut client = server.connect().await;
+        client.recv().await;
+        let connection_id = match admin.recv_as::<AdminEvent>().await {
+            AdminEvent::ClientConnected { connection_id, .. } => connection_id,
+            other => panic!("unexpected event: {:?}", other),
+        };
+
+        client.send(ClientMessage::Attack { from: Uuid::new_v4(), to: Uuid::new_v4() }).await;
+        match admin.recv_as::<AdminEvent>().await {
+            AdminEvent::CommandRejected { connection_id: rejected, command, .. } => {
+                assert_eq!(rejected, connection_id);
+                assert_eq!(command, "attack");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        client.close().await;
+        assert!(matches!(
+            admin.recv_as::<AdminEvent>().await,
+            AdminEvent::ClientDisconnected { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_each_connections_delivery() {
+        let server = TestServer::start(false).await;
+        let _client = server.connect().await;
+        let connection_id = server.session.clients.read().await[0].connection_id;
+
+        let mut admin = server.connect_admin(TEST_ADMIN_TOKEN).await.unwrap();
+        match admin.recv_as::<AdminEvent>().await {
+            AdminEvent::GameStatus { status } => {
+                assert_eq!(status.connections.len(), 1);
+                let connection = &status.connections[0];
+                assert_eq!(connection.connection_id, connection_id);
+                assert_eq!(connection.queue_depth, 0);
+                assert_eq!(connection.serialization_failures, 0);
+            }
+            other => panic!("unexpected first event: {:?}", other),
+        }
+    }
+}
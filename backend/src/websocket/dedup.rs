@@ -0,0 +1,84 @@
+//! Deduplication of retried commands.
+//!
+//! A client may tag any command with a `command_id` of its choosing. The
+//! outcome of each tagged command is remembered per player for a short while,
+//! so when a retry delivers the same command twice it is acknowledged again
+//! instead of being executed again. Ids only need to be unique per player
+//! within that window.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::PlayerId;
+
+/// How long a command id is remembered
+pub const COMMAND_ID_TTL: Duration = Duration::from_secs(60);
+/// Longest command id accepted
+pub const MAX_COMMAND_ID_CHARS: usize = 64;
+
+/// What is known about a command id
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSeen {
+    /// First time; the command should run
+    New,
+    /// Already being executed by an earlier delivery
+    InProgress,
+    /// Already executed, with this outcome
+    Done { accepted: bool },
+}
+
+struct Entry {
+    seen_at: Instant,
+    accepted: Option<bool>,
+}
+
+#[derive(Default)]
+pub struct CommandDedup {
+    entries: Mutex<HashMap<(PlayerId, String), Entry>>,
+}
+
+impl CommandDedup {
+    /// Claim `command_id` for `player`, reporting whether it was seen before
+    pub fn begin(&self, player: PlayerId, command_id: &str, now: Instant) -> CommandSeen {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| now.saturating_duration_since(entry.seen_at) < COMMAND_ID_TTL);
+
+        match entries.get(&(player, command_id.to_string())) {
+            Some(Entry { accepted: Some(accepted), .. }) => CommandSeen::Done { accepted: *accepted },
+            Some(Entry { accepted: None, .. }) => CommandSeen::InProgress,
+            None => {
+                entries.insert((player, command_id.to_string()), Entry { seen_at: now, accepted: None });
+                CommandSeen::New
+            }
+        }
+    }
+
+    /// Record the outcome of a command claimed with `begin`
+    pub fn finish(&self, player: PlayerId, command_id: &str, accepted: bool) {
+        if let Some(entry) = self.entries.lock().unwrap().get_mut(&(player, command_id.to_string())) {
+            entry.accepted = Some(accepted);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_command_ids_are_remembered_per_player_until_they_expire() {
+        let dedup = CommandDedup::default();
+        let (alice, bob): (PlayerId, PlayerId) = (Uuid::new_v4().into(), Uuid::new_v4().into());
+        let now = Instant::now();
+
+        assert_eq!(dedup.begin(alice, "a1", now), CommandSeen::New);
+        assert_eq!(dedup.begin(alice, "a1", now), CommandSeen::InProgress);
+        dedup.finish(alice, "a1", false);
+        assert_eq!(dedup.begin(alice, "a1", now), CommandSeen::Done { accepted: false });
+        assert_eq!(dedup.begin(bob, "a1", now), CommandSeen::New);
+
+        assert_eq!(dedup.begin(alice, "a1", now + COMMAND_ID_TTL), CommandSeen::New);
+    }
+}
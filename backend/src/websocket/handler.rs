@@ -1,65 +1,192 @@
 use axum::{
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        Query, State, WebSocketUpgrade,
     },
-    response::Response,
+    http::StatusCode,
+    response::{IntoResponse, Response},
 };
+use serde::Deserialize;
+use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::{error, info};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
+use crate::i18n::Locale;
+use crate::lobby::Lobby;
+use crate::protobuf;
 use crate::types::*;
-use super::session::GameSession;
+use super::outbox::Outbox;
+use super::session::{ConnectionSlot, GameSession, PendingClient};
+
+/// How often the server measures each connection's round-trip time
+const LATENCY_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+/// How long a new connection has to send `join_game`
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize)]
+pub struct JoinQuery {
+    /// Room to play in; the main room if omitted
+    pub room: Option<Uuid>,
+    /// Session token of a reserved seat, which also picks its room
+    pub token: Option<String>,
+    /// Encoding of the frames the server sends
+    #[serde(default)]
+    pub encoding: WireFormat,
+    /// `pb` for the protobuf protocol in both directions
+    pub proto: Option<WireFormat>,
+    /// Language of notification texts, such as `de`; English if unknown
+    pub locale: Option<String>,
+    /// State updates per second, such as 2 on mobile or 10 on desktop;
+    /// kept within the server's bounds
+    pub update_rate: Option<f32>,
+}
+
+/// How a connection's messages are encoded; JSON text commands are
+/// accepted whatever the format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum WireFormat {
+    /// Text frames holding JSON
+    #[default]
+    #[serde(rename = "json")]
+    Json,
+    /// Binary frames holding MessagePack, much cheaper for full states
+    #[serde(rename = "msgpack")]
+    MessagePack,
+    /// Binary frames holding the messages of `proto/game.proto`, both ways
+    #[serde(rename = "pb")]
+    Protobuf,
+}
+
+/// Optional `command_id` sent alongside a JSON command's own fields
+#[derive(Deserialize)]
+struct CommandId {
+    #[serde(default)]
+    command_id: Option<String>,
+}
+
+/// Decode a command frame and its `command_id`; `None` for frames that aren't commands
+fn parse_command(frame: &Message, format: WireFormat) -> Option<anyhow::Result<(ClientMessage, Option<String>)>> {
+    match frame {
+        Message::Text(text) => Some(parse_json_command(text)),
+        Message::Binary(bytes) if format == WireFormat::Protobuf => Some(protobuf::decode_client_message(bytes)),
+        _ => None,
+    }
+}
+
+fn parse_json_command(text: &str) -> anyhow::Result<(ClientMessage, Option<String>)> {
+    let message = serde_json::from_str(text)?;
+    let CommandId { command_id } = serde_json::from_str(text)?;
+    Ok((message, command_id))
+}
 
 /// WebSocket connection handler
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
-    State(game_session): State<Arc<GameSession>>,
+    Query(query): Query<JoinQuery>,
+    State(lobby): State<Arc<Lobby>>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_socket(socket, game_session))
+    let (game_session, seat) = match (query.token, query.room) {
+        (Some(token), _) => match lobby.find_seat(&token).await {
+            Some((session, player_id)) => (session, Some(player_id)),
+            None => return StatusCode::FORBIDDEN.into_response(),
+        },
+        (None, Some(room_id)) => match lobby.room(room_id).await {
+            Some(session) => (session, None),
+            None => return StatusCode::NOT_FOUND.into_response(),
+        },
+        (None, None) => (lobby.main_room().await, None),
+    };
+    let slot = match lobby.admit_client(&game_session) {
+        Ok(slot) => slot,
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e.to_string()).into_response(),
+    };
+    let format = query.proto.unwrap_or(query.encoding);
+    let locale = query.locale.as_deref().map(Locale::from_tag).unwrap_or_default();
+    let update_rate = query.update_rate;
+    ws.on_upgrade(move |socket| handle_socket(socket, game_session, slot, seat, format, locale, update_rate))
 }
 
-async fn handle_socket(socket: WebSocket, game_session: Arc<GameSession>) {
+async fn handle_socket(
+    socket: WebSocket,
+    game_session: Arc<GameSession>,
+    // Held until the connection closes
+    _slot: ConnectionSlot,
+    seat: Option<PlayerId>,
+    format: WireFormat,
+    locale: Locale,
+    update_rate: Option<f32>,
+) {
     let (mut sender, mut receiver) = socket.split();
 
-    // Create channel for outgoing messages
-    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
-
-    // Get human player ID (first non-AI player)
-    let player_id = {
-        let engine = game_session.engine.read().await;
-        engine.state.players
-            .iter()
-            .find(|p| !p.is_ai)
-            .map(|p| p.id.into())
-            .expect("No human player found")
-    };
+    // Create prioritized queues for outgoing messages
+    let (tx, mut rx) = Outbox::new();
 
-    // Register client
-    game_session.add_client(player_id, tx).await;
+    // Joining registers the client
+    let client = PendingClient { connection_id: Uuid::new_v4(), tx, update_rate };
+    let connection_id = client.connection_id;
+    let Some(player_id) = await_join(&mut sender, &mut receiver, &game_session, &client, seat, format).await else { return };
+    // The session now holds the only outbox, so dropping it there closes the connection
+    drop(client);
 
-    info!("Client connected: {:?}", player_id);
+    info!(game_id = %game_session.id, "Client connected: {:?}", player_id);
+    game_session.admin.publish(AdminEvent::ClientConnected {
+        game_id: game_session.id,
+        connection_id,
+        player_id: player_id.into(),
+    });
 
-    // Send initial game state
+    // Confirm the seat, then send the initial game state
     {
         let engine = game_session.engine.read().await;
-        let initial_state = ServerMessage::GameStateUpdate {
-            state: engine.state.clone(),
-        };
+        if let Ok(player) = engine.get_player(player_id) {
+            let joined = ServerMessage::Joined {
+                game_id: game_session.id,
+                player_id: player.id,
+                name: player.name.clone(),
+                color: player.color.clone(),
+            };
+            if let Ok(frame) = game_session.encode(&joined, format) {
+                let _ = sender.send(frame).await;
+            }
+        }
+        let initial_state = ServerMessage::state_update(engine.state.clone());
 
-        if let Ok(json) = serde_json::to_string(&initial_state) {
-            let _ = sender.send(Message::Text(json)).await;
+        if let Ok(frame) = game_session.encode(&initial_state, format) {
+            let _ = sender.send(frame).await;
         }
     }
 
-    // Spawn task to handle outgoing messages
+    // WebSocket pings carry their send time relative to this instant
+    let connected_at = Instant::now();
+
+    // Spawn task to handle outgoing messages and latency probes
+    let send_session = game_session.clone();
     let mut send_task = tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            if let Ok(json) = serde_json::to_string(&msg) {
-                if sender.send(Message::Text(json)).await.is_err() {
-                    break;
+        let mut probe = tokio::time::interval(LATENCY_PROBE_INTERVAL);
+        loop {
+            tokio::select! {
+                msg = rx.recv() => {
+                    let Some(msg) = msg else { break };
+                    match send_session.encode(&msg.localized(locale), format) {
+                        Ok(frame) => {
+                            if sender.send(frame).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            rx.record_serialization_failure();
+                            tracing::warn!("Skipping unserializable message for connection {}: {}", connection_id, e);
+                        }
+                    }
+                }
+                _ = probe.tick() => {
+                    let sent_us = connected_at.elapsed().as_micros() as u64;
+                    if sender.send(Message::Ping(sent_us.to_le_bytes().to_vec())).await.is_err() {
+                        break;
+                    }
                 }
             }
         }
@@ -69,17 +196,24 @@ async fn handle_socket(socket: WebSocket, game_session: Arc<GameSession>) {
     let session_clone = game_session.clone();
     let mut recv_task = tokio::spawn(async move {
         while let Some(Ok(msg)) = receiver.next().await {
-            if let Message::Text(text) = msg {
-                match serde_json::from_str::<ClientMessage>(&text) {
-                    Ok(client_msg) => {
-                        if let Err(e) = session_clone.handle_message(player_id, client_msg).await {
-                            error!("Error handling message: {}", e);
+            if let Some(command) = parse_command(&msg, format) {
+                match command {
+                    Ok((client_msg, command_id)) => {
+                        if let Err(e) = session_clone.handle_message(connection_id, player_id, client_msg, command_id).await {
+                            warn!(game_id = %session_clone.id, "Dropping client: {}", e);
+                            break;
                         }
                     }
                     Err(e) => {
                         error!("Failed to parse client message: {}", e);
                     }
                 }
+            } else if let Message::Pong(payload) = msg {
+                if let Ok(bytes) = <[u8; 8]>::try_from(payload.as_slice()) {
+                    let sent = Duration::from_micros(u64::from_le_bytes(bytes));
+                    let rtt = connected_at.elapsed().saturating_sub(sent);
+                    session_clone.record_latency(connection_id, rtt).await;
+                }
             } else if let Message::Close(_) = msg {
                 break;
             }
@@ -93,6 +227,543 @@ async fn handle_socket(socket: WebSocket, game_session: Arc<GameSession>) {
     }
 
     // Clean up
-    game_session.remove_client(player_id).await;
-    info!("Client disconnected: {:?}", player_id);
+    game_session.remove_client(connection_id).await;
+    info!(game_id = %game_session.id, "Client disconnected: {:?}", player_id);
+    game_session.admin.publish(AdminEvent::ClientDisconnected {
+        game_id: game_session.id,
+        connection_id,
+        player_id: player_id.into(),
+    });
+}
+
+/// Wait for the connection's `join_game` and seat it, answering anything
+/// else, and joins that fail, with an error; `None` if the client leaves or
+/// takes too long
+async fn await_join(
+    sender: &mut SplitSink<WebSocket, Message>,
+    receiver: &mut SplitStream<WebSocket>,
+    game_session: &GameSession,
+    client: &PendingClient,
+    seat: Option<PlayerId>,
+    format: WireFormat,
+) -> Option<PlayerId> {
+    let deadline = tokio::time::Instant::now() + JOIN_TIMEOUT;
+    loop {
+        let frame = match tokio::time::timeout_at(deadline, receiver.next()).await {
+            Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) => return None,
+            Ok(Some(Ok(frame))) => frame,
+            Err(_) => {
+                info!(game_id = %game_session.id, "Connection closed without joining");
+                return None;
+            }
+        };
+        let Some(command) = parse_command(&frame, format) else { continue };
+
+        let result = match command {
+            Ok((ClientMessage::JoinGame { name, color, player_key }, _)) => {
+                game_session.join(client, seat, name.as_deref(), color.as_deref(), player_key.as_deref()).await
+            }
+            Ok(_) => Err(anyhow::anyhow!("Send join_game first")),
+            Err(e) => Err(anyhow::anyhow!("Invalid message: {}", e)),
+        };
+        match result {
+            Ok(player_id) => return Some(player_id),
+            Err(e) => {
+                let error = ServerMessage::Error { message: e.to_string() };
+                if let Ok(frame) = game_session.encode(&error, format) {
+                    if sender.send(frame).await.is_err() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{GameEngine, MapGenerator};
+    use crate::test_support::{TestServer, TEST_ADMIN_TOKEN};
+    use crate::websocket::{CommandRateLimit, GameSession, RateLimiter};
+    use crate::types::*;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_client_receives_initial_state() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+
+        match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => {
+                assert_eq!(state.territories.len(), 20);
+                assert_eq!(state.players.len(), 4);
+            }
+            other => panic!("unexpected first message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_clients_join_the_room_they_name() {
+        let server = TestServer::start(false).await;
+        let request = serde_json::from_value(serde_json::json!({ "territories": 30, "players": 3 })).unwrap();
+        let room = server.lobby.create(request).await.unwrap();
+
+        let mut client = server.connect_to_room(room.room_id).await.unwrap();
+        match client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await {
+            ServerMessage::GameStateUpdate { state, .. } => assert_eq!(state.territories.len(), 30),
+            _ => unreachable!(),
+        }
+        let session = server.lobby.room(room.room_id).await.unwrap();
+        assert_eq!(session.clients.read().await.len(), 1);
+        assert!(server.session.clients.read().await.is_empty());
+
+        assert!(server.connect_to_room(Uuid::new_v4()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_full_game_turns_away_new_players() {
+        let server = TestServer::start(false).await;
+        let mut player = server.connect().await;
+        player.recv().await;
+
+        // The only human faction is taken, so a newcomer can't share it
+        let mut newcomer = server.open("/ws").await.unwrap();
+        newcomer.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        assert!(matches!(newcomer.recv().await, ServerMessage::Error { message } if message == "No player slots are left in this game"));
+        assert_eq!(server.session.clients.read().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quickplay_seat_is_reserved_for_its_token() {
+        let server = TestServer::start(false).await;
+        let seat = server.lobby.quickplay(None).await.unwrap();
+        assert!(server.connect_with_token("no-such-token").await.is_err());
+
+        // Without the token the only human player is off limits
+        let mut stranger = server.open(&format!("/ws?room={}", seat.room_id)).await.unwrap();
+        stranger.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        assert!(matches!(stranger.recv().await, ServerMessage::Error { message } if message == "No player slots are left in this game"));
+
+        let mut client = server.connect_with_token(&seat.token).await.unwrap();
+        client.recv().await;
+        let session = server.lobby.room(seat.room_id).await.unwrap();
+        assert_eq!(Uuid::from(session.clients.read().await[0].player_id), seat.player_id);
+        let engine = session.engine.read().await;
+        assert_eq!(engine.state.players.iter().filter(|p| p.is_ai).count(), 8);
+        drop(engine);
+
+        // Quick play with the token returns to the running game
+        let resumed = server.lobby.quickplay(Some(&seat.token)).await.unwrap();
+        assert_eq!((resumed.room_id, resumed.player_id), (seat.room_id, seat.player_id));
+    }
+
+    #[tokio::test]
+    async fn test_join_handshake_names_the_player() {
+        let server = TestServer::start(false).await;
+        let mut client = server.open("/ws").await.unwrap();
+
+        client.send(ClientMessage::GetGameState).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { message } if message == "Send join_game first"));
+        assert!(server.session.clients.read().await.is_empty());
+
+        client.send(ClientMessage::JoinGame { name: Some("Ada".into()), color: Some("red".into()), player_key: None }).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { .. }));
+
+        client.send(ClientMessage::JoinGame { name: Some(" Ada ".into()), color: Some("#12abef".into()), player_key: None }).await;
+        let player_id = match client.recv().await {
+            ServerMessage::Joined { game_id, player_id, name, color } => {
+                assert_eq!(game_id, server.session.id);
+                assert_eq!((name.as_str(), color.as_str()), ("Ada", "#12ABEF"));
+                player_id
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        };
+        match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => {
+                let player = state.players.iter().find(|p| p.id == player_id).unwrap();
+                assert!(!player.is_ai);
+                assert_eq!(player.name, "Ada");
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        client.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { message } if message == "You already joined this game"));
+    }
+
+    #[tokio::test]
+    async fn test_msgpack_clients_get_binary_frames() {
+        let server = TestServer::start(false).await;
+        let mut client = server.open("/ws?encoding=msgpack").await.unwrap();
+
+        client.send(ClientMessage::GetGameState).await;
+        assert!(matches!(client.recv_msgpack().await, ServerMessage::Error { message } if message == "Send join_game first"));
+
+        client.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        assert!(matches!(client.recv_msgpack().await, ServerMessage::Joined { .. }));
+        match client.recv_msgpack().await {
+            ServerMessage::GameStateUpdate { state, checksum } => {
+                assert_eq!(state.territories.len(), 20);
+                assert_eq!(checksum, state.checksum());
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        // Commands are still JSON, and replies keep the negotiated format
+        client.send(ClientMessage::Ping { nonce: 9 }).await;
+        assert!(matches!(client.recv_msgpack().await, ServerMessage::Pong { nonce: 9, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_protobuf_clients_speak_binary_both_ways() {
+        let server = TestServer::start(false).await;
+        let mut client = server.open("/ws?proto=pb").await.unwrap();
+
+        // ClientMessage { join_game: {} }, answered by `joined` (field 1) and the state (field 2)
+        client.send_binary(vec![0x0a, 0x00]).await;
+        assert_eq!(client.recv_binary().await[0], 1 << 3 | 2);
+        assert_eq!(client.recv_binary().await[0], 2 << 3 | 2);
+        assert_eq!(server.session.clients.read().await.len(), 1);
+
+        // ClientMessage { ping: { nonce: 9 } } -> ServerMessage { pong: { nonce: 9 } }
+        client.send_binary(vec![6 << 3 | 2, 0x02, 0x08, 0x09]).await;
+        assert_eq!(client.recv_binary().await, [5 << 3 | 2, 0x02, 0x08, 0x09]);
+
+        // JSON commands still work, and other replies are wrapped as JSON (field 15)
+        client.send(ClientMessage::GetPlayers { ids: Vec::new() }).await;
+        let reply = client.recv_binary().await;
+        assert_eq!(reply[0], 15 << 3 | 2);
+        assert!(String::from_utf8_lossy(&reply).contains("\"type\":\"players\""));
+    }
+
+    #[tokio::test]
+    async fn test_get_game_state_replies_to_requester() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send(ClientMessage::SetTroopRatio { ratio: 0.9 }).await;
+        client.send(ClientMessage::GetGameState).await;
+
+        match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => {
+                let human = state.players.iter().find(|p| !p.is_ai).unwrap();
+                assert_eq!(human.troop_ratio, 0.9);
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_partial_queries_return_only_the_requested_entities() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        let state = match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => state,
+            other => panic!("unexpected message: {:?}", other),
+        };
+
+        let wanted = vec![state.territories[3].id, Uuid::new_v4(), state.territories[1].id];
+        client.send(ClientMessage::GetTerritories { ids: wanted }).await;
+        match client.recv().await {
+            ServerMessage::Territories { territories, .. } => {
+                let ids: Vec<Uuid> = territories.iter().map(|t| t.id).collect();
+                assert_eq!(ids, [state.territories[3].id, state.territories[1].id]);
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+
+        client.send(ClientMessage::GetPlayers { ids: vec![state.players[2].id] }).await;
+        match client.recv().await {
+            ServerMessage::Players { players, .. } => {
+                assert_eq!(players.len(), 1);
+                assert_eq!(players[0].name, state.players[2].name);
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_path_query_returns_a_route() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        let state = match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => state,
+            other => panic!("unexpected message: {:?}", other),
+        };
+
+        let (from, to) = (state.territories[0].id, state.territories[0].neighbors[0]);
+        client.send(ClientMessage::GetPath { from, to, rules: TraversalRules::default() }).await;
+        match client.recv().await {
+            ServerMessage::Path { path, .. } => assert_eq!(path, Some(vec![from, to])),
+            other => panic!("unexpected reply: {:?}", other),
+        }
+
+        client.send(ClientMessage::GetPath { from, to: Uuid::new_v4(), rules: TraversalRules::default() }).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_commands_return_errors() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send(ClientMessage::Attack { from: Uuid::new_v4(), to: Uuid::new_v4() }).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { .. }));
+
+        let (territory, gold) = {
+            let engine = server.session.engine.read().await;
+            let human = engine.state.players.iter().find(|p| !p.is_ai).unwrap();
+            let owned = engine.state.territories.iter().find(|t| t.owner == Some(human.id)).unwrap();
+            (owned.id, human.gold)
+        };
+        assert!(gold < BuildingType::City.cost());
+        client.send(ClientMessage::BuildStructure { territory, building_type: BuildingType::City }).await;
+        match client.recv().await {
+            ServerMessage::Error { message } => assert_eq!(message, "Not enough gold"),
+            other => panic!("unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_messages_are_ignored() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send_raw("{\"type\":\"no_such_command\"}").await;
+        client.send_raw("not json").await;
+        client.expect_silence(Duration::from_millis(200)).await;
+
+        // The connection survives and keeps serving requests
+        client.send(ClientMessage::GetGameState).await;
+        assert!(matches!(client.recv().await, ServerMessage::GameStateUpdate { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_successful_build_is_broadcast_to_all_clients() {
+        let server = TestServer::with_humans(3, false).await;
+        let mut clients = server.connect_many(3).await;
+        for client in clients.iter_mut() {
+            client.recv().await;
+        }
+
+        let territory = {
+            let mut engine = server.session.engine.write().await;
+            let human_id = engine.state.players.iter().find(|p| !p.is_ai).unwrap().id;
+            engine.get_player_mut(human_id.into()).unwrap().gold = 10_000;
+            engine.state.territories.iter().find(|t| t.owner == Some(human_id)).unwrap().id
+        };
+
+        clients[0].send(ClientMessage::BuildStructure { territory, building_type: BuildingType::GoldMine }).await;
+
+        for client in clients.iter_mut() {
+            match client.recv().await {
+                ServerMessage::BuildingCompleted { territory_id, building_type, .. } => {
+                    assert_eq!(territory_id, territory);
+                    assert_eq!(building_type, BuildingType::GoldMine);
+                }
+                other => panic!("unexpected broadcast: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_game_loop_streams_state_updates() {
+        let server = TestServer::start(true).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        let mut last_tick = 0;
+        for _ in 0..3 {
+            let update = client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await;
+            if let ServerMessage::GameStateUpdate { state, .. } = update {
+                assert!(state.tick > last_tick);
+                last_tick = state.tick;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_perf_stats_require_debug_mode() {
+        let server = TestServer::start(true).await;
+        let mut client = server.connect().await;
+        client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { state, .. } if state.tick > 0)).await;
+
+        client.send(ClientMessage::GetPerfStats).await;
+        // Updates, deltas and notifications keep arriving meanwhile
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "Performance stats are only available in debug mode"
+        ));
+
+        let stats = server.session.perf_stats().await;
+        assert!(stats.tick.samples > 0);
+        assert!(stats.serialization.samples > 0);
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_unregisters_client() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+        assert_eq!(server.session.clients.read().await.len(), 1);
+
+        client.close().await;
+        for _ in 0..50 {
+            if server.session.clients.read().await.is_empty() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("client was never removed from the session");
+    }
+
+    #[tokio::test]
+    async fn test_ping_is_answered_with_pong() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send(ClientMessage::Ping { nonce: 42 }).await;
+        match client.recv().await {
+            ServerMessage::Pong { nonce, server_tick } => {
+                assert_eq!(nonce, 42);
+                assert_eq!(server_tick, 0);
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_latency_is_published_on_player() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        let connection_id = server.session.clients.read().await[0].connection_id;
+        server.session.record_latency(connection_id, Duration::from_millis(87)).await;
+
+        client.send(ClientMessage::GetGameState).await;
+        match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => {
+                let human = state.players.iter().find(|p| !p.is_ai).unwrap();
+                assert_eq!(human.latency_ms, Some(87));
+            }
+            other => panic!("unexpected reply: {:?}", other),
+        }
+
+        client.close().await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let engine = server.session.engine.read().await;
+        assert!(engine.state.players.iter().all(|p| p.latency_ms.is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_quick_chat_reaches_everyone_and_is_rate_limited() {
+        let server = TestServer::with_humans(2, false).await;
+        let mut clients = server.connect_many(2).await;
+        for client in clients.iter_mut() {
+            client.recv().await;
+        }
+
+        clients[0].send(ClientMessage::QuickChat { id: QuickChatId::GoodLuck, target: None }).await;
+        for client in clients.iter_mut() {
+            assert!(matches!(
+                client.recv().await,
+                ServerMessage::QuickChat { id: QuickChatId::GoodLuck, target: None, .. }
+            ));
+        }
+
+        clients[0].send(ClientMessage::QuickChat { id: QuickChatId::Laugh, target: None }).await;
+        assert!(matches!(clients[0].recv().await, ServerMessage::Error { .. }));
+        clients[1].expect_silence(Duration::from_millis(200)).await;
+    }
+
+    #[tokio::test]
+    async fn test_targeted_quick_chat_skips_other_players() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 3).generate(), 20);
+        engine.rules.allow_late_join = true;
+        let server = TestServer::with_session(GameSession::new(engine), false).await;
+        let mut clients = server.connect_many(3).await;
+        for client in clients.iter_mut() {
+            client.recv().await;
+        }
+        let players: Vec<PlayerId> = server.session.clients.read().await.iter().map(|c| c.player_id).collect();
+
+        clients[0].send(ClientMessage::QuickChat { id: QuickChatId::NeedHelp, target: Some(players[1].into()) }).await;
+        for client in &mut clients[..2] {
+            match client.recv().await {
+                ServerMessage::QuickChat { from, id, target } => {
+                    assert_eq!(from, Uuid::from(players[0]));
+                    assert_eq!(id, QuickChatId::NeedHelp);
+                    assert_eq!(target, Some(players[1].into()));
+                }
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        clients[2].expect_silence(Duration::from_millis(200)).await;
+    }
+
+    #[tokio::test]
+    async fn test_command_flood_is_rejected_then_disconnected() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        session.rate_limiter = Some(RateLimiter::new(CommandRateLimit { per_second: 2, max_violations: 2 }));
+        let server = TestServer::with_session(session, false).await;
+        let mut admin = server.connect_admin(TEST_ADMIN_TOKEN).await.unwrap();
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        for nonce in 0..3 {
+            client.send(ClientMessage::Ping { nonce }).await;
+        }
+        // The error is critical and may overtake the queued pongs
+        let (mut pongs, mut errors) = (Vec::new(), Vec::new());
+        for _ in 0..3 {
+            match client.recv().await {
+                ServerMessage::Pong { nonce, .. } => pongs.push(nonce),
+                ServerMessage::Error { message } => errors.push(message),
+                other => panic!("unexpected reply: {:?}", other),
+            }
+        }
+        assert_eq!(pongs, [0, 1]);
+        assert_eq!(errors, ["Too many commands, slow down"]);
+
+        for nonce in 3..5 {
+            client.send(ClientMessage::Ping { nonce }).await;
+        }
+        client.expect_closed().await;
+
+        loop {
+            if let AdminEvent::RateLimitExceeded { violations, disconnected, .. } = admin.recv_as().await {
+                if disconnected {
+                    assert_eq!(violations, 3);
+                    break;
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_notifications_follow_the_locale_picked_at_handshake() {
+        let server = TestServer::with_humans(2, false).await;
+        let mut german = server.open("/ws?locale=de").await.unwrap();
+        german.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+        german.recv().await;
+        let mut english = server.connect().await;
+
+        let notice = ServerMessage::notification(NotificationKey::PlayerEliminated, NotificationLevel::Warning, [("player", "AI 2".into())]);
+        server.session.broadcast(notice).await;
+        let is_notice = |m: &ServerMessage| matches!(m, ServerMessage::Notification { .. });
+        assert!(matches!(
+            german.recv_until(is_notice).await,
+            ServerMessage::Notification { message, key: NotificationKey::PlayerEliminated, .. } if message == "AI 2 wurde ausgeschaltet"
+        ));
+        assert!(matches!(
+            english.recv_until(is_notice).await,
+            ServerMessage::Notification { message, .. } if message == "AI 2 has been eliminated"
+        ));
+    }
 }
@@ -0,0 +1,48 @@
+//! Unexpected command failures.
+//!
+//! A command that breaks the rules is answered with the reason, as players
+//! need it. A command the server itself fails on, by panicking or by hitting
+//! a disk or encoding error, is logged with its full context under a new
+//! incident id instead, and the player only gets that id to quote.
+
+use futures_util::FutureExt;
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+
+/// A command handler panicked
+#[derive(Debug)]
+pub struct Panicked(pub String);
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "panicked: {}", self.0)
+    }
+}
+
+impl std::error::Error for Panicked {}
+
+/// Run a command handler, turning a panic into a `Panicked` error
+pub async fn catch_panic(handler: impl Future<Output = anyhow::Result<()>>) -> anyhow::Result<()> {
+    match AssertUnwindSafe(handler).catch_unwind().await {
+        Ok(result) => result,
+        Err(panic) => Err(Panicked(panic_message(panic.as_ref())).into()),
+    }
+}
+
+/// The text a panic was raised with
+pub fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Whether a failed command is the server's fault rather than a rules violation
+pub fn is_unexpected(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .any(|cause| cause.is::<Panicked>() || cause.is::<std::io::Error>() || cause.is::<serde_json::Error>())
+}
@@ -1,5 +1,17 @@
+pub mod admin;
+pub mod dedup;
 pub mod handler;
+pub mod incident;
+pub mod outbox;
+pub mod rate_limit;
+pub mod replay;
 pub mod session;
+pub mod spectate;
+pub mod vote_kick;
 
+pub use admin::*;
 pub use handler::*;
+pub use rate_limit::*;
+pub use replay::*;
 pub use session::*;
+pub use spectate::*;
@@ -0,0 +1,288 @@
+use anyhow::{anyhow, Result};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+
+use crate::types::*;
+
+/// Event messages a connection may have waiting before it is closed as a
+/// client that stopped reading
+pub const MAX_QUEUED_EVENTS: usize = 1024;
+
+/// Delivery lane of an outgoing message, in priority order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lane {
+    /// Never dropped and always sent first
+    Critical,
+    /// Regular events, sent in order; overflowing it closes the connection
+    Events,
+    /// High-volume state frames; only the newest unsent one is kept
+    State,
+}
+
+impl Lane {
+    pub fn of(message: &ServerMessage) -> Self {
+        match message {
+            ServerMessage::GameOver { .. } | ServerMessage::PlayerEliminated { .. } | ServerMessage::Error { .. } => {
+                Lane::Critical
+            }
+            ServerMessage::GameStateUpdate { .. } | ServerMessage::StateDelta { .. } => Lane::State,
+            _ => Lane::Events,
+        }
+    }
+}
+
+/// Delivery counters shared by both halves of an outbox
+#[derive(Debug, Default)]
+struct Counters {
+    /// Critical and event messages waiting to be sent
+    queued: AtomicU64,
+    sent: AtomicU64,
+    /// State frames replaced by a newer one before they were sent
+    dropped_frames: AtomicU64,
+    serialization_failures: AtomicU64,
+    /// A state frame is waiting to be sent
+    state_pending: AtomicBool,
+    /// Tick of the newest state frame queued, plus one; zero before the first
+    state_tick: AtomicU64,
+    /// The events lane filled up, so nothing more is queued
+    overflowed: AtomicBool,
+}
+
+/// Point-in-time view of a connection's delivery counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutboxStats {
+    pub queued: u64,
+    pub sent: u64,
+    pub dropped_frames: u64,
+    pub serialization_failures: u64,
+}
+
+/// Sending half of a connection's outgoing queues
+#[derive(Clone)]
+pub struct Outbox {
+    critical: mpsc::UnboundedSender<ServerMessage>,
+    events: mpsc::Sender<ServerMessage>,
+    state: watch::Sender<Option<ServerMessage>>,
+    counters: Arc<Counters>,
+}
+
+/// Receiving half, drained by the connection's send task
+pub struct OutboxReceiver {
+    critical: mpsc::UnboundedReceiver<ServerMessage>,
+    events: mpsc::Receiver<ServerMessage>,
+    state: watch::Receiver<Option<ServerMessage>>,
+    counters: Arc<Counters>,
+}
+
+impl Outbox {
+    pub fn new() -> (Self, OutboxReceiver) {
+        let (critical_tx, critical_rx) = mpsc::unbounded_channel();
+        let (events_tx, events_rx) = mpsc::channel(MAX_QUEUED_EVENTS);
+        let (state_tx, state_rx) = watch::channel(None);
+        let counters = Arc::new(Counters::default());
+        let outbox = Self { critical: critical_tx, events: events_tx, state: state_tx, counters: counters.clone() };
+        let receiver = OutboxReceiver { critical: critical_rx, events: events_rx, state: state_rx, counters };
+        (outbox, receiver)
+    }
+
+    /// Queue a message on its lane; fails once the connection is gone or its
+    /// client has fallen a full events lane behind
+    pub fn send(&self, message: ServerMessage) -> Result<()> {
+        let counters = &self.counters;
+        if counters.overflowed.load(Ordering::Relaxed) {
+            return Err(anyhow!("Client stopped reading"));
+        }
+        let lane = Lane::of(&message);
+        match lane {
+            Lane::Critical | Lane::Events => {}
+            // A newer state supersedes any the client has not been sent yet
+            Lane::State => {
+                let tick = match &message {
+                    ServerMessage::GameStateUpdate { state, .. } => state.tick,
+                    ServerMessage::StateDelta { delta } => delta.tick,
+                    _ => unreachable!(),
+                };
+                counters.state_tick.store(tick + 1, Ordering::Relaxed);
+                let sent = self.state.send(Some(message)).is_ok();
+                if sent && counters.state_pending.swap(true, Ordering::Relaxed) {
+                    counters.dropped_frames.fetch_add(1, Ordering::Relaxed);
+                }
+                return if sent { Ok(()) } else { Err(anyhow!("Connection closed")) };
+            }
+        }
+        // Count first so the send task never sees the queue below zero
+        counters.queued.fetch_add(1, Ordering::Relaxed);
+        let result = match lane {
+            Lane::Critical => self.critical.send(message).map_err(|_| anyhow!("Connection closed")),
+            _ => match self.events.try_send(message) {
+                Ok(()) => Ok(()),
+                Err(TrySendError::Full(_)) => {
+                    // Queue nothing more; the send task delivers what is
+                    // critical and then closes the connection
+                    counters.overflowed.store(true, Ordering::Relaxed);
+                    Err(anyhow!("Client stopped reading"))
+                }
+                Err(TrySendError::Closed(_)) => Err(anyhow!("Connection closed")),
+            },
+        };
+        if result.is_err() {
+            counters.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+        result
+    }
+
+    /// Whether the client has been sent the state at `base_tick` and nothing
+    /// newer, so a delta against it can follow without a gap
+    pub fn can_take_delta(&self, base_tick: u64) -> bool {
+        let counters = &self.counters;
+        !counters.state_pending.load(Ordering::Relaxed) && counters.state_tick.load(Ordering::Relaxed) == base_tick + 1
+    }
+
+    pub fn stats(&self) -> OutboxStats {
+        let counters = &self.counters;
+        OutboxStats {
+            queued: counters.queued.load(Ordering::Relaxed),
+            sent: counters.sent.load(Ordering::Relaxed),
+            dropped_frames: counters.dropped_frames.load(Ordering::Relaxed),
+            serialization_failures: counters.serialization_failures.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl OutboxReceiver {
+    /// Next message to send, highest priority lane first; `None` once every
+    /// sender is gone and the queues are drained, or once the events lane
+    /// overflowed and the critical messages queued before it are sent
+    pub async fn recv(&mut self) -> Option<ServerMessage> {
+        // The lane only fills while the send task is away writing to a stalled
+        // client, so the overflow is seen here on its way back
+        let message = if self.counters.overflowed.load(Ordering::Relaxed) {
+            self.critical.try_recv().ok()?
+        } else {
+            loop {
+                tokio::select! {
+                    biased;
+                    Some(message) = self.critical.recv() => break message,
+                    Some(message) = self.events.recv() => break message,
+                    Ok(()) = self.state.changed() => {
+                        self.counters.state_pending.store(false, Ordering::Relaxed);
+                        if let Some(message) = self.state.borrow_and_update().clone() {
+                            self.counters.sent.fetch_add(1, Ordering::Relaxed);
+                            return Some(message);
+                        }
+                    }
+                    else => return None,
+                }
+            }
+        };
+        self.counters.queued.fetch_sub(1, Ordering::Relaxed);
+        self.counters.sent.fetch_add(1, Ordering::Relaxed);
+        Some(message)
+    }
+
+    /// Note a message that could not be serialized and was skipped
+    pub fn record_serialization_failure(&self) {
+        self.counters.serialization_failures.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_update(tick: u64) -> ServerMessage {
+        let mut state = crate::game::MapGenerator::new(10, 2).generate();
+        state.tick = tick;
+        ServerMessage::state_update(state)
+    }
+
+    #[tokio::test]
+    async fn test_critical_messages_jump_the_state_backlog() {
+        let (outbox, mut rx) = Outbox::new();
+        for tick in 0..20 {
+            outbox.send(state_update(tick)).unwrap();
+        }
+        outbox.send(ServerMessage::notification(NotificationKey::BuildingCompleted, NotificationLevel::Info, [])).unwrap();
+        outbox.send(ServerMessage::Error { message: "nope".to_string() }).unwrap();
+
+        assert!(matches!(rx.recv().await, Some(ServerMessage::Error { .. })));
+        assert!(matches!(rx.recv().await, Some(ServerMessage::Notification { .. })));
+
+        // Stale frames were replaced rather than queued; only the newest is sent
+        match rx.recv().await {
+            Some(ServerMessage::GameStateUpdate { state, .. }) => assert_eq!(state.tick, 19),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        let next = tokio::time::timeout(std::time::Duration::from_millis(20), rx.recv()).await;
+        assert!(next.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_queued_sent_and_dropped() {
+        let (outbox, mut rx) = Outbox::new();
+        for tick in 0..5 {
+            outbox.send(state_update(tick)).unwrap();
+        }
+        outbox.send(ServerMessage::Error { message: "nope".to_string() }).unwrap();
+        assert_eq!(outbox.stats(), OutboxStats { queued: 1, sent: 0, dropped_frames: 4, serialization_failures: 0 });
+
+        rx.recv().await.unwrap();
+        rx.recv().await.unwrap();
+        rx.record_serialization_failure();
+        assert_eq!(outbox.stats(), OutboxStats { queued: 0, sent: 2, dropped_frames: 4, serialization_failures: 1 });
+
+        // A frame sent before the next one arrives is not a drop
+        outbox.send(state_update(5)).unwrap();
+        rx.recv().await.unwrap();
+        outbox.send(state_update(6)).unwrap();
+        assert_eq!(outbox.stats().dropped_frames, 4);
+    }
+
+    #[tokio::test]
+    async fn test_delta_needs_the_sent_base_state() {
+        let (outbox, mut rx) = Outbox::new();
+        assert!(!outbox.can_take_delta(5));
+
+        outbox.send(state_update(5)).unwrap();
+        // Still queued, so a delta would replace the base before it is sent
+        assert!(!outbox.can_take_delta(5));
+        rx.recv().await.unwrap();
+        assert!(outbox.can_take_delta(5));
+        assert!(!outbox.can_take_delta(0));
+    }
+
+    #[tokio::test]
+    async fn test_client_that_never_reads_is_closed_without_losing_critical_messages() {
+        let (outbox, mut rx) = Outbox::new();
+        outbox.send(ServerMessage::Error { message: "before".to_string() }).unwrap();
+        let event = || ServerMessage::notification(NotificationKey::BuildingCompleted, NotificationLevel::Info, []);
+        for _ in 0..MAX_QUEUED_EVENTS {
+            outbox.send(event()).unwrap();
+        }
+        assert_eq!(outbox.stats().queued, MAX_QUEUED_EVENTS as u64 + 1);
+
+        // The backlog stops growing instead of holding every later event
+        assert!(outbox.send(event()).is_err());
+        assert!(outbox.send(ServerMessage::Error { message: "after".to_string() }).is_err());
+        assert!(outbox.send(state_update(1)).is_err());
+        assert_eq!(outbox.stats().queued, MAX_QUEUED_EVENTS as u64 + 1);
+
+        // The critical message queued first still goes out, then the connection closes
+        match rx.recv().await {
+            Some(ServerMessage::Error { message }) => assert_eq!(message, "before"),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(rx.recv().await.is_none());
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_send_fails_after_receiver_drops() {
+        let (outbox, rx) = Outbox::new();
+        drop(rx);
+        assert!(outbox.send(state_update(1)).is_err());
+        assert!(outbox.send(ServerMessage::Error { message: "gone".to_string() }).is_err());
+    }
+}
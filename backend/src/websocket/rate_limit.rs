@@ -0,0 +1,156 @@
+//! Per-player command rate limiting.
+//!
+//! Every player gets a token bucket holding one second of commands. A
+//! command arriving with the bucket empty is rejected and counted as a
+//! violation; a player who keeps flooding the server is disconnected. The
+//! bucket and the violations belong to the player, not the connection, so
+//! reconnecting neither refills the bucket nor wipes the record; violations
+//! are forgiven one at a time as the player behaves.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Time without a new violation that forgives one old one
+const VIOLATION_DECAY: Duration = Duration::from_secs(10);
+
+/// How fast a connection may send commands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommandRateLimit {
+    /// Sustained commands per second, and the size of a burst
+    pub per_second: u32,
+    /// Recent rejected commands tolerated before the connection is closed
+    pub max_violations: u32,
+}
+
+/// What to do with an incoming command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateVerdict {
+    Allowed,
+    /// Drop the command; `violations` counts rejections so far
+    Rejected { violations: u32 },
+    /// Drop the command and close the connection
+    Disconnect { violations: u32 },
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+    violations: u32,
+    /// When a violation was last forgiven, or the record was last clean
+    forgiven_at: Instant,
+}
+
+pub struct RateLimiter {
+    limit: CommandRateLimit,
+    buckets: Mutex<HashMap<Uuid, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: CommandRateLimit) -> Self {
+        Self { limit, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn limit(&self) -> CommandRateLimit {
+        self.limit
+    }
+
+    /// Spend a token for a command from `player_id`, on any of their connections
+    pub fn check(&self, player_id: Uuid, now: Instant) -> RateVerdict {
+        let capacity = self.limit.per_second as f64;
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(player_id)
+            .or_insert(Bucket { tokens: capacity, refilled_at: now, violations: 0, forgiven_at: now });
+        bucket.refill(capacity, now);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return RateVerdict::Allowed;
+        }
+
+        bucket.violations += 1;
+        if bucket.violations > self.limit.max_violations {
+            RateVerdict::Disconnect { violations: bucket.violations }
+        } else {
+            RateVerdict::Rejected { violations: bucket.violations }
+        }
+    }
+
+    /// Drop the players whose bucket is full and record clean, so nothing is lost by forgetting them
+    pub fn prune(&self, now: Instant) {
+        let capacity = self.limit.per_second as f64;
+        self.buckets.lock().unwrap().retain(|_, bucket| {
+            bucket.refill(capacity, now);
+            bucket.violations > 0 || bucket.tokens < capacity
+        });
+    }
+}
+
+impl Bucket {
+    /// Add the tokens earned and forgive the violations lived down since the last command
+    fn refill(&mut self, capacity: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * capacity).min(capacity);
+        self.refilled_at = now;
+
+        if self.violations == 0 {
+            self.forgiven_at = now;
+            return;
+        }
+        let forgiven = (now.saturating_duration_since(self.forgiven_at).as_secs_f64() / VIOLATION_DECAY.as_secs_f64()) as u32;
+        if forgiven > 0 {
+            self.violations = self.violations.saturating_sub(forgiven);
+            self.forgiven_at += VIOLATION_DECAY * forgiven;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_and_repeat_offenders_are_disconnected() {
+        let limiter = RateLimiter::new(CommandRateLimit { per_second: 4, max_violations: 2 });
+        let player = Uuid::new_v4();
+        let start = Instant::now();
+
+        for _ in 0..4 {
+            assert_eq!(limiter.check(player, start), RateVerdict::Allowed);
+        }
+        assert_eq!(limiter.check(player, start), RateVerdict::Rejected { violations: 1 });
+
+        // A quarter second buys one more command
+        let later = start + Duration::from_millis(250);
+        assert_eq!(limiter.check(player, later), RateVerdict::Allowed);
+        assert_eq!(limiter.check(player, later), RateVerdict::Rejected { violations: 2 });
+        assert_eq!(limiter.check(player, later), RateVerdict::Disconnect { violations: 3 });
+
+        // Other players have their own budget
+        assert_eq!(limiter.check(Uuid::new_v4(), later), RateVerdict::Allowed);
+    }
+
+    #[test]
+    fn test_reconnecting_keeps_the_record_until_it_is_lived_down() {
+        let limiter = RateLimiter::new(CommandRateLimit { per_second: 1, max_violations: 2 });
+        let (player, bystander) = (Uuid::new_v4(), Uuid::new_v4());
+        let start = Instant::now();
+        assert_eq!(limiter.check(bystander, start), RateVerdict::Allowed);
+        assert_eq!(limiter.check(player, start), RateVerdict::Allowed);
+        assert_eq!(limiter.check(player, start), RateVerdict::Rejected { violations: 1 });
+        assert_eq!(limiter.check(player, start), RateVerdict::Rejected { violations: 2 });
+
+        // Disconnecting and coming straight back leaves the bucket empty and the record as it was
+        limiter.prune(start);
+        assert_eq!(limiter.check(player, start), RateVerdict::Disconnect { violations: 3 });
+
+        // Each quiet 10 seconds forgives a violation; a clean, full bucket is then forgotten
+        let later = start + VIOLATION_DECAY * 2;
+        assert_eq!(limiter.check(player, later), RateVerdict::Allowed);
+        assert_eq!(limiter.check(player, later), RateVerdict::Rejected { violations: 2 });
+        limiter.prune(later + VIOLATION_DECAY * 3);
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 0);
+    }
+}
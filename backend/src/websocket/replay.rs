@@ -0,0 +1,249 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Path, Query, State, WebSocketUpgrade,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::game::replay::Replay;
+use crate::i18n::Locale;
+use crate::lobby::Lobby;
+use crate::storage::ArchivedGame;
+use crate::types::*;
+
+/// Playback speeds a viewer can pick, as multiples of the recorded pace
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+const MAX_PLAYBACK_SPEED: f32 = 32.0;
+
+#[derive(Debug, Deserialize)]
+pub struct ReplayQuery {
+    /// Starting playback speed; 1 plays the game at its original pace
+    #[serde(default = "default_speed")]
+    speed: f32,
+    /// Language of notification texts, such as `de`
+    locale: Option<String>,
+}
+
+fn default_speed() -> f32 {
+    1.0
+}
+
+/// WebSocket playing back the replay of a finished game
+pub async fn replay_websocket_handler(
+    ws: WebSocketUpgrade,
+    Path(game_id): Path<Uuid>,
+    Query(query): Query<ReplayQuery>,
+    State(lobby): State<Arc<Lobby>>,
+) -> Response {
+    // A game finished a short while ago still has its replay at hand
+    if let Some(replay) = lobby.room(game_id).await.and_then(|session| session.finished_replay()) {
+        return play(ws, replay, &query);
+    }
+
+    // An archived one has it in storage
+    let game_session = lobby.main_room().await;
+    if let Some(storage) = game_session.storage.clone() {
+        match storage.archived_game(game_id).await {
+            Ok(Some(ArchivedGame { replay: Some(replay), .. })) => return play(ws, replay, &query),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to read archived game {}: {}", game_id, e),
+        }
+    }
+
+    let Some(dir) = game_session.replay_dir.clone() else {
+        return (StatusCode::NOT_FOUND, "Replays are disabled on this server").into_response();
+    };
+
+    let path = dir.join(format!("{}.replay", game_id));
+    match tokio::task::spawn_blocking(move || Replay::read(path)).await {
+        Ok(Ok(replay)) => play(ws, replay, &query),
+        Ok(Err(e)) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+        Err(e) => {
+            warn!("Failed to read replay {}: {}", game_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
+
+fn play(ws: WebSocketUpgrade, replay: Replay, query: &ReplayQuery) -> Response {
+    let playback = Playback::new(replay.initial.tick, query.speed);
+    let locale = query.locale.as_deref().map(Locale::from_tag).unwrap_or_default();
+    ws.on_upgrade(move |socket| play_replay(socket, replay, playback, locale))
+}
+
+/// The viewer's place in a replay and how fast it moves
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Playback {
+    /// Replay tick reached, with fractions for time spent between ticks
+    position: f64,
+    speed: f32,
+    paused: bool,
+}
+
+impl Playback {
+    fn new(start: u64, speed: f32) -> Self {
+        Self { position: start as f64, speed: speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED), paused: false }
+    }
+
+    /// Real time left until `tick` is reached at the current speed
+    fn time_until(&self, tick: u64, tick_rate_ms: u64) -> Duration {
+        let ticks = (tick as f64 - self.position).max(0.0);
+        Duration::from_secs_f64(ticks * tick_rate_ms as f64 / 1000.0 / self.speed as f64)
+    }
+
+    /// Move the replay clock on by `elapsed` real time
+    fn advance(&mut self, elapsed: Duration, tick_rate_ms: u64) {
+        if !self.paused {
+            self.position += elapsed.as_secs_f64() * 1000.0 * self.speed as f64 / tick_rate_ms.max(1) as f64;
+        }
+    }
+
+    /// Apply a viewer's command
+    fn control(&mut self, command: ClientMessage) -> Result<(), String> {
+        match command {
+            ClientMessage::PauseGame => self.paused = true,
+            ClientMessage::ResumeGame => self.paused = false,
+            ClientMessage::SetGameSpeed { speed } => self.speed = speed.clamp(MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED),
+            _ => return Err("Replays only take pause_game, resume_game and set_game_speed".to_string()),
+        }
+        Ok(())
+    }
+}
+
+type ReplaySender = SplitSink<WebSocket, Message>;
+
+async fn send(sender: &mut ReplaySender, message: &ServerMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(json) => sender.send(Message::Text(json)).await.is_ok(),
+        Err(e) => {
+            warn!("Failed to serialize replay message: {}", e);
+            true
+        }
+    }
+}
+
+async fn play_replay(socket: WebSocket, replay: Replay, mut playback: Playback, locale: Locale) {
+    let (mut sender, mut receiver) = socket.split();
+    let tick_rate_ms = replay.tick_rate_ms;
+    info!("Replay of game {} started", replay.game_id);
+
+    if !send(&mut sender, &ServerMessage::state_update(replay.initial.clone())).await {
+        return;
+    }
+
+    for tick in &replay.ticks {
+        // Wait for the tick on the replay clock, taking the viewer's controls meanwhile
+        loop {
+            let started = Instant::now();
+            tokio::select! {
+                _ = tokio::time::sleep(playback.time_until(tick.tick, tick_rate_ms)), if !playback.paused => {
+                    playback.position = playback.position.max(tick.tick as f64);
+                    break;
+                }
+                message = receiver.next() => {
+                    playback.advance(started.elapsed(), tick_rate_ms);
+                    match message {
+                        Some(Ok(Message::Text(text))) => {
+                            let outcome = serde_json::from_str(&text)
+                                .map_err(|e| format!("Invalid message: {}", e))
+                                .and_then(|command| playback.control(command));
+                            if let Err(message) = outcome {
+                                if !send(&mut sender, &ServerMessage::Error { message }).await {
+                                    return;
+                                }
+                            }
+                        }
+                        Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                        Some(Ok(_)) => {}
+                    }
+                }
+            }
+        }
+
+        for message in tick.messages() {
+            if !send(&mut sender, &message).await {
+                return;
+            }
+        }
+    }
+
+    let end = ServerMessage::notification(NotificationKey::ReplayEnded, NotificationLevel::Info, []).localized(locale);
+    send(&mut sender, &end).await;
+    let _ = sender.close().await;
+    info!("Replay of game {} finished", replay.game_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{GameEngine, MapGenerator};
+    use crate::test_support::TestServer;
+    use crate::websocket::GameSession;
+
+    #[test]
+    fn test_playback_clock_follows_speed_and_pause() {
+        let mut playback = Playback::new(100, 2.0);
+        assert_eq!(playback.time_until(110, 100), Duration::from_millis(500));
+
+        playback.advance(Duration::from_millis(250), 100);
+        assert_eq!(playback.position, 105.0);
+        playback.control(ClientMessage::PauseGame).unwrap();
+        playback.advance(Duration::from_secs(10), 100);
+        assert_eq!(playback.position, 105.0);
+
+        playback.control(ClientMessage::SetGameSpeed { speed: 1_000.0 }).unwrap();
+        assert_eq!(playback.speed, MAX_PLAYBACK_SPEED);
+        assert!(playback.control(ClientMessage::StartGame).is_err());
+        assert_eq!(playback.time_until(90, 100), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_replay_is_streamed_to_viewers() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 20);
+        let player = engine.state.players[0].id;
+        engine.start_replay(Uuid::new_v4());
+        engine.tick();
+        engine.record_command(player.into(), &ClientMessage::SetAttackRatio { ratio: 0.5 });
+        engine.tick();
+        let replay = engine.finish_replay().unwrap();
+
+        let dir = std::env::temp_dir().join(format!("replays-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        replay.write(dir.join(format!("{}.replay", replay.game_id))).unwrap();
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 2).generate(), 20));
+        session.replay_dir = Some(dir.clone());
+        let server = TestServer::with_session(session, false).await;
+
+        assert!(server.open(&format!("/ws/replay/{}", Uuid::new_v4())).await.is_err());
+        let mut viewer = server.open(&format!("/ws/replay/{}?speed=8", replay.game_id)).await.unwrap();
+        assert!(matches!(viewer.recv().await, ServerMessage::GameStateUpdate { state, .. } if state.tick == 0));
+        viewer.send(ClientMessage::Attack { from: Uuid::new_v4(), to: Uuid::new_v4() }).await;
+
+        // The refusal can land anywhere in the stream
+        let mut messages = Vec::new();
+        loop {
+            match viewer.recv().await {
+                ServerMessage::Error { .. } => {}
+                ServerMessage::Notification { .. } => break,
+                message => messages.push(message),
+            }
+        }
+        viewer.expect_closed().await;
+        assert!(matches!(
+            &messages[..],
+            [
+                ServerMessage::ReplayCommand { player_id, command: ClientMessage::SetAttackRatio { .. } },
+                ServerMessage::GameStateUpdate { state, .. },
+            ] if *player_id == player && state.tick == 2
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
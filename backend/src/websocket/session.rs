@@ -1,42 +1,428 @@
-use std::sync::Arc;
-use tokio::sync::{mpsc, RwLock};
-use anyhow::Result;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{oneshot, Notify, RwLock};
+use anyhow::{anyhow, Result};
+use axum::extract::ws::Message;
+use chrono::Utc;
+use tracing::{error, info, info_span, warn, Instrument, Span};
+use uuid::Uuid;
 
+use crate::audit::{AuditEntry, AuditLog};
+use crate::backplane::Backplane;
+use crate::game::ai::AiDecisionLog;
+use crate::game::draft::Draft;
+use crate::game::replay::Replay;
+use crate::game::snapshot::Checkpoint;
 use crate::game::GameEngine;
+use crate::community::Community;
+use crate::ladder::{player_identity, Ladder, MatchParticipant};
+use crate::storage::{ArchivedGame, MatchResult, Storage};
+use crate::msgpack;
+use crate::protobuf;
+use crate::room_store::token_hash;
+use super::handler::WireFormat;
+use super::admin::AdminHub;
+use super::incident::{catch_panic, is_unexpected, panic_message};
+use super::dedup::{CommandDedup, CommandSeen, MAX_COMMAND_ID_CHARS};
+use super::outbox::Outbox;
+use super::rate_limit::{RateLimiter, RateVerdict};
+use super::vote_kick::{VoteKickRules, VoteKicks, VoteOutcome};
 use crate::types::*;
 
 pub type GameEngineRef = Arc<RwLock<GameEngine>>;
 
 /// Represents a connected client session
 pub struct ClientSession {
+    pub connection_id: Uuid,
     pub player_id: PlayerId,
-    pub tx: mpsc::UnboundedSender<ServerMessage>,
+    /// Prioritized outgoing queues
+    pub tx: Outbox,
+    /// Last measured round-trip time
+    pub latency_ms: Option<u32>,
+    /// Ticks between the state updates this client is sent
+    pub update_interval_ticks: u64,
+}
+
+/// A connection's place under the game's client cap, given back when dropped
+pub struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// A connection waiting to be seated by `join_game`
+pub struct PendingClient {
+    pub connection_id: Uuid,
+    pub tx: Outbox,
+    /// State updates a second the client asked for
+    pub update_rate: Option<f32>,
+}
+
+impl ClientSession {
+    pub fn stats(&self) -> ConnectionStats {
+        let outbox = self.tx.stats();
+        ConnectionStats {
+            connection_id: self.connection_id,
+            player_id: self.player_id.into(),
+            queue_depth: outbox.queued,
+            messages_sent: outbox.sent,
+            dropped_frames: outbox.dropped_frames,
+            serialization_failures: outbox.serialization_failures,
+            latency_ms: self.latency_ms,
+        }
+    }
 }
 
 /// Manages all client connections and game state
 pub struct GameSession {
+    pub id: Uuid,
     pub engine: GameEngineRef,
     pub clients: Arc<RwLock<Vec<ClientSession>>>,
+    /// Connections let in, whether or not they have joined yet
+    connections: Arc<AtomicUsize>,
+    /// Time spent serializing outgoing messages across all connections
+    pub serialization: Mutex<SectionTiming>,
+    /// Enables debug-only messages such as `PerfStats`
+    pub debug: bool,
+    /// Record of every received command, if enabled
+    pub audit: Option<AuditLog>,
+    /// Operator event stream
+    pub admin: Arc<AdminHub>,
+    /// Game registry and spectator fan-out shared with other instances
+    pub backplane: Arc<Backplane>,
+    /// How far behind live play spectators watch, so a streamed match
+    /// doesn't give its players live information
+    pub spectator_delay: Option<Duration>,
+    /// Where to write the state of a game whose tick loop panicked
+    pub crash_dir: Option<PathBuf>,
+    /// Where the host's saved games go; saving is off without it
+    pub save_dir: Option<PathBuf>,
+    /// Where the replays of finished games go; nothing is recorded without it
+    pub replay_dir: Option<PathBuf>,
+    /// How long to wait for humans to claim their slots before the game
+    /// starts; unclaimed slots are then played by AI
+    pub lobby_wait: Option<Duration>,
+    /// Time each human gets to pick a starting territory before the game
+    /// starts; starts stay as generated if unset
+    pub start_pick_time: Option<Duration>,
+    /// Map veto and rule vote held in the lobby; the game starts when the
+    /// host says so
+    pub draft: Option<Mutex<Draft>>,
+    /// End the game once no client has been connected for this long
+    pub idle_timeout: Option<Duration>,
+    /// Hand an idle game to the AI and let it finish instead of ending it
+    pub finish_idle_headless: bool,
+    /// Stop simulating while humans hold seats but none of them is connected
+    pub sleep_when_empty: bool,
+    /// How long a finished game keeps its state, replay and summary in
+    /// memory before it may be archived; kept until the room is needed if unset
+    pub archive_after: Option<Duration>,
+    /// Where to POST the summary of the finished game
+    pub summary_webhook: Option<String>,
+    /// Report of the finished game
+    summary: Mutex<Option<GameSummary>>,
+    /// Ranked ladder the results of won games are recorded in
+    pub ladder: Option<Arc<Ladder>>,
+    /// Profiles finished games are added to
+    pub community: Option<Arc<Community>>,
+    /// Database finished games are recorded in, which also keeps the lobby chat
+    pub storage: Option<Arc<dyn Storage>>,
+    /// When the last client left, if nobody is connected
+    idle_since: Mutex<Option<Instant>>,
+    /// Per-connection command budget, if enabled
+    pub rate_limiter: Option<RateLimiter>,
+    /// Slowest and fastest state updates per second a client may ask for
+    pub min_update_rate: f32,
+    pub max_update_rate: f32,
+    /// Votes among humans to hand a griefer's faction to the AI
+    pub vote_kicks: VoteKicks,
+    /// Outcomes of recent commands that carried a `command_id`
+    command_dedup: CommandDedup,
+    /// Player whose start pick is awaited, and where to deliver it
+    pending_pick: Mutex<Option<(PlayerId, oneshot::Sender<TerritoryId>)>>,
+    /// When each player last sent a quick-chat message
+    quick_chat_sent: Mutex<HashMap<PlayerId, Instant>>,
+    /// Ladder identity of each player whose client joined with a player key
+    identities: Mutex<HashMap<PlayerId, Uuid>>,
+    /// Game at the last broadcast, rolled back to after a tick loop panic
+    last_good: Mutex<Option<Checkpoint>>,
+    /// State last sent on each update interval, the base of its next deltas
+    interval_states: Mutex<HashMap<u64, GameState>>,
+    /// Where the game is in its life, and since when
+    lifecycle: Mutex<(Lifecycle, Instant)>,
+    /// Replay of the finished game, until it is archived
+    finished_replay: Mutex<Option<Replay>>,
+    /// Players reserved for whoever presents the session token, by token hash
+    seats: Mutex<HashMap<String, PlayerId>>,
+    /// Tick and checksum of the latest broadcast states, oldest first
+    recent_checksums: Mutex<VecDeque<(u64, u32)>>,
+    /// Woken by whatever can end an idle spell: a connection or a command
+    activity: Notify,
+    /// States waiting out the spectator delay, with when they were live, oldest first
+    spectator_queue: Mutex<VecDeque<(Instant, ServerMessage)>>,
 }
 
+/// Ticks between periodic game summaries on the admin channel
+const ADMIN_STATUS_INTERVAL_TICKS: u64 = 50;
+/// Minimum time between quick-chat messages from one player
+const QUICK_CHAT_COOLDOWN: Duration = Duration::from_secs(1);
+/// Tick loop panics survived before a game is stopped
+const MAX_LOOP_RESTARTS: u32 = 3;
+/// Ticks between full state broadcasts; the broadcasts in between are deltas
+const KEYFRAME_INTERVAL_TICKS: u64 = 50;
+/// Ticks between state updates for clients that don't ask for a rate
+const DEFAULT_UPDATE_INTERVAL_TICKS: u64 = 5;
+/// Broadcast states whose checksums clients may still verify
+const CHECKSUM_HISTORY: usize = 64;
+/// How often an idle game checks in while it sleeps
+const IDLE_HEARTBEAT: Duration = Duration::from_secs(5);
+/// Longest player name accepted when joining
+const MAX_NAME_CHARS: usize = 24;
+
 impl GameSession {
     pub fn new(engine: GameEngine) -> Self {
         Self {
+            id: Uuid::new_v4(),
             engine: Arc::new(RwLock::new(engine)),
             clients: Arc::new(RwLock::new(Vec::new())),
+            connections: Arc::new(AtomicUsize::new(0)),
+            serialization: Mutex::new(SectionTiming::default()),
+            debug: false,
+            audit: None,
+            admin: Arc::new(AdminHub::new(None)),
+            backplane: Arc::new(Backplane::local(Uuid::new_v4().to_string())),
+            spectator_delay: None,
+            crash_dir: None,
+            save_dir: None,
+            replay_dir: None,
+            lobby_wait: None,
+            start_pick_time: None,
+            draft: None,
+            idle_timeout: None,
+            finish_idle_headless: false,
+            sleep_when_empty: false,
+            archive_after: None,
+            summary_webhook: None,
+            summary: Mutex::new(None),
+            ladder: None,
+            community: None,
+            storage: None,
+            idle_since: Mutex::new(None),
+            rate_limiter: None,
+            min_update_rate: 0.5,
+            max_update_rate: 10.0,
+            vote_kicks: VoteKicks::new(VoteKickRules::default()),
+            command_dedup: CommandDedup::default(),
+            pending_pick: Mutex::new(None),
+            quick_chat_sent: Mutex::new(HashMap::new()),
+            identities: Mutex::new(HashMap::new()),
+            last_good: Mutex::new(None),
+            interval_states: Mutex::new(HashMap::new()),
+            lifecycle: Mutex::new((Lifecycle::Lobby, Instant::now())),
+            finished_replay: Mutex::new(None),
+            seats: Mutex::new(HashMap::new()),
+            recent_checksums: Mutex::new(VecDeque::new()),
+            spectator_queue: Mutex::new(VecDeque::new()),
+            activity: Notify::new(),
+        }
+    }
+
+    /// A new game sharing this one's services and options, except its draft
+    pub fn sibling(&self, engine: GameEngine) -> Self {
+        let mut session = Self::new(engine);
+        session.debug = self.debug;
+        session.audit = self.audit.clone();
+        session.admin = self.admin.clone();
+        session.backplane = self.backplane.clone();
+        session.spectator_delay = self.spectator_delay;
+        session.crash_dir = self.crash_dir.clone();
+        session.save_dir = self.save_dir.clone();
+        session.replay_dir = self.replay_dir.clone();
+        session.lobby_wait = self.lobby_wait;
+        session.start_pick_time = self.start_pick_time;
+        session.idle_timeout = self.idle_timeout;
+        session.finish_idle_headless = self.finish_idle_headless;
+        session.sleep_when_empty = self.sleep_when_empty;
+        session.archive_after = self.archive_after;
+        session.summary_webhook = self.summary_webhook.clone();
+        session.ladder = self.ladder.clone();
+        session.community = self.community.clone();
+        session.storage = self.storage.clone();
+        session.rate_limiter = self.rate_limiter.as_ref().map(|limiter| RateLimiter::new(limiter.limit()));
+        session.min_update_rate = self.min_update_rate;
+        session.max_update_rate = self.max_update_rate;
+        session.vote_kicks = VoteKicks::new(self.vote_kicks.rules());
+        session
+    }
+
+    /// Whether the tick loop has ended, by victory, abandonment or repeated panics
+    pub fn is_stopped(&self) -> bool {
+        self.lifecycle() >= Lifecycle::Finished
+    }
+
+    pub fn lifecycle(&self) -> Lifecycle {
+        self.lifecycle.lock().unwrap().0
+    }
+
+    /// Move the game on to a later stage of its life; false if it is already there or past it
+    fn advance(&self, next: Lifecycle) -> bool {
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        if lifecycle.0 >= next {
+            return false;
+        }
+        info!(game_id = %self.id, "Game moved from {:?} to {:?}", lifecycle.0, next);
+        *lifecycle = (next, Instant::now());
+        true
+    }
+
+    /// Whether the game has been finished for longer than `archive_after`
+    pub fn archive_due(&self) -> bool {
+        let (lifecycle, since) = *self.lifecycle.lock().unwrap();
+        lifecycle == Lifecycle::Finished && self.archive_after.is_some_and(|grace| since.elapsed() >= grace)
+    }
+
+    /// Write a finished game's final state and summary to storage and let go
+    /// of its replay; the lobby then drops the room
+    pub async fn archive(&self) -> Result<()> {
+        if self.lifecycle() != Lifecycle::Finished {
+            return Err(anyhow!("Only finished games can be archived"));
+        }
+        if let Some(storage) = &self.storage {
+            let archive = ArchivedGame {
+                game_id: self.id,
+                archived_at: Utc::now(),
+                summary: self.summary(),
+                state: self.engine.read().await.state.clone(),
+                replay: self.finished_replay(),
+            };
+            storage.archive_game(&archive).await?;
+        }
+        // Another caller may have archived it meanwhile
+        if self.advance(Lifecycle::Archived) {
+            *self.finished_replay.lock().unwrap() = None;
+        }
+        Ok(())
+    }
+
+    /// Replay of the game, while it is finished but not yet archived
+    pub fn finished_replay(&self) -> Option<Replay> {
+        self.finished_replay.lock().unwrap().clone()
+    }
+
+    /// Current summary of this game for operators
+    pub async fn status(&self) -> GameStatus {
+        let perf = self.perf_stats().await;
+        let connections: Vec<ConnectionStats> = self.clients.read().await.iter().map(ClientSession::stats).collect();
+        let engine = self.engine.read().await;
+        GameStatus {
+            game_id: self.id,
+            lifecycle: self.lifecycle(),
+            tick: engine.state.tick,
+            game_time_seconds: engine.state.game_time_seconds,
+            is_paused: engine.state.is_paused,
+            players_alive: engine.state.players.iter().filter(|p| p.is_alive).count() as u32,
+            connected_clients: connections.len() as u32,
+            tick_rate_ms: engine.tick_rate_ms,
+            perf,
+            connections,
+        }
+    }
+
+    /// Advertise this game on the backplane
+    pub async fn announce(&self) {
+        let status = self.status().await;
+        let listing = GameListing {
+            game_id: self.id,
+            instance_id: self.backplane.instance_id().to_string(),
+            tick: status.tick,
+            players_alive: status.players_alive,
+            connected_clients: status.connected_clients,
+            updated_at: Utc::now(),
+        };
+        if let Err(e) = self.backplane.announce(&listing).await {
+            warn!("Failed to announce game on backplane: {}", e);
         }
     }
 
-    /// Add a new client connection
-    pub async fn add_client(&self, player_id: PlayerId, tx: mpsc::UnboundedSender<ServerMessage>) {
-        let session = ClientSession { player_id, tx };
-        self.clients.write().await.push(session);
+    /// Serialize an outgoing message, recording how long it took
+    pub fn serialize(&self, message: &ServerMessage) -> serde_json::Result<String> {
+        let started = Instant::now();
+        let json = serde_json::to_string(message);
+        self.serialization.lock().unwrap().record(started.elapsed());
+        json
+    }
+
+    /// Encode an outgoing message as a frame in the connection's wire format
+    pub fn encode(&self, message: &ServerMessage, format: WireFormat) -> Result<Message> {
+        let started = Instant::now();
+        let frame = match format {
+            WireFormat::Json => serde_json::to_string(message).map(Message::Text).map_err(anyhow::Error::from),
+            WireFormat::MessagePack => msgpack::to_vec(message).map(Message::Binary).map_err(anyhow::Error::from),
+            WireFormat::Protobuf => protobuf::encode_server_message(message).map(Message::Binary),
+        };
+        self.serialization.lock().unwrap().record(started.elapsed());
+        frame
+    }
+
+    /// Snapshot of all game loop timings
+    pub async fn perf_stats(&self) -> PerfStats {
+        let mut stats = self.engine.read().await.perf.clone();
+        stats.serialization = self.serialization.lock().unwrap().clone();
+        stats
+    }
+
+    /// Ticks between the state updates of a client asking for `rate` per
+    /// second, within the server's bounds
+    async fn update_interval(&self, rate: Option<f32>) -> u64 {
+        let ticks_per_second = 1000.0 / self.engine.read().await.tick_rate_ms.max(1) as f32;
+        let rate = rate
+            .filter(|rate| !rate.is_nan())
+            .unwrap_or(ticks_per_second / DEFAULT_UPDATE_INTERVAL_TICKS as f32)
+            .max(self.min_update_rate)
+            .min(self.max_update_rate);
+        ((ticks_per_second / rate).round() as u64).max(1)
     }
 
     /// Remove a client connection
-    pub async fn remove_client(&self, player_id: PlayerId) {
+    pub async fn remove_client(&self, connection_id: Uuid) {
+        let mut clients = self.clients.write().await;
+        let Some(idx) = clients.iter().position(|c| c.connection_id == connection_id) else { return };
+        let player_id = clients.remove(idx).player_id;
+        // The player's rate limit outlives the connection, so reconnecting doesn't reset it
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.prune(Instant::now());
+        }
+
+        let latency = Self::player_latency(&clients, player_id);
+        drop(clients);
+        if let Ok(player) = self.engine.write().await.get_player_mut(player_id) {
+            player.latency_ms = latency;
+        }
+    }
+
+    /// Store a connection's measured round-trip time and publish it on its player
+    pub async fn record_latency(&self, connection_id: Uuid, rtt: Duration) {
         let mut clients = self.clients.write().await;
-        clients.retain(|c| c.player_id != player_id);
+        let Some(client) = clients.iter_mut().find(|c| c.connection_id == connection_id) else { return };
+        client.latency_ms = Some(u32::try_from(rtt.as_millis()).unwrap_or(u32::MAX));
+        let player_id = client.player_id;
+
+        let latency = Self::player_latency(&clients, player_id);
+        drop(clients);
+        if let Ok(player) = self.engine.write().await.get_player_mut(player_id) {
+            player.latency_ms = latency;
+        }
+    }
+
+    /// A player's latency is that of their slowest connection
+    fn player_latency(clients: &[ClientSession], player_id: PlayerId) -> Option<u32> {
+        clients.iter().filter(|c| c.player_id == player_id).filter_map(|c| c.latency_ms).max()
     }
 
     /// Broadcast a message to all clients
@@ -47,103 +433,437 @@ impl GameSession {
         }
     }
 
-    /// Send a message to a specific client
-    pub async fn send_to_client(&self, player_id: PlayerId, message: ServerMessage) {
+    /// Send a state to the clients updated every `interval` ticks: the delta
+    /// to those holding its base state, and the full state to the rest
+    async fn broadcast_state(&self, interval: u64, full: &ServerMessage, delta: Option<StateDelta>) {
+        let base_tick = delta.as_ref().map(|delta| delta.base_tick);
+        let delta = delta.map(|delta| ServerMessage::StateDelta { delta });
+        let clients = self.clients.read().await;
+        for client in clients.iter().filter(|c| c.update_interval_ticks == interval) {
+            let message = match (&delta, base_tick) {
+                (Some(delta), Some(base_tick)) if client.tx.can_take_delta(base_tick) => delta,
+                _ => full,
+            };
+            let _ = client.tx.send(message.clone());
+        }
+    }
+
+    /// Send a message to a single connection
+    pub async fn send_to_connection(&self, connection_id: Uuid, message: ServerMessage) {
         let clients = self.clients.read().await;
-        if let Some(client) = clients.iter().find(|c| c.player_id == player_id) {
+        if let Some(client) = clients.iter().find(|c| c.connection_id == connection_id) {
             let _ = client.tx.send(message);
         }
     }
 
-    /// Handle a client message
-    pub async fn handle_message(&self, player_id: PlayerId, message: ClientMessage) -> Result<()> {
+    /// Send a message to every connection playing as a player
+    async fn send_to_player(&self, player_id: PlayerId, message: ServerMessage) {
+        let clients = self.clients.read().await;
+        for client in clients.iter().filter(|c| c.player_id == player_id) {
+            let _ = client.tx.send(message.clone());
+        }
+    }
+
+    /// Send an engine event to the player it is meant for, or to everyone
+    async fn publish(&self, event: ServerMessage) {
+        match event.recipient() {
+            Some(player_id) => self.send_to_player(player_id, event).await,
+            None => self.broadcast(event).await,
+        }
+    }
+
+    /// Route a quick-chat message: to everyone, or to the target player and
+    /// the sender's own connections only
+    async fn send_quick_chat(&self, from: PlayerId, id: QuickChatId, target: Option<PlayerId>) -> Result<()> {
+        if let Some(target) = target {
+            let engine = self.engine.read().await;
+            engine.get_player(target)?;
+            if target == from {
+                return Err(anyhow!("Cannot send a quick chat to yourself"));
+            }
+        }
+
+        {
+            let mut sent = self.quick_chat_sent.lock().unwrap();
+            let now = Instant::now();
+            if sent.get(&from).is_some_and(|last| now.duration_since(*last) < QUICK_CHAT_COOLDOWN) {
+                return Err(anyhow!("You are sending quick chats too fast"));
+            }
+            sent.insert(from, now);
+        }
+
+        let message = ServerMessage::QuickChat { from: from.into(), id, target: target.map(Uuid::from) };
+        let clients = self.clients.read().await;
+        for client in clients.iter() {
+            if target.is_none_or(|target| client.player_id == target || client.player_id == from) {
+                let _ = client.tx.send(message.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a client message; fails if the connection should be closed
+    pub async fn handle_message(
+        &self,
+        connection_id: Uuid,
+        player_id: PlayerId,
+        message: ClientMessage,
+        command_id: Option<String>,
+    ) -> Result<()> {
+        // Kicked connections are dropped from the client list before they close
+        if !self.clients.read().await.iter().any(|c| c.connection_id == connection_id) {
+            return Err(anyhow!("Connection was removed from the game"));
+        }
+        let verdict = self.rate_limiter.as_ref().map_or(RateVerdict::Allowed, |l| l.check(player_id.into(), Instant::now()));
+        if let RateVerdict::Rejected { violations } | RateVerdict::Disconnect { violations } = verdict {
+            let disconnected = matches!(verdict, RateVerdict::Disconnect { .. });
+            warn!(
+                game_id = %self.id,
+                player_id = %Uuid::from(player_id),
+                violations,
+                disconnected,
+                "Command rate limit exceeded"
+            );
+            self.admin.publish(AdminEvent::RateLimitExceeded {
+                game_id: self.id,
+                connection_id,
+                player_id: player_id.into(),
+                violations,
+                disconnected,
+            });
+            if disconnected {
+                return Err(anyhow!("Connection closed after {} rate limit violations", violations));
+            }
+            self.send_to_connection(connection_id, ServerMessage::Error { message: "Too many commands, slow down".to_string() })
+                .await;
+            return Ok(());
+        }
+
+        // A retried command is acknowledged again rather than run twice
+        if let Some(id) = &command_id {
+            if id.chars().count() > MAX_COMMAND_ID_CHARS {
+                let message = format!("Command ids can be at most {} characters", MAX_COMMAND_ID_CHARS);
+                self.send_to_connection(connection_id, ServerMessage::Error { message }).await;
+                return Ok(());
+            }
+            match self.command_dedup.begin(player_id, id, Instant::now()) {
+                CommandSeen::New => {}
+                // The first delivery acknowledges it once it's done
+                CommandSeen::InProgress => return Ok(()),
+                CommandSeen::Done { accepted } => {
+                    let ack = ServerMessage::CommandAck { command_id: id.clone(), accepted, duplicate: true };
+                    self.send_to_connection(connection_id, ack).await;
+                    return Ok(());
+                }
+            }
+        }
+
+        let tick = self.engine.read().await.state.tick;
+        let message_name = message.name();
+        let span = info_span!(
+            "command",
+            game_id = %self.id,
+            player_id = %Uuid::from(player_id),
+            tick,
+            command = message.name(),
+        );
+
+        let outcome = catch_panic(self.apply_message(connection_id, player_id, message.clone()).instrument(span)).await;
+        let accepted = outcome.is_ok();
+        if accepted {
+            self.activity.notify_one();
+        }
+
+        // Failures of the server itself are logged in full under an id the player can quote
+        let incident = outcome.as_ref().err().filter(|e| is_unexpected(e)).map(|e| {
+            let incident_id = Uuid::new_v4();
+            error!(
+                game_id = %self.id,
+                player_id = %Uuid::from(player_id),
+                %connection_id,
+                tick,
+                %incident_id,
+                command = ?message,
+                "Command failed unexpectedly: {:?}",
+                e
+            );
+            incident_id
+        });
+        let reason = outcome.as_ref().err().map(|e| match incident {
+            Some(incident_id) => format!("Incident {}: {}", incident_id, e),
+            None => e.to_string(),
+        });
+
+        if accepted && message.changes_game() && self.replay_dir.is_some() {
+            self.engine.write().await.record_command(player_id, &message);
+        }
+
+        // Latency probes would drown out real commands
+        let audited = !matches!(message, ClientMessage::Ping { .. });
+        if let Some(audit) = self.audit.as_ref().filter(|_| audited) {
+            audit.record(AuditEntry {
+                timestamp: Utc::now(),
+                game_id: self.id,
+                connection_id,
+                player_id: player_id.into(),
+                tick,
+                command: message,
+                accepted,
+                reason: reason.clone(),
+            });
+        }
+
+        // Rule violations go back to the sender only, incidents without their details
+        if let Some(reason) = reason {
+            self.admin.publish(AdminEvent::CommandRejected {
+                game_id: self.id,
+                connection_id,
+                command: message_name.to_string(),
+                reason: reason.clone(),
+            });
+
+            let reply = match incident {
+                Some(incident_id) => ServerMessage::InternalError {
+                    incident_id,
+                    message: "Something went wrong on the server; please report this incident".to_string(),
+                },
+                None => ServerMessage::Error { message: reason },
+            };
+            self.send_to_connection(connection_id, reply).await;
+        }
+
+        if let Some(command_id) = command_id {
+            self.command_dedup.finish(player_id, &command_id, accepted);
+            self.send_to_connection(connection_id, ServerMessage::CommandAck { command_id, accepted, duplicate: false })
+                .await;
+        }
+
+        Ok(())
+    }
+
+    async fn apply_message(&self, connection_id: Uuid, player_id: PlayerId, message: ClientMessage) -> Result<()> {
         match message {
+            ClientMessage::JoinGame { .. } => {
+                return Err(anyhow!("You already joined this game"));
+            }
             ClientMessage::Attack { from, to } => {
                 let mut engine = self.engine.write().await;
-                match engine.execute_attack(player_id, from.into(), to.into()) {
-                    Ok(result) => {
-                        // Broadcast attack result
-                        drop(engine);
-                        self.broadcast(ServerMessage::AttackResult { result: result.clone() }).await;
-
-                        if result.territory_conquered {
-                            self.broadcast(ServerMessage::TerritoryConquered {
-                                territory_id: result.to_territory,
-                                old_owner: Some(result.defender_id),
-                                new_owner: result.attacker_id,
-                            })
-                            .await;
-                        }
-                    }
-                    Err(e) => {
-                        self.send_to_client(
-                            player_id,
-                            ServerMessage::Error {
-                                message: e.to_string(),
-                            },
-                        )
-                        .await;
-                    }
+                // A battle over several ticks is announced once it ends
+                let Some(result) = engine.launch_attack(player_id, from.into(), to.into())? else { return Ok(()) };
+
+                // Broadcast attack result
+                drop(engine);
+                self.broadcast(ServerMessage::AttackResult { result: result.clone() }).await;
+
+                if result.territory_conquered {
+                    self.broadcast(ServerMessage::TerritoryConquered {
+                        territory_id: result.to_territory,
+                        old_owner: Some(result.defender_id),
+                        new_owner: result.attacker_id,
+                    })
+                    .await;
                 }
             }
+            ClientMessage::Retreat { battle } => {
+                // The outcome goes out with the engine's other events
+                self.engine.write().await.retreat(player_id, battle)?;
+            }
             ClientMessage::BuildStructure { territory, building_type } => {
                 let mut engine = self.engine.write().await;
-                match engine.build_structure(player_id, territory.into(), building_type) {
-                    Ok(_) => {
-                        drop(engine);
-                        self.broadcast(ServerMessage::BuildingCompleted {
-                            territory_id: territory,
-                            building_type,
-                            player_id: player_id.into(),
-                        })
-                        .await;
+                engine.build_structure(player_id, territory.into(), building_type)?;
 
-                        self.broadcast(ServerMessage::Notification {
-                            message: format!("Building completed!"),
-                            severity: NotificationLevel::Success,
-                        })
-                        .await;
-                    }
-                    Err(e) => {
-                        self.send_to_client(
-                            player_id,
-                            ServerMessage::Error {
-                                message: e.to_string(),
-                            },
-                        )
-                        .await;
-                    }
+                drop(engine);
+                self.broadcast(ServerMessage::BuildingCompleted {
+                    territory_id: territory,
+                    building_type,
+                    player_id: player_id.into(),
+                })
+                .await;
+
+                let notice = ServerMessage::notification(NotificationKey::BuildingCompleted, NotificationLevel::Success, [])
+                    .about([EntityRef::Player { id: player_id.into() }, EntityRef::Territory { id: territory }]);
+                self.broadcast(notice).await;
+            }
+            ClientMessage::ProposeTreaty { player } => {
+                let mut engine = self.engine.write().await;
+                let signed = engine.propose_treaty(player_id, player.into())?;
+                drop(engine);
+                if !signed {
+                    let offer = ServerMessage::TreatyOffered { from: player_id.into() };
+                    self.send_to_player(player.into(), offer).await;
                 }
             }
+            ClientMessage::BreakTreaty { player } => {
+                let mut engine = self.engine.write().await;
+                engine.break_treaty(player_id, player.into())?;
+            }
+            ClientMessage::GiftTerritory { territory, to_player } => {
+                let mut engine = self.engine.write().await;
+                engine.gift_territory(player_id, territory.into(), to_player.into())?;
+            }
+            ClientMessage::MoveTroops { from, to, amount } => {
+                let mut engine = self.engine.write().await;
+                engine.move_troops(player_id, from.into(), to.into(), amount)?;
+            }
+            ClientMessage::UpgradeBuilding { territory } => {
+                let mut engine = self.engine.write().await;
+                engine.upgrade_building(player_id, territory.into())?;
+            }
+            ClientMessage::DemolishBuilding { territory } => {
+                let mut engine = self.engine.write().await;
+                engine.demolish_building(player_id, territory.into())?;
+            }
             ClientMessage::SetTroopRatio { ratio } => {
                 let mut engine = self.engine.write().await;
-                let _ = engine.set_troop_ratio(player_id, ratio);
+                engine.set_troop_ratio(player_id, ratio)?;
             }
             ClientMessage::SetAttackRatio { ratio } => {
                 let mut engine = self.engine.write().await;
-                let _ = engine.set_attack_ratio(player_id, ratio);
+                engine.set_attack_ratio(player_id, ratio)?;
+            }
+            ClientMessage::SavePreset { name, troop_ratio, attack_ratio } => {
+                let mut engine = self.engine.write().await;
+                engine.save_preset(player_id, RatioPreset { name, troop_ratio, attack_ratio })?;
+            }
+            ClientMessage::ApplyPreset { name } => {
+                let mut engine = self.engine.write().await;
+                engine.apply_preset(player_id, &name)?;
+            }
+            ClientMessage::SetUnitMix { infantry, cavalry, siege } => {
+                let mut engine = self.engine.write().await;
+                engine.set_unit_mix(player_id, UnitMix { infantry, cavalry, siege })?;
+            }
+            ClientMessage::QueueOrder { order, tick } => {
+                let mut engine = self.engine.write().await;
+                let (order_id, tick) = engine.queue_order(player_id, order.clone(), tick)?;
+                drop(engine);
+                let queued = ServerMessage::OrderQueued { player_id: player_id.into(), order_id, order, tick };
+                self.send_to_player(player_id, queued).await;
+            }
+            ClientMessage::ClearOrders => {
+                self.engine.write().await.clear_orders(player_id);
+            }
+            ClientMessage::StartResearch { tech } => {
+                let mut engine = self.engine.write().await;
+                engine.start_research(player_id, tech)?;
             }
             ClientMessage::PauseGame => {
                 let mut engine = self.engine.write().await;
-                engine.set_paused(true);
+                engine.request_pause(player_id, true)?;
             }
             ClientMessage::ResumeGame => {
                 let mut engine = self.engine.write().await;
-                engine.set_paused(false);
+                engine.request_pause(player_id, false)?;
             }
             ClientMessage::SetGameSpeed { speed } => {
                 let mut engine = self.engine.write().await;
-                engine.set_game_speed(speed);
+                engine.request_game_speed(player_id, speed)?;
+            }
+            ClientMessage::GetPerfStats => {
+                if !self.debug {
+                    return Err(anyhow!("Performance stats are only available in debug mode"));
+                }
+                let stats = self.perf_stats().await;
+                self.send_to_connection(connection_id, ServerMessage::PerfStats { stats }).await;
+            }
+            ClientMessage::Ping { nonce } => {
+                let server_tick = self.engine.read().await.state.tick;
+                self.send_to_connection(connection_id, ServerMessage::Pong { nonce, server_tick }).await;
+            }
+            ClientMessage::QuickChat { id, target } => {
+                self.send_quick_chat(player_id, id, target.map(PlayerId::from)).await?;
+            }
+            ClientMessage::PickStart { territory } => {
+                if !self.engine.read().await.start_candidates().contains(&territory) {
+                    return Err(anyhow!("You can't start there"));
+                }
+                let mut pending = self.pending_pick.lock().unwrap();
+                match pending.take() {
+                    Some((picker, tx)) if picker == player_id => {
+                        let _ = tx.send(territory.into());
+                    }
+                    other => {
+                        *pending = other;
+                        return Err(anyhow!("It's not your turn to pick a start"));
+                    }
+                }
+            }
+            ClientMessage::VetoMap { map } => {
+                self.update_draft(|draft| draft.veto(player_id, map as usize)).await?;
+            }
+            ClientMessage::VoteRule { rule, enabled } => {
+                self.update_draft(|draft| draft.vote(player_id, rule, enabled)).await?;
+            }
+            ClientMessage::SetAiOpponents { personalities } => {
+                let (humans, territories) = {
+                    let engine = self.engine.read().await;
+                    (engine.state.players.iter().filter(|p| !p.is_ai).count(), engine.state.territories.len())
+                };
+                self.update_draft(|draft| draft.set_ai_opponents(player_id, personalities, humans, territories)).await?;
+            }
+            ClientMessage::StartGame => {
+                self.update_draft(|draft| draft.request_start(player_id)).await?;
+            }
+            ClientMessage::SaveGame { name } => {
+                let path = self.save_path(&name)?;
+                let engine = self.engine.read().await;
+                if engine.host() != Some(player_id) {
+                    return Err(anyhow!("Only the host can save the game"));
+                }
+                engine.save(&path)?;
+                drop(engine);
+                let notice = ServerMessage::notification(NotificationKey::GameSaved, NotificationLevel::Success, [("name", name)]);
+                self.send_to_connection(connection_id, notice).await;
+            }
+            ClientMessage::VoteKick { player_id: target } => {
+                self.vote_kick(player_id, target.into()).await?;
+            }
+            ClientMessage::LoadGame { name } => {
+                let path = self.save_path(&name)?;
+                if self.engine.read().await.host() != Some(player_id) {
+                    return Err(anyhow!("Only the host can load a game"));
+                }
+                self.load_game(&path).await?;
             }
             ClientMessage::GetGameState => {
                 let engine = self.engine.read().await;
-                self.send_to_client(
-                    player_id,
-                    ServerMessage::GameStateUpdate {
-                        state: engine.state.clone(),
-                    },
-                )
-                .await;
+                self.send_to_connection(connection_id, ServerMessage::state_update(engine.state.clone())).await;
+            }
+            ClientMessage::GetTerritories { ids } => {
+                let engine = self.engine.read().await;
+                let territories = ids.into_iter().filter_map(|id| engine.get_territory(id.into()).ok().cloned()).collect();
+                let reply = ServerMessage::Territories { tick: engine.state.tick, territories };
+                drop(engine);
+                self.send_to_connection(connection_id, reply).await;
+            }
+            ClientMessage::GetPlayers { ids } => {
+                let engine = self.engine.read().await;
+                let players = ids.into_iter().filter_map(|id| engine.get_player(id.into()).ok().cloned()).collect();
+                let reply = ServerMessage::Players { tick: engine.state.tick, players };
+                drop(engine);
+                self.send_to_connection(connection_id, reply).await;
+            }
+            ClientMessage::GetTerritoryStats => {
+                let engine = self.engine.read().await;
+                let reply = ServerMessage::TerritoryStats { tick: engine.state.tick, territories: engine.territory_stats() };
+                drop(engine);
+                self.send_to_connection(connection_id, reply).await;
+            }
+            ClientMessage::GetPath { from, to, rules } => {
+                let engine = self.engine.read().await;
+                let path = engine.path_between(from.into(), to.into(), rules)?;
+                let reply = ServerMessage::Path { tick: engine.state.tick, from, to, path };
+                drop(engine);
+                self.send_to_connection(connection_id, reply).await;
+            }
+            ClientMessage::VerifyState { tick, checksum } => {
+                // Ticks too old to remember can't be checked
+                let expected = self.checksum_at(tick);
+                if expected.is_some_and(|expected| expected != checksum) {
+                    warn!(game_id = %self.id, "Connection {} out of sync at tick {}, resending state", connection_id, tick);
+                    let state = self.engine.read().await.state.clone();
+                    self.send_to_connection(connection_id, ServerMessage::state_update(state)).await;
+                }
             }
         }
 
@@ -152,45 +872,1483 @@ impl GameSession {
 
     /// Game tick loop
     pub async fn start_game_loop(self: Arc<Self>) {
-        let tick_rate_ms = {
-            let engine = self.engine.read().await;
-            engine.tick_rate_ms
-        };
+        match self.backplane.claim_game(self.id).await {
+            Ok(true) => self.announce().await,
+            Ok(false) => {
+                warn!("Game {} is already hosted by another instance", self.id);
+                return;
+            }
+            // Keep serving local players even if the backplane is down
+            Err(e) => warn!("Failed to claim game on backplane: {}", e),
+        }
 
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(tokio::time::Duration::from_millis(tick_rate_ms));
-
-            loop {
-                interval.tick().await;
-
-                // Update game state
-                {
-                    let mut engine = self.engine.write().await;
-                    engine.tick();
-                    engine.tick_ai();
-
-                    // Check for game over
-                    if let Some(stats) = engine.check_game_over() {
-                        drop(engine);
-                        self.broadcast(ServerMessage::GameOver { stats }).await;
-                        break;
-                    }
+            if self.lobby_wait.is_some() || self.draft.is_some() {
+                self.clone().wait_for_players(self.lobby_wait).await;
+            }
+            if let Some(time) = self.start_pick_time {
+                self.pick_starts(time).await;
+            }
+            // Recorded for the replay files, and for the archive
+            if self.replay_dir.is_some() || self.archive_after.is_some() {
+                self.engine.write().await.start_replay(self.id);
+            }
+            self.advance(Lifecycle::Running);
+            self.clone().supervise(|session: Arc<Self>| async move { session.run_tick().await }).await;
+            self.retire().await;
+            self.drain_spectator_queue().await;
+        });
+    }
+
+    /// Let one more connection in, unless `max` are already in or joining
+    pub fn reserve_connection(&self, max: usize) -> Option<ConnectionSlot> {
+        self.connections.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| (n < max).then_some(n + 1)).ok()?;
+        Some(ConnectionSlot(self.connections.clone()))
+    }
+
+    /// Release what a stopped game no longer needs
+    async fn retire(&self) {
+        if let Err(e) = self.backplane.retire_game(self.id).await {
+            warn!("Failed to retire game on backplane: {}", e);
+        }
+        *self.last_good.lock().unwrap() = None;
+        self.interval_states.lock().unwrap().clear();
+        self.quick_chat_sent.lock().unwrap().clear();
+        // A game ended without a winner keeps its recording too
+        if let Some(replay) = self.engine.write().await.finish_replay() {
+            *self.finished_replay.lock().unwrap() = Some(replay);
+        }
+        self.advance(Lifecycle::Finished);
+        info!(game_id = %self.id, "Game stopped");
+    }
+
+    /// Queue a state for spectators and publish those whose delay is over
+    async fn publish_to_spectators(&self, update: ServerMessage) {
+        let delay = self.spectator_delay.unwrap_or_default();
+        let due: Vec<ServerMessage> = {
+            let mut queue = self.spectator_queue.lock().unwrap();
+            queue.push_back((Instant::now(), update));
+            let due = queue.iter().take_while(|(live_at, _)| live_at.elapsed() >= delay).count();
+            queue.drain(..due).map(|(_, update)| update).collect()
+        };
+
+        // Spectators on any instance watch through the backplane
+        if !self.backplane.has_subscribers(self.id) {
+            return;
+        }
+        for update in due {
+            if let Ok(json) = self.serialize(&update) {
+                if let Err(e) = self.backplane.publish_state(self.id, json) {
+                    warn!("Failed to publish state to backplane: {}", e);
                 }
+            }
+        }
+    }
 
-                // Broadcast state update every 5 ticks (reduce network traffic)
-                let tick = {
-                    let engine = self.engine.read().await;
-                    engine.state.tick
-                };
+    /// Play the end of a stopped game out to spectators, as delayed as the rest
+    async fn drain_spectator_queue(&self) {
+        let delay = self.spectator_delay.unwrap_or_default();
+        loop {
+            let Some((live_at, update)) = self.spectator_queue.lock().unwrap().pop_front() else { break };
+            tokio::time::sleep_until((live_at + delay).into()).await;
+            if !self.backplane.has_subscribers(self.id) {
+                continue;
+            }
+            if let Ok(json) = self.serialize(&update) {
+                if let Err(e) = self.backplane.publish_state(self.id, json) {
+                    warn!("Failed to publish state to backplane: {}", e);
+                }
+            }
+        }
+    }
 
-                if tick % 5 == 0 {
-                    let engine = self.engine.read().await;
-                    self.broadcast(ServerMessage::GameStateUpdate {
-                        state: engine.state.clone(),
-                    })
-                    .await;
+    fn record_checksum(&self, tick: u64, checksum: u32) {
+        let mut recent = self.recent_checksums.lock().unwrap();
+        // After a rewind the forgotten ticks will be played again
+        while recent.back().is_some_and(|&(t, _)| t >= tick) {
+            recent.pop_back();
+        }
+        if recent.len() == CHECKSUM_HISTORY {
+            recent.pop_front();
+        }
+        recent.push_back((tick, checksum));
+    }
+
+    /// Checksum of the state broadcast at `tick`, if it is still remembered
+    fn checksum_at(&self, tick: u64) -> Option<u32> {
+        self.recent_checksums.lock().unwrap().iter().find(|&&(t, _)| t == tick).map(|&(_, checksum)| checksum)
+    }
+
+    /// Summary of the game, once it has been won
+    pub fn summary(&self) -> Option<GameSummary> {
+        self.summary.lock().unwrap().clone()
+    }
+
+    fn publish_summary(&self, mut summary: GameSummary) {
+        summary.draft = self.draft.as_ref().and_then(|draft| draft.lock().unwrap().result().cloned());
+        *self.summary.lock().unwrap() = Some(summary.clone());
+        if let Some(url) = self.summary_webhook.clone() {
+            tokio::spawn(async move {
+                if let Err(e) = crate::webhook::post_summary(&url, &summary).await {
+                    warn!(game_id = %summary.game_id, "Failed to post game summary: {}", e);
                 }
+            });
+        }
+    }
+
+    /// Apply the idle policy; true if the game should end now
+    async fn check_idle(&self) -> bool {
+        let Some(timeout) = self.idle_timeout else { return false };
+        let connected = !self.clients.read().await.is_empty();
+        let idle_for = {
+            let mut idle_since = self.idle_since.lock().unwrap();
+            if connected {
+                *idle_since = None;
+                return false;
             }
-        });
+            idle_since.get_or_insert_with(Instant::now).elapsed()
+        };
+        if idle_for < timeout {
+            return false;
+        }
+
+        if !self.finish_idle_headless {
+            info!(game_id = %self.id, "Ending game abandoned for {:?}", idle_for);
+            self.admin.publish(AdminEvent::GameEnded { game_id: self.id, reason: GameEndReason::Abandoned, stats: None });
+            return true;
+        }
+
+        // Let the AI play out the abandoned seats at full speed; nobody is
+        // listening for the hand-over notices
+        let mut engine = self.engine.write().await;
+        if !engine.backfill_ai(&[]).is_empty() {
+            info!(game_id = %self.id, "Finishing abandoned game with AI only");
+            engine.set_game_speed(f32::MAX);
+            engine.take_events();
+        }
+        false
+    }
+
+    /// Hold the game until every human slot is claimed, or the host starts a
+    /// drafted game, or the wait runs out, then hand the empty slots to AI
+    async fn wait_for_players(self: Arc<Self>, wait: Option<Duration>) {
+        let deadline = wait.map(|wait| tokio::time::Instant::now() + wait);
+        while deadline.is_none_or(|deadline| tokio::time::Instant::now() < deadline) {
+            let ready = match &self.draft {
+                Some(draft) => draft.lock().unwrap().start_requested(),
+                None => self.unclaimed_slots().await.is_empty(),
+            };
+            if ready {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        if let Some(draft) = &self.draft {
+            let result = draft.lock().unwrap().resolve();
+            info!(
+                game_id = %self.id,
+                "Draft picked {:?} map {} with rules {:?}", result.map.style, result.map.seed, result.enabled_rules
+            );
+            self.engine.write().await.apply_draft(&result);
+            self.broadcast(ServerMessage::DraftResolved { result }).await;
+        }
+
+        let claimed: Vec<PlayerId> = self.clients.read().await.iter().map(|c| c.player_id).collect();
+        let mut engine = self.engine.write().await;
+        let replaced = engine.backfill_ai(&claimed);
+        if !replaced.is_empty() {
+            info!(game_id = %self.id, "Backfilled {} empty slots with AI", replaced.len());
+        }
+        let events = engine.take_events();
+        let state = engine.state.clone();
+        drop(engine);
+
+        for event in events {
+            self.publish(event).await;
+        }
+        self.broadcast(ServerMessage::state_update(state)).await;
+    }
+
+    /// Let every player choose a starting territory in turn; AI players, and
+    /// humans who let the countdown run out, get the heuristic's pick
+    async fn pick_starts(&self, time: Duration) {
+        let order: Vec<(PlayerId, bool)> = {
+            let mut engine = self.engine.write().await;
+            engine.release_starts();
+            engine.state.players.iter().filter(|p| p.is_alive).map(|p| (p.id.into(), p.is_ai)).collect()
+        };
+        let state = self.engine.read().await.state.clone();
+        self.broadcast(ServerMessage::state_update(state)).await;
+
+        for (player_id, is_ai) in order {
+            let choice = if is_ai { None } else { self.await_pick(player_id, time).await };
+
+            let mut engine = self.engine.write().await;
+            let claimed = match choice {
+                Some(territory) => engine.claim_start(player_id, territory).map(|()| territory),
+                None => Err(anyhow!("No pick made")),
+            }
+            .or_else(|_| {
+                let territory = engine.suggest_start().ok_or_else(|| anyhow!("No territory left to start on"))?;
+                engine.claim_start(player_id, territory.into()).map(|()| territory.into())
+            });
+            drop(engine);
+
+            match claimed {
+                Ok(territory) => {
+                    self.broadcast(ServerMessage::StartPicked { player_id: player_id.into(), territory_id: territory.into() })
+                        .await;
+                }
+                Err(e) => warn!(game_id = %self.id, "Could not place start for {:?}: {}", player_id, e),
+            }
+        }
+
+        let state = self.engine.read().await.state.clone();
+        self.broadcast(ServerMessage::state_update(state)).await;
+    }
+
+    /// Count a vote to kick `target`, handing their faction to the AI once it passes
+    async fn vote_kick(&self, voter: PlayerId, target: PlayerId) -> Result<()> {
+        self.expire_kick_vote().await;
+        let (voter_name, target_name) = {
+            let engine = self.engine.read().await;
+            let player = engine.get_player(target)?;
+            if player.is_ai || !player.is_alive {
+                return Err(anyhow!("Only human players still in the game can be kicked"));
+            }
+            if voter == target {
+                return Err(anyhow!("You can't vote to kick yourself"));
+            }
+            (engine.get_player(voter)?.name.clone(), player.name.clone())
+        };
+        let mut voters: Vec<PlayerId> = self.clients.read().await.iter().map(|c| c.player_id).filter(|p| *p != target).collect();
+        voters.sort_by_key(|p| Uuid::from(*p));
+        voters.dedup();
+
+        match self.vote_kicks.vote(voter, target, voters.len(), Instant::now())? {
+            VoteOutcome::Open { votes, needed } => {
+                let params = [
+                    ("voter", voter_name),
+                    ("player", target_name),
+                    ("votes", votes.to_string()),
+                    ("needed", needed.to_string()),
+                ];
+                let notice = ServerMessage::notification(NotificationKey::VoteKickProgress, NotificationLevel::Warning, params)
+                    .about([EntityRef::Player { id: voter.into() }, EntityRef::Player { id: target.into() }]);
+                self.broadcast(notice).await;
+            }
+            VoteOutcome::Passed { votes } => self.kick(target, votes).await?,
+        }
+        Ok(())
+    }
+
+    /// Announce the end of a vote-kick that ran out of time
+    async fn expire_kick_vote(&self) {
+        let Some(target) = self.vote_kicks.expire(Instant::now()) else { return };
+        let name = self.engine.read().await.get_player(target).map(|p| p.name.clone()).unwrap_or_default();
+        let notice = ServerMessage::notification(NotificationKey::VoteKickFailed, NotificationLevel::Info, [("player", name)])
+            .about([EntityRef::Player { id: target.into() }]);
+        self.broadcast(notice).await;
+    }
+
+    /// Hand a vote-kicked player's faction to the AI and close their connections
+    async fn kick(&self, target: PlayerId, votes: usize) -> Result<()> {
+        let (name, ai_name) = {
+            let mut engine = self.engine.write().await;
+            let name = engine.get_player(target)?.name.clone();
+            (name, engine.hand_to_ai(target)?)
+        };
+        self.seats.lock().unwrap().retain(|_, seat| *seat != target);
+
+        // Their connections close once the notice is sent and their outboxes are dropped
+        let kicked: Vec<ClientSession> = {
+            let mut clients = self.clients.write().await;
+            let (kicked, kept) = std::mem::take(&mut *clients).into_iter().partition(|c| c.player_id == target);
+            *clients = kept;
+            kicked
+        };
+        for client in kicked {
+            let _ = client.tx.send(ServerMessage::Error { message: "You were vote-kicked from the game".to_string() });
+        }
+
+        info!(game_id = %self.id, player_id = %Uuid::from(target), votes, "Vote-kicked {}", name);
+        self.admin.publish(AdminEvent::PlayerVoteKicked {
+            game_id: self.id,
+            player_id: target.into(),
+            name: name.clone(),
+            votes: votes as u32,
+        });
+        let notice =
+            ServerMessage::notification(NotificationKey::VoteKicked, NotificationLevel::Warning, [("player", name), ("ai", ai_name)])
+                .about([EntityRef::Player { id: target.into() }]);
+        self.engine.write().await.note_event(&notice);
+        self.broadcast(notice).await;
+        Ok(())
+    }
+
+    /// File of a named save; names are kept to letters, digits, `-` and `_`
+    fn save_path(&self, name: &str) -> Result<PathBuf> {
+        let dir = self.save_dir.as_ref().ok_or_else(|| anyhow!("Saving is disabled on this server"))?;
+        let valid = !name.is_empty()
+            && name.len() <= 64
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(anyhow!("Save names may only use letters, digits, - and _"));
+        }
+        std::fs::create_dir_all(dir)?;
+        Ok(dir.join(format!("{}.json", name)))
+    }
+
+    /// Write a finished game's replay to `replay_dir`
+    fn write_replay(&self, replay: &Replay) -> Result<()> {
+        let Some(dir) = &self.replay_dir else { return Ok(()) };
+        std::fs::create_dir_all(dir)?;
+        replay.write(dir.join(format!("{}.replay", self.id)))?;
+        info!(game_id = %self.id, events = replay.len(), "Replay written");
+
+        // Joins, kicks and plugins aren't in the log, so a mismatch is a hint rather than a failure
+        if self.debug {
+            let recorded = replay.ticks.last().and_then(|t| t.state.as_ref()).map(|s| s.checksum());
+            if recorded != Some(replay.resimulate().state.checksum()) {
+                warn!(game_id = %self.id, "Replay does not re-simulate to the final state");
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap the running game for a saved one; connected players keep their
+    /// ids and take over its human seats
+    async fn load_game(&self, path: &std::path::Path) -> Result<()> {
+        let mut loaded = GameEngine::load(path)?;
+        let mut players: Vec<PlayerId> = Vec::new();
+        for client in self.clients.read().await.iter() {
+            if !players.contains(&client.player_id) {
+                players.push(client.player_id);
+            }
+        }
+        loaded.seat_players(&players)?;
+
+        let mut engine = self.engine.write().await;
+        loaded.plugins = std::mem::take(&mut engine.plugins);
+        loaded.ai_log = engine.ai_log.take();
+        // The save's own recording is of other seats; record afresh if this game records
+        loaded.replay = None;
+        if engine.replay.is_some() {
+            loaded.start_replay(self.id);
+        }
+        *engine = loaded;
+        let state = engine.state.clone();
+        drop(engine);
+
+        // Deltas and checksums of the old game mean nothing now
+        *self.last_good.lock().unwrap() = None;
+        self.interval_states.lock().unwrap().clear();
+        self.recent_checksums.lock().unwrap().clear();
+        let tick = state.tick;
+        self.broadcast(ServerMessage::state_update(state)).await;
+        self.broadcast(ServerMessage::notification(
+            NotificationKey::GameLoaded,
+            NotificationLevel::Info,
+            [("tick", tick.to_string())],
+        ))
+        .await;
+        Ok(())
+    }
+
+    /// Ballot of the draft while it is still open
+    fn open_draft(&self) -> Option<DraftStatus> {
+        let draft = self.draft.as_ref()?.lock().unwrap();
+        draft.is_open().then(|| draft.status())
+    }
+
+    /// Apply a player's draft command and show everyone the new ballot
+    async fn update_draft(&self, update: impl FnOnce(&mut Draft) -> Result<()>) -> Result<()> {
+        let draft = self.draft.as_ref().ok_or_else(|| anyhow!("This lobby has no draft"))?;
+        let status = {
+            let mut draft = draft.lock().unwrap();
+            update(&mut draft)?;
+            draft.status()
+        };
+        self.broadcast(ServerMessage::DraftUpdate { draft: status }).await;
+        Ok(())
+    }
+
+    /// Offer a human their turn and wait for their choice until the countdown ends
+    async fn await_pick(&self, player_id: PlayerId, time: Duration) -> Option<TerritoryId> {
+        let (tx, rx) = oneshot::channel();
+        *self.pending_pick.lock().unwrap() = Some((player_id, tx));
+        let candidates = self.engine.read().await.start_candidates();
+        self.broadcast(ServerMessage::StartPickTurn {
+            player_id: player_id.into(),
+            candidates,
+            seconds: time.as_secs_f32().ceil() as u32,
+        })
+        .await;
+
+        let choice = tokio::time::timeout(time, rx).await.ok().and_then(Result::ok);
+        *self.pending_pick.lock().unwrap() = None;
+        choice
+    }
+
+    /// Reserve a human player for connections presenting the returned session token
+    pub fn reserve_seat(&self, player_id: PlayerId) -> String {
+        let token = Uuid::new_v4().simple().to_string();
+        self.seats.lock().unwrap().insert(token_hash(&token), player_id);
+        token
+    }
+
+    /// The player a session token was issued for
+    pub fn seat(&self, token: &str) -> Option<PlayerId> {
+        self.seats.lock().unwrap().get(&token_hash(token)).copied()
+    }
+
+    /// Every reserved seat by session token hash
+    pub fn seats(&self) -> HashMap<String, PlayerId> {
+        self.seats.lock().unwrap().clone()
+    }
+
+    /// Reserve a player for a token issued before a restart, given its hash
+    pub fn restore_seat(&self, token_hash: String, player_id: PlayerId) {
+        self.seats.lock().unwrap().insert(token_hash, player_id);
+    }
+
+    fn is_reserved(&self, player_id: PlayerId) -> bool {
+        self.seats.lock().unwrap().values().any(|seat| *seat == player_id)
+    }
+
+    /// Seat a connection that sent `join_game`: as the player its session
+    /// token reserves, or as assigned otherwise, and register it. The chosen name
+    /// and color are applied unless another connection already plays as that player
+    pub async fn join(
+        &self,
+        client: &PendingClient,
+        seat: Option<PlayerId>,
+        name: Option<&str>,
+        color: Option<&str>,
+        player_key: Option<&str>,
+    ) -> Result<PlayerId> {
+        let identity = player_key.map(player_identity).transpose()?;
+        let name = name.map(str::trim).filter(|name| !name.is_empty());
+        if name.is_some_and(|name| name.chars().count() > MAX_NAME_CHARS) {
+            return Err(anyhow!("Names can be at most {} characters", MAX_NAME_CHARS));
+        }
+        if color.is_some_and(|color| !is_hex_color(color)) {
+            return Err(anyhow!("Colors must look like #RRGGBB"));
+        }
+        if let Some(name) = name {
+            let engine = self.engine.read().await;
+            if engine.state.players.iter().any(|p| p.name == name && Some(PlayerId::from(p.id)) != seat) {
+                return Err(anyhow!("The name {} is taken", name));
+            }
+        }
+
+        let update_interval_ticks = self.update_interval(client.update_rate).await;
+
+        // Pick and claim the player under one lock, so concurrent joins can't take the same slot
+        let mut clients = self.clients.write().await;
+        let player_id = match seat {
+            Some(player_id) => player_id,
+            None => self.assign_player(&clients, name).await.ok_or_else(|| anyhow!("No player slots are left in this game"))?,
+        };
+        let shared = clients.iter().any(|c| c.player_id == player_id);
+        if !shared {
+            let mut engine = self.engine.write().await;
+            let player = engine.get_player_mut(player_id)?;
+            if let Some(name) = name {
+                player.name = name.to_string();
+            }
+            if let Some(color) = color {
+                player.color = color.to_ascii_uppercase();
+            }
+            if let Some(identity) = identity {
+                self.identities.lock().unwrap().insert(player_id, identity);
+            }
+        }
+        let connection_id = client.connection_id;
+        clients.push(ClientSession { connection_id, player_id, tx: client.tx.clone(), latency_ms: None, update_interval_ticks });
+        drop(clients);
+        self.activity.notify_one();
+
+        if let Some(draft) = self.open_draft() {
+            self.send_to_connection(connection_id, ServerMessage::DraftUpdate { draft }).await;
+        }
+        Ok(player_id)
+    }
+
+    /// Pick the player a new connection plays as: the first free human slot,
+    /// else a freshly spawned faction named `name` if late joining is allowed,
+    /// else none. Reserved seats are only handed to their token's holder
+    async fn assign_player(&self, clients: &[ClientSession], name: Option<&str>) -> Option<PlayerId> {
+        let mut engine = self.engine.write().await;
+        if let Some(slot) = Self::free_slots(clients, &engine).into_iter().find(|slot| !self.is_reserved(*slot)) {
+            return Some(slot);
+        }
+
+        if engine.rules.allow_late_join {
+            let taken = |name: &str| engine.state.players.iter().any(|p| p.name == name);
+            let name = match name.filter(|name| !taken(name)) {
+                Some(name) => name.to_string(),
+                None => (2..).map(|n| format!("Player {}", n)).find(|name| !taken(name)).unwrap(),
+            };
+            match engine.spawn_player(name) {
+                Ok(player_id) => {
+                    info!(game_id = %self.id, "Spawned late joiner {:?}", player_id);
+                    return Some(player_id);
+                }
+                Err(e) => warn!(game_id = %self.id, "Could not spawn late joiner: {}", e),
+            }
+        }
+        None
+    }
+
+    /// Human players no connected client is playing as
+    pub async fn unclaimed_slots(&self) -> Vec<PlayerId> {
+        let clients = self.clients.read().await;
+        let engine = self.engine.read().await;
+        Self::free_slots(&clients, &engine)
+    }
+
+    fn free_slots(clients: &[ClientSession], engine: &GameEngine) -> Vec<PlayerId> {
+        engine.state.players
+            .iter()
+            .filter(|p| !p.is_ai && p.is_alive)
+            .map(|p| PlayerId::from(p.id))
+            .filter(|id| !clients.iter().any(|c| c.player_id == *id))
+            .collect()
+    }
+
+    /// Run the tick loop in its own task, recovering from panics: the state is
+    /// rolled back to the last broadcast and the loop restarted, until it has
+    /// failed too often and the game is stopped
+    async fn supervise<F, Fut>(self: Arc<Self>, step: F)
+    where
+        F: Fn(Arc<Self>) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send,
+    {
+        let mut restarts = 0;
+
+        loop {
+            let session = self.clone();
+            let step = step.clone();
+            let result = tokio::spawn(async move { session.tick_loop(step).await }).await;
+
+            let panic = match result {
+                Ok(()) => break,
+                Err(e) if e.is_panic() => e.into_panic(),
+                Err(_) => break,
+            };
+            let message = panic_message(panic.as_ref());
+
+            restarts += 1;
+            let restart = restarts <= MAX_LOOP_RESTARTS;
+            self.recover_from_panic(&message, restart).await;
+            if !restart {
+                break;
+            }
+        }
+    }
+
+    async fn tick_loop<F, Fut>(self: Arc<Self>, step: F)
+    where
+        F: Fn(Arc<Self>) -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let tick_rate_ms = self.engine.read().await.tick_rate_ms;
+        let mut interval = tokio::time::interval(Duration::from_millis(tick_rate_ms));
+
+        loop {
+            interval.tick().await;
+
+            let span = info_span!("tick", game_id = %self.id, tick = tracing::field::Empty);
+            if step(self.clone()).instrument(span).await {
+                break;
+            }
+
+            // Sleep through an idle spell until something happens, then pick
+            // the cadence up again without catching up on the missed ticks
+            if self.is_idle().await {
+                let _ = tokio::time::timeout(self.idle_wait(), self.activity.notified()).await;
+                interval.reset();
+            }
+        }
+    }
+
+    async fn recover_from_panic(&self, message: &str, restart: bool) {
+        let mut engine = self.engine.write().await;
+        let tick = engine.state.tick;
+        error!(game_id = %self.id, tick, "Game loop panicked: {}", message);
+
+        if let Some(dir) = &self.crash_dir {
+            let path = dir.join(format!("game-{}-tick-{}.json", self.id, tick));
+            let written = serde_json::to_vec(&engine.state)
+                .map_err(anyhow::Error::from)
+                .and_then(|json| Ok(std::fs::write(&path, json)?));
+            match written {
+                Ok(()) => info!("Wrote crash snapshot to {}", path.display()),
+                Err(e) => warn!("Failed to write crash snapshot: {}", e),
+            }
+        }
+
+        let notice = if restart {
+            // The panicking tick may have left the game half-updated
+            if let Some(checkpoint) = self.last_good.lock().unwrap().clone() {
+                if let Err(e) = engine.roll_back(checkpoint) {
+                    warn!(game_id = %self.id, "Failed to roll back: {}", e);
+                }
+            }
+            ServerMessage::notification(NotificationKey::GameRecovered, NotificationLevel::Error, [("tick", engine.state.tick.to_string())])
+        } else {
+            engine.set_paused(true);
+            ServerMessage::notification(NotificationKey::GameStopped, NotificationLevel::Error, [])
+        };
+        let state = engine.state.clone();
+        drop(engine);
+
+        self.admin.publish(AdminEvent::GameLoopPanicked {
+            game_id: self.id,
+            tick,
+            message: message.to_string(),
+            restarted: restart,
+        });
+        self.broadcast(notice).await;
+        self.broadcast(ServerMessage::state_update(state)).await;
+    }
+
+    /// Whether there is nothing to simulate or, with `sleep_when_empty`, nobody
+    /// to play it: the game is paused, or humans hold seats but none of them
+    /// is connected
+    async fn is_idle(&self) -> bool {
+        let engine = self.engine.read().await;
+        if engine.stays_paused() {
+            return true;
+        }
+        let humans_seated = engine.state.players.iter().any(|p| p.is_alive && !p.is_ai);
+        drop(engine);
+        self.sleep_when_empty && humans_seated && self.clients.read().await.is_empty()
+    }
+
+    /// How long an idle game may sleep: a heartbeat, or less if it is about
+    /// to be ended as abandoned
+    fn idle_wait(&self) -> Duration {
+        let idle_since = *self.idle_since.lock().unwrap();
+        match self.idle_timeout.zip(idle_since) {
+            Some((timeout, since)) => timeout.saturating_sub(since.elapsed()).min(IDLE_HEARTBEAT),
+            None => IDLE_HEARTBEAT,
+        }
+    }
+
+    /// Advance the game by one tick and broadcast the results; returns true once the game is over
+    async fn run_tick(&self) -> bool {
+        let tick_started = Instant::now();
+
+        if self.check_idle().await {
+            return true;
+        }
+        self.expire_kick_vote().await;
+
+        let idle = self.is_idle().await;
+
+        // Update game state
+        let tick_rate_ms = {
+            let mut engine = self.engine.write().await;
+
+            if !idle {
+                let started = Instant::now();
+                engine.tick();
+                engine.perf.tick.record(started.elapsed());
+                Span::current().record("tick", engine.state.tick);
+
+                if self.debug {
+                    engine.ai_log.get_or_insert_with(AiDecisionLog::default);
+                }
+                let started = Instant::now();
+                engine.tick_ai();
+                engine.perf.ai.record(started.elapsed());
+            }
+
+            if cfg!(debug_assertions) {
+                if let Err(e) = engine.check_invariants() {
+                    warn!("Invariant violated at tick {}: {}", engine.state.tick, e);
+                }
+            }
+
+            let events = engine.take_events();
+            if !events.is_empty() {
+                drop(engine);
+                for event in events {
+                    self.publish(event).await;
+                }
+                engine = self.engine.write().await;
+            }
+
+            // Check for game over
+            if let Some(stats) = engine.check_game_over() {
+                let summary = engine.summary(self.id, &stats);
+                let identities = self.identities.lock().unwrap().clone();
+                let participants: Vec<MatchParticipant> = engine.state.players
+                    .iter()
+                    .map(|p| MatchParticipant {
+                        name: p.name.clone(),
+                        is_ai: p.is_ai,
+                        won: p.id == stats.winner,
+                        identity: identities.get(&PlayerId::from(p.id)).copied().filter(|_| !p.is_ai),
+                    })
+                    .collect();
+                let duration = engine.state.game_time_seconds;
+                let replay = engine.finish_replay();
+                drop(engine);
+                self.publish_summary(summary);
+                if let Err(e) = replay.as_ref().map_or(Ok(()), |replay| self.write_replay(replay)) {
+                    warn!(game_id = %self.id, "Failed to write replay: {}", e);
+                }
+                *self.finished_replay.lock().unwrap() = replay;
+                if let Some(ladder) = &self.ladder {
+                    if let Err(e) = ladder.record_match(&participants, Utc::now()) {
+                        warn!(game_id = %self.id, "Failed to record ladder result: {}", e);
+                    }
+                }
+                if let Some(community) = &self.community {
+                    if let Err(e) = community.record_match(self.id, &participants, duration, Utc::now()) {
+                        warn!(game_id = %self.id, "Failed to record match history: {}", e);
+                    }
+                }
+                if let Some(storage) = self.storage.clone() {
+                    let result = MatchResult {
+                        game_id: self.id,
+                        finished_at: Utc::now(),
+                        winner: participants.iter().find(|p| p.won).map(|p| p.name.clone()).unwrap_or_default(),
+                        duration_seconds: duration,
+                        players: participants.clone(),
+                        stats: stats.clone(),
+                    };
+                    let game_id = self.id;
+                    tokio::spawn(async move {
+                        if let Err(e) = storage.record_match(&result).await {
+                            warn!(game_id = %game_id, "Failed to store match: {}", e);
+                        }
+                    });
+                }
+                self.admin.publish(AdminEvent::GameEnded {
+                    game_id: self.id,
+                    reason: GameEndReason::Victory,
+                    stats: Some(stats.clone()),
+                });
+                self.broadcast(ServerMessage::GameOver { stats }).await;
+                return true;
+            }
+
+            engine.tick_rate_ms
+        };
+
+        // An idle game has nothing new to send; it only keeps its listing and
+        // the admins up to date
+        if idle {
+            self.admin.publish(AdminEvent::GameStatus { status: self.status().await });
+            self.announce().await;
+            return false;
+        }
+
+        // Each client is sent the state at the interval it asked for
+        let tick = {
+            let engine = self.engine.read().await;
+            engine.state.tick
+        };
+        let due: BTreeSet<u64> =
+            self.clients.read().await.iter().map(|c| c.update_interval_ticks).filter(|interval| tick % interval == 0).collect();
+
+        let regular = tick % DEFAULT_UPDATE_INTERVAL_TICKS == 0;
+        if regular || !due.is_empty() {
+            let engine = self.engine.read().await;
+            let state = engine.state.clone();
+            *self.last_good.lock().unwrap() = Some(engine.checkpoint());
+            drop(engine);
+            let checksum = state.checksum();
+            self.record_checksum(tick, checksum);
+
+            if regular && (self.spectator_delay.is_some() || self.backplane.has_subscribers(self.id)) {
+                self.publish_to_spectators(ServerMessage::GameStateUpdate { state: state.clone(), checksum }).await;
+            }
+            for interval in due {
+                let previous = self.interval_states.lock().unwrap().insert(interval, state.clone());
+                // Between keyframes, clients holding their previous update only get what changed
+                let delta = previous
+                    .filter(|base| base.tick / KEYFRAME_INTERVAL_TICKS == tick / KEYFRAME_INTERVAL_TICKS)
+                    .and_then(|base| state.delta_since(&base));
+                let update = ServerMessage::GameStateUpdate { state: state.clone(), checksum };
+                self.broadcast_state(interval, &update, delta).await;
+            }
+        }
+
+        if tick % ADMIN_STATUS_INTERVAL_TICKS == 0 {
+            self.admin.publish(AdminEvent::GameStatus { status: self.status().await });
+            self.announce().await;
+        }
+
+        let elapsed = tick_started.elapsed();
+        if elapsed.as_millis() as u64 > tick_rate_ms {
+            self.admin.publish(AdminEvent::TickOverrun {
+                game_id: self.id,
+                tick,
+                elapsed_ms: elapsed.as_millis() as u64,
+                budget_ms: tick_rate_ms,
+            });
+        }
+
+        false
+    }
+}
+
+/// `#RRGGBB`
+fn is_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::MapGenerator;
+    use crate::test_support::{TestServer, TEST_ADMIN_TOKEN};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[tokio::test]
+    async fn test_panicking_tick_is_rolled_back_and_restarted() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+        let mut admin = server.connect_admin(TEST_ADMIN_TOKEN).await.unwrap();
+        admin.recv_as::<AdminEvent>().await;
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let step_calls = calls.clone();
+        tokio::spawn(server.session.clone().supervise(move |session: Arc<GameSession>| {
+            let calls = step_calls.clone();
+            async move {
+                // Corrupt the state mid-tick, then blow up once
+                if calls.fetch_add(1, Ordering::SeqCst) == 7 {
+                    session.engine.write().await.state.tick = 9_999;
+                    panic!("tick exploded");
+                }
+                session.run_tick().await
+            }
+        }));
+
+        let notice = client.recv_until(|m| matches!(m, ServerMessage::Notification { .. })).await;
+        assert!(matches!(notice, ServerMessage::Notification { severity: NotificationLevel::Error, .. }));
+        match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => assert_eq!(state.tick, 5),
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        // The loop keeps going after the restart
+        let update = client.recv_until(|m| matches!(m, ServerMessage::StateDelta { .. })).await;
+        assert!(matches!(update, ServerMessage::StateDelta { delta } if delta.base_tick == 5 && delta.tick == 10));
+
+        loop {
+            if let AdminEvent::GameLoopPanicked { message, restarted, .. } = admin.recv_as().await {
+                assert_eq!(message, "tick exploded");
+                assert!(restarted);
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rollback_forgets_a_player_who_joined_after_the_last_good_state() {
+        let mut engine = GameEngine::new(MapGenerator::new(30, 3).generate(), 20);
+        engine.rules.allow_late_join = true;
+        let session = Arc::new(GameSession::new(engine));
+        for _ in 0..DEFAULT_UPDATE_INTERVAL_TICKS {
+            session.run_tick().await;
+        }
+        let late = session.engine.write().await.spawn_player("Late".to_string()).unwrap();
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let step_calls = calls.clone();
+        session.clone().supervise(move |session: Arc<GameSession>| {
+            let calls = step_calls.clone();
+            async move {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => panic!("tick exploded"),
+                    1..=3 => session.run_tick().await,
+                    _ => true,
+                }
+            }
+        }).await;
+
+        let engine = session.engine.read().await;
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+        assert_eq!(engine.state.tick, DEFAULT_UPDATE_INTERVAL_TICKS + 3);
+        assert!(engine.get_player(late).is_err());
+        assert!(!engine.state.is_paused);
+        engine.check_invariants().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_repeated_panics_stop_the_game() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        server.session.clone().supervise(|_session: Arc<GameSession>| async move {
+            panic!("always broken");
+        }).await;
+
+        assert!(server.session.engine.read().await.state.is_paused);
+        let mut notices = 0;
+        while notices <= MAX_LOOP_RESTARTS {
+            if let ServerMessage::Notification { message, .. } = client.recv().await {
+                notices += 1;
+                if notices == MAX_LOOP_RESTARTS + 1 {
+                    assert_eq!(message, "The game was stopped after repeated server errors");
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_joins_never_share_a_slot() {
+        let state = MapGenerator::new(20, 4).with_human_slots(2).generate();
+        let session = Arc::new(GameSession::new(GameEngine::new(state, 20)));
+        let joins = (0..3).map(|_| {
+            let session = session.clone();
+            tokio::spawn(async move {
+                let client = PendingClient { connection_id: Uuid::new_v4(), tx: Outbox::new().0, update_rate: None };
+                session.join(&client, None, None, None, None).await.ok()
+            })
+        });
+        let mut seated: Vec<PlayerId> = futures_util::future::join_all(joins).await.into_iter().filter_map(|r| r.unwrap()).collect();
+        seated.sort_by_key(|id| Uuid::from(*id));
+        seated.dedup();
+        assert_eq!(seated.len(), 2);
+        assert_eq!(session.clients.read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_lobby_backfills_empty_slots_with_ai() {
+        let state = MapGenerator::new(20, 4).with_human_slots(2).generate();
+        let mut session = GameSession::new(GameEngine::new(state, 20));
+        session.lobby_wait = Some(Duration::from_millis(300));
+        let server = TestServer::with_session(session, true).await;
+
+        let mut client = server.connect().await;
+        let human = match client.recv().await {
+            ServerMessage::GameStateUpdate { state, .. } => state.players[0].id,
+            other => panic!("unexpected message: {:?}", other),
+        };
+
+        let notice = client.recv_until(|m| matches!(m, ServerMessage::Notification { .. })).await;
+        assert!(matches!(notice, ServerMessage::Notification { message, .. } if message == "Player 2 did not join and was replaced by AI 3"));
+
+        let engine = server.session.engine.read().await;
+        assert!(engine.state.players.iter().filter(|p| !p.is_ai).map(|p| p.id).eq([human]));
+        assert!(engine.state.tick <= 1);
+    }
+
+    #[tokio::test]
+    async fn test_players_pick_starts_in_turn() {
+        let state = MapGenerator::new(40, 3).generate();
+        let mut session = GameSession::new(GameEngine::new(state, 20));
+        session.start_pick_time = Some(Duration::from_secs(5));
+        let server = TestServer::with_session(session, false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+        server.session.clone().start_game_loop().await;
+
+        // Starts are released, then the human picks first
+        let (human, candidates) = match client.recv_until(|m| matches!(m, ServerMessage::StartPickTurn { .. })).await {
+            ServerMessage::StartPickTurn { player_id, candidates, seconds } => {
+                assert_eq!(seconds, 5);
+                (player_id, candidates)
+            }
+            _ => unreachable!(),
+        };
+        let choice = *candidates.last().unwrap();
+        client.send(ClientMessage::PickStart { territory: choice }).await;
+
+        let mut picks = Vec::new();
+        while picks.len() < 3 {
+            if let ServerMessage::StartPicked { player_id, territory_id } = client.recv().await {
+                picks.push((player_id, territory_id));
+            }
+        }
+        assert_eq!(picks[0], (human, choice));
+
+        // Out of turn picks are refused
+        client.send(ClientMessage::PickStart { territory: choice }).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { .. }
+        ));
+
+        let engine = server.session.engine.read().await;
+        for (player_id, territory_id) in picks {
+            let owned: Vec<Uuid> = engine.state.territories.iter().filter(|t| t.owner == Some(player_id)).map(|t| t.id).collect();
+            assert!(owned.contains(&territory_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_host_starts_drafted_game() {
+        let state = MapGenerator::new(40, 3).generate();
+        let host = state.players.iter().find(|p| !p.is_ai).unwrap().id;
+        let maps: Vec<MapOption> = [(MapStyle::Scattered, 1), (MapStyle::Continents, 2)]
+            .into_iter()
+            .map(|(style, seed)| MapOption { style, seed })
+            .collect();
+        let mut session = GameSession::new(GameEngine::new(state, 20));
+        session.draft = Some(Mutex::new(Draft::new(host.into(), maps, &[RuleOption::DiceCombat]).unwrap()));
+        let server = TestServer::with_session(session, false).await;
+        let mut client = server.connect().await;
+        server.session.clone().start_game_loop().await;
+
+        let ballot = client.recv_until(|m| matches!(m, ServerMessage::DraftUpdate { .. })).await;
+        assert!(matches!(ballot, ServerMessage::DraftUpdate { draft } if draft.host == host && draft.maps.len() == 2));
+
+        client.send(ClientMessage::VetoMap { map: 0 }).await;
+        client.send(ClientMessage::VoteRule { rule: RuleOption::DiceCombat, enabled: true }).await;
+        let votes = client
+            .recv_until(|m| matches!(m, ServerMessage::DraftUpdate { draft } if draft.rules[0].yes == 1))
+            .await;
+        assert!(matches!(votes, ServerMessage::DraftUpdate { draft } if draft.vetoed == [0]));
+        // The game waits for the host even though every human slot is taken
+        assert_eq!(server.session.engine.read().await.state.tick, 0);
+
+        client.send(ClientMessage::StartGame).await;
+        let resolved = client.recv_until(|m| matches!(m, ServerMessage::DraftResolved { .. })).await;
+        let ServerMessage::DraftResolved { result } = resolved else { unreachable!() };
+        assert_eq!(result.map, MapOption { style: MapStyle::Continents, seed: 2 });
+        assert_eq!(result.enabled_rules, [RuleOption::DiceCombat]);
+
+        // Everyone keeps their seat on the drafted map
+        let expected = MapGenerator::new(40, 3).with_style(MapStyle::Continents).generate_seeded(2);
+        let update = client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await;
+        let ServerMessage::GameStateUpdate { state, .. } = update else { unreachable!() };
+        let terrain = |state: &GameState| state.territories.iter().map(|t| t.terrain).collect::<Vec<_>>();
+        assert_eq!(terrain(&state), terrain(&expected));
+        assert!(state.territories.iter().any(|t| t.owner == Some(host)));
+        assert_eq!(server.session.engine.read().await.rules.combat_model, "dice");
+
+        client.send(ClientMessage::VetoMap { map: 1 }).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "The draft is over"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_start_is_picked_for_idle_humans() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(30, 2).generate(), 20));
+        session.start_pick_time = Some(Duration::from_millis(200));
+        let server = TestServer::with_session(session, false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+        server.session.clone().start_game_loop().await;
+
+        let human = match client.recv_until(|m| matches!(m, ServerMessage::StartPickTurn { .. })).await {
+            ServerMessage::StartPickTurn { player_id, .. } => player_id,
+            _ => unreachable!(),
+        };
+        let picked = client.recv_until(|m| matches!(m, ServerMessage::StartPicked { .. })).await;
+        assert!(matches!(picked, ServerMessage::StartPicked { player_id, .. } if player_id == human));
+    }
+
+    #[tokio::test]
+    async fn test_out_of_sync_client_is_resent_the_state() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+        for _ in 0..5 {
+            server.session.run_tick().await;
+        }
+        let (tick, checksum) = match client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await {
+            ServerMessage::GameStateUpdate { state, checksum } => {
+                assert_eq!(checksum, state.checksum());
+                (state.tick, checksum)
+            }
+            _ => unreachable!(),
+        };
+
+        // A matching checksum needs no answer
+        client.send(ClientMessage::VerifyState { tick, checksum }).await;
+        client.send(ClientMessage::Ping { nonce: 1 }).await;
+        loop {
+            match client.recv().await {
+                ServerMessage::Pong { .. } => break,
+                ServerMessage::GameStateUpdate { .. } => panic!("resent the state of an in-sync client"),
+                _ => {}
+            }
+        }
+
+        client.send(ClientMessage::VerifyState { tick, checksum: checksum ^ 1 }).await;
+        let update = client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await;
+        assert!(matches!(update, ServerMessage::GameStateUpdate { state, .. } if state.tick == tick));
+    }
+
+    #[tokio::test]
+    async fn test_state_deltas_between_keyframes() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        let tick_to = |target: u64| {
+            let session = server.session.clone();
+            async move {
+                while session.engine.read().await.state.tick < target {
+                    session.run_tick().await;
+                }
+            }
+        };
+
+        // The state the client connected with is not a broadcast, so the first one is full
+        tick_to(5).await;
+        let mut state = match client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await {
+            ServerMessage::GameStateUpdate { state, .. } => state,
+            _ => unreachable!(),
+        };
+        tick_to(10).await;
+        match client.recv_until(|m| matches!(m, ServerMessage::StateDelta { .. } | ServerMessage::GameStateUpdate { .. })).await {
+            ServerMessage::StateDelta { delta } => {
+                assert_eq!(delta.base_tick, 5);
+                for territory in delta.territories {
+                    let id = territory.id;
+                    *state.territories.iter_mut().find(|t| t.id == id).unwrap() = territory;
+                }
+                for player in delta.players {
+                    let id = player.id;
+                    *state.players.iter_mut().find(|p| p.id == id).unwrap() = player;
+                }
+                state.tick = delta.tick;
+                assert_eq!(state.checksum(), delta.checksum);
+            }
+            other => panic!("expected a delta, got {:?}", other),
+        }
+
+        // A client that keeps up still gets the full state on a keyframe
+        let is_state = |m: &ServerMessage| matches!(m, ServerMessage::StateDelta { .. } | ServerMessage::GameStateUpdate { .. });
+        for target in (15..KEYFRAME_INTERVAL_TICKS).step_by(5) {
+            tick_to(target).await;
+            let update = client.recv_until(is_state).await;
+            assert!(matches!(update, ServerMessage::StateDelta { delta } if delta.tick == target));
+        }
+        tick_to(KEYFRAME_INTERVAL_TICKS).await;
+        let keyframe = client.recv_until(is_state).await;
+        assert!(matches!(keyframe, ServerMessage::GameStateUpdate { state, .. } if state.tick == KEYFRAME_INTERVAL_TICKS));
+    }
+
+    #[tokio::test]
+    async fn test_clients_are_updated_at_the_rate_they_asked_for() {
+        let state = MapGenerator::new(20, 4).with_human_slots(2).generate();
+        let server = TestServer::with_session(GameSession::new(GameEngine::new(state, 100)), false).await;
+        let is_state = |m: &ServerMessage| matches!(m, ServerMessage::StateDelta { .. } | ServerMessage::GameStateUpdate { .. });
+        let mut clients = Vec::new();
+        for path in ["/ws?update_rate=10", "/ws?update_rate=0.1"] {
+            let mut client = server.open(path).await.unwrap();
+            client.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+            client.recv_until(is_state).await;
+            clients.push(client);
+        }
+        let intervals: Vec<u64> = server.session.clients.read().await.iter().map(|c| c.update_interval_ticks).collect();
+        assert_eq!(intervals, [1, 20]);
+
+        // Every tick for the desktop client, with deltas against the tick before
+        let [fast, slow] = &mut clients[..] else { unreachable!() };
+        server.session.run_tick().await;
+        assert!(matches!(fast.recv_until(is_state).await, ServerMessage::GameStateUpdate { state, .. } if state.tick == 1));
+        for tick in 2..=20 {
+            server.session.run_tick().await;
+            let update = fast.recv_until(is_state).await;
+            assert!(matches!(update, ServerMessage::StateDelta { delta } if delta.base_tick == tick - 1 && delta.tick == tick));
+        }
+
+        // The slow client only now gets its first state, clamped to one every 2 seconds
+        assert!(matches!(slow.recv_until(is_state).await, ServerMessage::GameStateUpdate { state, .. } if state.tick == 20));
+        assert!(server.session.clients.read().await.iter().all(|c| c.stats().dropped_frames == 0));
+    }
+
+    #[tokio::test]
+    async fn test_late_joiners_get_their_own_faction() {
+        let mut engine = GameEngine::new(MapGenerator::new(20, 3).generate(), 20);
+        engine.rules.allow_late_join = true;
+        let server = TestServer::with_session(GameSession::new(engine), false).await;
+
+        let mut first = server.connect().await;
+        first.recv().await;
+        let mut second = server.connect().await;
+        second.recv().await;
+
+        let clients: Vec<PlayerId> = server.session.clients.read().await.iter().map(|c| c.player_id).collect();
+        assert_eq!(clients.len(), 2);
+        assert_ne!(clients[0], clients[1]);
+        let engine = server.session.engine.read().await;
+        let joiner = engine.get_player(clients[1]).unwrap();
+        assert_eq!(joiner.name, "Player 2");
+        assert!(!joiner.is_ai);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_game_is_ended_and_delisted() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        session.idle_timeout = Some(Duration::from_millis(200));
+        let server = TestServer::with_session(session, true).await;
+        let mut admin = server.connect_admin(TEST_ADMIN_TOKEN).await.unwrap();
+
+        // A connected client keeps the game alive
+        let mut client = server.connect().await;
+        client.recv().await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(server.session.backplane.list_games().await.unwrap().len(), 1);
+        client.close().await;
+
+        loop {
+            if let AdminEvent::GameEnded { reason, stats, .. } = admin.recv_as().await {
+                assert_eq!(reason, GameEndReason::Abandoned);
+                assert!(stats.is_none());
+                break;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(server.session.backplane.list_games().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_game_can_finish_headless() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        session.idle_timeout = Some(Duration::from_millis(50));
+        session.finish_idle_headless = true;
+        let server = TestServer::with_session(session, true).await;
+
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let engine = server.session.engine.read().await;
+            if engine.state.players.iter().all(|p| p.is_ai) {
+                assert_eq!(engine.state.game_speed, 4.0);
+                return;
+            }
+        }
+        panic!("idle game was never handed to the AI");
+    }
+
+    #[tokio::test]
+    async fn test_idle_game_sleeps_until_someone_plays() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 2).generate(), 20));
+        session.sleep_when_empty = true;
+        let server = TestServer::with_session(session, true).await;
+        let tick = || async { server.session.engine.read().await.state.tick };
+
+        // Nobody has joined, so nothing runs
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(tick().await, 0);
+
+        // A joining player wakes it without waiting out the heartbeat
+        let mut client = server.connect().await;
+        client.recv().await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(tick().await > 0);
+
+        client.send(ClientMessage::PauseGame).await;
+        client.recv_until(|m| matches!(m, ServerMessage::Notification { key: NotificationKey::GamePaused, .. })).await;
+        let paused_at = tick().await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(tick().await, paused_at);
+
+        client.send(ClientMessage::ResumeGame).await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(tick().await > paused_at);
+    }
+
+    #[tokio::test]
+    async fn test_host_saves_and_loads_the_game() {
+        let dir = std::env::temp_dir().join(format!("saves-test-{}", Uuid::new_v4()));
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 2).generate(), 20));
+        session.save_dir = Some(dir.clone());
+        let server = TestServer::with_session(session, false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send(ClientMessage::SaveGame { name: "../escape".to_string() }).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "Save names may only use letters, digits, - and _"
+        ));
+        client.send(ClientMessage::SaveGame { name: "opening".to_string() }).await;
+        client.recv_until(|m| matches!(m, ServerMessage::Notification { message, .. } if message == "Game saved as opening")).await;
+        assert!(dir.join("opening.json").exists());
+
+        let saved = server.session.engine.read().await.state.checksum();
+        for _ in 0..7 {
+            server.session.engine.write().await.tick();
+        }
+        client.send(ClientMessage::LoadGame { name: "opening".to_string() }).await;
+        let update = client.recv_until(|m| matches!(m, ServerMessage::GameStateUpdate { .. })).await;
+        assert!(matches!(update, ServerMessage::GameStateUpdate { state, .. } if state.tick == 0));
+        assert_eq!(server.session.engine.read().await.state.checksum(), saved);
+
+        client.send(ClientMessage::LoadGame { name: "missing".to_string() }).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "No such save"
+        ));
+        std::fs::remove_dir_all(dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_server_failure_is_reported_as_an_incident() {
+        // The save directory is a file, so saving fails on the disk rather than on the rules
+        let file = std::env::temp_dir().join(format!("saves-file-{}", Uuid::new_v4()));
+        std::fs::write(&file, "").unwrap();
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 2).generate(), 20));
+        session.save_dir = Some(file.clone());
+        let server = TestServer::with_session(session, false).await;
+        let mut client = server.connect().await;
+        client.recv().await;
+
+        client.send(ClientMessage::SaveGame { name: "opening".to_string() }).await;
+        let reply = client.recv_until(|m| matches!(m, ServerMessage::InternalError { .. } | ServerMessage::Error { .. })).await;
+        assert!(matches!(
+            reply,
+            ServerMessage::InternalError { incident_id, message } if !incident_id.is_nil() && !message.contains("os error")
+        ));
+        std::fs::remove_file(file).ok();
+    }
+
+    #[tokio::test]
+    async fn test_vote_kick_hands_faction_to_ai() {
+        let engine = GameEngine::new(MapGenerator::new(30, 4).with_human_slots(3).generate(), 20);
+        let server = TestServer::with_session(GameSession::new(engine), false).await;
+        let mut clients = Vec::new();
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let mut client = server.open("/ws").await.unwrap();
+            client.send(ClientMessage::JoinGame { name: None, color: None, player_key: None }).await;
+            let ServerMessage::Joined { player_id, .. } = client.recv().await else { panic!("expected to join") };
+            clients.push(client);
+            ids.push(player_id);
+        }
+        let target = ids[2];
+
+        clients[0].send(ClientMessage::VoteKick { player_id: ids[0] }).await;
+        assert!(matches!(
+            clients[0].recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "You can't vote to kick yourself"
+        ));
+        clients[0].send(ClientMessage::VoteKick { player_id: target }).await;
+        clients[1]
+            .recv_until(|m| matches!(m, ServerMessage::Notification { message, .. } if message.ends_with("(1/2)")))
+            .await;
+        clients[1].send(ClientMessage::VoteKick { player_id: target }).await;
+
+        assert!(matches!(
+            clients[2].recv_until(|m| matches!(m, ServerMessage::Error { .. })).await,
+            ServerMessage::Error { message } if message == "You were vote-kicked from the game"
+        ));
+        clients.pop().unwrap().expect_closed().await;
+        clients[0]
+            .recv_until(|m| matches!(m, ServerMessage::Notification { message, .. } if message.contains("was vote-kicked and replaced by AI")))
+            .await;
+        let engine = server.session.engine.read().await;
+        assert!(engine.get_player(target.into()).unwrap().is_ai);
+        assert!(server.session.clients.read().await.iter().all(|c| c.player_id != target.into()));
+    }
+
+    #[tokio::test]
+    async fn test_finished_game_summary_is_stored_and_posted() {
+        let (posted_tx, mut posted) = tokio::sync::mpsc::unbounded_channel();
+        let hook = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move |body: String| async move {
+                let _ = posted_tx.send(body);
+                axum::http::StatusCode::NO_CONTENT
+            }),
+        );
+        let webhook = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut engine = GameEngine::new(MapGenerator::new(20, 2).generate(), 20);
+        let (winner, loser) = (engine.state.players[0].id, engine.state.players[1].id);
+        engine.eliminate_player(loser.into(), Some(winner));
+        let mut session = GameSession::new(engine);
+        session.summary_webhook = Some(format!("http://{}/hook", webhook.local_addr().unwrap()));
+        let storage = Arc::new(crate::storage::MemoryStorage::default());
+        session.storage = Some(storage.clone());
+        let replay_dir = std::env::temp_dir().join(format!("replays-{}", Uuid::new_v4()));
+        session.replay_dir = Some(replay_dir.clone());
+        tokio::spawn(async move { axum::serve(webhook, hook).await });
+        let server = TestServer::with_session(session, true).await;
+
+        let posted = tokio::time::timeout(Duration::from_secs(5), posted.recv()).await.unwrap().unwrap();
+        assert!(posted.contains("\"content\":\"**🏆 Player wins!**"));
+
+        let summary = server.session.summary().unwrap();
+        assert_eq!(summary.stats.winner, winner);
+        assert_eq!(summary.timeline[0].text, "AI 1 has been eliminated");
+
+        let query = crate::storage::MatchQuery { player: Some("AI 1".to_string()), limit: 10 };
+        let mut stored = Vec::new();
+        for _ in 0..50 {
+            stored = storage.matches(&query).await.unwrap();
+            if !stored.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(stored.len(), 1);
+        assert_eq!((stored[0].game_id, stored[0].winner.as_str()), (server.session.id, "Player"));
+
+        let replay = crate::game::replay::Replay::read(replay_dir.join(format!("{}.replay", server.session.id))).unwrap();
+        assert_eq!(replay.game_id, server.session.id);
+        std::fs::remove_dir_all(replay_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keyed_players_are_rated_under_their_identity() {
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 2).generate(), 20));
+        session.ladder = Some(Arc::new(Ladder::in_memory(chrono::Duration::days(30))));
+        let server = TestServer::with_session(session, false).await;
+
+        let mut client = server.open("/ws").await.unwrap();
+        client.send(ClientMessage::JoinGame { name: None, color: None, player_key: Some("guessable".into()) }).await;
+        assert!(matches!(client.recv().await, ServerMessage::Error { message } if message.starts_with("Player keys must be")));
+        let key = Some("ada's long player key".to_string());
+        client.send(ClientMessage::JoinGame { name: Some("Ada".into()), color: None, player_key: key }).await;
+        client.recv().await;
+
+        {
+            let mut engine = server.session.engine.write().await;
+            let (winner, loser) = (engine.state.players[0].id, engine.state.players[1].id);
+            engine.eliminate_player(loser.into(), Some(winner));
+        }
+        assert!(server.session.run_tick().await);
+        let board = server.session.ladder.as_ref().unwrap().leaderboard(None, Utc::now()).unwrap();
+        assert_eq!(board.unplaced_players, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retried_command_is_acknowledged_without_running_twice() {
+        let server = TestServer::start(false).await;
+        let mut client = server.connect().await;
+        let (from, to) = {
+            let engine = server.session.engine.read().await;
+            let human = engine.state.players.iter().find(|p| !p.is_ai).unwrap().id;
+            let from = engine.state.territories.iter().find(|t| t.owner == Some(human)).unwrap();
+            let land = |id: &&Uuid| engine.get_territory((**id).into()).is_ok_and(|t| t.terrain != TerrainType::Water);
+            (from.id, *from.neighbors.iter().find(land).unwrap())
+        };
+        let attack = format!(r#"{{"type":"attack","from":"{}","to":"{}","command_id":"attack-1"}}"#, from, to);
+        let troops = || async { server.session.engine.read().await.get_territory(from.into()).unwrap().troops() };
+
+        client.send_raw(&attack).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::CommandAck { .. })).await,
+            ServerMessage::CommandAck { command_id, accepted: true, duplicate: false } if command_id == "attack-1"
+        ));
+        let after_attack = troops().await;
+
+        client.send_raw(&attack).await;
+        assert!(matches!(
+            client.recv_until(|m| matches!(m, ServerMessage::CommandAck { .. })).await,
+            ServerMessage::CommandAck { accepted: true, duplicate: true, .. }
+        ));
+        assert_eq!(troops().await, after_attack);
+
+        client.send_raw(&format!(r#"{{"type":"pause_game","command_id":"{}"}}"#, "x".repeat(65))).await;
+        assert!(matches!(client.recv_until(|m| matches!(m, ServerMessage::Error { .. })).await, ServerMessage::Error { .. }));
     }
 }
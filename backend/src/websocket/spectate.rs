@@ -0,0 +1,104 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Path, State, WebSocketUpgrade,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::lobby::Lobby;
+
+/// Read-only WebSocket streaming state updates of any game on the backplane
+pub async fn spectate_websocket_handler(
+    ws: WebSocketUpgrade,
+    Path(game_id): Path<Uuid>,
+    State(lobby): State<Arc<Lobby>>,
+) -> Response {
+    let game_session = lobby.main_room().await;
+    let known = lobby.room(game_id).await.is_some()
+        || game_session.backplane.list_games().await
+            .is_ok_and(|games| games.iter().any(|g| g.game_id == game_id));
+    if !known {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    // Subscribe before upgrading so no frame published in between is lost
+    match game_session.backplane.subscribe_state(game_id).await {
+        Ok(frames) => ws.on_upgrade(move |socket| handle_spectator_socket(socket, game_id, frames)),
+        Err(e) => {
+            warn!("Failed to subscribe to game {}: {}", game_id, e);
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+async fn handle_spectator_socket(socket: WebSocket, game_id: Uuid, mut frames: mpsc::Receiver<String>) {
+    let (mut sender, mut receiver) = socket.split();
+
+    info!("Spectator joined game {}", game_id);
+
+    loop {
+        tokio::select! {
+            frame = frames.recv() => match frame {
+                Some(json) => {
+                    if sender.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            // Spectators can't send commands; only watch for the close
+            message = receiver.next() => match message {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    info!("Spectator left game {}", game_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::game::{GameEngine, MapGenerator};
+    use crate::test_support::TestServer;
+    use crate::types::*;
+    use crate::websocket::GameSession;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_spectator_receives_state_updates() {
+        let server = TestServer::start(true).await;
+        let mut spectator = server.spectate(server.session.id).await.unwrap();
+
+        let update = spectator.recv().await;
+        assert!(matches!(update, ServerMessage::GameStateUpdate { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_spectators_watch_behind_live_play() {
+        let started = Instant::now();
+        let mut session = GameSession::new(GameEngine::new(MapGenerator::new(20, 4).generate(), 20));
+        session.spectator_delay = Some(Duration::from_millis(300));
+        let server = TestServer::with_session(session, true).await;
+        let mut spectator = server.spectate(server.session.id).await.unwrap();
+
+        let ServerMessage::GameStateUpdate { state, .. } = spectator.recv().await else { panic!("expected a state") };
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(state.tick < server.session.engine.read().await.state.tick);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_game_is_rejected() {
+        let server = TestServer::start(false).await;
+        assert!(server.spectate(Uuid::new_v4()).await.is_err());
+    }
+}
@@ -0,0 +1,169 @@
+//! Vote-kicks among the human players of a game.
+//!
+//! Any human can start a vote to kick another, one vote at a time. It passes
+//! once enough of the other connected humans agree within the voting window,
+//! and the kicked player's faction is handed to the AI. A player can't be
+//! voted on again for a while after a vote about them ends, and starting
+//! votes has its own cooldown so one player can't keep the lobby busy.
+
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::types::PlayerId;
+
+/// When a vote-kick passes, and how often one can be held
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VoteKickRules {
+    /// Share of the other connected humans that must agree
+    pub threshold: f32,
+    /// Votes needed however few players there are
+    pub min_votes: usize,
+    /// How long a vote stays open
+    pub window: Duration,
+    /// Wait after a vote ends before its target can be voted on again, and
+    /// after starting a vote before the same player can start another
+    pub cooldown: Duration,
+}
+
+impl Default for VoteKickRules {
+    fn default() -> Self {
+        Self {
+            threshold: 0.6,
+            min_votes: 2,
+            window: Duration::from_secs(60),
+            cooldown: Duration::from_secs(180),
+        }
+    }
+}
+
+/// Where a vote stands after a ballot
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoteOutcome {
+    Open { votes: usize, needed: usize },
+    Passed { votes: usize },
+}
+
+struct OpenVote {
+    target: PlayerId,
+    voters: Vec<PlayerId>,
+    opened_at: Instant,
+}
+
+#[derive(Default)]
+struct Ballots {
+    open: Option<OpenVote>,
+    /// When each player may be voted on again
+    target_ready: HashMap<PlayerId, Instant>,
+    /// When each player may start another vote
+    starter_ready: HashMap<PlayerId, Instant>,
+}
+
+pub struct VoteKicks {
+    rules: VoteKickRules,
+    ballots: Mutex<Ballots>,
+}
+
+impl VoteKicks {
+    pub fn new(rules: VoteKickRules) -> Self {
+        Self { rules, ballots: Mutex::new(Ballots::default()) }
+    }
+
+    pub fn rules(&self) -> VoteKickRules {
+        self.rules
+    }
+
+    /// Vote to kick `target`, starting a vote if none is open; `eligible`
+    /// counts the connected humans other than the target
+    pub fn vote(&self, voter: PlayerId, target: PlayerId, eligible: usize, now: Instant) -> Result<VoteOutcome> {
+        let needed = self.rules.min_votes.max((eligible as f32 * self.rules.threshold).ceil() as usize);
+        if needed > eligible {
+            return Err(anyhow!("Not enough players are connected to hold a vote-kick"));
+        }
+
+        let mut ballots = self.ballots.lock().unwrap();
+        let wait = |ready: Option<&Instant>| ready.map(|ready| ready.saturating_duration_since(now)).filter(|w| !w.is_zero());
+        match &mut ballots.open {
+            Some(vote) if vote.target != target => return Err(anyhow!("Another vote-kick is already running")),
+            Some(vote) if vote.voters.contains(&voter) => return Err(anyhow!("You already voted")),
+            Some(vote) => vote.voters.push(voter),
+            None => {
+                if let Some(wait) = wait(ballots.target_ready.get(&target)) {
+                    return Err(anyhow!("This player can't be voted on again for {} seconds", wait.as_secs().max(1)));
+                }
+                if let Some(wait) = wait(ballots.starter_ready.get(&voter)) {
+                    return Err(anyhow!("You can start another vote-kick in {} seconds", wait.as_secs().max(1)));
+                }
+                ballots.starter_ready.insert(voter, now + self.rules.cooldown);
+                ballots.open = Some(OpenVote { target, voters: vec![voter], opened_at: now });
+            }
+        }
+
+        let votes = ballots.open.as_ref().map_or(0, |vote| vote.voters.len());
+        if votes < needed {
+            return Ok(VoteOutcome::Open { votes, needed });
+        }
+        ballots.open = None;
+        ballots.target_ready.insert(target, now + self.rules.cooldown);
+        Ok(VoteOutcome::Passed { votes })
+    }
+
+    /// Close a vote whose window ran out, returning its target
+    pub fn expire(&self, now: Instant) -> Option<PlayerId> {
+        let mut ballots = self.ballots.lock().unwrap();
+        let expired = ballots.open.as_ref().is_some_and(|vote| now.saturating_duration_since(vote.opened_at) >= self.rules.window);
+        if !expired {
+            return None;
+        }
+        let target = ballots.open.take()?.target;
+        ballots.target_ready.insert(target, now + self.rules.cooldown);
+        Some(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn players(count: usize) -> Vec<PlayerId> {
+        (0..count).map(|_| Uuid::new_v4().into()).collect()
+    }
+
+    #[test]
+    fn test_vote_passes_at_threshold() {
+        let kicks = VoteKicks::new(VoteKickRules::default());
+        let [target, a, b, c, d]: [PlayerId; 5] = players(5).try_into().unwrap();
+        let now = Instant::now();
+
+        // 60% of four voters rounds up to three
+        assert_eq!(kicks.vote(a, target, 4, now).unwrap(), VoteOutcome::Open { votes: 1, needed: 3 });
+        assert_eq!(kicks.vote(a, target, 4, now).unwrap_err().to_string(), "You already voted");
+        assert!(kicks.vote(b, d, 4, now).is_err());
+        assert_eq!(kicks.vote(b, target, 4, now).unwrap(), VoteOutcome::Open { votes: 2, needed: 3 });
+        assert_eq!(kicks.vote(c, target, 4, now).unwrap(), VoteOutcome::Passed { votes: 3 });
+
+        // Two players can't kick each other
+        assert!(kicks.vote(d, a, 1, now).is_err());
+    }
+
+    #[test]
+    fn test_expired_vote_puts_target_on_cooldown() {
+        let rules = VoteKickRules::default();
+        let kicks = VoteKicks::new(rules);
+        let [target, a, b]: [PlayerId; 3] = players(3).try_into().unwrap();
+        let now = Instant::now();
+
+        kicks.vote(a, target, 2, now).unwrap();
+        assert_eq!(kicks.expire(now + rules.window / 2), None);
+        assert_eq!(kicks.expire(now + rules.window), Some(target));
+
+        let later = now + rules.window + Duration::from_secs(1);
+        let err = kicks.vote(b, target, 2, later).unwrap_err();
+        assert!(err.to_string().starts_with("This player can't be voted on again"), "{}", err);
+        let err = kicks.vote(a, b, 2, later).unwrap_err();
+        assert!(err.to_string().starts_with("You can start another vote-kick in"), "{}", err);
+        assert!(kicks.vote(b, target, 2, now + rules.window + rules.cooldown).is_ok());
+    }
+}
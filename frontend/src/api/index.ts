@@ -7,14 +7,88 @@ export { CancelablePromise, CancelError } from './core/CancelablePromise';
 export { OpenAPI } from './core/OpenAPI';
 export type { OpenAPIConfig } from './core/OpenAPI';
 
+export type { ActionError } from './models/ActionError';
+export { AdminEvent } from './models/AdminEvent';
+export type { AiDecision } from './models/AiDecision';
+export { AiDecisionKind } from './models/AiDecisionKind';
+export type { AiOption } from './models/AiOption';
 export { AIPersonality } from './models/AIPersonality';
+export type { Battle } from './models/Battle';
+export type { BattleHighlight } from './models/BattleHighlight';
+export type { Border } from './models/Border';
 export { BuildingType } from './models/BuildingType';
+export type { ChatMessage } from './models/ChatMessage';
+export type { ChatPost } from './models/ChatPost';
+export type { Chokepoint } from './models/Chokepoint';
 export { ClientMessage } from './models/ClientMessage';
 export type { CombatResult } from './models/CombatResult';
+export type { ConnectionStats } from './models/ConnectionStats';
+export type { CreateRoomRequest } from './models/CreateRoomRequest';
+export { Difficulty } from './models/Difficulty';
+export type { Diplomacy } from './models/Diplomacy';
+export type { DraftResult } from './models/DraftResult';
+export type { DraftStatus } from './models/DraftStatus';
+export { EntityRef } from './models/EntityRef';
+export { EventCategory } from './models/EventCategory';
+export type { FairnessReport } from './models/FairnessReport';
+export { GameEndReason } from './models/GameEndReason';
+export type { GameListing } from './models/GameListing';
 export type { GameState } from './models/GameState';
 export type { GameStats } from './models/GameStats';
+export type { GameStatus } from './models/GameStatus';
+export type { GameSummary } from './models/GameSummary';
+export type { GymConfig } from './models/GymConfig';
+export type { GymOpened } from './models/GymOpened';
+export type { ImportedMap } from './models/ImportedMap';
+export type { LadderEntry } from './models/LadderEntry';
+export type { Leaderboard } from './models/Leaderboard';
+export { Lifecycle } from './models/Lifecycle';
+export type { MapDefinition } from './models/MapDefinition';
+export { MapGrid } from './models/MapGrid';
+export type { MapOption } from './models/MapOption';
+export type { MapPreview } from './models/MapPreview';
+export { MapStyle } from './models/MapStyle';
+export type { MatchParticipant } from './models/MatchParticipant';
+export type { MatchRecord } from './models/MatchRecord';
+export type { MatchResult } from './models/MatchResult';
+export { NotificationKey } from './models/NotificationKey';
 export { NotificationLevel } from './models/NotificationLevel';
+export type { Observation } from './models/Observation';
+export { Order } from './models/Order';
+export type { PerfStats } from './models/PerfStats';
 export type { Player } from './models/Player';
+export type { Profile } from './models/Profile';
+export { QuickChatId } from './models/QuickChatId';
+export type { QuickplayRequest } from './models/QuickplayRequest';
+export type { QuickplayResponse } from './models/QuickplayResponse';
+export type { RatioPreset } from './models/RatioPreset';
+export type { Region } from './models/Region';
+export type { ResearchProgress } from './models/ResearchProgress';
+export { ResourceType } from './models/ResourceType';
+export type { RoomInfo } from './models/RoomInfo';
+export { RuleOption } from './models/RuleOption';
+export type { RuleTally } from './models/RuleTally';
+export type { SectionTiming } from './models/SectionTiming';
 export { ServerMessage } from './models/ServerMessage';
+export type { StartReport } from './models/StartReport';
+export type { StateDelta } from './models/StateDelta';
+export type { StepRequest } from './models/StepRequest';
+export type { StepResult } from './models/StepResult';
+export type { SweepRequest } from './models/SweepRequest';
+export type { SweepSummary } from './models/SweepSummary';
+export { Tech } from './models/Tech';
 export { TerrainType } from './models/TerrainType';
 export type { Territory } from './models/Territory';
+export type { TerritoryDefinition } from './models/TerritoryDefinition';
+export type { TerritoryStats } from './models/TerritoryStats';
+export type { TimelineEntry } from './models/TimelineEntry';
+export type { TraversalRules } from './models/TraversalRules';
+export type { Treaty } from './models/Treaty';
+export type { TreatyOffer } from './models/TreatyOffer';
+export type { Truce } from './models/Truce';
+export type { UnitMix } from './models/UnitMix';
+export type { Units } from './models/Units';
+export { UnitType } from './models/UnitType';
+export type { VictoryProgress } from './models/VictoryProgress';
+
+export { StrategyGameService } from './services/StrategyGameService';
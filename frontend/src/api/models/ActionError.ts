@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * An action the game refused
+ */
+export type ActionError = {
+    index: number;
+    message: string;
+};
+
@@ -0,0 +1,69 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { GameEndReason } from './GameEndReason';
+import type { GameStats } from './GameStats';
+import type { GameStatus } from './GameStatus';
+/**
+ * Server-wide events streamed to the admin channel
+ */
+export type AdminEvent = ({
+    connection_id: string;
+    game_id: string;
+    player_id: string;
+    type: AdminEvent.type;
+} | {
+    connection_id: string;
+    game_id: string;
+    player_id: string;
+    type: AdminEvent.type;
+} | {
+    command: string;
+    connection_id: string;
+    game_id: string;
+    reason: string;
+    type: AdminEvent.type;
+} | {
+    connection_id: string;
+    /**
+     * The connection was closed as a penalty
+     */
+    disconnected: boolean;
+    game_id: string;
+    player_id: string;
+    type: AdminEvent.type;
+    violations: number;
+} | {
+    budget_ms: number;
+    elapsed_ms: number;
+    game_id: string;
+    tick: number;
+    type: AdminEvent.type;
+} | {
+    game_id: string;
+    name: string;
+    player_id: string;
+    type: AdminEvent.type;
+    votes: number;
+} | {
+    game_id: string;
+    message: string;
+    restarted: boolean;
+    tick: number;
+    type: AdminEvent.type;
+} | {
+    status: GameStatus;
+    type: AdminEvent.type;
+} | {
+    game_id: string;
+    reason: GameEndReason;
+    stats?: (null | GameStats);
+    type: AdminEvent.type;
+});
+export namespace AdminEvent {
+    export enum type {
+        CLIENT_CONNECTED = 'client_connected',
+    }
+}
+
@@ -0,0 +1,25 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { AiDecisionKind } from './AiDecisionKind';
+import type { AiOption } from './AiOption';
+import type { AIPersonality } from './AIPersonality';
+/**
+ * A recorded AI decision, for tuning and bug reports
+ */
+export type AiDecision = {
+    /**
+     * Index of the option picked
+     */
+    chosen?: number | null;
+    kind: AiDecisionKind;
+    options: Array<AiOption>;
+    /**
+     * What came of it, such as a failed roll or the engine's error
+     */
+    outcome: string;
+    personality: AIPersonality;
+    tick: number;
+};
+
@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * What an AI decision was about
+ */
+export enum AiDecisionKind {
+    BUILD = 'build',
+    ATTACK = 'attack',
+    ADVANCE = 'advance',
+    WAVE = 'wave',
+}
@@ -0,0 +1,24 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { BuildingType } from './BuildingType';
+/**
+ * One option an AI weighed; the highest score wins
+ */
+export type AiOption = {
+    building_type?: (null | BuildingType);
+    /**
+     * Territory an attack starts from
+     */
+    from?: string | null;
+    /**
+     * `None` if the personality ruled the option out
+     */
+    score?: number | null;
+    /**
+     * Territory attacked or built on
+     */
+    territory?: string | null;
+};
+
@@ -0,0 +1,48 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Units } from './Units';
+/**
+ * An attack fought over several ticks, taking casualties every tick until
+ * the territory falls, the attackers are spent or they retreat. An attack on
+ * a fortified territory starts as a siege
+ */
+export type Battle = {
+    attacker_id: string;
+    attacker_losses: Units;
+    /**
+     * Attackers still fighting
+     */
+    attackers: Units;
+    /**
+     * Every troop sent in, reinforcements included
+     */
+    committed: Units;
+    /**
+     * `None` for a neutral territory
+     */
+    defender_id: string | null;
+    defender_losses: Units;
+    /**
+     * The garrison when the battle began
+     */
+    defenders: Units;
+    /**
+     * Where the attack was launched from, and where survivors return to
+     */
+    from_territory: string;
+    id: number;
+    /**
+     * Rounds until the battle is decided
+     */
+    rounds_left: number;
+    /**
+     * Ticks the attackers still lay siege before they assault; 0 once the
+     * fighting has started
+     */
+    siege_ticks_left?: number;
+    started_tick: number;
+    territory: string;
+};
+
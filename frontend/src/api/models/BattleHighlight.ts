@@ -0,0 +1,23 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * The largest battle of a game
+ */
+export type BattleHighlight = {
+    attacker_name: string;
+    /**
+     * `None` for a neutral territory
+     */
+    defender_name?: string | null;
+    game_time_seconds: number;
+    territory_conquered: boolean;
+    territory_id: string;
+    territory_name: string;
+    /**
+     * Attacking plus defending troops
+     */
+    troops_involved: number;
+};
+
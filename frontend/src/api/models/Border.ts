@@ -0,0 +1,16 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Features of the border between a territory and one of its neighbors,
+ * listed on both sides
+ */
+export type Border = {
+    neighbor: string;
+    /**
+     * Attacks across a river are weaker unless either side has a Bridge
+     */
+    river?: boolean;
+};
+
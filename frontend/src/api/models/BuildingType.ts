@@ -9,4 +9,8 @@ export enum BuildingType {
     CITY = 'city',
     DEFENSE_POST = 'defense_post',
     GOLD_MINE = 'gold_mine',
+    BARRACKS = 'barracks',
+    HARBOR = 'harbor',
+    BRIDGE = 'bridge',
+    FARM = 'farm',
 }
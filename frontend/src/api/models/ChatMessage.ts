@@ -0,0 +1,17 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A message in the lobby chat
+ */
+export type ChatMessage = {
+    /**
+     * Increases with every message; poll with the last one seen
+     */
+    id: number;
+    name: string;
+    sent_at: string;
+    text: string;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+export type ChatPost = {
+    text: string;
+    /**
+     * Session token of a seat in any room; the message is posted under that player's name
+     */
+    token: string;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A border between two regions crossed by very few connections
+ */
+export type Chokepoint = {
+    from: string;
+    to: string;
+};
+
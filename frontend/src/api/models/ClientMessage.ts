@@ -2,11 +2,34 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { AIPersonality } from './AIPersonality';
 import type { BuildingType } from './BuildingType';
+import type { Order } from './Order';
+import type { QuickChatId } from './QuickChatId';
+import type { RuleOption } from './RuleOption';
+import type { Tech } from './Tech';
+import type { TraversalRules } from './TraversalRules';
 /**
  * Messages sent from client to server
  */
 export type ClientMessage = ({
+    color?: string | null;
+    name?: string | null;
+    /**
+     * Secret the client keeps across games, 16 to 128 characters; games
+     * played with it are rated on the ladder under one identity
+     */
+    player_key?: string | null;
+    type: ClientMessage.type;
+} | {
+    from: string;
+    to: string;
+    type: ClientMessage.type;
+} | {
+    battle: number;
+    type: ClientMessage.type;
+} | {
+    amount: number;
     from: string;
     to: string;
     type: ClientMessage.type;
@@ -14,18 +37,113 @@ export type ClientMessage = ({
     building_type: BuildingType;
     territory: string;
     type: ClientMessage.type;
+} | {
+    territory: string;
+    type: ClientMessage.type;
+} | {
+    territory: string;
+    type: ClientMessage.type;
 } | {
     ratio: number;
     type: ClientMessage.type;
+} | {
+    ratio: number;
+    type: ClientMessage.type;
+} | {
+    attack_ratio: number;
+    name: string;
+    troop_ratio: number;
+    type: ClientMessage.type;
+} | {
+    name: string;
+    type: ClientMessage.type;
+} | {
+    cavalry: number;
+    infantry: number;
+    siege: number;
+    type: ClientMessage.type;
+} | {
+    order: Order;
+    tick?: number | null;
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
+} | {
+    tech: Tech;
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
 } | {
     type: ClientMessage.type;
 } | {
     speed: number;
     type: ClientMessage.type;
+} | {
+    player: string;
+    type: ClientMessage.type;
+} | {
+    player: string;
+    type: ClientMessage.type;
+} | {
+    territory: string;
+    to_player: string;
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
+} | {
+    ids: Array<string>;
+    type: ClientMessage.type;
+} | {
+    ids: Array<string>;
+    type: ClientMessage.type;
+} | {
+    from: string;
+    rules?: TraversalRules;
+    to: string;
+    type: ClientMessage.type;
+} | {
+    checksum: number;
+    tick: number;
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
+} | {
+    nonce: number;
+    type: ClientMessage.type;
+} | {
+    id: QuickChatId;
+    target?: string | null;
+    type: ClientMessage.type;
+} | {
+    territory: string;
+    type: ClientMessage.type;
+} | {
+    map: number;
+    type: ClientMessage.type;
+} | {
+    enabled: boolean;
+    rule: RuleOption;
+    type: ClientMessage.type;
+} | {
+    personalities: Array<AIPersonality>;
+    type: ClientMessage.type;
+} | {
+    type: ClientMessage.type;
+} | {
+    name: string;
+    type: ClientMessage.type;
+} | {
+    name: string;
+    type: ClientMessage.type;
+} | {
+    player_id: string;
+    type: ClientMessage.type;
 });
 export namespace ClientMessage {
     export enum type {
-        ATTACK = 'attack',
+        JOIN_GAME = 'join_game',
     }
 }
 
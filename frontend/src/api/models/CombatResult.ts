@@ -2,6 +2,7 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { Units } from './Units';
 /**
  * Combat result after an attack
  */
@@ -9,9 +10,25 @@ export type CombatResult = {
     attacker_id: string;
     attacker_losses: number;
     attacker_troops_committed: number;
+    /**
+     * `attacker_losses` by unit type
+     */
+    attacker_unit_losses?: Units;
+    /**
+     * The committed troops by unit type
+     */
+    attacker_units?: Units;
     defender_id: string;
     defender_losses: number;
     defender_troops: number;
+    /**
+     * `defender_losses` by unit type
+     */
+    defender_unit_losses?: Units;
+    /**
+     * The defending garrison by unit type
+     */
+    defender_units?: Units;
     from_territory: string;
     territory_conquered: boolean;
     to_territory: string;
@@ -0,0 +1,26 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Outgoing delivery counters of one connection
+ */
+export type ConnectionStats = {
+    connection_id: string;
+    /**
+     * State updates replaced by a newer one before they could be sent
+     */
+    dropped_frames: number;
+    latency_ms?: number | null;
+    messages_sent: number;
+    player_id: string;
+    /**
+     * Events and critical messages waiting to be sent
+     */
+    queue_depth: number;
+    /**
+     * Messages skipped because they could not be serialized
+     */
+    serialization_failures: number;
+};
+
@@ -0,0 +1,36 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Difficulty } from './Difficulty';
+import type { MapGrid } from './MapGrid';
+import type { MapStyle } from './MapStyle';
+/**
+ * Settings of a room to open
+ */
+export type CreateRoomRequest = {
+    allow_late_join?: boolean;
+    difficulty?: Difficulty;
+    grid?: MapGrid;
+    /**
+     * Players reserved for humans, the rest are AI
+     */
+    human_slots?: number;
+    /**
+     * Imported map to play on instead of a generated one, with a player for
+     * each of its starts; `territories`, `players`, `style` and `grid` are ignored
+     */
+    map?: string | null;
+    /**
+     * Shown in the room list; generated if omitted
+     */
+    name?: string | null;
+    players?: number;
+    /**
+     * Map seed, random if omitted
+     */
+    seed?: number | null;
+    style?: MapStyle;
+    territories?: number;
+};
+
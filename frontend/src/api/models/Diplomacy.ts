@@ -0,0 +1,16 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Treaty } from './Treaty';
+import type { TreatyOffer } from './TreatyOffer';
+import type { Truce } from './Truce';
+/**
+ * Relations between players
+ */
+export type Diplomacy = {
+    offers: Array<TreatyOffer>;
+    treaties: Array<Treaty>;
+    truces?: Array<Truce>;
+};
+
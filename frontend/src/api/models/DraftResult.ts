@@ -0,0 +1,19 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { MapOption } from './MapOption';
+import type { RuleOption } from './RuleOption';
+/**
+ * What a lobby's draft settled on
+ */
+export type DraftResult = {
+    /**
+     * AI opponents the host picked, in order; `null` keeps those the game was set up with
+     */
+    ai_opponents?: any[] | null;
+    enabled_rules: Array<RuleOption>;
+    map: MapOption;
+    vetoed_maps: Array<MapOption>;
+};
+
@@ -0,0 +1,26 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { MapOption } from './MapOption';
+import type { RuleTally } from './RuleTally';
+/**
+ * State of a lobby's draft, sent after every veto and vote
+ */
+export type DraftStatus = {
+    /**
+     * AI opponents the host picked, in order; `null` keeps those the game was set up with
+     */
+    ai_opponents?: any[] | null;
+    /**
+     * The player who may start the game
+     */
+    host: string;
+    maps: Array<MapOption>;
+    rules: Array<RuleTally>;
+    /**
+     * Indices into `maps`
+     */
+    vetoed: Array<number>;
+};
+
@@ -0,0 +1,20 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A player or territory an event concerns
+ */
+export type EntityRef = ({
+    id: string;
+    kind: EntityRef.kind;
+} | {
+    id: string;
+    kind: EntityRef.kind;
+});
+export namespace EntityRef {
+    export enum kind {
+        PLAYER = 'player',
+    }
+}
+
@@ -0,0 +1,14 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * What an event is about, for filtering and screen readers
+ */
+export enum EventCategory {
+    COMBAT = 'combat',
+    ECONOMY = 'economy',
+    DIPLOMACY = 'diplomacy',
+    PLAYERS = 'players',
+    SYSTEM = 'system',
+}
@@ -0,0 +1,32 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { StartReport } from './StartReport';
+/**
+ * Fairness of a map's starting positions
+ */
+export type FairnessReport = {
+    /**
+     * Worst to best ratio of distances to the nearest opponent
+     */
+    distance_balance: number;
+    /**
+     * Radius of the terrain value measure
+     */
+    hops: number;
+    /**
+     * Worst to best ratio of neighbor counts
+     */
+    neighbor_balance: number;
+    /**
+     * Average of the three balances, from 0 (unfair) to 1 (every start alike)
+     */
+    score: number;
+    starts: Array<StartReport>;
+    /**
+     * Worst to best ratio of nearby terrain value
+     */
+    terrain_balance: number;
+};
+
@@ -0,0 +1,11 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Why a game ended
+ */
+export enum GameEndReason {
+    VICTORY = 'victory',
+    ABANDONED = 'abandoned',
+}
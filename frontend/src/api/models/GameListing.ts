@@ -0,0 +1,19 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A game as advertised to every server instance
+ */
+export type GameListing = {
+    connected_clients: number;
+    game_id: string;
+    /**
+     * Server instance hosting the game
+     */
+    instance_id: string;
+    players_alive: number;
+    tick: number;
+    updated_at: string;
+};
+
@@ -2,17 +2,42 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { Battle } from './Battle';
+import type { Chokepoint } from './Chokepoint';
+import type { Diplomacy } from './Diplomacy';
+import type { MapGrid } from './MapGrid';
 import type { Player } from './Player';
+import type { Region } from './Region';
 import type { Territory } from './Territory';
+import type { VictoryProgress } from './VictoryProgress';
 /**
  * Complete game state
  */
 export type GameState = {
+    /**
+     * Attacks still being fought, when battles last several ticks
+     */
+    battles?: Array<Battle>;
+    chokepoints?: Array<Chokepoint>;
+    diplomacy?: Diplomacy;
     game_speed: number;
     game_time_seconds: number;
+    /**
+     * How to read territory positions
+     */
+    grid?: MapGrid;
     is_paused: boolean;
     players: Array<Player>;
+    /**
+     * Map structure for strategic overlays
+     */
+    regions?: Array<Region>;
     territories: Array<Territory>;
     tick: number;
+    /**
+     * How close every living player is to each active victory condition,
+     * updated every tick
+     */
+    victory?: Array<VictoryProgress>;
 };
 
@@ -0,0 +1,29 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { ConnectionStats } from './ConnectionStats';
+import type { Lifecycle } from './Lifecycle';
+import type { PerfStats } from './PerfStats';
+/**
+ * Live snapshot of one running game, for operators
+ */
+export type GameStatus = {
+    connected_clients: number;
+    /**
+     * Delivery health of each connection
+     */
+    connections?: Array<ConnectionStats>;
+    game_id: string;
+    game_time_seconds: number;
+    is_paused: boolean;
+    lifecycle?: Lifecycle;
+    perf: PerfStats;
+    players_alive: number;
+    tick: number;
+    /**
+     * Time budget of a tick
+     */
+    tick_rate_ms?: number;
+};
+
@@ -0,0 +1,29 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { BattleHighlight } from './BattleHighlight';
+import type { DraftResult } from './DraftResult';
+import type { GameStats } from './GameStats';
+import type { TerritoryStats } from './TerritoryStats';
+import type { TimelineEntry } from './TimelineEntry';
+/**
+ * Shareable end-of-game report
+ */
+export type GameSummary = {
+    biggest_battle?: (null | BattleHighlight);
+    draft?: (null | DraftResult);
+    game_id: string;
+    /**
+     * Territories fought over most, the most contested first
+     */
+    most_contested?: Array<TerritoryStats>;
+    stats: GameStats;
+    /**
+     * Ready-to-post Markdown, within Discord's message length limit
+     */
+    text: string;
+    timeline: Array<TimelineEntry>;
+    winner_name: string;
+};
+
@@ -0,0 +1,23 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Difficulty } from './Difficulty';
+import type { MapStyle } from './MapStyle';
+/**
+ * Settings for a new environment
+ */
+export type GymConfig = {
+    difficulty?: Difficulty;
+    /**
+     * The agent and the AIs it plays against
+     */
+    players?: number;
+    /**
+     * Seeds the map and every random choice the game makes
+     */
+    seed?: number;
+    style?: MapStyle;
+    territories?: number;
+};
+
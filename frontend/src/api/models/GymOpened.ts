@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Observation } from './Observation';
+/**
+ * A new environment and where the agent starts
+ */
+export type GymOpened = {
+    env_id: string;
+    observation: Observation;
+};
+
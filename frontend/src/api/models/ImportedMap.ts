@@ -0,0 +1,17 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A map accepted by `POST /maps`
+ */
+export type ImportedMap = {
+    /**
+     * Pass as `map` when creating a room
+     */
+    map_id: string;
+    name: string;
+    players: number;
+    territories: number;
+};
+
@@ -0,0 +1,18 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * One player's record in a season
+ */
+export type LadderEntry = {
+    games: number;
+    /**
+     * The name the player last played a rated game under
+     */
+    name: string;
+    player: string;
+    rating: number;
+    wins: number;
+};
+
@@ -0,0 +1,19 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { LadderEntry } from './LadderEntry';
+/**
+ * Placed players of a season, best first
+ */
+export type Leaderboard = {
+    ends_at: string;
+    season: number;
+    standings: Array<LadderEntry>;
+    started_at: string;
+    /**
+     * Players still in their placement matches
+     */
+    unplaced_players: number;
+};
+
@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Where a game is in its life; it only ever moves forward
+ */
+export enum Lifecycle {
+    LOBBY = 'lobby',
+    RUNNING = 'running',
+    FINISHED = 'finished',
+    ARCHIVED = 'archived',
+}
@@ -0,0 +1,22 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { MapGrid } from './MapGrid';
+import type { TerritoryDefinition } from './TerritoryDefinition';
+/**
+ * A map drawn by hand
+ */
+export type MapDefinition = {
+    /**
+     * How to read the territory positions
+     */
+    grid?: MapGrid;
+    name?: string;
+    /**
+     * Keys of the starting territories, one per player in player order
+     */
+    starts: Array<string>;
+    territories: Array<TerritoryDefinition>;
+};
+
@@ -0,0 +1,11 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * How territories are shaped and placed
+ */
+export enum MapGrid {
+    VORONOI = 'voronoi',
+    HEX = 'hex',
+}
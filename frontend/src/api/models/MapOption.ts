@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { MapStyle } from './MapStyle';
+/**
+ * A map on a lobby's ballot, regenerated from its seed if picked
+ */
+export type MapOption = {
+    seed: number;
+    style: MapStyle;
+};
+
@@ -0,0 +1,18 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { FairnessReport } from './FairnessReport';
+import type { GameState } from './GameState';
+/**
+ * A generated map and how fair its starts are
+ */
+export type MapPreview = {
+    fairness: FairnessReport;
+    /**
+     * Seed that regenerates this map
+     */
+    seed: number;
+    state: GameState;
+};
+
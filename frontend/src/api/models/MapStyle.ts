@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * How the map generator picks terrain
+ */
+export enum MapStyle {
+    SCATTERED = 'scattered',
+    CONTINENTS = 'continents',
+    ISLANDS = 'islands',
+    PANGAEA = 'pangaea',
+}
@@ -0,0 +1,17 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A participant in a finished game
+ */
+export type MatchParticipant = {
+    /**
+     * Ladder identity of a human who joined with a player key
+     */
+    identity?: string | null;
+    is_ai: boolean;
+    name: string;
+    won: boolean;
+};
+
@@ -0,0 +1,18 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A finished game from one player's side
+ */
+export type MatchRecord = {
+    duration_seconds: number;
+    finished_at: string;
+    game_id: string;
+    /**
+     * Everyone else in the game, AI players included
+     */
+    opponents: Array<string>;
+    won: boolean;
+};
+
@@ -0,0 +1,18 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { GameStats } from './GameStats';
+import type { MatchParticipant } from './MatchParticipant';
+/**
+ * A finished game as stored
+ */
+export type MatchResult = {
+    duration_seconds: number;
+    finished_at: string;
+    game_id: string;
+    players: Array<MatchParticipant>;
+    stats: GameStats;
+    winner: string;
+};
+
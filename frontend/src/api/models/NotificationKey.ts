@@ -0,0 +1,29 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * What a notification is about; see `src/i18n.rs` for each key's parameters
+ */
+export enum NotificationKey {
+    ANNOUNCEMENT = 'announcement',
+    PLAYER_JOINED = 'player_joined',
+    PLAYER_ELIMINATED = 'player_eliminated',
+    SLOT_FILLED_BY_AI = 'slot_filled_by_ai',
+    TERRITORY_CAPTURED = 'territory_captured',
+    CAPITAL_CAPTURED = 'capital_captured',
+    TREATY_SIGNED = 'treaty_signed',
+    TREATY_BROKEN = 'treaty_broken',
+    BUILDING_COMPLETED = 'building_completed',
+    GAME_SAVED = 'game_saved',
+    GAME_LOADED = 'game_loaded',
+    VOTE_KICK_PROGRESS = 'vote_kick_progress',
+    VOTE_KICK_FAILED = 'vote_kick_failed',
+    VOTE_KICKED = 'vote_kicked',
+    GAME_RECOVERED = 'game_recovered',
+    GAME_STOPPED = 'game_stopped',
+    REPLAY_ENDED = 'replay_ended',
+    GAME_PAUSED = 'game_paused',
+    GAME_RESUMED = 'game_resumed',
+    GAME_SPEED_CHANGED = 'game_speed_changed',
+}
@@ -0,0 +1,36 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * The game as the agent sees it. Territory arrays follow `territory_ids`
+ * and player arrays follow `player_ids`, whose first entry is the agent
+ */
+export type Observation = {
+    alive: Array<boolean>;
+    army: Array<number>;
+    /**
+     * Building as its position in `BuildingType`, -1 if none
+     */
+    building: Array<number>;
+    /**
+     * Borders as pairs of territory indices, each listed once
+     */
+    edges: Array<Array<number>>;
+    gold: Array<number>;
+    /**
+     * Index of each territory's owner in the player arrays, -1 if neutral
+     */
+    owner: Array<number>;
+    player_ids: Array<string>;
+    population: Array<number>;
+    /**
+     * Terrain as 0 plains, 1 mountains, 2 forests, 3 water
+     */
+    terrain: Array<number>;
+    territories_held: Array<number>;
+    territory_ids: Array<string>;
+    tick: number;
+    troops: Array<number>;
+};
+
@@ -0,0 +1,28 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { BuildingType } from './BuildingType';
+/**
+ * A command queued for a later tick
+ */
+export type Order = ({
+    from: string;
+    to: string;
+    type: Order.type;
+} | {
+    building_type: BuildingType;
+    territory: string;
+    type: Order.type;
+} | {
+    amount: number;
+    from: string;
+    to: string;
+    type: Order.type;
+});
+export namespace Order {
+    export enum type {
+        ATTACK = 'attack',
+    }
+}
+
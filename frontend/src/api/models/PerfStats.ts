@@ -0,0 +1,27 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { SectionTiming } from './SectionTiming';
+/**
+ * Per-section timings of the game loop
+ */
+export type PerfStats = {
+    /**
+     * Troop distribution and AI decisions
+     */
+    ai: SectionTiming;
+    /**
+     * Individual attack resolutions (human and AI)
+     */
+    combat: SectionTiming;
+    /**
+     * Serializing outgoing messages for clients
+     */
+    serialization: SectionTiming;
+    /**
+     * Economy and bookkeeping in `GameEngine::tick`
+     */
+    tick: SectionTiming;
+};
+
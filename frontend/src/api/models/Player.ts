@@ -3,6 +3,10 @@
 /* tslint:disable */
 /* eslint-disable */
 import type { AIPersonality } from './AIPersonality';
+import type { RatioPreset } from './RatioPreset';
+import type { ResearchProgress } from './ResearchProgress';
+import type { Tech } from './Tech';
+import type { UnitMix } from './UnitMix';
 /**
  * A player in the game (human or AI)
  */
@@ -13,17 +17,56 @@ export type Player = {
      */
     attack_ratio: number;
     color: string;
+    /**
+     * Food stockpiled
+     */
+    food?: number;
+    /**
+     * Food harvested minus food eaten per second at the current game speed;
+     * the population starves while this is negative and nothing is stockpiled
+     */
+    food_per_second?: number;
     gold: number;
+    /**
+     * Gold earned per second at the current game speed, all bonuses included
+     */
+    gold_per_second?: number;
     id: string;
     is_ai: boolean;
     is_alive: boolean;
+    /**
+     * Round-trip time to the player's client, while connected
+     */
+    latency_ms?: number | null;
     max_population: number;
     name: string;
     population: number;
+    /**
+     * Population growth per second at the current game speed; 0 at the cap
+     * or without a food surplus
+     */
+    population_growth_per_second?: number;
+    /**
+     * Ratio presets the player saved; the built-in ones aren't listed
+     */
+    presets?: Array<RatioPreset>;
+    research?: (null | ResearchProgress);
+    /**
+     * Techs researched, in the order they were finished
+     */
+    techs?: Array<Tech>;
     territories_controlled: number;
     /**
-     * Percentage of population used as troops (rest are workers)
+     * Percentage actually trained; follows `troop_ratio` at the training rate
+     */
+    trained_ratio?: number;
+    /**
+     * Target percentage of population used as troops (rest are workers)
      */
     troop_ratio: number;
+    /**
+     * How new troops are split between unit types
+     */
+    unit_mix?: UnitMix;
 };
 
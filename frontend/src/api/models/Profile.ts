@@ -0,0 +1,22 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { MatchRecord } from './MatchRecord';
+/**
+ * A player's totals and recent matches, newest first
+ */
+export type Profile = {
+    games: number;
+    /**
+     * The name the player last played under
+     */
+    name: string;
+    /**
+     * Ladder identity of the player
+     */
+    player: string;
+    recent_matches: Array<MatchRecord>;
+    wins: number;
+};
+
@@ -0,0 +1,22 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Predefined quick-chat messages and emotes; clients render them in the
+ * reader's language, so there is no free text to abuse
+ */
+export enum QuickChatId {
+    HELLO = 'hello',
+    GOOD_LUCK = 'good_luck',
+    GOOD_GAME = 'good_game',
+    WELL_PLAYED = 'well_played',
+    THANKS = 'thanks',
+    SORRY = 'sorry',
+    OOPS = 'oops',
+    WAIT = 'wait',
+    ATTACK_NOW = 'attack_now',
+    HOLD_POSITION = 'hold_position',
+    NEED_HELP = 'need_help',
+    LAUGH = 'laugh',
+}
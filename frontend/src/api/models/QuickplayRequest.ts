@@ -0,0 +1,11 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+export type QuickplayRequest = {
+    /**
+     * Token from an earlier quick-play game, to get back into it while it runs
+     */
+    token?: string | null;
+};
+
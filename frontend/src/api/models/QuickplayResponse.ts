@@ -0,0 +1,20 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Where to connect for a quick-play game
+ */
+export type QuickplayResponse = {
+    player_id: string;
+    room_id: string;
+    /**
+     * Session token reserving the player; only connections presenting it play as them
+     */
+    token: string;
+    /**
+     * WebSocket URL to connect to, token included
+     */
+    ws_url: string;
+};
+
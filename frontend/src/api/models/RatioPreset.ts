@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A named pair of troop and attack ratios a player can switch to with one command
+ */
+export type RatioPreset = {
+    attack_ratio: number;
+    name: string;
+    troop_ratio: number;
+};
+
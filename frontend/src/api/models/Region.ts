@@ -0,0 +1,17 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A group of neighboring territories, computed by the map generator
+ */
+export type Region = {
+    /**
+     * Average position of the region's territories
+     */
+    center: any[];
+    id: number;
+    name?: string;
+    territories: Array<string>;
+};
+
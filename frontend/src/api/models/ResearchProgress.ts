@@ -0,0 +1,16 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Tech } from './Tech';
+/**
+ * A research under way
+ */
+export type ResearchProgress = {
+    /**
+     * Share done, from 0 to 1
+     */
+    progress: number;
+    tech: Tech;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Deposits a territory can hold; whoever owns it gets the bonus
+ */
+export enum ResourceType {
+    IRON = 'iron',
+    HORSES = 'horses',
+    GEMS = 'gems',
+}
@@ -0,0 +1,26 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Lifecycle } from './Lifecycle';
+/**
+ * A room as shown in the room list
+ */
+export type RoomInfo = {
+    connected_clients: number;
+    created_at: string;
+    /**
+     * The game has ended and the room can't be joined
+     */
+    finished: boolean;
+    human_slots: number;
+    lifecycle: Lifecycle;
+    name: string;
+    /**
+     * Human slots nobody has claimed yet
+     */
+    open_slots: number;
+    room_id: string;
+    tick: number;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Rule switches lobby players vote on
+ */
+export enum RuleOption {
+    LATE_JOIN = 'late_join',
+    DICE_COMBAT = 'dice_combat',
+    AGGRESSIVE_NEUTRALS = 'aggressive_neutrals',
+}
@@ -0,0 +1,14 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { RuleOption } from './RuleOption';
+/**
+ * Votes cast on one rule switch
+ */
+export type RuleTally = {
+    no: number;
+    rule: RuleOption;
+    yes: number;
+};
+
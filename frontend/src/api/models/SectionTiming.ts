@@ -0,0 +1,14 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Timing statistics for one instrumented section of the game loop
+ */
+export type SectionTiming = {
+    last_us: number;
+    max_us: number;
+    samples: number;
+    total_us: number;
+};
+
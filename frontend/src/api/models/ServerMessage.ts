@@ -3,16 +3,43 @@
 /* tslint:disable */
 /* eslint-disable */
 import type { BuildingType } from './BuildingType';
+import type { ClientMessage } from './ClientMessage';
 import type { CombatResult } from './CombatResult';
+import type { DraftResult } from './DraftResult';
+import type { DraftStatus } from './DraftStatus';
+import type { EntityRef } from './EntityRef';
+import type { EventCategory } from './EventCategory';
 import type { GameState } from './GameState';
 import type { GameStats } from './GameStats';
+import type { NotificationKey } from './NotificationKey';
 import type { NotificationLevel } from './NotificationLevel';
+import type { Order } from './Order';
+import type { PerfStats } from './PerfStats';
+import type { Player } from './Player';
+import type { QuickChatId } from './QuickChatId';
+import type { StateDelta } from './StateDelta';
+import type { Tech } from './Tech';
+import type { Territory } from './Territory';
+import type { TerritoryStats } from './TerritoryStats';
 /**
  * Messages sent from server to client
  */
 export type ServerMessage = ({
+    color: string;
+    game_id: string;
+    name: string;
+    player_id: string;
+    type: ServerMessage.type;
+} | {
+    /**
+     * `state`'s checksum, for clients checking they are in sync
+     */
+    checksum?: number;
     state: GameState;
     type: ServerMessage.type;
+} | {
+    delta: StateDelta;
+    type: ServerMessage.type;
 } | {
     result: CombatResult;
     type: ServerMessage.type;
@@ -21,29 +48,140 @@ export type ServerMessage = ({
     old_owner: string | null;
     territory_id: string;
     type: ServerMessage.type;
+} | {
+    from: string;
+    type: ServerMessage.type;
+} | {
+    from: string;
+    territory_id: string;
+    to: string;
+    type: ServerMessage.type;
 } | {
     building_type: BuildingType;
     player_id: string;
     territory_id: string;
     type: ServerMessage.type;
+} | {
+    order: Order;
+    order_id: number;
+    player_id: string;
+    /**
+     * Tick it is due at
+     */
+    tick: number;
+    type: ServerMessage.type;
+} | {
+    order_id: number;
+    player_id: string;
+    tick: number;
+    type: ServerMessage.type;
+} | {
+    order_id: number;
+    player_id: string;
+    reason: string;
+    type: ServerMessage.type;
+} | {
+    player_id: string;
+    tech: Tech;
+    type: ServerMessage.type;
 } | {
     eliminated_by: string;
-    player_id_test: string;
+    player_id: string;
     type: ServerMessage.type;
 } | {
     stats: GameStats;
     type: ServerMessage.type;
 } | {
+    /**
+     * Follows from the key
+     */
+    category?: EventCategory;
+    /**
+     * Players and territories involved, for screen readers and filters
+     */
+    entities?: Array<EntityRef>;
+    /**
+     * What happened, for clients that word notifications themselves
+     */
+    key: NotificationKey;
+    /**
+     * Text in the locale the client connected with
+     */
     message: string;
+    /**
+     * Values of the placeholders in the key's text
+     */
+    params?: Record<string, string>;
     severity: NotificationLevel;
     type: ServerMessage.type;
 } | {
     message: string;
     type: ServerMessage.type;
+} | {
+    incident_id: string;
+    message: string;
+    type: ServerMessage.type;
+} | {
+    territories: Array<Territory>;
+    tick: number;
+    type: ServerMessage.type;
+} | {
+    players: Array<Player>;
+    tick: number;
+    type: ServerMessage.type;
+} | {
+    from: string;
+    path: any[] | null;
+    tick: number;
+    to: string;
+    type: ServerMessage.type;
+} | {
+    territories: Array<TerritoryStats>;
+    tick: number;
+    type: ServerMessage.type;
+} | {
+    stats: PerfStats;
+    type: ServerMessage.type;
+} | {
+    nonce: number;
+    server_tick: number;
+    type: ServerMessage.type;
+} | {
+    accepted: boolean;
+    command_id: string;
+    duplicate: boolean;
+    type: ServerMessage.type;
+} | {
+    from: string;
+    id: QuickChatId;
+    /**
+     * Set when only the target player was meant to see it
+     */
+    target: string | null;
+    type: ServerMessage.type;
+} | {
+    candidates: Array<string>;
+    player_id: string;
+    seconds: number;
+    type: ServerMessage.type;
+} | {
+    player_id: string;
+    territory_id: string;
+    type: ServerMessage.type;
+} | {
+    draft: DraftStatus;
+    type: ServerMessage.type;
+} | {
+    result: DraftResult;
+    type: ServerMessage.type;
+} | {
+    command: ClientMessage;
+    player_id: string;
+    type: ServerMessage.type;
 });
 export namespace ServerMessage {
     export enum type {
-        GAME_STATE_UPDATE = 'game_state_update',
+        JOINED = 'joined',
     }
 }
 
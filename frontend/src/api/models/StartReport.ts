@@ -0,0 +1,21 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * How one player's start compares
+ */
+export type StartReport = {
+    /**
+     * Hops to the closest territory held by another player; `None` if unreachable
+     */
+    nearest_opponent_hops?: number | null;
+    neighbors: number;
+    player_id: string;
+    /**
+     * Summed terrain bonuses of the territories within `hops` of the start
+     */
+    terrain_value: number;
+    territory_id: string;
+};
+
@@ -0,0 +1,42 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Diplomacy } from './Diplomacy';
+import type { Player } from './Player';
+import type { Territory } from './Territory';
+/**
+ * Territories and players that changed between two broadcast states
+ */
+export type StateDelta = {
+    /**
+     * Tick of the state this delta applies to
+     */
+    base_tick: number;
+    /**
+     * Battles under way, if they changed
+     */
+    battles?: any[] | null;
+    /**
+     * Checksum of the state once the delta is applied
+     */
+    checksum: number;
+    diplomacy?: (null | Diplomacy);
+    game_speed: number;
+    game_time_seconds: number;
+    is_paused: boolean;
+    /**
+     * Changed players, replacing those with the same id; new players are appended
+     */
+    players: Array<Player>;
+    /**
+     * Changed territories, replacing those with the same id
+     */
+    territories: Array<Territory>;
+    tick: number;
+    /**
+     * Victory progress, if it changed
+     */
+    victory?: any[] | null;
+};
+
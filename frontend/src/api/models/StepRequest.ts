@@ -0,0 +1,19 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { ClientMessage } from './ClientMessage';
+/**
+ * Actions for one step and how far to play it
+ */
+export type StepRequest = {
+    /**
+     * Commands as the agent would send them over the WebSocket, applied in order
+     */
+    actions?: Array<ClientMessage>;
+    /**
+     * Ticks to play after the actions
+     */
+    ticks?: number;
+};
+
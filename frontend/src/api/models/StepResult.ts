@@ -0,0 +1,26 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { ActionError } from './ActionError';
+import type { Observation } from './Observation';
+/**
+ * What a step led to
+ */
+export type StepResult = {
+    /**
+     * The game is over or the agent was eliminated; further steps change nothing
+     */
+    done: boolean;
+    /**
+     * Why each rejected action was rejected, by its position in `actions`
+     */
+    errors: Array<ActionError>;
+    observation: Observation;
+    /**
+     * Territories the agent gained over the step, negative if it lost ground
+     */
+    reward: number;
+    winner?: string | null;
+};
+
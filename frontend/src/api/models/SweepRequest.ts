@@ -0,0 +1,29 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A balance parameter sweep
+ */
+export type SweepRequest = {
+    /**
+     * Games played per value, on maps seeded `seed`, `seed + 1`, ...
+     */
+    games_per_value: number;
+    /**
+     * Games still running after this many ticks count as unfinished
+     */
+    max_ticks?: number;
+    /**
+     * Rule to vary, e.g. `gold_mine_multiplier`
+     */
+    parameter: string;
+    players?: number;
+    seed?: number;
+    territories?: number;
+    /**
+     * Values to try
+     */
+    values: Array<number>;
+};
+
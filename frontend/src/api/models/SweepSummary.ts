@@ -0,0 +1,25 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Aggregated results for one parameter value
+ */
+export type SweepSummary = {
+    /**
+     * Average length of finished games, in ticks
+     */
+    avg_ticks: number;
+    finished_games: number;
+    games: number;
+    /**
+     * Territory leaders of unfinished games, per AI personality
+     */
+    leads: Record<string, number>;
+    value: number;
+    /**
+     * Wins per AI personality
+     */
+    wins: Record<string, number>;
+};
+
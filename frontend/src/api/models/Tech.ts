@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Upgrades players research with gold over time
+ */
+export enum Tech {
+    DRILL = 'drill',
+    TACTICS = 'tactics',
+    MASONRY = 'masonry',
+    AGRICULTURE = 'agriculture',
+}
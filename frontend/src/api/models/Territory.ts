@@ -2,27 +2,67 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { Border } from './Border';
 import type { BuildingType } from './BuildingType';
+import type { ResourceType } from './ResourceType';
 import type { TerrainType } from './TerrainType';
+import type { Units } from './Units';
 /**
  * A territory on the map
  */
 export type Territory = {
+    /**
+     * Borders with a natural feature, such as a river; the others are open
+     */
+    borders?: Array<Border>;
     building?: (null | BuildingType);
+    /**
+     * Tick until which a captured building does nothing for its new owner
+     */
+    building_idle_until?: number;
+    /**
+     * Level of the building, from 1; upgrades raise it
+     */
+    building_level?: number;
+    /**
+     * Player whose capital this is, their starting territory; it stays
+     * theirs to take back when conquered
+     */
+    capital_of?: string | null;
+    /**
+     * Land bordering a water territory
+     */
+    coastal?: boolean;
     id: string;
+    /**
+     * Display name, unique within the map
+     */
+    name?: string;
     /**
      * Neighboring territory IDs
      */
     neighbors: any[] | null;
     owner: string | null;
     /**
-     * Visual position for rendering (x, y normalized 0-1)
+     * Outline of the territory's cell or hex, counter-clockwise, in the same
+     * coordinates as `position`
+     */
+    polygon?: Array<any[]>;
+    /**
+     * Visual position for rendering (x, y normalized 0-1), or axial
+     * coordinates (q, r) on hex maps
      */
     position: any[];
+    /**
+     * Index of the region this territory belongs to
+     */
+    region?: number;
+    resource?: (null | ResourceType);
     terrain: TerrainType;
     /**
-     * Current troops stationed in this territory
+     * Troops stationed in this territory, by unit type; a plain number is
+     * read as that many infantry
      */
-    troops: number;
+    units: Units;
 };
 
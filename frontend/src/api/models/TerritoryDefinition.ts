@@ -0,0 +1,34 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { BuildingType } from './BuildingType';
+import type { ResourceType } from './ResourceType';
+import type { TerrainType } from './TerrainType';
+/**
+ * A territory of a hand-made map
+ */
+export type TerritoryDefinition = {
+    building?: (null | BuildingType);
+    /**
+     * Unique within the map; neighbors and starts refer to it
+     */
+    key: string;
+    /**
+     * Display name; generated if blank
+     */
+    name?: string;
+    /**
+     * Keys of the neighboring territories, which must list this one back
+     */
+    neighbors: Array<string>;
+    polygon?: Array<any[]>;
+    position?: any[];
+    resource?: (null | ResourceType);
+    /**
+     * Keys of the neighbors across a river, which must list this one back too
+     */
+    rivers?: Array<string>;
+    terrain: TerrainType;
+};
+
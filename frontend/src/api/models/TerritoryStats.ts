@@ -0,0 +1,25 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * How much fighting a territory has seen
+ */
+export type TerritoryStats = {
+    /**
+     * Attacks on the territory, failed ones included
+     */
+    battles: number;
+    last_battle_seconds: number;
+    territory_id: string;
+    territory_name: string;
+    /**
+     * Conquests and gifts of the territory
+     */
+    times_changed_hands: number;
+    /**
+     * Attacking plus defending troops over all its battles
+     */
+    troops_involved: number;
+};
+
@@ -0,0 +1,19 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { EntityRef } from './EntityRef';
+import type { EventCategory } from './EventCategory';
+/**
+ * A notable moment in a game
+ */
+export type TimelineEntry = {
+    category?: EventCategory;
+    /**
+     * Players and territories involved
+     */
+    entities?: Array<EntityRef>;
+    game_time_seconds: number;
+    text: string;
+};
+
@@ -0,0 +1,18 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Which territories a path may pass through
+ */
+export type TraversalRules = {
+    /**
+     * Never enter water territories
+     */
+    land_only?: boolean;
+    /**
+     * Stay on this player's territories; only the destination may belong to someone else
+     */
+    through_owner?: string | null;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A peace treaty; partners can't attack each other and may trade territory
+ */
+export type Treaty = {
+    players: Array<string>;
+    signed_tick: number;
+};
+
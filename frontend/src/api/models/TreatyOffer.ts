@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A treaty proposed by one player and not yet accepted by the other
+ */
+export type TreatyOffer = {
+    from: string;
+    to: string;
+};
+
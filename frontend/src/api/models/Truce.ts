@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A period after a peace deal in which two players can't attack each
+ * other, even if their treaty is broken
+ */
+export type Truce = {
+    players: Array<string>;
+    remaining_ticks: number;
+};
+
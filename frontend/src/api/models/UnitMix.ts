@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Shares of new recruits trained as each unit type
+ */
+export type UnitMix = {
+    cavalry: number;
+    infantry: number;
+    siege: number;
+};
+
@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Kinds of soldier; each counters one of the others
+ */
+export enum UnitType {
+    INFANTRY = 'infantry',
+    CAVALRY = 'cavalry',
+    SIEGE = 'siege',
+}
@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Soldiers of every unit type in one place
+ */
+export type Units = {
+    cavalry: number;
+    infantry: number;
+    siege: number;
+};
+
@@ -0,0 +1,24 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * A player's way toward one victory condition
+ */
+export type VictoryProgress = {
+    /**
+     * `last_standing`, `domination`, or a rule plugin's own condition
+     */
+    condition: string;
+    /**
+     * How far the player has come, in the condition's own unit such as
+     * territories held or rivals eliminated
+     */
+    current: number;
+    player_id: string;
+    /**
+     * Where the player wins
+     */
+    target: number;
+};
+
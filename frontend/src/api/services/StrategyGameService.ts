@@ -0,0 +1,600 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { AiDecision } from '../models/AiDecision';
+import type { ChatMessage } from '../models/ChatMessage';
+import type { ChatPost } from '../models/ChatPost';
+import type { CreateRoomRequest } from '../models/CreateRoomRequest';
+import type { GameListing } from '../models/GameListing';
+import type { GameSummary } from '../models/GameSummary';
+import type { GymConfig } from '../models/GymConfig';
+import type { GymOpened } from '../models/GymOpened';
+import type { ImportedMap } from '../models/ImportedMap';
+import type { Leaderboard } from '../models/Leaderboard';
+import type { MapDefinition } from '../models/MapDefinition';
+import type { MapGrid } from '../models/MapGrid';
+import type { MapPreview } from '../models/MapPreview';
+import type { MapStyle } from '../models/MapStyle';
+import type { MatchResult } from '../models/MatchResult';
+import type { Profile } from '../models/Profile';
+import type { QuickplayRequest } from '../models/QuickplayRequest';
+import type { QuickplayResponse } from '../models/QuickplayResponse';
+import type { RoomInfo } from '../models/RoomInfo';
+import type { StepRequest } from '../models/StepRequest';
+import type { StepResult } from '../models/StepResult';
+import type { SweepRequest } from '../models/SweepRequest';
+import type { SweepSummary } from '../models/SweepSummary';
+import type { CancelablePromise } from '../core/CancelablePromise';
+import { OpenAPI } from '../core/OpenAPI';
+import { request as __request } from '../core/request';
+export class StrategyGameService {
+    /**
+     * Status page for operators running the server without the frontend: live
+     * games, connections, tick health and recent errors, drawn from the admin
+     * channel; open it with `?token=` set to the admin token
+     * @returns string The dashboard page
+     * @throws ApiError
+     */
+    public static dashboardHandler(): CancelablePromise<string> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/dashboard',
+        });
+    }
+    /**
+     * CPU profile of the whole server, every game and connection included, as a
+     * flamegraph; needs the admin token
+     * @returns string Flamegraph of the sampled stacks
+     * @returns any The server was idle; nothing was sampled
+     * @throws ApiError
+     */
+    public static cpuProfileHandler({
+        token,
+        seconds,
+    }: {
+        /**
+         * Admin token
+         */
+        token: string,
+        /**
+         * Seconds to sample for, 1 to 60 (default 10)
+         */
+        seconds?: number,
+    }): CancelablePromise<string | any> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/debug/pprof/profile',
+            query: {
+                'token': token,
+                'seconds': seconds,
+            },
+            errors: {
+                400: `Sampling time out of range`,
+                403: `Missing or wrong admin token`,
+                409: `Another profile is being taken`,
+            },
+        });
+    }
+    /**
+     * Games hosted by every server instance sharing the backplane
+     * @returns GameListing Live games across instances
+     * @throws ApiError
+     */
+    public static listGamesHandler(): CancelablePromise<Array<GameListing>> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/games',
+            errors: {
+                503: `Backplane unavailable`,
+            },
+        });
+    }
+    /**
+     * Recent decisions of an AI player with the options it weighed and their
+     * scores; needs the admin token, and the server running with `GAME_DEBUG`
+     * @returns AiDecision Up to 20 decisions, oldest first
+     * @throws ApiError
+     */
+    public static aiDecisionsHandler({
+        gameId,
+        playerId,
+        token,
+    }: {
+        /**
+         * Game id
+         */
+        gameId: string,
+        /**
+         * AI player id
+         */
+        playerId: string,
+        /**
+         * Admin token
+         */
+        token: string,
+    }): CancelablePromise<Array<AiDecision>> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/games/{game_id}/ai/{player_id}/last_decisions',
+            path: {
+                'game_id': gameId,
+                'player_id': playerId,
+            },
+            query: {
+                'token': token,
+            },
+            errors: {
+                403: `Missing or wrong admin token`,
+                404: `Unknown game or AI player, or debug mode is off`,
+            },
+        });
+    }
+    /**
+     * Map of a game hosted here, in the format `POST /maps` imports: the map the
+     * game started on if it is being recorded, its current map otherwise
+     * @returns MapDefinition Territories with their geometry, terrain, neighbors and buildings
+     * @throws ApiError
+     */
+    public static gameMapHandler({
+        gameId,
+    }: {
+        /**
+         * Game id
+         */
+        gameId: string,
+    }): CancelablePromise<MapDefinition> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/games/{game_id}/map',
+            path: {
+                'game_id': gameId,
+            },
+            errors: {
+                404: `Unknown game`,
+            },
+        });
+    }
+    /**
+     * End-of-game summary of a finished game hosted here, or archived by this server's storage
+     * @returns GameSummary Summary of the finished game
+     * @throws ApiError
+     */
+    public static gameSummaryHandler({
+        gameId,
+    }: {
+        /**
+         * Game id
+         */
+        gameId: string,
+    }): CancelablePromise<GameSummary> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/games/{game_id}/summary',
+            path: {
+                'game_id': gameId,
+            },
+            errors: {
+                404: `Unknown game, or not finished yet`,
+                503: `Storage unavailable`,
+            },
+        });
+    }
+    /**
+     * Deal a headless game for an agent, which advances it with `/gym/{env_id}/step`;
+     * needs the admin token
+     * @returns GymOpened The new environment
+     * @throws ApiError
+     */
+    public static openGymHandler({
+        token,
+        requestBody,
+    }: {
+        /**
+         * Admin token
+         */
+        token: string,
+        requestBody: GymConfig,
+    }): CancelablePromise<GymOpened> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/gym',
+            query: {
+                'token': token,
+            },
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `Invalid map size, or a map bigger than the server allows`,
+                403: `Missing or wrong admin token`,
+                503: `The server hosts as many environments as it may`,
+            },
+        });
+    }
+    /**
+     * Drop an environment the agent is done with; needs the admin token
+     * @returns any Dropped
+     * @throws ApiError
+     */
+    public static closeGymHandler({
+        envId,
+        token,
+    }: {
+        /**
+         * Environment id
+         */
+        envId: string,
+        /**
+         * Admin token
+         */
+        token: string,
+    }): CancelablePromise<any> {
+        return __request(OpenAPI, {
+            method: 'DELETE',
+            url: '/gym/{env_id}',
+            path: {
+                'env_id': envId,
+            },
+            query: {
+                'token': token,
+            },
+            errors: {
+                403: `Missing or wrong admin token`,
+                404: `No such environment`,
+            },
+        });
+    }
+    /**
+     * Apply the agent's actions and play the environment forward; needs the admin token
+     * @returns StepResult Observation, reward and whether the game is over
+     * @throws ApiError
+     */
+    public static gymStepHandler({
+        envId,
+        token,
+        requestBody,
+    }: {
+        /**
+         * Environment id
+         */
+        envId: string,
+        /**
+         * Admin token
+         */
+        token: string,
+        requestBody: StepRequest,
+    }): CancelablePromise<StepResult> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/gym/{env_id}/step',
+            path: {
+                'env_id': envId,
+            },
+            query: {
+                'token': token,
+            },
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `Too many ticks in one step`,
+                403: `Missing or wrong admin token`,
+                404: `No such environment, or it sat idle too long`,
+            },
+        });
+    }
+    /**
+     * Ranked ladder standings for a season
+     * @returns Leaderboard Placed players, best first
+     * @throws ApiError
+     */
+    public static ladderHandler({
+        season,
+    }: {
+        /**
+         * Season number, defaults to the current season
+         */
+        season?: number,
+    }): CancelablePromise<Leaderboard> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/ladder',
+            query: {
+                'season': season,
+            },
+            errors: {
+                404: `Ladder disabled or unknown season`,
+            },
+        });
+    }
+    /**
+     * Lobby chat messages, oldest first
+     * @returns ChatMessage Recent messages
+     * @throws ApiError
+     */
+    public static lobbyChatHandler({
+        after,
+    }: {
+        /**
+         * Only messages with a higher id
+         */
+        after?: number,
+    }): CancelablePromise<Array<ChatMessage>> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/lobby/chat',
+            query: {
+                'after': after,
+            },
+            errors: {
+                404: `Lobby chat disabled`,
+                503: `Storage unavailable`,
+            },
+        });
+    }
+    /**
+     * Post to the lobby chat as a seated player
+     * @returns ChatMessage The posted message
+     * @throws ApiError
+     */
+    public static postLobbyChatHandler({
+        requestBody,
+    }: {
+        requestBody: ChatPost,
+    }): CancelablePromise<ChatMessage> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/lobby/chat',
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `Empty or overlong message`,
+                401: `No seat was issued for this token`,
+                404: `Lobby chat disabled`,
+                429: `Posting too fast`,
+                503: `Storage unavailable`,
+            },
+        });
+    }
+    /**
+     * Import a hand-made map; create a room on it by passing its `map_id` as `map`
+     * @returns ImportedMap The map passed validation
+     * @throws ApiError
+     */
+    public static importMapHandler({
+        requestBody,
+    }: {
+        requestBody: MapDefinition,
+    }): CancelablePromise<ImportedMap> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/maps',
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `The map is invalid, e.g. a neighbor that doesn't list the territory back or a part that can't be reached`,
+            },
+        });
+    }
+    /**
+     * Generate a map without starting a game and report its fairness
+     * @returns MapPreview Generated map with its fairness report
+     * @throws ApiError
+     */
+    public static mapPreviewHandler({
+        territories,
+        players,
+        seed,
+        style,
+        grid,
+        hops,
+        minFairness,
+    }: {
+        /**
+         * Territory count, default 75
+         */
+        territories?: number,
+        /**
+         * Player count, default 9
+         */
+        players?: number,
+        /**
+         * Map seed, random if omitted
+         */
+        seed?: number,
+        /**
+         * `scattered` (default), `continents`, `islands` or `pangaea`
+         */
+        style?: MapStyle,
+        /**
+         * `voronoi` (default) or `hex`
+         */
+        grid?: MapGrid,
+        /**
+         * Radius of the terrain value measure, default 2
+         */
+        hops?: number,
+        /**
+         * Reject maps scoring below this, from 0 to 1
+         */
+        minFairness?: number,
+    }): CancelablePromise<MapPreview> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/maps/preview',
+            query: {
+                'territories': territories,
+                'players': players,
+                'seed': seed,
+                'style': style,
+                'grid': grid,
+                'hops': hops,
+                'min_fairness': minFairness,
+            },
+            errors: {
+                400: `Invalid map size`,
+                422: `Map is less fair than \`min_fairness\`; the preview is still returned`,
+            },
+        });
+    }
+    /**
+     * Finished games recorded in the match database, most recent first
+     * @returns MatchResult Finished games
+     * @throws ApiError
+     */
+    public static listMatchesHandler({
+        player,
+        limit,
+    }: {
+        /**
+         * Only games this player took part in
+         */
+        player?: string,
+        /**
+         * Number of matches, 20 by default and 100 at most
+         */
+        limit?: number,
+    }): CancelablePromise<Array<MatchResult>> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/matches',
+            query: {
+                'player': player,
+                'limit': limit,
+            },
+            errors: {
+                404: `Match history disabled`,
+                500: `The database couldn't be queried`,
+            },
+        });
+    }
+    /**
+     * Prometheus-style metrics for the main game, and the number of rooms
+     * @returns string Metrics in Prometheus text format
+     * @throws ApiError
+     */
+    public static metricsHandler(): CancelablePromise<string> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/metrics',
+        });
+    }
+    /**
+     * A player's totals and recent matches
+     * @returns Profile The player's profile
+     * @throws ApiError
+     */
+    public static profileHandler({
+        player,
+    }: {
+        /**
+         * Ladder identity of the player, as on the leaderboard
+         */
+        player: string,
+    }): CancelablePromise<Profile> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/profiles/{player}',
+            path: {
+                'player': player,
+            },
+            errors: {
+                404: `Profiles disabled or no games played by this player`,
+            },
+        });
+    }
+    /**
+     * Start a solo game against AI with default settings in one call
+     * @returns QuickplayResponse The game to connect to
+     * @throws ApiError
+     */
+    public static quickplayHandler({
+        requestBody,
+    }: {
+        /**
+         * Optional token to resume an earlier game
+         */
+        requestBody?: (null | QuickplayRequest),
+    }): CancelablePromise<QuickplayResponse> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/quickplay',
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                429: `The client opened as many rooms as it may this minute`,
+                503: `The server hosts as many rooms as it may`,
+            },
+        });
+    }
+    /**
+     * Rooms hosted by this server, oldest first
+     * @returns RoomInfo Hosted rooms
+     * @throws ApiError
+     */
+    public static listRoomsHandler(): CancelablePromise<Array<RoomInfo>> {
+        return __request(OpenAPI, {
+            method: 'GET',
+            url: '/rooms',
+        });
+    }
+    /**
+     * Open a room with its own game; join it at `/ws?room=<room_id>`
+     * @returns RoomInfo The new room, already running
+     * @throws ApiError
+     */
+    public static createRoomHandler({
+        requestBody,
+    }: {
+        requestBody: CreateRoomRequest,
+    }): CancelablePromise<RoomInfo> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/rooms',
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `Invalid map size, or a map bigger than the server allows`,
+                404: `No imported map has the given \`map\` id`,
+                429: `The client opened as many rooms as it may this minute`,
+                503: `The server hosts as many rooms as it may`,
+            },
+        });
+    }
+    /**
+     * Run a balance sweep of headless AI-only games and summarize the results;
+     * needs the admin token
+     * @returns SweepSummary One summary per parameter value
+     * @throws ApiError
+     */
+    public static runSimulationsHandler({
+        token,
+        requestBody,
+        format,
+    }: {
+        /**
+         * Admin token
+         */
+        token: string,
+        requestBody: SweepRequest,
+        /**
+         * `json` (default) or `csv`
+         */
+        format?: string,
+    }): CancelablePromise<Array<SweepSummary>> {
+        return __request(OpenAPI, {
+            method: 'POST',
+            url: '/simulations',
+            query: {
+                'token': token,
+                'format': format,
+            },
+            body: requestBody,
+            mediaType: 'application/json',
+            errors: {
+                400: `Invalid sweep`,
+                403: `Missing or wrong admin token`,
+            },
+        });
+    }
+}
+
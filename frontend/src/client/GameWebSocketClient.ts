@@ -11,7 +11,7 @@
  *
  * const client = new GameWebSocketClient('ws://localhost:3000/ws');
  *
- * client.on('game_state_update', (state: GameState) => {
+ * client.on('state', (state: GameState) => {
  *   console.log('Game state:', state);
  * });
  *
@@ -23,27 +23,41 @@
 // Import types from generated SDK
 // These will be available after running: make sdk
 import type {
+  ClientMessage,
   ServerMessage,
   GameState,
+  StateDelta,
+  BuildingType,
 } from '../api';
-import { ClientMessage, BuildingType } from '../api';
 
-type ServerMessageType = ServerMessage['type'];
+/**
+ * A client message's fields besides its `type`. The generated `type` enums
+ * only hold each union's first variant, so messages are matched by their
+ * fields and their types are plain strings.
+ */
+type Fields<M> = M extends unknown ? Omit<M, 'type'> : never;
+type ClientMessageFields = Fields<ClientMessage>;
+
+type MessageHandler = (message: ServerMessage) => void;
 
 type EventHandlers = {
-  [K in ServerMessageType]?: (message: ServerMessage) => void;
-} & {
   connect?: () => void;
   disconnect?: () => void;
   error?: (error: Error) => void;
+  /** Every new game state, whether sent in full or patched by a delta */
+  state?: (state: GameState, checksum?: number) => void;
 };
 
 export class GameWebSocketClient {
   private ws: WebSocket | null = null;
   private handlers: EventHandlers = {};
+  /** Server message handlers by message `type`, such as `notification` */
+  private messageHandlers = new Map<string, MessageHandler>();
   private reconnectAttempts = 0;
   private readonly maxReconnectAttempts = 5;
   private readonly reconnectDelay = 1000;
+  /** Latest full state, which `state_delta` messages are applied to */
+  private state: GameState | null = null;
 
   constructor(private url: string) {}
 
@@ -58,6 +72,8 @@ export class GameWebSocketClient {
         this.ws.onopen = () => {
           console.log('🎮 Connected to game server');
           this.reconnectAttempts = 0;
+          // The server seats the connection once it has joined
+          this.joinGame();
           this.handlers.connect?.();
           resolve();
         };
@@ -112,6 +128,20 @@ export class GameWebSocketClient {
     delete this.handlers[event];
   }
 
+  /**
+   * Register a handler for the server messages of one `type`
+   */
+  onMessage(type: string, handler: MessageHandler): void {
+    this.messageHandlers.set(type, handler);
+  }
+
+  /**
+   * Unregister a server message handler
+   */
+  offMessage(type: string): void {
+    this.messageHandlers.delete(type);
+  }
+
   /**
    * Check if connected
    */
@@ -122,10 +152,58 @@ export class GameWebSocketClient {
   // ===== Private Methods =====
 
   private handleMessage(message: ServerMessage): void {
-    const handler = this.handlers[message.type];
-    if (handler) {
-      (handler as any)(message);
+    if ('delta' in message) {
+      this.applyDelta(message.delta);
+      return;
+    }
+    if ('state' in message) {
+      this.setState(message.state, message.checksum);
     }
+    this.messageHandlers.get(message.type)?.(message);
+  }
+
+  private setState(state: GameState, checksum?: number): void {
+    this.state = state;
+    this.handlers.state?.(state, checksum);
+  }
+
+  /**
+   * Apply a delta to a copy of the held state and report it like a full one;
+   * without the delta's base state, ask for a full one instead
+   */
+  private applyDelta(delta: StateDelta): void {
+    const state = this.state;
+    if (!state || state.tick !== delta.base_tick) {
+      this.requestGameState();
+      return;
+    }
+    const territories = [...state.territories];
+    for (const territory of delta.territories) {
+      const idx = territories.findIndex((t) => t.id === territory.id);
+      if (idx >= 0) territories[idx] = territory;
+    }
+    const players = [...state.players];
+    for (const player of delta.players) {
+      const idx = players.findIndex((p) => p.id === player.id);
+      if (idx >= 0) players[idx] = player;
+      else players.push(player);
+    }
+    this.setState(
+      {
+        ...state,
+        territories,
+        players,
+        // Left out of the delta when they didn't change
+        diplomacy: delta.diplomacy ?? state.diplomacy,
+        battles: delta.battles ?? state.battles,
+        victory: delta.victory ?? state.victory,
+        tick: delta.tick,
+        game_speed: delta.game_speed,
+        is_paused: delta.is_paused,
+        game_time_seconds: delta.game_time_seconds,
+      },
+      delta.checksum,
+    );
   }
 
   private attemptReconnect(): void {
@@ -141,87 +219,79 @@ export class GameWebSocketClient {
     }
   }
 
-  private send(message: ClientMessage): void {
+  private send(type: string, fields: ClientMessageFields = {}): void {
     if (!this.isConnected()) {
       console.error('Cannot send message: not connected');
       return;
     }
-    this.ws!.send(JSON.stringify(message));
+    this.ws!.send(JSON.stringify({ type, ...fields }));
   }
 
   // ===== Game Actions (Type-safe API) =====
 
+  /**
+   * Take a seat in the game; the server answers with `joined`. A `playerKey`
+   * of 16 to 128 characters, kept across games, rates them on the ladder
+   * under one identity.
+   */
+  joinGame(name?: string, color?: string, playerKey?: string): void {
+    this.send('join_game', { name, color, player_key: playerKey });
+  }
+
   /**
    * Attack a neighboring territory
    */
   attack(from: string, to: string): void {
-    this.send({
-      type: ClientMessage.type.ATTACK,
-      from,
-      to,
-    } as ClientMessage);
+    this.send('attack', { from, to });
   }
 
   /**
    * Build a structure in a territory
    */
   buildStructure(territory: string, buildingType: BuildingType): void {
-    this.send({
-      type: 'build_structure' as any,
-      territory,
-      building_type: buildingType,
-    } as ClientMessage);
+    this.send('build_structure', { territory, building_type: buildingType });
   }
 
   /**
    * Set troop/worker ratio (0.0 - 1.0)
    */
   setTroopRatio(ratio: number): void {
-    this.send({
-      type: 'set_troop_ratio' as any,
-      ratio: Math.max(0, Math.min(1, ratio)),
-    } as ClientMessage);
+    this.send('set_troop_ratio', { ratio: Math.max(0, Math.min(1, ratio)) });
   }
 
   /**
    * Set attack commitment ratio (0.0 - 1.0)
    */
   setAttackRatio(ratio: number): void {
-    this.send({
-      type: 'set_attack_ratio' as any,
-      ratio: Math.max(0, Math.min(1, ratio)),
-    } as ClientMessage);
+    this.send('set_attack_ratio', { ratio: Math.max(0, Math.min(1, ratio)) });
   }
 
   /**
    * Pause the game
    */
   pauseGame(): void {
-    this.send({ type: 'pause_game' as any } as ClientMessage);
+    this.send('pause_game');
   }
 
   /**
    * Resume the game
    */
   resumeGame(): void {
-    this.send({ type: 'resume_game' as any } as ClientMessage);
+    this.send('resume_game');
   }
 
   /**
    * Set game speed multiplier
    */
   setGameSpeed(speed: number): void {
-    this.send({
-      type: 'set_game_speed' as any,
-      speed,
-    } as ClientMessage);
+    this.send('set_game_speed', { speed });
   }
 
   /**
    * Request current game state
    */
   requestGameState(): void {
-    this.send({ type: 'get_game_state' as any } as ClientMessage);
+    this.send('get_game_state');
   }
 }
 
@@ -1,7 +1,6 @@
 import { useEffect, useRef, useState, useCallback } from 'react';
 import { GameWebSocketClient } from './GameWebSocketClient';
 import type { GameState } from '../api';
-import { ServerMessage } from '../api';
 
 interface UseGameClientOptions {
   url?: string;
@@ -61,10 +60,8 @@ export function useGameClient(options: UseGameClientOptions = {}): UseGameClient
     const client = new GameWebSocketClient(url);
 
     // Register event handlers
-    client.on(ServerMessage.type.GAME_STATE_UPDATE as any, (message: any) => {
-      if ('state' in message) {
-        setGameState(message.state);
-      }
+    client.on('state', (state) => {
+      setGameState(state);
     });
 
     client.on('connect', () => {
@@ -15,6 +15,12 @@ interface VoronoiCell {
 }
 
 // Simple Voronoi diagram generator using Fortune's algorithm (simplified)
+// Troops stationed in a territory, every unit type together
+function troops(territory: Territory): number {
+  const { infantry, cavalry, siege } = territory.units;
+  return infantry + cavalry + siege;
+}
+
 function generateVoronoiCells(territories: Territory[]): VoronoiCell[] {
   const cells: VoronoiCell[] = [];
   const width = 1200;
@@ -300,7 +306,7 @@ export function GameMap({ gameState, client, humanPlayerId }: GameMapProps) {
       ctx.textBaseline = 'middle';
       ctx.shadowColor = 'rgba(0, 0, 0, 0.8)';
       ctx.shadowBlur = 4;
-      ctx.fillText(Math.floor(territory.troops).toString(), centerX, centerY);
+      ctx.fillText(troops(territory).toString(), centerX, centerY);
       ctx.shadowBlur = 0;
 
       // Draw building indicator
@@ -356,7 +362,10 @@ export function GameMap({ gameState, client, humanPlayerId }: GameMapProps) {
           <h3>Territory {hoveredTerritory.id.substring(0, 8)}</h3>
           <p>Owner: {hoveredTerritory.owner ? gameState.players.find(p => p.id === hoveredTerritory.owner)?.name : 'Neutral'}</p>
           <p>Terrain: {hoveredTerritory.terrain}</p>
-          <p>Troops: {Math.floor(hoveredTerritory.troops)}</p>
+          <p>Troops: {troops(hoveredTerritory)}</p>
+          <p>
+            Infantry {hoveredTerritory.units.infantry} · Cavalry {hoveredTerritory.units.cavalry} · Siege {hoveredTerritory.units.siege}
+          </p>
           {hoveredTerritory.building && <p>Building: {hoveredTerritory.building}</p>}
         </div>
       )}